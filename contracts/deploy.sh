@@ -37,6 +37,11 @@ cd milestone-manager
 cargo build --target wasm32-unknown-unknown --release
 cd ..
 
+echo "🔨 Building matching-pool contract..."
+cd matching-pool
+cargo build --target wasm32-unknown-unknown --release
+cd ..
+
 # Optimize WASM files
 echo "⚡ Optimizing WASM files..."
 soroban contract optimize \
@@ -51,6 +56,10 @@ soroban contract optimize \
     --wasm milestone-manager/target/wasm32-unknown-unknown/release/milestone_manager.wasm \
     --wasm-out milestone-manager/target/wasm32-unknown-unknown/release/milestone_manager_optimized.wasm
 
+soroban contract optimize \
+    --wasm matching-pool/target/wasm32-unknown-unknown/release/matching_pool.wasm \
+    --wasm-out matching-pool/target/wasm32-unknown-unknown/release/matching_pool_optimized.wasm
+
 # Deploy contracts
 echo "📦 Deploying project-registry contract..."
 PROJECT_REGISTRY_ID=$(soroban contract deploy \
@@ -76,6 +85,14 @@ MILESTONE_MANAGER_ID=$(soroban contract deploy \
 
 echo "✅ Milestone Manager deployed: $MILESTONE_MANAGER_ID"
 
+echo "📦 Deploying matching-pool contract..."
+MATCHING_POOL_ID=$(soroban contract deploy \
+    --wasm matching-pool/target/wasm32-unknown-unknown/release/matching_pool_optimized.wasm \
+    --source-account default \
+    --network $NETWORK)
+
+echo "✅ Matching Pool deployed: $MATCHING_POOL_ID"
+
 # Save contract addresses to file
 echo "💾 Saving contract addresses..."
 cat > contract-addresses.json <<EOF
@@ -84,7 +101,8 @@ cat > contract-addresses.json <<EOF
   "contracts": {
     "project_registry": "$PROJECT_REGISTRY_ID",
     "funding_escrow": "$FUNDING_ESCROW_ID",
-    "milestone_manager": "$MILESTONE_MANAGER_ID"
+    "milestone_manager": "$MILESTONE_MANAGER_ID",
+    "matching_pool": "$MATCHING_POOL_ID"
   },
   "deployed_at": "$(date -u +"%Y-%m-%dT%H:%M:%SZ")"
 }
@@ -98,6 +116,7 @@ echo "Contract Addresses:"
 echo "  Project Registry: $PROJECT_REGISTRY_ID"
 echo "  Funding Escrow:   $FUNDING_ESCROW_ID"
 echo "  Milestone Manager: $MILESTONE_MANAGER_ID"
+echo "  Matching Pool:    $MATCHING_POOL_ID"
 echo ""
 echo "Next steps:"
 echo "  1. Update your backend .env with these contract addresses"
@@ -108,5 +127,34 @@ echo "       --source-account default \\"
 echo "       --network $NETWORK \\"
 echo "       -- initialize \\"
 echo "       --token <USDC_TOKEN_ADDRESS> \\"
+echo "       --admin <ADMIN_ADDRESS> \\"
 echo "       --attestation_pubkey <YOUR_ATTESTATION_PUBKEY>"
-
+echo "  3. Initialize the matching-pool contract with:"
+echo "     soroban contract invoke \\"
+echo "       --id $MATCHING_POOL_ID \\"
+echo "       --source-account default \\"
+echo "       --network $NETWORK \\"
+echo "       -- initialize \\"
+echo "       --token <USDC_TOKEN_ADDRESS> \\"
+echo "       --admin <ADMIN_ADDRESS> \\"
+echo "       --attestation_pubkey <YOUR_ATTESTATION_PUBKEY>"
+echo "  4. To enable donor voting on milestones, point the milestone manager at the escrow:"
+echo "     soroban contract invoke \\"
+echo "       --id $MILESTONE_MANAGER_ID \\"
+echo "       --source-account default \\"
+echo "       --network $NETWORK \\"
+echo "       -- set_escrow_contract \\"
+echo "       --escrow $FUNDING_ESCROW_ID"
+echo "  5. Initialize the project registry and let the escrow check project status:"
+echo "     soroban contract invoke \\"
+echo "       --id $PROJECT_REGISTRY_ID \\"
+echo "       --source-account default \\"
+echo "       --network $NETWORK \\"
+echo "       -- initialize \\"
+echo "       --admin <ADMIN_ADDRESS>"
+echo "     soroban contract invoke \\"
+echo "       --id $FUNDING_ESCROW_ID \\"
+echo "       --source-account default \\"
+echo "       --network $NETWORK \\"
+echo "       -- set_registry \\"
+echo "       --registry $PROJECT_REGISTRY_ID"
@@ -1,5 +1,5 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, contracttype, token, Address, Bytes, BytesN, Env, String, log};
+use soroban_sdk::{contract, contractimpl, contracttype, token, vec, xdr::ToXdr, Address, Bytes, BytesN, Env, IntoVal, String, Symbol, log};
 
 #[contracttype]
 #[derive(Clone)]
@@ -10,44 +10,194 @@ pub struct EscrowInfo {
     pub attestation_pubkey: BytesN<32>,
 }
 
+/// Attestation key kept valid after a rotation until `expires_at`
+#[contracttype]
+#[derive(Clone)]
+pub struct RetiredKey {
+    pub key: BytesN<32>,
+    pub expires_at: u64,
+}
+
 #[contracttype]
 pub enum DataKey {
     Escrow(BytesN<32>),
     Token,
+    Admin,
+    AttestationKey,
+    PreviousAttestationKey,
+    AttestationGracePeriod,
+    Donor(BytesN<32>, Address), // cumulative deposits per (project, donor)
+    FundingCap(BytesN<32>),
+    Registry,
 }
 
+/// Default time a rotated-out attestation key stays valid (24 hours)
+const DEFAULT_GRACE_PERIOD_SECS: u64 = 86_400;
+
 #[contract]
 pub struct FundingEscrow;
 
 #[contractimpl]
 impl FundingEscrow {
-    /// Initialize the contract with token address and attestation public key
-    pub fn initialize(env: Env, token: Address, attestation_pubkey: BytesN<32>) {
+    /// Initialize the contract with token address, admin, and attestation public key
+    pub fn initialize(env: Env, token: Address, admin: Address, attestation_pubkey: BytesN<32>) {
         if env.storage().instance().has(&DataKey::Token) {
             panic!("Already initialized");
         }
         
         env.storage().instance().set(&DataKey::Token, &token);
+        env.storage().instance().set(&DataKey::Admin, &admin);
         
         // Store attestation key at a global level for verification
         // In production, this could be set per-project
+        env.storage().instance().set(&DataKey::AttestationKey, &attestation_pubkey);
         log!(&env, "Contract initialized with attestation key");
     }
 
-    /// Deposit funds to a project escrow
+    /// Replace the attestation key (admin only). The previous key stays valid
+    /// for the grace period so in-flight releases signed with it still succeed.
+    pub fn rotate_attestation_key(env: Env, new_key: BytesN<32>) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::Admin)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        let current: BytesN<32> = env.storage().instance()
+            .get(&DataKey::AttestationKey)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        if current == new_key {
+            return Err(String::from_str(&env, "Key already active"));
+        }
+
+        let grace: u64 = env.storage().instance()
+            .get(&DataKey::AttestationGracePeriod)
+            .unwrap_or(DEFAULT_GRACE_PERIOD_SECS);
+        let expires_at = env.ledger().timestamp() + grace;
+
+        env.storage().instance().set(&DataKey::PreviousAttestationKey, &RetiredKey {
+            key: current,
+            expires_at,
+        });
+        env.storage().instance().set(&DataKey::AttestationKey, &new_key);
+
+        log!(&env, "AttestationKeyRotated: previous key valid until {}", expires_at);
+
+        Ok(())
+    }
+
+    /// Set how long a rotated-out attestation key remains valid (admin only)
+    pub fn set_attestation_grace_period(env: Env, seconds: u64) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::Admin)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        env.storage().instance().set(&DataKey::AttestationGracePeriod, &seconds);
+
+        Ok(())
+    }
+
+    /// Check whether a key is the active attestation key or a rotated-out key
+    /// still inside its grace window
+    pub fn is_attestation_key_valid(env: Env, key: BytesN<32>) -> bool {
+        let current: Option<BytesN<32>> = env.storage().instance().get(&DataKey::AttestationKey);
+        if current.as_ref() == Some(&key) {
+            return true;
+        }
+
+        env.storage().instance()
+            .get::<DataKey, RetiredKey>(&DataKey::PreviousAttestationKey)
+            .map(|retired| retired.key == key && env.ledger().timestamp() <= retired.expires_at)
+            .unwrap_or(false)
+    }
+
+    /// Point the escrow at the project registry (admin only). Once set, deposits
+    /// are refused for projects the registry reports as completed, cancelled, or unknown.
+    pub fn set_registry(env: Env, registry: Address) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::Admin)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        env.storage().instance().set(&DataKey::Registry, &registry);
+
+        Ok(())
+    }
+
+    /// Set the most a project can raise (admin only), normally at project creation
+    pub fn set_funding_cap(env: Env, project_id: BytesN<32>, cap: i128) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::Admin)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        if cap <= 0 {
+            return Err(String::from_str(&env, "Cap must be positive"));
+        }
+
+        env.storage().persistent().set(&DataKey::FundingCap(project_id.clone()), &cap);
+        log!(&env, "FundingCapSet: project={:?}, cap={}", project_id, cap);
+
+        Ok(())
+    }
+
+    /// How much more a capped project can accept; `None` if the project has no cap
+    pub fn get_remaining_capacity(env: Env, project_id: BytesN<32>) -> Option<i128> {
+        let cap: i128 = env.storage().persistent().get(&DataKey::FundingCap(project_id.clone()))?;
+        let deposited = env.storage().persistent()
+            .get::<DataKey, EscrowInfo>(&DataKey::Escrow(project_id))
+            .map(|info| info.total_deposited)
+            .unwrap_or(0);
+        Some((cap - deposited).max(0))
+    }
+
+    /// Deposit funds to a project escrow. Deposits to a capped project are
+    /// trimmed to its remaining capacity, and only the accepted amount is
+    /// transferred; returns the accepted amount.
     pub fn deposit(
         env: Env,
         from: Address,
         project_id: BytesN<32>,
         amount: i128,
         memo: String,
-    ) -> Result<(), String> {
+    ) -> Result<i128, String> {
+        Self::deposit_for(env, from.clone(), from, project_id, amount, memo)
+    }
+
+    /// Deposit funds paid by `from` on behalf of `donor`, who is credited
+    /// with the deposit for refunds and votes. Used when the platform
+    /// submits a donation for a donor.
+    pub fn deposit_for(
+        env: Env,
+        from: Address,
+        donor: Address,
+        project_id: BytesN<32>,
+        amount: i128,
+        memo: String,
+    ) -> Result<i128, String> {
         from.require_auth();
 
         if amount <= 0 {
             return Err(String::from_str(&env, "Amount must be positive"));
         }
 
+        if let Some(registry) = env.storage().instance().get::<DataKey, Address>(&DataKey::Registry) {
+            let accepting: bool = env.invoke_contract(
+                &registry,
+                &Symbol::new(&env, "accepts_deposits"),
+                vec![&env, project_id.into_val(&env)],
+            );
+            if !accepting {
+                return Err(String::from_str(&env, "Project not accepting deposits"));
+            }
+        }
+
+        let amount = match Self::get_remaining_capacity(env.clone(), project_id.clone()) {
+            Some(0) => return Err(String::from_str(&env, "Funding cap reached")),
+            Some(remaining) => amount.min(remaining),
+            None => amount,
+        };
+
         // Get token
         let token: Address = env.storage().instance()
             .get(&DataKey::Token)
@@ -72,10 +222,19 @@ impl FundingEscrow {
         escrow_info.total_deposited += amount;
         env.storage().persistent().set(&key, &escrow_info);
 
+        // Track the donor's cumulative contribution for refunds and receipts
+        let donor_key = DataKey::Donor(project_id.clone(), donor.clone());
+        let donor_total: i128 = env.storage().persistent().get(&donor_key).unwrap_or(0);
+        env.storage().persistent().set(&donor_key, &(donor_total + amount));
+
         // Emit event
         log!(&env, "Deposit: project={:?}, amount={}, memo={:?}", project_id, amount, memo);
+        env.events().publish(
+            (Symbol::new(&env, "deposit"), project_id),
+            (donor, amount, memo),
+        );
 
-        Ok(())
+        Ok(amount)
     }
 
     /// Claim funds from escrow with attestation signature
@@ -102,17 +261,8 @@ impl FundingEscrow {
             return Err(String::from_str(&env, "Insufficient balance"));
         }
 
-        // Verify attestation signature against stored key
-        let stored_attestation_key = escrow_info.attestation_pubkey;
-        if attestation.len() < 64 {
-            return Err(String::from_str(&env, "Invalid attestation"));
-        }
-
-        // In production, this would verify the signature against the stored public key
-        // For now, we'll do a simple length check
-        if attestation.len() < 64 {
-            return Err(String::from_str(&env, "Invalid attestation"));
-        }
+        let payload = Self::claim_payload(&env, &project_id, escrow_info.total_claimed, amount);
+        Self::verify_attestation(&env, &payload, &attestation)?;
 
         // Update claimed amount
         escrow_info.total_claimed += amount;
@@ -149,10 +299,9 @@ impl FundingEscrow {
             return Err(String::from_str(&env, "Insufficient balance"));
         }
 
-        // Verify attestation signature
-        if attestation.len() < 64 {
-            return Err(String::from_str(&env, "Invalid attestation"));
-        }
+        let mut payload = Self::claim_payload(&env, &project_id, escrow_info.total_claimed, amount);
+        payload.append(&recipient.clone().to_xdr(&env));
+        Self::verify_attestation(&env, &payload, &attestation)?;
 
         // Get token
         let token: Address = env.storage().instance()
@@ -174,6 +323,68 @@ impl FundingEscrow {
         Ok(())
     }
 
+    /// Return up to `amount` of a donor's deposits from a closed project's
+    /// escrow (admin only). Limited to what the donor put in and what is left
+    /// in the escrow; returns the amount refunded.
+    pub fn refund(
+        env: Env,
+        project_id: BytesN<32>,
+        donor: Address,
+        amount: i128,
+    ) -> Result<i128, String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::Admin)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        if amount <= 0 {
+            return Err(String::from_str(&env, "Amount must be positive"));
+        }
+
+        if let Some(registry) = env.storage().instance().get::<DataKey, Address>(&DataKey::Registry) {
+            let accepting: bool = env.invoke_contract(
+                &registry,
+                &Symbol::new(&env, "accepts_deposits"),
+                vec![&env, project_id.clone().into_val(&env)],
+            );
+            if accepting {
+                return Err(String::from_str(&env, "Project is still accepting deposits"));
+            }
+        }
+
+        let key = DataKey::Escrow(project_id.clone());
+        let mut escrow_info: EscrowInfo = env.storage()
+            .persistent()
+            .get(&key)
+            .ok_or(String::from_str(&env, "Project not found"))?;
+
+        let donor_key = DataKey::Donor(project_id.clone(), donor.clone());
+        let donor_total: i128 = env.storage().persistent().get(&donor_key).unwrap_or(0);
+        let available = escrow_info.total_deposited - escrow_info.total_claimed;
+        let amount = amount.min(donor_total).min(available);
+        if amount <= 0 {
+            return Err(String::from_str(&env, "Nothing to refund"));
+        }
+
+        let token: Address = env.storage().instance()
+            .get(&DataKey::Token)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        let token_client = token::Client::new(&env, &token);
+        token_client.transfer(&env.current_contract_address(), &donor, &amount);
+
+        escrow_info.total_claimed += amount;
+        env.storage().persistent().set(&key, &escrow_info);
+        env.storage().persistent().set(&donor_key, &(donor_total - amount));
+
+        log!(&env, "Refund: project={:?}, donor={:?}, amount={}", project_id, donor, amount);
+        env.events().publish(
+            (Symbol::new(&env, "refund"), project_id),
+            (donor, amount),
+        );
+
+        Ok(amount)
+    }
+
     /// Get escrow balance for a project
     pub fn get_balance(env: Env, project_id: BytesN<32>) -> i128 {
         let key = DataKey::Escrow(project_id);
@@ -189,12 +400,82 @@ impl FundingEscrow {
         let key = DataKey::Escrow(project_id);
         env.storage().persistent().get(&key)
     }
+
+    /// Get the total a donor has deposited to a project
+    pub fn get_donor_total(env: Env, project_id: BytesN<32>, donor: Address) -> i128 {
+        env.storage().persistent()
+            .get(&DataKey::Donor(project_id, donor))
+            .unwrap_or(0)
+    }
+}
+
+impl FundingEscrow {
+    /// Payload an attestation signs for a claim: project_id || total claimed
+    /// so far || amount (big-endian). Including the running total means a
+    /// signature can't be replayed for a second claim. Releases append the
+    /// recipient's XDR.
+    pub fn claim_payload(env: &Env, project_id: &BytesN<32>, total_claimed: i128, amount: i128) -> Bytes {
+        let mut payload = Bytes::from_array(env, &project_id.to_array());
+        payload.extend_from_array(&total_claimed.to_be_bytes());
+        payload.extend_from_array(&amount.to_be_bytes());
+        payload
+    }
+
+    /// Check an attestation: the signer's public key (32 bytes) followed by
+    /// its ed25519 signature (64 bytes) over `payload`. The key must be the
+    /// active attestation key, or the previous one inside its grace window.
+    /// Traps if the signature is invalid.
+    fn verify_attestation(env: &Env, payload: &Bytes, attestation: &Bytes) -> Result<(), String> {
+        if attestation.len() != 96 {
+            return Err(String::from_str(env, "Invalid attestation"));
+        }
+        let key: BytesN<32> = attestation.slice(0..32).try_into()
+            .map_err(|_| String::from_str(env, "Invalid attestation"))?;
+        let signature: BytesN<64> = attestation.slice(32..96).try_into()
+            .map_err(|_| String::from_str(env, "Invalid attestation"))?;
+
+        if !Self::is_attestation_key_valid(env.clone(), key.clone()) {
+            return Err(String::from_str(env, "Attestation key not valid"));
+        }
+        env.crypto().ed25519_verify(&key, payload, &signature);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::{Address as _, BytesN as _}, token, Env};
+    use soroban_sdk::{testutils::{Address as _, BytesN as _, Ledger}, token, Env};
+    use ed25519_dalek::{Signer, SigningKey};
+
+    /// Stand-in for the project registry's lifecycle check
+    #[contract]
+    pub struct MockRegistry;
+
+    #[contractimpl]
+    impl MockRegistry {
+        pub fn set_accepting(env: Env, project_id: BytesN<32>, accepting: bool) {
+            env.storage().persistent().set(&project_id, &accepting);
+        }
+
+        pub fn accepts_deposits(env: Env, project_id: BytesN<32>) -> bool {
+            env.storage().persistent().get(&project_id).unwrap_or(false)
+        }
+    }
+
+    fn public_key(env: &Env, key: &SigningKey) -> BytesN<32> {
+        BytesN::from_array(env, &key.verifying_key().to_bytes())
+    }
+
+    /// The signer's public key followed by its signature over `payload`
+    fn attest(env: &Env, key: &SigningKey, payload: &Bytes) -> Bytes {
+        let mut message = [0u8; 128];
+        let len = payload.len() as usize;
+        payload.copy_into_slice(&mut message[..len]);
+        let mut attestation = Bytes::from_array(env, &key.verifying_key().to_bytes());
+        attestation.extend_from_array(&key.sign(&message[..len]).to_bytes());
+        attestation
+    }
 
     fn create_token_contract<'a>(env: &Env, admin: &Address) -> token::Client<'a> {
         let token_contract_id = env.register_stellar_asset_contract(admin.clone());
@@ -209,7 +490,7 @@ mod test {
         let admin = Address::generate(&env);
         let user = Address::generate(&env);
         let project_id = BytesN::from_array(&env, &[1u8; 32]);
-        let attestation_key = BytesN::from_array(&env, &[2u8; 32]);
+        let signing_key = SigningKey::from_bytes(&[2u8; 32]);
 
         // Create token
         let token = create_token_contract(&env, &admin);
@@ -220,7 +501,7 @@ mod test {
         let client = FundingEscrowClient::new(&env, &contract_id);
 
         // Initialize
-        client.initialize(&token.address, &attestation_key);
+        client.initialize(&token.address, &admin, &public_key(&env, &signing_key));
 
         // Deposit
         let memo = String::from_str(&env, "donation:123");
@@ -230,13 +511,21 @@ mod test {
         let balance = client.get_balance(&project_id);
         assert_eq!(balance, 500);
 
+        // Junk and short attestations are refused
+        assert!(client.try_claim(&project_id, &200, &Bytes::from_array(&env, &[0u8; 96])).is_err());
+        assert!(client.try_claim(&project_id, &200, &Bytes::from_array(&env, &[0u8; 64])).is_err());
+
         // Claim with attestation
-        let attestation = Bytes::from_array(&env, &[0u8; 64]);
+        let payload = FundingEscrow::claim_payload(&env, &project_id, 0, 200);
+        let attestation = attest(&env, &signing_key, &payload);
         client.claim(&project_id, &200, &attestation);
 
         // Check updated balance
         let balance = client.get_balance(&project_id);
         assert_eq!(balance, 300);
+
+        // The same attestation can't claim again
+        assert!(client.try_claim(&project_id, &200, &attestation).is_err());
     }
 
     #[test]
@@ -259,7 +548,7 @@ mod test {
         let client = FundingEscrowClient::new(&env, &contract_id);
 
         // Initialize
-        client.initialize(&token.address, &attestation_key);
+        client.initialize(&token.address, &admin, &attestation_key);
 
         // Deposit
         let memo = String::from_str(&env, "donation:123");
@@ -269,5 +558,238 @@ mod test {
         let attestation = Bytes::from_array(&env, &[0u8; 64]);
         client.claim(&project_id, &600, &attestation);
     }
-}
 
+    #[test]
+    fn test_deposit_tracks_donor_totals() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let project_id = BytesN::from_array(&env, &[1u8; 32]);
+        let other_project = BytesN::from_array(&env, &[9u8; 32]);
+        let attestation_key = BytesN::from_array(&env, &[2u8; 32]);
+
+        let token = create_token_contract(&env, &admin);
+        token.mint(&alice, &1000);
+        token.mint(&bob, &1000);
+
+        let contract_id = env.register_contract(None, FundingEscrow);
+        let client = FundingEscrowClient::new(&env, &contract_id);
+        client.initialize(&token.address, &admin, &attestation_key);
+
+        let memo = String::from_str(&env, "donation:123");
+        client.deposit(&alice, &project_id, &100, &memo);
+        client.deposit(&alice, &project_id, &150, &memo);
+        client.deposit(&bob, &project_id, &40, &memo);
+        client.deposit(&alice, &other_project, &70, &memo);
+
+        assert_eq!(client.get_donor_total(&project_id, &alice), 250);
+        assert_eq!(client.get_donor_total(&project_id, &bob), 40);
+        assert_eq!(client.get_donor_total(&other_project, &alice), 70);
+        assert_eq!(client.get_donor_total(&other_project, &bob), 0);
+        assert_eq!(client.get_balance(&project_id), 290);
+    }
+
+    #[test]
+    fn test_deposit_for_credits_donor() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let platform = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let project_id = BytesN::from_array(&env, &[1u8; 32]);
+        let attestation_key = BytesN::from_array(&env, &[2u8; 32]);
+
+        let token = create_token_contract(&env, &admin);
+        token.mint(&platform, &1000);
+
+        let contract_id = env.register_contract(None, FundingEscrow);
+        let client = FundingEscrowClient::new(&env, &contract_id);
+        client.initialize(&token.address, &admin, &attestation_key);
+
+        let memo = String::from_str(&env, "donation:123");
+        client.deposit_for(&platform, &alice, &project_id, &300, &memo);
+
+        assert_eq!(client.get_donor_total(&project_id, &alice), 300);
+        assert_eq!(client.get_donor_total(&project_id, &platform), 0);
+        assert_eq!(token.balance(&platform), 700);
+        assert_eq!(client.get_balance(&project_id), 300);
+    }
+
+    #[test]
+    fn test_deposit_respects_funding_cap() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let user = Address::generate(&env);
+        let project_id = BytesN::from_array(&env, &[1u8; 32]);
+        let attestation_key = BytesN::from_array(&env, &[2u8; 32]);
+
+        let token = create_token_contract(&env, &admin);
+        token.mint(&user, &1000);
+
+        let contract_id = env.register_contract(None, FundingEscrow);
+        let client = FundingEscrowClient::new(&env, &contract_id);
+        client.initialize(&token.address, &admin, &attestation_key);
+
+        assert_eq!(client.get_remaining_capacity(&project_id), None);
+        client.set_funding_cap(&project_id, &500);
+        assert_eq!(client.get_remaining_capacity(&project_id), Some(500));
+
+        let memo = String::from_str(&env, "donation:123");
+        assert_eq!(client.deposit(&user, &project_id, &300, &memo), 300);
+
+        // Only the remaining 200 is taken from an oversized deposit
+        assert_eq!(client.deposit(&user, &project_id, &300, &memo), 200);
+        assert_eq!(token.balance(&user), 500);
+        assert_eq!(client.get_remaining_capacity(&project_id), Some(0));
+        assert_eq!(client.get_donor_total(&project_id, &user), 500);
+
+        assert!(client.try_deposit(&user, &project_id, &10, &memo).is_err());
+    }
+
+    #[test]
+    fn test_deposit_refused_for_closed_projects() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let user = Address::generate(&env);
+        let active_id = BytesN::from_array(&env, &[1u8; 32]);
+        let cancelled_id = BytesN::from_array(&env, &[2u8; 32]);
+        let attestation_key = BytesN::from_array(&env, &[3u8; 32]);
+
+        let token = create_token_contract(&env, &admin);
+        token.mint(&user, &1000);
+
+        let registry_id = env.register_contract(None, MockRegistry);
+        let registry = MockRegistryClient::new(&env, &registry_id);
+        registry.set_accepting(&active_id, &true);
+        registry.set_accepting(&cancelled_id, &false);
+
+        let contract_id = env.register_contract(None, FundingEscrow);
+        let client = FundingEscrowClient::new(&env, &contract_id);
+        client.initialize(&token.address, &admin, &attestation_key);
+        client.set_registry(&registry_id);
+
+        let memo = String::from_str(&env, "donation:123");
+        assert_eq!(client.deposit(&user, &active_id, &100, &memo), 100);
+        assert!(client.try_deposit(&user, &cancelled_id, &100, &memo).is_err());
+        assert_eq!(token.balance(&user), 900);
+    }
+
+    #[test]
+    fn test_refund_after_cancellation() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let project_id = BytesN::from_array(&env, &[1u8; 32]);
+        let attestation_key = BytesN::from_array(&env, &[3u8; 32]);
+
+        let token = create_token_contract(&env, &admin);
+        token.mint(&alice, &1000);
+        token.mint(&bob, &1000);
+
+        let registry_id = env.register_contract(None, MockRegistry);
+        let registry = MockRegistryClient::new(&env, &registry_id);
+        registry.set_accepting(&project_id, &true);
+
+        let contract_id = env.register_contract(None, FundingEscrow);
+        let client = FundingEscrowClient::new(&env, &contract_id);
+        client.initialize(&token.address, &admin, &attestation_key);
+        client.set_registry(&registry_id);
+
+        let memo = String::from_str(&env, "donation:123");
+        client.deposit(&alice, &project_id, &300, &memo);
+        client.deposit(&bob, &project_id, &100, &memo);
+
+        // Refunds wait until the project stops taking deposits
+        assert!(client.try_refund(&project_id, &alice, &300).is_err());
+        registry.set_accepting(&project_id, &false);
+
+        // Capped at what the donor deposited
+        assert_eq!(client.refund(&project_id, &alice, &500), 300);
+        assert_eq!(token.balance(&alice), 1000);
+        assert_eq!(client.get_donor_total(&project_id, &alice), 0);
+        assert!(client.try_refund(&project_id, &alice, &10).is_err());
+
+        assert_eq!(client.refund(&project_id, &bob, &60), 60);
+        assert_eq!(client.get_balance(&project_id), 40);
+    }
+
+    #[test]
+    fn test_rotate_attestation_key_with_grace_period() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let old_key = BytesN::from_array(&env, &[2u8; 32]);
+        let new_key = BytesN::from_array(&env, &[3u8; 32]);
+
+        let token = create_token_contract(&env, &admin);
+        let contract_id = env.register_contract(None, FundingEscrow);
+        let client = FundingEscrowClient::new(&env, &contract_id);
+        client.initialize(&token.address, &admin, &old_key);
+
+        client.set_attestation_grace_period(&3600);
+        env.ledger().with_mut(|li| li.timestamp = 1000);
+        client.rotate_attestation_key(&new_key);
+
+        // Both keys are accepted during the grace window
+        assert!(client.is_attestation_key_valid(&new_key));
+        assert!(client.is_attestation_key_valid(&old_key));
+
+        // Old key expires once the window passes
+        env.ledger().with_mut(|li| li.timestamp = 1000 + 3601);
+        assert!(client.is_attestation_key_valid(&new_key));
+        assert!(!client.is_attestation_key_valid(&old_key));
+    }
+
+    #[test]
+    fn test_release_accepts_retired_key_only_during_grace_period() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let user = Address::generate(&env);
+        let recipient = Address::generate(&env);
+        let project_id = BytesN::from_array(&env, &[1u8; 32]);
+        let old_key = SigningKey::from_bytes(&[2u8; 32]);
+        let new_key = SigningKey::from_bytes(&[3u8; 32]);
+
+        let token = create_token_contract(&env, &admin);
+        token.mint(&user, &1000);
+        let contract_id = env.register_contract(None, FundingEscrow);
+        let client = FundingEscrowClient::new(&env, &contract_id);
+        client.initialize(&token.address, &admin, &public_key(&env, &old_key));
+        client.deposit(&user, &project_id, &500, &String::from_str(&env, "donation:1"));
+
+        client.set_attestation_grace_period(&3600);
+        env.ledger().with_mut(|li| li.timestamp = 1000);
+        client.rotate_attestation_key(&public_key(&env, &new_key));
+
+        let release_payload = |total_claimed: i128, amount: i128| {
+            let mut payload = FundingEscrow::claim_payload(&env, &project_id, total_claimed, amount);
+            payload.append(&recipient.clone().to_xdr(&env));
+            payload
+        };
+
+        // Inside the grace window the old key still signs releases
+        client.release_to_recipient(&project_id, &recipient, &100, &attest(&env, &old_key, &release_payload(0, 100)));
+        assert_eq!(token.balance(&recipient), 100);
+
+        // Afterwards only the new key does
+        env.ledger().with_mut(|li| li.timestamp = 1000 + 3601);
+        let stale = attest(&env, &old_key, &release_payload(100, 100));
+        assert!(client.try_release_to_recipient(&project_id, &recipient, &100, &stale).is_err());
+        client.release_to_recipient(&project_id, &recipient, &100, &attest(&env, &new_key, &release_payload(100, 100)));
+        assert_eq!(token.balance(&recipient), 200);
+    }
+}
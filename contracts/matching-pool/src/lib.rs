@@ -0,0 +1,340 @@
+#![no_std]
+use soroban_sdk::{contract, contractimpl, contracttype, token, Address, Bytes, BytesN, Env, String, log};
+
+/// Total match ratio in basis points (10000 = 1:1)
+const RATIO_DENOMINATOR: i128 = 10_000;
+
+/// Sponsor funds locked to match donations for a campaign
+#[contracttype]
+#[derive(Clone)]
+pub struct MatchingPoolInfo {
+    pub pool_id: BytesN<32>,
+    pub sponsor: Address,
+    pub ratio_bps: u32,   // match per donated stroop, e.g. 5000 = 50 cents on the dollar
+    pub cap: i128,        // most the pool will ever match
+    pub total_locked: i128,
+    pub total_matched: i128,
+    pub active: bool,
+}
+
+#[contracttype]
+pub enum DataKey {
+    Pool(BytesN<32>),
+    ProjectMatched(BytesN<32>, BytesN<32>), // (pool_id, project_id)
+    MatchedDeposit(BytesN<32>),             // deposit tx hash, guards against double matching
+    Token,
+    Admin,
+    AttestationKey,
+}
+
+#[contract]
+pub struct MatchingPool;
+
+#[contractimpl]
+impl MatchingPool {
+    /// Initialize the contract with token address, admin, and attestation public key
+    pub fn initialize(env: Env, token: Address, admin: Address, attestation_pubkey: BytesN<32>) {
+        if env.storage().instance().has(&DataKey::Token) {
+            panic!("Already initialized");
+        }
+
+        env.storage().instance().set(&DataKey::Token, &token);
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::AttestationKey, &attestation_pubkey);
+        log!(&env, "Matching pool contract initialized");
+    }
+
+    /// Replace the attestation key used to verify match requests (admin only)
+    pub fn set_attestation_key(env: Env, new_key: BytesN<32>) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::Admin)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        env.storage().instance().set(&DataKey::AttestationKey, &new_key);
+
+        Ok(())
+    }
+
+    /// Lock sponsor funds into a new matching pool
+    pub fn create_pool(
+        env: Env,
+        sponsor: Address,
+        pool_id: BytesN<32>,
+        amount: i128,
+        ratio_bps: u32,
+        cap: i128,
+    ) -> Result<(), String> {
+        sponsor.require_auth();
+
+        if amount <= 0 || cap <= 0 {
+            return Err(String::from_str(&env, "Amount and cap must be positive"));
+        }
+        if ratio_bps == 0 {
+            return Err(String::from_str(&env, "Ratio must be positive"));
+        }
+
+        let key = DataKey::Pool(pool_id.clone());
+        if env.storage().persistent().has(&key) {
+            return Err(String::from_str(&env, "Pool already exists"));
+        }
+
+        let token: Address = env.storage().instance()
+            .get(&DataKey::Token)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        let token_client = token::Client::new(&env, &token);
+        token_client.transfer(&sponsor, &env.current_contract_address(), &amount);
+
+        env.storage().persistent().set(&key, &MatchingPoolInfo {
+            pool_id: pool_id.clone(),
+            sponsor: sponsor.clone(),
+            ratio_bps,
+            cap,
+            total_locked: amount,
+            total_matched: 0,
+            active: true,
+        });
+
+        log!(&env, "PoolCreated: pool={:?}, sponsor={:?}, amount={}, ratio_bps={}, cap={}",
+             pool_id, sponsor, amount, ratio_bps, cap);
+
+        Ok(())
+    }
+
+    /// Match a verified escrow deposit. The backend attests the deposit by
+    /// signing `match_payload`; the matched amount is sent to `escrow`.
+    /// Returns the amount matched, which may be less than the ratio allows
+    /// once the pool nears its cap or runs out of locked funds.
+    pub fn record_match(
+        env: Env,
+        pool_id: BytesN<32>,
+        project_id: BytesN<32>,
+        deposit_tx: BytesN<32>,
+        deposit_amount: i128,
+        escrow: Address,
+        attestation: BytesN<64>,
+    ) -> Result<i128, String> {
+        if deposit_amount <= 0 {
+            return Err(String::from_str(&env, "Amount must be positive"));
+        }
+
+        let key = DataKey::Pool(pool_id.clone());
+        let mut pool: MatchingPoolInfo = env.storage().persistent()
+            .get(&key)
+            .ok_or(String::from_str(&env, "Pool not found"))?;
+        if !pool.active {
+            return Err(String::from_str(&env, "Pool is closed"));
+        }
+
+        let matched_key = DataKey::MatchedDeposit(deposit_tx.clone());
+        if env.storage().persistent().has(&matched_key) {
+            return Err(String::from_str(&env, "Deposit already matched"));
+        }
+
+        let attestation_key: BytesN<32> = env.storage().instance()
+            .get(&DataKey::AttestationKey)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        let payload = Self::match_payload(&env, &pool_id, &project_id, &deposit_tx, deposit_amount);
+        // Traps if the signature is invalid
+        env.crypto().ed25519_verify(&attestation_key, &payload, &attestation);
+
+        let remaining = Self::remaining(&pool);
+        let wanted = deposit_amount * pool.ratio_bps as i128 / RATIO_DENOMINATOR;
+        let matched = wanted.min(remaining);
+
+        env.storage().persistent().set(&matched_key, &matched);
+        if matched <= 0 {
+            log!(&env, "MatchSkipped: pool={:?}, deposit={:?}, pool exhausted", pool_id, deposit_tx);
+            return Ok(0);
+        }
+
+        let token: Address = env.storage().instance()
+            .get(&DataKey::Token)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        let token_client = token::Client::new(&env, &token);
+        token_client.transfer(&env.current_contract_address(), &escrow, &matched);
+
+        pool.total_matched += matched;
+        env.storage().persistent().set(&key, &pool);
+
+        let project_key = DataKey::ProjectMatched(pool_id.clone(), project_id.clone());
+        let project_total: i128 = env.storage().persistent().get(&project_key).unwrap_or(0);
+        env.storage().persistent().set(&project_key, &(project_total + matched));
+
+        log!(&env, "MatchRecorded: pool={:?}, project={:?}, deposit={}, matched={}",
+             pool_id, project_id, deposit_amount, matched);
+
+        Ok(matched)
+    }
+
+    /// Close a pool and return unmatched funds to the sponsor (sponsor only)
+    pub fn close_pool(env: Env, pool_id: BytesN<32>) -> Result<i128, String> {
+        let key = DataKey::Pool(pool_id.clone());
+        let mut pool: MatchingPoolInfo = env.storage().persistent()
+            .get(&key)
+            .ok_or(String::from_str(&env, "Pool not found"))?;
+        pool.sponsor.require_auth();
+
+        if !pool.active {
+            return Err(String::from_str(&env, "Pool is closed"));
+        }
+
+        let refund = pool.total_locked - pool.total_matched;
+        if refund > 0 {
+            let token: Address = env.storage().instance()
+                .get(&DataKey::Token)
+                .ok_or(String::from_str(&env, "Not initialized"))?;
+            let token_client = token::Client::new(&env, &token);
+            token_client.transfer(&env.current_contract_address(), &pool.sponsor, &refund);
+        }
+
+        pool.active = false;
+        env.storage().persistent().set(&key, &pool);
+
+        log!(&env, "PoolClosed: pool={:?}, refunded={}", pool_id, refund);
+
+        Ok(refund)
+    }
+
+    /// Get pool info
+    pub fn get_pool(env: Env, pool_id: BytesN<32>) -> Option<MatchingPoolInfo> {
+        env.storage().persistent().get(&DataKey::Pool(pool_id))
+    }
+
+    /// Amount the pool can still match
+    pub fn get_remaining(env: Env, pool_id: BytesN<32>) -> i128 {
+        env.storage().persistent()
+            .get::<DataKey, MatchingPoolInfo>(&DataKey::Pool(pool_id))
+            .filter(|pool| pool.active)
+            .map(|pool| Self::remaining(&pool))
+            .unwrap_or(0)
+    }
+
+    /// Total a pool has matched for a project
+    pub fn get_project_matched(env: Env, pool_id: BytesN<32>, project_id: BytesN<32>) -> i128 {
+        env.storage().persistent()
+            .get(&DataKey::ProjectMatched(pool_id, project_id))
+            .unwrap_or(0)
+    }
+}
+
+impl MatchingPool {
+    /// Bytes the attestation key signs for a match: pool_id || project_id || deposit_tx || amount (be)
+    pub fn match_payload(
+        env: &Env,
+        pool_id: &BytesN<32>,
+        project_id: &BytesN<32>,
+        deposit_tx: &BytesN<32>,
+        deposit_amount: i128,
+    ) -> Bytes {
+        let mut payload = Bytes::from_array(env, &pool_id.to_array());
+        payload.extend_from_array(&project_id.to_array());
+        payload.extend_from_array(&deposit_tx.to_array());
+        payload.extend_from_array(&deposit_amount.to_be_bytes());
+        payload
+    }
+
+    fn remaining(pool: &MatchingPoolInfo) -> i128 {
+        pool.cap.min(pool.total_locked) - pool.total_matched
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use soroban_sdk::{testutils::Address as _, token, Env};
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn create_token_contract<'a>(env: &Env, admin: &Address) -> token::Client<'a> {
+        let token_contract_id = env.register_stellar_asset_contract(admin.clone());
+        token::Client::new(env, &token_contract_id)
+    }
+
+    fn attest(
+        env: &Env,
+        key: &SigningKey,
+        pool_id: &BytesN<32>,
+        project_id: &BytesN<32>,
+        deposit_tx: &BytesN<32>,
+        amount: i128,
+    ) -> BytesN<64> {
+        let payload = MatchingPool::match_payload(env, pool_id, project_id, deposit_tx, amount);
+        let mut buf = [0u8; 112];
+        payload.copy_into_slice(&mut buf);
+        BytesN::from_array(env, &key.sign(&buf).to_bytes())
+    }
+
+    #[test]
+    fn test_match_until_cap_then_refund() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let sponsor = Address::generate(&env);
+        let escrow = Address::generate(&env);
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let pool_id = BytesN::from_array(&env, &[1u8; 32]);
+        let project_id = BytesN::from_array(&env, &[2u8; 32]);
+
+        let token = create_token_contract(&env, &admin);
+        token.mint(&sponsor, &1000);
+
+        let contract_id = env.register_contract(None, MatchingPool);
+        let client = MatchingPoolClient::new(&env, &contract_id);
+        client.initialize(&token.address, &admin, &BytesN::from_array(&env, &key.verifying_key().to_bytes()));
+
+        // Lock 1000, match 1:2, never more than 300
+        client.create_pool(&sponsor, &pool_id, &1000, &5000, &300);
+
+        let tx1 = BytesN::from_array(&env, &[10u8; 32]);
+        let sig1 = attest(&env, &key, &pool_id, &project_id, &tx1, 400);
+        assert_eq!(client.record_match(&pool_id, &project_id, &tx1, &400, &escrow, &sig1), 200);
+
+        // Replaying the same deposit is rejected
+        assert!(client.try_record_match(&pool_id, &project_id, &tx1, &400, &escrow, &sig1).is_err());
+
+        // Second deposit is only partially matched at the cap
+        let tx2 = BytesN::from_array(&env, &[11u8; 32]);
+        let sig2 = attest(&env, &key, &pool_id, &project_id, &tx2, 400);
+        assert_eq!(client.record_match(&pool_id, &project_id, &tx2, &400, &escrow, &sig2), 100);
+        assert_eq!(client.get_remaining(&pool_id), 0);
+        assert_eq!(client.get_project_matched(&pool_id, &project_id), 300);
+        assert_eq!(token.balance(&escrow), 300);
+
+        // Closing returns the unmatched 700 to the sponsor
+        assert_eq!(client.close_pool(&pool_id), 700);
+        assert_eq!(token.balance(&sponsor), 700);
+        assert!(!client.get_pool(&pool_id).unwrap().active);
+    }
+
+    #[test]
+    fn test_record_match_rejects_bad_attestation() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let sponsor = Address::generate(&env);
+        let escrow = Address::generate(&env);
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let other = SigningKey::from_bytes(&[8u8; 32]);
+        let pool_id = BytesN::from_array(&env, &[1u8; 32]);
+        let project_id = BytesN::from_array(&env, &[2u8; 32]);
+
+        let token = create_token_contract(&env, &admin);
+        token.mint(&sponsor, &1000);
+
+        let contract_id = env.register_contract(None, MatchingPool);
+        let client = MatchingPoolClient::new(&env, &contract_id);
+        client.initialize(&token.address, &admin, &BytesN::from_array(&env, &key.verifying_key().to_bytes()));
+        client.create_pool(&sponsor, &pool_id, &1000, &10_000, &1000);
+
+        let tx = BytesN::from_array(&env, &[10u8; 32]);
+        let forged = attest(&env, &other, &pool_id, &project_id, &tx, 400);
+        assert!(client.try_record_match(&pool_id, &project_id, &tx, &400, &escrow, &forged).is_err());
+
+        // Signature over a different amount does not verify either
+        let wrong_amount = attest(&env, &key, &pool_id, &project_id, &tx, 100);
+        assert!(client.try_record_match(&pool_id, &project_id, &tx, &400, &escrow, &wrong_amount).is_err());
+        assert_eq!(token.balance(&escrow), 0);
+    }
+}
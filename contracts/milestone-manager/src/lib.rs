@@ -1,5 +1,13 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, contracttype, token, Address, Bytes, BytesN, Env, String, log};
+use soroban_sdk::{contract, contractimpl, contracttype, token, vec, Address, Bytes, BytesN, Env, IntoVal, String, Symbol, Vec, log};
+
+/// A milestone payee and their share in basis points (10000 = 100%)
+#[contracttype]
+#[derive(Clone)]
+pub struct RecipientShare {
+    pub address: Address,
+    pub share_bps: u32,
+}
 
 #[contracttype]
 #[derive(Clone)]
@@ -10,7 +18,16 @@ pub struct MilestoneInfo {
     pub proof_required: bool,
     pub released: bool,
     pub released_at: u64,
-    pub recipient: Address,
+    pub recipients: Vec<RecipientShare>,
+}
+
+/// Deliverable proof anchored on-chain before release
+#[contracttype]
+#[derive(Clone)]
+pub struct MilestoneProof {
+    pub proof_hash: BytesN<32>,
+    pub proof_uri: String,
+    pub submitted_at: u64,
 }
 
 #[contracttype]
@@ -23,14 +40,70 @@ pub struct ProjectMilestones {
     pub released_amount: i128,
 }
 
+/// Attestation signers for threshold releases of large milestones
+#[contracttype]
+#[derive(Clone)]
+pub struct SignerSet {
+    pub signers: Vec<BytesN<32>>,
+    pub threshold: u32,
+    pub min_amount: i128, // milestones at or above this amount need `threshold` signatures
+}
+
+#[contracttype]
+#[derive(Clone)]
+pub struct SignerSignature {
+    pub signer: BytesN<32>,
+    pub signature: BytesN<64>,
+}
+
+/// Attestation key kept valid after a rotation until `expires_at`
+#[contracttype]
+#[derive(Clone)]
+pub struct RetiredKey {
+    pub key: BytesN<32>,
+    pub expires_at: u64,
+}
+
+/// Donor vote on a milestone, weighted by the donor's escrow deposits.
+/// Release is blocked while the window is open and afterwards if disputes outweigh approvals.
+#[contracttype]
+#[derive(Clone)]
+pub struct VoteTally {
+    pub opens_at: u64,
+    pub closes_at: u64,
+    pub approve_weight: i128,
+    pub dispute_weight: i128,
+    pub voters: u32,
+}
+
+#[contracttype]
+#[derive(Clone)]
+pub struct DonorVote {
+    pub approve: bool,
+    pub weight: i128,
+}
+
 #[contracttype]
 pub enum DataKey {
     Milestone(BytesN<32>), // milestone_id as key
     ProjectMilestones(BytesN<32>), // project_id as key
     AttestationKey,
     AdminKey,
+    SignerSet,
+    PreviousAttestationKey,
+    AttestationGracePeriod,
+    Token,
+    Proof(BytesN<32>), // milestone_id as key
+    EscrowContract,
+    VoteTally(BytesN<32>), // milestone_id as key
+    Vote(BytesN<32>, Address), // (milestone_id, donor)
 }
 
+const TOTAL_SHARE_BPS: u32 = 10_000;
+
+/// Default time a rotated-out attestation key stays valid (24 hours)
+const DEFAULT_GRACE_PERIOD_SECS: u64 = 86_400;
+
 #[contract]
 pub struct MilestoneManager;
 
@@ -55,7 +128,7 @@ impl MilestoneManager {
         milestone_id: BytesN<32>,
         amount_stroops: i128,
         proof_required: bool,
-        recipient: Address,
+        recipients: Vec<RecipientShare>,
     ) -> Result<(), String> {
         // Only admin can register milestones
         let admin: Address = env.storage().instance()
@@ -67,6 +140,20 @@ impl MilestoneManager {
             return Err(String::from_str(&env, "Amount must be positive"));
         }
 
+        if recipients.is_empty() {
+            return Err(String::from_str(&env, "At least one recipient required"));
+        }
+        let mut total_bps: u32 = 0;
+        for share in recipients.iter() {
+            if share.share_bps == 0 {
+                return Err(String::from_str(&env, "Recipient share must be positive"));
+            }
+            total_bps += share.share_bps;
+        }
+        if total_bps != TOTAL_SHARE_BPS {
+            return Err(String::from_str(&env, "Recipient shares must sum to 10000"));
+        }
+
         // Check if milestone already exists
         let milestone_key = DataKey::Milestone(milestone_id.clone());
         if env.storage().persistent().has(&milestone_key) {
@@ -81,7 +168,7 @@ impl MilestoneManager {
             proof_required,
             released: false,
             released_at: 0,
-            recipient,
+            recipients,
         };
 
         // Store milestone
@@ -110,7 +197,55 @@ impl MilestoneManager {
         Ok(())
     }
 
-    /// Release funds for a milestone with admin attestation
+    /// Anchor a hash of the milestone deliverable. Must be authorized by the
+    /// milestone's first (lead) recipient, or by the admin submitting on the
+    /// student's behalf; can be resubmitted until release.
+    pub fn submit_proof(
+        env: Env,
+        caller: Address,
+        milestone_id: BytesN<32>,
+        proof_hash: BytesN<32>,
+        proof_uri: String,
+    ) -> Result<(), String> {
+        let milestone_info: MilestoneInfo = env.storage()
+            .persistent()
+            .get(&DataKey::Milestone(milestone_id.clone()))
+            .ok_or(String::from_str(&env, "Milestone not found"))?;
+
+        if milestone_info.released {
+            return Err(String::from_str(&env, "Milestone already released"));
+        }
+
+        let lead = milestone_info.recipients
+            .get(0)
+            .ok_or(String::from_str(&env, "Milestone has no recipients"))?;
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::AdminKey)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        if caller != lead.address && caller != admin {
+            return Err(String::from_str(&env, "Only the lead recipient or admin can submit proofs"));
+        }
+        caller.require_auth();
+
+        env.storage().persistent().set(&DataKey::Proof(milestone_id.clone()), &MilestoneProof {
+            proof_hash: proof_hash.clone(),
+            proof_uri,
+            submitted_at: env.ledger().timestamp(),
+        });
+
+        log!(&env, "ProofSubmitted: milestone={:?}, hash={:?}", milestone_id, proof_hash);
+
+        Ok(())
+    }
+
+    /// Get the proof anchored for a milestone
+    pub fn get_proof(env: Env, milestone_id: BytesN<32>) -> Option<MilestoneProof> {
+        env.storage().persistent().get(&DataKey::Proof(milestone_id))
+    }
+
+    /// Release funds for a milestone with admin attestation. The attestation
+    /// is the signer's public key (32 bytes) followed by its ed25519
+    /// signature (64 bytes) over the release payload
     pub fn release_milestone(
         env: Env,
         milestone_id: BytesN<32>,
@@ -118,7 +253,7 @@ impl MilestoneManager {
     ) -> Result<(), String> {
         // Get milestone info
         let milestone_key = DataKey::Milestone(milestone_id.clone());
-        let mut milestone_info: MilestoneInfo = env.storage()
+        let milestone_info: MilestoneInfo = env.storage()
             .persistent()
             .get(&milestone_key)
             .ok_or(String::from_str(&env, "Milestone not found"))?;
@@ -127,44 +262,306 @@ impl MilestoneManager {
             return Err(String::from_str(&env, "Milestone already released"));
         }
 
-        // Verify attestation signature (simplified for now)
-        let attestation_key: BytesN<32> = env.storage().instance()
-            .get(&DataKey::AttestationKey)
+        if !Self::proof_satisfied(&env, &milestone_id, &milestone_info) {
+            return Err(String::from_str(&env, "Proof required"));
+        }
+
+        Self::check_vote(&env, &milestone_id)?;
+
+        if Self::requires_threshold(&env, milestone_info.amount_stroops) {
+            return Err(String::from_str(&env, "Milestone requires threshold attestation"));
+        }
+
+        let payload = Self::release_payload(&env, &milestone_id, milestone_info.amount_stroops);
+        Self::verify_attestation(&env, &payload, &attestation_signature)?;
+
+        Self::mark_released(&env, &milestone_id, milestone_info);
+
+        Ok(())
+    }
+
+    /// Release funds for a large milestone once `threshold` distinct signers
+    /// from the signer set have signed the release payload
+    pub fn release_milestone_multisig(
+        env: Env,
+        milestone_id: BytesN<32>,
+        signatures: Vec<SignerSignature>,
+    ) -> Result<(), String> {
+        let milestone_key = DataKey::Milestone(milestone_id.clone());
+        let milestone_info: MilestoneInfo = env.storage()
+            .persistent()
+            .get(&milestone_key)
+            .ok_or(String::from_str(&env, "Milestone not found"))?;
+
+        if milestone_info.released {
+            return Err(String::from_str(&env, "Milestone already released"));
+        }
+
+        if !Self::proof_satisfied(&env, &milestone_id, &milestone_info) {
+            return Err(String::from_str(&env, "Proof required"));
+        }
+
+        Self::check_vote(&env, &milestone_id)?;
+
+        let signer_set: SignerSet = env.storage().instance()
+            .get(&DataKey::SignerSet)
+            .ok_or(String::from_str(&env, "Signer set not configured"))?;
+
+        let payload = Self::release_payload(&env, &milestone_id, milestone_info.amount_stroops);
+        let mut seen: Vec<BytesN<32>> = Vec::new(&env);
+        for entry in signatures.iter() {
+            if !signer_set.signers.contains(&entry.signer) {
+                return Err(String::from_str(&env, "Unknown signer"));
+            }
+            if seen.contains(&entry.signer) {
+                return Err(String::from_str(&env, "Duplicate signer"));
+            }
+            // Traps if the signature is invalid
+            env.crypto().ed25519_verify(&entry.signer, &payload, &entry.signature);
+            seen.push_back(entry.signer.clone());
+        }
+
+        if seen.len() < signer_set.threshold {
+            return Err(String::from_str(&env, "Not enough attestation signatures"));
+        }
+
+        Self::mark_released(&env, &milestone_id, milestone_info);
+
+        Ok(())
+    }
+
+    /// Replace the threshold signer set (admin only)
+    pub fn rotate_signer_set(
+        env: Env,
+        signers: Vec<BytesN<32>>,
+        threshold: u32,
+        min_amount: i128,
+    ) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::AdminKey)
             .ok_or(String::from_str(&env, "Not initialized"))?;
-        
-        // In production, this would verify the signature against the attestation key
-        if attestation_signature.len() < 64 {
-            return Err(String::from_str(&env, "Invalid attestation signature"));
+        admin.require_auth();
+
+        if threshold == 0 || threshold > signers.len() {
+            return Err(String::from_str(&env, "Invalid threshold"));
+        }
+        if min_amount <= 0 {
+            return Err(String::from_str(&env, "Minimum amount must be positive"));
+        }
+        for i in 0..signers.len() {
+            for j in (i + 1)..signers.len() {
+                if signers.get_unchecked(i) == signers.get_unchecked(j) {
+                    return Err(String::from_str(&env, "Duplicate signer"));
+                }
+            }
         }
 
-        // Mark milestone as released
-        milestone_info.released = true;
-        milestone_info.released_at = env.ledger().timestamp();
-        env.storage().persistent().set(&milestone_key, &milestone_info);
+        let signer_count = signers.len();
+        env.storage().instance().set(&DataKey::SignerSet, &SignerSet {
+            signers,
+            threshold,
+            min_amount,
+        });
 
-        // Update project milestones summary
-        let project_key = DataKey::ProjectMilestones(milestone_info.project_id.clone());
-        let mut project_milestones: ProjectMilestones = env.storage()
+        log!(&env, "SignerSetRotated: signers={}, threshold={}, min_amount={}", signer_count, threshold, min_amount);
+
+        Ok(())
+    }
+
+    /// Set the funding escrow whose donor records weight governance votes (admin only)
+    pub fn set_escrow_contract(env: Env, escrow: Address) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::AdminKey)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        env.storage().instance().set(&DataKey::EscrowContract, &escrow);
+
+        Ok(())
+    }
+
+    /// Put a milestone to a donor vote for `window_secs` (admin only). Once
+    /// opened, the milestone cannot be released until the window closes with
+    /// approvals at least matching disputes.
+    pub fn open_vote(env: Env, milestone_id: BytesN<32>, window_secs: u64) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::AdminKey)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        if !env.storage().instance().has(&DataKey::EscrowContract) {
+            return Err(String::from_str(&env, "Escrow contract not configured"));
+        }
+        if window_secs == 0 {
+            return Err(String::from_str(&env, "Voting window must be positive"));
+        }
+
+        let milestone_info: MilestoneInfo = env.storage()
             .persistent()
-            .get(&project_key)
-            .unwrap_or(ProjectMilestones {
-                project_id: milestone_info.project_id.clone(),
-                total_milestones: 0,
-                released_milestones: 0,
-                total_amount: 0,
-                released_amount: 0,
-            });
+            .get(&DataKey::Milestone(milestone_id.clone()))
+            .ok_or(String::from_str(&env, "Milestone not found"))?;
+        if milestone_info.released {
+            return Err(String::from_str(&env, "Milestone already released"));
+        }
 
-        project_milestones.released_milestones += 1;
-        project_milestones.released_amount += milestone_info.amount_stroops;
-        env.storage().persistent().set(&project_key, &project_milestones);
+        let tally_key = DataKey::VoteTally(milestone_id.clone());
+        if env.storage().persistent().has(&tally_key) {
+            return Err(String::from_str(&env, "Vote already opened"));
+        }
+
+        let opens_at = env.ledger().timestamp();
+        env.storage().persistent().set(&tally_key, &VoteTally {
+            opens_at,
+            closes_at: opens_at + window_secs,
+            approve_weight: 0,
+            dispute_weight: 0,
+            voters: 0,
+        });
+
+        log!(&env, "VoteOpened: milestone={:?}, closes_at={}", milestone_id, opens_at + window_secs);
+
+        Ok(())
+    }
+
+    /// Approve or dispute a milestone as a donor. The vote is weighted by the
+    /// donor's deposits to the project in the funding escrow; returns the weight.
+    pub fn cast_vote(
+        env: Env,
+        milestone_id: BytesN<32>,
+        voter: Address,
+        approve: bool,
+    ) -> Result<i128, String> {
+        voter.require_auth();
+
+        let tally_key = DataKey::VoteTally(milestone_id.clone());
+        let mut tally: VoteTally = env.storage()
+            .persistent()
+            .get(&tally_key)
+            .ok_or(String::from_str(&env, "No vote open for milestone"))?;
+        if env.ledger().timestamp() > tally.closes_at {
+            return Err(String::from_str(&env, "Voting window closed"));
+        }
+
+        let vote_key = DataKey::Vote(milestone_id.clone(), voter.clone());
+        if env.storage().persistent().has(&vote_key) {
+            return Err(String::from_str(&env, "Already voted"));
+        }
+
+        let milestone_info: MilestoneInfo = env.storage()
+            .persistent()
+            .get(&DataKey::Milestone(milestone_id.clone()))
+            .ok_or(String::from_str(&env, "Milestone not found"))?;
+
+        let escrow: Address = env.storage().instance()
+            .get(&DataKey::EscrowContract)
+            .ok_or(String::from_str(&env, "Escrow contract not configured"))?;
+        let weight: i128 = env.invoke_contract(
+            &escrow,
+            &Symbol::new(&env, "get_donor_total"),
+            vec![&env, milestone_info.project_id.into_val(&env), voter.into_val(&env)],
+        );
+        if weight <= 0 {
+            return Err(String::from_str(&env, "Only project donors can vote"));
+        }
+
+        if approve {
+            tally.approve_weight += weight;
+        } else {
+            tally.dispute_weight += weight;
+        }
+        tally.voters += 1;
+        env.storage().persistent().set(&tally_key, &tally);
+        env.storage().persistent().set(&vote_key, &DonorVote { approve, weight });
+
+        log!(&env, "VoteCast: milestone={:?}, voter={:?}, approve={}, weight={}", milestone_id, voter, approve, weight);
+
+        Ok(weight)
+    }
+
+    /// Get the running vote tally for a milestone
+    pub fn get_vote_tally(env: Env, milestone_id: BytesN<32>) -> Option<VoteTally> {
+        env.storage().persistent().get(&DataKey::VoteTally(milestone_id))
+    }
 
-        log!(&env, "MilestoneReleased: project={:?}, milestone={:?}, amount={}, recipient={:?}", 
-             milestone_info.project_id, milestone_id, milestone_info.amount_stroops, milestone_info.recipient);
+    /// Get a donor's vote on a milestone
+    pub fn get_vote(env: Env, milestone_id: BytesN<32>, voter: Address) -> Option<DonorVote> {
+        env.storage().persistent().get(&DataKey::Vote(milestone_id, voter))
+    }
+
+    /// Set the token used to pay milestone recipients (admin only)
+    pub fn set_payout_token(env: Env, token: Address) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::AdminKey)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        env.storage().instance().set(&DataKey::Token, &token);
 
         Ok(())
     }
 
+    /// Replace the attestation key (admin only). The previous key stays valid
+    /// for the grace period so in-flight releases signed with it still succeed.
+    pub fn rotate_attestation_key(env: Env, new_key: BytesN<32>) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::AdminKey)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        let current: BytesN<32> = env.storage().instance()
+            .get(&DataKey::AttestationKey)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        if current == new_key {
+            return Err(String::from_str(&env, "Key already active"));
+        }
+
+        let grace: u64 = env.storage().instance()
+            .get(&DataKey::AttestationGracePeriod)
+            .unwrap_or(DEFAULT_GRACE_PERIOD_SECS);
+        let expires_at = env.ledger().timestamp() + grace;
+
+        env.storage().instance().set(&DataKey::PreviousAttestationKey, &RetiredKey {
+            key: current,
+            expires_at,
+        });
+        env.storage().instance().set(&DataKey::AttestationKey, &new_key);
+
+        log!(&env, "AttestationKeyRotated: previous key valid until {}", expires_at);
+
+        Ok(())
+    }
+
+    /// Set how long a rotated-out attestation key remains valid (admin only)
+    pub fn set_attestation_grace_period(env: Env, seconds: u64) -> Result<(), String> {
+        let admin: Address = env.storage().instance()
+            .get(&DataKey::AdminKey)
+            .ok_or(String::from_str(&env, "Not initialized"))?;
+        admin.require_auth();
+
+        env.storage().instance().set(&DataKey::AttestationGracePeriod, &seconds);
+
+        Ok(())
+    }
+
+    /// Check whether a key is the active attestation key or a rotated-out key
+    /// still inside its grace window
+    pub fn is_attestation_key_valid(env: Env, key: BytesN<32>) -> bool {
+        let current: Option<BytesN<32>> = env.storage().instance().get(&DataKey::AttestationKey);
+        if current.as_ref() == Some(&key) {
+            return true;
+        }
+
+        env.storage().instance()
+            .get::<DataKey, RetiredKey>(&DataKey::PreviousAttestationKey)
+            .map(|retired| retired.key == key && env.ledger().timestamp() <= retired.expires_at)
+            .unwrap_or(false)
+    }
+
+    /// Get the current threshold signer set
+    pub fn get_signer_set(env: Env) -> Option<SignerSet> {
+        env.storage().instance().get(&DataKey::SignerSet)
+    }
+
     /// Get milestone information
     pub fn get_milestone(env: Env, milestone_id: BytesN<32>) -> Option<MilestoneInfo> {
         let milestone_key = DataKey::Milestone(milestone_id);
@@ -179,9 +576,11 @@ impl MilestoneManager {
 
     /// Check if milestone can be released (proof verification)
     pub fn can_release_milestone(env: Env, milestone_id: BytesN<32>) -> bool {
-        let milestone_key = DataKey::Milestone(milestone_id);
+        let milestone_key = DataKey::Milestone(milestone_id.clone());
         if let Some(milestone_info) = env.storage().persistent().get::<DataKey, MilestoneInfo>(&milestone_key) {
-            !milestone_info.released && (!milestone_info.proof_required || milestone_info.released_at > 0)
+            !milestone_info.released
+                && Self::proof_satisfied(&env, &milestone_id, &milestone_info)
+                && Self::check_vote(&env, &milestone_id).is_ok()
         } else {
             false
         }
@@ -198,10 +597,169 @@ impl MilestoneManager {
     }
 }
 
+impl MilestoneManager {
+    /// Payload signed by attestation signers: milestone_id || amount (big-endian)
+    pub fn release_payload(env: &Env, milestone_id: &BytesN<32>, amount_stroops: i128) -> Bytes {
+        let mut payload = Bytes::from_array(env, &milestone_id.to_array());
+        payload.extend_from_array(&amount_stroops.to_be_bytes());
+        payload
+    }
+
+    /// Accepts the active attestation key, or the previous key inside its
+    /// grace window
+    fn verify_attestation(env: &Env, payload: &Bytes, attestation: &Bytes) -> Result<(), String> {
+        if attestation.len() != 96 {
+            return Err(String::from_str(env, "Invalid attestation signature"));
+        }
+        let key: BytesN<32> = attestation.slice(0..32).try_into()
+            .map_err(|_| String::from_str(env, "Invalid attestation signature"))?;
+        let signature: BytesN<64> = attestation.slice(32..96).try_into()
+            .map_err(|_| String::from_str(env, "Invalid attestation signature"))?;
+
+        if !Self::is_attestation_key_valid(env.clone(), key.clone()) {
+            return Err(String::from_str(env, "Attestation key not valid"));
+        }
+        // Traps if the signature is invalid
+        env.crypto().ed25519_verify(&key, payload, &signature);
+        Ok(())
+    }
+
+    fn proof_satisfied(env: &Env, milestone_id: &BytesN<32>, milestone_info: &MilestoneInfo) -> bool {
+        !milestone_info.proof_required
+            || env.storage().persistent().has(&DataKey::Proof(milestone_id.clone()))
+    }
+
+    /// Milestones without an opened vote are not gated
+    fn check_vote(env: &Env, milestone_id: &BytesN<32>) -> Result<(), String> {
+        let tally: VoteTally = match env.storage().persistent().get(&DataKey::VoteTally(milestone_id.clone())) {
+            Some(tally) => tally,
+            None => return Ok(()),
+        };
+
+        if env.ledger().timestamp() <= tally.closes_at {
+            return Err(String::from_str(env, "Voting still open"));
+        }
+        if tally.dispute_weight > tally.approve_weight {
+            return Err(String::from_str(env, "Milestone disputed by donors"));
+        }
+
+        Ok(())
+    }
+
+    fn requires_threshold(env: &Env, amount_stroops: i128) -> bool {
+        env.storage().instance()
+            .get::<DataKey, SignerSet>(&DataKey::SignerSet)
+            .map(|set| amount_stroops >= set.min_amount)
+            .unwrap_or(false)
+    }
+
+    fn mark_released(env: &Env, milestone_id: &BytesN<32>, mut milestone_info: MilestoneInfo) {
+        let milestone_key = DataKey::Milestone(milestone_id.clone());
+
+        // Mark milestone as released
+        milestone_info.released = true;
+        milestone_info.released_at = env.ledger().timestamp();
+        env.storage().persistent().set(&milestone_key, &milestone_info);
+
+        // Update project milestones summary
+        let project_key = DataKey::ProjectMilestones(milestone_info.project_id.clone());
+        let mut project_milestones: ProjectMilestones = env.storage()
+            .persistent()
+            .get(&project_key)
+            .unwrap_or(ProjectMilestones {
+                project_id: milestone_info.project_id.clone(),
+                total_milestones: 0,
+                released_milestones: 0,
+                total_amount: 0,
+                released_amount: 0,
+            });
+
+        project_milestones.released_milestones += 1;
+        project_milestones.released_amount += milestone_info.amount_stroops;
+        env.storage().persistent().set(&project_key, &project_milestones);
+
+        // Pay recipients proportionally when a payout token is configured;
+        // the last recipient absorbs rounding dust
+        if let Some(token) = env.storage().instance().get::<DataKey, Address>(&DataKey::Token) {
+            let token_client = token::Client::new(env, &token);
+            let count = milestone_info.recipients.len();
+            let mut paid: i128 = 0;
+            for (i, share) in milestone_info.recipients.iter().enumerate() {
+                let amount = if i as u32 == count - 1 {
+                    milestone_info.amount_stroops - paid
+                } else {
+                    milestone_info.amount_stroops * share.share_bps as i128 / TOTAL_SHARE_BPS as i128
+                };
+                token_client.transfer(&env.current_contract_address(), &share.address, &amount);
+                paid += amount;
+
+                log!(env, "MilestonePayout: milestone={:?}, recipient={:?}, amount={}", 
+                     milestone_id, share.address, amount);
+            }
+        }
+
+        log!(env, "MilestoneReleased: project={:?}, milestone={:?}, amount={}, recipients={}", 
+             milestone_info.project_id, milestone_id, milestone_info.amount_stroops, milestone_info.recipients.len());
+        env.events().publish(
+            (Symbol::new(env, "released"), milestone_info.project_id.clone(), milestone_id.clone()),
+            milestone_info.amount_stroops,
+        );
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use soroban_sdk::{testutils::Address as _, Env, BytesN};
+    use soroban_sdk::{testutils::{Address as _, Ledger}, vec, Env, BytesN};
+    use ed25519_dalek::{Signer, SigningKey};
+
+    /// Stand-in for FundingEscrow's donor records
+    #[contract]
+    pub struct MockEscrow;
+
+    #[contractimpl]
+    impl MockEscrow {
+        pub fn set_donor_total(env: Env, project_id: BytesN<32>, donor: Address, total: i128) {
+            env.storage().persistent().set(&(project_id, donor), &total);
+        }
+
+        pub fn get_donor_total(env: Env, project_id: BytesN<32>, donor: Address) -> i128 {
+            env.storage().persistent().get(&(project_id, donor)).unwrap_or(0)
+        }
+    }
+
+    fn sole_recipient(env: &Env, recipient: &Address) -> Vec<RecipientShare> {
+        vec![env, RecipientShare { address: recipient.clone(), share_bps: 10_000 }]
+    }
+
+    fn submit_test_proof(env: &Env, client: &MilestoneManagerClient, recipient: &Address, milestone_id: &BytesN<32>) {
+        let proof_hash = BytesN::from_array(env, &[9u8; 32]);
+        client.submit_proof(recipient, milestone_id, &proof_hash, &String::from_str(env, "ipfs://deliverable"));
+    }
+
+    fn sign_release(env: &Env, key: &SigningKey, milestone_id: &BytesN<32>, amount: i128) -> SignerSignature {
+        let payload = MilestoneManager::release_payload(env, milestone_id, amount);
+        let mut buf = [0u8; 48];
+        payload.copy_into_slice(&mut buf);
+        SignerSignature {
+            signer: BytesN::from_array(env, &key.verifying_key().to_bytes()),
+            signature: BytesN::from_array(env, &key.sign(&buf).to_bytes()),
+        }
+    }
+
+    /// Single-key release attestation: public key || signature
+    fn attest_release(env: &Env, key: &SigningKey, milestone_id: &BytesN<32>, amount: i128) -> Bytes {
+        let signed = sign_release(env, key, milestone_id, amount);
+        let mut attestation = Bytes::from_array(env, &signed.signer.to_array());
+        attestation.extend_from_array(&signed.signature.to_array());
+        attestation
+    }
+
+    fn attestation_signer(env: &Env, seed: u8) -> (SigningKey, BytesN<32>) {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        let public = BytesN::from_array(env, &key.verifying_key().to_bytes());
+        (key, public)
+    }
 
     #[test]
     fn test_register_and_release_milestone() {
@@ -212,7 +770,7 @@ mod test {
         let recipient = Address::generate(&env);
         let project_id = BytesN::from_array(&env, &[1u8; 32]);
         let milestone_id = BytesN::from_array(&env, &[2u8; 32]);
-        let attestation_key = BytesN::from_array(&env, &[3u8; 32]);
+        let (signing_key, attestation_key) = attestation_signer(&env, 3);
 
         // Create contract
         let contract_id = env.register_contract(None, MilestoneManager);
@@ -222,7 +780,8 @@ mod test {
         client.initialize(&admin, &attestation_key);
 
         // Register milestone
-        client.register_milestone(&project_id, &milestone_id, &500, &true, &recipient);
+        client.register_milestone(&project_id, &milestone_id, &500, &true, &sole_recipient(&env, &recipient));
+        submit_test_proof(&env, &client, &recipient, &milestone_id);
 
         // Check milestone info
         let milestone = client.get_milestone(&milestone_id);
@@ -240,7 +799,9 @@ mod test {
         assert_eq!(project_info.released_amount, 0);
 
         // Release milestone
-        let attestation = Bytes::from_array(&env, &[0u8; 64]);
+        let junk = Bytes::from_array(&env, &[0u8; 96]);
+        assert!(client.try_release_milestone(&milestone_id, &junk).is_err());
+        let attestation = attest_release(&env, &signing_key, &milestone_id, 500);
         client.release_milestone(&milestone_id, &attestation);
 
         // Check released milestone
@@ -268,7 +829,7 @@ mod test {
         let recipient = Address::generate(&env);
         let project_id = BytesN::from_array(&env, &[1u8; 32]);
         let milestone_id = BytesN::from_array(&env, &[2u8; 32]);
-        let attestation_key = BytesN::from_array(&env, &[3u8; 32]);
+        let (signing_key, attestation_key) = attestation_signer(&env, 3);
 
         // Create contract
         let contract_id = env.register_contract(None, MilestoneManager);
@@ -278,10 +839,11 @@ mod test {
         client.initialize(&admin, &attestation_key);
 
         // Register milestone
-        client.register_milestone(&project_id, &milestone_id, &500, &true, &recipient);
+        client.register_milestone(&project_id, &milestone_id, &500, &true, &sole_recipient(&env, &recipient));
+        submit_test_proof(&env, &client, &recipient, &milestone_id);
 
         // Release milestone
-        let attestation = Bytes::from_array(&env, &[0u8; 64]);
+        let attestation = attest_release(&env, &signing_key, &milestone_id, 500);
         client.release_milestone(&milestone_id, &attestation);
 
         // Try to release again - should panic
@@ -298,7 +860,7 @@ mod test {
         let project_id = BytesN::from_array(&env, &[1u8; 32]);
         let milestone1_id = BytesN::from_array(&env, &[2u8; 32]);
         let milestone2_id = BytesN::from_array(&env, &[3u8; 32]);
-        let attestation_key = BytesN::from_array(&env, &[4u8; 32]);
+        let (signing_key, attestation_key) = attestation_signer(&env, 4);
 
         // Create contract
         let contract_id = env.register_contract(None, MilestoneManager);
@@ -308,8 +870,10 @@ mod test {
         client.initialize(&admin, &attestation_key);
 
         // Register two milestones
-        client.register_milestone(&project_id, &milestone1_id, &300, &true, &recipient);
-        client.register_milestone(&project_id, &milestone2_id, &700, &true, &recipient);
+        client.register_milestone(&project_id, &milestone1_id, &300, &true, &sole_recipient(&env, &recipient));
+        submit_test_proof(&env, &client, &recipient, &milestone1_id);
+        client.register_milestone(&project_id, &milestone2_id, &700, &true, &sole_recipient(&env, &recipient));
+        submit_test_proof(&env, &client, &recipient, &milestone2_id);
 
         // Check project milestones
         let project_milestones = client.get_project_milestones(&project_id);
@@ -320,7 +884,7 @@ mod test {
         assert_eq!(project_info.released_amount, 0);
 
         // Release first milestone
-        let attestation = Bytes::from_array(&env, &[0u8; 64]);
+        let attestation = attest_release(&env, &signing_key, &milestone1_id, 300);
         client.release_milestone(&milestone1_id, &attestation);
 
         // Check updated project milestones
@@ -330,7 +894,9 @@ mod test {
         assert_eq!(project_info.released_milestones, 1);
         assert_eq!(project_info.released_amount, 300);
 
-        // Release second milestone
+        // Release second milestone; the first milestone's attestation doesn't cover it
+        assert!(client.try_release_milestone(&milestone2_id, &attestation).is_err());
+        let attestation = attest_release(&env, &signing_key, &milestone2_id, 700);
         client.release_milestone(&milestone2_id, &attestation);
 
         // Check final project milestones
@@ -340,4 +906,231 @@ mod test {
         assert_eq!(project_info.released_milestones, 2);
         assert_eq!(project_info.released_amount, 1000);
     }
+
+    #[test]
+    fn test_threshold_release_for_large_milestone() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let recipient = Address::generate(&env);
+        let project_id = BytesN::from_array(&env, &[1u8; 32]);
+        let milestone_id = BytesN::from_array(&env, &[2u8; 32]);
+        let (signing_key, attestation_key) = attestation_signer(&env, 3);
+
+        let contract_id = env.register_contract(None, MilestoneManager);
+        let client = MilestoneManagerClient::new(&env, &contract_id);
+        client.initialize(&admin, &attestation_key);
+
+        // 2-of-3 signer set for milestones of 1000 stroops or more
+        let keys = [
+            SigningKey::from_bytes(&[10u8; 32]),
+            SigningKey::from_bytes(&[11u8; 32]),
+            SigningKey::from_bytes(&[12u8; 32]),
+        ];
+        let signers = vec![
+            &env,
+            BytesN::from_array(&env, &keys[0].verifying_key().to_bytes()),
+            BytesN::from_array(&env, &keys[1].verifying_key().to_bytes()),
+            BytesN::from_array(&env, &keys[2].verifying_key().to_bytes()),
+        ];
+        client.rotate_signer_set(&signers, &2, &1000);
+
+        client.register_milestone(&project_id, &milestone_id, &5000, &true, &sole_recipient(&env, &recipient));
+        submit_test_proof(&env, &client, &recipient, &milestone_id);
+
+        // Single-key release is rejected for large milestones
+        let attestation = attest_release(&env, &signing_key, &milestone_id, 5000);
+        assert!(client.try_release_milestone(&milestone_id, &attestation).is_err());
+
+        // One signature is below the threshold
+        let one = vec![&env, sign_release(&env, &keys[0], &milestone_id, 5000)];
+        assert!(client.try_release_milestone_multisig(&milestone_id, &one).is_err());
+
+        // Duplicate signer doesn't count twice
+        let duplicate = vec![
+            &env,
+            sign_release(&env, &keys[0], &milestone_id, 5000),
+            sign_release(&env, &keys[0], &milestone_id, 5000),
+        ];
+        assert!(client.try_release_milestone_multisig(&milestone_id, &duplicate).is_err());
+
+        let two = vec![
+            &env,
+            sign_release(&env, &keys[0], &milestone_id, 5000),
+            sign_release(&env, &keys[2], &milestone_id, 5000),
+        ];
+        client.release_milestone_multisig(&milestone_id, &two);
+
+        let milestone_info = client.get_milestone(&milestone_id).unwrap();
+        assert_eq!(milestone_info.released, true);
+        assert_eq!(client.get_project_released_amount(&project_id), 5000);
+    }
+
+    #[test]
+    fn test_rotate_attestation_key_with_grace_period() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let old_key = BytesN::from_array(&env, &[3u8; 32]);
+        let new_key = BytesN::from_array(&env, &[4u8; 32]);
+
+        let contract_id = env.register_contract(None, MilestoneManager);
+        let client = MilestoneManagerClient::new(&env, &contract_id);
+        client.initialize(&admin, &old_key);
+
+        env.ledger().with_mut(|li| li.timestamp = 1000);
+        client.rotate_attestation_key(&new_key);
+
+        // Default grace window keeps the old key valid for a day
+        assert!(client.is_attestation_key_valid(&new_key));
+        assert!(client.is_attestation_key_valid(&old_key));
+
+        env.ledger().with_mut(|li| li.timestamp = 1000 + 86_401);
+        assert!(!client.is_attestation_key_valid(&old_key));
+
+        // Rotating to the active key is rejected
+        assert!(client.try_rotate_attestation_key(&new_key).is_err());
+    }
+
+    #[test]
+    fn test_release_splits_payout_between_recipients() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let alice = Address::generate(&env);
+        let bob = Address::generate(&env);
+        let project_id = BytesN::from_array(&env, &[1u8; 32]);
+        let milestone_id = BytesN::from_array(&env, &[2u8; 32]);
+        let (signing_key, attestation_key) = attestation_signer(&env, 3);
+
+        let contract_id = env.register_contract(None, MilestoneManager);
+        let client = MilestoneManagerClient::new(&env, &contract_id);
+        client.initialize(&admin, &attestation_key);
+
+        // Fund the contract with the payout token
+        let token_id = env.register_stellar_asset_contract(admin.clone());
+        token::StellarAssetClient::new(&env, &token_id).mint(&contract_id, &1001);
+        client.set_payout_token(&token_id);
+
+        // Shares that don't sum to 10000 are rejected
+        let bad = vec![
+            &env,
+            RecipientShare { address: alice.clone(), share_bps: 7000 },
+            RecipientShare { address: bob.clone(), share_bps: 2000 },
+        ];
+        assert!(client.try_register_milestone(&project_id, &milestone_id, &1001, &false, &bad).is_err());
+
+        let recipients = vec![
+            &env,
+            RecipientShare { address: alice.clone(), share_bps: 7000 },
+            RecipientShare { address: bob.clone(), share_bps: 3000 },
+        ];
+        client.register_milestone(&project_id, &milestone_id, &1001, &false, &recipients);
+
+        let attestation = attest_release(&env, &signing_key, &milestone_id, 1001);
+        client.release_milestone(&milestone_id, &attestation);
+
+        let token_client = token::Client::new(&env, &token_id);
+        assert_eq!(token_client.balance(&alice), 700);
+        assert_eq!(token_client.balance(&bob), 301);
+        assert_eq!(token_client.balance(&contract_id), 0);
+    }
+
+    #[test]
+    fn test_release_requires_proof() {
+        let env = Env::default();
+        env.mock_all_auths();
+
+        let admin = Address::generate(&env);
+        let recipient = Address::generate(&env);
+        let project_id = BytesN::from_array(&env, &[1u8; 32]);
+        let milestone_id = BytesN::from_array(&env, &[2u8; 32]);
+        let (signing_key, attestation_key) = attestation_signer(&env, 3);
+
+        let contract_id = env.register_contract(None, MilestoneManager);
+        let client = MilestoneManagerClient::new(&env, &contract_id);
+        client.initialize(&admin, &attestation_key);
+        client.register_milestone(&project_id, &milestone_id, &500, &true, &sole_recipient(&env, &recipient));
+
+        // No proof anchored yet
+        assert!(!client.can_release_milestone(&milestone_id));
+        let attestation = attest_release(&env, &signing_key, &milestone_id, 500);
+        assert!(client.try_release_milestone(&milestone_id, &attestation).is_err());
+
+        let proof_hash = BytesN::from_array(&env, &[7u8; 32]);
+        // Neither the lead recipient nor the admin
+        let stranger = Address::generate(&env);
+        assert!(client.try_submit_proof(&stranger, &milestone_id, &proof_hash, &String::from_str(&env, "ipfs://report")).is_err());
+
+        client.submit_proof(&admin, &milestone_id, &proof_hash, &String::from_str(&env, "ipfs://report"));
+
+        let proof = client.get_proof(&milestone_id).unwrap();
+        assert_eq!(proof.proof_hash, proof_hash);
+        assert!(client.can_release_milestone(&milestone_id));
+
+        client.release_milestone(&milestone_id, &attestation);
+        assert!(!client.can_release_milestone(&milestone_id));
+        assert!(client.try_submit_proof(&recipient, &milestone_id, &proof_hash, &String::from_str(&env, "ipfs://late")).is_err());
+    }
+
+    #[test]
+    fn test_donor_vote_gates_release() {
+        let env = Env::default();
+        env.mock_all_auths();
+        env.ledger().with_mut(|li| li.timestamp = 1000);
+
+        let admin = Address::generate(&env);
+        let recipient = Address::generate(&env);
+        let big_donor = Address::generate(&env);
+        let small_donor = Address::generate(&env);
+        let outsider = Address::generate(&env);
+        let project_id = BytesN::from_array(&env, &[1u8; 32]);
+        let approved_id = BytesN::from_array(&env, &[2u8; 32]);
+        let disputed_id = BytesN::from_array(&env, &[3u8; 32]);
+        let (signing_key, attestation_key) = attestation_signer(&env, 4);
+        let attestation = attest_release(&env, &signing_key, &approved_id, 500);
+
+        let escrow_id = env.register_contract(None, MockEscrow);
+        let escrow = MockEscrowClient::new(&env, &escrow_id);
+        escrow.set_donor_total(&project_id, &big_donor, &300);
+        escrow.set_donor_total(&project_id, &small_donor, &100);
+
+        let contract_id = env.register_contract(None, MilestoneManager);
+        let client = MilestoneManagerClient::new(&env, &contract_id);
+        client.initialize(&admin, &attestation_key);
+        client.set_escrow_contract(&escrow_id);
+        client.register_milestone(&project_id, &approved_id, &500, &false, &sole_recipient(&env, &recipient));
+        client.register_milestone(&project_id, &disputed_id, &500, &false, &sole_recipient(&env, &recipient));
+
+        client.open_vote(&approved_id, &3600);
+        client.open_vote(&disputed_id, &3600);
+
+        // Votes are weighted by escrow deposits; non-donors and repeat votes are refused
+        assert_eq!(client.cast_vote(&approved_id, &big_donor, &true), 300);
+        assert_eq!(client.cast_vote(&approved_id, &small_donor, &false), 100);
+        assert!(client.try_cast_vote(&approved_id, &big_donor, &true).is_err());
+        assert!(client.try_cast_vote(&approved_id, &outsider, &true).is_err());
+        assert_eq!(client.cast_vote(&disputed_id, &big_donor, &false), 300);
+
+        let tally = client.get_vote_tally(&approved_id).unwrap();
+        assert_eq!(tally.approve_weight, 300);
+        assert_eq!(tally.dispute_weight, 100);
+        assert_eq!(tally.voters, 2);
+
+        // Release waits for the window to close
+        assert!(!client.can_release_milestone(&approved_id));
+        assert!(client.try_release_milestone(&approved_id, &attestation).is_err());
+
+        env.ledger().with_mut(|li| li.timestamp = 1000 + 3601);
+        assert!(client.try_cast_vote(&disputed_id, &small_donor, &true).is_err());
+
+        assert!(client.can_release_milestone(&approved_id));
+        client.release_milestone(&approved_id, &attestation);
+
+        assert!(!client.can_release_milestone(&disputed_id));
+        assert!(client.try_release_milestone(&disputed_id, &attestation).is_err());
+    }
 }
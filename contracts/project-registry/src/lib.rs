@@ -1,6 +1,16 @@
 #![no_std]
 use soroban_sdk::{contract, contractimpl, contracttype, Address, Env, String, BytesN, log};
 
+/// Project lifecycle. Completed and Cancelled are terminal.
+#[contracttype]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectStatus {
+    Registered,
+    Active,
+    Completed,
+    Cancelled,
+}
+
 #[contracttype]
 #[derive(Clone)]
 pub struct ProjectInfo {
@@ -8,12 +18,15 @@ pub struct ProjectInfo {
     pub project_id: BytesN<32>,
     pub metadata_uri: String,
     pub registered_at: u64,
+    pub status: ProjectStatus,
+    pub status_updated_at: u64,
 }
 
 #[contracttype]
 pub enum DataKey {
     Project(BytesN<32>),
     ProjectCount,
+    Admin,
 }
 
 #[contract]
@@ -21,6 +34,17 @@ pub struct ProjectRegistry;
 
 #[contractimpl]
 impl ProjectRegistry {
+    /// Set the admin allowed to activate projects and override owners
+    pub fn initialize(env: Env, admin: Address) {
+        if env.storage().instance().has(&DataKey::Admin) {
+            panic!("Already initialized");
+        }
+
+        env.storage().instance().set(&DataKey::Admin, &admin);
+
+        log!(&env, "ProjectRegistry initialized with admin: {:?}", admin);
+    }
+
     /// Register a new project
     pub fn register(
         env: Env,
@@ -44,6 +68,8 @@ impl ProjectRegistry {
             project_id: project_id.clone(),
             metadata_uri,
             registered_at: env.ledger().timestamp(),
+            status: ProjectStatus::Registered,
+            status_updated_at: env.ledger().timestamp(),
         };
 
         // Store project
@@ -91,6 +117,63 @@ impl ProjectRegistry {
         Ok(())
     }
 
+    /// Move a project through its lifecycle. Only the admin can activate a
+    /// registered project; the owner or admin can complete an active project
+    /// or cancel one that is not yet finished.
+    pub fn set_status(
+        env: Env,
+        caller: Address,
+        project_id: BytesN<32>,
+        status: ProjectStatus,
+    ) -> Result<(), String> {
+        caller.require_auth();
+
+        let key = DataKey::Project(project_id.clone());
+        let mut project_info: ProjectInfo = env.storage()
+            .persistent()
+            .get(&key)
+            .ok_or(String::from_str(&env, "Project not found"))?;
+
+        let is_admin = env.storage().instance()
+            .get::<DataKey, Address>(&DataKey::Admin)
+            .map(|admin| admin == caller)
+            .unwrap_or(false);
+        let is_owner = project_info.owner == caller;
+
+        let allowed = match (project_info.status, status) {
+            (ProjectStatus::Registered, ProjectStatus::Active) => is_admin,
+            (ProjectStatus::Active, ProjectStatus::Completed) => is_admin || is_owner,
+            (ProjectStatus::Registered, ProjectStatus::Cancelled)
+            | (ProjectStatus::Active, ProjectStatus::Cancelled) => is_admin || is_owner,
+            _ => return Err(String::from_str(&env, "Invalid status transition")),
+        };
+        if !allowed {
+            return Err(String::from_str(&env, "Not authorized for this transition"));
+        }
+
+        project_info.status = status;
+        project_info.status_updated_at = env.ledger().timestamp();
+        env.storage().persistent().set(&key, &project_info);
+
+        log!(&env, "ProjectStatusChanged: {:?} -> {:?}", project_id, status);
+
+        Ok(())
+    }
+
+    /// Get a project's lifecycle status
+    pub fn get_status(env: Env, project_id: BytesN<32>) -> Option<ProjectStatus> {
+        Self::get_project(env, project_id).map(|info| info.status)
+    }
+
+    /// Whether the escrow should take deposits for a project: only registered
+    /// or active projects do
+    pub fn accepts_deposits(env: Env, project_id: BytesN<32>) -> bool {
+        matches!(
+            Self::get_status(env, project_id),
+            Some(ProjectStatus::Registered) | Some(ProjectStatus::Active)
+        )
+    }
+
     /// Get total project count
     pub fn get_project_count(env: Env) -> u32 {
         let count_key = DataKey::ProjectCount;
@@ -145,5 +228,42 @@ mod test {
         // Try to register again - should panic
         client.register(&owner, &project_id, &metadata_uri);
     }
-}
 
+    #[test]
+    fn test_status_transitions() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let contract_id = env.register_contract(None, ProjectRegistry);
+        let client = ProjectRegistryClient::new(&env, &contract_id);
+
+        let admin = Address::generate(&env);
+        let owner = Address::generate(&env);
+        let stranger = Address::generate(&env);
+        let project_id = BytesN::from_array(&env, &[1u8; 32]);
+        let other_id = BytesN::from_array(&env, &[2u8; 32]);
+        let metadata_uri = String::from_str(&env, "ipfs://QmTest123");
+
+        client.initialize(&admin);
+        client.register(&owner, &project_id, &metadata_uri);
+        client.register(&owner, &other_id, &metadata_uri);
+        assert_eq!(client.get_status(&project_id), Some(ProjectStatus::Registered));
+        assert!(client.accepts_deposits(&project_id));
+
+        // Only the admin can activate
+        assert!(client.try_set_status(&owner, &project_id, &ProjectStatus::Active).is_err());
+        client.set_status(&admin, &project_id, &ProjectStatus::Active);
+
+        // Strangers can't complete; the owner can, and it's terminal
+        assert!(client.try_set_status(&stranger, &project_id, &ProjectStatus::Completed).is_err());
+        client.set_status(&owner, &project_id, &ProjectStatus::Completed);
+        assert!(!client.accepts_deposits(&project_id));
+        assert!(client.try_set_status(&admin, &project_id, &ProjectStatus::Cancelled).is_err());
+
+        // Registered projects can't skip straight to completed, but can be cancelled
+        assert!(client.try_set_status(&owner, &other_id, &ProjectStatus::Completed).is_err());
+        client.set_status(&owner, &other_id, &ProjectStatus::Cancelled);
+        assert_eq!(client.get_status(&other_id), Some(ProjectStatus::Cancelled));
+        assert!(!client.accepts_deposits(&other_id));
+        assert!(!client.accepts_deposits(&BytesN::from_array(&env, &[3u8; 32])));
+    }
+}
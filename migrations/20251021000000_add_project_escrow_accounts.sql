@@ -0,0 +1,22 @@
+-- Per-project escrow accounts
+-- In per-project escrow mode every published project gets a dedicated Stellar
+-- account whose address is stored in projects.contract_address.
+
+CREATE TABLE IF NOT EXISTS project_escrow_accounts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL UNIQUE REFERENCES projects(id) ON DELETE CASCADE,
+    public_key VARCHAR(255) NOT NULL UNIQUE,
+    secret_key TEXT NOT NULL, -- restrict access; only workers should read this
+    funding_tx_hash VARCHAR(255),
+    last_balance DECIMAL(20,7),
+    expected_balance DECIMAL(20,7),
+    last_reconciled_at TIMESTAMP WITH TIME ZONE,
+    swept_at TIMESTAMP WITH TIME ZONE,
+    sweep_tx_hash VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_project_escrow_accounts_project_id ON project_escrow_accounts(project_id);
+CREATE INDEX IF NOT EXISTS idx_project_escrow_accounts_public_key ON project_escrow_accounts(public_key);
+
+COMMENT ON TABLE project_escrow_accounts IS 'Dedicated Stellar escrow accounts created for projects on publish';
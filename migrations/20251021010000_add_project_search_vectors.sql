@@ -0,0 +1,12 @@
+-- Full-text search vectors for projects
+-- Rebuilt by the ops re-index endpoint after bulk edits or dictionary changes.
+
+ALTER TABLE projects
+    ADD COLUMN IF NOT EXISTS search_vector TSVECTOR;
+
+UPDATE projects
+SET search_vector = setweight(to_tsvector('english', COALESCE(title, '')), 'A')
+    || setweight(to_tsvector('english', COALESCE(description, '')), 'B')
+    || setweight(to_tsvector('english', array_to_string(tags, ' ')), 'C');
+
+CREATE INDEX IF NOT EXISTS idx_projects_search_vector ON projects USING GIN(search_vector);
@@ -0,0 +1,10 @@
+-- Milestone recipient splits
+-- A milestone can pay several team members; each entry is {"address", "share_bps"}
+-- and shares sum to 10000. recipient_address keeps the first recipient for older readers.
+
+ALTER TABLE contract_milestones
+    ADD COLUMN IF NOT EXISTS recipient_splits JSONB;
+
+UPDATE contract_milestones
+SET recipient_splits = jsonb_build_array(jsonb_build_object('address', recipient_address, 'share_bps', 10000))
+WHERE recipient_splits IS NULL AND recipient_address IS NOT NULL;
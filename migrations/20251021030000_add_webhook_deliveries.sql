@@ -0,0 +1,23 @@
+-- Webhook delivery log
+-- Records inbound payment provider callbacks and outbound webhook calls with
+-- their request/response bodies so failed deliveries can be inspected and replayed.
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    direction VARCHAR(20) NOT NULL, -- 'inbound' (provider callback) or 'outbound'
+    provider VARCHAR(100) NOT NULL, -- payment provider or subscriber name
+    event_type VARCHAR(255),
+    target_url TEXT, -- outbound only
+    request_headers JSONB,
+    request_body TEXT NOT NULL,
+    response_status INTEGER,
+    response_body TEXT,
+    status VARCHAR(20) NOT NULL, -- 'succeeded' or 'failed'
+    error TEXT,
+    replay_of UUID REFERENCES webhook_deliveries(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_status ON webhook_deliveries(status);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_provider ON webhook_deliveries(provider);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_created_at ON webhook_deliveries(created_at);
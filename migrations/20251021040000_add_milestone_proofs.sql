@@ -0,0 +1,8 @@
+-- Milestone proof anchoring
+-- Students anchor a SHA-256 hash of their deliverable before a milestone with
+-- proof_required can be released.
+
+ALTER TABLE contract_milestones
+    ADD COLUMN IF NOT EXISTS proof_hash VARCHAR(64),
+    ADD COLUMN IF NOT EXISTS proof_uri TEXT,
+    ADD COLUMN IF NOT EXISTS proof_submitted_at TIMESTAMPTZ;
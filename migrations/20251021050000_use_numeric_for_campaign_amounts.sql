@@ -0,0 +1,8 @@
+-- Store campaign amounts as fixed-point XLM (7 decimal places) instead of floats
+-- so reward pools and distributions add up to the stroop.
+
+ALTER TABLE campaigns
+    ALTER COLUMN reward_pool_xlm TYPE NUMERIC(20, 7) USING ROUND(reward_pool_xlm::NUMERIC, 7);
+
+ALTER TABLE campaign_distributions
+    ALTER COLUMN amount TYPE NUMERIC(20, 7) USING ROUND(amount::NUMERIC, 7);
@@ -0,0 +1,35 @@
+-- Campaign matching pools
+-- A sponsor locks funds in the matching-pool contract; verified escrow deposits
+-- are matched at ratio_bps (10000 = 1:1) until cap_stroops or the locked funds run out.
+
+CREATE TABLE IF NOT EXISTS campaign_matching_pools (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    campaign_id UUID NOT NULL UNIQUE REFERENCES campaigns(id) ON DELETE CASCADE,
+    sponsor_address VARCHAR(255) NOT NULL,
+    ratio_bps INTEGER NOT NULL CHECK (ratio_bps > 0),
+    cap_stroops BIGINT NOT NULL CHECK (cap_stroops > 0),
+    locked_stroops BIGINT NOT NULL CHECK (locked_stroops > 0),
+    matched_stroops BIGINT NOT NULL DEFAULT 0,
+    status VARCHAR(20) NOT NULL DEFAULT 'active', -- active, closed
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    closed_at TIMESTAMP WITH TIME ZONE
+);
+
+-- One row per matched deposit; a deposit is only ever matched once
+CREATE TABLE IF NOT EXISTS contract_matches (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    campaign_id UUID NOT NULL REFERENCES campaign_matching_pools(campaign_id) ON DELETE CASCADE,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    deposit_tx_hash VARCHAR(255) NOT NULL UNIQUE,
+    deposit_stroops BIGINT NOT NULL,
+    matched_stroops BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_campaign_matching_pools_status ON campaign_matching_pools(status);
+CREATE INDEX IF NOT EXISTS idx_contract_matches_campaign_id ON contract_matches(campaign_id);
+CREATE INDEX IF NOT EXISTS idx_contract_matches_project_id ON contract_matches(project_id);
+
+INSERT INTO contracts (name, address, network) VALUES
+    ('matching_pool', 'PLACEHOLDER_MATCHING_POOL_ADDRESS', 'testnet')
+ON CONFLICT (name) DO NOTHING;
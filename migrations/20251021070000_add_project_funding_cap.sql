@@ -0,0 +1,5 @@
+-- Optional hard limit on how much a project's escrow will accept (XLM).
+-- NULL means the project is uncapped.
+
+ALTER TABLE projects ADD COLUMN funding_cap NUMERIC(20, 7)
+    CHECK (funding_cap IS NULL OR funding_cap > 0);
@@ -0,0 +1,20 @@
+-- Donor governance: milestones put to a vote cannot be released until the
+-- window closes with approvals (weighted by escrow deposits) at least matching disputes.
+
+ALTER TABLE contract_milestones
+    ADD COLUMN vote_opens_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN vote_closes_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE IF NOT EXISTS contract_milestone_votes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    milestone_id VARCHAR(255) NOT NULL, -- On-chain milestone ID
+    voter_address VARCHAR(255) NOT NULL, -- Stellar address
+    approve BOOLEAN NOT NULL,
+    weight_stroops BIGINT NOT NULL CHECK (weight_stroops > 0),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (project_id, milestone_id, voter_address)
+);
+
+CREATE INDEX IF NOT EXISTS idx_contract_milestone_votes_milestone
+    ON contract_milestone_votes(project_id, milestone_id);
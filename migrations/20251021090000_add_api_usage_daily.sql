@@ -0,0 +1,14 @@
+-- Per-user API usage, flushed from the in-memory recorder by the analytics worker
+
+CREATE TABLE IF NOT EXISTS api_usage_daily (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    endpoint VARCHAR(255) NOT NULL, -- "METHOD /matched/route"
+    requests BIGINT NOT NULL DEFAULT 0,
+    errors BIGINT NOT NULL DEFAULT 0,
+    total_latency_ms BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, day, endpoint)
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_usage_daily_day ON api_usage_daily(day);
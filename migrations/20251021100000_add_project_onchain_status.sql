@@ -0,0 +1,15 @@
+-- Lifecycle status mirrored from the project registry contract, which is the
+-- source of truth. `status` stays as the review/visibility workflow column.
+
+ALTER TABLE projects ADD COLUMN onchain_status VARCHAR(20) NOT NULL DEFAULT 'registered'
+    CHECK (onchain_status IN ('registered', 'active', 'completed', 'cancelled'));
+
+UPDATE projects SET onchain_status = CASE status
+    WHEN 'active' THEN 'active'
+    WHEN 'paused' THEN 'active'
+    WHEN 'completed' THEN 'completed'
+    WHEN 'rejected' THEN 'cancelled'
+    ELSE 'registered'
+END;
+
+ALTER TABLE projects ADD COLUMN onchain_status_updated_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,17 @@
+-- Admin-scheduled featuring slots. Live slots rotate on /api/projects/featured,
+-- least recently shown first.
+
+CREATE TABLE IF NOT EXISTS project_features (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    starts_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    ends_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    impressions BIGINT NOT NULL DEFAULT 0,
+    last_shown_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CHECK (ends_at > starts_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_project_features_window ON project_features(starts_at, ends_at);
+CREATE INDEX IF NOT EXISTS idx_project_features_project_id ON project_features(project_id);
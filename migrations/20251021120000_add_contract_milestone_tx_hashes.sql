@@ -0,0 +1,5 @@
+-- Hashes of the Soroban transactions that registered and released each milestone
+
+ALTER TABLE contract_milestones
+    ADD COLUMN register_tx_hash VARCHAR(64),
+    ADD COLUMN release_tx_hash VARCHAR(64);
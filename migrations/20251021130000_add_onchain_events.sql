@@ -0,0 +1,29 @@
+-- Contract events indexed from soroban-rpc getEvents
+
+CREATE TABLE IF NOT EXISTS onchain_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    event_id VARCHAR(64) NOT NULL UNIQUE, -- soroban-rpc event id
+    contract_name VARCHAR(255) NOT NULL,
+    contract_address VARCHAR(255) NOT NULL,
+    event_type VARCHAR(50) NOT NULL,
+    ledger BIGINT NOT NULL,
+    ledger_closed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    tx_hash VARCHAR(64),
+    project_id UUID REFERENCES projects(id) ON DELETE SET NULL,
+    milestone_id VARCHAR(255),
+    address VARCHAR(255),
+    amount_stroops BIGINT,
+    topics TEXT[] NOT NULL,
+    value TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_onchain_events_project_id ON onchain_events(project_id);
+CREATE INDEX IF NOT EXISTS idx_onchain_events_type_ledger ON onchain_events(event_type, ledger);
+
+-- Resume points for streaming consumers
+CREATE TABLE IF NOT EXISTS indexer_cursors (
+    name VARCHAR(255) PRIMARY KEY,
+    cursor TEXT NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,5 @@
+-- Donation memos are matched exactly against incoming payments, so each one
+-- must identify a single donation
+
+DROP INDEX IF EXISTS idx_donations_memo;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_donations_memo_unique ON donations(memo) WHERE memo IS NOT NULL;
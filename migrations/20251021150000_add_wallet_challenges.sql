@@ -0,0 +1,15 @@
+-- SEP-10 challenges issued to prove wallet ownership
+CREATE TABLE IF NOT EXISTS wallet_challenges (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    public_key VARCHAR(56) NOT NULL,
+    nonce VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_wallet_challenges_user_id ON wallet_challenges(user_id);
+
+-- Wallets stay unverified until their owner signs a challenge
+ALTER TABLE wallets ALTER COLUMN status SET DEFAULT 'unverified';
@@ -0,0 +1,17 @@
+-- Claimable balances created for students whose wallet can't receive a direct payment
+CREATE TABLE IF NOT EXISTS claimable_balances (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    balance_id VARCHAR(72) NOT NULL UNIQUE,
+    student_id UUID NOT NULL REFERENCES students(id),
+    claimant VARCHAR(56) NOT NULL,
+    amount NUMERIC(20, 7) NOT NULL,
+    source_type VARCHAR(50) NOT NULL,
+    source_id UUID,
+    tx_hash VARCHAR(64) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'unclaimed',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    settled_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_claimable_balances_student_id ON claimable_balances(student_id);
+CREATE INDEX IF NOT EXISTS idx_claimable_balances_claimant ON claimable_balances(claimant);
@@ -0,0 +1,2 @@
+-- Muxed (M...) addresses are 69 characters
+ALTER TABLE wallet_challenges ALTER COLUMN public_key TYPE VARCHAR(69);
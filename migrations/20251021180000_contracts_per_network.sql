@@ -0,0 +1,5 @@
+-- Contract addresses are tracked per Stellar network
+UPDATE contracts SET network = 'mainnet' WHERE network IN ('public', 'pubnet');
+
+ALTER TABLE contracts DROP CONSTRAINT IF EXISTS contracts_name_key;
+ALTER TABLE contracts ADD CONSTRAINT contracts_name_network_key UNIQUE (name, network);
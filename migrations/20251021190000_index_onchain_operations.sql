@@ -0,0 +1,11 @@
+-- Index onchain_transactions per operation
+-- A transaction can carry several operations touching indexed wallets, so rows
+-- are keyed by Horizon operation id rather than transaction hash.
+
+ALTER TABLE onchain_transactions ADD COLUMN IF NOT EXISTS operation_id BIGINT;
+ALTER TABLE onchain_transactions ADD COLUMN IF NOT EXISTS asset VARCHAR(255);
+
+ALTER TABLE onchain_transactions DROP CONSTRAINT IF EXISTS onchain_transactions_tx_hash_key;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_onchain_operation_id ON onchain_transactions(operation_id);
+CREATE INDEX IF NOT EXISTS idx_onchain_source ON onchain_transactions(source_account);
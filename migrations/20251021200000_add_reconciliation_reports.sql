@@ -0,0 +1,19 @@
+-- Escrow reconciliation: database totals against the funding escrow contract
+
+CREATE TABLE IF NOT EXISTS reconciliation_reports (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    onchain_balance_stroops BIGINT NOT NULL,
+    deposits_stroops BIGINT NOT NULL,
+    releases_stroops BIGINT NOT NULL,
+    donations_stroops BIGINT NOT NULL,
+    -- on-chain balance minus (deposits - releases)
+    contract_drift_stroops BIGINT NOT NULL,
+    -- confirmed donations minus recorded deposits
+    donation_drift_stroops BIGINT NOT NULL,
+    exceeds_threshold BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_reconciliation_reports_project_created ON reconciliation_reports(project_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_reconciliation_reports_exceeds ON reconciliation_reports(exceeds_threshold) WHERE exceeds_threshold;
@@ -0,0 +1,29 @@
+-- Mobile money (M-Pesa B2C) payouts for milestone releases
+-- Requested from the milestone release handler, sent once an admin approves,
+-- and settled by the provider's result callback.
+
+CREATE TABLE IF NOT EXISTS mobile_payouts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    provider VARCHAR(50) NOT NULL DEFAULT 'mpesa',
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    milestone_id UUID NOT NULL REFERENCES milestones(id) ON DELETE CASCADE,
+    student_id UUID NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    phone_number VARCHAR(20) NOT NULL,
+    amount_xlm DECIMAL(20, 7) NOT NULL,
+    amount_kes DECIMAL(14, 2) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending_approval'
+        CHECK (status IN ('pending_approval', 'rejected', 'submitted', 'completed', 'failed')),
+    approved_by UUID REFERENCES users(id),
+    approved_at TIMESTAMP WITH TIME ZONE,
+    conversation_id VARCHAR(255),
+    receipt VARCHAR(255),
+    result_description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_mobile_payouts_status ON mobile_payouts(status);
+CREATE INDEX IF NOT EXISTS idx_mobile_payouts_conversation ON mobile_payouts(conversation_id);
+-- One live payout per milestone
+CREATE UNIQUE INDEX IF NOT EXISTS idx_mobile_payouts_active_milestone
+    ON mobile_payouts(milestone_id) WHERE status IN ('pending_approval', 'submitted', 'completed');
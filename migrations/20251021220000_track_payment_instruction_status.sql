@@ -0,0 +1,14 @@
+-- Track provider payment instructions until they settle, so the payment
+-- reconciler can query the provider for pushes whose callback never arrived
+ALTER TABLE payment_instructions
+    ADD COLUMN IF NOT EXISTS status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    ADD COLUMN IF NOT EXISTS status_checked_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS status_checks INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS resolved_at TIMESTAMP WITH TIME ZONE;
+
+-- Instructions from before this migration have either settled or gone stale
+UPDATE payment_instructions SET status = 'expired', resolved_at = NOW() WHERE expires_at < NOW();
+
+CREATE INDEX IF NOT EXISTS idx_payment_instructions_pending
+    ON payment_instructions(payment_method, created_at)
+    WHERE status = 'pending';
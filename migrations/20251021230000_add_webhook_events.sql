@@ -0,0 +1,11 @@
+-- Provider event ids that have been processed, so redelivered webhooks are
+-- acknowledged without being applied twice
+CREATE TABLE IF NOT EXISTS webhook_events (
+    provider VARCHAR(50) NOT NULL,
+    event_id VARCHAR(255) NOT NULL,
+    event_type VARCHAR(100),
+    processed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (provider, event_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_events_processed_at ON webhook_events(processed_at);
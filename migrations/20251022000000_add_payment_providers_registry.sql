@@ -0,0 +1,13 @@
+-- Operator-managed payment provider settings. A row overrides the provider's
+-- environment variables key by key; providers without a row keep using the
+-- environment. Disabling a row switches the provider off without a redeploy.
+CREATE TABLE IF NOT EXISTS payment_providers (
+    name VARCHAR(50) PRIMARY KEY,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    config JSONB NOT NULL DEFAULT '{}'::jsonb,
+    currencies TEXT[] NOT NULL DEFAULT '{}',
+    countries TEXT[] NOT NULL DEFAULT '{}',
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
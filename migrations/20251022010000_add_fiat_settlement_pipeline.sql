@@ -0,0 +1,23 @@
+-- Settle confirmed fiat payments on-chain. Instructions remember what was
+-- charged and for which project, and each settlement records the rate used,
+-- where the XLM went, and the donation it produced.
+ALTER TABLE payment_instructions
+    ADD COLUMN IF NOT EXISTS project_id UUID REFERENCES projects(id),
+    ADD COLUMN IF NOT EXISTS amount DECIMAL(20,8),
+    ADD COLUMN IF NOT EXISTS currency VARCHAR(10);
+
+-- The XLM amount and rate are only known once the settlement runs
+ALTER TABLE fiat_settlements
+    ALTER COLUMN xlm_amount DROP NOT NULL,
+    ALTER COLUMN exchange_rate DROP NOT NULL,
+    ADD COLUMN IF NOT EXISTS project_id UUID REFERENCES projects(id),
+    ADD COLUMN IF NOT EXISTS rate_source VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS destination VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS donation_id UUID REFERENCES donations(id),
+    ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS last_error TEXT,
+    ADD COLUMN IF NOT EXISTS settled_at TIMESTAMP WITH TIME ZONE;
+
+-- One settlement per provider payment, however many webhooks confirm it
+CREATE UNIQUE INDEX IF NOT EXISTS idx_fiat_settlements_provider_payment
+    ON fiat_settlements(provider, payment_id);
@@ -0,0 +1,17 @@
+-- Platform fee taken from each confirmed donation, so projects can be shown
+-- gross and net funding and admins can report fee income
+CREATE TABLE IF NOT EXISTS donation_fees (
+    donation_id UUID PRIMARY KEY REFERENCES donations(id) ON DELETE CASCADE,
+    project_id UUID REFERENCES projects(id),
+    payment_method VARCHAR(50) NOT NULL,
+    gross_amount DECIMAL(20,8) NOT NULL,
+    fee_amount DECIMAL(20,8) NOT NULL,
+    net_amount DECIMAL(20,8) NOT NULL,
+    -- The rule in force when the fee was taken
+    fee_bps INTEGER NOT NULL,
+    fixed_fee DECIMAL(20,8) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_donation_fees_project ON donation_fees(project_id);
+CREATE INDEX IF NOT EXISTS idx_donation_fees_created_at ON donation_fees(created_at);
@@ -0,0 +1,31 @@
+-- Refunds go through the originating provider and move through
+-- pending -> submitted -> succeeded | failed. Instructions keep the donor's
+-- email for refund notices and the provider's reference for the charge,
+-- which some refund APIs need instead of our payment id.
+ALTER TABLE payment_instructions
+    ADD COLUMN IF NOT EXISTS donor_email VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS provider_reference VARCHAR(255);
+
+-- The provider's refund id is only known once the refund is submitted
+ALTER TABLE refunds
+    ALTER COLUMN refund_id DROP NOT NULL,
+    ADD COLUMN IF NOT EXISTS provider VARCHAR(50),
+    ADD COLUMN IF NOT EXISTS currency VARCHAR(10),
+    ADD COLUMN IF NOT EXISTS project_id UUID REFERENCES projects(id),
+    ADD COLUMN IF NOT EXISTS donation_id UUID REFERENCES donations(id),
+    ADD COLUMN IF NOT EXISTS xlm_amount DECIMAL(20,7),
+    ADD COLUMN IF NOT EXISTS failure_reason TEXT,
+    ADD COLUMN IF NOT EXISTS requested_by UUID REFERENCES users(id),
+    ADD COLUMN IF NOT EXISTS submitted_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP;
+
+-- Earlier refunds were only recorded after the provider accepted them
+UPDATE refunds SET status = 'submitted' WHERE status IS NULL OR status = 'pending';
+
+ALTER TABLE refunds
+    ALTER COLUMN status SET NOT NULL,
+    ADD CONSTRAINT refunds_status_check
+        CHECK (status IN ('pending', 'submitted', 'succeeded', 'failed'));
+
+CREATE INDEX IF NOT EXISTS idx_refunds_payment_id ON refunds(payment_id);
+CREATE INDEX IF NOT EXISTS idx_refunds_status ON refunds(status);
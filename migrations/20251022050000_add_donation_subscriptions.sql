@@ -0,0 +1,56 @@
+-- Monthly recurring gifts. Card subscriptions save a Stripe payment method
+-- through a setup session and are charged off-session; Stellar subscriptions
+-- get a payment reminder with a fresh memo each month.
+CREATE TABLE IF NOT EXISTS donation_subscriptions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    donor_id UUID NOT NULL REFERENCES users(id),
+    project_id UUID NOT NULL REFERENCES projects(id),
+    -- Fiat amount in `currency` for card subscriptions, XLM for Stellar
+    amount DECIMAL(20,7) NOT NULL CHECK (amount > 0),
+    currency VARCHAR(10) NOT NULL,
+    payment_method VARCHAR(20) NOT NULL CHECK (payment_method IN ('stripe', 'stellar')),
+    status VARCHAR(20) NOT NULL DEFAULT 'pending_setup'
+        CHECK (status IN ('pending_setup', 'active', 'past_due', 'cancelled')),
+    -- Day of the month gifts fall due, clamped to shorter months
+    billing_day INTEGER NOT NULL CHECK (billing_day BETWEEN 1 AND 31),
+    -- Billing period the next charge is for; retries keep it while
+    -- `next_charge_at` moves on a day at a time
+    current_period DATE,
+    next_charge_at TIMESTAMP WITH TIME ZONE,
+    last_charged_at TIMESTAMP WITH TIME ZONE,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    donor_email VARCHAR(255),
+    setup_session_id VARCHAR(255),
+    stripe_customer_id VARCHAR(255),
+    stripe_payment_method_id VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    cancelled_at TIMESTAMP WITH TIME ZONE
+);
+
+-- One charge per subscription and billing period, however often it is retried
+CREATE TABLE IF NOT EXISTS subscription_charges (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    subscription_id UUID NOT NULL REFERENCES donation_subscriptions(id),
+    period DATE NOT NULL,
+    amount DECIMAL(20,7) NOT NULL,
+    currency VARCHAR(10) NOT NULL,
+    -- `charging`, `charged`, `reminded` or `failed`
+    status VARCHAR(20) NOT NULL DEFAULT 'charging',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- Stripe PaymentIntent for card charges
+    payment_id VARCHAR(255),
+    -- Pending memo donation for Stellar reminders
+    donation_id UUID REFERENCES donations(id),
+    last_error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (subscription_id, period)
+);
+
+CREATE INDEX IF NOT EXISTS idx_donation_subscriptions_donor ON donation_subscriptions(donor_id);
+CREATE INDEX IF NOT EXISTS idx_donation_subscriptions_project ON donation_subscriptions(project_id);
+CREATE INDEX IF NOT EXISTS idx_donation_subscriptions_due
+    ON donation_subscriptions(next_charge_at)
+    WHERE status IN ('active', 'past_due');
+CREATE INDEX IF NOT EXISTS idx_subscription_charges_payment_id ON subscription_charges(payment_id);
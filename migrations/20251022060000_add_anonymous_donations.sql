@@ -0,0 +1,4 @@
+-- Donors can hide their identity from project owners and public leaderboards;
+-- admins still see who gave
+ALTER TABLE donations
+    ADD COLUMN IF NOT EXISTS is_anonymous BOOLEAN NOT NULL DEFAULT FALSE;
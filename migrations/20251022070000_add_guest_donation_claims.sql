@@ -0,0 +1,10 @@
+-- Guest donations can be claimed by the account that later registers the
+-- same email; verified ones are copied into (or linked to) donations so they
+-- show up in the donor's history and stats
+ALTER TABLE guest_donations
+    ADD COLUMN IF NOT EXISTS claimed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS claimed_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS donation_id UUID REFERENCES donations(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_guest_donations_unclaimed_email
+    ON guest_donations (LOWER(TRIM(guest_email))) WHERE claimed_by IS NULL;
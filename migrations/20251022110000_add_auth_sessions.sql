@@ -0,0 +1,25 @@
+-- Device sessions: each login starts a session whose refresh tokens rotate
+-- on every use. Presenting a rotated token again revokes the session.
+CREATE TABLE IF NOT EXISTS auth_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_agent VARCHAR(255),
+    ip_address VARCHAR(64),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ,
+    revoked_reason VARCHAR(50)
+);
+
+CREATE INDEX IF NOT EXISTS idx_auth_sessions_user_active
+    ON auth_sessions(user_id) WHERE revoked_at IS NULL;
+
+-- Existing tokens were stored as salted Argon2 hashes and can never be
+-- looked up, so they are dropped; users sign in again
+DELETE FROM refresh_tokens;
+
+ALTER TABLE refresh_tokens
+    ADD COLUMN session_id UUID NOT NULL REFERENCES auth_sessions(id) ON DELETE CASCADE,
+    ADD COLUMN used_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_session_id ON refresh_tokens(session_id);
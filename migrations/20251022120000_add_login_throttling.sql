@@ -0,0 +1,16 @@
+-- Login attempts, for per-account and per-IP backoff and lockouts
+CREATE TABLE IF NOT EXISTS login_attempts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    -- Normalized email as submitted, whether or not an account has it
+    email VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(64),
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    succeeded BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_login_attempts_email_created ON login_attempts(email, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_login_attempts_ip_created ON login_attempts(ip_address, created_at DESC)
+    WHERE ip_address IS NOT NULL;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ;
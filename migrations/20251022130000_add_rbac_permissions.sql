@@ -0,0 +1,48 @@
+-- Fine-grained permissions. A user's permissions come from their primary
+-- role (users.role) plus any roles assigned to them here. Admins hold every
+-- permission implicitly, so they aren't listed.
+CREATE TABLE IF NOT EXISTS roles (
+    name VARCHAR(50) PRIMARY KEY,
+    description TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS permissions (
+    key VARCHAR(100) PRIMARY KEY,
+    description TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role VARCHAR(50) NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    permission VARCHAR(100) NOT NULL REFERENCES permissions(key) ON DELETE CASCADE,
+    PRIMARY KEY (role, permission)
+);
+
+CREATE TABLE IF NOT EXISTS user_role_assignments (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(50) NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    granted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, role)
+);
+
+INSERT INTO roles (name, description) VALUES
+    ('admin', 'Full platform administration'),
+    ('user', 'Donor account'),
+    ('student', 'Student account'),
+    ('moderator', 'Reviews and publishes projects'),
+    ('finance', 'Runs campaign payouts and donor refunds')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO permissions (key, description) VALUES
+    ('projects.publish', 'Publish or reject submitted projects'),
+    ('campaigns.execute', 'Trigger campaign fund distribution'),
+    ('payments.refund', 'Issue donor refunds'),
+    ('roles.manage', 'Assign and revoke user roles')
+ON CONFLICT (key) DO NOTHING;
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('moderator', 'projects.publish'),
+    ('finance', 'campaigns.execute'),
+    ('finance', 'payments.refund')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,26 @@
+-- Staff sub-roles. users.role may now be 'moderator' (verifications and
+-- projects, no funds) or 'finance' (payouts, refunds and the ledger, no
+-- moderation). Existing admins keep the admin role and full access.
+UPDATE roles SET description = 'Reviews student verifications and projects; no access to funds'
+WHERE name = 'moderator';
+UPDATE roles SET description = 'Runs payouts, refunds and campaign distribution; no moderation'
+WHERE name = 'finance';
+
+-- Donors already assigned a staff role get it as their primary role so the
+-- admin route groups let them in. Moderator wins if they hold both.
+UPDATE users u
+SET role = a.role
+FROM (
+    SELECT DISTINCT ON (user_id) user_id, role
+    FROM user_role_assignments
+    WHERE role IN ('moderator', 'finance')
+    ORDER BY user_id, role DESC
+) a
+WHERE u.id = a.user_id AND u.role = 'user';
+
+DELETE FROM user_role_assignments a
+USING users u
+WHERE a.user_id = u.id AND a.role = u.role;
+
+CREATE INDEX IF NOT EXISTS idx_users_staff_role ON users(role)
+WHERE role IN ('admin', 'moderator', 'finance');
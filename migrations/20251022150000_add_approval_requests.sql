@@ -0,0 +1,22 @@
+-- Dual control for fund-moving admin actions. One admin initiates, another
+-- approves, and the action is then run once with the approval's id. The
+-- payload is what was approved; the action must be repeated with it exactly.
+CREATE TABLE IF NOT EXISTS approval_requests (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    action VARCHAR(50) NOT NULL,
+    payload JSONB NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'approved', 'rejected', 'expired', 'executed')),
+    initiated_by UUID NOT NULL REFERENCES users(id),
+    decided_by UUID REFERENCES users(id),
+    decision_note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    decided_at TIMESTAMPTZ,
+    executed_at TIMESTAMPTZ,
+    CHECK (decided_by IS NULL OR decided_by <> initiated_by)
+);
+
+CREATE INDEX IF NOT EXISTS idx_approval_requests_status ON approval_requests(status, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_approval_requests_open ON approval_requests(expires_at)
+WHERE status IN ('pending', 'approved');
@@ -0,0 +1,7 @@
+-- Cursor pagination walks lists by (created_at, id), newest first
+CREATE INDEX IF NOT EXISTS idx_projects_created_at_id ON projects(created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_projects_student_created_at ON projects(student_id, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_donations_project_created_at ON donations(project_id, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_donations_donor_created_at ON donations(donor_id, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_activity_logs_created_at_id ON activity_logs(created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_notifications_user_created_at ON notifications(user_id, created_at DESC, id DESC);
@@ -0,0 +1,42 @@
+-- Managed project categories. Free-form tags stay on projects.tags (now
+-- normalized); categories are the browsable taxonomy admins curate.
+CREATE TABLE IF NOT EXISTS categories (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    slug VARCHAR(50) NOT NULL UNIQUE,
+    name VARCHAR(100) NOT NULL,
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS project_categories (
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (project_id, category_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_project_categories_category ON project_categories(category_id);
+
+INSERT INTO categories (slug, name, description) VALUES
+    ('technology', 'Technology', 'Software, hardware and engineering projects'),
+    ('education', 'Education', 'Tuition, learning materials and teaching initiatives'),
+    ('health', 'Health', 'Medical research and community health'),
+    ('environment', 'Environment', 'Climate, conservation and clean energy'),
+    ('arts', 'Arts & Culture', 'Creative work, media and heritage'),
+    ('community', 'Community', 'Local development and social impact'),
+    ('research', 'Research', 'Academic and scientific research')
+ON CONFLICT (slug) DO NOTHING;
+
+-- Tidy existing free-form tags the way new ones are normalized: lowercase,
+-- hyphenated, at most 32 characters, no duplicates, at most 10 per project
+UPDATE projects
+SET tags = ARRAY(
+    SELECT t
+    FROM unnest(tags) WITH ORDINALITY AS u(raw, pos),
+         LATERAL (SELECT trim(both '-' from left(regexp_replace(lower(trim(raw)), '[^a-z0-9]+', '-', 'g'), 32)) AS t) n
+    WHERE t <> ''
+    GROUP BY t
+    ORDER BY MIN(pos)
+    LIMIT 10
+)
+WHERE tags IS NOT NULL AND cardinality(tags) > 0;
@@ -0,0 +1,18 @@
+-- Comments and questions on projects. Replies hang off a top-level comment;
+-- replying to a reply files it under the same top-level comment, so
+-- threads are one level deep. Deletes are soft so a removed question
+-- keeps its answers.
+CREATE TABLE IF NOT EXISTS project_comments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    parent_id UUID REFERENCES project_comments(id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP WITH TIME ZONE,
+    deleted_by UUID REFERENCES users(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_project_comments_threads
+    ON project_comments(project_id, created_at DESC, id DESC) WHERE parent_id IS NULL;
+CREATE INDEX IF NOT EXISTS idx_project_comments_parent ON project_comments(parent_id, created_at);
@@ -0,0 +1,30 @@
+-- Progress posts students write on their projects, optionally about a
+-- milestone. Drafts have no published_at and are visible only to the owner.
+-- The table already exists with title and content; updates written before
+-- drafts existed count as published by the project's owner.
+ALTER TABLE project_updates RENAME COLUMN content TO body;
+
+ALTER TABLE project_updates
+    ADD COLUMN IF NOT EXISTS author_id UUID REFERENCES users(id),
+    ADD COLUMN IF NOT EXISTS milestone_id UUID REFERENCES project_milestones(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS media_urls TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS published_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+UPDATE project_updates pu
+SET author_id = s.user_id
+FROM projects p
+JOIN students s ON s.id = p.student_id
+WHERE p.id = pu.project_id AND pu.author_id IS NULL;
+
+UPDATE project_updates
+SET created_at = COALESCE(created_at, CURRENT_TIMESTAMP),
+    published_at = COALESCE(created_at, CURRENT_TIMESTAMP)
+WHERE published_at IS NULL;
+
+ALTER TABLE project_updates
+    ALTER COLUMN author_id SET NOT NULL,
+    ALTER COLUMN created_at SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_project_updates_feed ON project_updates(project_id, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_project_updates_milestone ON project_updates(milestone_id) WHERE milestone_id IS NOT NULL;
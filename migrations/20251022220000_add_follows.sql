@@ -0,0 +1,23 @@
+-- Users following a project or a student. Followers hear about project
+-- updates, new milestones and funding thresholds.
+CREATE TABLE IF NOT EXISTS follows (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    follower_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    project_id UUID REFERENCES projects(id) ON DELETE CASCADE,
+    student_id UUID REFERENCES students(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((project_id IS NULL) <> (student_id IS NULL))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_follows_project ON follows(project_id, follower_id) WHERE project_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_follows_student ON follows(student_id, follower_id) WHERE student_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_follows_follower ON follows(follower_id, created_at DESC, id DESC);
+
+-- Share-of-goal thresholds each project has crossed, so followers hear
+-- about each one once
+CREATE TABLE IF NOT EXISTS funding_thresholds_reached (
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    percent INTEGER NOT NULL,
+    reached_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (project_id, percent)
+);
@@ -0,0 +1,39 @@
+-- Cancelling a project returns what donors gave: escrow deposits go back
+-- on-chain to the depositing address and fiat payments are refunded through
+-- the provider that took them. Each refund is tracked until it settles.
+ALTER TABLE projects
+    ADD COLUMN IF NOT EXISTS cancelled_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS cancelled_by UUID REFERENCES users(id),
+    ADD COLUMN IF NOT EXISTS cancellation_reason TEXT;
+
+CREATE TABLE IF NOT EXISTS project_refunds (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    -- 'escrow' returns a contract deposit; 'fiat' refunds a provider payment
+    source VARCHAR(10) NOT NULL CHECK (source IN ('escrow', 'fiat')),
+    deposit_id UUID REFERENCES contract_deposits(id),
+    payment_id VARCHAR(255),
+    -- Where an escrow refund is sent
+    donor_address VARCHAR(255),
+    -- XLM for escrow refunds, the payment's currency for fiat ones
+    amount DECIMAL(20, 8) NOT NULL,
+    currency VARCHAR(10) NOT NULL,
+    -- Fiat refunds stay 'submitted' until the provider settles them
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'submitted', 'succeeded', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    tx_hash VARCHAR(255),
+    refund_id UUID REFERENCES refunds(id),
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    processed_at TIMESTAMP WITH TIME ZONE,
+    CHECK ((source = 'escrow') = (deposit_id IS NOT NULL)),
+    CHECK ((source = 'fiat') = (payment_id IS NOT NULL))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_project_refunds_deposit ON project_refunds(deposit_id) WHERE deposit_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_project_refunds_payment ON project_refunds(payment_id) WHERE payment_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_project_refunds_project ON project_refunds(project_id, status);
+CREATE INDEX IF NOT EXISTS idx_project_refunds_due ON project_refunds(next_attempt_at) WHERE status = 'pending';
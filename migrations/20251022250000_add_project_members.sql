@@ -0,0 +1,37 @@
+-- Team projects: several students can work on a project. The owner is the
+-- student the project was created for; members join by accepting an
+-- invitation and take `share_bps` of each milestone payout, the owner
+-- keeping whatever the members don't.
+CREATE TABLE IF NOT EXISTS project_members (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    student_id UUID NOT NULL REFERENCES students(id) ON DELETE CASCADE,
+    role VARCHAR(10) NOT NULL CHECK (role IN ('owner', 'member')),
+    status VARCHAR(10) NOT NULL DEFAULT 'invited' CHECK (status IN ('invited', 'active')),
+    share_bps INTEGER NOT NULL DEFAULT 0 CHECK (share_bps BETWEEN 0 AND 10000),
+    invited_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    joined_at TIMESTAMP WITH TIME ZONE,
+    UNIQUE (project_id, student_id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_project_members_one_owner ON project_members(project_id) WHERE role = 'owner';
+CREATE INDEX IF NOT EXISTS idx_project_members_student ON project_members(student_id);
+
+-- Every existing project's student becomes its owner
+INSERT INTO project_members (project_id, student_id, role, status, joined_at)
+SELECT id, student_id, 'owner', 'active', created_at
+FROM projects
+ON CONFLICT (project_id, student_id) DO NOTHING;
+
+-- What each member was paid for a milestone, so a release that fails part
+-- way through doesn't pay anyone twice when retried
+CREATE TABLE IF NOT EXISTS milestone_member_payouts (
+    milestone_id UUID NOT NULL REFERENCES milestones(id) ON DELETE CASCADE,
+    student_id UUID NOT NULL REFERENCES students(id),
+    amount DECIMAL(20, 7) NOT NULL,
+    tx_hash VARCHAR(255) NOT NULL,
+    claimable_balance_id VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (milestone_id, student_id)
+);
@@ -0,0 +1,21 @@
+-- Every edit to a project is kept as a revision. Changes to what donors
+-- backed on a published project (its funding goal and milestone plan) wait
+-- for an admin to approve them; everything else goes live straight away.
+CREATE TABLE IF NOT EXISTS project_revisions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    author_id UUID REFERENCES users(id),
+    -- [{"field": ..., "before": ..., "after": ...}]
+    changes JSONB NOT NULL,
+    requires_review BOOLEAN NOT NULL DEFAULT FALSE,
+    status VARCHAR(20) NOT NULL DEFAULT 'applied'
+        CHECK (status IN ('pending', 'approved', 'rejected', 'applied')),
+    review_note TEXT,
+    reviewed_by UUID REFERENCES users(id),
+    reviewed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_project_revisions_project ON project_revisions(project_id, created_at DESC);
+-- At most one revision awaiting review per project
+CREATE UNIQUE INDEX IF NOT EXISTS idx_project_revisions_pending ON project_revisions(project_id) WHERE status = 'pending';
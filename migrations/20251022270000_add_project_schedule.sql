@@ -0,0 +1,14 @@
+-- Projects can be approved to go live later and can stop taking donations at
+-- a deadline. A project approved for later waits as 'scheduled'; when its
+-- deadline passes it's either funded (and keeps going without new
+-- donations) or cancelled with its donors refunded.
+ALTER TABLE projects
+    ADD COLUMN IF NOT EXISTS publish_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS funding_deadline TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS funding_closed_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS funding_outcome VARCHAR(20)
+        CHECK (funding_outcome IN ('funded', 'unfunded'));
+
+CREATE INDEX IF NOT EXISTS idx_projects_publish_due ON projects(publish_at) WHERE status = 'scheduled';
+CREATE INDEX IF NOT EXISTS idx_projects_deadline_due ON projects(funding_deadline)
+    WHERE status = 'active' AND funding_closed_at IS NULL;
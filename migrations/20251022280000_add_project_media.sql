@@ -0,0 +1,25 @@
+-- A project's gallery: uploaded images kept in object storage and video
+-- links, in order and with captions. `projects.media_url` stays as the
+-- cover, the first ready image.
+CREATE TABLE IF NOT EXISTS project_media (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    kind VARCHAR(10) NOT NULL CHECK (kind IN ('image', 'video')),
+    -- The stored upload; NULL for links
+    file_id UUID REFERENCES files(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    caption VARCHAR(500),
+    position INTEGER NOT NULL DEFAULT 0,
+    -- Presigned uploads stay 'pending' until the client confirms them
+    status VARCHAR(10) NOT NULL DEFAULT 'ready' CHECK (status IN ('pending', 'ready')),
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_project_media_project ON project_media(project_id, position);
+
+-- Existing covers become the first image of each gallery
+INSERT INTO project_media (project_id, kind, url, position)
+SELECT id, 'image', media_url, 0
+FROM projects
+WHERE media_url IS NOT NULL AND media_url <> '';
@@ -0,0 +1,8 @@
+-- Where a file's bytes live: 'object' for the object storage bucket, where
+-- `path` is the object key; 'local' for paths on this server's disk and
+-- references recorded before uploads were stored.
+ALTER TABLE files ADD COLUMN IF NOT EXISTS storage VARCHAR(10) NOT NULL DEFAULT 'local'
+    CHECK (storage IN ('local', 'object'));
+
+UPDATE files SET storage = 'object'
+WHERE id IN (SELECT file_id FROM project_media WHERE file_id IS NOT NULL);
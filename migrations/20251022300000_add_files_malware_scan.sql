@@ -0,0 +1,16 @@
+-- Uploads are scanned for malware before they can be downloaded: 'pending'
+-- until scanned, then 'clean' or 'quarantined', or 'failed' after repeated
+-- scanner errors. Files that never went through object storage are
+-- 'not_scanned'.
+ALTER TABLE files
+    ADD COLUMN IF NOT EXISTS scan_status VARCHAR(20) NOT NULL DEFAULT 'not_scanned'
+        CHECK (scan_status IN ('not_scanned', 'pending', 'clean', 'quarantined', 'failed')),
+    -- The signature that matched, or the last scanner error
+    ADD COLUMN IF NOT EXISTS scan_result TEXT,
+    ADD COLUMN IF NOT EXISTS scan_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS scanned_at TIMESTAMP WITH TIME ZONE;
+
+-- Everything already in storage waits for its first scan
+UPDATE files SET scan_status = 'pending' WHERE storage = 'object';
+
+CREATE INDEX IF NOT EXISTS idx_files_scan_pending ON files(created_at) WHERE scan_status = 'pending';
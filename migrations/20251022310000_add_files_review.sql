@@ -0,0 +1,7 @@
+-- Admins' decision on each submitted verification document. A verification
+-- can only be approved once at least one of its documents is accepted.
+ALTER TABLE files
+    ADD COLUMN IF NOT EXISTS review_status VARCHAR(10) CHECK (review_status IN ('accepted', 'rejected')),
+    ADD COLUMN IF NOT EXISTS review_note TEXT,
+    ADD COLUMN IF NOT EXISTS reviewed_by UUID REFERENCES users(id),
+    ADD COLUMN IF NOT EXISTS reviewed_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,23 @@
+-- School email domains accepted for student verification. `domain` is either
+-- exact (`uonbi.ac.ke`) or a wildcard over subdomains (`*.ac.ke`); when
+-- several match an address the most specific wins. The policy decides what
+-- happens to an application from the domain:
+--   manual       queued for an admin
+--   auto_verify  approved on submission
+--   blocked      refused
+CREATE TABLE IF NOT EXISTS university_domains (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    domain VARCHAR(255) NOT NULL UNIQUE,
+    university_name VARCHAR(255),
+    policy VARCHAR(20) NOT NULL DEFAULT 'manual' CHECK (policy IN ('manual', 'auto_verify', 'blocked')),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- What was accepted before the list existed
+INSERT INTO university_domains (domain, university_name) VALUES
+    ('*.edu', NULL),
+    ('*.ac.ke', NULL)
+ON CONFLICT (domain) DO NOTHING;
@@ -0,0 +1,14 @@
+-- Applicants prove they own their school address by entering a code emailed
+-- to it; only confirmed applications reach the admin queue. The code is
+-- stored hashed.
+ALTER TABLE student_verifications
+    ADD COLUMN IF NOT EXISTS school_email_confirmed_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS school_email_code_hash VARCHAR(64),
+    ADD COLUMN IF NOT EXISTS school_email_code_expires_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS school_email_code_sent_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS school_email_code_attempts INTEGER NOT NULL DEFAULT 0;
+
+-- Applications made before confirmation existed keep their place in the queue
+UPDATE student_verifications
+SET school_email_confirmed_at = COALESCE(submitted_at, created_at, NOW())
+WHERE school_email_confirmed_at IS NULL;
@@ -0,0 +1,19 @@
+-- Applicants may give their admission number, which is matched against
+-- lists schools send in of students they vouch for
+ALTER TABLE student_verifications ADD COLUMN IF NOT EXISTS admission_number VARCHAR(50);
+
+-- Admission numbers are stored uppercased; the domain is that of the
+-- school's email addresses. A row approves at most one verification.
+CREATE TABLE IF NOT EXISTS preapproved_admissions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    admission_number VARCHAR(50) NOT NULL,
+    school_domain VARCHAR(255) NOT NULL,
+    full_name VARCHAR(255),
+    imported_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    matched_verification_id UUID REFERENCES student_verifications(id) ON DELETE SET NULL,
+    matched_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (school_domain, admission_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_preapproved_admissions_created ON preapproved_admissions(created_at DESC, id DESC);
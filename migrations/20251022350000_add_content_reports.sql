@@ -0,0 +1,34 @@
+-- Content taken out of public view by moderation. `hidden_by` is the
+-- moderator who hid it, or NULL when it was hidden automatically for
+-- drawing too many reports.
+ALTER TABLE projects
+    ADD COLUMN IF NOT EXISTS hidden_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS hidden_by UUID REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE project_comments
+    ADD COLUMN IF NOT EXISTS hidden_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS hidden_by UUID REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE student_profiles
+    ADD COLUMN IF NOT EXISTS hidden_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS hidden_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+-- Users flagging a project, comment or profile (keyed by the user's id) for
+-- moderators to review
+CREATE TABLE IF NOT EXISTS content_reports (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    reporter_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    target_type VARCHAR(20) NOT NULL CHECK (target_type IN ('project', 'comment', 'profile')),
+    target_id UUID NOT NULL,
+    reason VARCHAR(20) NOT NULL CHECK (reason IN ('spam', 'abuse', 'fraud', 'inappropriate', 'other')),
+    details TEXT,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'dismissed', 'actioned')),
+    resolution VARCHAR(20) CHECK (resolution IN ('dismiss', 'hide', 'suspend')),
+    resolution_note TEXT,
+    resolved_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    resolved_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- One open report per user per piece of content
+CREATE UNIQUE INDEX IF NOT EXISTS idx_content_reports_open
+    ON content_reports(target_type, target_id, reporter_id) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_content_reports_queue ON content_reports(status, created_at DESC, id DESC);
@@ -0,0 +1,21 @@
+-- Switches for new behavior, flipped without a redeploy. A flag is on for a
+-- request when it's enabled, the server's APP_ENV is in `environments` (or
+-- the list is empty), and the caller falls inside `rollout_percent`; callers
+-- are bucketed by user id, so each keeps the same answer as a rollout grows.
+-- Anonymous callers only see flags rolled out to everyone.
+CREATE TABLE IF NOT EXISTS feature_flags (
+    name VARCHAR(64) PRIMARY KEY CHECK (name ~ '^[a-z][a-z0-9_]*$'),
+    description TEXT,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    environments TEXT[] NOT NULL DEFAULT '{}',
+    rollout_percent INTEGER NOT NULL DEFAULT 100 CHECK (rollout_percent BETWEEN 0 AND 100),
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Features that shipped before flags existed stay on
+INSERT INTO feature_flags (name, description, enabled) VALUES
+    ('recurring_donations', 'Monthly recurring gifts by card or Stellar', TRUE),
+    ('guest_checkout', 'Donating without an account', TRUE)
+ON CONFLICT (name) DO NOTHING;
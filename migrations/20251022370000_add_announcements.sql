@@ -0,0 +1,39 @@
+-- Announcements composed by admins and delivered to everyone matching their
+-- audience as notifications, with an SSE event when they go out. An empty
+-- `audience_roles` means every role; `cohort` narrows further:
+--   verified_students    students whose verification was approved
+--   unverified_students  students not yet verified
+--   donors               users with a confirmed donation
+--   project_owners       students with a live project
+-- With `email_digest`, recipients who haven't read it in the app get it in
+-- the next daily email digest.
+CREATE TABLE IF NOT EXISTS announcements (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    title VARCHAR(200) NOT NULL,
+    body TEXT NOT NULL,
+    kind VARCHAR(20) NOT NULL DEFAULT 'general' CHECK (kind IN ('general', 'maintenance', 'campaign')),
+    audience_roles TEXT[] NOT NULL DEFAULT '{}',
+    cohort VARCHAR(30) CHECK (cohort IN ('verified_students', 'unverified_students', 'donors', 'project_owners')),
+    email_digest BOOLEAN NOT NULL DEFAULT FALSE,
+    -- The window a maintenance announcement is about
+    starts_at TIMESTAMP WITH TIME ZONE,
+    ends_at TIMESTAMP WITH TIME ZONE,
+    status VARCHAR(20) NOT NULL DEFAULT 'scheduled' CHECK (status IN ('scheduled', 'sent', 'cancelled')),
+    publish_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMP WITH TIME ZONE,
+    recipients INTEGER NOT NULL DEFAULT 0,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CHECK (ends_at IS NULL OR starts_at IS NULL OR ends_at > starts_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_announcements_due ON announcements (publish_at) WHERE status = 'scheduled';
+
+-- Which announcement a notification delivered, for read rates, and when it
+-- went out in a digest
+ALTER TABLE notifications
+    ADD COLUMN IF NOT EXISTS announcement_id UUID REFERENCES announcements(id) ON DELETE CASCADE,
+    ADD COLUMN IF NOT EXISTS emailed_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS idx_notifications_announcement ON notifications (announcement_id) WHERE announcement_id IS NOT NULL;
@@ -0,0 +1,7 @@
+-- The typed event behind each notification, with the fields clients use to
+-- find what it's about; the same payload goes out over SSE
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS event JSONB;
+
+UPDATE notifications
+SET event = jsonb_build_object('event', 'announcement', 'announcement_id', announcement_id)
+WHERE announcement_id IS NOT NULL AND event IS NULL;
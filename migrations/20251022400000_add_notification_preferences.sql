@@ -0,0 +1,32 @@
+-- Per-user overrides of how each event type reaches them. A NULL medium
+-- follows the default for the user's role.
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- A notification event name, as in notifications.event->>'event'
+    event_type VARCHAR(50) NOT NULL,
+    in_app BOOLEAN,
+    email BOOLEAN,
+    push BOOLEAN,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, event_type)
+);
+
+-- In-app notifications are on by default for every role, so only an
+-- explicit opt-out drops one. Done here so every insert path honors it.
+CREATE OR REPLACE FUNCTION skip_muted_notifications()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.event IS NOT NULL AND EXISTS (
+        SELECT 1 FROM notification_preferences
+        WHERE user_id = NEW.user_id AND event_type = NEW.event->>'event' AND in_app = FALSE
+    ) THEN
+        RETURN NULL;
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER skip_muted_notifications
+    BEFORE INSERT ON notifications
+    FOR EACH ROW
+    EXECUTE FUNCTION skip_muted_notifications();
@@ -0,0 +1,17 @@
+-- Users' choice of a daily or weekly email digest of what happened since the
+-- last one: unread notifications, donations to their projects and updates
+-- from what they follow
+CREATE TABLE IF NOT EXISTS notification_digests (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    cadence VARCHAR(10) NOT NULL DEFAULT 'off' CHECK (cadence IN ('off', 'daily', 'weekly')),
+    -- The hour (UTC) the digest goes out, and the day for weekly ones (0 = Monday)
+    send_hour SMALLINT NOT NULL DEFAULT 8 CHECK (send_hour BETWEEN 0 AND 23),
+    send_weekday SMALLINT NOT NULL DEFAULT 0 CHECK (send_weekday BETWEEN 0 AND 6),
+    -- Carried by the unsubscribe link in every digest, so kept as issued
+    unsubscribe_token VARCHAR(64) NOT NULL UNIQUE,
+    last_sent_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_notification_digests_due ON notification_digests(send_hour) WHERE cadence <> 'off';
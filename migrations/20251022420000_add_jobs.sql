@@ -0,0 +1,29 @@
+-- Durable background work. Runners claim due jobs with FOR UPDATE SKIP
+-- LOCKED and lease them by pushing run_at forward, so a crashed runner's
+-- jobs come due again instead of being lost.
+CREATE TABLE IF NOT EXISTS jobs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    kind VARCHAR(50) NOT NULL,
+    -- The typed job, its kind included
+    payload JSONB NOT NULL,
+    -- `dead` jobs ran out of attempts and wait for an operator to retry them
+    status VARCHAR(20) NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'succeeded', 'dead')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 6,
+    run_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    -- Keeps the same job, such as one run of a recurring job, from being queued twice
+    dedupe_key VARCHAR(255) UNIQUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_due ON jobs(run_at) WHERE status = 'queued';
+CREATE INDEX IF NOT EXISTS idx_jobs_finished ON jobs(status, finished_at DESC) WHERE status <> 'queued';
+
+-- Emails still waiting in the outbox are sent as jobs from now on
+INSERT INTO jobs (kind, payload, run_at, dedupe_key)
+SELECT 'send_email', jsonb_build_object('kind', 'send_email', 'email_id', id), next_attempt_at, 'send_email:' || id
+FROM email_outbox
+WHERE status = 'pending'
+ON CONFLICT (dedupe_key) DO NOTHING;
@@ -0,0 +1,13 @@
+-- Each background worker's latest run, written by the workers themselves so
+-- the admin workers endpoint reports what actually ran rather than what was
+-- started. Loops sharing a worker name share its row.
+CREATE TABLE IF NOT EXISTS worker_heartbeats (
+    name VARCHAR(50) PRIMARY KEY,
+    last_run_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_success_at TIMESTAMP WITH TIME ZONE,
+    last_error_at TIMESTAMP WITH TIME ZONE,
+    last_error TEXT,
+    runs BIGINT NOT NULL DEFAULT 0,
+    -- Donations, emails, jobs and so on handled across all runs
+    items_processed BIGINT NOT NULL DEFAULT 0
+);
@@ -0,0 +1,9 @@
+-- How far the analytics worker has folded confirmed donations into
+-- analytics_summary, so each run only recomputes what changed since
+CREATE TABLE IF NOT EXISTS analytics_watermarks (
+    name VARCHAR(50) PRIMARY KEY,
+    high_water TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_donations_confirmed_at ON donations(confirmed_at) WHERE confirmed_at IS NOT NULL;
@@ -0,0 +1,11 @@
+-- Campaign start and end dates, run by the campaign scheduler: scheduled
+-- campaigns go active at their start, and at their end either queue their
+-- own distribution ('end_date') or wait for an admin to execute ('manual')
+ALTER TABLE campaigns
+    ADD COLUMN IF NOT EXISTS start_date TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS end_date TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN IF NOT EXISTS distribution_trigger VARCHAR(20) NOT NULL DEFAULT 'end_date'
+        CHECK (distribution_trigger IN ('end_date', 'manual'));
+
+CREATE INDEX IF NOT EXISTS idx_campaigns_scheduled_start ON campaigns(start_date) WHERE status = 'scheduled';
+CREATE INDEX IF NOT EXISTS idx_campaigns_active_end ON campaigns(end_date) WHERE status = 'active';
@@ -0,0 +1,5 @@
+-- How a campaign's reward pool is split among the students its criteria
+-- select: {"type": "equal"}, {"type": "weighted_by_funds"} or
+-- {"type": "fixed_per_head", "amount_xlm": ..., "max_recipients": ...}
+ALTER TABLE campaigns
+    ADD COLUMN IF NOT EXISTS distribution_strategy JSONB NOT NULL DEFAULT '{"type": "equal"}';
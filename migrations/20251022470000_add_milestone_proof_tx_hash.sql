@@ -0,0 +1,4 @@
+-- Hash of the Soroban transaction that anchored each milestone proof
+
+ALTER TABLE contract_milestones
+    ADD COLUMN IF NOT EXISTS proof_tx_hash VARCHAR(64);
@@ -0,0 +1,3 @@
+-- A member payout row is reserved before the payment is sent, so it has no
+-- tx hash until the payment goes through
+ALTER TABLE milestone_member_payouts ALTER COLUMN tx_hash DROP NOT NULL;
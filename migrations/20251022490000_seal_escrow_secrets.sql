@@ -0,0 +1,3 @@
+-- Escrow account secrets are sealed with ESCROW_ENCRYPTION_KEY. Secrets stored
+-- in plaintext before this are sealed by the escrow sweeper on its next run.
+COMMENT ON COLUMN project_escrow_accounts.secret_key IS 'Sealed with ESCROW_ENCRYPTION_KEY: "v1:" then base64 of nonce || ciphertext';
@@ -0,0 +1,23 @@
+-- Keep project search vectors current as projects are created and edited.
+-- The ops re-index endpoint is still there for dictionary changes.
+
+CREATE OR REPLACE FUNCTION projects_search_vector()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector := setweight(to_tsvector('english', COALESCE(NEW.title, '')), 'A')
+        || setweight(to_tsvector('english', COALESCE(NEW.description, '')), 'B')
+        || setweight(to_tsvector('english', array_to_string(NEW.tags, ' ')), 'C');
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER projects_search_vector
+    BEFORE INSERT OR UPDATE OF title, description, tags ON projects
+    FOR EACH ROW
+    EXECUTE FUNCTION projects_search_vector();
+
+-- Projects created or edited since the vectors were first built
+UPDATE projects
+SET search_vector = setweight(to_tsvector('english', COALESCE(title, '')), 'A')
+    || setweight(to_tsvector('english', COALESCE(description, '')), 'B')
+    || setweight(to_tsvector('english', array_to_string(tags, ' ')), 'C');
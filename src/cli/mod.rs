@@ -92,30 +92,13 @@ impl FundHubCLI {
         Ok(())
     }
 
-    pub async fn start_workers(&self) -> Result<()> {
-        let pb = self.multi_progress.add(ProgressBar::new(100));
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} {msg} {bar:40.cyan/blue} {pos}/{len}")
-                .unwrap()
-                .progress_chars("#>-"),
+    /// Workers report their own runs; this only says where to look
+    pub fn show_workers_launched(&self) {
+        println!(
+            "{} {}",
+            "⚙️  Background workers launched; live status at".bright_white(),
+            "GET /api/admin/workers".bright_cyan()
         );
-        pb.set_message("Starting background workers...");
-
-        for i in 0..=100 {
-            pb.set_position(i);
-            pb.set_message(match i {
-                0..=20 => "Starting donation verification worker...",
-                21..=40 => "Starting wallet sync worker...",
-                41..=60 => "Starting analytics worker...",
-                61..=80 => "Starting campaign distribution worker...",
-                81..=100 => "All workers started!",
-                _ => "Starting workers...",
-            });
-            sleep(Duration::from_millis(40)).await;
-        }
-        pb.finish_with_message("✅ Background workers started");
-        Ok(())
     }
 
     pub fn show_server_info(&self, port: u16) {
@@ -194,28 +177,11 @@ impl FundHubCLI {
         println!("{}", "║                                                                              ║".bright_red());
         println!("{}", "╚══════════════════════════════════════════════════════════════════════════════╝".bright_red());
         println!();
-        
-        let pb = ProgressBar::new(100);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.red} {msg} {bar:40.red/red} {pos}/{len}")
-                .unwrap()
-                .progress_chars("#>-"),
-        );
-        pb.set_message("Stopping services...");
+    }
 
-        for i in 0..=100 {
-            pb.set_position(i);
-            pb.set_message(match i {
-                0..=25 => "Stopping background workers...",
-                26..=50 => "Closing database connections...",
-                51..=75 => "Stopping HTTP server...",
-                76..=100 => "Shutdown complete!",
-                _ => "Shutting down...",
-            });
-            std::thread::sleep(Duration::from_millis(20));
-        }
-        pb.finish_with_message("✅ Server stopped successfully");
+    /// Printed once workers, requests and the database pool are closed
+    pub fn show_shutdown_complete(&self) {
+        println!("{}", "✅ Server stopped successfully".bright_green());
         println!();
         println!("{}", "Thank you for using FundHub! 👋".bright_cyan());
     }
@@ -1,31 +1,357 @@
-use serde::Deserialize;
-use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+
+/// How donation funds are held on-chain
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscrowMode {
+    /// All donations flow into the shared platform/student wallets
+    Pool,
+    /// Each published project gets a dedicated escrow account
+    PerProject,
+}
+
+impl EscrowMode {
+    pub fn from_env() -> Self {
+        match std::env::var("ESCROW_MODE").unwrap_or_default().to_lowercase().as_str() {
+            "per_project" | "per-project" => EscrowMode::PerProject,
+            _ => EscrowMode::Pool,
+        }
+    }
+}
+
+/// Stellar network the platform runs against, from `STELLAR_NETWORK`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StellarNetwork {
+    Testnet,
+    Futurenet,
+    Mainnet,
+}
+
+impl StellarNetwork {
+    /// Accepts the names used by the Stellar CLI as well as `public`/`pubnet` for mainnet
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "testnet" => Some(StellarNetwork::Testnet),
+            "futurenet" => Some(StellarNetwork::Futurenet),
+            "mainnet" | "public" | "pubnet" => Some(StellarNetwork::Mainnet),
+            _ => None,
+        }
+    }
+
+    /// Defaults to testnet when `STELLAR_NETWORK` is unset
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("STELLAR_NETWORK") {
+            Ok(name) if !name.trim().is_empty() => {
+                Self::parse(&name).ok_or_else(|| anyhow!("Unknown STELLAR_NETWORK {}", name))
+            }
+            _ => Ok(StellarNetwork::Testnet),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StellarNetwork::Testnet => "testnet",
+            StellarNetwork::Futurenet => "futurenet",
+            StellarNetwork::Mainnet => "mainnet",
+        }
+    }
+
+    pub fn default_passphrase(&self) -> &'static str {
+        match self {
+            StellarNetwork::Testnet => "Test SDF Network ; September 2015",
+            StellarNetwork::Futurenet => "Test SDF Future Network ; October 2022",
+            StellarNetwork::Mainnet => "Public Global Stellar Network ; September 2015",
+        }
+    }
+
+    pub fn default_horizon_url(&self) -> &'static str {
+        match self {
+            StellarNetwork::Testnet => "https://horizon-testnet.stellar.org",
+            StellarNetwork::Futurenet => "https://horizon-futurenet.stellar.org",
+            StellarNetwork::Mainnet => "https://horizon.stellar.org",
+        }
+    }
+
+    /// SDF's public RPC for test networks; mainnet needs a provider of your own
+    pub fn default_soroban_rpc_url(&self) -> Option<&'static str> {
+        match self {
+            StellarNetwork::Testnet => Some("https://soroban-testnet.stellar.org"),
+            StellarNetwork::Futurenet => Some("https://rpc-futurenet.stellar.org"),
+            StellarNetwork::Mainnet => None,
+        }
+    }
+
+    /// `STELLAR_NETWORK_PASSPHRASE` overrides the default, e.g. for a standalone network
+    pub fn network_passphrase(&self) -> String {
+        env_override("STELLAR_NETWORK_PASSPHRASE").unwrap_or_else(|| self.default_passphrase().to_string())
+    }
+
+    /// `STELLAR_HORIZON_URL` overrides the default
+    pub fn horizon_url(&self) -> String {
+        env_override("STELLAR_HORIZON_URL").unwrap_or_else(|| self.default_horizon_url().to_string())
+    }
+
+    /// Horizon page for a transaction, for linking donors to what settled on-chain
+    pub fn transaction_url(&self, tx_hash: &str) -> String {
+        format!("{}/transactions/{}", self.horizon_url().trim_end_matches('/'), tx_hash)
+    }
+
+    /// Soroban RPC endpoint from `SOROBAN_RPC_URL`, or the network default when
+    /// `SOROBAN_RPC_ENABLED` is set. `None` keeps contract state in the database only.
+    pub fn soroban_rpc_url(&self) -> Option<String> {
+        env_override("SOROBAN_RPC_URL").or_else(|| {
+            let enabled = std::env::var("SOROBAN_RPC_ENABLED")
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false);
+            enabled.then(|| self.default_soroban_rpc_url()).flatten().map(str::to_string)
+        })
+    }
+}
+
+impl std::fmt::Display for StellarNetwork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Which S3-compatible service uploads are kept in, from `STORAGE_BACKEND`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// AWS S3, addressed virtual-host style
+    S3,
+    /// MinIO or another self-hosted service at `STORAGE_ENDPOINT`, addressed path-style
+    Minio,
+}
+
+impl StorageBackend {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "s3" | "aws" => Some(StorageBackend::S3),
+            "minio" => Some(StorageBackend::Minio),
+            _ => None,
+        }
+    }
+}
+
+/// Longest a SigV4 presigned URL may stay valid
+const MAX_PRESIGN_SECS: u32 = 7 * 24 * 60 * 60;
+/// S3 refuses multipart parts smaller than this, except the last
+const MIN_PART_SIZE_MB: usize = 5;
+
+/// Object storage for uploads
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    pub bucket: String,
+    pub region: String,
+    pub endpoint: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Where objects are read from publicly, e.g. a CDN in front of the bucket
+    pub public_url: Option<String>,
+    /// Uploads larger than this are sent as a multipart upload in parts of this size
+    pub part_size: usize,
+    /// How long presigned download links stay valid
+    pub download_url_ttl: Duration,
+}
+
+impl StorageConfig {
+    /// `None` when `STORAGE_BUCKET` is unset, which leaves uploads off. A
+    /// `STORAGE_ENDPOINT` without a `STORAGE_BACKEND` is taken to be MinIO.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(bucket) = env_override("STORAGE_BUCKET") else {
+            return Ok(None);
+        };
+        let region = env_override("STORAGE_REGION").unwrap_or_else(|| "us-east-1".to_string());
+        let endpoint = env_override("STORAGE_ENDPOINT");
+        let backend = match env_override("STORAGE_BACKEND") {
+            Some(name) => StorageBackend::parse(&name).ok_or_else(|| anyhow!("Unknown STORAGE_BACKEND {}", name))?,
+            None if endpoint.is_some() => StorageBackend::Minio,
+            None => StorageBackend::S3,
+        };
+        let endpoint = match (backend, endpoint) {
+            (_, Some(endpoint)) => endpoint,
+            (StorageBackend::S3, None) => format!("https://s3.{}.amazonaws.com", region),
+            (StorageBackend::Minio, None) => return Err(anyhow!("STORAGE_ENDPOINT must be set for MinIO")),
+        };
+        let part_size_mb = env_override("STORAGE_PART_SIZE_MB")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(8)
+            .max(MIN_PART_SIZE_MB);
+
+        Ok(Some(Self {
+            backend,
+            bucket,
+            region,
+            endpoint,
+            access_key_id: env_override("STORAGE_ACCESS_KEY_ID").ok_or_else(|| anyhow!("STORAGE_ACCESS_KEY_ID must be set"))?,
+            secret_access_key: env_override("STORAGE_SECRET_ACCESS_KEY")
+                .ok_or_else(|| anyhow!("STORAGE_SECRET_ACCESS_KEY must be set"))?,
+            public_url: env_override("STORAGE_PUBLIC_URL"),
+            part_size: part_size_mb * 1024 * 1024,
+            download_url_ttl: Duration::from_secs(env_u32("STORAGE_DOWNLOAD_URL_TTL_SECS", 900).clamp(1, MAX_PRESIGN_SECS).into()),
+        }))
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub database_url: String,
     pub redis_url: String,
-    pub jwt_secret: String,
-    pub stellar_network: String,
+    /// Only needed when tokens aren't signed with an RSA key
+    pub jwt_secret: Option<String>,
+    pub stellar_network: StellarNetwork,
     pub stellar_horizon_url: String,
     pub platform_wallet_public_key: String,
     pub platform_wallet_secret_key: String,
+    /// When set, workers log the actions they would take without writing or submitting anything
+    pub worker_dry_run: bool,
+    pub escrow_mode: EscrowMode,
+    /// SQL statements slower than this are logged and tracked
+    pub slow_query_threshold: Duration,
+    /// `None` turns uploads off
+    pub storage: Option<StorageConfig>,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self> {
+        let stellar_network = StellarNetwork::from_env()?;
+
         Ok(Self {
             database_url: std::env::var("DATABASE_URL")?,
             redis_url: std::env::var("REDIS_URL")?,
-            jwt_secret: std::env::var("JWT_SECRET")?,
-            stellar_network: std::env::var("STELLAR_NETWORK")?,
-            stellar_horizon_url: std::env::var("STELLAR_HORIZON_URL")?,
+            jwt_secret: std::env::var("JWT_SECRET").ok(),
+            stellar_network,
+            stellar_horizon_url: stellar_network.horizon_url(),
             platform_wallet_public_key: std::env::var("PLATFORM_WALLET_PUBLIC_KEY")?,
             platform_wallet_secret_key: std::env::var("PLATFORM_WALLET_SECRET_KEY")?,
+            worker_dry_run: std::env::var("WORKER_DRY_RUN")
+                .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false),
+            escrow_mode: EscrowMode::from_env(),
+            slow_query_threshold: env_millis("SLOW_QUERY_THRESHOLD_MS", 500),
+            storage: StorageConfig::from_env()?,
         })
     }
 }
 
+/// Per-request latency budget; slower requests are logged and counted
+pub fn latency_budget() -> Duration {
+    env_millis("LATENCY_BUDGET_MS", 1000)
+}
+
+/// How long `/api/projects/compare` responses are served from memory
+pub fn compare_cache_ttl() -> Duration {
+    env_millis("PROJECT_COMPARE_CACHE_TTL_MS", 60_000)
+}
+
+/// Which deployment this is (`development`, `staging`, `production`, ...),
+/// for feature flags limited to some environments
+pub fn app_environment() -> String {
+    env_override("APP_ENV")
+        .map(|v| v.trim().to_lowercase())
+        .unwrap_or_else(|| "development".to_string())
+}
+
+/// How long feature flags are served from memory before being reread, so
+/// changes made through another instance reach this one
+pub fn feature_flag_cache_ttl() -> Duration {
+    env_millis("FEATURE_FLAG_CACHE_TTL_MS", 30_000)
+}
+
+/// How often an idle SSE stream gets a keep-alive comment, so proxies don't
+/// close it
+pub fn sse_keep_alive() -> Duration {
+    env_millis("SSE_KEEP_ALIVE_MS", 15_000)
+}
+
+/// How often notification WebSockets are pinged; those silent for two
+/// intervals are closed
+pub fn ws_ping_interval() -> Duration {
+    env_millis("WS_PING_INTERVAL_MS", 30_000)
+}
+
+/// How long shutdown waits for workers and in-flight requests to finish
+/// before the process exits anyway
+pub fn shutdown_grace() -> Duration {
+    env_millis("SHUTDOWN_GRACE_MS", 30_000)
+}
+
+/// Whether replicas elect one leader per worker so periodic work runs on a
+/// single instance; on unless `WORKER_LEADER_ELECTION=false`
+pub fn worker_leader_election() -> bool {
+    env_override("WORKER_LEADER_ELECTION").is_none_or(|v| !v.trim().eq_ignore_ascii_case("false"))
+}
+
+/// Escrow drift (either direction) above which admins are notified
+pub fn reconciliation_drift_threshold() -> crate::utils::money::Stroops {
+    env_override("RECONCILIATION_DRIFT_THRESHOLD_XLM")
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or_else(|| crate::utils::money::Stroops::from_stroops(crate::utils::money::STROOPS_PER_XLM))
+}
+
+/// KES paid per XLM for mobile money milestone payouts; `None` disables them
+pub fn mpesa_payout_kes_per_xlm() -> Option<f64> {
+    env_override("MPESA_PAYOUT_KES_PER_XLM")
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|rate: &f64| rate.is_finite() && *rate > 0.0)
+}
+
+/// A set, non-empty environment variable
+fn env_override(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.trim().is_empty())
+}
+
+pub(crate) fn env_millis(key: &str, default: u64) -> Duration {
+    let millis = std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default);
+    Duration::from_millis(millis)
+}
+
+pub(crate) fn env_u32(key: &str, default: u32) -> u32 {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
 pub fn init() -> Result<Config> {
     Config::from_env()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stellar_network_parse() {
+        assert_eq!(StellarNetwork::parse("testnet"), Some(StellarNetwork::Testnet));
+        assert_eq!(StellarNetwork::parse("Futurenet"), Some(StellarNetwork::Futurenet));
+        assert_eq!(StellarNetwork::parse("public"), Some(StellarNetwork::Mainnet));
+        assert_eq!(StellarNetwork::parse("pubnet"), Some(StellarNetwork::Mainnet));
+        assert_eq!(StellarNetwork::parse("devnet"), None);
+    }
+
+    #[test]
+    fn test_storage_backend_parse() {
+        assert_eq!(StorageBackend::parse("S3"), Some(StorageBackend::S3));
+        assert_eq!(StorageBackend::parse(" minio "), Some(StorageBackend::Minio));
+        assert_eq!(StorageBackend::parse("gcs"), None);
+    }
+
+    #[test]
+    fn test_stellar_network_defaults() {
+        assert_eq!(StellarNetwork::Testnet.default_passphrase(), "Test SDF Network ; September 2015");
+        assert_eq!(StellarNetwork::Mainnet.default_passphrase(), "Public Global Stellar Network ; September 2015");
+        assert_eq!(StellarNetwork::Futurenet.default_passphrase(), "Test SDF Future Network ; October 2022");
+        assert_eq!(StellarNetwork::Mainnet.default_horizon_url(), "https://horizon.stellar.org");
+        assert!(StellarNetwork::Mainnet.default_soroban_rpc_url().is_none());
+        assert_eq!(StellarNetwork::Mainnet.to_string(), "mainnet");
+    }
+}
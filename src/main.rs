@@ -5,7 +5,11 @@ use axum::{
 };
 use std::net::SocketAddr;
 use tracing::info;
-use sqlx::postgres::PgPoolOptions;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::ConnectOptions;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 use tower_http::cors::{CorsLayer, Any, AllowOrigin};
 
 mod config;
@@ -23,11 +27,18 @@ async fn main() -> Result<()> {
     let cli = cli::FundHubCLI::new();
     cli.show_banner();
 
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
-    
     // Load environment variables
     dotenvy::dotenv().ok();
+
+    // Initialize tracing; sqlx slow-statement events also feed the latency tracker
+    let latency = utils::latency::LatencyTracker::new(config::latency_budget());
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(EnvFilter::from_default_env()))
+        .with(
+            utils::latency::SlowQueryLayer::new(latency.clone())
+                .with_filter(Targets::new().with_target("sqlx::query", tracing::Level::WARN)),
+        )
+        .init();
     
     // Show startup progress
     let startup_pb = cli.show_startup_progress();
@@ -36,6 +47,7 @@ async fn main() -> Result<()> {
     startup_pb.set_message("Loading configuration...");
     startup_pb.inc(10);
     let config = config::init()?;
+    utils::jwt::init_keys()?;
     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
     
     // Set up database connection
@@ -43,9 +55,11 @@ async fn main() -> Result<()> {
     startup_pb.inc(20);
     cli.initialize_database().await?;
     
+    let connect_options = PgConnectOptions::from_str(&config.database_url)?
+        .log_slow_statements(log::LevelFilter::Warn, config.slow_query_threshold);
     let pool = PgPoolOptions::new()
         .max_connections(5)
-        .connect(&config.database_url)
+        .connect_with(connect_options)
         .await?;
     
     // Initialize Stellar service
@@ -60,50 +74,339 @@ async fn main() -> Result<()> {
         &config.platform_wallet_public_key,
     )?;
     
+    let payments = services::stellar_tx::TxSubmitter::new(
+        &config.stellar_horizon_url,
+        &config.platform_wallet_secret_key,
+        &config.stellar_network.network_passphrase(),
+    )
+    .map_err(|e| eprintln!("Platform payments disabled: {}", e))
+    .ok();
+    let stellar_api: Arc<dyn services::stellar_api::StellarApi> = Arc::new(
+        services::stellar_api::HorizonStellar::new(stellar_service.clone(), payments.clone()),
+    );
+    let web_auth = services::sep10::WebAuth::new(
+        &config.platform_wallet_secret_key,
+        &config.stellar_network.network_passphrase(),
+        &services::sep10::configured_home_domain(),
+    )
+    .map_err(|e| eprintln!("Wallet ownership challenges disabled: {}", e))
+    .ok();
+    
+    let payment_providers = services::payment_service::ProviderRegistry::load(pool.clone()).await?;
+    let flags = services::flags::FlagRegistry::load(pool.clone()).await?;
+
     // Start background workers
     startup_pb.set_message("Starting background workers...");
     startup_pb.inc(20);
-    cli.start_workers().await?;
     
-    let worker = workers::Worker::new(pool.clone(), stellar_service.clone());
+    let mut worker_control = workers::control::WorkerControl::new();
+    if config::worker_leader_election() {
+        worker_control = worker_control.with_leader_election(pool.clone());
+    }
+    let worker = workers::Worker::new(
+        pool.clone(),
+        stellar_service.clone(),
+        config.worker_dry_run,
+        config.escrow_mode,
+        worker_control.clone(),
+    );
     worker.start().await?;
     
     // Start analytics worker
-    let analytics_worker = workers::analytics::AnalyticsWorker::new(pool.clone());
+    let usage = utils::usage::UsageRecorder::new();
+    let analytics_worker = workers::analytics::AnalyticsWorker::new(
+        pool.clone(),
+        worker_control.clone(),
+        usage.clone(),
+    );
     analytics_worker.start().await?;
     
-    // Start payment reconciler worker
-    let payment_reconciler = workers::payment_reconciler::PaymentReconciler::new(pool.clone());
-    tokio::spawn(async move {
-        if let Err(e) = payment_reconciler.start().await {
-            eprintln!("Payment reconciler error: {}", e);
+    // Start ledger indexer for platform and project wallets
+    let ledger_indexer = workers::ledger_indexer::LedgerIndexer::new(
+        pool.clone(),
+        stellar_service.clone(),
+        config.worker_dry_run,
+        worker_control.clone(),
+    );
+    worker_control.spawn(async move {
+        if let Err(e) = ledger_indexer.start().await {
+            eprintln!("Ledger indexer error: {}", e);
+        }
+    });
+
+    // Web Push for high-priority events; off unless VAPID keys are configured
+    let push = services::web_push::WebPush::from_env(pool.clone()).map_err(anyhow::Error::msg)?;
+    if push.is_none() {
+        tracing::info!("VAPID_PRIVATE_KEY is not set; push notifications are off");
+    }
+    let notifier = state::Notifier::new(push.clone());
+
+    // Start contract event indexer when soroban-rpc is configured
+    match services::soroban_rpc::SorobanRpc::from_env(config.stellar_network) {
+        Ok(Some(rpc)) => {
+            let event_indexer = workers::event_indexer::EventIndexer::new(
+                pool.clone(),
+                rpc,
+                notifier.clone(),
+                config.worker_dry_run,
+                worker_control.clone(),
+            );
+            worker_control.spawn(async move {
+                if let Err(e) = event_indexer.start().await {
+                    eprintln!("Event indexer error: {}", e);
+                }
+            });
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Event indexer disabled: {}", e),
+    }
+
+    // Start recurring donation scheduler
+    let subscription_scheduler = workers::subscription_scheduler::SubscriptionScheduler::new(
+        pool.clone(),
+        payment_providers.clone(),
+        config.escrow_mode,
+        config.stellar_network,
+        notifier.clone(),
+        config.worker_dry_run,
+        worker_control.clone(),
+    );
+    worker_control.spawn(async move {
+        if let Err(e) = subscription_scheduler.start().await {
+            eprintln!("Subscription scheduler error: {}", e);
+        }
+    });
+
+    // Start the job queue: email delivery, campaign payouts, payment
+    // settlement and escrow reconciliation
+    let email_provider = match services::email::provider_from_env() {
+        Ok(Some(provider)) => Some(provider),
+        Ok(None) => {
+            tracing::warn!("EMAIL_PROVIDER is not set; emails will be queued but not sent");
+            None
+        }
+        Err(e) => {
+            eprintln!("Email sending disabled: {}", e);
+            None
+        }
+    };
+    let job_handlers = workers::job_runner::JobHandlers {
+        email: email_provider,
+        payments: payments.clone(),
+        payment_reconciler: workers::payment_reconciler::PaymentReconciler::new(
+            pool.clone(),
+            payment_providers.clone(),
+            payments.clone(),
+            config.escrow_mode,
+            config.stellar_network,
+            config.worker_dry_run,
+        ),
+        escrow_reconciler: workers::escrow_reconciler::EscrowReconciler::new(
+            pool.clone(),
+            config.stellar_network,
+            notifier.clone(),
+            config.worker_dry_run,
+        ),
+    };
+    let job_runner = workers::job_runner::JobRunner::new(
+        pool.clone(),
+        job_handlers,
+        config.worker_dry_run,
+        worker_control.clone(),
+    );
+    worker_control.spawn(async move {
+        if let Err(e) = job_runner.start().await {
+            eprintln!("Job runner error: {}", e);
+        }
+    });
+
+    // Start announcement delivery and the daily digest
+    let announcement_dispatcher = workers::announcement_dispatcher::AnnouncementDispatcher::new(
+        pool.clone(),
+        notifier.clone(),
+        config.worker_dry_run,
+        worker_control.clone(),
+    );
+    worker_control.spawn(async move {
+        if let Err(e) = announcement_dispatcher.start().await {
+            eprintln!("Announcement dispatcher error: {}", e);
+        }
+    });
+
+    // Start daily and weekly notification digests
+    let digest_sender = workers::digest_sender::DigestSender::new(
+        pool.clone(),
+        config.worker_dry_run,
+        worker_control.clone(),
+    );
+    worker_control.spawn(async move {
+        if let Err(e) = digest_sender.start().await {
+            eprintln!("Digest sender error: {}", e);
+        }
+    });
+
+    // Start outgoing webhook delivery
+    let webhook_dispatcher = workers::webhook_dispatcher::WebhookDispatcher::new(
+        pool.clone(),
+        config.worker_dry_run,
+        worker_control.clone(),
+    );
+    worker_control.spawn(async move {
+        if let Err(e) = webhook_dispatcher.start().await {
+            eprintln!("Webhook dispatcher error: {}", e);
+        }
+    });
+
+    // Start refunds for cancelled projects
+    let refund_processor = workers::refund_processor::RefundProcessor::new(
+        pool.clone(),
+        payment_providers.clone(),
+        config.stellar_network,
+        config.worker_dry_run,
+        worker_control.clone(),
+    );
+    worker_control.spawn(async move {
+        if let Err(e) = refund_processor.start().await {
+            eprintln!("Refund processor error: {}", e);
+        }
+    });
+
+    // Start project scheduler: scheduled publishing and funding deadlines
+    let project_scheduler = workers::project_scheduler::ProjectScheduler::new(
+        pool.clone(),
+        config.stellar_network,
+        (config.escrow_mode == config::EscrowMode::PerProject)
+            .then(|| services::escrow::EscrowService::new(pool.clone(), new_stellar_service.clone())),
+        config.worker_dry_run,
+        worker_control.clone(),
+    );
+    worker_control.spawn(async move {
+        if let Err(e) = project_scheduler.start().await {
+            eprintln!("Project scheduler error: {}", e);
         }
     });
+
+    // Start campaign scheduler: campaign start and end dates
+    let campaign_scheduler =
+        workers::campaign_scheduler::CampaignScheduler::new(pool.clone(), config.worker_dry_run, worker_control.clone());
+    worker_control.spawn(async move {
+        if let Err(e) = campaign_scheduler.start().await {
+            eprintln!("Campaign scheduler error: {}", e);
+        }
+    });
+
+    // Start escrow sweeper when projects hold their own escrow accounts
+    if config.escrow_mode == config::EscrowMode::PerProject {
+        let escrow_sweeper = workers::escrow_sweeper::EscrowSweeper::new(
+            pool.clone(),
+            stellar_service.clone(),
+            new_stellar_service.clone(),
+            config.worker_dry_run,
+            worker_control.clone(),
+        );
+        worker_control.spawn(async move {
+            if let Err(e) = escrow_sweeper.start().await {
+                eprintln!("Escrow sweeper error: {}", e);
+            }
+        });
+    }
     
+    // Per-IP and per-user request limits, shared through Redis when configured
+    let rate_limiter = utils::rate_limit::RateLimiter::from_env(&config.redis_url).await;
+    // X-Api-Key access for partner integrations, limited per key
+    let api_key_auth = utils::api_key::ApiKeyAuth { pool: pool.clone(), limiter: rate_limiter.clone() };
+
+    // CAPTCHA for logins after repeated failures; off unless configured
+    let captcha = services::captcha::CaptchaVerifier::from_env().map_err(anyhow::Error::msg)?;
+    // S3-compatible storage for uploads; off unless configured
+    let storage = config.storage.clone().map(services::storage::ObjectStorage::new).transpose()?;
+
+    // Start file scanner: uploads can't be downloaded until scanned clean
+    let malware_scanner = services::malware_scan::MalwareScanner::from_env().map_err(anyhow::Error::msg)?;
+    match (storage.clone(), malware_scanner) {
+        (Some(storage), Some(scanner)) => {
+            let file_scanner = workers::file_scanner::FileScanner::new(
+                pool.clone(),
+                storage,
+                scanner,
+                config.worker_dry_run,
+                worker_control.clone(),
+            );
+            worker_control.spawn(async move {
+                if let Err(e) = file_scanner.start().await {
+                    eprintln!("File scanner error: {}", e);
+                }
+            });
+        }
+        (Some(_), None) => tracing::warn!("MALWARE_SCANNER is not set; uploads stay pending and can't be downloaded"),
+        (None, _) => {}
+    }
+
     // Build our application
     startup_pb.set_message("Building application...");
     startup_pb.inc(20);
-    let (tx, _rx) = tokio::sync::broadcast::channel::<String>(100);
 
     let app = Router::new()
         .route("/health", get(health_check))
+        .route("/metrics", get(routes::handlers::status::metrics))
+        .route("/.well-known/jwks.json", get(routes::handlers::auth::jwks))
         // Mount API routes
         .nest("/api/auth", routes::auth_routes())
         .nest("/api/students", routes::student_routes())
+        .nest("/api/users", routes::user_routes())
+        .nest("/api/files", routes::file_routes())
+        .nest("/api/reports", routes::report_routes())
         .nest("/api/wallets", routes::wallet_routes())
-        .nest("/api/projects", routes::project_routes())
-        .nest("/api/donations", routes::donation_routes())
+        .nest(
+            "/api/projects",
+            routes::project_routes().route_layer(axum::middleware::from_fn_with_state(
+                api_key_auth.clone(),
+                |state, req, next| utils::api_key::api_key_mw(state, services::api_keys::PROJECTS_READ, req, next),
+            )),
+        )
+        .nest(
+            "/api/donations",
+            routes::donation_routes().route_layer(axum::middleware::from_fn_with_state(
+                api_key_auth,
+                |state, req, next| utils::api_key::api_key_mw(state, services::api_keys::DONATIONS_READ, req, next),
+            )),
+        )
+        .nest("/api/categories", routes::category_routes())
+        .nest("/api/donors", routes::donor_routes())
         .nest("/api/campaigns", routes::campaign_routes())
-        .nest("/api/admin", routes::admin_routes())
+        .nest(
+            "/api/admin",
+            routes::admin_routes().route_layer(axum::middleware::from_fn_with_state(
+                pool.clone(),
+                utils::roles::require_admin_two_factor_mw,
+            )),
+        )
         .nest("/api/analytics", routes::analytics_routes())
         .nest("/api/guest", routes::guest_routes())
         .nest("/api/milestones", routes::milestone_routes())
         .nest("/api/contracts", routes::contract_routes())
         .nest("/api/payments", routes::payment_routes())
         .nest("/api/notifications", routes::notification_routes())
-        .route("/api/notifications/sse", get(routes::sse_notifications))
+        .nest("/api/webhooks", routes::webhook_routes())
+        .route("/api/notifications/sse", get(routes::handlers::realtime::sse_notifications))
+        .route("/api/notifications/ws", get(routes::handlers::realtime::notifications_ws))
         // Documentation routes
         .nest("/api/docs", routes::docs_routes())
+        // Track per-route latency (route_layer so the matched path is known)
+        .route_layer(axum::middleware::from_fn_with_state(
+            latency.clone(),
+            utils::latency::latency_mw,
+        ))
+        // Per-user API usage for analytics and rate-limit tiers
+        .route_layer(axum::middleware::from_fn_with_state(
+            usage.clone(),
+            utils::usage::usage_mw,
+        ))
+        // Limit request rates per IP, per user and more tightly on /api/auth
+        .layer(axum::middleware::from_fn_with_state(
+            rate_limiter,
+            utils::rate_limit::rate_limit_mw,
+        ))
         // Add CORS middleware
         .layer(
             CorsLayer::new()
@@ -133,17 +436,43 @@ async fn main() -> Result<()> {
                     "accept".parse().unwrap(),
                     "origin".parse().unwrap(),
                     "x-requested-with".parse().unwrap(),
+                    "x-request-id".parse().unwrap(),
+                    "x-api-key".parse().unwrap(),
+                ])
+                .expose_headers([
+                    "x-request-id".parse::<axum::http::HeaderName>().unwrap(),
+                    "retry-after".parse().unwrap(),
+                    "x-ratelimit-limit".parse().unwrap(),
+                    "x-ratelimit-remaining".parse().unwrap(),
                 ])
                 .allow_credentials(true)
         )
+        // Tag each request with an id for error bodies and logs
+        .layer(axum::middleware::from_fn(utils::request_id::request_id_mw))
         // Add middleware
         .layer(tower_http::trace::TraceLayer::new_for_http())
         // Add state
         .with_state(state::AppState { 
-            pool, 
+            pool: pool.clone(), 
             stellar: stellar_service, 
             stellar_service: new_stellar_service,
-            notifier: tx 
+            stellar_api,
+            notifier: notifier.clone(),
+            worker_dry_run: config.worker_dry_run,
+            escrow_mode: config.escrow_mode,
+            network: config.stellar_network,
+            worker_control: worker_control.clone(),
+            latency,
+            compare_cache: utils::ttl_cache::TtlCache::new(config::compare_cache_ttl(), 256),
+            usage,
+            payments,
+            web_auth,
+            payment_providers,
+            rates: services::rates::Rates::from_env(),
+            captcha,
+            storage,
+            flags,
+            push,
         });
 
     // Complete startup
@@ -157,32 +486,82 @@ async fn main() -> Result<()> {
         .parse::<u16>()
         .unwrap_or(3000);
     cli.show_server_info(port);
+    cli.show_workers_launched();
 
-    // Set up graceful shutdown
-    let start_time = std::time::Instant::now();
-    let shutdown_signal = async {
-        tokio::signal::ctrl_c()
+    // Run the server - bind to 0.0.0.0 for production. On shutdown it stops
+    // accepting connections and drains the requests in flight.
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let stopping = worker_control.clone();
+    let mut server = tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async move { stopping.stopping().await })
             .await
-            .expect("Failed to install Ctrl+C handler");
+    });
+
+    let server_stopped = tokio::select! {
+        result = &mut server => {
+            match result {
+                Ok(Err(e)) => tracing::error!("HTTP server error: {}", e),
+                Err(e) => tracing::error!("HTTP server task failed: {}", e),
+                Ok(Ok(())) => tracing::error!("HTTP server stopped unexpectedly"),
+            }
+            true
+        }
+        _ = shutdown_signal() => false,
     };
+    cli.show_shutdown_message();
 
-    // Run the server - bind to 0.0.0.0 for production
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    let server = axum::serve(tokio::net::TcpListener::bind(addr).await?, app.into_make_service());
+    // Workers finish the iteration they're in, and open SSE and WebSocket
+    // streams end so their connections can drain
+    let grace = config::shutdown_grace();
+    info!("Stopping background workers and HTTP server...");
+    worker_control.shutdown();
+    notifier.rotate();
 
-    // Handle shutdown
-    tokio::select! {
-        _ = server => {
-            cli.show_shutdown_message();
-        }
-        _ = shutdown_signal => {
-            cli.show_shutdown_message();
-        }
+    if !server_stopped && tokio::time::timeout(grace, server).await.is_err() {
+        tracing::warn!("Requests still in flight after {:?}; closing them", grace);
+    }
+    let running = worker_control.wait(grace).await;
+    if running > 0 {
+        tracing::warn!("{} background tasks still running after {:?}; stopping anyway", running, grace);
+    }
+    // Other instances take over this one's workers without waiting for a timeout
+    worker_control.release_leadership().await;
+    if tokio::time::timeout(grace, notifier.flush()).await.is_err() {
+        tracing::warn!("Push notifications still sending after {:?}; dropping them", grace);
     }
 
+    info!("Closing database connections...");
+    pool.close().await;
+    cli.show_shutdown_complete();
+
     Ok(())
 }
 
+/// Resolves on Ctrl+C, or on SIGTERM from a process manager or container runtime
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
 async fn health_check() -> &'static str {
     "OK"
 }
\ No newline at end of file
@@ -3,6 +3,9 @@ use sqlx::Type;
 use sqlx::types::BigDecimal;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::money::Stroops;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct User {
@@ -28,6 +31,10 @@ pub enum UserRole {
     User,
     Student,
     Admin,
+    /// Reviews verifications and projects; no access to funds
+    Moderator,
+    /// Runs payouts and refunds; no moderation
+    Finance,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
@@ -132,16 +139,30 @@ pub struct Donation {
     pub id: Uuid,
     pub donor_id: Option<Uuid>,
     pub project_id: Option<Uuid>,
-    pub amount: BigDecimal,
+    pub amount: Stroops,
     pub tx_hash: Option<String>,
     pub memo: Option<String>,
     pub status: String,
     pub payment_method: String,
     pub donation_type: Option<String>,
+    /// Hides the donor from project owners and public leaderboards
+    pub is_anonymous: bool,
     pub confirmed_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
+impl Donation {
+    /// This donation as shown to anyone but the donor and admins: anonymous
+    /// donations lose their donor and the transaction that would reveal them
+    pub fn redacted(mut self) -> Self {
+        if self.is_anonymous {
+            self.donor_id = None;
+            self.tx_hash = None;
+        }
+        self
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Type, Clone)]
 #[sqlx(type_name = "text", rename_all = "lowercase")]
 pub enum DonationStatus {
@@ -213,7 +234,7 @@ pub struct Campaign {
     pub id: Uuid,
     pub name: String,
     pub description: String,
-    pub reward_pool_xlm: BigDecimal,
+    pub reward_pool_xlm: Stroops,
     pub criteria: serde_json::Value,
     pub status: String,
     pub start_date: DateTime<Utc>,
@@ -226,7 +247,7 @@ pub struct CampaignDistribution {
     pub id: Uuid,
     pub campaign_id: Uuid,
     pub recipient_id: Uuid,
-    pub amount: f64,
+    pub amount: Stroops,
     pub tx_hash: Option<String>,
     pub created_at: DateTime<Utc>,
 }
@@ -240,7 +261,7 @@ pub struct GuestDonation {
     pub guest_email: String,
     pub project_id: Uuid,
     pub tx_hash: Option<String>,
-    pub amount: BigDecimal,
+    pub amount: Stroops,
     pub verified: bool,
     pub created_at: DateTime<Utc>,
 }
@@ -261,18 +282,27 @@ pub struct StudentVerification {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct StudentProfile {
     pub id: Uuid,
     pub user_id: Uuid,
+    #[validate(custom(function = "crate::routes::validation::not_blank"))]
     pub full_name: String,
+    #[validate(custom(function = "crate::routes::validation::not_blank"))]
     pub school_name: String,
+    #[validate(email(message = "Enter a valid email address"))]
     pub school_email: String,
+    #[validate(length(max = 2000, message = "Bio must be at most 2000 characters"))]
     pub student_bio: Option<String>,
+    #[validate(length(max = 2000, message = "Motivation must be at most 2000 characters"))]
     pub motivation_text: Option<String>,
+    #[validate(url(message = "Enter a valid URL"))]
     pub profile_picture_url: Option<String>,
+    #[validate(url(message = "Enter a valid URL"))]
     pub linkedin_url: Option<String>,
+    #[validate(url(message = "Enter a valid URL"))]
     pub github_url: Option<String>,
+    #[validate(url(message = "Enter a valid URL"))]
     pub portfolio_url: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -294,7 +324,7 @@ pub struct Milestone {
     pub id: Uuid,
     pub project_id: Uuid,
     pub title: String,
-    pub target_amount: BigDecimal,
+    pub target_amount: Stroops,
     pub released: bool,
     pub released_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
@@ -319,7 +349,7 @@ pub struct GuestFundingRequest {
     pub guest_email: String,
     pub project_id: Uuid,
     pub tx_hash: Option<String>,
-    pub amount: BigDecimal,
+    pub amount: Stroops,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -331,13 +361,22 @@ pub struct StudentVerificationRequest {
     pub motivation_text: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct EnhancedStudentVerificationRequest {
+    /// Checked against the university domain list when applying
+    #[validate(email(message = "Enter a valid email address"))]
     pub school_email: String,
+    #[validate(custom(function = "crate::routes::validation::not_blank"))]
     pub full_name: String,
+    #[validate(custom(function = "crate::routes::validation::not_blank"))]
     pub school_name: String,
+    #[validate(length(max = 2000, message = "Bio must be at most 2000 characters"))]
     pub student_bio: Option<String>,
+    #[validate(length(max = 2000, message = "Motivation must be at most 2000 characters"))]
     pub motivation_text: Option<String>,
+    /// Matched against the school's pre-approved admission list, if any
+    #[validate(length(max = 50, message = "Admission number must be at most 50 characters"))]
+    pub admission_number: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -371,7 +410,20 @@ pub struct VerificationResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MilestoneReleaseRequest {
     pub milestone_id: Uuid,
-    pub tx_hash: String,
+    /// Transaction that released the funds on-chain. Omit in pool escrow
+    /// mode to have the platform pay the student instead.
+    #[serde(default)]
+    pub tx_hash: Option<String>,
+    /// Pay the student by M-Pesa instead. The payout waits for admin
+    /// approval and the milestone is released once M-Pesa confirms it.
+    #[serde(default)]
+    pub mobile_money_phone: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MilestoneProofRequest {
+    pub proof_hash: String, // hex-encoded SHA-256 of the deliverable
+    pub proof_uri: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -384,4 +436,36 @@ pub struct PublicProjectInfo {
     pub current_funding: BigDecimal,
     pub tags: Vec<String>,
     pub created_at: DateTime<Utc>,
+    pub funding_deadline: Option<DateTime<Utc>>,
+    /// Until donations close; `0` once the deadline has passed
+    pub seconds_until_deadline: Option<i64>,
+    pub funding_open: bool,
+    /// The gallery in order; `media_url` is its cover
+    pub media: sqlx::types::Json<Vec<PublicMedia>>,
+}
+
+/// A gallery item as shown on public listings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicMedia {
+    pub kind: String,
+    pub url: String,
+    pub caption: Option<String>,
+}
+
+/// One column of the public project comparison view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectComparison {
+    pub id: Uuid,
+    pub title: String,
+    pub status: String,
+    pub funding_goal: Stroops,
+    pub current_funding: Stroops,
+    pub funding_progress_pct: f64,
+    pub donor_count: i64,
+    pub milestones_total: i64,
+    pub milestones_completed: i64,
+    pub created_at: DateTime<Utc>,
+    pub first_donation_at: Option<DateTime<Utc>>,
+    pub last_donation_at: Option<DateTime<Utc>>,
+    pub last_milestone_completed_at: Option<DateTime<Utc>>,
 }
\ No newline at end of file
@@ -0,0 +1,227 @@
+use axum::{
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use crate::utils::request_id;
+
+pub type AppResult<T> = Result<T, AppError>;
+
+/// A problem with one field of the request body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Handler errors. Every variant renders as
+/// `{"error": message, "code": machine_code, "details"?: ..., "request_id": ...}`
+/// with the request id also in the `X-Request-Id` header. A
+/// `retry_after_secs` detail is sent as `Retry-After` too.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("The request has invalid fields")]
+    Validation(Vec<FieldError>),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    Gone(String),
+    #[error("{message}")]
+    RateLimited { message: String, retry_after_secs: i64 },
+    /// A provider or chain service refused or failed
+    #[error("{0}")]
+    Upstream(String),
+    #[error("{0}")]
+    Unavailable(String),
+    /// A condition clients handle specifically, e.g. `captcha_required`
+    #[error("{message}")]
+    Coded {
+        status: StatusCode,
+        code: &'static str,
+        message: String,
+        details: Option<serde_json::Value>,
+    },
+    /// Logged with the request id; the client only sees a generic message
+    #[error("Internal server error")]
+    Internal(#[from] anyhow::Error),
+}
+
+impl AppError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        AppError::BadRequest(message.into())
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        AppError::Unauthorized(message.into())
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        AppError::Forbidden(message.into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        AppError::Conflict(message.into())
+    }
+
+    /// A single invalid field
+    pub fn invalid(field: &str, message: impl Into<String>) -> Self {
+        AppError::Validation(vec![FieldError { field: field.to_string(), message: message.into() }])
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Gone(_) => StatusCode::GONE,
+            AppError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Coded { status, .. } => *status,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable identifier clients can branch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Validation(_) => "validation_failed",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Gone(_) => "gone",
+            AppError::RateLimited { .. } => "rate_limited",
+            AppError::Upstream(_) => "upstream_error",
+            AppError::Unavailable(_) => "service_unavailable",
+            AppError::Coded { code, .. } => code,
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::Validation(fields) => Some(serde_json::json!(fields)),
+            AppError::RateLimited { retry_after_secs, .. } => {
+                Some(serde_json::json!({"retry_after_secs": retry_after_secs}))
+            }
+            AppError::Coded { details, .. } => details.clone(),
+            _ => None,
+        }
+    }
+
+    fn body(&self, request_id: Option<String>) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "error": self.to_string(),
+            "code": self.code(),
+            "request_id": request_id,
+        });
+        if let Some(details) = self.details() {
+            body["details"] = details;
+        }
+        body
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => AppError::not_found("Not found"),
+            e => AppError::Internal(e.into()),
+        }
+    }
+}
+
+/// For helpers that still report a bare status
+impl From<StatusCode> for AppError {
+    fn from(status: StatusCode) -> Self {
+        let message = status.canonical_reason().unwrap_or("Request failed").to_string();
+        match status {
+            StatusCode::BAD_REQUEST => AppError::BadRequest(message),
+            StatusCode::UNAUTHORIZED => AppError::Unauthorized(message),
+            StatusCode::FORBIDDEN => AppError::Forbidden(message),
+            StatusCode::NOT_FOUND => AppError::NotFound(message),
+            StatusCode::CONFLICT => AppError::Conflict(message),
+            StatusCode::GONE => AppError::Gone(message),
+            StatusCode::BAD_GATEWAY => AppError::Upstream(message),
+            StatusCode::SERVICE_UNAVAILABLE => AppError::Unavailable(message),
+            s if s.is_server_error() => AppError::Internal(anyhow::anyhow!("handler returned {}", s)),
+            status => AppError::Coded { status, code: "request_failed", message, details: None },
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let request_id = request_id::current();
+        if let AppError::Internal(e) = &self {
+            tracing::error!(request_id = request_id.as_deref().unwrap_or("-"), "Internal error: {:#}", e);
+        }
+
+        let body = self.body(request_id);
+        let retry_after = body["details"]["retry_after_secs"].as_i64();
+        let mut response = (self.status(), Json(body)).into_response();
+        if let Some(secs) = retry_after {
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_has_code_and_details() {
+        let error = AppError::invalid("email", "Enter a valid email address");
+        assert_eq!(error.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = error.body(Some("req-1".to_string()));
+        assert_eq!(body["code"], "validation_failed");
+        assert_eq!(body["request_id"], "req-1");
+        assert_eq!(body["details"][0]["field"], "email");
+
+        let body = AppError::not_found("Project not found").body(None);
+        assert_eq!(body["error"], "Project not found");
+        assert!(body.get("details").is_none());
+    }
+
+    #[test]
+    fn test_internal_errors_hide_the_cause() {
+        let error = AppError::from(anyhow::anyhow!("connection refused to 10.0.0.5"));
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.body(None)["error"], "Internal server error");
+    }
+
+    #[test]
+    fn test_rate_limited_sets_retry_after() {
+        let response = AppError::RateLimited { message: "Slow down".into(), retry_after_secs: 30 }.into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "30");
+    }
+
+    #[test]
+    fn test_status_conversion() {
+        assert_eq!(AppError::from(StatusCode::NOT_FOUND).code(), "not_found");
+        assert_eq!(AppError::from(StatusCode::INTERNAL_SERVER_ERROR).code(), "internal_error");
+        assert_eq!(AppError::from(StatusCode::PAYLOAD_TOO_LARGE).status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
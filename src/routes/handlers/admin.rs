@@ -1,12 +1,20 @@
-use axum::{extract::{State, Json, Path}, http::StatusCode};
+use anyhow::Context;
+use axum::extract::{State, Json, Path, Query};
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use validator::Validate;
 
 use crate::models::{
     Student, StudentVerification, VerificationStatus, StudentProfile, VerificationHistory,
     EnhancedStudentVerificationRequest, ApproveVerificationRequest, RejectVerificationRequest, VerificationResponse
 };
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::handlers::verification_documents::require_accepted_document;
+use crate::routes::validation::{self, ValidatedJson};
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::utils::money::Stroops;
+use crate::utils::pagination::{Page, PageQuery, PageRequest};
 
 #[derive(Serialize)]
 pub struct ApiMessage { 
@@ -50,7 +58,7 @@ pub struct EnhancedVerificationList {
 
 pub async fn list_students(
     State(state): State<crate::state::AppState>
-) -> Result<Json<Vec<Student>>, StatusCode> {
+) -> AppResult<Json<Vec<Student>>> {
     let students = sqlx::query_as!(
         Student,
         r#"
@@ -62,15 +70,28 @@ pub async fn list_students(
         "#
     )
     .fetch_all(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     Ok(Json(students))
 }
 
+/// Approving a student needs them to have confirmed their school email
+async fn require_confirmed_school_email(state: &crate::state::AppState, user_id: Uuid) -> AppResult<()> {
+    let confirmed = crate::services::school_email_otp::confirmed(&state.pool, user_id).await?;
+    if confirmed {
+        return Ok(());
+    }
+    Err(AppError::Coded {
+        status: axum::http::StatusCode::CONFLICT,
+        code: "school_email_unconfirmed",
+        message: "The student hasn't confirmed their school email yet".to_string(),
+        details: None,
+    })
+}
+
 pub async fn list_pending_verifications(
     State(state): State<crate::state::AppState>
-) -> Result<Json<Vec<PendingVerification>>, StatusCode> {
+) -> AppResult<Json<Vec<PendingVerification>>> {
     // Get all pending verifications with user details from the new student_verifications table
     let rows = sqlx::query!(
         r#"
@@ -80,16 +101,13 @@ pub async fn list_pending_verifications(
                sv.submitted_at, sv.created_at
         FROM student_verifications sv
         JOIN users u ON u.id = sv.user_id
-        WHERE sv.status = 'pending'
+        WHERE sv.status = 'pending' AND sv.school_email_confirmed_at IS NOT NULL
         ORDER BY sv.created_at ASC
         "#
     )
     .fetch_all(&state.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Database error fetching pending verifications: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .context("Failed to fetch pending verifications")?;
 
     let verifications = rows.into_iter().map(|row| PendingVerification {
         id: row.id,
@@ -112,7 +130,7 @@ pub async fn list_pending_verifications(
 
 pub async fn list_all_verifications(
     State(state): State<crate::state::AppState>
-) -> Result<Json<Vec<PendingVerification>>, StatusCode> {
+) -> AppResult<Json<Vec<PendingVerification>>> {
     // Get all verifications (pending, approved, rejected) with user details from the new student_verifications table
     let rows = sqlx::query!(
         r#"
@@ -127,10 +145,7 @@ pub async fn list_all_verifications(
     )
     .fetch_all(&state.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Database error fetching all verifications: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .context("Failed to fetch all verifications")?;
 
     let verifications = rows.into_iter().map(|row| PendingVerification {
         id: row.id,
@@ -153,7 +168,7 @@ pub async fn list_all_verifications(
 
 pub async fn list_approved_verifications(
     State(state): State<crate::state::AppState>
-) -> Result<Json<Vec<PendingVerification>>, StatusCode> {
+) -> AppResult<Json<Vec<PendingVerification>>> {
     // Get all approved verifications from the new student_verifications table
     let rows = sqlx::query!(
         r#"
@@ -169,10 +184,7 @@ pub async fn list_approved_verifications(
     )
     .fetch_all(&state.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Database error fetching approved verifications: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .context("Failed to fetch approved verifications")?;
 
     let verifications = rows.into_iter().map(|row| PendingVerification {
         id: row.id,
@@ -195,7 +207,7 @@ pub async fn list_approved_verifications(
 
 pub async fn list_rejected_verifications(
     State(state): State<crate::state::AppState>
-) -> Result<Json<Vec<PendingVerification>>, StatusCode> {
+) -> AppResult<Json<Vec<PendingVerification>>> {
     // Get all rejected verifications from the new student_verifications table
     let rows = sqlx::query!(
         r#"
@@ -211,10 +223,7 @@ pub async fn list_rejected_verifications(
     )
     .fetch_all(&state.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Database error fetching rejected verifications: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .context("Failed to fetch rejected verifications")?;
 
     let verifications = rows.into_iter().map(|row| PendingVerification {
         id: row.id,
@@ -239,7 +248,7 @@ pub async fn approve_verification(
     State(state): State<crate::state::AppState>,
     Path(verification_id): Path<Uuid>,
     Json(req): Json<ApproveVerificationRequest>,
-) -> Result<Json<VerificationResponse>, StatusCode> {
+) -> AppResult<Json<VerificationResponse>> {
     // First get the verification details
     let verification = sqlx::query!(
         r#"
@@ -248,9 +257,11 @@ pub async fn approve_verification(
         verification_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Verification not found"))?;
+
+    require_confirmed_school_email(&state, verification.user_id).await?;
+    require_accepted_document(&state, verification.user_id).await?;
 
     // Update student verification status in the new student_verifications table
     let result = sqlx::query!(
@@ -266,8 +277,7 @@ pub async fn approve_verification(
         req.message
     )
     .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     // Update user role to student
     sqlx::query!(
@@ -279,8 +289,7 @@ pub async fn approve_verification(
         result.user_id
     )
     .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     // Create or update student record
     sqlx::query!(
@@ -299,14 +308,21 @@ pub async fn approve_verification(
         req.admin_id
     )
     .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
-    // Emit SSE notification
-    let _ = state.notifier.send(format!(
-        "verification_status:{}:verified", 
-        result.user_id
-    ));
+    let event = NotificationEvent::VerificationStatus {
+        user_id: result.user_id,
+        status: "verified".to_string(),
+        reason: None,
+    };
+    state.notifier.send(Channel::User(result.user_id), event);
+
+    if let Err(e) = crate::services::email::queue_verification_decision(&state.pool, verification_id, result.user_id, true, req.message.clone()).await {
+        tracing::error!("Failed to queue the verification decision email for {}: {}", verification_id, e);
+    }
+    if let Err(e) = crate::services::outgoing_webhooks::queue_verification_approved(&state.pool, verification_id, result.user_id).await {
+        tracing::error!("Failed to queue verification webhooks for {}: {}", verification_id, e);
+    }
 
     Ok(Json(VerificationResponse {
         verification_id: result.id,
@@ -319,7 +335,7 @@ pub async fn reject_verification(
     State(state): State<crate::state::AppState>,
     Path(verification_id): Path<Uuid>,
     Json(req): Json<RejectVerificationRequest>,
-) -> Result<Json<VerificationResponse>, StatusCode> {
+) -> AppResult<Json<VerificationResponse>> {
     // Update student verification status in the new student_verifications table
     let result = sqlx::query!(
         r#"
@@ -333,15 +349,18 @@ pub async fn reject_verification(
         req.reason
     )
     .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
-    // Emit SSE notification with rejection message
-    let _ = state.notifier.send(format!(
-        "verification_status:{}:rejected:{}", 
-        result.user_id,
-        req.reason
-    ));
+    let event = NotificationEvent::VerificationStatus {
+        user_id: result.user_id,
+        status: "rejected".to_string(),
+        reason: Some(req.reason.clone()),
+    };
+    state.notifier.send(Channel::User(result.user_id), event);
+
+    if let Err(e) = crate::services::email::queue_verification_decision(&state.pool, verification_id, result.user_id, false, Some(req.reason.clone())).await {
+        tracing::error!("Failed to queue the verification decision email for {}: {}", verification_id, e);
+    }
 
     Ok(Json(VerificationResponse {
         verification_id: result.id,
@@ -353,11 +372,13 @@ pub async fn reject_verification(
 pub async fn verify_student(
     State(state): State<crate::state::AppState>, 
     Json(req): Json<VerifyStudentRequest>
-) -> Result<Json<ApiMessage>, StatusCode> {
+) -> AppResult<Json<ApiMessage>> {
     let status = if req.approve { "verified" } else { "rejected" };
     let progress = if req.approve { 100 } else { 0 };
     
     if req.approve {
+        require_confirmed_school_email(&state, req.user_id).await?;
+        require_accepted_document(&state, req.user_id).await?;
         sqlx::query!(
             r#"
             UPDATE students
@@ -371,8 +392,7 @@ pub async fn verify_student(
             req.user_id
         )
         .execute(&state.pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
     } else {
         sqlx::query!(
             r#"
@@ -384,21 +404,85 @@ pub async fn verify_student(
             req.user_id
         )
         .execute(&state.pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
     }
     
-    // Emit SSE
-    let _ = state.notifier.send(format!("student_verification:{}:{}", req.user_id, status));
+    let event = NotificationEvent::VerificationStatus {
+        user_id: req.user_id,
+        status: status.to_string(),
+        reason: None,
+    };
+    state.notifier.send(Channel::User(req.user_id), event);
     Ok(Json(ApiMessage { message: "student verification updated".into() }))
 }
 
+#[derive(Deserialize, Validate)]
+pub struct FundStudentRequest {
+    pub student_id: Uuid,
+    #[validate(custom(function = "validation::positive_stroops"))]
+    pub amount: Stroops,
+    /// Text memo, at most 28 bytes
+    #[validate(custom(function = "validation::text_memo"))]
+    pub memo: Option<String>,
+}
+
+/// Pay XLM from the platform wallet to a student's connected wallet
 pub async fn fund_student(
-    State(_state): State<crate::state::AppState>
-) -> Json<ApiMessage> {
-    Json(ApiMessage { 
-        message: "admin fund student (stub)".into() 
-    })
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    ValidatedJson(req): ValidatedJson<FundStudentRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    if !state.stellar_api.can_submit_payments() {
+        return Err(AppError::Unavailable("Platform payments are not configured".to_string()));
+    }
+
+    let wallet = sqlx::query!(
+        "SELECT public_key FROM wallets WHERE student_id = $1 AND status = 'connected'",
+        req.student_id
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Student has no connected wallet"))?;
+
+    let tx_hash = state
+        .stellar_api
+        .submit_payment(&wallet.public_key, req.amount, req.memo.as_deref())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fund student {}: {}", req.student_id, e);
+            AppError::Upstream(e.to_string())
+        })?;
+
+    let platform = std::env::var("PLATFORM_WALLET_PUBLIC_KEY").unwrap_or_default();
+    let entry = crate::services::ledger::payout_entry("student_funding", &tx_hash, req.student_id, &platform, req.amount);
+    if let Err(e) = crate::services::ledger::post(&state.pool, &entry).await {
+        tracing::error!("Failed to post student funding {} to the ledger: {}", tx_hash, e);
+    }
+
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers).ok();
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        "student_funded",
+        req.student_id,
+        "student",
+        serde_json::json!({
+            "amount_xlm": req.amount,
+            "wallet": wallet.public_key,
+            "tx_hash": tx_hash
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(serde_json::json!({
+        "student_id": req.student_id,
+        "amount_xlm": req.amount,
+        "tx_hash": tx_hash
+    })))
 }
 
 /// Approve a student verification
@@ -417,7 +501,7 @@ pub async fn approve_student_verification(
     State(state): State<crate::state::AppState>,
     Path(verification_id): Path<Uuid>,
     Json(payload): Json<ApproveVerificationRequest>,
-) -> Result<Json<VerificationResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> AppResult<Json<VerificationResponse>> {
     // Get verification details
     let verification = sqlx::query_as!(
         StudentVerification,
@@ -431,27 +515,16 @@ pub async fn approve_student_verification(
         verification_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?
-    .ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({"error": "Verification not found"})),
-        )
-    })?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Verification not found"))?;
 
     if verification.status != crate::models::VerificationStatus::Pending {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": "Verification is not pending"})),
-        ));
+        return Err(AppError::conflict("Verification is not pending"));
     }
 
+    require_confirmed_school_email(&state, verification.user_id).await?;
+    require_accepted_document(&state, verification.user_id).await?;
+
     // Update verification status
     let _ = sqlx::query!(
         r#"
@@ -464,12 +537,7 @@ pub async fn approve_student_verification(
     )
     .execute(&state.pool)
     .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Failed to approve verification"})),
-        )
-    })?;
+    .context("Failed to approve verification")?;
 
     // Update user role to student
     let _ = sqlx::query!(
@@ -482,12 +550,7 @@ pub async fn approve_student_verification(
     )
     .execute(&state.pool)
     .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Failed to update user role"})),
-        )
-    })?;
+    .context("Failed to update user role")?;
 
     // Create or update student record
     let _ = sqlx::query!(
@@ -507,12 +570,7 @@ pub async fn approve_student_verification(
     )
     .execute(&state.pool)
     .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Failed to create student record"})),
-        )
-    })?;
+    .context("Failed to create student record")?;
 
     // Log activity
     let _ = sqlx::query!(
@@ -532,6 +590,13 @@ pub async fn approve_student_verification(
     .execute(&state.pool)
     .await;
 
+    if let Err(e) = crate::services::email::queue_verification_decision(&state.pool, verification_id, verification.user_id, true, payload.message.clone()).await {
+        tracing::error!("Failed to queue the verification decision email for {}: {}", verification_id, e);
+    }
+    if let Err(e) = crate::services::outgoing_webhooks::queue_verification_approved(&state.pool, verification_id, verification.user_id).await {
+        tracing::error!("Failed to queue verification webhooks for {}: {}", verification_id, e);
+    }
+
     Ok(Json(VerificationResponse {
         verification_id,
         status: "verified".to_string(),
@@ -544,33 +609,34 @@ pub async fn approve_student_verification(
     get,
     path = "/api/admin/logs",
     responses(
-        (status = 200, description = "Activity logs retrieved successfully", body = Vec<ActivityLog>),
+        (status = 200, description = "A page of activity logs, newest first, with next_cursor and has_more", body = serde_json::Value),
         (status = 500, description = "Internal server error")
     ),
     tag = "Admin"
 )]
 pub async fn get_activity_logs(
     State(state): State<crate::state::AppState>,
-) -> Result<Json<Vec<crate::models::ActivityLog>>, (StatusCode, Json<serde_json::Value>)> {
+    Query(query): Query<PageQuery>,
+) -> AppResult<Json<Page<crate::models::ActivityLog>>> {
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
     let logs = sqlx::query_as!(
         crate::models::ActivityLog,
         r#"
         SELECT id, user_id, action, target_id, target_type, metadata, created_at as "created_at!: chrono::DateTime<chrono::Utc>"
         FROM activity_logs
-        ORDER BY created_at DESC
-        LIMIT 100
-        "#
+        WHERE ($1::timestamptz IS NULL OR (created_at, id) < ($1, $2::uuid))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $3
+        "#,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
     )
     .fetch_all(&state.pool)
     .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Failed to fetch activity logs"})),
-        )
-    })?;
+    .context("Failed to fetch activity logs")?;
 
-    Ok(Json(logs))
+    Ok(Json(Page::new(logs, &page, |log| (Some(log.created_at), log.id))))
 }
 
 /// Get admin overview statistics
@@ -585,79 +651,43 @@ pub async fn get_activity_logs(
 )]
 pub async fn get_admin_overview(
     State(state): State<crate::state::AppState>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+) -> AppResult<Json<serde_json::Value>> {
     // Get comprehensive platform statistics
     let total_users = sqlx::query_scalar!(
         "SELECT COUNT(*) FROM users"
     )
     .fetch_one(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?;
+    .await?;
 
     let verified_students = sqlx::query_scalar!(
         "SELECT COUNT(*) FROM students WHERE verification_status = 'verified'"
     )
     .fetch_one(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?;
+    .await?;
 
     let pending_verifications = sqlx::query_scalar!(
         "SELECT COUNT(*) FROM students WHERE verification_status = 'pending'"
     )
     .fetch_one(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?;
+    .await?;
 
     let rejected_verifications = sqlx::query_scalar!(
         "SELECT COUNT(*) FROM students WHERE verification_status = 'rejected'"
     )
     .fetch_one(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?;
+    .await?;
 
     let total_projects = sqlx::query_scalar!(
         "SELECT COUNT(*) FROM projects"
     )
     .fetch_one(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?;
+    .await?;
 
     let total_donations = sqlx::query_scalar!(
         "SELECT COALESCE(SUM(amount), 0) FROM donations WHERE status = 'confirmed'"
     )
     .fetch_one(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?;
+    .await?;
 
     let overview = serde_json::json!({
         "total_users": total_users,
@@ -683,7 +713,7 @@ pub async fn get_admin_overview(
 )]
 pub async fn get_enhanced_verifications(
     State(state): State<crate::state::AppState>,
-) -> Result<Json<EnhancedVerificationList>, (StatusCode, Json<serde_json::Value>)> {
+) -> AppResult<Json<EnhancedVerificationList>> {
     let verifications = sqlx::query_as!(
         PendingVerification,
         r#"
@@ -699,13 +729,7 @@ pub async fn get_enhanced_verifications(
         "#
     )
     .fetch_all(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?;
+    .await?;
 
     let total_count = verifications.len() as i64;
     let pending_count = verifications.iter().filter(|v| v.verification_status.as_deref() == Some("pending")).count() as i64;
@@ -735,7 +759,7 @@ pub async fn get_enhanced_verifications(
 pub async fn get_verification_details(
     State(state): State<crate::state::AppState>,
     Path(verification_id): Path<Uuid>,
-) -> Result<Json<PendingVerification>, (StatusCode, Json<serde_json::Value>)> {
+) -> AppResult<Json<PendingVerification>> {
     let verification = sqlx::query_as!(
         PendingVerification,
         r#"
@@ -752,19 +776,8 @@ pub async fn get_verification_details(
         verification_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?
-    .ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({"error": "Verification not found"})),
-        )
-    })?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Verification not found"))?;
 
     Ok(Json(verification))
 }
@@ -785,7 +798,7 @@ pub async fn approve_verification_enhanced(
     State(state): State<crate::state::AppState>,
     Path(verification_id): Path<Uuid>,
     Json(payload): Json<ApproveVerificationRequest>,
-) -> Result<Json<VerificationResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> AppResult<Json<VerificationResponse>> {
     // Get verification details
     let verification = sqlx::query!(
         r#"
@@ -794,19 +807,11 @@ pub async fn approve_verification_enhanced(
         verification_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?
-    .ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({"error": "Verification not found"})),
-        )
-    })?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Verification not found"))?;
+
+    require_confirmed_school_email(&state, verification.user_id).await?;
+    require_accepted_document(&state, verification.user_id).await?;
 
     // Update student verification status
     let result = sqlx::query!(
@@ -823,12 +828,7 @@ pub async fn approve_verification_enhanced(
     )
     .fetch_one(&state.pool)
     .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Failed to update verification"})),
-        )
-    })?;
+    .context("Failed to update verification")?;
 
     // Update user role and verification status
     sqlx::query!(
@@ -842,12 +842,7 @@ pub async fn approve_verification_enhanced(
     )
     .execute(&state.pool)
     .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Failed to update user role"})),
-        )
-    })?;
+    .context("Failed to update user role")?;
 
     // Create or update student record
     sqlx::query!(
@@ -864,13 +859,7 @@ pub async fn approve_verification_enhanced(
     )
     .execute(&state.pool)
     .await
-    .map_err(|e| {
-        eprintln!("Database error creating student record: {:?}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": format!("Failed to create student record: {}", e)})),
-        )
-    })?;
+    .context("Failed to create student record")?;
 
     // Create or update student profile
     let profile_result = sqlx::query!(
@@ -927,6 +916,13 @@ pub async fn approve_verification_enhanced(
     .execute(&state.pool)
     .await;
 
+    if let Err(e) = crate::services::email::queue_verification_decision(&state.pool, verification_id, result.user_id, true, payload.message.clone()).await {
+        tracing::error!("Failed to queue the verification decision email for {}: {}", verification_id, e);
+    }
+    if let Err(e) = crate::services::outgoing_webhooks::queue_verification_approved(&state.pool, verification_id, result.user_id).await {
+        tracing::error!("Failed to queue verification webhooks for {}: {}", verification_id, e);
+    }
+
     Ok(Json(VerificationResponse {
         verification_id,
         status: "verified".to_string(),
@@ -950,7 +946,7 @@ pub async fn reject_verification_enhanced(
     State(state): State<crate::state::AppState>,
     Path(verification_id): Path<Uuid>,
     Json(payload): Json<RejectVerificationRequest>,
-) -> Result<Json<VerificationResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> AppResult<Json<VerificationResponse>> {
     // Get verification details
     let verification = sqlx::query!(
         r#"
@@ -959,19 +955,8 @@ pub async fn reject_verification_enhanced(
         verification_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?
-    .ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({"error": "Verification not found"})),
-        )
-    })?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Verification not found"))?;
 
     // Update verification status to rejected
     let _ = sqlx::query!(
@@ -986,12 +971,7 @@ pub async fn reject_verification_enhanced(
     )
     .execute(&state.pool)
     .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Failed to update verification"})),
-        )
-    })?;
+    .context("Failed to update verification")?;
 
     // Update user verification status
     sqlx::query!(
@@ -1004,12 +984,7 @@ pub async fn reject_verification_enhanced(
     )
     .execute(&state.pool)
     .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Failed to update user status"})),
-        )
-    })?;
+    .context("Failed to update user status")?;
 
     // Add to verification history
     let _ = sqlx::query!(
@@ -1042,6 +1017,10 @@ pub async fn reject_verification_enhanced(
     .execute(&state.pool)
     .await;
 
+    if let Err(e) = crate::services::email::queue_verification_decision(&state.pool, verification_id, verification.user_id, false, Some(payload.reason.clone())).await {
+        tracing::error!("Failed to queue the verification decision email for {}: {}", verification_id, e);
+    }
+
     Ok(Json(VerificationResponse {
         verification_id,
         status: "rejected".to_string(),
@@ -1049,4 +1028,99 @@ pub async fn reject_verification_enhanced(
     }))
 }
 
+#[derive(Deserialize)]
+pub struct MobilePayoutsQuery {
+    pub status: Option<String>,
+}
 
+/// Mobile money milestone payouts, newest first; defaults to those awaiting approval
+pub async fn list_mobile_payouts(
+    State(state): State<crate::state::AppState>,
+    axum::extract::Query(query): axum::extract::Query<MobilePayoutsQuery>,
+) -> AppResult<Json<Vec<crate::services::mobile_payouts::MobilePayout>>> {
+    let status = query.status.unwrap_or_else(|| "pending_approval".to_string());
+    let payouts = sqlx::query_as!(
+        crate::services::mobile_payouts::MobilePayout,
+        r#"
+        SELECT id, project_id, milestone_id, student_id, phone_number,
+               amount_xlm as "amount_xlm: Stroops", amount_kes as "amount_kes: crate::utils::money::Cents", status,
+               conversation_id, receipt, result_description, created_at
+        FROM mobile_payouts
+        WHERE status = $1
+        ORDER BY created_at DESC
+        LIMIT 200
+        "#,
+        status
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(payouts))
+}
+
+/// Approve a pending mobile money payout and send it through M-Pesa B2C
+pub async fn approve_mobile_payout(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    Path(payout_id): Path<Uuid>,
+) -> AppResult<Json<serde_json::Value>> {
+    let mpesa = state
+        .payment_providers
+        .mpesa_config()
+        .filter(|c| c.b2c.is_some())
+        .map(crate::routes::payments::mpesa::MpesaProvider::new)
+        .ok_or_else(|| AppError::Unavailable("Mobile money payouts are not configured".to_string()))?;
+
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers).ok();
+    let payout = crate::services::mobile_payouts::approve_payout(&state.pool, &mpesa, payout_id, admin_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to send mobile payout {}: {}", payout_id, e);
+            AppError::Upstream(e.to_string())
+        })?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        "mobile_payout_approved",
+        payout.id,
+        "mobile_payout",
+        serde_json::json!({
+            "milestone_id": payout.milestone_id,
+            "amount_kes": payout.amount_kes,
+            "conversation_id": payout.conversation_id
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(serde_json::json!({
+        "payout_id": payout.id,
+        "status": payout.status,
+        "conversation_id": payout.conversation_id
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct RejectMobilePayoutRequest {
+    pub reason: Option<String>,
+}
+
+pub async fn reject_mobile_payout(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    Path(payout_id): Path<Uuid>,
+    Json(req): Json<RejectMobilePayoutRequest>,
+) -> AppResult<Json<ApiMessage>> {
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers).ok();
+    let rejected = crate::services::mobile_payouts::reject_payout(&state.pool, payout_id, admin_id, req.reason.as_deref())
+        .await?;
+
+    if !rejected {
+        return Err(AppError::not_found("Payout not found or already decided"));
+    }
+    Ok(Json(ApiMessage { message: "Payout rejected".to_string() }))
+}
@@ -2,8 +2,7 @@ use axum::{extract::{State, Path, Query}, Json, http::StatusCode};
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc, Duration};
-use sqlx::types::BigDecimal;
-use num_traits::cast::ToPrimitive;
+use crate::utils::money::Stroops;
 
 #[derive(Serialize)]
 pub struct ApiMessage { 
@@ -17,13 +16,26 @@ pub struct DateRangeQuery {
     pub limit: Option<i64>,
 }
 
+#[derive(Deserialize)]
+pub struct TopProjectsQuery {
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    /// Category slug to rank within
+    pub category: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct ProjectAnalytics {
     pub project_id: Uuid,
     pub title: String,
-    pub total_donations: f64,
+    /// Confirmed donations before platform fees
+    pub total_donations: Stroops,
+    pub total_fees: Stroops,
+    /// What the project receives after platform fees
+    pub net_donations: Stroops,
     pub donation_count: i64,
-    pub funding_goal: f64,
+    pub funding_goal: Stroops,
     pub funding_percentage: f64,
     pub created_at: DateTime<Utc>,
 }
@@ -32,18 +44,28 @@ pub struct ProjectAnalytics {
 pub struct StudentAnalytics {
     pub student_id: Uuid,
     pub username: String,
-    pub total_donations_received: f64,
+    pub total_donations_received: Stroops,
     pub project_count: i64,
     pub active_projects: i64,
     pub verification_status: String,
 }
 
+#[derive(Serialize)]
+pub struct DonorAnalytics {
+    /// Empty on the row that stands in for anonymous donors
+    pub donor_id: Option<Uuid>,
+    pub username: Option<String>,
+    pub anonymous: bool,
+    pub total_donated: Stroops,
+    pub donation_count: i64,
+}
+
 #[derive(Serialize)]
 pub struct CampaignAnalytics {
     pub campaign_id: Uuid,
     pub name: String,
-    pub reward_pool_xlm: f64,
-    pub distributed_amount: f64,
+    pub reward_pool_xlm: Stroops,
+    pub distributed_amount: Stroops,
     pub recipient_count: i64,
     pub status: String,
     pub created_at: DateTime<Utc>,
@@ -53,7 +75,7 @@ pub struct CampaignAnalytics {
 pub struct DonationTrend {
     pub date: String,
     pub count: i64,
-    pub total_amount: f64,
+    pub total_amount: Stroops,
 }
 
 #[derive(Serialize)]
@@ -62,15 +84,15 @@ pub struct PlatformStats {
     pub verified_students: i64,
     pub total_projects: i64,
     pub active_projects: i64,
-    pub total_donations: f64,
+    pub total_donations: Stroops,
     pub total_campaigns: i64,
     pub active_campaigns: i64,
-    pub total_reward_pool: f64,
+    pub total_reward_pool: Stroops,
 }
 
 pub async fn top_projects(
     State(state): State<crate::state::AppState>, 
-    Query(params): Query<DateRangeQuery>
+    Query(params): Query<TopProjectsQuery>
 ) -> Result<Json<Vec<ProjectAnalytics>>, StatusCode> {
     let limit = params.limit.unwrap_or(10);
     let start_date = params.start_date.unwrap_or(Utc::now() - Duration::days(30));
@@ -81,29 +103,31 @@ pub async fn top_projects(
         SELECT 
             p.id as project_id,
             p.title,
-            p.funding_goal,
+            p.funding_goal as "funding_goal: Stroops",
             p.created_at,
-            COALESCE(SUM(d.amount), 0) as total_donations,
+            COALESCE(SUM(d.amount), 0) as "total_donations!: Stroops",
+            COALESCE(SUM(f.fee_amount), 0) as "total_fees!: Stroops",
             COUNT(d.id) as donation_count
         FROM projects p
         LEFT JOIN donations d ON p.id = d.project_id 
             AND d.status = 'confirmed'
             AND d.created_at >= $1 
             AND d.created_at <= $2
+        LEFT JOIN donation_fees f ON f.donation_id = d.id
+        WHERE $4::text IS NULL OR EXISTS (
+            SELECT 1 FROM project_categories pc JOIN categories c ON c.id = pc.category_id
+            WHERE pc.project_id = p.id AND c.slug = $4
+        )
         GROUP BY p.id, p.title, p.funding_goal, p.created_at
-        ORDER BY total_donations DESC
+        ORDER BY COALESCE(SUM(d.amount), 0) DESC
         LIMIT $3
         "#,
-        start_date, end_date, limit
+        start_date, end_date, limit, params.category.as_deref()
     ).fetch_all(&state.pool).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     let analytics: Vec<ProjectAnalytics> = rows.into_iter().map(|r| {
-        let total_donations = r.total_donations.unwrap_or(BigDecimal::from(0));
-        let funding_goal = r.funding_goal.clone();
-        
-        let funding_percentage = if funding_goal > BigDecimal::from(0) {
-            let percentage = (total_donations.clone() / funding_goal.clone()) * BigDecimal::from(100);
-            percentage.to_f64().unwrap_or(0.0)
+        let funding_percentage = if r.funding_goal.is_positive() {
+            r.total_donations.as_stroops() as f64 / r.funding_goal.as_stroops() as f64 * 100.0
         } else {
             0.0
         };
@@ -111,9 +135,11 @@ pub async fn top_projects(
         ProjectAnalytics {
             project_id: r.project_id,
             title: r.title,
-            total_donations: total_donations.to_f64().unwrap_or(0.0),
+            total_donations: r.total_donations,
+            net_donations: r.total_donations - r.total_fees,
+            total_fees: r.total_fees,
             donation_count: r.donation_count.unwrap_or(0),
-            funding_goal: funding_goal.to_f64().unwrap_or(0.0),
+            funding_goal: r.funding_goal,
             funding_percentage,
             created_at: r.created_at,
         }
@@ -136,7 +162,7 @@ pub async fn top_students(
             s.id as student_id,
             u.username,
             s.verification_status,
-            COALESCE(SUM(d.amount), 0) as total_donations_received,
+            COALESCE(SUM(d.amount), 0) as "total_donations_received!: Stroops",
             COUNT(DISTINCT p.id) as project_count,
             COUNT(DISTINCT CASE WHEN p.created_at >= NOW() - INTERVAL '30 days' THEN p.id END) as active_projects
         FROM students s
@@ -147,7 +173,7 @@ pub async fn top_students(
             AND d.created_at >= $1 
             AND d.created_at <= $2
         GROUP BY s.id, u.username, s.verification_status
-        ORDER BY total_donations_received DESC
+        ORDER BY COALESCE(SUM(d.amount), 0) DESC
         LIMIT $3
         "#,
         start_date, end_date, limit
@@ -157,7 +183,7 @@ pub async fn top_students(
         StudentAnalytics {
             student_id: r.student_id,
             username: r.username,
-            total_donations_received: r.total_donations_received.unwrap_or(BigDecimal::from(0)).to_f64().unwrap_or(0.0),
+            total_donations_received: r.total_donations_received,
             project_count: r.project_count.unwrap_or(0),
             active_projects: r.active_projects.unwrap_or(0),
             verification_status: r.verification_status,
@@ -167,6 +193,52 @@ pub async fn top_students(
     Ok(Json(analytics))
 }
 
+/// Donors who gave the most. Anonymous donations are pooled under a single
+/// row, except for admins, who see every donor.
+pub async fn top_donors(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    Query(params): Query<DateRangeQuery>
+) -> Result<Json<Vec<DonorAnalytics>>, StatusCode> {
+    let limit = params.limit.unwrap_or(10);
+    let start_date = params.start_date.unwrap_or(Utc::now() - Duration::days(30));
+    let end_date = params.end_date.unwrap_or(Utc::now());
+    let is_admin = crate::utils::roles::caller_is_admin(&state.pool, &headers).await;
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT 
+            CASE WHEN d.is_anonymous AND NOT $4 THEN NULL ELSE d.donor_id END as donor_id,
+            CASE WHEN d.is_anonymous AND NOT $4 THEN NULL ELSE u.username END as username,
+            (d.is_anonymous AND NOT $4) as "anonymous!",
+            COALESCE(SUM(d.amount), 0) as "total_donated!: Stroops",
+            COUNT(d.id) as donation_count
+        FROM donations d
+        LEFT JOIN users u ON u.id = d.donor_id
+        WHERE d.status = 'confirmed'
+            AND d.donor_id IS NOT NULL
+            AND d.created_at >= $1 
+            AND d.created_at <= $2
+        GROUP BY 1, 2, 3
+        ORDER BY COALESCE(SUM(d.amount), 0) DESC
+        LIMIT $3
+        "#,
+        start_date, end_date, limit, is_admin
+    ).fetch_all(&state.pool).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let analytics: Vec<DonorAnalytics> = rows.into_iter().map(|r| {
+        DonorAnalytics {
+            donor_id: r.donor_id,
+            username: r.username,
+            anonymous: r.anonymous,
+            total_donated: r.total_donated,
+            donation_count: r.donation_count.unwrap_or(0),
+        }
+    }).collect();
+
+    Ok(Json(analytics))
+}
+
 pub async fn campaign_performance(
     State(state): State<crate::state::AppState>,
     Query(params): Query<DateRangeQuery>
@@ -180,10 +252,10 @@ pub async fn campaign_performance(
         SELECT 
             c.id as campaign_id,
             c.name,
-            c.reward_pool_xlm,
+            c.reward_pool_xlm as "reward_pool_xlm: Stroops",
             c.status,
             c.created_at,
-            COALESCE(SUM(cd.amount), 0) as distributed_amount,
+            COALESCE(SUM(cd.amount), 0) as "distributed_amount!: Stroops",
             COUNT(DISTINCT cd.recipient_id) as recipient_count
         FROM campaigns c
         LEFT JOIN campaign_distributions cd ON c.id = cd.campaign_id
@@ -191,7 +263,7 @@ pub async fn campaign_performance(
             AND cd.created_at <= $2
         WHERE c.created_at >= $1 AND c.created_at <= $2
         GROUP BY c.id, c.name, c.reward_pool_xlm, c.status, c.created_at
-        ORDER BY distributed_amount DESC
+        ORDER BY COALESCE(SUM(cd.amount), 0) DESC
         LIMIT $3
         "#,
         start_date, end_date, limit
@@ -202,7 +274,7 @@ pub async fn campaign_performance(
             campaign_id: r.campaign_id,
             name: r.name,
             reward_pool_xlm: r.reward_pool_xlm,
-            distributed_amount: r.distributed_amount.unwrap_or(0.0),
+            distributed_amount: r.distributed_amount,
             recipient_count: r.recipient_count.unwrap_or(0),
             status: r.status,
             created_at: r.created_at,
@@ -224,7 +296,7 @@ pub async fn donation_trends(
         SELECT 
             DATE(created_at) as donation_date,
             COUNT(*) as count,
-            SUM(amount) as total_amount
+            SUM(amount) as "total_amount!: Stroops"
         FROM donations 
         WHERE status = 'confirmed'
             AND created_at >= $1 
@@ -239,7 +311,7 @@ pub async fn donation_trends(
         DonationTrend {
             date: r.donation_date.unwrap_or(chrono::Utc::now().date_naive()).format("%Y-%m-%d").to_string(),
             count: r.count.unwrap_or(0),
-            total_amount: r.total_amount.unwrap_or(BigDecimal::from(0)).to_f64().unwrap_or(0.0),
+            total_amount: r.total_amount,
         }
     }).collect();
 
@@ -256,10 +328,10 @@ pub async fn platform_stats(
             (SELECT COUNT(*) FROM students WHERE verification_status = 'verified') as verified_students,
             (SELECT COUNT(*) FROM projects) as total_projects,
             (SELECT COUNT(*) FROM projects WHERE created_at >= NOW() - INTERVAL '30 days') as active_projects,
-            (SELECT COALESCE(SUM(amount), 0) FROM donations WHERE status = 'confirmed') as total_donations,
+            (SELECT COALESCE(SUM(amount), 0) FROM donations WHERE status = 'confirmed') as "total_donations!: Stroops",
             (SELECT COUNT(*) FROM campaigns WHERE status != 'deleted') as total_campaigns,
             (SELECT COUNT(*) FROM campaigns WHERE status = 'active') as active_campaigns,
-            (SELECT COALESCE(SUM(reward_pool_xlm), 0) FROM campaigns WHERE status = 'active') as total_reward_pool
+            (SELECT COALESCE(SUM(reward_pool_xlm), 0) FROM campaigns WHERE status = 'active') as "total_reward_pool!: Stroops"
         "#
     ).fetch_one(&state.pool).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -268,10 +340,10 @@ pub async fn platform_stats(
         verified_students: stats.verified_students.unwrap_or(0),
         total_projects: stats.total_projects.unwrap_or(0),
         active_projects: stats.active_projects.unwrap_or(0),
-        total_donations: stats.total_donations.unwrap_or(BigDecimal::from(0)).to_f64().unwrap_or(0.0),
+        total_donations: stats.total_donations,
         total_campaigns: stats.total_campaigns.unwrap_or(0),
         active_campaigns: stats.active_campaigns.unwrap_or(0),
-        total_reward_pool: stats.total_reward_pool.unwrap_or(0.0),
+        total_reward_pool: stats.total_reward_pool,
     }))
 }
 
@@ -284,12 +356,14 @@ pub async fn project_analytics(
         SELECT 
             p.id as project_id,
             p.title,
-            p.funding_goal,
+            p.funding_goal as "funding_goal: Stroops",
             p.created_at,
-            COALESCE(SUM(d.amount), 0) as total_donations,
+            COALESCE(SUM(d.amount), 0) as "total_donations!: Stroops",
+            COALESCE(SUM(f.fee_amount), 0) as "total_fees!: Stroops",
             COUNT(d.id) as donation_count
         FROM projects p
         LEFT JOIN donations d ON p.id = d.project_id AND d.status = 'confirmed'
+        LEFT JOIN donation_fees f ON f.donation_id = d.id
         WHERE p.id = $1
         GROUP BY p.id, p.title, p.funding_goal, p.created_at
         "#,
@@ -298,12 +372,8 @@ pub async fn project_analytics(
 
     match row {
         Some(r) => {
-            let total_donations = r.total_donations.unwrap_or(BigDecimal::from(0));
-            let funding_goal = r.funding_goal.clone();
-            
-            let funding_percentage = if funding_goal > BigDecimal::from(0) {
-                let percentage = (total_donations.clone() / funding_goal.clone()) * BigDecimal::from(100);
-                percentage.to_f64().unwrap_or(0.0)
+            let funding_percentage = if r.funding_goal.is_positive() {
+                r.total_donations.as_stroops() as f64 / r.funding_goal.as_stroops() as f64 * 100.0
             } else {
                 0.0
             };
@@ -311,9 +381,11 @@ pub async fn project_analytics(
             Ok(Json(ProjectAnalytics {
                 project_id: r.project_id,
                 title: r.title,
-                total_donations: total_donations.to_f64().unwrap_or(0.0),
+                total_donations: r.total_donations,
+                net_donations: r.total_donations - r.total_fees,
+                total_fees: r.total_fees,
                 donation_count: r.donation_count.unwrap_or(0),
-                funding_goal: funding_goal.to_f64().unwrap_or(0.0),
+                funding_goal: r.funding_goal,
                 funding_percentage,
                 created_at: r.created_at,
             }))
@@ -332,7 +404,7 @@ pub async fn student_analytics(
             s.id as student_id,
             u.username,
             s.verification_status,
-            COALESCE(SUM(d.amount), 0) as total_donations_received,
+            COALESCE(SUM(d.amount), 0) as "total_donations_received!: Stroops",
             COUNT(DISTINCT p.id) as project_count,
             COUNT(DISTINCT CASE WHEN p.created_at >= NOW() - INTERVAL '30 days' THEN p.id END) as active_projects
         FROM students s
@@ -350,7 +422,7 @@ pub async fn student_analytics(
             Ok(Json(StudentAnalytics {
                 student_id: r.student_id,
                 username: r.username,
-                total_donations_received: r.total_donations_received.unwrap_or(BigDecimal::from(0)).to_f64().unwrap_or(0.0),
+                total_donations_received: r.total_donations_received,
                 project_count: r.project_count.unwrap_or(0),
                 active_projects: r.active_projects.unwrap_or(0),
                 verification_status: r.verification_status,
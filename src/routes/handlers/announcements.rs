@@ -0,0 +1,174 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::ValidatedJson;
+use crate::services::announcements::{
+    self, Announcement, AnnouncementError, AnnouncementWithStats, NewAnnouncement, COHORTS, KINDS, ROLES,
+};
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::state::AppState;
+use crate::utils::pagination::{Page, PageRequest};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateAnnouncementRequest {
+    #[validate(length(min = 1, max = 200, message = "Title must be 1 to 200 characters"))]
+    pub title: String,
+    #[validate(length(min = 1, max = 5000, message = "Body must be 1 to 5000 characters"))]
+    pub body: String,
+    /// `general` (the default), `maintenance` or `campaign`
+    pub kind: Option<String>,
+    /// Roles to reach; leave out for everyone
+    pub audience_roles: Option<Vec<String>>,
+    /// `verified_students`, `unverified_students`, `donors` or `project_owners`
+    pub cohort: Option<String>,
+    /// Also email it to recipients who haven't read it by the next digest
+    pub email_digest: Option<bool>,
+    /// The window a maintenance announcement is about
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    /// When to send it; leave out to send now
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnnouncementsQuery {
+    /// `scheduled`, `sent` or `cancelled`
+    pub status: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+fn one_of<'a>(field: &str, value: &str, allowed: &[&'a str]) -> AppResult<&'a str> {
+    allowed
+        .iter()
+        .find(|a| **a == value)
+        .copied()
+        .ok_or_else(|| AppError::invalid(field, format!("Must be one of {}", allowed.join(", "))))
+}
+
+fn admin(headers: &HeaderMap) -> AppResult<Uuid> {
+    crate::utils::jwt::extract_user_id_from_headers(headers).map_err(|_| AppError::unauthorized("Authentication required"))
+}
+
+async fn log_activity(state: &AppState, admin_id: Uuid, action: &str, announcement: &Announcement) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        action,
+        announcement.id,
+        "announcement",
+        serde_json::json!({
+            "title": announcement.title,
+            "status": announcement.status,
+            "publish_at": announcement.publish_at,
+            "recipients": announcement.recipients,
+        })
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+/// Announcements, newest first, with how many recipients have read each
+pub async fn list_announcements(
+    State(state): State<AppState>,
+    Query(query): Query<AnnouncementsQuery>,
+) -> AppResult<Json<Page<AnnouncementWithStats>>> {
+    let status = query.status.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    if let Some(status) = status {
+        one_of("status", status, &["scheduled", "sent", "cancelled"])?;
+    }
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
+    Ok(Json(announcements::list(&state.pool, status, &page).await?))
+}
+
+pub async fn get_announcement(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<AnnouncementWithStats>> {
+    let announcement = announcements::get(&state.pool, id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Announcement not found"))?;
+    Ok(Json(announcement))
+}
+
+/// Compose an announcement, sent now or at `publish_at` as a notification to
+/// everyone in its audience
+pub async fn create_announcement(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<CreateAnnouncementRequest>,
+) -> AppResult<(StatusCode, Json<Announcement>)> {
+    let admin_id = admin(&headers)?;
+    let kind = one_of("kind", req.kind.as_deref().map(str::trim).unwrap_or("general"), KINDS)?;
+    let cohort = match req.cohort.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+        Some(cohort) => Some(one_of("cohort", cohort, COHORTS)?),
+        None => None,
+    };
+    let mut audience_roles = Vec::new();
+    for role in req.audience_roles.iter().flatten() {
+        let role = one_of("audience_roles", &role.trim().to_lowercase(), ROLES)?.to_string();
+        if !audience_roles.contains(&role) {
+            audience_roles.push(role);
+        }
+    }
+    if let (Some(starts_at), Some(ends_at)) = (req.starts_at, req.ends_at) {
+        if ends_at <= starts_at {
+            return Err(AppError::invalid("ends_at", "The window must end after it starts"));
+        }
+    }
+    let publish_at = req.publish_at.filter(|at| *at > Utc::now());
+
+    let new = NewAnnouncement {
+        title: req.title.trim(),
+        body: req.body.trim(),
+        kind,
+        audience_roles: &audience_roles,
+        cohort,
+        email_digest: req.email_digest.unwrap_or(false),
+        starts_at: req.starts_at,
+        ends_at: req.ends_at,
+        publish_at,
+    };
+    let mut announcement = announcements::create(&state.pool, &new, admin_id).await?;
+    if publish_at.is_none() {
+        if let Some(sent) = announcements::deliver(&state.pool, announcement.id).await? {
+            let users = announcements::recipients(&state.pool, sent.id).await?;
+            let event = NotificationEvent::Announcement { announcement_id: sent.id };
+            state.notifier.send_all(users.into_iter().map(Channel::User), event);
+            announcement = sent;
+        }
+    }
+
+    log_activity(&state, admin_id, "announcement_created", &announcement).await;
+    Ok((StatusCode::CREATED, Json(announcement)))
+}
+
+/// Call off an announcement that hasn't been sent yet
+pub async fn cancel_announcement(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<Announcement>> {
+    let admin_id = admin(&headers)?;
+    let cancelled = announcements::cancel(&state.pool, id)
+        .await
+        .map_err(|e| match e {
+            AnnouncementError::NotScheduled => AppError::conflict(e.to_string()),
+            AnnouncementError::Internal(e) => AppError::Internal(e),
+        })?
+        .ok_or_else(|| AppError::not_found("Announcement not found"))?;
+
+    log_activity(&state, admin_id, "announcement_cancelled", &cancelled).await;
+    Ok(Json(cancelled))
+}
@@ -0,0 +1,97 @@
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, Json};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::{self, ValidatedJson};
+use crate::services::api_keys::{self, ApiKey, IssuedKey, NewKey};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    /// Who the key is for, e.g. the partner university
+    #[validate(
+        custom(function = "validation::not_blank"),
+        length(max = 100, message = "Name must be at most 100 characters")
+    )]
+    pub name: String,
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    pub scopes: Vec<String>,
+    #[validate(range(min = 1, max = 10000, message = "Rate limit must be between 1 and 10000 requests a minute"))]
+    pub rate_limit_per_minute: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+async fn log_activity(state: &crate::state::AppState, admin_id: Uuid, action: &str, key: &ApiKey) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        action,
+        key.id,
+        "api_key",
+        serde_json::json!({"name": key.name, "key_prefix": key.key_prefix, "scopes": key.scopes})
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+pub async fn list_api_keys(State(state): State<crate::state::AppState>) -> AppResult<Json<Vec<ApiKey>>> {
+    Ok(Json(api_keys::list(&state.pool).await?))
+}
+
+/// Issue a key for a partner integration; the response is the only time the key is shown
+pub async fn create_api_key(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<CreateApiKeyRequest>,
+) -> AppResult<(StatusCode, Json<IssuedKey>)> {
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let unknown = api_keys::unknown_scopes(&req.scopes);
+    if !unknown.is_empty() {
+        return Err(AppError::invalid(
+            "scopes",
+            format!("Unknown scopes {}; use {}", unknown.join(", "), api_keys::ALL_SCOPES.join(", ")),
+        ));
+    }
+    if req.expires_at.is_some_and(|expires| expires <= Utc::now()) {
+        return Err(AppError::invalid("expires_at", "Expiry must be in the future"));
+    }
+
+    let issued = api_keys::issue(
+        &state.pool,
+        &NewKey {
+            name: req.name.trim(),
+            scopes: &req.scopes,
+            rate_limit_per_minute: req.rate_limit_per_minute,
+            expires_at: req.expires_at,
+            created_by: admin_id,
+        },
+    )
+    .await?;
+
+    log_activity(&state, admin_id, "api_key_issued", &issued.api_key).await;
+    Ok((StatusCode::CREATED, Json(issued)))
+}
+
+/// Revoked keys stop working immediately
+pub async fn revoke_api_key(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<ApiKey>> {
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let key = api_keys::revoke(&state.pool, id, admin_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("API key not found or already revoked"))?;
+
+    log_activity(&state, admin_id, "api_key_revoked", &key).await;
+    Ok(Json(key))
+}
@@ -0,0 +1,157 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::approvals::{self, ApprovalError, ApprovalRequest};
+use crate::state::AppState;
+use crate::utils::jwt::{self, Claims};
+
+type ApprovalResult<T> = Result<Json<T>, (StatusCode, Json<serde_json::Value>)>;
+
+fn approval_error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({"error": message})))
+}
+
+fn map_error(e: ApprovalError) -> (StatusCode, Json<serde_json::Value>) {
+    match e {
+        ApprovalError::NotFound => approval_error(StatusCode::NOT_FOUND, &e.to_string()),
+        ApprovalError::SelfApproval | ApprovalError::NotPermitted => {
+            approval_error(StatusCode::FORBIDDEN, &e.to_string())
+        }
+        ApprovalError::NotOpen(_) | ApprovalError::Mismatch => approval_error(StatusCode::CONFLICT, &e.to_string()),
+        ApprovalError::Internal(e) => internal(e),
+    }
+}
+
+fn internal(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    tracing::error!("Approval request error: {}", e);
+    approval_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to process approval request")
+}
+
+fn caller(headers: &HeaderMap) -> Result<Claims, (StatusCode, Json<serde_json::Value>)> {
+    jwt::extract_claims_from_headers(headers)
+        .map_err(|_| approval_error(StatusCode::UNAUTHORIZED, "Authentication required"))
+}
+
+async fn log_activity(state: &AppState, admin_id: Uuid, action: &str, request: &ApprovalRequest) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        action,
+        request.id,
+        "approval_request",
+        serde_json::json!({
+            "action": request.action,
+            "payload": request.payload,
+            "initiated_by": request.initiated_by,
+            "status": request.status
+        })
+    )
+    .execute(&state.pool)
+    .await;
+    let event = NotificationEvent::ApprovalStatus { approval_id: request.id, status: request.status.clone() };
+    state.notifier.send(Channel::Admins, event);
+}
+
+/// `?approval_id=` on a fund-moving endpoint, to run an approved request
+#[derive(Debug, Default, Deserialize)]
+pub struct ApprovalQuery {
+    pub approval_id: Option<Uuid>,
+}
+
+/// Dual control for fund-moving handlers. Without an approval id the call
+/// opens an approval request and the returned 202 should be sent as is;
+/// with one, the approval is spent and the handler goes ahead (`None`).
+pub(crate) async fn dual_control(
+    state: &AppState,
+    headers: &HeaderMap,
+    action: &str,
+    approval_id: Option<Uuid>,
+    payload: serde_json::Value,
+) -> Result<Option<Response>, (StatusCode, Json<serde_json::Value>)> {
+    let admin = caller(headers)?;
+
+    let Some(approval_id) = approval_id else {
+        let request = approvals::initiate(&state.pool, action, &payload, admin.sub).await.map_err(internal)?;
+        log_activity(state, admin.sub, "approval_requested", &request).await;
+        let body = serde_json::json!({
+            "approval_required": true,
+            "message": "A second admin must approve this at /api/admin/approvals, then repeat the request with ?approval_id=",
+            "approval": request
+        });
+        return Ok(Some((StatusCode::ACCEPTED, Json(body)).into_response()));
+    };
+
+    let request = approvals::consume(&state.pool, approval_id, action, &payload).await.map_err(map_error)?;
+    log_activity(state, admin.sub, "approval_executed", &request).await;
+    Ok(None)
+}
+
+#[derive(Deserialize)]
+pub struct ApprovalsQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct DecisionRequest {
+    pub note: Option<String>,
+}
+
+/// Approval requests, newest first, optionally by status
+pub async fn list_approvals(
+    State(state): State<AppState>,
+    Query(query): Query<ApprovalsQuery>,
+) -> ApprovalResult<Vec<ApprovalRequest>> {
+    approvals::list(&state.pool, query.status.as_deref()).await.map(Json).map_err(internal)
+}
+
+pub async fn get_approval(State(state): State<AppState>, Path(id): Path<Uuid>) -> ApprovalResult<ApprovalRequest> {
+    approvals::get(&state.pool, id)
+        .await
+        .map_err(internal)?
+        .map(Json)
+        .ok_or_else(|| map_error(ApprovalError::NotFound))
+}
+
+pub async fn approve_request(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    body: Option<Json<DecisionRequest>>,
+) -> ApprovalResult<ApprovalRequest> {
+    decide(&state, &headers, id, true, body.map(|Json(b)| b).unwrap_or_default()).await
+}
+
+pub async fn reject_request(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    body: Option<Json<DecisionRequest>>,
+) -> ApprovalResult<ApprovalRequest> {
+    decide(&state, &headers, id, false, body.map(|Json(b)| b).unwrap_or_default()).await
+}
+
+async fn decide(
+    state: &AppState,
+    headers: &HeaderMap,
+    id: Uuid,
+    approve: bool,
+    body: DecisionRequest,
+) -> ApprovalResult<ApprovalRequest> {
+    let admin = caller(headers)?;
+    let note = body.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    let request = approvals::decide(&state.pool, id, &admin, approve, note).await.map_err(map_error)?;
+
+    let action = if approve { "approval_granted" } else { "approval_rejected" };
+    log_activity(state, admin.sub, action, &request).await;
+    Ok(Json(request))
+}
@@ -1,30 +1,52 @@
+use anyhow::Context;
+use std::net::SocketAddr;
 use axum::{
-    extract::{Json, State, Path, Query},
-    http::StatusCode,
+    extract::{ConnectInfo, Json, State, Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use validator::Validate;
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
     Argon2,
 };
 use argon2::password_hash::{PasswordVerifier, PasswordHash};
-use chrono::{Duration, Utc};
-use rand::Rng;
+use chrono::Utc;
 
 use crate::models::{User, UserRole, UserStatus, BaseRole};
-
-#[derive(Debug, Deserialize)]
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::{self, ValidatedJson};
+use crate::services::email_verification;
+use crate::services::login_throttle::{self, Attempt, LoginGate, ThrottlePolicy};
+use crate::services::password_reset::{self, ResetError};
+use crate::services::sessions::{self, SessionError};
+use crate::services::two_factor;
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct SignupRequest {
+    #[validate(
+        custom(function = "validation::not_blank"),
+        length(max = 50, message = "Username must be at most 50 characters")
+    )]
     pub username: String,
+    #[validate(email(message = "Enter a valid email address"))]
     pub email: String,
+    #[validate(custom(function = "validation::password"))]
     pub password: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct LoginRequest {
+    // Not format-checked so accounts created before signup validation can still sign in
+    #[validate(custom(function = "validation::not_blank"))]
     pub email: String,
+    #[validate(length(min = 1, message = "Password is required"))]
     pub password: String,
+    /// Needed after repeated failures when a CAPTCHA provider is configured
+    #[serde(default)]
+    pub captcha_token: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -33,6 +55,8 @@ pub struct SignupResponse {
     pub username: String,
     pub email: String,
     pub status: String,
+    /// Guest donations made with this email, claimable via `POST /api/guest/claim`
+    pub claimable_guest_donations: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -42,6 +66,18 @@ pub struct AuthResponse {
     pub expires_in: i64,
 }
 
+/// A session, or the pre-auth token for a login still owing its second factor
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Session(AuthResponse),
+    TwoFactorRequired {
+        two_factor_required: bool,
+        pre_auth_token: String,
+        expires_in: i64,
+    },
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RefreshTokenRequest {
     pub refresh_token: String,
@@ -69,17 +105,17 @@ pub struct ProfileResponse {
 
 pub async fn signup(
     State(state): State<crate::state::AppState>,
-    Json(payload): Json<SignupRequest>,
-) -> Result<(StatusCode, Json<SignupResponse>), StatusCode> {
+    ValidatedJson(payload): ValidatedJson<SignupRequest>,
+) -> AppResult<(StatusCode, Json<SignupResponse>)> {
     // Hash password
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
     let password_hash = argon2
         .hash_password(payload.password.as_bytes(), &salt)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| anyhow::anyhow!("Password hashing failed: {}", e))?
         .to_string();
 
-    // Create user with active status (skip email verification for now)
+    // New accounts can't sign in until their email is confirmed
     let user = sqlx::query!(
         r#"
         INSERT INTO users (username, email, password_hash, role, base_role, is_verified, status)
@@ -91,30 +127,94 @@ pub async fn signup(
         password_hash,
         "user",
         "base_user",
-        true,
-        "active",
+        false,
+        "pending_email_verification",
     )
     .fetch_one(&state.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            AppError::conflict("An account with that username or email already exists")
+        }
+        e => AppError::Internal(anyhow::Error::new(e).context("Failed to create user")),
+    })?;
 
-    // Skip email verification for now
+    if let Err(e) = email_verification::send(&state.pool, user.id, &user.username, &user.email).await {
+        // The user can ask for another link
+        tracing::error!("Failed to send the verification email to {}: {}", user.email, e);
+    }
     tracing::info!("User created successfully: {}", user.email);
 
+    let claimable_guest_donations = crate::services::guest_claims::claimable_count(&state.pool, &user.email)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to count guest donations for {}: {}", user.email, e);
+            0
+        });
+
     Ok((StatusCode::CREATED, Json(SignupResponse {
         id: user.id,
         username: user.username,
         email: user.email,
-        status: "active".to_string(),
+        status: "pending_email_verification".to_string(),
+        claimable_guest_donations,
     })))
 }
 
 pub async fn login(
     State(state): State<crate::state::AppState>,
-    Json(payload): Json<LoginRequest>,
-) -> Result<Json<AuthResponse>, StatusCode> {
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<LoginRequest>,
+) -> AppResult<Json<LoginResponse>> {
     tracing::info!("Login attempt for email: {}", payload.email);
-    
+
+    // Always has an address, so every login counts against its IP
+    let client = sessions::ClientInfo::from_request(&headers, Some(peer));
+    let email = crate::services::guest_claims::normalize_email(&payload.email);
+    let policy = ThrottlePolicy::from_env();
+
+    // Throttled logins are refused before the password is checked
+    let gate = login_throttle::check(&state.pool, &policy, &email, client.ip_address.as_deref())
+        .await
+        .context("Login throttling check failed")?;
+    match gate {
+        LoginGate::Throttled { retry_after_secs, locked: true } => {
+            return Err(AppError::Coded {
+                status: StatusCode::TOO_MANY_REQUESTS,
+                code: "account_locked",
+                message: "Account temporarily locked after repeated failed logins".to_string(),
+                details: Some(serde_json::json!({"retry_after_secs": retry_after_secs})),
+            });
+        }
+        LoginGate::Throttled { retry_after_secs, locked: false } => {
+            return Err(AppError::RateLimited {
+                message: "Too many failed logins; try again shortly".to_string(),
+                retry_after_secs,
+            });
+        }
+        LoginGate::Allowed { captcha_required: true } => {
+            if let Some(captcha) = &state.captcha {
+                let passed = match payload.captcha_token.as_deref() {
+                    Some(token) => captcha.verify(token, client.ip_address.as_deref()).await.map_err(|e| {
+                        tracing::error!("CAPTCHA verification failed: {}", e);
+                        AppError::Unavailable("CAPTCHA verification is unavailable".to_string())
+                    })?,
+                    None => false,
+                };
+                if !passed {
+                    return Err(AppError::Coded {
+                        status: StatusCode::UNAUTHORIZED,
+                        code: "captcha_required",
+                        message: "Complete the CAPTCHA to continue".to_string(),
+                        details: Some(serde_json::json!({"captcha_provider": captcha.provider()})),
+                    });
+                }
+            }
+        }
+        LoginGate::Allowed { captcha_required: false } => {}
+    }
+
     let user = sqlx::query_as!(
         User,
         r#"
@@ -132,78 +232,168 @@ pub async fn login(
     )
     .fetch_optional(&state.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Database error during login: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?
-    .ok_or_else(|| {
-        tracing::info!("User not found for email: {}", payload.email);
-        StatusCode::UNAUTHORIZED
-    })?;
+    .context("Failed to load user for login")?;
 
     // Verify password
-    let argon2 = Argon2::default();
-    let parsed = PasswordHash::new(&user.password_hash)
-        .map_err(|e| {
-            tracing::error!("Password hash parsing error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    let is_valid = argon2.verify_password(payload.password.as_bytes(), &parsed).is_ok();
+    let is_valid = match &user {
+        Some(user) => {
+            let parsed = PasswordHash::new(&user.password_hash)
+                .map_err(|e| anyhow::anyhow!("Stored password hash for {} is unreadable: {}", user.id, e))?;
+            Argon2::default().verify_password(payload.password.as_bytes(), &parsed).is_ok()
+        }
+        None => false,
+    };
+
+    let attempt = Attempt {
+        email: &email,
+        ip: client.ip_address.as_deref(),
+        user_id: user.as_ref().map(|u| u.id),
+        succeeded: is_valid,
+    };
+    let locked_until = login_throttle::record(&state.pool, &policy, attempt)
+        .await
+        .context("Failed to record login attempt")?;
+
+    let user = match user {
+        Some(user) if is_valid => user,
+        _ => {
+            tracing::info!("Invalid credentials for email: {}", payload.email);
+            if let Some(locked_until) = locked_until {
+                tracing::warn!("Locked account for {} until {}", payload.email, locked_until);
+            }
+            return Err(AppError::Coded {
+                status: StatusCode::UNAUTHORIZED,
+                code: "invalid_credentials",
+                message: "Invalid email or password".to_string(),
+                details: None,
+            });
+        }
+    };
+
+    if matches!(user.status, UserStatus::Suspended) {
+        tracing::info!("Login by suspended user: {}", user.id);
+        return Err(AppError::Coded {
+            status: StatusCode::FORBIDDEN,
+            code: "account_suspended",
+            message: "This account has been suspended".to_string(),
+            details: None,
+        });
+    }
 
-    if !is_valid {
-        tracing::info!("Invalid password for user: {}", payload.email);
-        return Err(StatusCode::UNAUTHORIZED);
+    if matches!(user.status, UserStatus::PendingEmailVerification) {
+        tracing::info!("Login before email verification for user: {}", user.id);
+        return Err(AppError::Coded {
+            status: StatusCode::FORBIDDEN,
+            code: "email_not_verified",
+            message: "Confirm your email address before signing in".to_string(),
+            details: None,
+        });
     }
 
     tracing::info!("Password verified for user: {}", user.id);
 
-    // Generate JWT access token
-    let access_token = crate::utils::jwt::create_token(&user.id)
-        .map_err(|e| {
-            tracing::error!("JWT token creation error: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    // Accounts with 2FA get a short-lived pre-auth token to trade for a
+    // session at /2fa/verify
+    let two_factor = two_factor::is_enabled(&state.pool, user.id)
+        .await
+        .context("Failed to check two-factor status")?;
+    if two_factor {
+        let pre_auth_token = two_factor::create_challenge(&state.pool, user.id)
+            .await
+            .context("Failed to create login challenge")?;
+        return Ok(Json(LoginResponse::TwoFactorRequired {
+            two_factor_required: true,
+            pre_auth_token,
+            expires_in: two_factor::CHALLENGE_TTL_MINUTES * 60,
+        }));
+    }
 
-    // Generate refresh token
-    let refresh_token = generate_random_token();
-    let refresh_token_hash = hash_token(&refresh_token);
-    let refresh_expires_at = Utc::now() + Duration::days(30);
+    let session = issue_session(&state.pool, user.id, &client).await?;
+    Ok(Json(LoginResponse::Session(session)))
+}
 
-    sqlx::query!(
+/// Access and refresh tokens for a user who has fully signed in, in a
+/// new device session
+pub(crate) async fn issue_session(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    client: &sessions::ClientInfo,
+) -> AppResult<AuthResponse> {
+    let refresh = sessions::start(pool, user_id, client).await.context("Failed to start session")?;
+
+    let roles = token_roles(pool, user_id).await?;
+    let access_token = crate::utils::jwt::create_token(&user_id, &roles, Some(refresh.session_id))
+        .context("Failed to create access token")?;
+
+    Ok(AuthResponse {
+        access_token,
+        refresh_token: refresh.token,
+        expires_in: crate::utils::jwt::ACCESS_TOKEN_TTL_SECS,
+    })
+}
+
+/// Role claims for a new access token
+async fn token_roles(pool: &sqlx::PgPool, user_id: Uuid) -> AppResult<crate::utils::jwt::TokenRoles> {
+    let row = sqlx::query!(
         r#"
-        INSERT INTO refresh_tokens (user_id, token_hash, expires_at)
-        VALUES ($1, $2, $3)
+        SELECT u.role, COALESCE(u.base_role, 'base_user') as "base_role!",
+               (SELECT s.verification_status FROM students s
+                WHERE s.user_id = u.id
+                ORDER BY s.created_at DESC
+                LIMIT 1) as verification_status
+        FROM users u
+        WHERE u.id = $1
         "#,
-        user.id,
-        refresh_token_hash,
-        refresh_expires_at,
+        user_id
     )
-    .execute(&state.pool)
+    .fetch_optional(pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Refresh token insertion error: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .context("Failed to load token roles")?
+    .ok_or_else(|| AppError::unauthorized("Account no longer exists"))?;
+
+    let permissions = crate::services::rbac::effective_permissions(pool, user_id)
+        .await
+        .context("Failed to load permissions")?;
+
+    Ok(crate::utils::jwt::TokenRoles {
+        role: row.role,
+        base_role: row.base_role,
+        verification_status: row.verification_status,
+        permissions,
+    })
+}
 
-    Ok(Json(AuthResponse {
-        access_token,
-        refresh_token,
-        expires_in: 3600, // 1 hour
-    }))
+/// Public keys for validating access tokens, in JWKS form
+pub async fn jwks() -> impl IntoResponse {
+    (
+        [(header::CACHE_CONTROL, "public, max-age=300")],
+        Json(crate::utils::jwt::jwks()),
+    )
 }
 
-pub async fn logout() -> StatusCode {
-    // Since we're using JWTs, logout is handled client-side
-    StatusCode::OK
+/// Revoke the session the access token was issued for. The access token
+/// itself stays valid until it expires.
+pub async fn logout(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+) -> AppResult<StatusCode> {
+    let claims = crate::utils::jwt::extract_claims_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+    if let Some(session_id) = claims.sid {
+        sessions::revoke(&state.pool, claims.sub, session_id, "logout")
+            .await
+            .context("Failed to revoke session")?;
+    }
+    Ok(StatusCode::OK)
 }
 
 pub async fn get_me(
     State(state): State<crate::state::AppState>,
     headers: axum::http::HeaderMap,
-) -> Result<Json<ProfileResponse>, StatusCode> {
+) -> AppResult<Json<ProfileResponse>> {
     // Extract user ID from JWT token
     let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
 
     let user = sqlx::query_as!(
         User,
@@ -222,8 +412,8 @@ pub async fn get_me(
     )
     .fetch_optional(&state.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .context("Failed to load profile")?
+    .ok_or_else(|| AppError::not_found("User not found"))?;
 
     Ok(Json(ProfileResponse {
         id: user.id,
@@ -243,7 +433,7 @@ pub async fn get_me(
 pub async fn get_profile(
     State(state): State<crate::state::AppState>,
     Path(user_id): Path<Uuid>,
-) -> Result<Json<ProfileResponse>, StatusCode> {
+) -> AppResult<Json<ProfileResponse>> {
     let user = sqlx::query_as!(
         User,
         r#"
@@ -261,8 +451,8 @@ pub async fn get_profile(
     )
     .fetch_optional(&state.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .context("Failed to load profile")?
+    .ok_or_else(|| AppError::not_found("User not found"))?;
 
     Ok(Json(ProfileResponse {
         id: user.id,
@@ -281,91 +471,83 @@ pub async fn get_profile(
 
 pub async fn refresh(
     State(state): State<crate::state::AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(payload): Json<RefreshTokenRequest>,
-) -> Result<Json<AuthResponse>, StatusCode> {
-    let token_hash = hash_token(&payload.refresh_token);
-    
-    // Verify refresh token exists and is not expired
-    let token_record = sqlx::query!(
-        r#"
-        SELECT user_id, expires_at
-        FROM refresh_tokens
-        WHERE token_hash = $1 AND expires_at > NOW()
-        "#,
-        token_hash
-    )
-    .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    // Generate new access token
-    let access_token = crate::utils::jwt::create_token(&token_record.user_id)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    // Generate new refresh token
-    let new_refresh_token = generate_random_token();
-    let new_refresh_token_hash = hash_token(&new_refresh_token);
-    let refresh_expires_at = Utc::now() + Duration::days(30);
-
-    // Delete old refresh token
-    sqlx::query!(
-        r#"
-        DELETE FROM refresh_tokens WHERE token_hash = $1
-        "#,
-        token_hash
-    )
-    .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> AppResult<Json<AuthResponse>> {
+    let client = sessions::ClientInfo::from_request(&headers, peer.map(|ConnectInfo(addr)| addr));
+    let refresh = match sessions::rotate(&state.pool, &payload.refresh_token, &client).await {
+        Ok(refresh) => refresh,
+        Err(e @ SessionError::InvalidToken) => return Err(AppError::unauthorized(e.to_string())),
+        Err(SessionError::TokenReused { user_id, session_id }) => {
+            // Reuse of a rotated token usually means it was stolen
+            tracing::warn!("Refresh token reuse for user {}; revoked session {}", user_id, session_id);
+            let _ = sqlx::query!(
+                r#"
+                INSERT INTO activity_logs (user_id, action, target_id, target_type)
+                VALUES ($1, $2, $3, $4)
+                "#,
+                user_id,
+                "session_revoked_token_reuse",
+                session_id,
+                "session"
+            )
+            .execute(&state.pool)
+            .await;
+            return Err(AppError::Coded {
+                status: StatusCode::UNAUTHORIZED,
+                code: "refresh_token_reused",
+                message: "Refresh token was already used; sign in again".to_string(),
+                details: None,
+            });
+        }
+        Err(SessionError::Internal(e)) => return Err(e.context("Refresh token rotation failed").into()),
+    };
 
-    // Insert new refresh token
-    sqlx::query!(
-        r#"
-        INSERT INTO refresh_tokens (user_id, token_hash, expires_at)
-        VALUES ($1, $2, $3)
-        "#,
-        token_record.user_id,
-        new_refresh_token_hash,
-        refresh_expires_at,
-    )
-    .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // New access token, picking up any role changes
+    let roles = token_roles(&state.pool, refresh.user_id).await?;
+    let access_token = crate::utils::jwt::create_token(&refresh.user_id, &roles, Some(refresh.session_id))
+        .context("Failed to create access token")?;
 
     Ok(Json(AuthResponse {
         access_token,
-        refresh_token: new_refresh_token,
-        expires_in: 3600,
+        refresh_token: refresh.token,
+        expires_in: crate::utils::jwt::ACCESS_TOKEN_TTL_SECS,
     }))
 }
 
 pub async fn verify_email(
     State(state): State<crate::state::AppState>,
     Query(query): Query<VerifyEmailQuery>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> AppResult<Json<serde_json::Value>> {
     // Find verification token
+    let token_hash = email_verification::hash_token(&query.token);
     let token_record = sqlx::query!(
         r#"
         SELECT user_id, expires_at, verified_at
         FROM email_verification_tokens
         WHERE token = $1
         "#,
-        query.token
+        token_hash
     )
     .fetch_optional(&state.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .context("Failed to load verification token")?
+    .ok_or_else(|| AppError::not_found("Verification link not found"))?;
 
     // Check if already verified
     if token_record.verified_at.is_some() {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(AppError::Coded {
+            status: StatusCode::BAD_REQUEST,
+            code: "already_verified",
+            message: "This email address is already verified".to_string(),
+            details: None,
+        });
     }
 
     // Check if expired
     if token_record.expires_at < Utc::now() {
-        return Err(StatusCode::GONE);
+        return Err(AppError::Gone("Verification link has expired; request a new one".to_string()));
     }
 
     // Mark token as verified
@@ -375,50 +557,163 @@ pub async fn verify_email(
         SET verified_at = NOW()
         WHERE token = $1
         "#,
-        query.token
+        token_hash
     )
     .execute(&state.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .context("Failed to mark verification token used")?;
 
-    // Update user status to active
-    sqlx::query!(
+    // Activate the account; suspended users stay suspended
+    let user = sqlx::query!(
         r#"
         UPDATE users
-        SET status = 'active'
+        SET status = CASE WHEN status = 'pending_email_verification' THEN 'active' ELSE status END
         WHERE id = $1
+        RETURNING email
         "#,
         token_record.user_id
     )
-    .execute(&state.pool)
+    .fetch_one(&state.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .context("Failed to activate account")?;
+
+    let claimable_guest_donations = crate::services::guest_claims::claimable_count(&state.pool, &user.email)
+        .await
+        .unwrap_or(0);
 
     Ok(Json(serde_json::json!({
         "message": "Email verified successfully",
-        "user_id": token_record.user_id
+        "user_id": token_record.user_id,
+        "claimable_guest_donations": claimable_guest_donations
     })))
 }
 
-// Helper functions
-fn generate_random_token() -> String {
-    let mut rng = rand::thread_rng();
-    let token: String = (0..32)
-        .map(|_| format!("{:02x}", rng.gen::<u8>()))
-        .collect();
-    token
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResendVerificationRequest {
+    #[validate(email(message = "Enter a valid email address"))]
+    pub email: String,
 }
 
-fn hash_token(token: &str) -> String {
-    use argon2::Argon2;
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    argon2
-        .hash_password(token.as_bytes(), &salt)
-        .unwrap()
-        .to_string()
+/// Send a new verification link. Answers the same whether or not the email
+/// belongs to an unverified account; repeated requests are rate limited.
+pub async fn resend_verification(
+    State(state): State<crate::state::AppState>,
+    ValidatedJson(payload): ValidatedJson<ResendVerificationRequest>,
+) -> AppResult<(StatusCode, Json<serde_json::Value>)> {
+    let user = sqlx::query!(
+        r#"
+        SELECT id, username, email
+        FROM users
+        WHERE email = $1 AND status = 'pending_email_verification'
+        "#,
+        payload.email.trim()
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .context("Failed to look up account for verification resend")?;
+
+    if let Some(user) = user {
+        let wait = email_verification::resend_wait_for(&state.pool, user.id)
+            .await
+            .context("Failed to check verification resend limit")?;
+        if let Some(retry_after) = wait {
+            return Err(AppError::RateLimited {
+                message: "Too many verification emails requested".to_string(),
+                retry_after_secs: retry_after,
+            });
+        }
+
+        email_verification::send(&state.pool, user.id, &user.username, &user.email)
+            .await
+            .context("Failed to send verification email")?;
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "If the account is awaiting verification, a new link has been sent"
+        })),
+    ))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "Enter a valid email address"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    #[validate(custom(function = "validation::password"))]
+    pub new_password: String,
+}
+
+/// Email a password reset link. Answers the same whether or not the email
+/// has an account.
+pub async fn forgot_password(
+    State(state): State<crate::state::AppState>,
+    ValidatedJson(payload): ValidatedJson<ForgotPasswordRequest>,
+) -> AppResult<(StatusCode, Json<serde_json::Value>)> {
+    let user_id = password_reset::request(&state.pool, &payload.email)
+        .await
+        .context("Failed to send password reset email")?;
+
+    if let Some(user_id) = user_id {
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO activity_logs (user_id, action, target_id, target_type)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            user_id,
+            "password_reset_requested",
+            user_id,
+            "user"
+        )
+        .execute(&state.pool)
+        .await;
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "If an account exists for that email, a reset link has been sent"
+        })),
+    ))
 }
 
+/// Set a new password from a reset link; signs the user out everywhere
+pub async fn reset_password(
+    State(state): State<crate::state::AppState>,
+    ValidatedJson(payload): ValidatedJson<ResetPasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let user_id = password_reset::reset(&state.pool, &payload.token, &payload.new_password)
+        .await
+        .map_err(|e| match e {
+            ResetError::InvalidToken => AppError::bad_request(e.to_string()),
+            ResetError::WeakPassword => AppError::invalid("new_password", e.to_string()),
+            ResetError::Internal(e) => AppError::Internal(e.context("Failed to reset password")),
+        })?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        "password_reset_completed",
+        user_id,
+        "user"
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(serde_json::json!({
+        "message": "Password has been reset; sign in with your new password"
+    })))
+}
+
+// Helper functions
 #[derive(Debug, Serialize)]
 pub struct StudentStatusResponse {
     pub has_student_account: bool,
@@ -428,9 +723,9 @@ pub struct StudentStatusResponse {
 pub async fn get_student_status(
     State(state): State<crate::state::AppState>,
     headers: axum::http::HeaderMap,
-) -> Result<Json<StudentStatusResponse>, StatusCode> {
+) -> AppResult<Json<StudentStatusResponse>> {
     let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
 
     // Check if user has a student record
     let student_record = sqlx::query!(
@@ -443,7 +738,7 @@ pub async fn get_student_status(
     )
     .fetch_optional(&state.pool)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .context("Failed to load student record")?;
 
     match student_record {
         Some(student) => {
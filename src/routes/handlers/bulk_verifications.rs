@@ -0,0 +1,138 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::ValidatedJson;
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::preapproved_admissions::{self, ImportSummary, PreapprovedAdmission};
+use crate::services::verification_decisions::{self, BulkResult, Decision};
+use crate::state::AppState;
+use crate::utils::pagination::{Page, PageQuery, PageRequest};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct BulkVerificationRequest {
+    #[validate(length(min = 1, max = 100, message = "Give 1 to 100 verification ids"))]
+    pub verification_ids: Vec<Uuid>,
+    pub action: Decision,
+    /// Sent to every applicant; required to reject
+    #[validate(length(max = 2000, message = "Message must be at most 2000 characters"))]
+    pub message: Option<String>,
+    /// Roll every decision back if any item fails
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkVerificationResponse {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BulkResult>,
+}
+
+fn admin(headers: &HeaderMap) -> AppResult<Uuid> {
+    crate::utils::jwt::extract_user_id_from_headers(headers).map_err(|_| AppError::unauthorized("Authentication required"))
+}
+
+/// Tell applicants the outcome, once their decisions are committed
+async fn announce(state: &AppState, verification_id: Uuid, user_id: Uuid, approved: bool, message: Option<String>) {
+    let event = NotificationEvent::VerificationStatus {
+        user_id,
+        status: if approved { "verified" } else { "rejected" }.to_string(),
+        reason: if approved { None } else { message.clone() },
+    };
+    state.notifier.send(Channel::User(user_id), event);
+    verification_decisions::announce(&state.pool, verification_id, user_id, approved, message).await;
+}
+
+/// Approve or reject many pending verifications at once with a shared
+/// message, answering with how each went
+pub async fn bulk_verifications(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<BulkVerificationRequest>,
+) -> AppResult<Json<BulkVerificationResponse>> {
+    let admin_id = admin(&headers)?;
+    let message = req.message.as_deref().map(str::trim).filter(|m| !m.is_empty());
+    if req.action == Decision::Reject && message.is_none() {
+        return Err(AppError::invalid("message", "Give a reason to reject with"));
+    }
+
+    let results =
+        verification_decisions::bulk(&state.pool, &req.verification_ids, req.action, admin_id, message, req.atomic).await?;
+    for result in results.iter().filter(|r| r.ok) {
+        let Some(user_id) = result.user_id else { continue };
+        announce(&state, result.verification_id, user_id, req.action == Decision::Approve, message.map(str::to_string)).await;
+    }
+
+    let succeeded = results.iter().filter(|r| r.ok).count();
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        "verifications_bulk_decided",
+        None::<Uuid>,
+        "student_verification",
+        serde_json::json!({
+            "action": req.action,
+            "succeeded": results.iter().filter(|r| r.ok).map(|r| r.verification_id).collect::<Vec<_>>(),
+            "failed": results.len() - succeeded,
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(BulkVerificationResponse { succeeded, failed: results.len() - succeeded, results }))
+}
+
+/// Import a CSV of admission numbers schools have vouched for (columns
+/// `admission_number`, `school_domain` and optionally `full_name`); pending
+/// confirmed applications that match are approved
+pub async fn import_preapproved(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> AppResult<(StatusCode, Json<ImportSummary>)> {
+    let admin_id = admin(&headers)?;
+    let rows = preapproved_admissions::parse_csv(&body).map_err(|e| AppError::invalid("csv", e.to_string()))?;
+    if rows.is_empty() {
+        return Err(AppError::invalid("csv", "The file has no rows"));
+    }
+
+    let summary = preapproved_admissions::import(&state.pool, &rows, admin_id).await?;
+    for matched in &summary.matched {
+        let message = Some(preapproved_admissions::MATCH_MESSAGE.to_string());
+        announce(&state, matched.verification_id, matched.user_id, true, message).await;
+    }
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        "preapproved_admissions_imported",
+        None::<Uuid>,
+        "preapproved_admission",
+        serde_json::json!({"rows": summary.rows, "imported": summary.imported, "matched": summary.matched.len()})
+    )
+    .execute(&state.pool)
+    .await;
+    Ok((StatusCode::CREATED, Json(summary)))
+}
+
+/// Imported admission numbers, newest first, with what they matched
+pub async fn list_preapproved(
+    State(state): State<AppState>,
+    Query(query): Query<PageQuery>,
+) -> AppResult<Json<Page<PreapprovedAdmission>>> {
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
+    Ok(Json(preapproved_admissions::list(&state.pool, &page).await?))
+}
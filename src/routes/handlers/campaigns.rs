@@ -1,7 +1,14 @@
-use axum::{extract::{State, Path}, Json, http::StatusCode};
+use axum::{extract::{State, Path, Query}, Json, http::{HeaderMap, StatusCode}, response::{IntoResponse, Response}};
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
-use crate::workers::distribute_campaign_funds;
+use crate::routes::handlers::approvals::{dual_control, ApprovalQuery};
+use crate::services::approvals;
+use crate::services::campaign_rules::{Criteria, Strategy};
+use crate::services::campaign_schedule;
+use crate::services::contract_client::{ContractClient, MatchingPoolInfo};
+use crate::utils::money::Stroops;
+use crate::services::jobs::{self, Job};
+use crate::utils::jwt;
 
 #[derive(Serialize)]
 pub struct ApiMessage { pub message: String }
@@ -10,15 +17,23 @@ pub struct ApiMessage { pub message: String }
 pub struct CreateCampaignRequest { 
     pub name: String, 
     pub criteria: String, 
-    pub reward_pool_xlm: f64 
+    pub reward_pool_xlm: Stroops,
+    /// Scheduled until then; active straight away when unset
+    pub start_date: Option<chrono::DateTime<chrono::Utc>>,
+    pub end_date: Option<chrono::DateTime<chrono::Utc>>,
+    /// `end_date` (default) pays out at the end date; `manual` waits for execute
+    pub distribution_trigger: Option<String>,
+    /// Equal split when unset
+    pub distribution_strategy: Option<Strategy>,
 }
 
 #[derive(Deserialize)]
 pub struct UpdateCampaignRequest {
     pub name: Option<String>,
     pub criteria: Option<String>,
-    pub reward_pool_xlm: Option<f64>,
+    pub reward_pool_xlm: Option<Stroops>,
     pub status: Option<String>,
+    pub distribution_strategy: Option<Strategy>,
 }
 
 #[derive(Serialize)]
@@ -26,35 +41,77 @@ pub struct CampaignResponse {
     pub id: Uuid,
     pub name: String,
     pub criteria: String,
-    pub reward_pool_xlm: f64,
+    pub reward_pool_xlm: Stroops,
     pub status: String,
+    pub start_date: Option<chrono::DateTime<chrono::Utc>>,
+    pub end_date: Option<chrono::DateTime<chrono::Utc>>,
+    pub distribution_trigger: String,
+    pub distribution_strategy: serde_json::Value,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+#[derive(Deserialize)]
+pub struct CreateMatchingPoolRequest {
+    pub sponsor_address: String,
+    pub amount_xlm: Stroops,
+    pub ratio_bps: i32,
+    pub cap_xlm: Stroops,
+}
+
 #[derive(Serialize)]
 pub struct CampaignStats {
     pub total_campaigns: i64,
     pub active_campaigns: i64,
-    pub total_reward_pool: f64,
-    pub distributed_amount: f64,
+    pub total_reward_pool: Stroops,
+    pub distributed_amount: Stroops,
 }
 
-pub async fn create(State(state): State<crate::state::AppState>, Json(req): Json<CreateCampaignRequest>) -> Json<ApiMessage> {
+pub async fn create(
+    State(state): State<crate::state::AppState>,
+    Json(req): Json<CreateCampaignRequest>,
+) -> Result<Json<ApiMessage>, (StatusCode, Json<ApiMessage>)> {
+    let now = chrono::Utc::now();
+    let trigger = req.distribution_trigger.as_deref().unwrap_or("end_date");
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, Json(ApiMessage { message }));
+    campaign_schedule::check_schedule(now, req.start_date, req.end_date, trigger).map_err(|e| bad_request(e.to_string()))?;
+    Criteria::parse(&req.criteria).map_err(|e| bad_request(e.to_string()))?;
+    let strategy = req.distribution_strategy.unwrap_or_default();
+    strategy.check().map_err(|e| bad_request(e.to_string()))?;
+
     let _ = sqlx::query!(
-        r#"INSERT INTO campaigns (id, name, criteria, reward_pool_xlm, status, created_at)
-           VALUES ($1, $2, $3, $4, 'active', NOW())"#,
-        Uuid::new_v4(), req.name, req.criteria, req.reward_pool_xlm
+        r#"INSERT INTO campaigns (id, name, criteria, reward_pool_xlm, status, start_date, end_date, distribution_trigger, distribution_strategy, created_at)
+           VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())"#,
+        Uuid::new_v4(), req.name, req.criteria, req.reward_pool_xlm.to_decimal(),
+        campaign_schedule::initial_status(now, req.start_date), req.start_date, req.end_date, trigger,
+        serde_json::to_value(&strategy).unwrap_or_default()
     ).execute(&state.pool).await;
-    Json(ApiMessage { message: "campaign created".into() })
+    Ok(Json(ApiMessage { message: "campaign created".into() }))
 }
-pub async fn execute(State(state): State<crate::state::AppState>) -> Json<ApiMessage> {
-    let _ = distribute_campaign_funds(&state.pool, &state.stellar).await;
-    Json(ApiMessage { message: "campaign distribution triggered".into() })
+/// Distribute campaign reward pools. Needs a second admin: the first call
+/// opens an approval request. The payout runs as a background job.
+pub async fn execute(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Query(approval): Query<ApprovalQuery>,
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+    let payload = serde_json::json!({});
+    if let Some(pending) = dual_control(&state, &headers, approvals::CAMPAIGN_EXECUTE, approval.approval_id, payload).await? {
+        return Ok(pending);
+    }
+
+    let requested_by = jwt::extract_claims_from_headers(&headers).ok().map(|claims| claims.sub);
+    let job_id = jobs::enqueue(&state.pool, &Job::DistributeCampaignFunds { requested_by, campaign_id: None }, chrono::Utc::now(), None)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to queue campaign distribution: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "Failed to queue campaign distribution"})))
+        })?;
+    Ok(Json(serde_json::json!({"message": "campaign distribution queued", "job_id": job_id})).into_response())
 }
 pub async fn list(State(state): State<crate::state::AppState>) -> Json<serde_json::Value> {
     let rows = sqlx::query!(
-        r#"SELECT id, name, criteria, reward_pool_xlm, status, created_at FROM campaigns WHERE status = 'active' ORDER BY created_at DESC"#
+        r#"SELECT id, name, criteria, reward_pool_xlm as "reward_pool_xlm: Stroops", status, start_date, end_date, created_at FROM campaigns WHERE status = 'active' ORDER BY created_at DESC"#
     ).fetch_all(&state.pool).await.unwrap_or_default();
     let json: Vec<_> = rows.into_iter().map(|r| serde_json::json!({
         "id": r.id,
@@ -62,6 +119,8 @@ pub async fn list(State(state): State<crate::state::AppState>) -> Json<serde_jso
         "criteria": r.criteria,
         "reward_pool_xlm": r.reward_pool_xlm,
         "status": r.status,
+        "start_date": r.start_date,
+        "end_date": r.end_date,
         "created_at": r.created_at,
     })).collect();
     Json(serde_json::json!(json))
@@ -69,7 +128,7 @@ pub async fn list(State(state): State<crate::state::AppState>) -> Json<serde_jso
 
 pub async fn get_by_id(State(state): State<crate::state::AppState>, Path(id): Path<Uuid>) -> Result<Json<CampaignResponse>, StatusCode> {
     let row = sqlx::query!(
-        r#"SELECT id, name, criteria, reward_pool_xlm, status, created_at, updated_at FROM campaigns WHERE id = $1"#,
+        r#"SELECT id, name, criteria, reward_pool_xlm as "reward_pool_xlm: Stroops", status, start_date, end_date, distribution_trigger, distribution_strategy, created_at, updated_at FROM campaigns WHERE id = $1"#,
         id
     ).fetch_optional(&state.pool).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
@@ -80,6 +139,10 @@ pub async fn get_by_id(State(state): State<crate::state::AppState>, Path(id): Pa
             criteria: r.criteria,
             reward_pool_xlm: r.reward_pool_xlm,
             status: r.status,
+            start_date: r.start_date,
+            end_date: r.end_date,
+            distribution_trigger: r.distribution_trigger,
+            distribution_strategy: r.distribution_strategy,
             created_at: r.created_at,
             updated_at: r.updated_at,
         })),
@@ -93,6 +156,23 @@ pub async fn update(State(state): State<crate::state::AppState>, Path(id): Path<
     let mut param_count = 1;
     let mut updates = Vec::new();
 
+    if let Some(criteria) = &req.criteria {
+        if let Err(e) = Criteria::parse(criteria) {
+            tracing::warn!("Rejected criteria for campaign {}: {}", id, e);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+    if let Some(strategy) = &req.distribution_strategy {
+        strategy.check().map_err(|_| StatusCode::BAD_REQUEST)?;
+        sqlx::query!(
+            r#"UPDATE campaigns SET distribution_strategy = $1, updated_at = NOW() WHERE id = $2"#,
+            serde_json::to_value(strategy).unwrap_or_default(), id
+        ).execute(&state.pool).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if req.name.is_none() {
+            return Ok(Json(ApiMessage { message: "Campaign updated successfully".into() }));
+        }
+    }
+
     if let Some(name) = &req.name {
         updates.push(format!("name = ${}", param_count));
         params.push(Box::new(name.clone()));
@@ -130,12 +210,12 @@ pub async fn update(State(state): State<crate::state::AppState>, Path(id): Path<
                 if let Some(status) = req.status {
                     sqlx::query!(
                         r#"UPDATE campaigns SET name = $1, criteria = $2, reward_pool_xlm = $3, status = $4, updated_at = NOW() WHERE id = $5"#,
-                        name, criteria, reward_pool, status, id
+                        name, criteria, reward_pool.to_decimal(), status, id
                     ).execute(&state.pool).await
                 } else {
                     sqlx::query!(
                         r#"UPDATE campaigns SET name = $1, criteria = $2, reward_pool_xlm = $3, updated_at = NOW() WHERE id = $4"#,
-                        name, criteria, reward_pool, id
+                        name, criteria, reward_pool.to_decimal(), id
                     ).execute(&state.pool).await
                 }
             } else {
@@ -220,8 +300,8 @@ pub async fn stats(State(state): State<crate::state::AppState>) -> Result<Json<C
         SELECT 
             COUNT(*) as total_campaigns,
             COUNT(CASE WHEN status = 'active' THEN 1 END) as active_campaigns,
-            COALESCE(SUM(reward_pool_xlm), 0) as total_reward_pool,
-            COALESCE(SUM(CASE WHEN status = 'completed' THEN reward_pool_xlm ELSE 0 END), 0) as distributed_amount
+            COALESCE(SUM(reward_pool_xlm), 0) as "total_reward_pool!: Stroops",
+            COALESCE(SUM(CASE WHEN status = 'completed' THEN reward_pool_xlm ELSE 0 END), 0) as "distributed_amount!: Stroops"
         FROM campaigns 
         WHERE status != 'deleted'
         "#
@@ -230,9 +310,59 @@ pub async fn stats(State(state): State<crate::state::AppState>) -> Result<Json<C
     Ok(Json(CampaignStats {
         total_campaigns: stats.total_campaigns.unwrap_or(0),
         active_campaigns: stats.active_campaigns.unwrap_or(0),
-        total_reward_pool: stats.total_reward_pool.unwrap_or(0.0),
-        distributed_amount: stats.distributed_amount.unwrap_or(0.0),
+        total_reward_pool: stats.total_reward_pool,
+        distributed_amount: stats.distributed_amount,
     }))
 }
 
+/// Lock sponsor funds that match deposits for this campaign
+pub async fn create_matching_pool(
+    State(state): State<crate::state::AppState>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CreateMatchingPoolRequest>,
+) -> Result<(StatusCode, Json<ApiMessage>), StatusCode> {
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let pool = MatchingPoolInfo {
+        campaign_id: id,
+        sponsor_address: req.sponsor_address,
+        ratio_bps: req.ratio_bps,
+        cap_stroops: req.cap_xlm.as_stroops(),
+        locked_stroops: req.amount_xlm.as_stroops(),
+        matched_stroops: 0,
+        status: "active".to_string(),
+    };
+
+    match contract_client.create_matching_pool(&pool).await {
+        Ok(message) => Ok((StatusCode::CREATED, Json(ApiMessage { message }))),
+        Err(e) => {
+            tracing::warn!("Failed to create matching pool for campaign {}: {}", id, e);
+            Err(StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
+pub async fn get_matching_pool(
+    State(state): State<crate::state::AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let contract_client = ContractClient::new(state.pool.clone(), state.network);
+    let pool = contract_client
+        .get_matching_pool(id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
 
+    let remaining = (pool.cap_stroops.min(pool.locked_stroops) - pool.matched_stroops).max(0);
+    Ok(Json(serde_json::json!({
+        "campaign_id": pool.campaign_id,
+        "sponsor_address": pool.sponsor_address,
+        "ratio_bps": pool.ratio_bps,
+        "cap_xlm": Stroops::from_stroops(pool.cap_stroops),
+        "locked_xlm": Stroops::from_stroops(pool.locked_stroops),
+        "matched_xlm": Stroops::from_stroops(pool.matched_stroops),
+        "remaining_xlm": Stroops::from_stroops(remaining),
+        "status": pool.status,
+    })))
+}
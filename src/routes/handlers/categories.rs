@@ -0,0 +1,79 @@
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, Json};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::{self, ValidatedJson};
+use crate::services::categories::{self, Category, CategorySummary};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCategoryRequest {
+    #[validate(
+        custom(function = "validation::not_blank"),
+        length(max = 100, message = "Name must be at most 100 characters")
+    )]
+    pub name: String,
+    /// Derived from the name when left out
+    pub slug: Option<String>,
+    #[validate(length(max = 1000, message = "Description must be at most 1000 characters"))]
+    pub description: Option<String>,
+}
+
+async fn log_activity(state: &crate::state::AppState, admin_id: Uuid, action: &str, category: &Category) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        action,
+        category.id,
+        "category",
+        serde_json::json!({"slug": category.slug, "name": category.name})
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+/// Every category with its count of active projects
+pub async fn list_categories(State(state): State<crate::state::AppState>) -> AppResult<Json<Vec<CategorySummary>>> {
+    Ok(Json(categories::list(&state.pool).await?))
+}
+
+pub async fn create_category(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<CreateCategoryRequest>,
+) -> AppResult<(StatusCode, Json<Category>)> {
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    // Slugs follow the same rules as tags so they read the same in URLs
+    let slug = categories::normalize_tag(req.slug.as_deref().unwrap_or(&req.name))
+        .ok_or_else(|| AppError::invalid("slug", "Slug needs at least one letter or digit"))?;
+
+    let category = categories::create(&state.pool, &slug, req.name.trim(), req.description.as_deref())
+        .await?
+        .ok_or_else(|| AppError::conflict(format!("A category with slug {} already exists", slug)))?;
+
+    log_activity(&state, admin_id, "category_created", &category).await;
+    Ok((StatusCode::CREATED, Json(category)))
+}
+
+/// Projects in the category keep their tags but leave the category
+pub async fn delete_category(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Path(slug): Path<String>,
+) -> AppResult<Json<Category>> {
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let category = categories::delete(&state.pool, &slug)
+        .await?
+        .ok_or_else(|| AppError::not_found("Category not found"))?;
+
+    log_activity(&state, admin_id, "category_deleted", &category).await;
+    Ok(Json(category))
+}
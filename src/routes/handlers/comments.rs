@@ -0,0 +1,126 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::{self, ValidatedJson};
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::comments::{self, Comment, CommentError};
+use crate::state::AppState;
+use crate::utils::pagination::{Page, PageQuery, PageRequest};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCommentRequest {
+    #[validate(
+        custom(function = "validation::not_blank"),
+        length(max = 2000, message = "Comment must be at most 2000 characters")
+    )]
+    pub body: String,
+    /// Reply to this comment instead of starting a thread
+    pub parent_id: Option<Uuid>,
+}
+
+/// A project's comment threads, newest first, each with its replies
+pub async fn list_comments(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    Query(query): Query<PageQuery>,
+) -> AppResult<Json<Page<Comment>>> {
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
+
+    let exists = sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1) as "exists!""#, project_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if !exists {
+        return Err(AppError::not_found("Project not found"));
+    }
+
+    Ok(Json(comments::list_threads(&state.pool, project_id, &page).await?))
+}
+
+/// Comment on an active project, or reply to a comment on it. The owner is
+/// notified in-app and over SSE.
+pub async fn create_comment(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<CreateCommentRequest>,
+) -> AppResult<(StatusCode, Json<Comment>)> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let status = sqlx::query_scalar!("SELECT status FROM projects WHERE id = $1", project_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project not found"))?;
+    if status != "active" {
+        return Err(AppError::forbidden("Only active projects take comments"));
+    }
+
+    let comment = comments::create(&state.pool, project_id, user_id, req.parent_id, req.body.trim())
+        .await
+        .map_err(|e| match e {
+            CommentError::ParentNotFound => AppError::invalid("parent_id", e.to_string()),
+            CommentError::Internal(e) => AppError::Internal(e),
+        })?;
+
+    match comments::notify_owner(&state.pool, &comment).await {
+        Ok(Some(owner_id)) => {
+            let event = NotificationEvent::ProjectComment { project_id, comment_id: comment.id };
+            state.notifier.send(Channel::User(owner_id), event);
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to notify owner of comment {}: {}", comment.id, e),
+    }
+
+    Ok((StatusCode::CREATED, Json(comment)))
+}
+
+/// Remove a comment. Authors can remove their own; the project's owner and
+/// admins can remove any on the project.
+pub async fn delete_comment(
+    State(state): State<AppState>,
+    Path((project_id, comment_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> AppResult<StatusCode> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let access = comments::access(&state.pool, project_id, comment_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Comment not found"))?;
+
+    let moderated = access.author_id != user_id;
+    if moderated && access.owner_user_id != user_id && !crate::utils::roles::caller_is_admin(&state.pool, &headers).await {
+        return Err(AppError::forbidden("Only the author, the project's owner or an admin can delete this comment"));
+    }
+
+    if !comments::delete(&state.pool, comment_id, user_id).await? {
+        return Err(AppError::not_found("Comment not found"));
+    }
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        "comment_deleted",
+        comment_id,
+        "project_comment",
+        serde_json::json!({
+            "project_id": access.project_id,
+            "author_id": access.author_id,
+            "moderated": moderated
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,23 +1,32 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::services::contract_client::{ContractClient, MilestoneInfo, DepositInfo, ReleaseInfo};
+use crate::config::StellarNetwork;
+use crate::routes::handlers::approvals::{dual_control, ApprovalQuery};
+use crate::services::approvals;
+use crate::services::contract_client::{
+    validate_recipient_splits, ContractClient, DepositInfo, MilestoneInfo, RecipientShare,
+};
 use crate::state::AppState;
+use crate::utils::money::Stroops;
 use crate::utils::roles::require_admin_mw;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DeployContractsRequest {
-    pub network: String,
+    /// Defaults to the network the server runs against
+    #[serde(default)]
+    pub network: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DeployContractsResponse {
     pub success: bool,
+    pub network: StellarNetwork,
     pub contracts: std::collections::HashMap<String, String>,
     pub message: String,
 }
@@ -28,7 +37,10 @@ pub struct RegisterMilestoneRequest {
     pub milestone_id: String,
     pub amount_stroops: i64,
     pub proof_required: bool,
-    pub recipient_address: String,
+    /// Single recipient; shorthand for a 100% split
+    pub recipient_address: Option<String>,
+    /// Recipients with shares in basis points summing to 10000
+    pub recipients: Option<Vec<RecipientShare>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -38,12 +50,48 @@ pub struct ReleaseMilestoneRequest {
     pub attestation_signature: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct OnchainEventsQuery {
+    pub project_id: Option<Uuid>,
+    pub event_type: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OnchainEvent {
+    pub event_id: String,
+    pub contract_name: String,
+    pub event_type: String,
+    pub ledger: i64,
+    pub ledger_closed_at: chrono::DateTime<chrono::Utc>,
+    pub tx_hash: Option<String>,
+    pub project_id: Option<Uuid>,
+    pub milestone_id: Option<String>,
+    pub address: Option<String>,
+    pub amount_stroops: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenMilestoneVoteRequest {
+    pub project_id: Uuid,
+    pub milestone_id: String,
+    pub window_secs: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CastMilestoneVoteRequest {
+    pub voter_address: String,
+    pub approve: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RecordDepositRequest {
     pub project_id: Uuid,
     pub donor_address: String,
     pub amount_stroops: i64,
     pub memo: Option<String>,
+    /// Omit to have the platform account submit the deposit
+    #[serde(default)]
     pub tx_hash: String,
 }
 
@@ -52,11 +100,20 @@ pub async fn deploy_contracts(
     State(state): State<AppState>,
     Json(request): Json<DeployContractsRequest>,
 ) -> Result<Json<DeployContractsResponse>, StatusCode> {
-    let mut contract_client = ContractClient::new(state.pool.clone());
+    let network = match request.network.as_deref() {
+        Some(name) => StellarNetwork::parse(name).ok_or(StatusCode::BAD_REQUEST)?,
+        None => state.network,
+    };
+    if network != state.network {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let contract_client = ContractClient::new(state.pool.clone(), state.network);
     
-    match contract_client.deploy_contracts(&request.network).await {
+    match contract_client.deploy_contracts(network).await {
         Ok(contracts) => Ok(Json(DeployContractsResponse {
             success: true,
+            network,
             contracts,
             message: "Contracts deployed successfully".to_string(),
         })),
@@ -69,15 +126,24 @@ pub async fn register_milestone(
     State(state): State<AppState>,
     Json(request): Json<RegisterMilestoneRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut contract_client = ContractClient::new(state.pool.clone());
-    
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let recipients = match (request.recipients, request.recipient_address) {
+        (Some(recipients), _) => recipients,
+        (None, Some(address)) => vec![RecipientShare { address, share_bps: 10_000 }],
+        (None, None) => return Err(StatusCode::BAD_REQUEST),
+    };
+    validate_recipient_splits(&recipients).map_err(|_| StatusCode::BAD_REQUEST)?;
+
     let milestone = MilestoneInfo {
         project_id: request.project_id,
         milestone_id: Some(request.milestone_id.clone()),
         amount_stroops: Some(request.amount_stroops),
         proof_required: Some(request.proof_required),
         released: Some(false),
-        recipient_address: Some(request.recipient_address),
+        recipient_address: recipients.first().map(|r| r.address.clone()),
+        recipients: Some(sqlx::types::Json(recipients)),
     };
 
     match contract_client.register_milestone(&milestone).await {
@@ -90,23 +156,138 @@ pub async fn register_milestone(
     }
 }
 
-/// Release a milestone (admin only)
+/// Release a milestone (admin only). Needs a second admin: the first call
+/// opens an approval request.
 pub async fn release_milestone(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(approval): Query<ApprovalQuery>,
     Json(request): Json<ReleaseMilestoneRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut contract_client = ContractClient::new(state.pool.clone());
-    
-    match contract_client.release_milestone(
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+    let payload = serde_json::json!({
+        "project_id": request.project_id,
+        "milestone_id": request.milestone_id,
+        "attestation_signature": request.attestation_signature
+    });
+    if let Some(pending) = dual_control(&state, &headers, approvals::MILESTONE_RELEASE, approval.approval_id, payload).await? {
+        return Ok(pending);
+    }
+
+    let failed = |e: anyhow::Error| {
+        tracing::error!("Failed to release milestone {}: {}", request.milestone_id, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "Failed to release milestone"})))
+    };
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(failed)?;
+
+    let result = contract_client
+        .release_milestone(request.project_id, &request.milestone_id, &request.attestation_signature)
+        .await
+        .map_err(failed)?;
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": result,
+        "milestone_id": request.milestone_id
+    }))
+    .into_response())
+}
+
+/// Open a donor vote on a milestone (admin only)
+pub async fn open_milestone_vote(
+    State(state): State<AppState>,
+    Json(request): Json<OpenMilestoneVoteRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": "Failed to load contracts"})),
+        )
+    })?;
+
+    let tally = contract_client
+        .open_milestone_vote(request.project_id, &request.milestone_id, request.window_secs)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        "milestone_vote_opened",
         request.project_id,
-        &request.milestone_id,
-        &request.attestation_signature,
-    ).await {
-        Ok(result) => Ok(Json(serde_json::json!({
-            "success": true,
-            "message": result,
-            "milestone_id": request.milestone_id
+        "project",
+        serde_json::json!({
+            "milestone_id": request.milestone_id,
+            "closes_at": tally.closes_at
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "milestone_id": request.milestone_id,
+        "tally": tally
+    })))
+}
+
+/// Approve or dispute a milestone as a project donor
+pub async fn cast_milestone_vote(
+    State(state): State<AppState>,
+    Path((project_id, milestone_id)): Path<(Uuid, String)>,
+    Json(request): Json<CastMilestoneVoteRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": "Failed to load contracts"})),
+        )
+    })?;
+
+    let weight = contract_client
+        .cast_milestone_vote(project_id, &milestone_id, &request.voter_address, request.approve)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "milestone_id": milestone_id,
+        "voter_address": request.voter_address,
+        "approve": request.approve,
+        "weight_stroops": weight,
+        "weight_xlm": Stroops::from_stroops(weight)
+    })))
+}
+
+/// Get the donor vote tally for a milestone
+pub async fn get_milestone_votes(
+    State(state): State<AppState>,
+    Path((project_id, milestone_id)): Path<(Uuid, String)>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match contract_client.get_vote_tally(project_id, &milestone_id).await {
+        Ok(Some(tally)) => Ok(Json(serde_json::json!({
+            "project_id": project_id,
+            "milestone_id": milestone_id,
+            "blocking_reason": tally.blocking_reason(chrono::Utc::now()),
+            "tally": tally
         }))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
@@ -116,7 +297,8 @@ pub async fn record_deposit(
     State(state): State<AppState>,
     Json(request): Json<RecordDepositRequest>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut contract_client = ContractClient::new(state.pool.clone());
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
     let deposit = DepositInfo {
         project_id: request.project_id,
@@ -127,10 +309,10 @@ pub async fn record_deposit(
     };
 
     match contract_client.record_deposit(&deposit).await {
-        Ok(result) => Ok(Json(serde_json::json!({
+        Ok(tx_hash) => Ok(Json(serde_json::json!({
             "success": true,
-            "message": result,
-            "tx_hash": request.tx_hash
+            "message": "Deposit recorded",
+            "tx_hash": tx_hash
         }))),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
@@ -141,13 +323,33 @@ pub async fn get_project_balance(
     State(state): State<AppState>,
     Path(project_id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut contract_client = ContractClient::new(state.pool.clone());
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
     match contract_client.get_project_balance(project_id).await {
         Ok(balance) => Ok(Json(serde_json::json!({
             "project_id": project_id,
             "balance_stroops": balance,
-            "balance_xlm": balance as f64 / 10_000_000.0
+            "balance_xlm": Stroops::from_stroops(balance)
+        }))),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Get a donor's cumulative deposits to a project's escrow
+pub async fn get_donor_total(
+    State(state): State<AppState>,
+    Path((project_id, donor_address)): Path<(Uuid, String)>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    match contract_client.get_donor_total(project_id, &donor_address).await {
+        Ok(total) => Ok(Json(serde_json::json!({
+            "project_id": project_id,
+            "donor_address": donor_address,
+            "total_stroops": total,
+            "total_xlm": Stroops::from_stroops(total)
         }))),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
@@ -158,7 +360,7 @@ pub async fn get_project_milestones(
     State(state): State<AppState>,
     Path(project_id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut contract_client = ContractClient::new(state.pool.clone());
+    let contract_client = ContractClient::new(state.pool.clone(), state.network);
     
     match contract_client.get_project_milestones(project_id).await {
         Ok(milestones) => Ok(Json(serde_json::json!({
@@ -169,11 +371,39 @@ pub async fn get_project_milestones(
     }
 }
 
+/// Contract events picked up by the event indexer, newest first
+pub async fn get_onchain_events(
+    State(state): State<AppState>,
+    Query(query): Query<OnchainEventsQuery>,
+) -> Result<Json<Vec<OnchainEvent>>, StatusCode> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    sqlx::query_as!(
+        OnchainEvent,
+        r#"
+        SELECT event_id, contract_name, event_type, ledger, ledger_closed_at, tx_hash,
+               project_id, milestone_id, address, amount_stroops
+        FROM onchain_events
+        WHERE ($1::UUID IS NULL OR project_id = $1)
+          AND ($2::TEXT IS NULL OR event_type = $2)
+        ORDER BY ledger DESC, event_id DESC
+        LIMIT $3
+        "#,
+        query.project_id,
+        query.event_type,
+        limit
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map(Json)
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
 /// Get contract addresses
 pub async fn get_contract_addresses(
     State(state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut contract_client = ContractClient::new(state.pool.clone());
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
     
     match contract_client.load_contracts().await {
         Ok(_) => {
@@ -183,6 +413,7 @@ pub async fn get_contract_addresses(
             
             Ok(Json(serde_json::json!({
                 "success": true,
+                "network": state.network,
                 "contracts": addresses
             })))
         },
@@ -25,250 +25,1143 @@ pub async fn api_info() -> Result<Json<ApiInfo>, StatusCode> {
         EndpointInfo {
             method: "POST".to_string(),
             path: "/api/auth/signup".to_string(),
-            description: "Register a new user account".to_string(),
+            description: "Register a new user account; a verification link is emailed before login is allowed".to_string(),
             category: "Authentication".to_string(),
             auth_required: false,
         },
         EndpointInfo {
             method: "POST".to_string(),
             path: "/api/auth/login".to_string(),
-            description: "Login with email and password".to_string(),
+            description: "Login with email and password; accounts with 2FA get a pre-auth token for /api/auth/2fa/verify. Repeated failures are throttled (429 with Retry-After), may require a captcha_token, and lock the account for a while".to_string(),
             category: "Authentication".to_string(),
             auth_required: false,
         },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/auth/verify-email".to_string(),
+            description: "Confirm an email address with the token from the verification link".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/auth/resend-verification".to_string(),
+            description: "Email a new verification link (rate limited per account)".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/auth/forgot-password".to_string(),
+            description: "Email a single-use password reset link that expires in an hour".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/auth/reset-password".to_string(),
+            description: "Set a new password with a reset token; signs out existing sessions".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/auth/2fa".to_string(),
+            description: "Whether the caller has two-factor authentication on, whether it is required, and backup codes left".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/auth/2fa/setup".to_string(),
+            description: "Start TOTP enrollment (admin and student accounts); returns the secret, otpauth URI and QR code".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/auth/2fa/enable".to_string(),
+            description: "Confirm enrollment with a code; returns single-use backup codes".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/auth/2fa/disable".to_string(),
+            description: "Turn off two-factor authentication with a current or backup code".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/auth/2fa/verify".to_string(),
+            description: "Finish a two-factor login with the pre-auth token and a code".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/.well-known/jwks.json".to_string(),
+            description: "Public keys (JWKS) for validating access tokens by key id".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/auth/sessions".to_string(),
+            description: "List the devices you're signed in on".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/auth/sessions".to_string(),
+            description: "Sign out of every other device".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/auth/sessions/:session_id".to_string(),
+            description: "Sign out of one device".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: true,
+        },
         EndpointInfo {
             method: "POST".to_string(),
             path: "/api/auth/logout".to_string(),
-            description: "Logout user".to_string(),
+            description: "Logout user, revoking the current device session".to_string(),
+            category: "Authentication".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/auth/profile/:user_id".to_string(),
+            description: "Get user profile".to_string(),
             category: "Authentication".to_string(),
             auth_required: true,
         },
+        
+        // Students
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/students".to_string(),
+            description: "Create a new student profile".to_string(),
+            category: "Students".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/students/:id".to_string(),
+            description: "Get student profile by ID".to_string(),
+            category: "Students".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/students/:id".to_string(),
+            description: "Update student profile".to_string(),
+            category: "Students".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/students".to_string(),
+            description: "List all students".to_string(),
+            category: "Students".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/students/documents".to_string(),
+            description: "Upload a verification document (multipart `student_id` or `verification_id`, `document_type`, `file`, up to 20 MB), streamed to object storage with its SHA-256 recorded".to_string(),
+            category: "Students".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/students/verification/confirm-email".to_string(),
+            description: "Confirm the school email on your verification application with the six-digit code emailed to it; unconfirmed applications don't reach the admin queue and can't be approved".to_string(),
+            category: "Students".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/students/verification/resend-code".to_string(),
+            description: "Email a new school email code, at most once a minute; the code expires after 15 minutes".to_string(),
+            category: "Students".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/files/:id/download".to_string(),
+            description: "A presigned download link for a stored file, valid for STORAGE_DOWNLOAD_URL_TTL_SECS, once it has been scanned clean of malware (owner or admin)".to_string(),
+            category: "Files".to_string(),
+            auth_required: true,
+        },
+        
+        // Reports
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/reports".to_string(),
+            description: "Flag a project, comment or profile (by user id) as spam, abuse, fraud, inappropriate or other; content reported by REPORT_AUTO_HIDE_THRESHOLD different users is hidden until a moderator reviews it".to_string(),
+            category: "Reports".to_string(),
+            auth_required: true,
+        },
+        
+        // Wallets
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/wallets/connect".to_string(),
+            description: "Link a Stellar wallet; it stays unverified until a challenge is signed".to_string(),
+            category: "Wallets".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/wallets/challenge".to_string(),
+            description: "Get a SEP-10 challenge transaction for the wallet to sign".to_string(),
+            category: "Wallets".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/wallets/verify-challenge".to_string(),
+            description: "Verify a signed SEP-10 challenge and mark the wallet connected".to_string(),
+            category: "Wallets".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/wallets/claimable".to_string(),
+            description: "List claimable balances created for the caller".to_string(),
+            category: "Wallets".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/wallets/resolve/:address".to_string(),
+            description: "Resolve a federation or muxed address to the account to pay".to_string(),
+            category: "Wallets".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/wallets/balance/:wallet_id".to_string(),
+            description: "Get wallet balance".to_string(),
+            category: "Wallets".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/wallets/transactions/:wallet_id".to_string(),
+            description: "Get wallet transactions".to_string(),
+            category: "Wallets".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/wallets/:wallet_id/ledger-history".to_string(),
+            description: "Get indexed on-chain operations for a wallet".to_string(),
+            category: "Wallets".to_string(),
+            auth_required: true,
+        },
+        
+        // Donations
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/donations/initiate".to_string(),
+            description: "Initiate a donation; Stellar donations include a SEP-7 pay URI and QR code. Signed-in donors may set is_anonymous".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/donations/verify".to_string(),
+            description: "Verify a donation".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/donations/:project_id".to_string(),
+            description: "Get donations for a project".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/donations/student/:student_id".to_string(),
+            description: "Get donations to a student's projects, newest first (cursor-paginated)".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/donations/mine".to_string(),
+            description: "The caller's donations (cursor-paginated), per-project totals, and milestones their donations helped release".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/guest/claim".to_string(),
+            description: "Link the guest donations made with the caller's verified email to their account and donation history".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/donations/:id/receipt".to_string(),
+            description: "Download the signed PDF receipt for one of the caller's confirmed donations".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/donations/receipts/annual/:year".to_string(),
+            description: "Download the caller's signed PDF donation summary for a year".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/donations/subscriptions".to_string(),
+            description: "Start a monthly recurring gift by card (Stripe) or Stellar; card subscriptions return a setup URL. Off (404) unless the recurring_donations feature flag is on for the caller".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/donations/subscriptions".to_string(),
+            description: "List the caller's recurring gifts".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/donations/subscriptions/:id".to_string(),
+            description: "Cancel a recurring gift".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/donations/subscriptions/project/:project_id".to_string(),
+            description: "Recurring gift analytics for a project (owner or admin)".to_string(),
+            category: "Donations".to_string(),
+            auth_required: true,
+        },
+        
+        // Campaigns
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/campaigns/create".to_string(),
+            description: "Create a new campaign; criteria is JSON filtering on verification_status, min/max_project_age_days, min/max_funds_raised_xlm and schools, distribution_strategy is equal (default), weighted_by_funds or fixed_per_head with amount_xlm and max_recipients; optional start_date and end_date are run by the campaign scheduler, and distribution_trigger end_date (default) pays out at the end while manual waits for execute".to_string(),
+            category: "Campaigns".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/campaigns/execute".to_string(),
+            description: "Queue a payout job for active and ended campaigns (needs campaigns.execute and a second admin's approval; pass ?approval_id=)".to_string(),
+            category: "Campaigns".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/campaigns/active".to_string(),
+            description: "List active campaigns".to_string(),
+            category: "Campaigns".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/campaigns/stats".to_string(),
+            description: "Get campaign statistics".to_string(),
+            category: "Campaigns".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/campaigns/:id".to_string(),
+            description: "Get campaign by ID".to_string(),
+            category: "Campaigns".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/campaigns/:id".to_string(),
+            description: "Update campaign; criteria and distribution_strategy are checked like on create".to_string(),
+            category: "Campaigns".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/campaigns/:id".to_string(),
+            description: "Delete campaign".to_string(),
+            category: "Campaigns".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/campaigns/:id/pause".to_string(),
+            description: "Pause campaign".to_string(),
+            category: "Campaigns".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/campaigns/:id/resume".to_string(),
+            description: "Resume campaign".to_string(),
+            category: "Campaigns".to_string(),
+            auth_required: true,
+        },
+        
+        // Analytics
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/analytics/platform/stats".to_string(),
+            description: "Get platform statistics".to_string(),
+            category: "Analytics".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/analytics/projects/top".to_string(),
+            description: "Get top performing projects; ?category= ranks within one category".to_string(),
+            category: "Analytics".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/analytics/students/top".to_string(),
+            description: "Get top performing students".to_string(),
+            category: "Analytics".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/analytics/donors/top".to_string(),
+            description: "Get top donors; anonymous donors are pooled except for admins".to_string(),
+            category: "Analytics".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/analytics/campaigns/performance".to_string(),
+            description: "Get campaign performance metrics".to_string(),
+            category: "Analytics".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/analytics/donations/trends".to_string(),
+            description: "Get donation trends".to_string(),
+            category: "Analytics".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/analytics/projects/:id".to_string(),
+            description: "Get project-specific analytics".to_string(),
+            category: "Analytics".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/analytics/students/:id".to_string(),
+            description: "Get student-specific analytics".to_string(),
+            category: "Analytics".to_string(),
+            auth_required: true,
+        },
+        
+        // Donors
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/donors/me/tax-summary".to_string(),
+            description: "Yearly donation tax summary (json, csv, or pdf)".to_string(),
+            category: "Donors".to_string(),
+            auth_required: true,
+        },
+        
+        // Admin
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/students".to_string(),
+            description: "List all students (admin or moderator)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/verify-student".to_string(),
+            description: "Verify a student (admin or moderator)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/fund-student".to_string(),
+            description: "Pay XLM from the platform wallet to a student's connected wallet (admin or finance)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/payouts".to_string(),
+            description: "List mobile money milestone payouts by status (admin or finance)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/payouts/:id/approve".to_string(),
+            description: "Approve and send a mobile money payout via M-Pesa B2C (admin or finance)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/payouts/:id/reject".to_string(),
+            description: "Reject a pending mobile money payout (admin or finance)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/payment-providers".to_string(),
+            description: "List payment providers with their stored settings and whether each is loaded (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/admin/payment-providers/:name".to_string(),
+            description: "Enable, disable or reconfigure a payment provider without a redeploy (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/ledger/trial-balance".to_string(),
+            description: "Debits, credits and balance of every ledger account, with per-currency totals (admin or finance)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/ledger/accounts/:account/statement".to_string(),
+            description: "Ledger entries for one account with its running balance (admin or finance)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/fees/report".to_string(),
+            description: "Monthly platform fee income by payment method (admin or finance)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/refunds".to_string(),
+            description: "Refund a fiat payment through its provider (admin with payments.refund; needs a second admin's approval, pass ?approval_id=)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/api-keys".to_string(),
+            description: "List integration API keys with their scopes, limits and last use (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/api-keys".to_string(),
+            description: "Issue an API key with scopes (projects:read, donations:read), a per-minute rate limit and optional expiry; the key is only shown in this response (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/admin/api-keys/:id".to_string(),
+            description: "Revoke an API key (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/webhooks/endpoints".to_string(),
+            description: "List your webhook endpoints".to_string(),
+            category: "Webhooks".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/webhooks/endpoints".to_string(),
+            description: "Register an https endpoint for donation.confirmed, milestone.released or verification.approved events about your projects; optionally narrowed to one project_id. The signing secret is only shown in this response".to_string(),
+            category: "Webhooks".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/webhooks/endpoints/:id".to_string(),
+            description: "Disable one of your webhook endpoints; its pending events are dead-lettered".to_string(),
+            category: "Webhooks".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/webhooks/endpoints".to_string(),
+            description: "List every webhook endpoint, owners' and partners' (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/webhooks/endpoints".to_string(),
+            description: "Register a partner webhook endpoint that receives every event it subscribes to (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/admin/webhooks/endpoints/:id".to_string(),
+            description: "Disable any webhook endpoint (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/webhooks/events".to_string(),
+            description: "List outgoing webhook events; filter with ?status=pending|delivered|dead and ?endpoint_id= (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/webhooks/events/:id/redeliver".to_string(),
+            description: "Requeue a dead-lettered webhook event with a fresh set of attempts (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/workers".to_string(),
+            description: "Each background worker's last run, last success and error, items processed, paused state and queue lag (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/ops/jobs".to_string(),
+            description: "List background jobs (emails, campaign payouts, settlement, reconciliation); filter with ?status=queued|succeeded|dead (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/ops/jobs/:id/retry".to_string(),
+            description: "Requeue a dead-lettered job with a fresh set of attempts (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/files/:id/verify".to_string(),
+            description: "Re-hash a stored file and compare it with the checksum recorded at upload (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/files/scans".to_string(),
+            description: "Uploaded files with their malware scan status, matched signature or scanner error; filter with ?status=pending|clean|quarantined|failed (admin only, cursor-paginated)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/files/:id/rescan".to_string(),
+            description: "Queue a file to be scanned for malware again (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/verifications/:id/documents".to_string(),
+            description: "The documents a student submitted for verification, with their accept/reject reviews and presigned links to view those scanned clean (admin or moderator)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/admin/verifications/:id/documents/:file_id".to_string(),
+            description: "Accept or reject a verification document with an optional note; approving a verification needs at least one accepted document (admin or moderator)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/reports".to_string(),
+            description: "The moderation queue, newest first, with how many open reports each piece of content has and whether it's hidden; filter by status (default pending) and target_type, paginated by cursor (admin or moderator)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/reports/:id/resolve".to_string(),
+            description: "Resolve a report and every other open report on the same content: dismiss (restoring auto-hidden content), hide the content, or hide it and suspend its author (admin or moderator)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/verifications/bulk".to_string(),
+            description: "Approve or reject up to 100 pending verifications with a shared message in one transaction, with a result per item; `atomic: true` rolls all back if any fails (admin or moderator)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/verifications/preapproved".to_string(),
+            description: "Import a CSV of admission numbers schools have vouched for (admission_number, school_domain, optional full_name); matching applications with a confirmed school email are approved automatically (admin or moderator)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/verifications/preapproved".to_string(),
+            description: "Imported pre-approved admission numbers and the verifications they matched (admin or moderator, cursor-paginated)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/announcements".to_string(),
+            description: "Announcements, newest first, optionally by status, with recipients, reads, read rate and digest emails (admin only, cursor-paginated)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/announcements".to_string(),
+            description: "Send an announcement (general, maintenance with its window, or campaign) now or at publish_at as a notification and SSE event to active users in the given roles and cohort; with email_digest, unread ones go out in the daily email digest (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/announcements/:id".to_string(),
+            description: "An announcement with its read-rate stats (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/announcements/:id/cancel".to_string(),
+            description: "Cancel a scheduled announcement before it's sent (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/feature-flags".to_string(),
+            description: "Every feature flag with its environments and rollout percentage, and this server's APP_ENV (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/feature-flags".to_string(),
+            description: "Add a feature flag, off unless enabled, optionally limited to some environments and a percentage of signed-in users (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/admin/feature-flags/:name".to_string(),
+            description: "Replace a feature flag's settings; takes effect here at once and on other instances within FEATURE_FLAG_CACHE_TTL_MS (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/admin/feature-flags/:name".to_string(),
+            description: "Delete a feature flag, turning it off everywhere (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/admin/university-domains".to_string(),
+            description: "School email domains accepted for student verification, with their policies (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/university-domains".to_string(),
+            description: "List a school domain, exact (uonbi.ac.ke) or wildcard over subdomains (*.ac.ke), with a policy: manual review, auto_verify on application, or blocked (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/admin/university-domains/:id".to_string(),
+            description: "Replace a school domain's pattern, name, policy or active flag (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
         EndpointInfo {
-            method: "GET".to_string(),
-            path: "/api/auth/profile/:user_id".to_string(),
-            description: "Get user profile".to_string(),
-            category: "Authentication".to_string(),
+            method: "DELETE".to_string(),
+            path: "/api/admin/university-domains/:id".to_string(),
+            description: "Remove a school domain from the list (admin only)".to_string(),
+            category: "Admin".to_string(),
             auth_required: true,
         },
-        
-        // Students
         EndpointInfo {
             method: "POST".to_string(),
-            path: "/api/students".to_string(),
-            description: "Create a new student profile".to_string(),
-            category: "Students".to_string(),
+            path: "/api/admin/categories".to_string(),
+            description: "Add a project category; the slug is derived from the name unless given (moderator)".to_string(),
+            category: "Admin".to_string(),
             auth_required: true,
         },
         EndpointInfo {
-            method: "GET".to_string(),
-            path: "/api/students/:id".to_string(),
-            description: "Get student profile by ID".to_string(),
-            category: "Students".to_string(),
+            method: "DELETE".to_string(),
+            path: "/api/admin/categories/:slug".to_string(),
+            description: "Remove a category; its projects keep their tags (moderator)".to_string(),
+            category: "Admin".to_string(),
             auth_required: true,
         },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/categories".to_string(),
+            description: "List project categories with their active project counts".to_string(),
+            category: "Projects".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/projects/category/:slug".to_string(),
+            description: "Active projects in a category, newest first (cursor-paginated)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: false,
+        },
         EndpointInfo {
             method: "PUT".to_string(),
-            path: "/api/students/:id".to_string(),
-            description: "Update student profile".to_string(),
-            category: "Students".to_string(),
+            path: "/api/projects/:id/tags".to_string(),
+            description: "Set a project's tags (normalized to lowercase-hyphenated, deduplicated, at most 10) and category slugs (owner or admin)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
-            method: "GET".to_string(),
-            path: "/api/students".to_string(),
-            description: "List all students".to_string(),
-            category: "Students".to_string(),
+            method: "POST".to_string(),
+            path: "/api/projects/:id/complete".to_string(),
+            description: "Complete an active project once every milestone is released or cancelled: settles the remaining balance (payout or refund; admins may pick), freezes edits and emails donors a completion report (owner or admin)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
-        
-        // Wallets
         EndpointInfo {
             method: "POST".to_string(),
-            path: "/api/wallets/connect".to_string(),
-            description: "Connect a Stellar wallet".to_string(),
-            category: "Wallets".to_string(),
+            path: "/api/projects/:id/milestones/:milestone_id/cancel".to_string(),
+            description: "Cancel an unreleased milestone so the project can be completed (owner or admin)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/projects/:id/cancel".to_string(),
+            description: "Cancel a project with an optional reason: stops donations, ends recurring gifts and refunds donors (escrow deposits on-chain, fiat payments through their provider) in the background (owner or admin)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/wallets/balance/:wallet_id".to_string(),
-            description: "Get wallet balance".to_string(),
-            category: "Wallets".to_string(),
+            path: "/api/projects/:id/refunds".to_string(),
+            description: "Refund progress for a cancelled project by status and currency; admins also get the refunds that failed (owner or admin)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/wallets/transactions/:wallet_id".to_string(),
-            description: "Get wallet transactions".to_string(),
-            category: "Wallets".to_string(),
+            path: "/api/projects/:id/revisions".to_string(),
+            description: "Paginated edit history with each revision's field diff; funding goal and milestone changes to published projects stay pending until reviewed (team or admin)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
-        
-        // Donations
         EndpointInfo {
             method: "POST".to_string(),
-            path: "/api/donations/initiate".to_string(),
-            description: "Initiate a donation".to_string(),
-            category: "Donations".to_string(),
+            path: "/api/projects/:id/revisions/:revision_id/approve".to_string(),
+            description: "Approve and apply a pending revision, with an optional note; 409 if the project changed since (projects.publish)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "POST".to_string(),
-            path: "/api/donations/verify".to_string(),
-            description: "Verify a donation".to_string(),
-            category: "Donations".to_string(),
+            path: "/api/projects/:id/revisions/:revision_id/reject".to_string(),
+            description: "Reject a pending revision with an optional note (projects.publish)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/donations/:project_id".to_string(),
-            description: "Get donations for a project".to_string(),
-            category: "Donations".to_string(),
+            path: "/api/projects/:id/media".to_string(),
+            description: "The project's image and video gallery in order; the team also sees unfinished uploads".to_string(),
+            category: "Projects".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/projects/:id/media".to_string(),
+            description: "Upload an image (PNG, JPEG, WebP or GIF, up to 10 MB) as multipart `file` with an optional `caption` (team; 503 if storage is not configured)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
-            method: "GET".to_string(),
-            path: "/api/donations/student/:student_id".to_string(),
-            description: "Get donations by a student".to_string(),
-            category: "Donations".to_string(),
+            method: "POST".to_string(),
+            path: "/api/projects/:id/media/uploads".to_string(),
+            description: "Start a direct upload: adds a pending image and returns a presigned PUT URL valid for 15 minutes (team)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
-        
-        // Campaigns
         EndpointInfo {
             method: "POST".to_string(),
-            path: "/api/campaigns/create".to_string(),
-            description: "Create a new campaign".to_string(),
-            category: "Campaigns".to_string(),
+            path: "/api/projects/:id/media/:media_id/complete".to_string(),
+            description: "Confirm a direct upload once the file is in storage; checks its size and, when one was given, its SHA-256 `checksum` (team)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "POST".to_string(),
-            path: "/api/campaigns/execute".to_string(),
-            description: "Execute a campaign".to_string(),
-            category: "Campaigns".to_string(),
+            path: "/api/projects/:id/media/links".to_string(),
+            description: "Add a video, or an externally hosted image, by URL (team)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/projects/:id/media/order".to_string(),
+            description: "Reorder the gallery with every item's id; the first image becomes the cover (team)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/projects/:id/media/:media_id".to_string(),
+            description: "Change or clear an item's caption (team)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/projects/:id/media/:media_id".to_string(),
+            description: "Remove an item and its stored file (team)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/campaigns/active".to_string(),
-            description: "List active campaigns".to_string(),
-            category: "Campaigns".to_string(),
+            path: "/api/projects/:id/comments".to_string(),
+            description: "Comment threads on a project, newest first, with replies and owner/donor/verified badges on each commenter (cursor-paginated)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/projects/:id/comments".to_string(),
+            description: "Comment on an active project, or reply with parent_id; notifies the owner".to_string(),
+            category: "Projects".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/projects/:id/comments/:comment_id".to_string(),
+            description: "Delete a comment (author, project owner or admin); replies stay under a placeholder".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/campaigns/stats".to_string(),
-            description: "Get campaign statistics".to_string(),
-            category: "Campaigns".to_string(),
+            path: "/api/projects/:id/updates".to_string(),
+            description: "Progress updates on a project, newest first; the owner and admins also see drafts (cursor-paginated)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/projects/:id/updates".to_string(),
+            description: "Post a progress update with optional media and milestone; published and sent to donors and followers unless draft is set (owner)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/campaigns/:id".to_string(),
-            description: "Get campaign by ID".to_string(),
-            category: "Campaigns".to_string(),
+            path: "/api/projects/:id/updates/:update_id".to_string(),
+            description: "Get one update; drafts only for the owner and admins".to_string(),
+            category: "Projects".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/projects/:id/updates/:update_id".to_string(),
+            description: "Replace an update's title, body, media and milestone (owner or admin)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/projects/:id/updates/:update_id".to_string(),
+            description: "Delete an update (owner or admin)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/projects/:id/updates/:update_id/publish".to_string(),
+            description: "Publish a draft update and notify donors and followers (owner)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/projects/:id/members".to_string(),
+            description: "A project's team with roles and payout shares; members and admins also see open invitations".to_string(),
+            category: "Projects".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/projects/:id/members".to_string(),
+            description: "Invite a verified student onto the team with a share (bps) of milestone payouts (owner or admin)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/projects/:id/members/accept".to_string(),
+            description: "Accept the caller's invitation to a project's team".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "PUT".to_string(),
-            path: "/api/campaigns/:id".to_string(),
-            description: "Update campaign".to_string(),
-            category: "Campaigns".to_string(),
+            path: "/api/projects/:id/members/:student_id".to_string(),
+            description: "Change a member's payout share; the owner keeps whatever members don't take (owner or admin)".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "DELETE".to_string(),
-            path: "/api/campaigns/:id".to_string(),
-            description: "Delete campaign".to_string(),
-            category: "Campaigns".to_string(),
+            path: "/api/projects/:id/members/:student_id".to_string(),
+            description: "Remove a member or withdraw an invitation (owner or admin); members can leave or decline themselves".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "POST".to_string(),
-            path: "/api/campaigns/:id/pause".to_string(),
-            description: "Pause campaign".to_string(),
-            category: "Campaigns".to_string(),
+            path: "/api/projects/:id/follow".to_string(),
+            description: "Follow a project for notifications on updates, new milestones and funding thresholds (25/50/75/100%)".to_string(),
+            category: "Projects".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/projects/:id/follow".to_string(),
+            description: "Stop following a project".to_string(),
+            category: "Projects".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "POST".to_string(),
-            path: "/api/campaigns/:id/resume".to_string(),
-            description: "Resume campaign".to_string(),
-            category: "Campaigns".to_string(),
+            path: "/api/students/:student_id/follow".to_string(),
+            description: "Follow a student for notifications on all of their projects".to_string(),
+            category: "Students".to_string(),
             auth_required: true,
         },
-        
-        // Analytics
         EndpointInfo {
-            method: "GET".to_string(),
-            path: "/api/analytics/platform/stats".to_string(),
-            description: "Get platform statistics".to_string(),
-            category: "Analytics".to_string(),
+            method: "DELETE".to_string(),
+            path: "/api/students/:student_id/follow".to_string(),
+            description: "Stop following a student".to_string(),
+            category: "Students".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/analytics/projects/top".to_string(),
-            description: "Get top performing projects".to_string(),
-            category: "Analytics".to_string(),
+            path: "/api/users/me/following".to_string(),
+            description: "Projects and students you follow, most recent first (cursor-paginated)".to_string(),
+            category: "Users".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/analytics/students/top".to_string(),
-            description: "Get top performing students".to_string(),
-            category: "Analytics".to_string(),
+            path: "/api/admin/roles".to_string(),
+            description: "List roles and the permissions each grants (admin only)".to_string(),
+            category: "Admin".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/analytics/campaigns/performance".to_string(),
-            description: "Get campaign performance metrics".to_string(),
-            category: "Analytics".to_string(),
+            path: "/api/admin/users/:user_id/roles".to_string(),
+            description: "A user's primary role, assigned roles and effective permissions (admin only)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/admin/users/:user_id/roles".to_string(),
+            description: "Assign a role to a user (admin with roles.manage)".to_string(),
+            category: "Admin".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/admin/users/:user_id/roles/:role".to_string(),
+            description: "Revoke an assigned role (admin with roles.manage)".to_string(),
+            category: "Admin".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/analytics/donations/trends".to_string(),
-            description: "Get donation trends".to_string(),
-            category: "Analytics".to_string(),
+            path: "/api/admin/refunds".to_string(),
+            description: "List refunds by status or payment (admin or finance)".to_string(),
+            category: "Admin".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/analytics/projects/:id".to_string(),
-            description: "Get project-specific analytics".to_string(),
-            category: "Analytics".to_string(),
+            path: "/api/admin/refunds/:id".to_string(),
+            description: "Get a refund and its progress (admin or finance)".to_string(),
+            category: "Admin".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/analytics/students/:id".to_string(),
-            description: "Get student-specific analytics".to_string(),
-            category: "Analytics".to_string(),
+            path: "/api/admin/approvals".to_string(),
+            description: "List dual-control approval requests by status (admin or finance)".to_string(),
+            category: "Admin".to_string(),
             auth_required: true,
         },
-        
-        // Admin
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/admin/students".to_string(),
-            description: "List all students (admin only)".to_string(),
+            path: "/api/admin/approvals/:id".to_string(),
+            description: "Get an approval request and the payload it covers (admin or finance)".to_string(),
             category: "Admin".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "POST".to_string(),
-            path: "/api/admin/verify-student".to_string(),
-            description: "Verify a student (admin only)".to_string(),
+            path: "/api/admin/approvals/:id/approve".to_string(),
+            description: "Approve another admin's milestone release, refund or campaign execution".to_string(),
             category: "Admin".to_string(),
             auth_required: true,
         },
         EndpointInfo {
             method: "POST".to_string(),
-            path: "/api/admin/fund-student".to_string(),
-            description: "Fund a student (admin only)".to_string(),
+            path: "/api/admin/approvals/:id/reject".to_string(),
+            description: "Reject a pending approval request with an optional note".to_string(),
             category: "Admin".to_string(),
             auth_required: true,
         },
@@ -276,11 +1169,74 @@ pub async fn api_info() -> Result<Json<ApiInfo>, StatusCode> {
         // Notifications
         EndpointInfo {
             method: "GET".to_string(),
-            path: "/api/notifications/stream".to_string(),
-            description: "Stream real-time notifications (SSE)".to_string(),
+            path: "/api/notifications/sse".to_string(),
+            description: "Stream the caller's real-time notifications (SSE): their own events, those of projects they own, joined, follow or donated to, and admin alerts for admins. One named event per notification event carrying its fields, the entity it is about and a deep link. Pass the JWT as a bearer token or, for EventSource, as ?token=. Events carry ids; reconnecting with Last-Event-ID replays recently buffered events after it. Keep-alive comments are sent every SSE_KEEP_ALIVE_MS".to_string(),
+            category: "Notifications".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/notifications/ws".to_string(),
+            description: "The same notification events over a WebSocket, as {\"type\": \"event\", \"id\", ...}; authenticate on upgrade with a bearer token or ?token= and resume with ?last_event_id=. Clients may send {\"type\": \"subscribe\"|\"unsubscribe\", \"project_id\"} to follow a project for the connection and {\"type\": \"mark_read\", \"notification_id\"} as a read receipt. Pinged every WS_PING_INTERVAL_MS; silent connections are closed".to_string(),
+            category: "Notifications".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/notifications/push/public-key".to_string(),
+            description: "The VAPID public key browsers subscribe to Web Push with (503 when push is not configured)".to_string(),
+            category: "Notifications".to_string(),
+            auth_required: false,
+        },
+        EndpointInfo {
+            method: "POST".to_string(),
+            path: "/api/notifications/push/subscribe".to_string(),
+            description: "Register a browser's PushSubscription (endpoint and p256dh/auth keys) for Web Push of verification approvals, milestone releases and donations received".to_string(),
+            category: "Notifications".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "DELETE".to_string(),
+            path: "/api/notifications/push/subscribe".to_string(),
+            description: "Stop Web Push to a browser, by its endpoint".to_string(),
+            category: "Notifications".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/notifications/preferences".to_string(),
+            description: "How each event type reaches you (in-app, email, push), with your role's defaults filled in; email and push are null for event types never sent that way".to_string(),
+            category: "Notifications".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/notifications/preferences".to_string(),
+            description: "Turn in-app, email or push on or off per event type ({preferences: [{event_type, in_app?, email?, push?}]}); returns every preference".to_string(),
+            category: "Notifications".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/notifications/digest".to_string(),
+            description: "Your email digest settings: cadence (off, daily or weekly), send_hour (UTC), send_weekday (0 = Monday) and when the last one went out".to_string(),
+            category: "Notifications".to_string(),
+            auth_required: true,
+        },
+        EndpointInfo {
+            method: "PUT".to_string(),
+            path: "/api/notifications/digest".to_string(),
+            description: "Choose a daily or weekly digest of unread notifications, donations to your projects and updates from what you follow, and when it's sent".to_string(),
             category: "Notifications".to_string(),
             auth_required: true,
         },
+        EndpointInfo {
+            method: "GET".to_string(),
+            path: "/api/notifications/digest/unsubscribe".to_string(),
+            description: "Turn off digest emails with the ?token= from the link in one (POST also accepted, for one-click unsubscribe)".to_string(),
+            category: "Notifications".to_string(),
+            auth_required: false,
+        },
     ];
 
     Ok(Json(ApiInfo {
@@ -292,11 +1248,16 @@ pub async fn api_info() -> Result<Json<ApiInfo>, StatusCode> {
     }))
 }
 
-pub async fn health_check() -> Result<Json<serde_json::Value>, StatusCode> {
+pub async fn health_check(
+    State(state): State<crate::state::AppState>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let horizon = state.stellar.horizon_health();
     Ok(Json(serde_json::json!({
-        "status": "healthy",
+        "status": if horizon.degraded { "degraded" } else { "healthy" },
         "timestamp": chrono::Utc::now().to_rfc3339(),
         "version": "1.0.0",
+        "network": state.network,
+        "horizon": horizon,
         "service": "FundHub API",
         "uptime": "running"
     })))
@@ -652,6 +1613,10 @@ pub async fn docs_html() -> Html<&'static str> {
             <p>Most endpoints require authentication using Bearer tokens. Include your token in the Authorization header:</p>
             <div class="code-block">
 Authorization: Bearer your_jwt_token_here
+            </div>
+            <p>Server-to-server integrations can read project and donation data with an API key issued by an admin instead:</p>
+            <div class="code-block">
+X-Api-Key: fhk_your_key_here
             </div>
             <p><strong>Getting Started:</strong></p>
             <ol>
@@ -661,6 +1626,15 @@ Authorization: Bearer your_jwt_token_here
             </ol>
         </div>
 
+        <div class="section">
+            <h2>⚠️ Errors</h2>
+            <p>Errors share one JSON shape. <code>code</code> is stable and safe to branch on; quote <code>request_id</code> (also sent as the <code>X-Request-Id</code> header) when reporting a problem:</p>
+            <div class="code-block">
+{"error": "The request has invalid fields", "code": "validation_failed", "request_id": "…", "details": [{"field": "milestones[0].amount_xlm", "message": "Amount must be a positive XLM value"}]}
+            </div>
+            <p>Invalid request bodies answer <code>422</code> and list every failing field in <code>details</code>.</p>
+        </div>
+
         <div class="section">
             <h2>📋 Quick Start Guide</h2>
             <ol>
@@ -673,15 +1647,36 @@ Authorization: Bearer your_jwt_token_here
             </ol>
         </div>
 
+        <div class="section">
+            <h2>📄 Pagination</h2>
+            <p>Project, donation, notification and activity log lists return one page at a time, newest first. Ask for up to 100 items with <code>?limit=</code> (default 20) and pass <code>next_cursor</code> back as <code>?cursor=</code> for the next page:</p>
+            <div class="code-block">
+{"items": [...], "next_cursor": "MTcyOTYwMDAwMDEyMzQ1Nnw…", "has_more": true}
+            </div>
+            <p>Cursors are opaque; <code>next_cursor</code> is <code>null</code> on the last page.</p>
+        </div>
+
         <div class="section">
             <h2>📊 Rate Limiting</h2>
-            <p>API requests are rate limited to ensure fair usage:</p>
+            <p>API requests are rate limited to ensure fair usage. Signed-in requests count per user, others per IP address; authentication endpoints always count per IP:</p>
             <ul>
                 <li><strong>General endpoints:</strong> 100 requests per minute</li>
                 <li><strong>Authentication endpoints:</strong> 10 requests per minute</li>
                 <li><strong>Analytics endpoints:</strong> 50 requests per minute</li>
                 <li><strong>Admin endpoints:</strong> 20 requests per minute</li>
             </ul>
+            <p>Responses carry <code>X-RateLimit-Limit</code> and <code>X-RateLimit-Remaining</code>. Past the limit the API answers <code>429</code> with code <code>rate_limited</code> and a <code>Retry-After</code> header giving the seconds until the window resets.</p>
+        </div>
+
+        <div class="section">
+            <h2>🪝 Webhooks</h2>
+            <p>Project owners and partners can have platform events POSTed to an <code>https://</code> endpoint: <code>donation.confirmed</code>, <code>milestone.released</code> and <code>verification.approved</code>. Every delivery is signed with the endpoint's secret:</p>
+            <div class="code-block">
+X-FundHub-Event: donation.confirmed
+X-FundHub-Delivery: 6f1c…
+X-FundHub-Signature: t=1700000000,v1=hex(HMAC-SHA256(secret, "1700000000." + body))
+            </div>
+            <p>Recompute the signature over the raw body and reject stale timestamps. Answer with any <code>2xx</code>; other answers and timeouts are retried with backoff and, after 8 attempts, dead-lettered until an admin redelivers them. <code>X-FundHub-Delivery</code> stays the same across retries, so use it to ignore duplicates.</p>
         </div>
 
         <div class="section">
@@ -1,33 +1,51 @@
+use anyhow::Context;
 use axum::{
-    extract::{Json, Path, State},
-    http::StatusCode,
+    extract::{Json, Path, Query, State},
+    http::{HeaderMap, StatusCode},
 };
 use serde::{Deserialize, Serialize};
-use sqlx::types::BigDecimal;
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
     models::{Donation, DonationStatus, PaymentMethod},
+    routes::error::{AppError, AppResult},
+    routes::validation::{self, ValidatedJson},
+    services::contract_client::{ContractClient, OnchainProjectStatus},
+    services::donation_memo::{self, MemoKind},
+    services::notifications::{Channel, NotificationEvent},
+    services::{email, fees, follows, ledger, outgoing_webhooks},
+    services::sep7,
+    utils::money::Stroops,
+    utils::pagination::{Page, PageQuery, PageRequest},
 };
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct InitiateDonationRequest {
     pub donor_id: Option<Uuid>,
     pub project_id: Uuid,
+    #[validate(custom(function = "validation::positive_xlm"))]
     pub amount_xlm: String,
     pub payment_method: String,
+    #[validate(length(max = 500, message = "Memo must be at most 500 characters"))]
     pub memo: Option<String>,
+    /// Hide the donor from the project and leaderboards; requires signing in
+    #[serde(default)]
+    pub is_anonymous: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct PlatformDonationRequest {
-    pub amount: f64,
+    #[validate(custom(function = "validation::positive_stroops"))]
+    pub amount: Stroops,
+    #[validate(length(max = 500, message = "Message must be at most 500 characters"))]
     pub message: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct VerifyDonationRequest {
     pub donation_id: Uuid,
+    #[validate(length(equal = 64, message = "Transaction hash must be 64 hex characters"))]
     pub tx_hash: String,
 }
 
@@ -36,96 +54,157 @@ pub struct DonationResponse {
     pub donation_id: Uuid,
     pub status: String,
     pub payment_instruction: serde_json::Value,
+    /// Set when the project has a funding cap
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_capacity_xlm: Option<Stroops>,
+    /// Present when the donation would exceed the project's remaining capacity
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
 }
 
 pub async fn initiate(
     State(state): State<crate::state::AppState>,
-    Json(payload): Json<InitiateDonationRequest>,
-) -> Result<(StatusCode, Json<DonationResponse>), StatusCode> {
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<InitiateDonationRequest>,
+) -> AppResult<(StatusCode, Json<DonationResponse>)> {
+    // Anonymity is only offered to signed-in donors, who are recorded as the
+    // donor so admins can still see who gave
+    let donor_id = if payload.is_anonymous {
+        let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+            .map_err(|_| AppError::unauthorized("Sign in to donate anonymously"))?;
+        Some(user_id)
+    } else {
+        payload.donor_id
+    };
+
     // Get project with contract address
     let project = sqlx::query!(
         r#"
-        SELECT id, student_id, contract_address, status
+        SELECT id, status, onchain_status
         FROM projects 
         WHERE id = $1
         "#,
         payload.project_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Project not found"))?;
 
-    // Check project is active
-    if project.status != "active" {
-        return Err(StatusCode::BAD_REQUEST);
+    // Check project is active and still open for deposits on-chain
+    let accepts_deposits = OnchainProjectStatus::parse(&project.onchain_status).is_some_and(|s| s.accepts_deposits());
+    if project.status != "active" || !accepts_deposits {
+        return Err(AppError::Coded {
+            status: StatusCode::BAD_REQUEST,
+            code: "project_not_accepting_donations",
+            message: "This project isn't accepting donations".to_string(),
+            details: None,
+        });
     }
 
-    // Get student's wallet for escrow address
-    let wallet = sqlx::query!(
-        r#"
-        SELECT public_key FROM wallets WHERE student_id = $1
-        "#,
-        project.student_id
-    )
-    .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let amount: Stroops = payload.amount_xlm.parse().context("Invalid donation amount")?;
 
-    // Parse amount to BigDecimal
-    let amount: BigDecimal = payload.amount_xlm
-        .parse()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    // Warn donors up front if the escrow would only accept part of this donation
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    let remaining_capacity = match contract_client.load_contracts().await {
+        Ok(()) => contract_client
+            .get_remaining_capacity(payload.project_id)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to get remaining capacity: {}", e);
+                None
+            })
+            .map(Stroops::from_stroops),
+        Err(_) => None,
+    };
+    let warning = match remaining_capacity {
+        Some(remaining) if !remaining.is_positive() => {
+            Some("Project has reached its funding cap; this donation would be refused".to_string())
+        }
+        Some(remaining) if amount > remaining => Some(format!(
+            "Project can only accept {} XLM more; the excess would be refunded",
+            remaining
+        )),
+        _ => None,
+    };
 
-    // Generate donation ID and use as memo
+    // Create donation record under a short memo unique to this donation
     let donation_id = Uuid::new_v4();
-    let memo = format!("donation:{}", donation_id);
-
-    // Create donation record
-    let _donation = sqlx::query!(
-        r#"
-        INSERT INTO donations (
-            id,
+    let mut attempt = 0;
+    let memo = loop {
+        let memo = donation_memo::generate(MemoKind::Project, donation_id, attempt);
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO donations (
+                id,
+                donor_id,
+                project_id,
+                amount,
+                payment_method,
+                memo,
+                is_anonymous,
+                status
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, 'pending')
+            RETURNING id
+            "#,
+            donation_id,
             donor_id,
-            project_id,
-            amount,
-            payment_method,
+            payload.project_id,
+            amount.to_decimal(),
+            payload.payment_method,
             memo,
-            status
+            payload.is_anonymous,
         )
-        VALUES ($1, $2, $3, $4, $5, $6, 'pending')
-        RETURNING id
-        "#,
-        donation_id,
-        payload.donor_id,
-        payload.project_id,
-        amount,
-        payload.payment_method,
-        memo,
-    )
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .fetch_one(&state.pool)
+        .await;
+
+        match inserted {
+            Ok(_) => break memo,
+            Err(e) if donation_memo::is_memo_collision(&e) && attempt + 1 < donation_memo::MAX_MEMO_ATTEMPTS => {
+                attempt += 1;
+            }
+            Err(e) => return Err(anyhow::Error::new(e).context("Failed to create donation").into()),
+        }
+    };
 
     // Build payment instruction based on payment method
     let payment_instruction = match payload.payment_method.as_str() {
         "stellar" => {
-            let destination = wallet
-                .map(|w| w.public_key)
-                .or(project.contract_address)
-                .unwrap_or_else(|| std::env::var("PLATFORM_WALLET_PUBLIC_KEY").unwrap_or_default());
+            let destination = donation_memo::expected_destination(
+                &state.pool,
+                Some(payload.project_id),
+                state.escrow_mode,
+            )
+            .await
+            .context("Failed to resolve donation destination")?;
+
+            // One-tap payment link for wallet apps, also offered as a QR code
+            let pay_uri = sep7::PayRequest {
+                destination: &destination,
+                amount,
+                memo: Some(&memo),
+                asset: None,
+                network_passphrase: &state.network.network_passphrase(),
+            }
+            .to_uri();
+            let qr_code = sep7::qr_png_base64(&pay_uri)
+                .map_err(|e| tracing::warn!("Failed to render payment QR code: {}", e))
+                .ok();
 
             serde_json::json!({
                 "destination": destination,
-                "amount_xlm": payload.amount_xlm,
+                "amount_xlm": amount,
+                "asset": "native",
                 "memo": memo,
-                "memo_type": "text"
+                "memo_type": "text",
+                "sep7_uri": pay_uri,
+                "qr_code_png_base64": qr_code
             })
         }
         "mpesa" | "card" => {
             serde_json::json!({
                 "checkout_url": format!("/checkout/{}", donation_id),
-                "amount": payload.amount_xlm
+                "amount": amount
             })
         }
         _ => serde_json::json!({})
@@ -135,36 +214,40 @@ pub async fn initiate(
         donation_id,
         status: "pending".to_string(),
         payment_instruction,
+        remaining_capacity_xlm: remaining_capacity,
+        warning,
     })))
 }
 
 pub async fn verify(
     State(state): State<crate::state::AppState>,
-    Json(payload): Json<VerifyDonationRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    ValidatedJson(payload): ValidatedJson<VerifyDonationRequest>,
+) -> AppResult<Json<serde_json::Value>> {
     // Get donation
     let donation = sqlx::query!(
         r#"
-        SELECT id, project_id, amount, memo, status
+        SELECT id, donor_id, project_id, amount as "amount: Stroops", memo, status
         FROM donations
         WHERE id = $1
         "#,
         payload.donation_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Donation not found"))?;
 
     if donation.status != "pending" {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(AppError::conflict(format!("Donation is already {}", donation.status)));
     }
 
     // Verify transaction on Stellar network
     let is_valid = state.stellar
         .verify_transaction(&payload.tx_hash)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|e| {
+            tracing::warn!("Failed to look up transaction {}: {}", payload.tx_hash, e);
+            AppError::Upstream("Couldn't look up the transaction on the Stellar network".to_string())
+        })?;
 
     if !is_valid {
         // Mark as failed
@@ -178,34 +261,79 @@ pub async fn verify(
             payload.tx_hash
         )
         .execute(&state.pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
+
+        return Err(AppError::invalid("tx_hash", "Transaction was not found or did not succeed"));
+    }
 
-        return Err(StatusCode::BAD_REQUEST);
+    // The transaction must pay this donation's destination, with its memo,
+    // exactly its amount
+    let memo = donation
+        .memo
+        .as_deref()
+        .ok_or_else(|| AppError::bad_request("Donation has no memo to match a payment against"))?;
+    let destination = donation_memo::expected_destination(&state.pool, donation.project_id, state.escrow_mode)
+        .await
+        .context("Failed to resolve donation destination")?;
+    let payments = state.stellar
+        .fetch_transaction_payments(&payload.tx_hash)
+        .await
+        .map_err(|_| AppError::invalid("tx_hash", "Couldn't read the transaction's payments"))?;
+    if !payments
+        .iter()
+        .any(|p| donation_memo::payment_matches(p, &destination, memo, donation.amount))
+    {
+        return Err(AppError::Coded {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            code: "payment_mismatch",
+            message: "Transaction doesn't pay this donation's destination, memo and amount".to_string(),
+            details: Some(serde_json::json!({"destination": destination, "memo": memo, "amount_xlm": donation.amount})),
+        });
     }
 
     // Update donation status to confirmed
-    sqlx::query!(
+    let confirmed = sqlx::query!(
         r#"
         UPDATE donations
         SET status = 'confirmed', 
             tx_hash = $2,
             confirmed_at = NOW()
-        WHERE id = $1
+        WHERE id = $1 AND status = 'pending'
         "#,
         payload.donation_id,
         payload.tx_hash
     )
     .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?
+    .rows_affected();
+
+    if confirmed > 0 {
+        let entry = ledger::donation_entry(donation.id, donation.project_id, &destination, donation.amount);
+        if let Err(e) = ledger::post(&state.pool, &entry).await {
+            tracing::error!("Failed to post donation {} to the ledger: {}", donation.id, e);
+        }
+        if let Err(e) = fees::apply(&state.pool, donation.id, donation.project_id, "stellar", donation.amount).await {
+            tracing::error!("Failed to take the platform fee on donation {}: {}", donation.id, e);
+        }
+        if let Err(e) = email::queue_donation_receipt(&state.pool, state.network, donation.id).await {
+            tracing::error!("Failed to queue the receipt email for donation {}: {}", donation.id, e);
+        }
+        if let Err(e) = outgoing_webhooks::queue_donation_confirmed(&state.pool, donation.id).await {
+            tracing::error!("Failed to queue webhooks for donation {}: {}", donation.id, e);
+        }
+        match follows::check_funding_thresholds(&state.pool, donation.id).await {
+            Ok(Some((project_id, percent))) => {
+                state.notifier.send(Channel::Project(project_id), NotificationEvent::FundingThreshold { project_id, percent });
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to check funding thresholds after donation {}: {}", donation.id, e),
+        }
+    }
 
     // Emit SSE notification
-    let _ = state.notifier.send(format!(
-        "donation_confirmed:{}:{}",
-        donation.project_id.map(|id| id.to_string()).unwrap_or_else(|| "platform".to_string()),
-        payload.donation_id
-    ));
+    let event = NotificationEvent::DonationConfirmed { donation_id: payload.donation_id, project_id: donation.project_id };
+    let channels = donation.donor_id.map(Channel::User).into_iter().chain(donation.project_id.map(Channel::Project));
+    state.notifier.send_all(channels, event);
 
     Ok(Json(serde_json::json!({
         "donation_id": payload.donation_id,
@@ -214,101 +342,130 @@ pub async fn verify(
     })))
 }
 
+/// A project's donations; anonymous donors are only shown to admins
 pub async fn get_project_donations(
     State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
     Path(project_id): Path<Uuid>,
-) -> Result<Json<Vec<Donation>>, StatusCode> {
+    Query(query): Query<PageQuery>,
+) -> AppResult<Json<Page<Donation>>> {
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
     let donations = sqlx::query_as!(
         Donation,
         r#"
-        SELECT id, donor_id, project_id, amount, tx_hash, memo,
-               status, payment_method, donation_type, confirmed_at, created_at
+        SELECT id, donor_id, project_id, amount as "amount: Stroops", tx_hash, memo,
+               status, payment_method, donation_type, is_anonymous, confirmed_at, created_at
         FROM donations
         WHERE project_id = $1
-        ORDER BY created_at DESC
+        AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3::uuid))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
         "#,
-        project_id
+        project_id,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
     )
     .fetch_all(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
-    Ok(Json(donations))
+    let donations = Page::new(donations, &page, |d| (d.created_at, d.id));
+    if crate::utils::roles::caller_is_admin(&state.pool, &headers).await {
+        return Ok(Json(donations));
+    }
+    Ok(Json(donations.map(Donation::redacted)))
 }
 
+/// Donations to a student's projects; anonymous donors are only shown to admins
 pub async fn get_student_donations(
     State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
     Path(student_id): Path<Uuid>,
-) -> Result<Json<Vec<Donation>>, StatusCode> {
+    Query(query): Query<PageQuery>,
+) -> AppResult<Json<Page<Donation>>> {
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
     let donations = sqlx::query_as!(
         Donation,
         r#"
-        SELECT d.id, d.donor_id, d.project_id, d.amount, d.tx_hash, d.memo,
-               d.status, d.payment_method, d.donation_type, d.confirmed_at, d.created_at
+        SELECT d.id, d.donor_id, d.project_id, d.amount as "amount: Stroops", d.tx_hash, d.memo,
+               d.status, d.payment_method, d.donation_type, d.is_anonymous, d.confirmed_at, d.created_at
         FROM donations d
         JOIN projects p ON p.id = d.project_id
         WHERE p.student_id = $1
-        ORDER BY d.created_at DESC
+        AND ($2::timestamptz IS NULL OR (d.created_at, d.id) < ($2, $3::uuid))
+        ORDER BY d.created_at DESC, d.id DESC
+        LIMIT $4
         "#,
-        student_id
+        student_id,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
     )
     .fetch_all(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
-    Ok(Json(donations))
+    let donations = Page::new(donations, &page, |d| (d.created_at, d.id));
+    if crate::utils::roles::caller_is_admin(&state.pool, &headers).await {
+        return Ok(Json(donations));
+    }
+    Ok(Json(donations.map(Donation::redacted)))
 }
 
 pub async fn initiate_platform_donation(
     State(state): State<crate::state::AppState>,
-    Json(payload): Json<PlatformDonationRequest>,
-) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    // Platform wallet address (this should be configured in environment)
-    let platform_wallet = std::env::var("PLATFORM_WALLET_ADDRESS")
-        .unwrap_or_else(|_| "GALAXY_PLATFORM_WALLET_ADDRESS".to_string());
-    
-    // Parse amount to BigDecimal
-    let amount: BigDecimal = payload.amount
-        .to_string()
-        .parse()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
-
-    // Generate donation ID and use as memo
-    let donation_id = Uuid::new_v4();
-    let memo = format!("platform_donation:{}", donation_id);
+    ValidatedJson(payload): ValidatedJson<PlatformDonationRequest>,
+) -> AppResult<(StatusCode, Json<serde_json::Value>)> {
+    let amount = payload.amount;
+
+    // Platform donations are paid into the platform wallet and verified against it
+    let platform_wallet = donation_memo::expected_destination(&state.pool, None, state.escrow_mode)
+        .await
+        .context("Failed to resolve the platform wallet")?;
 
     // Create platform donation record (project_id = NULL for platform donations)
-    let _donation = sqlx::query!(
-        r#"
-        INSERT INTO donations (
-            id,
-            donor_id,
-            project_id,
-            amount,
-            payment_method,
+    let donation_id = Uuid::new_v4();
+    let mut attempt = 0;
+    let memo = loop {
+        let memo = donation_memo::generate(MemoKind::Platform, donation_id, attempt);
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO donations (
+                id,
+                donor_id,
+                project_id,
+                amount,
+                payment_method,
+                memo,
+                status,
+                donation_type
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, 'pending', 'platform')
+            RETURNING id
+            "#,
+            donation_id,
+            None::<Uuid>, // No donor_id for platform donations
+            None::<Uuid>, // No project_id for platform donations
+            amount.to_decimal(),
+            "platform_fund",
             memo,
-            status,
-            donation_type
         )
-        VALUES ($1, $2, $3, $4, $5, $6, 'pending', 'platform')
-        RETURNING id
-        "#,
-        donation_id,
-        None::<Uuid>, // No donor_id for platform donations
-        None::<Uuid>, // No project_id for platform donations
-        amount,
-        "platform_fund",
-        memo,
-    )
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .fetch_one(&state.pool)
+        .await;
+
+        match inserted {
+            Ok(_) => break memo,
+            Err(e) if donation_memo::is_memo_collision(&e) && attempt + 1 < donation_memo::MAX_MEMO_ATTEMPTS => {
+                attempt += 1;
+            }
+            Err(e) => return Err(anyhow::Error::new(e).context("Failed to create donation").into()),
+        }
+    };
 
     // Build payment instruction for platform donation
     let payment_instruction = serde_json::json!({
         "type": "platform_donation",
         "recipient_wallet": platform_wallet,
-        "amount_xlm": payload.amount,
+        "amount_xlm": amount,
         "memo": memo,
         "donation_id": donation_id,
         "message": payload.message
@@ -0,0 +1,466 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use num_traits::cast::ToPrimitive;
+use sqlx::types::BigDecimal;
+use uuid::Uuid;
+
+use crate::utils::money::Stroops;
+use crate::utils::pagination::{Page, PageRequest};
+
+#[derive(Debug, Deserialize)]
+pub struct TaxSummaryQuery {
+    pub year: Option<i32>,
+    /// `json` (default), `csv`, or `pdf`
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlatformDetails {
+    pub name: String,
+    pub tax_id: Option<String>,
+    pub wallet_address: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaxSummaryEntry {
+    pub donation_id: Uuid,
+    pub project_id: Option<Uuid>,
+    pub project_title: Option<String>,
+    pub amount_xlm: BigDecimal,
+    pub tx_hash: String,
+    pub ledger: Option<i32>,
+    pub donated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaxSummary {
+    pub donor_id: Uuid,
+    pub year: i32,
+    pub total_xlm: BigDecimal,
+    pub donation_count: usize,
+    pub platform: PlatformDetails,
+    pub donations: Vec<TaxSummaryEntry>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Yearly summary of a donor's confirmed donations, built from ledger-indexed
+/// transactions so amounts and dates match what settled on-chain
+pub async fn tax_summary(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<TaxSummaryQuery>,
+) -> Result<Response, StatusCode> {
+    let donor_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let year = query.year.unwrap_or_else(|| Utc::now().year());
+    if !(2000..=9999).contains(&year) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let donations = sqlx::query_as!(
+        TaxSummaryEntry,
+        r#"
+        SELECT d.id as donation_id,
+               d.project_id,
+               p.title as "project_title?",
+               COALESCE(o.amount_xlm, d.amount) as "amount_xlm!",
+               o.tx_hash,
+               o.ledger,
+               o.created_at as "donated_at!"
+        FROM donations d
+        -- A donation transaction may hold several operations; use its payment
+        JOIN LATERAL (
+            SELECT amount_xlm, tx_hash, ledger, created_at
+            FROM onchain_transactions
+            WHERE tx_hash = d.tx_hash AND successful = true
+            ORDER BY (operation_type = 'payment') DESC, operation_id
+            LIMIT 1
+        ) o ON true
+        LEFT JOIN projects p ON p.id = d.project_id
+        WHERE d.donor_id = $1
+        AND d.status = 'confirmed'
+        AND EXTRACT(YEAR FROM o.created_at)::INTEGER = $2
+        ORDER BY o.created_at
+        "#,
+        donor_id,
+        year
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load tax summary for donor {}: {}", donor_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let total_xlm = donations
+        .iter()
+        .fold(BigDecimal::from(0), |acc, d| acc + &d.amount_xlm);
+
+    let summary = TaxSummary {
+        donor_id,
+        year,
+        total_xlm,
+        donation_count: donations.len(),
+        platform: PlatformDetails {
+            name: std::env::var("PLATFORM_LEGAL_NAME").unwrap_or_else(|_| "FundHub".to_string()),
+            tax_id: std::env::var("PLATFORM_TAX_ID").ok(),
+            wallet_address: std::env::var("PLATFORM_WALLET_PUBLIC_KEY").unwrap_or_default(),
+        },
+        donations,
+        generated_at: Utc::now(),
+    };
+
+    match query.format.as_deref().unwrap_or("json") {
+        "json" => Ok(Json(summary).into_response()),
+        "csv" => Ok(attachment(
+            "text/csv",
+            &format!("fundhub-tax-summary-{}.csv", year),
+            render_csv(&summary).into_bytes(),
+        )),
+        "pdf" => Ok(attachment(
+            "application/pdf",
+            &format!("fundhub-tax-summary-{}.pdf", year),
+            crate::utils::pdf::text_document(
+                &format!("{} - Donation Summary {}", summary.platform.name, year),
+                &render_lines(&summary),
+            ),
+        )),
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MyDonationsQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MyDonation {
+    pub id: Uuid,
+    pub project_id: Option<Uuid>,
+    pub project_title: Option<String>,
+    pub amount: Stroops,
+    pub status: String,
+    pub payment_method: String,
+    pub is_anonymous: bool,
+    pub tx_hash: Option<String>,
+    /// Horizon page for `tx_hash`
+    pub tx_url: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectContribution {
+    pub project_id: Uuid,
+    pub project_title: String,
+    pub project_status: String,
+    pub total_xlm: Stroops,
+    pub donation_count: i64,
+    /// Share of everything the project has raised, in percent
+    pub share_of_project: f64,
+    pub last_donated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReleasedMilestone {
+    pub project_id: Uuid,
+    pub project_title: String,
+    pub title: String,
+    pub amount_xlm: BigDecimal,
+    pub released_at: DateTime<Utc>,
+    pub release_tx_hash: Option<String>,
+    pub tx_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DonorImpact {
+    pub total_xlm: Stroops,
+    pub confirmed_donations: i64,
+    pub projects_supported: i64,
+    pub milestones_released: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MyDonations {
+    pub impact: DonorImpact,
+    pub projects: Vec<ProjectContribution>,
+    /// Milestones released on supported projects since the donor first gave to them
+    pub milestones: Vec<ReleasedMilestone>,
+    /// One page of the donor's donations, newest first
+    pub donations: Vec<MyDonation>,
+    pub total_donations: i64,
+    /// Pass back as `?cursor=` for the next page of donations
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+/// The signed-in donor's donation history, what each project has had from
+/// them, and the milestones their money helped release
+pub async fn my_donations(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<MyDonationsQuery>,
+) -> Result<Json<MyDonations>, StatusCode> {
+    let donor_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let internal = |e: sqlx::Error| {
+        tracing::error!("Failed to load donations for donor {}: {}", donor_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let totals = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "total!",
+               COUNT(*) FILTER (WHERE status = 'confirmed') as "confirmed!",
+               COALESCE(SUM(amount) FILTER (WHERE status = 'confirmed'), 0) as "total_xlm!: Stroops",
+               COUNT(DISTINCT project_id) FILTER (WHERE status = 'confirmed') as "projects!"
+        FROM donations
+        WHERE donor_id = $1
+        "#,
+        donor_id
+    )
+    .fetch_one(&state.pool)
+    .await
+    .map_err(internal)?;
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT d.id, d.project_id, p.title as "project_title?", d.amount as "amount: Stroops", d.status,
+               d.payment_method, d.is_anonymous, d.tx_hash, d.created_at, d.confirmed_at
+        FROM donations d
+        LEFT JOIN projects p ON p.id = d.project_id
+        WHERE d.donor_id = $1
+        AND ($2::timestamptz IS NULL OR (d.created_at, d.id) < ($2, $3::uuid))
+        ORDER BY d.created_at DESC, d.id DESC
+        LIMIT $4
+        "#,
+        donor_id,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal)?;
+    let donations: Vec<MyDonation> = rows
+        .into_iter()
+        .map(|r| MyDonation {
+            id: r.id,
+            project_id: r.project_id,
+            project_title: r.project_title,
+            amount: r.amount,
+            status: r.status,
+            payment_method: r.payment_method,
+            is_anonymous: r.is_anonymous,
+            tx_url: r.tx_hash.as_deref().map(|hash| state.network.transaction_url(hash)),
+            tx_hash: r.tx_hash,
+            created_at: r.created_at,
+            confirmed_at: r.confirmed_at,
+        })
+        .collect();
+    let donations = Page::new(donations, &page, |d| (d.created_at, d.id));
+
+    let rows = sqlx::query!(
+        r#"
+        WITH mine AS (
+            SELECT project_id, COUNT(*) as donation_count, SUM(amount) as total,
+                   MAX(COALESCE(confirmed_at, created_at)) as last_donated_at
+            FROM donations
+            WHERE donor_id = $1 AND status = 'confirmed' AND project_id IS NOT NULL
+            GROUP BY project_id
+        )
+        SELECT m.project_id as "project_id!", p.title, p.status as project_status,
+               m.donation_count as "donation_count!", m.total as "total!: Stroops",
+               m.last_donated_at as "last_donated_at!",
+               (SELECT SUM(amount) FROM donations WHERE project_id = m.project_id AND status = 'confirmed')
+                   as "project_total!"
+        FROM mine m
+        JOIN projects p ON p.id = m.project_id
+        ORDER BY m.total DESC
+        "#,
+        donor_id
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal)?;
+    let projects = rows
+        .into_iter()
+        .map(|r| {
+            let share = if r.project_total > BigDecimal::from(0) {
+                (r.total.to_decimal() / r.project_total * BigDecimal::from(100)).to_f64().unwrap_or(0.0)
+            } else {
+                0.0
+            };
+            ProjectContribution {
+                project_id: r.project_id,
+                project_title: r.title,
+                project_status: r.project_status,
+                total_xlm: r.total,
+                donation_count: r.donation_count,
+                share_of_project: share,
+                last_donated_at: r.last_donated_at,
+            }
+        })
+        .collect();
+
+    let rows = sqlx::query!(
+        r#"
+        WITH mine AS (
+            SELECT project_id, MIN(COALESCE(confirmed_at, created_at)) as first_donated_at
+            FROM donations
+            WHERE donor_id = $1 AND status = 'confirmed' AND project_id IS NOT NULL
+            GROUP BY project_id
+        )
+        SELECT m.project_id as "project_id!", p.title as "project_title!", m.title as "title!",
+               m.target_amount as "amount_xlm!", m.released_at as "released_at!",
+               NULL::TEXT as release_tx_hash
+        FROM milestones m
+        JOIN mine ON mine.project_id = m.project_id
+        JOIN projects p ON p.id = m.project_id
+        WHERE m.released = true AND m.released_at >= mine.first_donated_at
+        UNION ALL
+        SELECT cm.project_id, p.title, cm.milestone_id, cm.amount_stroops::NUMERIC / 10000000,
+               cm.released_at, cm.release_tx_hash
+        FROM contract_milestones cm
+        JOIN mine ON mine.project_id = cm.project_id
+        JOIN projects p ON p.id = cm.project_id
+        WHERE cm.released = true AND cm.released_at >= mine.first_donated_at
+        ORDER BY 5 DESC
+        "#,
+        donor_id
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal)?;
+    let milestones: Vec<ReleasedMilestone> = rows
+        .into_iter()
+        .map(|r| ReleasedMilestone {
+            project_id: r.project_id,
+            project_title: r.project_title,
+            title: r.title,
+            amount_xlm: r.amount_xlm,
+            released_at: r.released_at,
+            tx_url: r.release_tx_hash.as_deref().map(|hash| state.network.transaction_url(hash)),
+            release_tx_hash: r.release_tx_hash,
+        })
+        .collect();
+
+    Ok(Json(MyDonations {
+        impact: DonorImpact {
+            total_xlm: totals.total_xlm,
+            confirmed_donations: totals.confirmed,
+            projects_supported: totals.projects,
+            milestones_released: milestones.len(),
+        },
+        projects,
+        milestones,
+        donations: donations.items,
+        total_donations: totals.total,
+        next_cursor: donations.next_cursor,
+        has_more: donations.has_more,
+    }))
+}
+
+/// Signed PDF receipt for one of the caller's confirmed donations
+pub async fn donation_receipt(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    axum::extract::Path(donation_id): axum::extract::Path<Uuid>,
+) -> Result<Response, StatusCode> {
+    let donor_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let receipt = crate::services::receipts::donation_receipt(&state.pool, donor_id, donation_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to generate receipt for donation {}: {}", donation_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(attachment("application/pdf", &receipt.filename, receipt.pdf))
+}
+
+/// Signed PDF summary of the caller's confirmed donations in `year`
+pub async fn annual_receipt(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    axum::extract::Path(year): axum::extract::Path<i32>,
+) -> Result<Response, StatusCode> {
+    let donor_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    if !(2000..=Utc::now().year()).contains(&year) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let receipt = crate::services::receipts::annual_summary(&state.pool, donor_id, year)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to generate {} summary for donor {}: {}", year, donor_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(attachment("application/pdf", &receipt.filename, receipt.pdf))
+}
+
+fn attachment(content_type: &'static str, filename: &str, body: Vec<u8>) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+fn render_csv(summary: &TaxSummary) -> String {
+    let mut csv = String::from("date,donation_id,project,amount_xlm,tx_hash,ledger\n");
+    for d in &summary.donations {
+        csv.push_str(&format!(
+            "{},{},\"{}\",{},{},{}\n",
+            d.donated_at.format("%Y-%m-%d"),
+            d.donation_id,
+            d.project_title.clone().unwrap_or_else(|| "Platform".to_string()).replace('"', "\"\""),
+            d.amount_xlm,
+            d.tx_hash,
+            d.ledger.map(|l| l.to_string()).unwrap_or_default(),
+        ));
+    }
+    csv.push_str(&format!(",,\"Total\",{},,\n", summary.total_xlm));
+    csv
+}
+
+fn render_lines(summary: &TaxSummary) -> Vec<String> {
+    let mut lines = vec![
+        format!("Recipient platform: {}", summary.platform.name),
+        format!("Platform tax ID: {}", summary.platform.tax_id.as_deref().unwrap_or("n/a")),
+        format!("Platform wallet: {}", summary.platform.wallet_address),
+        format!("Donor ID: {}", summary.donor_id),
+        format!("Generated: {}", summary.generated_at.format("%Y-%m-%d %H:%M UTC")),
+        String::new(),
+    ];
+    for d in &summary.donations {
+        lines.push(format!(
+            "{}  {} XLM  {}  tx {}",
+            d.donated_at.format("%Y-%m-%d"),
+            d.amount_xlm,
+            d.project_title.as_deref().unwrap_or("Platform"),
+            d.tx_hash,
+        ));
+    }
+    lines.push(String::new());
+    lines.push(format!("Total donated in {}: {} XLM ({} donations)", summary.year, summary.total_xlm, summary.donation_count));
+    lines
+}
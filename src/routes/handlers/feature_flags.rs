@@ -0,0 +1,149 @@
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, Json};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::ValidatedJson;
+use crate::services::flags::{self, Flag, FlagChange};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct FeatureFlagRequest {
+    #[validate(length(max = 500, message = "Description must be at most 500 characters"))]
+    pub description: Option<String>,
+    pub enabled: Option<bool>,
+    /// `APP_ENV` values to turn the flag on in; leave out for every environment
+    pub environments: Option<Vec<String>>,
+    /// Defaults to 100
+    #[validate(range(min = 0, max = 100, message = "Rollout must be between 0 and 100 percent"))]
+    pub rollout_percent: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateFeatureFlagRequest {
+    /// Lowercase letters, digits and underscores, like `guest_checkout`
+    pub name: String,
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub settings: FeatureFlagRequest,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeatureFlags {
+    /// This server's `APP_ENV`
+    pub environment: String,
+    pub flags: Vec<Flag>,
+}
+
+fn environments(req: &FeatureFlagRequest) -> Vec<String> {
+    let mut environments: Vec<String> = req
+        .environments
+        .iter()
+        .flatten()
+        .map(|e| e.trim().to_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect();
+    environments.sort();
+    environments.dedup();
+    environments
+}
+
+fn change<'a>(req: &'a FeatureFlagRequest, environments: &'a [String]) -> FlagChange<'a> {
+    FlagChange {
+        description: req.description.as_deref().map(str::trim).filter(|d| !d.is_empty()),
+        enabled: req.enabled.unwrap_or(false),
+        environments,
+        rollout_percent: req.rollout_percent.unwrap_or(100),
+    }
+}
+
+fn admin(headers: &HeaderMap) -> AppResult<Uuid> {
+    crate::utils::jwt::extract_user_id_from_headers(headers).map_err(|_| AppError::unauthorized("Authentication required"))
+}
+
+/// Pick up the change here straight away; other instances see it once their
+/// cache expires
+async fn applied(state: &AppState, admin_id: Uuid, action: &str, flag: &Flag) {
+    if let Err(e) = state.flags.reload().await {
+        tracing::warn!("Failed to reload feature flags after {}: {}", action, e);
+    }
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        action,
+        None::<Uuid>,
+        "feature_flag",
+        serde_json::json!({
+            "name": flag.name,
+            "enabled": flag.enabled,
+            "environments": flag.environments,
+            "rollout_percent": flag.rollout_percent,
+        })
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+/// Every flag, alphabetically, and the environment this server runs as
+pub async fn list_feature_flags(State(state): State<AppState>) -> AppResult<Json<FeatureFlags>> {
+    Ok(Json(FeatureFlags {
+        environment: state.flags.environment().to_string(),
+        flags: flags::list(&state.pool).await?,
+    }))
+}
+
+/// Flags start off unless `enabled` is set
+pub async fn create_feature_flag(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<CreateFeatureFlagRequest>,
+) -> AppResult<(StatusCode, Json<Flag>)> {
+    let admin_id = admin(&headers)?;
+    let name = req.name.trim();
+    if !flags::is_valid_name(name) {
+        return Err(AppError::invalid("name", "Use lowercase letters, digits and underscores, starting with a letter"));
+    }
+    let environments = environments(&req.settings);
+    let created = flags::create(&state.pool, name, &change(&req.settings, &environments), admin_id)
+        .await?
+        .ok_or_else(|| AppError::conflict(format!("Flag {} already exists", name)))?;
+
+    applied(&state, admin_id, "feature_flag_created", &created).await;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// Replace a flag's settings; fields left out go back to their defaults
+pub async fn update_feature_flag(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    ValidatedJson(req): ValidatedJson<FeatureFlagRequest>,
+) -> AppResult<Json<Flag>> {
+    let admin_id = admin(&headers)?;
+    let environments = environments(&req);
+    let updated = flags::update(&state.pool, &name, &change(&req, &environments), admin_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Feature flag not found"))?;
+
+    applied(&state, admin_id, "feature_flag_updated", &updated).await;
+    Ok(Json(updated))
+}
+
+/// Deleting a flag turns it off, so anything gated on it stops working
+pub async fn delete_feature_flag(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> AppResult<Json<Flag>> {
+    let admin_id = admin(&headers)?;
+    let deleted = flags::delete(&state.pool, &name)
+        .await?
+        .ok_or_else(|| AppError::not_found("Feature flag not found"))?;
+
+    applied(&state, admin_id, "feature_flag_deleted", &deleted).await;
+    Ok(Json(deleted))
+}
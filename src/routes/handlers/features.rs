@@ -0,0 +1,124 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::services::featuring::{self, FeatureSlot, FeatureUplift, FeaturedProject};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ScheduleFeatureRequest {
+    pub project_id: Uuid,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListFeaturesQuery {
+    pub include_past: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeaturedQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpliftQuery {
+    /// Only slots that started within this many days (default 30)
+    pub days: Option<i64>,
+}
+
+/// Schedule a project to be featured for a date range (admin only)
+pub async fn schedule_feature(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<ScheduleFeatureRequest>,
+) -> Result<(StatusCode, Json<FeatureSlot>), (StatusCode, Json<serde_json::Value>)> {
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers).ok();
+
+    let slot = featuring::schedule(&state.pool, req.project_id, req.starts_at, req.ends_at, admin_id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        "project_feature_scheduled",
+        req.project_id,
+        "project",
+        serde_json::json!({
+            "slot_id": slot.id,
+            "starts_at": slot.starts_at,
+            "ends_at": slot.ends_at
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok((StatusCode::CREATED, Json(slot)))
+}
+
+/// List scheduled feature slots (admin only)
+pub async fn list_features(
+    State(state): State<AppState>,
+    Query(query): Query<ListFeaturesQuery>,
+) -> Result<Json<Vec<FeatureSlot>>, StatusCode> {
+    featuring::list(&state.pool, query.include_past.unwrap_or(false))
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Remove a feature slot (admin only)
+pub async fn cancel_feature(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    match featuring::cancel(&state.pool, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// Projects currently in the spotlight, rotated among live slots
+pub async fn featured_projects(
+    State(state): State<AppState>,
+    Query(query): Query<FeaturedQuery>,
+) -> Result<Json<Vec<FeaturedProject>>, StatusCode> {
+    let limit = query.limit.unwrap_or(3).clamp(1, 12);
+
+    featuring::rotate(&state.pool, limit)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("Failed to rotate featured projects: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Funding uplift of recent feature slots
+pub async fn feature_uplift(
+    State(state): State<AppState>,
+    Query(query): Query<UpliftQuery>,
+) -> Result<Json<Vec<FeatureUplift>>, StatusCode> {
+    let days = query.days.unwrap_or(30).clamp(1, 365);
+    let since = Utc::now() - chrono::Duration::days(days);
+
+    featuring::uplift(&state.pool, since)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
@@ -0,0 +1,137 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::services::malware_scan::{self, FileScan, SCAN_STATUSES};
+use crate::services::storage::{self, FileDownload, FileVerification, ObjectStorage};
+use crate::state::AppState;
+use crate::utils::pagination::{Page, PageRequest};
+use crate::utils::roles::caller_is_admin;
+
+#[derive(Debug, Deserialize)]
+pub struct FileScansQuery {
+    /// `pending`, `clean`, `quarantined` or `failed`
+    pub status: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+fn object_storage(state: &AppState) -> AppResult<&ObjectStorage> {
+    state
+        .storage
+        .as_ref()
+        .ok_or_else(|| AppError::Unavailable("File storage is not configured".to_string()))
+}
+
+/// A presigned link to download a stored file, for its owner or an admin,
+/// once it has been scanned clean
+pub async fn download_file(
+    State(state): State<AppState>,
+    Path(file_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Json<FileDownload>> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+    let storage = object_storage(&state)?;
+    let file = storage::find_file(&state.pool, file_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("File not found"))?;
+    if file.owner_id != Some(user_id) && !caller_is_admin(&state.pool, &headers).await {
+        return Err(AppError::forbidden("Only the file's owner or an admin can download it"));
+    }
+    match file.scan_status.as_str() {
+        "clean" => {}
+        "quarantined" => return Err(AppError::forbidden("This file was quarantined by the malware scan")),
+        "failed" => return Err(AppError::conflict("This file couldn't be scanned for malware")),
+        _ => return Err(AppError::conflict("This file is waiting for its malware scan")),
+    }
+
+    let download = storage.download_url(&file.key, &file.filename);
+    Ok(Json(FileDownload { file, download }))
+}
+
+/// Re-hash a stored file and compare it with the checksum recorded at upload
+/// (admin)
+pub async fn verify_file(
+    State(state): State<AppState>,
+    Path(file_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Json<FileVerification>> {
+    let storage = object_storage(&state)?;
+    let file = storage::find_file(&state.pool, file_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("File not found"))?;
+
+    let verification = storage::verify_file(storage, &file).await?;
+    if !verification.intact {
+        tracing::warn!(
+            "File {} failed verification: expected {:?}, found {:?}",
+            file_id,
+            verification.expected,
+            verification.actual
+        );
+    }
+    if let Ok(admin_id) = crate::utils::jwt::extract_user_id_from_headers(&headers) {
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            admin_id,
+            "file_verified",
+            file_id,
+            "file",
+            serde_json::json!({"intact": verification.intact})
+        )
+        .execute(&state.pool)
+        .await;
+    }
+    Ok(Json(verification))
+}
+
+/// Uploaded files with their malware scan results, newest first, optionally
+/// in one status (admin)
+pub async fn list_file_scans(
+    State(state): State<AppState>,
+    Query(query): Query<FileScansQuery>,
+) -> AppResult<Json<Page<FileScan>>> {
+    let status = query.status.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    if status.is_some_and(|s| !SCAN_STATUSES.contains(&s)) {
+        return Err(AppError::invalid("status", format!("Status must be one of {}", SCAN_STATUSES.join(", "))));
+    }
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
+    Ok(Json(malware_scan::list(&state.pool, status, &page).await?))
+}
+
+/// Queue a file to be scanned again, e.g. after a false positive or once the
+/// scanner is back (admin)
+pub async fn rescan_file(
+    State(state): State<AppState>,
+    Path(file_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Json<FileScan>> {
+    let scan = malware_scan::rescan(&state.pool, file_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("File not found"))?;
+    if let Ok(admin_id) = crate::utils::jwt::extract_user_id_from_headers(&headers) {
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            admin_id,
+            "file_rescan_requested",
+            file_id,
+            "file",
+            serde_json::json!({})
+        )
+        .execute(&state.pool)
+        .await;
+    }
+    Ok(Json(scan))
+}
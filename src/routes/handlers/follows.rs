@@ -0,0 +1,77 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use uuid::Uuid;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::services::follows::{self, FollowStatus, FollowTarget, Followed};
+use crate::state::AppState;
+use crate::utils::pagination::{Page, PageQuery, PageRequest};
+
+async fn set_following(state: &AppState, headers: &HeaderMap, target: FollowTarget, follow: bool) -> AppResult<Json<FollowStatus>> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    if !follows::target_exists(&state.pool, target).await? {
+        return Err(match target {
+            FollowTarget::Project(_) => AppError::not_found("Project not found"),
+            FollowTarget::Student(_) => AppError::not_found("Student not found"),
+        });
+    }
+
+    let status = if follow {
+        follows::follow(&state.pool, user_id, target).await?
+    } else {
+        follows::unfollow(&state.pool, user_id, target).await?
+    };
+    Ok(Json(status))
+}
+
+/// Follow a project to hear about its updates, milestones and funding
+pub async fn follow_project(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Json<FollowStatus>> {
+    set_following(&state, &headers, FollowTarget::Project(project_id), true).await
+}
+
+pub async fn unfollow_project(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Json<FollowStatus>> {
+    set_following(&state, &headers, FollowTarget::Project(project_id), false).await
+}
+
+/// Follow a student to hear about all of their projects
+pub async fn follow_student(
+    State(state): State<AppState>,
+    Path(student_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Json<FollowStatus>> {
+    set_following(&state, &headers, FollowTarget::Student(student_id), true).await
+}
+
+pub async fn unfollow_student(
+    State(state): State<AppState>,
+    Path(student_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Json<FollowStatus>> {
+    set_following(&state, &headers, FollowTarget::Student(student_id), false).await
+}
+
+/// Projects and students the caller follows, most recent first
+pub async fn my_following(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<PageQuery>,
+) -> AppResult<Json<Page<Followed>>> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
+
+    Ok(Json(follows::following(&state.pool, user_id, &page).await?))
+}
@@ -1,14 +1,15 @@
 use axum::{
     extract::{State, Path},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::Json,
 };
 use uuid::Uuid;
 use sqlx::types::BigDecimal;
 use crate::{
     models::{GuestDonation, GuestFundingRequest},
+    services::{flags::GuestCheckout, guest_claims::{self, ClaimError, ClaimSummary}},
     state::AppState,
-    utils::jwt::Claims,
+    utils::{feature_gate::RequireFlag, money::Stroops},
 };
 
 /// Create a guest donation
@@ -26,26 +27,39 @@ use crate::{
 )]
 pub async fn create_guest_donation(
     State(state): State<AppState>,
+    _: RequireFlag<GuestCheckout>,
     Json(payload): Json<GuestFundingRequest>,
 ) -> Result<(StatusCode, Json<GuestDonation>), (StatusCode, Json<serde_json::Value>)> {
-    // Verify project exists
-    let project_exists = sqlx::query_scalar!(
-        "SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1)",
-        payload.project_id
-    )
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Database error"})),
-        )
-    })?;
+    // Verify project exists and is taking donations
+    let accepting = crate::services::project_schedule::accepting_donations(&state.pool, payload.project_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": "Database error"})),
+            )
+        })?;
 
-    if !project_exists.unwrap_or(false) {
+    match accepting {
+        None => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({"error": "Project not found"})),
+            ))
+        }
+        Some(true) => {}
+        Some(false) => {
+            return Err((
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({"error": "Project is not accepting donations"})),
+            ))
+        }
+    }
+
+    if !payload.amount.is_positive() {
         return Err((
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({"error": "Project not found"})),
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "Amount must be positive"})),
         ));
     }
 
@@ -55,13 +69,13 @@ pub async fn create_guest_donation(
         r#"
         INSERT INTO guest_donations (guest_name, guest_email, project_id, tx_hash, amount)
         VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, guest_name, guest_email, project_id, tx_hash, amount, verified as "verified!: bool", created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        RETURNING id, guest_name, guest_email, project_id, tx_hash, amount as "amount: Stroops", verified as "verified!: bool", created_at as "created_at!: chrono::DateTime<chrono::Utc>"
         "#,
         payload.guest_name,
         payload.guest_email,
         payload.project_id,
         payload.tx_hash,
-        payload.amount
+        payload.amount.to_decimal()
     )
     .fetch_one(&state.pool)
     .await
@@ -153,12 +167,81 @@ pub async fn verify_guest_donation(
     .execute(&state.pool)
     .await;
 
+    // Already claimed by an account: it now belongs in that donor's history
+    if let Err(e) = guest_claims::merge_verified(&state.pool, tx_hash).await {
+        tracing::error!("Failed to merge claimed guest donation {}: {}", tx_hash, e);
+    }
+
     Ok(Json(serde_json::json!({
         "message": "Transaction verified successfully",
         "tx_hash": tx_hash
     })))
 }
 
+/// Claim the guest donations made with the caller's email
+#[utoipa::path(
+    post,
+    path = "/api/guest/claim",
+    responses(
+        (status = 200, description = "Guest donations linked to the caller's account"),
+        (status = 401, description = "Authentication required"),
+        (status = 403, description = "Email address not verified"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Guest"
+)]
+pub async fn claim_guest_donations(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ClaimSummary>, (StatusCode, Json<serde_json::Value>)> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers).map_err(|_| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "Authentication required"})),
+        )
+    })?;
+
+    let summary = guest_claims::claim(&state.pool, user_id).await.map_err(|e| match e {
+        ClaimError::UserNotFound => (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "Authentication required"})),
+        ),
+        ClaimError::EmailNotVerified => (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+        ClaimError::Internal(e) => {
+            tracing::error!("Failed to claim guest donations for {}: {}", user_id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": "Failed to claim guest donations"})),
+            )
+        }
+    })?;
+
+    if summary.claimed > 0 {
+        let _ = sqlx::query!(
+            r#"
+            INSERT INTO activity_logs (user_id, action, target_type, metadata)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            user_id,
+            "guest_donations_claimed",
+            "guest_donation",
+            serde_json::json!({
+                "claimed": summary.claimed,
+                "merged": summary.merged,
+                "total_amount": summary.total_amount,
+                "guest_donation_ids": summary.donations.iter().map(|d| d.guest_donation_id).collect::<Vec<_>>()
+            })
+        )
+        .execute(&state.pool)
+        .await;
+    }
+
+    Ok(Json(summary))
+}
+
 /// Get public project information (limited for guests)
 #[utoipa::path(
     get,
@@ -183,10 +266,19 @@ pub async fn get_public_projects(
             p.funding_goal as "funding_goal!: sqlx::types::BigDecimal",
             COALESCE(SUM(d.amount), 0) as "current_funding!: sqlx::types::BigDecimal",
             p.tags,
-            p.created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+            p.created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+            p.funding_deadline,
+            GREATEST(CEIL(EXTRACT(EPOCH FROM p.funding_deadline - NOW())), 0)::BIGINT as seconds_until_deadline,
+            (p.funding_closed_at IS NULL AND (p.funding_deadline IS NULL OR p.funding_deadline > NOW())) as "funding_open!",
+            COALESCE(
+                (SELECT json_agg(json_build_object('kind', m.kind, 'url', m.url, 'caption', m.caption) ORDER BY m.position)
+                 FROM project_media m
+                 WHERE m.project_id = p.id AND m.status = 'ready'),
+                '[]'
+            ) as "media!: sqlx::types::Json<Vec<crate::models::PublicMedia>>"
         FROM projects p
         LEFT JOIN donations d ON p.id = d.project_id AND d.status = 'confirmed'
-        WHERE p.visibility = 'public' AND p.status = 'active'
+        WHERE p.visibility = 'public' AND p.status = 'active' AND p.hidden_at IS NULL
         GROUP BY p.id, p.title, p.description, p.media_url, p.funding_goal, p.tags, p.created_at
         ORDER BY p.created_at DESC
         "#
@@ -0,0 +1,221 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::services::ledger::format_amount;
+use crate::utils::money::Stroops;
+
+#[derive(Deserialize)]
+pub struct LedgerQuery {
+    pub currency: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct FeeReportQuery {
+    pub months: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct FeeReportLine {
+    /// Calendar month, `YYYY-MM`
+    pub month: String,
+    pub payment_method: String,
+    pub donation_count: i64,
+    pub gross: Stroops,
+    pub fees: Stroops,
+    pub net: Stroops,
+}
+
+#[derive(Serialize)]
+pub struct TrialBalanceLine {
+    pub account: String,
+    pub currency: String,
+    pub debits: String,
+    pub credits: String,
+    /// Debits less credits; negative for accounts that hold value owed to others
+    pub balance: String,
+}
+
+#[derive(Serialize)]
+pub struct CurrencyTotals {
+    pub currency: String,
+    pub debits: String,
+    pub credits: String,
+    pub balanced: bool,
+}
+
+#[derive(Serialize)]
+pub struct TrialBalance {
+    pub accounts: Vec<TrialBalanceLine>,
+    pub totals: Vec<CurrencyTotals>,
+    pub balanced: bool,
+}
+
+#[derive(Serialize)]
+pub struct StatementLine {
+    pub transaction_id: Uuid,
+    pub kind: String,
+    pub reference_type: String,
+    pub reference_id: String,
+    pub description: Option<String>,
+    pub currency: String,
+    pub debit: String,
+    pub credit: String,
+    /// Balance after this entry
+    pub balance: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Every account's debits, credits and balance, with per-currency totals.
+/// Postings balance one transaction at a time, so totals that disagree mean
+/// entries were written outside the ledger service.
+pub async fn trial_balance(
+    State(state): State<crate::state::AppState>,
+    Query(query): Query<LedgerQuery>,
+) -> Result<Json<TrialBalance>, StatusCode> {
+    let currency = query.currency.map(|c| c.to_uppercase());
+    let rows = sqlx::query!(
+        r#"
+        SELECT account, currency,
+               COALESCE(SUM(debit), 0)::BIGINT as "debits!",
+               COALESCE(SUM(credit), 0)::BIGINT as "credits!"
+        FROM ledger_entries
+        WHERE $1::TEXT IS NULL OR currency = $1
+        GROUP BY account, currency
+        ORDER BY currency, account
+        "#,
+        currency
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to build trial balance: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut totals: Vec<(String, i64, i64)> = Vec::new();
+    let accounts = rows
+        .iter()
+        .map(|row| {
+            match totals.iter_mut().find(|(currency, _, _)| *currency == row.currency) {
+                Some(total) => {
+                    total.1 += row.debits;
+                    total.2 += row.credits;
+                }
+                None => totals.push((row.currency.clone(), row.debits, row.credits)),
+            }
+            TrialBalanceLine {
+                account: row.account.clone(),
+                currency: row.currency.clone(),
+                debits: format_amount(&row.currency, row.debits),
+                credits: format_amount(&row.currency, row.credits),
+                balance: format_amount(&row.currency, row.debits - row.credits),
+            }
+        })
+        .collect();
+
+    let totals: Vec<CurrencyTotals> = totals
+        .into_iter()
+        .map(|(currency, debits, credits)| CurrencyTotals {
+            debits: format_amount(&currency, debits),
+            credits: format_amount(&currency, credits),
+            balanced: debits == credits,
+            currency,
+        })
+        .collect();
+    let balanced = totals.iter().all(|total| total.balanced);
+    if !balanced {
+        tracing::error!("Ledger trial balance does not balance");
+    }
+
+    Ok(Json(TrialBalance { accounts, totals, balanced }))
+}
+
+/// Entries posted to one account, newest first, with the running balance
+pub async fn account_statement(
+    State(state): State<crate::state::AppState>,
+    Path(account): Path<String>,
+    Query(query): Query<LedgerQuery>,
+) -> Result<Json<Vec<StatementLine>>, StatusCode> {
+    let currency = query.currency.map(|c| c.to_uppercase());
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+    let rows = sqlx::query!(
+        r#"
+        SELECT t.id as transaction_id, t.kind, t.reference_type, t.reference_id, t.description,
+               e.currency, e.debit, e.credit, e.created_at,
+               SUM(e.debit - e.credit) OVER (
+                   PARTITION BY e.currency ORDER BY e.created_at, e.id
+               )::BIGINT as "balance!"
+        FROM ledger_entries e
+        JOIN ledger_transactions t ON t.id = e.transaction_id
+        WHERE e.account = $1 AND ($2::TEXT IS NULL OR e.currency = $2)
+        ORDER BY e.created_at DESC, e.id DESC
+        LIMIT $3
+        "#,
+        account,
+        currency,
+        limit
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load statement for {}: {}", account, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if rows.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let lines = rows
+        .into_iter()
+        .map(|row| StatementLine {
+            transaction_id: row.transaction_id,
+            kind: row.kind,
+            reference_type: row.reference_type,
+            reference_id: row.reference_id,
+            description: row.description,
+            debit: format_amount(&row.currency, row.debit),
+            credit: format_amount(&row.currency, row.credit),
+            balance: format_amount(&row.currency, row.balance),
+            currency: row.currency,
+            created_at: row.created_at,
+        })
+        .collect();
+
+    Ok(Json(lines))
+}
+
+/// Platform fees by month and payment method, newest month first
+pub async fn fee_report(
+    State(state): State<crate::state::AppState>,
+    Query(query): Query<FeeReportQuery>,
+) -> Result<Json<Vec<FeeReportLine>>, StatusCode> {
+    let months = query.months.unwrap_or(12).clamp(1, 60);
+    let rows = sqlx::query_as!(
+        FeeReportLine,
+        r#"
+        SELECT to_char(date_trunc('month', created_at), 'YYYY-MM') as "month!",
+               payment_method,
+               COUNT(*) as "donation_count!",
+               SUM(gross_amount) as "gross!: Stroops",
+               SUM(fee_amount) as "fees!: Stroops",
+               SUM(net_amount) as "net!: Stroops"
+        FROM donation_fees
+        WHERE created_at >= date_trunc('month', NOW()) - make_interval(months => $1 - 1)
+        GROUP BY 1, payment_method
+        ORDER BY 1 DESC, payment_method
+        "#,
+        months
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to build fee report: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(rows))
+}
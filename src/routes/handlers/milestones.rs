@@ -1,13 +1,19 @@
 use axum::{
-    extract::{State, Path},
-    http::StatusCode,
-    response::Json,
+    extract::{State, Path, Query},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
+use serde::Serialize;
 use uuid::Uuid;
 use crate::{
-    models::{Milestone, MilestoneReleaseRequest},
+    config::EscrowMode,
+    models::{Milestone, MilestoneProofRequest, MilestoneReleaseRequest},
+    routes::handlers::approvals::{dual_control, ApprovalQuery},
+    services::approvals,
+    services::notifications::NotificationEvent,
+    services::{contract_client::ContractClient, email, follows, mobile_payouts, outgoing_webhooks, payouts, project_members, stellar_tx::TxSubmitter},
     state::AppState,
-    utils::jwt,
+    utils::{jwt, money::Stroops},
 };
 
 /// Create a milestone for a project
@@ -40,7 +46,7 @@ pub async fn create_milestone(
             )
         })?;
 
-    let target_amount: sqlx::types::BigDecimal = payload.get("target_amount")
+    let target_amount: Stroops = payload.get("target_amount")
         .and_then(|v| serde_json::from_value(v.clone()).ok())
         .ok_or_else(|| {
             (
@@ -49,17 +55,42 @@ pub async fn create_milestone(
             )
         })?;
 
+    let status = sqlx::query_scalar!("SELECT status FROM projects WHERE id = $1", project_id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": "Failed to load project"})),
+            )
+        })?;
+    match status.as_deref() {
+        None => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({"error": "Project not found"})),
+            ))
+        }
+        Some("completed") => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({"error": "A completed project can't be edited", "code": "project_closed"})),
+            ))
+        }
+        Some(_) => {}
+    }
+
     // Create milestone
     let milestone = sqlx::query_as!(
         Milestone,
         r#"
         INSERT INTO milestones (project_id, title, target_amount)
         VALUES ($1, $2, $3)
-        RETURNING id, project_id, title, target_amount as "target_amount!: sqlx::types::BigDecimal", released as "released!: bool", released_at, created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        RETURNING id, project_id, title, target_amount as "target_amount!: Stroops", released as "released!: bool", released_at, created_at as "created_at!: chrono::DateTime<chrono::Utc>"
         "#,
         project_id,
         title,
-        target_amount
+        target_amount.to_decimal()
     )
     .fetch_one(&state.pool)
     .await
@@ -88,6 +119,19 @@ pub async fn create_milestone(
     .execute(&state.pool)
     .await;
 
+    let notified = follows::notify_followers(
+        &state.pool,
+        project_id,
+        "New milestone",
+        &format!("A new milestone was added: {}", title),
+        serde_json::json!({"project_id": project_id, "milestone_id": milestone.id}),
+        &NotificationEvent::MilestoneCreated { project_id, milestone_id: milestone.id },
+    )
+    .await;
+    if let Err(e) = notified {
+        tracing::warn!("Failed to notify followers of milestone {}: {}", milestone.id, e);
+    }
+
     Ok((StatusCode::CREATED, Json(milestone)))
 }
 
@@ -109,7 +153,7 @@ pub async fn get_project_milestones(
     let milestones = sqlx::query_as!(
         Milestone,
         r#"
-        SELECT id, project_id, title, target_amount as "target_amount!: sqlx::types::BigDecimal", released as "released!: bool", released_at, created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        SELECT id, project_id, title, target_amount as "target_amount!: Stroops", released as "released!: bool", released_at, created_at as "created_at!: chrono::DateTime<chrono::Utc>"
         FROM milestones
         WHERE project_id = $1
         ORDER BY created_at ASC
@@ -128,13 +172,105 @@ pub async fn get_project_milestones(
     Ok(Json(milestones))
 }
 
-/// Release a milestone (admin only)
+/// Anchor a proof of the milestone deliverable on-chain
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/milestones/{milestone_id}/proof",
+    request_body = MilestoneProofRequest,
+    responses(
+        (status = 200, description = "Proof submitted successfully"),
+        (status = 400, description = "Invalid proof hash or milestone already released"),
+        (status = 403, description = "Forbidden - not on the project's team"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Milestones"
+)]
+pub async fn submit_proof(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((project_id, milestone_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<MilestoneProofRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    // User verification is handled by the middleware; only the project's
+    // owner or an active team member may anchor proofs
+    let user_id = jwt::extract_user_id_from_headers(&headers).map_err(|_| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "Authentication required"})),
+        )
+    })?;
+    let membership = project_members::membership(&state.pool, project_id, user_id).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": "Failed to load project membership"})),
+        )
+    })?;
+    if !membership.is_some_and(|m| m.is_active()) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "Only the project's team can submit proofs"})),
+        ));
+    }
+
+    if payload.proof_uri.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "Proof URI is required"})),
+        ));
+    }
+
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": "Failed to load contracts"})),
+        )
+    })?;
+
+    let result = contract_client
+        .submit_proof(project_id, &milestone_id.to_string(), &payload.proof_hash, &payload.proof_uri)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        })?;
+
+    // Log activity
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        "milestone_proof_submitted",
+        milestone_id,
+        "milestone",
+        serde_json::json!({
+            "project_id": project_id,
+            "proof_hash": payload.proof_hash,
+            "proof_uri": payload.proof_uri
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(serde_json::json!({
+        "message": result,
+        "milestone_id": milestone_id,
+        "proof_hash": payload.proof_hash
+    })))
+}
+
+/// Release a milestone (admin only). Needs a second admin: the first call
+/// opens an approval request.
 #[utoipa::path(
     post,
     path = "/api/projects/{project_id}/milestones/{milestone_id}/release",
     request_body = MilestoneReleaseRequest,
     responses(
         (status = 200, description = "Milestone released successfully"),
+        (status = 202, description = "Approval request opened"),
         (status = 400, description = "Invalid request data"),
         (status = 403, description = "Forbidden - admin only"),
         (status = 404, description = "Milestone not found"),
@@ -144,16 +280,36 @@ pub async fn get_project_milestones(
 )]
 pub async fn release_milestone(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Path((project_id, milestone_id)): Path<(Uuid, Uuid)>,
+    Query(approval): Query<ApprovalQuery>,
     Json(payload): Json<MilestoneReleaseRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    // Admin verification is handled by the middleware
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+    // The route is behind require_admin_mw
+    let approval_payload = serde_json::json!({
+        "project_id": project_id,
+        "milestone_id": milestone_id,
+        "tx_hash": payload.tx_hash,
+        "mobile_money_phone": payload.mobile_money_phone
+    });
+    if let Some(pending) = dual_control(&state, &headers, approvals::MILESTONE_RELEASE, approval.approval_id, approval_payload).await? {
+        return Ok(pending);
+    }
 
+    release(&state, project_id, milestone_id, payload).await.map(IntoResponse::into_response)
+}
+
+async fn release(
+    state: &AppState,
+    project_id: Uuid,
+    milestone_id: Uuid,
+    payload: MilestoneReleaseRequest,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     // Get milestone details
     let milestone = sqlx::query_as!(
         Milestone,
         r#"
-        SELECT id, project_id, title, target_amount as "target_amount!: sqlx::types::BigDecimal", released as "released!: bool", released_at, created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        SELECT id, project_id, title, target_amount as "target_amount!: Stroops", released as "released!: bool", released_at, created_at as "created_at!: chrono::DateTime<chrono::Utc>"
         FROM milestones
         WHERE id = $1 AND project_id = $2
         "#,
@@ -176,16 +332,65 @@ pub async fn release_milestone(
         ));
     }
 
-    // Update milestone as released
-    let _ = sqlx::query!(
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": "Failed to load contracts"})),
+        )
+    })?;
+    let releasable = contract_client
+        .can_release_milestone(project_id, &milestone_id.to_string())
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": "Failed to check milestone proof"})),
+            )
+        })?;
+
+    if !releasable {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "Proof required before release"})),
+        ));
+    }
+
+    // Milestones put to a donor vote wait for the outcome
+    let tally = contract_client
+        .get_vote_tally(project_id, &milestone_id.to_string())
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": "Failed to check milestone vote"})),
+            )
+        })?;
+
+    if let Some(reason) = tally.and_then(|t| t.blocking_reason(chrono::Utc::now())) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": reason})),
+        ));
+    }
+
+    // Mobile money payouts are queued for approval; the B2C result releases the milestone
+    if let Some(phone) = payload.mobile_money_phone.as_deref().filter(|p| !p.trim().is_empty()) {
+        return request_mobile_payout(state, project_id, &milestone, phone.trim()).await;
+    }
+
+    // Claim the milestone before paying so a concurrent release can't pay it again
+    let claimed = sqlx::query_scalar!(
         r#"
-        UPDATE milestones 
+        UPDATE milestones
         SET released = true, released_at = CURRENT_TIMESTAMP
-        WHERE id = $1
+        WHERE id = $1 AND project_id = $2 AND NOT released
+        RETURNING id
         "#,
-        milestone_id
+        milestone_id,
+        project_id
     )
-    .execute(&state.pool)
+    .fetch_optional(&state.pool)
     .await
     .map_err(|_| {
         (
@@ -193,6 +398,59 @@ pub async fn release_milestone(
             Json(serde_json::json!({"error": "Failed to release milestone"})),
         )
     })?;
+    if claimed.is_none() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": "Milestone already released"})),
+        ));
+    }
+
+    // Pooled funds sit in the platform wallet, so the platform pays out,
+    // split between the project's team by their shares; students without a
+    // usable wallet get a claimable balance
+    let mut claimable_balance_id = None;
+    let mut member_payouts = Vec::new();
+    let paid = match payload.tx_hash {
+        Some(tx_hash) => Ok(tx_hash),
+        None if state.escrow_mode == EscrowMode::Pool => match state.payments.as_ref() {
+            Some(payments) => pay_team(state, payments, project_id, &milestone).await.and_then(|payouts| {
+                member_payouts = payouts;
+                let owner = member_payouts.first().ok_or_else(|| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(serde_json::json!({"error": "Milestone has nothing to pay out"})),
+                    )
+                })?;
+                claimable_balance_id = owner.claimable_balance_id.clone();
+                Ok(owner.tx_hash.clone())
+            }),
+            None => Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({"error": "Platform payments are not configured"})),
+            )),
+        },
+        None => Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "tx_hash is required"})),
+        )),
+    };
+    let tx_hash = match paid {
+        Ok(tx_hash) => tx_hash,
+        Err(e) => {
+            // Hand the claim back so the release can be retried; members
+            // already paid are skipped on the retry
+            let unclaimed = sqlx::query!(
+                "UPDATE milestones SET released = false, released_at = NULL WHERE id = $1",
+                milestone_id
+            )
+            .execute(&state.pool)
+            .await;
+            if let Err(db) = unclaimed {
+                tracing::error!("Failed to unclaim milestone {} after a failed release: {}", milestone_id, db);
+            }
+            return Err(e);
+        }
+    };
 
     // Log activity
     let _ = sqlx::query!(
@@ -205,16 +463,213 @@ pub async fn release_milestone(
         "milestone",
         serde_json::json!({
             "project_id": project_id,
-            "tx_hash": payload.tx_hash,
+            "tx_hash": tx_hash,
+            "claimable_balance_id": claimable_balance_id,
             "amount": milestone.target_amount
         })
     )
     .execute(&state.pool)
     .await;
 
+    if let Err(e) = email::queue_milestone_released(&state.pool, milestone_id, Some(state.network.transaction_url(&tx_hash))).await {
+        tracing::error!("Failed to queue milestone release emails for {}: {}", milestone_id, e);
+    }
+    if let Err(e) = outgoing_webhooks::queue_milestone_released(&state.pool, milestone_id, Some(&tx_hash)).await {
+        tracing::error!("Failed to queue milestone release webhooks for {}: {}", milestone_id, e);
+    }
+
     Ok(Json(serde_json::json!({
         "message": "Milestone released successfully",
         "milestone_id": milestone_id,
-        "tx_hash": payload.tx_hash
+        "tx_hash": tx_hash,
+        "claimable_balance_id": claimable_balance_id,
+        "payouts": member_payouts
+    })))
+}
+
+/// One team member's part of a milestone payout
+#[derive(Debug, Clone, Serialize)]
+struct MemberPayout {
+    student_id: Uuid,
+    amount: Stroops,
+    tx_hash: String,
+    claimable_balance_id: Option<String>,
+}
+
+/// Pay each team member their share of a milestone, owner first. Each
+/// member's payout row is reserved before paying, so members already paid
+/// by an earlier or concurrent attempt aren't paid again.
+async fn pay_team(
+    state: &AppState,
+    payments: &TxSubmitter,
+    project_id: Uuid,
+    milestone: &Milestone,
+) -> Result<Vec<MemberPayout>, (StatusCode, Json<serde_json::Value>)> {
+    let internal = |message: &str| (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": message})));
+
+    let split = project_members::payout_split(&state.pool, project_id, milestone.target_amount)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to split milestone {} between the team: {}", milestone.id, e);
+            internal("Failed to load the project's team")
+        })?;
+
+    let memo = format!("milestone:{}", &milestone.id.simple().to_string()[..16]);
+    let mut paid = Vec::new();
+    for (student_id, amount) in split {
+        let reserved = sqlx::query_scalar!(
+            r#"
+            INSERT INTO milestone_member_payouts (milestone_id, student_id, amount)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (milestone_id, student_id) DO NOTHING
+            RETURNING student_id
+            "#,
+            milestone.id,
+            student_id,
+            amount.to_decimal()
+        )
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| internal("Failed to reserve the member payout"))?;
+        if reserved.is_none() {
+            let earlier = sqlx::query!(
+                "SELECT tx_hash, claimable_balance_id FROM milestone_member_payouts WHERE milestone_id = $1 AND student_id = $2",
+                milestone.id,
+                student_id
+            )
+            .fetch_one(&state.pool)
+            .await
+            .map_err(|_| internal("Failed to load earlier payouts"))?;
+            // A reservation without a tx hash is a payout still being sent
+            let tx_hash = earlier.tx_hash.ok_or_else(|| {
+                (
+                    StatusCode::CONFLICT,
+                    Json(serde_json::json!({"error": "A payout to this team member is already in progress", "student_id": student_id})),
+                )
+            })?;
+            paid.push(MemberPayout { student_id, amount, tx_hash, claimable_balance_id: earlier.claimable_balance_id });
+            continue;
+        }
+
+        let payout = payouts::pay_student(&state.pool, payments, student_id, amount, Some(&memo), "milestone", Some(milestone.id))
+            .await
+            .map_err(|e| {
+                tracing::error!("Milestone {} payout to student {} failed: {}", milestone.id, student_id, e);
+                (
+                    StatusCode::BAD_GATEWAY,
+                    Json(serde_json::json!({"error": "Failed to pay out milestone"})),
+                )
+            })
+            .and_then(|payout| {
+                payout.ok_or_else(|| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(serde_json::json!({"error": "A team member has no wallet to pay out to", "student_id": student_id})),
+                    )
+                })
+            });
+        let payout = match payout {
+            Ok(payout) => payout,
+            Err(e) => {
+                // The payment didn't go through, so free the reservation for a retry
+                let released = sqlx::query!(
+                    "DELETE FROM milestone_member_payouts WHERE milestone_id = $1 AND student_id = $2 AND tx_hash IS NULL",
+                    milestone.id,
+                    student_id
+                )
+                .execute(&state.pool)
+                .await;
+                if let Err(db) = released {
+                    tracing::error!("Failed to free milestone {} payout reservation for {}: {}", milestone.id, student_id, db);
+                }
+                return Err(e);
+            }
+        };
+
+        let claimable_balance_id = match &payout {
+            payouts::Payout::Claimable { balance_id, .. } => Some(balance_id.clone()),
+            payouts::Payout::Paid { .. } => None,
+        };
+        let recorded = sqlx::query!(
+            r#"
+            UPDATE milestone_member_payouts
+            SET tx_hash = $3, claimable_balance_id = $4
+            WHERE milestone_id = $1 AND student_id = $2
+            "#,
+            milestone.id,
+            student_id,
+            payout.tx_hash(),
+            claimable_balance_id
+        )
+        .execute(&state.pool)
+        .await;
+        if let Err(e) = recorded {
+            tracing::error!("Failed to record milestone {} payout {}: {}", milestone.id, payout.tx_hash(), e);
+        }
+
+        paid.push(MemberPayout { student_id, amount, tx_hash: payout.tx_hash().to_string(), claimable_balance_id });
+    }
+    Ok(paid)
+}
+
+async fn request_mobile_payout(
+    state: &AppState,
+    project_id: Uuid,
+    milestone: &Milestone,
+    phone: &str,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let kes_per_xlm = crate::config::mpesa_payout_kes_per_xlm()
+        .filter(|_| state.payment_providers.mpesa_config().is_some_and(|c| c.b2c.is_some()))
+        .ok_or_else(|| {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({"error": "Mobile money payouts are not configured"})),
+            )
+        })?;
+
+    let too_small = mobile_payouts::kes_amount(milestone.target_amount, kes_per_xlm).map_or(true, |kes| !kes.is_positive());
+    if too_small {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "Milestone amount is too small to pay out by mobile money"})),
+        ));
+    }
+
+    // The active-payout unique index turns a second request into an error here
+    let payout = mobile_payouts::request_payout(&state.pool, project_id, milestone.id, phone, milestone.target_amount, kes_per_xlm)
+        .await
+        .map_err(|e| {
+            tracing::warn!("Mobile payout request for milestone {} failed: {}", milestone.id, e);
+            (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({"error": "A payout for this milestone is already in progress"})),
+            )
+        })?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        "milestone_payout_requested",
+        milestone.id,
+        "milestone",
+        serde_json::json!({
+            "project_id": project_id,
+            "payout_id": payout.id,
+            "payout_method": "mpesa",
+            "amount": payout.amount_xlm,
+            "amount_kes": payout.amount_kes
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(serde_json::json!({
+        "message": "Mobile money payout awaiting approval",
+        "milestone_id": milestone.id,
+        "payout_id": payout.id,
+        "amount_kes": payout.amount_kes,
+        "status": payout.status
     })))
 }
@@ -1,15 +1,47 @@
 pub mod auth;
 pub mod students;
+pub mod files;
+pub mod verification_documents;
+pub mod bulk_verifications;
 pub mod wallets;
 pub mod wallet;
 pub mod projects;
+pub mod project_members;
+pub mod project_revisions;
+pub mod project_media;
+pub mod project_updates;
+pub mod refunds;
 pub mod donations;
+pub mod donors;
+pub mod feature_flags;
+pub mod features;
+pub mod follows;
 pub mod campaigns;
+pub mod categories;
+pub mod university_domains;
+pub mod comments;
+pub mod reports;
 pub mod admin;
+pub mod api_keys;
 pub mod analytics;
+pub mod announcements;
+pub mod realtime;
+pub mod push_subscriptions;
+pub mod notification_preferences;
 pub mod contracts;
 pub mod docs;
 pub mod guest;
+pub mod ledger;
 pub mod milestones;
 pub mod notifications;
-pub mod payments;
\ No newline at end of file
+pub mod ops;
+pub mod payment_providers;
+pub mod payments;
+pub mod status;
+pub mod subscriptions;
+pub mod two_factor;
+pub mod sessions;
+pub mod roles;
+pub mod approvals;
+pub mod usage;
+pub mod webhooks;
\ No newline at end of file
@@ -0,0 +1,177 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::ValidatedJson;
+use crate::services::digests::{self, DigestSettings, CADENCES};
+use crate::services::notification_preferences::{self, Medium, Overrides, Preference, EVENT_TYPES};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdatePreferencesRequest {
+    #[validate(length(min = 1, max = 50, message = "Send 1 to 50 preferences"))]
+    pub preferences: Vec<PreferenceChange>,
+}
+
+/// New settings for one event type; mediums left out are unchanged
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PreferenceChange {
+    pub event_type: String,
+    pub in_app: Option<bool>,
+    pub email: Option<bool>,
+    pub push: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateDigestRequest {
+    /// `off`, `daily` or `weekly`
+    pub cadence: String,
+    /// Hour of the day (UTC) to send it; unchanged when left out
+    #[validate(range(min = 0, max = 23, message = "send_hour must be 0 to 23"))]
+    pub send_hour: Option<i16>,
+    /// Day for weekly digests, 0 for Monday through 6 for Sunday
+    #[validate(range(min = 0, max = 6, message = "send_weekday must be 0 to 6"))]
+    pub send_weekday: Option<i16>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeQuery {
+    pub token: String,
+}
+
+fn caller(headers: &HeaderMap) -> AppResult<Uuid> {
+    crate::utils::jwt::extract_user_id_from_headers(headers).map_err(|_| AppError::unauthorized("Authentication required"))
+}
+
+fn check(change: &PreferenceChange) -> AppResult<()> {
+    if !EVENT_TYPES.contains(&change.event_type.as_str()) {
+        return Err(AppError::invalid("event_type", format!("Unknown event type '{}'", change.event_type)));
+    }
+    for (field, medium, value) in [("email", Medium::Email, change.email), ("push", Medium::Push, change.push)] {
+        if value.is_some() && !notification_preferences::offers(&change.event_type, medium) {
+            return Err(AppError::invalid(field, format!("'{}' events are not sent by {}", change.event_type, field)));
+        }
+    }
+    Ok(())
+}
+
+/// How each event type reaches the caller: in-app, by email and by push,
+/// with their role's defaults filled in
+pub async fn get_preferences(State(state): State<AppState>, headers: HeaderMap) -> AppResult<Json<Vec<Preference>>> {
+    let user_id = caller(&headers)?;
+    Ok(Json(notification_preferences::for_user(&state.pool, user_id).await?))
+}
+
+/// Change how event types reach the caller, returning every preference
+pub async fn update_preferences(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<UpdatePreferencesRequest>,
+) -> AppResult<Json<Vec<Preference>>> {
+    let user_id = caller(&headers)?;
+    for change in &req.preferences {
+        check(change)?;
+    }
+
+    for change in &req.preferences {
+        let overrides = Overrides { in_app: change.in_app, email: change.email, push: change.push };
+        notification_preferences::update(&state.pool, user_id, &change.event_type, overrides).await?;
+    }
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        "notification_preferences_updated",
+        user_id,
+        "user",
+        serde_json::json!({
+            "event_types": req.preferences.iter().map(|c| c.event_type.as_str()).collect::<Vec<_>>(),
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(notification_preferences::for_user(&state.pool, user_id).await?))
+}
+
+/// The caller's digest cadence and send time
+pub async fn get_digest(State(state): State<AppState>, headers: HeaderMap) -> AppResult<Json<DigestSettings>> {
+    let user_id = caller(&headers)?;
+    Ok(Json(digests::settings(&state.pool, user_id).await?))
+}
+
+/// Choose a daily or weekly email digest, or turn it off
+pub async fn update_digest(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<UpdateDigestRequest>,
+) -> AppResult<Json<DigestSettings>> {
+    let user_id = caller(&headers)?;
+    if !CADENCES.contains(&req.cadence.as_str()) {
+        return Err(AppError::invalid("cadence", format!("Must be one of {}", CADENCES.join(", "))));
+    }
+    let current = digests::settings(&state.pool, user_id).await?;
+    let settings = digests::update_settings(
+        &state.pool,
+        user_id,
+        &req.cadence,
+        req.send_hour.unwrap_or(current.send_hour),
+        req.send_weekday.unwrap_or(current.send_weekday),
+    )
+    .await?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        "notification_digest_updated",
+        user_id,
+        "user",
+        serde_json::json!({
+            "cadence": settings.cadence,
+            "send_hour": settings.send_hour,
+            "send_weekday": settings.send_weekday,
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(settings))
+}
+
+/// Turn off a digest from the unsubscribe link in it; no sign-in needed
+pub async fn unsubscribe_digest(
+    State(state): State<AppState>,
+    Query(query): Query<UnsubscribeQuery>,
+) -> AppResult<Json<serde_json::Value>> {
+    let user_id = digests::unsubscribe(&state.pool, &query.token)
+        .await?
+        .ok_or_else(|| AppError::not_found("Unsubscribe link not found"))?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        "notification_digest_unsubscribed",
+        user_id,
+        "user",
+        serde_json::json!({})
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(serde_json::json!({ "message": "You won't get digest emails anymore" })))
+}
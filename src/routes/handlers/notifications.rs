@@ -1,5 +1,5 @@
 use axum::{
-    extract::{State, Path},
+    extract::{State, Path, Query},
     http::StatusCode,
     Json,
 };
@@ -7,6 +7,9 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+use crate::services::notifications::{EventPayload, NotificationEvent};
+use crate::utils::pagination::{Page, PageQuery, PageRequest};
+
 #[derive(Serialize)]
 pub struct NotificationResponse {
     pub id: Uuid,
@@ -16,10 +19,48 @@ pub struct NotificationResponse {
     pub message: String,
     pub is_read: bool,
     pub metadata: Option<serde_json::Value>,
+    /// The event behind the notification, as sent over SSE; absent for
+    /// notifications created by hand or before events were recorded
+    pub event: Option<EventPayload>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+struct NotificationRow {
+    id: Uuid,
+    user_id: Uuid,
+    notification_type: String,
+    title: String,
+    message: String,
+    is_read: bool,
+    metadata: Option<serde_json::Value>,
+    event: Option<serde_json::Value>,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl From<NotificationRow> for NotificationResponse {
+    fn from(row: NotificationRow) -> Self {
+        // Events this build doesn't know are left out rather than failing the list
+        let event = row
+            .event
+            .and_then(|event| serde_json::from_value::<NotificationEvent>(event).ok())
+            .map(EventPayload::from);
+        Self {
+            id: row.id,
+            user_id: row.user_id,
+            notification_type: row.notification_type,
+            title: row.title,
+            message: row.message,
+            is_read: row.is_read,
+            metadata: row.metadata,
+            event,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct CreateNotificationRequest {
     pub user_id: Uuid,
@@ -32,12 +73,14 @@ pub struct CreateNotificationRequest {
 pub async fn get_notifications(
     State(state): State<crate::state::AppState>,
     headers: axum::http::HeaderMap,
-) -> Result<Json<Vec<NotificationResponse>>, StatusCode> {
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Page<NotificationResponse>>, StatusCode> {
     let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
         .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit).map_err(|_| StatusCode::BAD_REQUEST)?;
 
     let notifications = sqlx::query_as!(
-        NotificationResponse,
+        NotificationRow,
         r#"
         SELECT 
             id,
@@ -47,19 +90,26 @@ pub async fn get_notifications(
             message,
             is_read,
             metadata,
+            event,
             created_at,
             updated_at
         FROM notifications 
         WHERE user_id = $1 
-        ORDER BY created_at DESC
+        AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3::uuid))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
         "#,
-        user_id
+        user_id,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
     )
     .fetch_all(&state.pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let notifications: Vec<NotificationResponse> = notifications.into_iter().map(NotificationResponse::from).collect();
 
-    Ok(Json(notifications))
+    Ok(Json(Page::new(notifications, &page, |n| (Some(n.created_at), n.id))))
 }
 
 pub async fn mark_notification_read(
@@ -150,13 +200,13 @@ pub async fn create_notification(
     Json(req): Json<CreateNotificationRequest>,
 ) -> Result<Json<NotificationResponse>, StatusCode> {
     let notification = sqlx::query_as!(
-        NotificationResponse,
+        NotificationRow,
         r#"
         INSERT INTO notifications (
             id, user_id, notification_type, title, message, metadata, is_read, created_at
         )
         VALUES ($1, $2, $3, $4, $5, $6, false, NOW())
-        RETURNING id, user_id, notification_type, title, message, is_read, metadata, created_at, updated_at
+        RETURNING id, user_id, notification_type, title, message, is_read, metadata, event, created_at, updated_at
         "#,
         Uuid::new_v4(),
         req.user_id,
@@ -169,7 +219,7 @@ pub async fn create_notification(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    Ok(Json(notification))
+    Ok(Json(notification.into()))
 }
 
 pub async fn get_unread_count(
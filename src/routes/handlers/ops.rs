@@ -0,0 +1,231 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::services::contract_client::ContractClient;
+use crate::services::jobs;
+use crate::workers::control::{WorkerControl, WORKER_NAMES};
+
+type OpsResult = Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)>;
+
+#[derive(Serialize)]
+pub struct WorkerStatus {
+    pub name: String,
+    pub paused: bool,
+}
+
+fn ops_error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({"error": message})))
+}
+
+/// Record an ops action in the activity log so runbook steps are auditable
+async fn audit(
+    state: &crate::state::AppState,
+    headers: &axum::http::HeaderMap,
+    action: &str,
+    metadata: serde_json::Value,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let admin_id: Uuid = crate::utils::jwt::extract_user_id_from_headers(headers)
+        .map_err(|_| ops_error(StatusCode::UNAUTHORIZED, "Invalid token"))?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_type, metadata)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        admin_id,
+        action,
+        "ops",
+        metadata
+    )
+    .execute(&state.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to audit ops action {}: {}", action, e);
+        ops_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to record audit log")
+    })?;
+
+    Ok(())
+}
+
+/// List background workers and whether they are paused
+pub async fn list_workers(
+    State(state): State<crate::state::AppState>,
+) -> Result<Json<Vec<WorkerStatus>>, StatusCode> {
+    let workers = WORKER_NAMES
+        .iter()
+        .map(|name| WorkerStatus {
+            name: name.to_string(),
+            paused: state.worker_control.is_paused(name),
+        })
+        .collect();
+
+    Ok(Json(workers))
+}
+
+pub async fn pause_worker(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    Path(name): Path<String>,
+) -> OpsResult {
+    if !WorkerControl::is_known(&name) {
+        return Err(ops_error(StatusCode::NOT_FOUND, "Unknown worker"));
+    }
+
+    audit(&state, &headers, "ops_worker_paused", serde_json::json!({"worker": name})).await?;
+    state.worker_control.pause(&name);
+    tracing::warn!("Worker {} paused by admin", name);
+
+    Ok(Json(serde_json::json!({"worker": name, "paused": true})))
+}
+
+pub async fn resume_worker(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    Path(name): Path<String>,
+) -> OpsResult {
+    if !WorkerControl::is_known(&name) {
+        return Err(ops_error(StatusCode::NOT_FOUND, "Unknown worker"));
+    }
+
+    audit(&state, &headers, "ops_worker_resumed", serde_json::json!({"worker": name})).await?;
+    state.worker_control.resume(&name);
+    tracing::info!("Worker {} resumed by admin", name);
+
+    Ok(Json(serde_json::json!({"worker": name, "paused": false})))
+}
+
+/// Clear cached analytics summaries; the analytics workers rebuild them on their next run
+pub async fn flush_analytics_cache(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+) -> OpsResult {
+    let result = sqlx::query!("DELETE FROM analytics_summary")
+        .execute(&state.pool)
+        .await
+        .map_err(|_| ops_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to flush analytics cache"))?;
+
+    let rows = result.rows_affected();
+    audit(&state, &headers, "ops_analytics_cache_flushed", serde_json::json!({"rows": rows})).await?;
+
+    Ok(Json(serde_json::json!({"flushed_rows": rows})))
+}
+
+/// Rebuild full-text search vectors for all projects
+pub async fn reindex_search(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+) -> OpsResult {
+    let result = sqlx::query!(
+        r#"
+        UPDATE projects
+        SET search_vector = setweight(to_tsvector('english', COALESCE(title, '')), 'A')
+            || setweight(to_tsvector('english', COALESCE(description, '')), 'B')
+            || setweight(to_tsvector('english', array_to_string(tags, ' ')), 'C')
+        "#
+    )
+    .execute(&state.pool)
+    .await
+    .map_err(|_| ops_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to re-index search vectors"))?;
+
+    let rows = result.rows_affected();
+    audit(&state, &headers, "ops_search_reindexed", serde_json::json!({"projects": rows})).await?;
+
+    Ok(Json(serde_json::json!({"reindexed_projects": rows})))
+}
+
+/// Drop every SSE channel, disconnecting current subscribers
+pub async fn rotate_sse_channel(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+) -> OpsResult {
+    audit(&state, &headers, "ops_sse_channel_rotated", serde_json::json!({})).await?;
+    let disconnected = state.notifier.rotate();
+    tracing::warn!("SSE channel rotated, {} subscribers disconnected", disconnected);
+
+    Ok(Json(serde_json::json!({"disconnected_subscribers": disconnected})))
+}
+
+/// Re-load contract addresses from the deploy script output into the contracts table
+pub async fn reload_contracts(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+) -> OpsResult {
+    let path = std::env::var("CONTRACT_ADDRESSES_FILE")
+        .unwrap_or_else(|_| "contracts/contract-addresses.json".to_string());
+    let raw = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|_| ops_error(StatusCode::NOT_FOUND, "Contract addresses file not found"))?;
+    let parsed: serde_json::Value = serde_json::from_str(&raw)
+        .map_err(|_| ops_error(StatusCode::UNPROCESSABLE_ENTITY, "Invalid contract addresses file"))?;
+    let addresses = parsed
+        .get("contracts")
+        .and_then(|c| c.as_object())
+        .ok_or_else(|| ops_error(StatusCode::UNPROCESSABLE_ENTITY, "Missing contracts section"))?;
+
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    for (name, address) in addresses {
+        if let Some(address) = address.as_str() {
+            contract_client
+                .update_contract_address(name, address)
+                .await
+                .map_err(|_| ops_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update contract address"))?;
+        }
+    }
+
+    contract_client
+        .load_contracts()
+        .await
+        .map_err(|_| ops_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load contracts"))?;
+
+    let loaded: serde_json::Map<String, serde_json::Value> = contract_client
+        .get_contracts()
+        .iter()
+        .map(|(name, info)| (name.clone(), serde_json::json!(info.address)))
+        .collect();
+
+    audit(&state, &headers, "ops_contracts_reloaded", serde_json::json!({"source": path, "contracts": loaded})).await?;
+
+    Ok(Json(serde_json::json!({"contracts": loaded})))
+}
+
+#[derive(Deserialize)]
+pub struct JobsQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Background jobs, newest first; `?status=dead` lists the dead-letter queue
+pub async fn list_jobs(
+    State(state): State<crate::state::AppState>,
+    Query(query): Query<JobsQuery>,
+) -> OpsResult {
+    if let Some(status) = query.status.as_deref() {
+        if !["queued", "succeeded", "dead"].contains(&status) {
+            return Err(ops_error(StatusCode::BAD_REQUEST, "status must be queued, succeeded or dead"));
+        }
+    }
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let jobs = jobs::list(&state.pool, query.status.as_deref(), limit)
+        .await
+        .map_err(|_| ops_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list jobs"))?;
+
+    Ok(Json(serde_json::json!({"jobs": jobs})))
+}
+
+/// Requeue a dead-lettered job with a fresh set of attempts
+pub async fn retry_job(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    Path(id): Path<Uuid>,
+) -> OpsResult {
+    let job = jobs::retry(&state.pool, id)
+        .await
+        .map_err(|_| ops_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to retry job"))?
+        .ok_or_else(|| ops_error(StatusCode::NOT_FOUND, "No dead-lettered job with that id"))?;
+
+    audit(&state, &headers, "ops_job_retried", serde_json::json!({"job_id": id, "kind": job.kind})).await?;
+    tracing::info!("Job {} ({}) requeued by admin", id, job.kind);
+
+    Ok(Json(serde_json::json!({"job": job})))
+}
@@ -0,0 +1,174 @@
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::services::payment_service::PROVIDER_NAMES;
+
+type ProviderResult<T> = Result<Json<T>, (StatusCode, Json<serde_json::Value>)>;
+
+fn provider_error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({"error": message})))
+}
+
+#[derive(Serialize)]
+pub struct PaymentProviderSettings {
+    pub name: String,
+    /// Whether the provider is live right now, from either its row or the environment
+    pub loaded: bool,
+    pub source: Option<String>,
+    pub enabled: Option<bool>,
+    /// Keys set in the row; values are credentials and never leave the server
+    pub config_keys: Vec<String>,
+    pub currencies: Vec<String>,
+    pub countries: Vec<String>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdatePaymentProviderRequest {
+    pub enabled: Option<bool>,
+    /// Merged into the stored config; a `null` value removes the key
+    pub config: Option<serde_json::Map<String, serde_json::Value>>,
+    pub currencies: Option<Vec<String>>,
+    pub countries: Option<Vec<String>>,
+}
+
+/// List every known payment provider with its stored settings and load state
+pub async fn list_payment_providers(
+    State(state): State<crate::state::AppState>,
+) -> ProviderResult<Vec<PaymentProviderSettings>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT name, enabled, config, currencies, countries, updated_by, updated_at
+        FROM payment_providers
+        "#
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list payment providers: {}", e);
+        provider_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list payment providers")
+    })?;
+
+    let summaries = state.payment_providers.summaries();
+    let providers = PROVIDER_NAMES
+        .iter()
+        .map(|name| {
+            let row = rows.iter().find(|row| row.name == *name);
+            let summary = summaries.iter().find(|summary| summary.name == *name);
+            let mut config_keys: Vec<String> = row
+                .and_then(|row| row.config.as_object())
+                .map(|config| config.keys().cloned().collect())
+                .unwrap_or_default();
+            config_keys.sort();
+
+            PaymentProviderSettings {
+                name: name.to_string(),
+                loaded: summary.is_some(),
+                source: summary.map(|summary| summary.source.clone()),
+                enabled: row.map(|row| row.enabled),
+                config_keys,
+                currencies: row.map(|row| row.currencies.clone()).unwrap_or_default(),
+                countries: row.map(|row| row.countries.clone()).unwrap_or_default(),
+                updated_by: row.and_then(|row| row.updated_by),
+                updated_at: row.map(|row| row.updated_at),
+            }
+        })
+        .collect();
+
+    Ok(Json(providers))
+}
+
+/// Create or update a provider's row and reload the registry so the change
+/// takes effect immediately
+pub async fn update_payment_provider(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    Path(name): Path<String>,
+    Json(req): Json<UpdatePaymentProviderRequest>,
+) -> ProviderResult<serde_json::Value> {
+    if !PROVIDER_NAMES.contains(&name.as_str()) {
+        return Err(provider_error(StatusCode::NOT_FOUND, "Unknown payment provider"));
+    }
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers).ok();
+
+    // Settings are looked up by lowercase key
+    let config: serde_json::Map<String, serde_json::Value> = req
+        .config
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(key, value)| (key.to_lowercase(), value))
+        .collect();
+    let changed_keys: Vec<&String> = config.keys().collect();
+    let currencies = req
+        .currencies
+        .map(|codes| codes.iter().map(|code| code.trim().to_uppercase()).collect::<Vec<_>>());
+    let countries = req
+        .countries
+        .map(|codes| codes.iter().map(|code| code.trim().to_uppercase()).collect::<Vec<_>>());
+
+    sqlx::query!(
+        r#"
+        INSERT INTO payment_providers (name, enabled, config, currencies, countries, updated_by)
+        VALUES ($1, COALESCE($2, TRUE), jsonb_strip_nulls($3), COALESCE($4, '{}'::text[]), COALESCE($5, '{}'::text[]), $6)
+        ON CONFLICT (name) DO UPDATE SET
+            enabled = COALESCE($2, payment_providers.enabled),
+            config = jsonb_strip_nulls(payment_providers.config || $3),
+            currencies = COALESCE($4, payment_providers.currencies),
+            countries = COALESCE($5, payment_providers.countries),
+            updated_by = $6,
+            updated_at = NOW()
+        "#,
+        name,
+        req.enabled,
+        serde_json::Value::Object(config.clone()),
+        currencies.as_deref(),
+        countries.as_deref(),
+        admin_id
+    )
+    .execute(&state.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update payment provider {}: {}", name, e);
+        provider_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to update payment provider")
+    })?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_type, metadata)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        admin_id,
+        "payment_provider_updated",
+        "payment_provider",
+        serde_json::json!({
+            "provider": name,
+            "enabled": req.enabled,
+            "config_keys": changed_keys,
+            "currencies": currencies,
+            "countries": countries,
+        })
+    )
+    .execute(&state.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to log payment provider update: {}", e);
+        provider_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to record audit log")
+    })?;
+
+    state.payment_providers.reload().await.map_err(|e| {
+        tracing::error!("Failed to reload payment providers: {}", e);
+        provider_error(StatusCode::INTERNAL_SERVER_ERROR, "Saved, but failed to reload payment providers")
+    })?;
+
+    let loaded = state
+        .payment_providers
+        .summaries()
+        .iter()
+        .any(|summary| summary.name == name);
+    tracing::warn!("Payment provider {} updated by admin (loaded: {})", name, loaded);
+
+    Ok(Json(serde_json::json!({"provider": name, "loaded": loaded})))
+}
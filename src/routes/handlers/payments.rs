@@ -6,17 +6,28 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
+use validator::Validate;
 
-use crate::services::payment_service::PaymentService;
+use crate::routes::validation::{self, ValidatedJson};
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::project_schedule;
+use crate::services::webhook_deliveries::{self, NewDelivery};
 use crate::routes::payments::provider::*;
 use crate::state::AppState;
+use crate::utils::money::Cents;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct InitiatePaymentRequest {
-    pub provider: String,
-    pub amount: f64,
+    /// Provider to pay with; omitted or "auto" picks one for the currency
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[validate(custom(function = "validation::positive_cents"))]
+    pub amount: Cents,
+    #[validate(length(equal = 3, message = "Currency must be a 3-letter ISO code"))]
     pub currency: String,
+    #[validate(email(message = "Enter a valid email address"))]
     pub donor_email: String,
+    #[validate(custom(function = "validation::phone_number"))]
     pub donor_phone: Option<String>,
     pub project_id: Uuid,
     pub memo: Option<String>,
@@ -37,35 +48,36 @@ pub struct WebhookRequest {
     pub provider: String,
     pub event_type: String,
     pub payment_id: String,
-    pub amount: f64,
+    pub amount: Cents,
     pub currency: String,
     pub status: String,
     pub raw_data: serde_json::Value,
     pub signature: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct RefundRequest {
-    pub provider: String,
-    pub payment_id: String,
-    pub amount: Option<f64>,
-    pub reason: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct RefundResponse {
-    pub success: bool,
-    pub refund_id: String,
-    pub message: String,
-}
-
 /// Initiate payment with specified provider
 pub async fn initiate_payment(
     State(state): State<AppState>,
-    Json(request): Json<InitiatePaymentRequest>,
+    ValidatedJson(request): ValidatedJson<InitiatePaymentRequest>,
 ) -> Result<Json<PaymentInstructionResponse>, StatusCode> {
-    let mut payment_service = PaymentService::new(state.pool.clone());
-    payment_service.initialize_providers().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Cancelled, completed, unpublished and closed projects don't take donations
+    let accepting = project_schedule::accepting_donations(&state.pool, request.project_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match accepting {
+        None => return Err(StatusCode::NOT_FOUND),
+        Some(false) => return Err(StatusCode::CONFLICT),
+        Some(true) => {}
+    }
+
+    let payment_service = state.payment_providers.service();
+
+    let provider = match request.provider.as_deref() {
+        Some(provider) if provider != "auto" => provider.to_string(),
+        _ => payment_service
+            .select_provider(&request.currency, request.donor_phone.as_deref())
+            .ok_or(StatusCode::SERVICE_UNAVAILABLE)?,
+    };
 
     let payment_request = crate::routes::payments::provider::InitiatePaymentRequest {
         amount: request.amount,
@@ -76,7 +88,7 @@ pub async fn initiate_payment(
         memo: request.memo,
     };
 
-    match payment_service.initiate_payment(&request.provider, payment_request).await {
+    match payment_service.initiate_payment(&provider, payment_request).await {
         Ok(instruction) => Ok(Json(PaymentInstructionResponse {
             success: true,
             payment_id: instruction.payment_id,
@@ -95,37 +107,216 @@ pub async fn initiate_payment(
 /// M-Pesa webhook handler
 pub async fn mpesa_webhook(
     State(state): State<AppState>,
-    Json(webhook_data): Json<serde_json::Value>,
+    body: String,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    handle_provider_callback(&state, "mpesa", &body, None, chrono::Utc::now(), None)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("M-Pesa webhook error: {}", e);
+            StatusCode::BAD_REQUEST
+        })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackTokenQuery {
+    pub token: Option<String>,
+}
+
+/// Refuse B2C and reversal callbacks that don't carry the configured token
+fn check_b2c_callback_token(state: &AppState, query: &CallbackTokenQuery) -> Result<(), StatusCode> {
+    let authorized = state
+        .payment_providers
+        .mpesa_config()
+        .and_then(|config| config.b2c)
+        .is_some_and(|b2c| b2c.accepts_callback_token(query.token.as_deref()));
+    if !authorized {
+        eprintln!("Refused M-Pesa callback without a valid token");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(())
+}
+
+/// M-Pesa B2C result callback for milestone payouts
+pub async fn mpesa_b2c_result(
+    State(state): State<AppState>,
+    Query(query): Query<CallbackTokenQuery>,
+    body: String,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    check_b2c_callback_token(&state, &query)?;
+    handle_b2c_callback(&state, "result", &body, None)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("M-Pesa B2C result error: {}", e);
+            StatusCode::BAD_REQUEST
+        })
+}
+
+/// M-Pesa B2C queue timeout callback; the payout is treated as failed
+pub async fn mpesa_b2c_timeout(
+    State(state): State<AppState>,
+    Query(query): Query<CallbackTokenQuery>,
+    body: String,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut payment_service = PaymentService::new(state.pool.clone());
-    payment_service.initialize_providers().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let webhook = ProviderWebhook {
-        provider: "mpesa".to_string(),
-        event_type: "payment_completed".to_string(),
-        payment_id: webhook_data["Body"]["stkCallback"]["CheckoutRequestID"]
-            .as_str()
-            .unwrap_or("")
-            .to_string(),
-        amount: 0.0, // Will be extracted from callback
-        currency: "KES".to_string(),
-        status: "pending".to_string(),
-        raw_data: webhook_data,
-        signature: None,
+    check_b2c_callback_token(&state, &query)?;
+    handle_b2c_callback(&state, "timeout", &body, None)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("M-Pesa B2C timeout error: {}", e);
+            StatusCode::BAD_REQUEST
+        })
+}
+
+/// Settle a mobile payout from a B2C `result` or `timeout` callback and
+/// record the delivery so it can be replayed
+pub(crate) async fn handle_b2c_callback(
+    state: &AppState,
+    event_type: &str,
+    body: &str,
+    replay_of: Option<Uuid>,
+) -> Result<serde_json::Value, String> {
+    let result: Result<serde_json::Value, String> = async {
+        let value: serde_json::Value = serde_json::from_str(body)
+            .map_err(|e| format!("Invalid callback body: {}", e))?;
+        let mut b2c = crate::routes::payments::mpesa::B2cResult::from_callback(&value)?;
+        if event_type == "timeout" {
+            b2c.successful = false;
+        }
+
+        let payout = crate::services::mobile_payouts::apply_result(&state.pool, &b2c)
+            .await
+            .map_err(|e| e.to_string())?;
+        // Unknown or already settled payouts are acknowledged so M-Pesa stops retrying
+        Ok(serde_json::json!({
+            "ResultCode": 0,
+            "ResultDesc": "Accepted",
+            "payout_id": payout.as_ref().map(|p| p.id),
+            "status": payout.as_ref().map(|p| p.status.clone())
+        }))
+    }
+    .await;
+
+    let (response_status, response_body, error) = match &result {
+        Ok(response) => (200, Some(response.to_string()), None),
+        Err(e) => (400, None, Some(e.clone())),
     };
+    webhook_deliveries::record(&state.pool, NewDelivery {
+        direction: "inbound",
+        provider: "mpesa_b2c",
+        event_type: Some(event_type),
+        target_url: None,
+        request_headers: None,
+        request_body: body,
+        response_status: Some(response_status),
+        response_body,
+        error,
+        replay_of,
+    })
+    .await;
 
-    match payment_service.process_webhook("mpesa", webhook).await {
-        Ok(verification) => Ok(Json(serde_json::json!({
-            "success": true,
-            "payment_id": verification.payment_id,
-            "status": format!("{:?}", verification.status),
-            "amount": verification.amount
-        }))),
-        Err(e) => {
-            eprintln!("M-Pesa webhook error: {}", e);
-            Err(StatusCode::BAD_REQUEST)
+    result
+}
+
+/// M-Pesa reversal result callback for donation refunds
+pub async fn mpesa_reversal_result(
+    State(state): State<AppState>,
+    Query(query): Query<CallbackTokenQuery>,
+    body: String,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    check_b2c_callback_token(&state, &query)?;
+    handle_reversal_callback(&state, "result", &body, None)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("M-Pesa reversal result error: {}", e);
+            StatusCode::BAD_REQUEST
+        })
+}
+
+/// M-Pesa reversal queue timeout callback; the refund is treated as failed
+pub async fn mpesa_reversal_timeout(
+    State(state): State<AppState>,
+    Query(query): Query<CallbackTokenQuery>,
+    body: String,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    check_b2c_callback_token(&state, &query)?;
+    handle_reversal_callback(&state, "timeout", &body, None)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("M-Pesa reversal timeout error: {}", e);
+            StatusCode::BAD_REQUEST
+        })
+}
+
+/// Settle a refund from a reversal `result` or `timeout` callback and record
+/// the delivery so it can be replayed
+pub(crate) async fn handle_reversal_callback(
+    state: &AppState,
+    event_type: &str,
+    body: &str,
+    replay_of: Option<Uuid>,
+) -> Result<serde_json::Value, String> {
+    let result: Result<serde_json::Value, String> = async {
+        let value: serde_json::Value = serde_json::from_str(body)
+            .map_err(|e| format!("Invalid callback body: {}", e))?;
+        let mut reversal = crate::routes::payments::mpesa::B2cResult::from_callback(&value)?;
+        if event_type == "timeout" {
+            reversal.successful = false;
+        }
+
+        let refund = crate::services::refunds::apply_reversal_result(&state.pool, &reversal)
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(refund) = &refund {
+            let event = NotificationEvent::RefundStatus { refund_id: refund.id, status: refund.status.clone() };
+            state.notifier.send(Channel::Admins, event);
         }
+        // Unknown or already settled refunds are acknowledged so M-Pesa stops retrying
+        Ok(serde_json::json!({
+            "ResultCode": 0,
+            "ResultDesc": "Accepted",
+            "refund_id": refund.as_ref().map(|r| r.id),
+            "status": refund.as_ref().map(|r| r.status.clone())
+        }))
     }
+    .await;
+
+    let (response_status, response_body, error) = match &result {
+        Ok(response) => (200, Some(response.to_string()), None),
+        Err(e) => (400, None, Some(e.clone())),
+    };
+    webhook_deliveries::record(&state.pool, NewDelivery {
+        direction: "inbound",
+        provider: "mpesa_reversal",
+        event_type: Some(event_type),
+        target_url: None,
+        request_headers: None,
+        request_body: body,
+        response_status: Some(response_status),
+        response_body,
+        error,
+        replay_of,
+    })
+    .await;
+
+    result
+}
+
+/// Airtel Money callback handler
+pub async fn airtel_callback(
+    State(state): State<AppState>,
+    body: String,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    handle_provider_callback(&state, "airtel", &body, None, chrono::Utc::now(), None)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("Airtel Money callback error: {}", e);
+            StatusCode::BAD_REQUEST
+        })
 }
 
 /// Stripe webhook handler
@@ -134,96 +325,302 @@ pub async fn stripe_webhook(
     headers: axum::http::HeaderMap,
     body: String,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut payment_service = PaymentService::new(state.pool.clone());
-    payment_service.initialize_providers().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
     let signature = headers
         .get("stripe-signature")
         .and_then(|h| h.to_str().ok())
-        .unwrap_or("");
-
-    let webhook_data: serde_json::Value = serde_json::from_str(&body)
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
-
-    let webhook = ProviderWebhook {
-        provider: "stripe".to_string(),
-        event_type: webhook_data["type"].as_str().unwrap_or("").to_string(),
-        payment_id: webhook_data["data"]["object"]["id"]
-            .as_str()
-            .unwrap_or("")
-            .to_string(),
-        amount: webhook_data["data"]["object"]["amount"]
-            .as_f64()
-            .unwrap_or(0.0) / 100.0,
-        currency: webhook_data["data"]["object"]["currency"]
-            .as_str()
-            .unwrap_or("")
-            .to_string(),
-        status: webhook_data["data"]["object"]["status"]
-            .as_str()
-            .unwrap_or("")
-            .to_string(),
-        raw_data: webhook_data,
-        signature: Some(signature.to_string()),
-    };
+        .map(|s| s.to_string());
 
-    match payment_service.process_webhook("stripe", webhook).await {
-        Ok(verification) => Ok(Json(serde_json::json!({
-            "success": true,
-            "payment_id": verification.payment_id,
-            "status": format!("{:?}", verification.status),
-            "amount": verification.amount
-        }))),
-        Err(e) => {
+    handle_provider_callback(&state, "stripe", &body, signature, chrono::Utc::now(), None)
+        .await
+        .map(Json)
+        .map_err(|e| {
             eprintln!("Stripe webhook error: {}", e);
-            Err(StatusCode::BAD_REQUEST)
-        }
-    }
+            StatusCode::BAD_REQUEST
+        })
 }
 
-/// Process refund
-pub async fn process_refund(
+/// Flutterwave webhook handler
+pub async fn flutterwave_webhook(
     State(state): State<AppState>,
-    Json(request): Json<RefundRequest>,
-) -> Result<Json<RefundResponse>, StatusCode> {
-    let mut payment_service = PaymentService::new(state.pool.clone());
-    payment_service.initialize_providers().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    headers: axum::http::HeaderMap,
+    body: String,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let signature = headers
+        .get("verif-hash")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string());
 
-    let refund_request = crate::routes::payments::provider::RefundRequest {
-        payment_id: request.payment_id,
-        amount: request.amount,
-        reason: request.reason,
+    handle_provider_callback(&state, "flutterwave", &body, signature, chrono::Utc::now(), None)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("Flutterwave webhook error: {}", e);
+            StatusCode::BAD_REQUEST
+        })
+}
+
+/// PayPal webhook handler
+pub async fn paypal_webhook(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    body: String,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    // The transmission headers travel as one JSON signature so replays keep them
+    let signature = crate::routes::payments::paypal::PaypalTransmission::from_headers(&headers)
+        .and_then(|transmission| serde_json::to_string(&transmission).ok());
+
+    handle_provider_callback(&state, "paypal", &body, signature, chrono::Utc::now(), None)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("PayPal webhook error: {}", e);
+            StatusCode::BAD_REQUEST
+        })
+}
+
+/// Process a provider callback body and record the delivery so it can be
+/// inspected and replayed later. `received_at` is when the body first
+/// arrived; signature timestamps are checked against it, so a replay of a
+/// recorded delivery verifies the same way the original did.
+pub(crate) async fn handle_provider_callback(
+    state: &AppState,
+    provider: &str,
+    body: &str,
+    signature: Option<String>,
+    received_at: chrono::DateTime<chrono::Utc>,
+    replay_of: Option<Uuid>,
+) -> Result<serde_json::Value, String> {
+    let result = process_provider_callback(state, provider, body, signature.clone(), received_at).await;
+
+    let (response_status, response_body, error) = match &result {
+        Ok(response) => (200, Some(response.to_string()), None),
+        Err(e) => (400, None, Some(e.clone())),
     };
+    let event_type = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| {
+            v["type"]
+                .as_str()
+                .or(v["event"].as_str())
+                .or(v["event_type"].as_str())
+                .map(|t| t.to_string())
+        });
 
-    match payment_service.process_refund(&request.provider, refund_request).await {
-        Ok(refund_id) => Ok(Json(RefundResponse {
-            success: true,
-            refund_id,
-            message: "Refund processed successfully".to_string(),
-        })),
-        Err(e) => {
-            eprintln!("Refund error: {}", e);
-            Ok(Json(RefundResponse {
-                success: false,
-                refund_id: "".to_string(),
-                message: e,
-            }))
+    webhook_deliveries::record(&state.pool, NewDelivery {
+        direction: "inbound",
+        provider,
+        event_type: event_type.as_deref(),
+        target_url: None,
+        request_headers: signature.map(|s| serde_json::json!({"signature": s})),
+        request_body: body,
+        response_status: Some(response_status),
+        response_body,
+        error,
+        replay_of,
+    })
+    .await;
+
+    result
+}
+
+async fn process_provider_callback(
+    state: &AppState,
+    provider: &str,
+    body: &str,
+    signature: Option<String>,
+    received_at: chrono::DateTime<chrono::Utc>,
+) -> Result<serde_json::Value, String> {
+    let payment_service = state.payment_providers.service();
+
+    let webhook_data: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| format!("Invalid webhook body: {}", e))?;
+
+    let webhook = match provider {
+        "mpesa" => ProviderWebhook {
+            provider: "mpesa".to_string(),
+            event_id: None,
+            event_type: "payment_completed".to_string(),
+            payment_id: webhook_data["Body"]["stkCallback"]["CheckoutRequestID"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            amount: Cents::ZERO, // Will be extracted from callback
+            currency: "KES".to_string(),
+            status: "pending".to_string(),
+            raw_data: webhook_data,
+            signature: None,
+        },
+        "stripe" => ProviderWebhook {
+            provider: "stripe".to_string(),
+            event_id: webhook_data["id"].as_str().map(|id| id.to_string()),
+            event_type: webhook_data["type"].as_str().unwrap_or("").to_string(),
+            payment_id: webhook_data["data"]["object"]["id"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            // Checkout sessions report amount_total rather than amount
+            amount: Cents::from_cents(
+                webhook_data["data"]["object"]["amount"]
+                    .as_i64()
+                    .or_else(|| webhook_data["data"]["object"]["amount_total"].as_i64())
+                    .unwrap_or(0),
+            ),
+            currency: webhook_data["data"]["object"]["currency"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            status: webhook_data["data"]["object"]["status"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            raw_data: webhook_data,
+            signature: Some(signature.unwrap_or_default()),
+        },
+        "flutterwave" => ProviderWebhook {
+            provider: "flutterwave".to_string(),
+            event_id: webhook_data["data"]["id"].as_i64().map(|id| id.to_string()),
+            event_type: webhook_data["event"].as_str().unwrap_or("").to_string(),
+            payment_id: webhook_data["data"]["tx_ref"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            // Major units; the verified amount comes from the provider lookup
+            amount: Cents::from_f64(webhook_data["data"]["amount"].as_f64().unwrap_or(0.0))
+                .unwrap_or(Cents::ZERO),
+            currency: webhook_data["data"]["currency"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            status: webhook_data["data"]["status"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            raw_data: webhook_data,
+            signature: Some(signature.unwrap_or_default()),
+        },
+        "paypal" => ProviderWebhook {
+            provider: "paypal".to_string(),
+            event_id: webhook_data["id"].as_str().map(|id| id.to_string()),
+            event_type: webhook_data["event_type"].as_str().unwrap_or("").to_string(),
+            payment_id: webhook_data["resource"]["id"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            amount: webhook_data["resource"]["amount"]["value"]
+                .as_str()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(Cents::ZERO),
+            currency: webhook_data["resource"]["amount"]["currency_code"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            status: webhook_data["resource"]["status"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+            raw_data: webhook_data,
+            signature: Some(signature.unwrap_or_default()),
+        },
+        "airtel" => {
+            let transaction = &webhook_data["transaction"];
+            ProviderWebhook {
+                provider: "airtel".to_string(),
+                // Airtel may call back more than once for a transaction as its status moves
+                event_id: transaction["id"]
+                    .as_str()
+                    .zip(transaction["status_code"].as_str())
+                    .map(|(id, status)| format!("{}:{}", id, status)),
+                event_type: "payment_callback".to_string(),
+                payment_id: transaction["id"].as_str().unwrap_or("").to_string(),
+                amount: Cents::ZERO, // Not included in the callback
+                currency: String::new(),
+                status: transaction["status_code"].as_str().unwrap_or("").to_string(),
+                raw_data: webhook_data,
+                signature: None,
+            }
         }
-    }
+        other => return Err(format!("Unsupported provider '{}'", other)),
+    };
+
+    let event_id = webhook.event_id.clone();
+    let Some(verification) = payment_service.process_webhook(provider, webhook, body, received_at).await? else {
+        return Ok(serde_json::json!({
+            "success": true,
+            "duplicate": true,
+            "event_id": event_id
+        }));
+    };
+
+    Ok(serde_json::json!({
+        "success": true,
+        "payment_id": verification.payment_id,
+        "status": format!("{:?}", verification.status),
+        "amount": verification.amount
+    }))
 }
 
 /// Get available payment providers
 pub async fn get_providers(
     State(state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut payment_service = PaymentService::new(state.pool.clone());
-    payment_service.initialize_providers().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let details = state.payment_providers.summaries();
+    let providers: Vec<&str> = details.iter().map(|p| p.name.as_str()).collect();
 
-    let providers = payment_service.get_available_providers();
-    
     Ok(Json(serde_json::json!({
         "success": true,
-        "providers": providers
+        "providers": providers,
+        "details": details
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuoteQuery {
+    pub amount: f64,
+    pub from: String,
+    pub to: String,
+}
+
+/// Convert an amount at the current cached rate, so donors can see what a
+/// payment is worth before starting it
+pub async fn get_quote(
+    State(state): State<AppState>,
+    Query(query): Query<QuoteQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": message})));
+    if !(query.amount.is_finite() && query.amount > 0.0) {
+        return Err(bad_request("amount must be a positive number".to_string()));
+    }
+    for currency in [&query.from, &query.to] {
+        if !crate::services::rates::is_supported(&currency.to_uppercase()) {
+            return Err(bad_request(format!("Unsupported currency {}", currency)));
+        }
+    }
+
+    let quote = state.rates.quote(&query.from, &query.to).await.map_err(|e| {
+        eprintln!("Exchange rate lookup error: {}", e);
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({"error": "Exchange rates are unavailable"})),
+        )
+    })?;
+
+    // On-chain assets carry 7 decimals, fiat 2
+    let converted = query.amount * quote.rate;
+    let converted = match quote.to.as_str() {
+        "XLM" | "USDC" => crate::utils::money::Stroops::from_f64(converted).map(|a| a.to_string()),
+        _ => Cents::from_f64(converted).map(|a| a.to_string()),
+    }
+    .map_err(|e| bad_request(e.to_string()))?;
+
+    Ok(Json(serde_json::json!({
+        "amount": query.amount,
+        "from": quote.from,
+        "to": quote.to,
+        "rate": quote.rate,
+        "converted_amount": converted,
+        "source": quote.source,
+        "fetched_at": quote.fetched_at,
+        "expires_at": quote.expires_at
     })))
 }
 
@@ -235,9 +632,6 @@ pub async fn get_payment_status(
     let provider = params.get("provider").ok_or(StatusCode::BAD_REQUEST)?;
     let payment_id = params.get("payment_id").ok_or(StatusCode::BAD_REQUEST)?;
 
-    let mut payment_service = PaymentService::new(state.pool.clone());
-    payment_service.initialize_providers().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
     // Get status from database first
     let donation = sqlx::query!(
         "SELECT status, provider_status, amount FROM donations WHERE tx_hash = $1",
@@ -247,13 +641,52 @@ pub async fn get_payment_status(
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // Where a confirmed payment's XLM went, once the reconciler has settled it
+    let settlement = sqlx::query!(
+        r#"
+        SELECT status, xlm_amount, exchange_rate, destination, tx_hash, settled_at
+        FROM fiat_settlements
+        WHERE provider = $1 AND payment_id = $2
+        "#,
+        provider,
+        payment_id
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map(|s| serde_json::json!({
+        "status": s.status,
+        "xlm_amount": s.xlm_amount,
+        "exchange_rate": s.exchange_rate,
+        "destination": s.destination,
+        "tx_hash": s.tx_hash,
+        "settled_at": s.settled_at
+    }));
+
     if let Some(donation) = donation {
         Ok(Json(serde_json::json!({
             "success": true,
             "payment_id": payment_id,
             "status": donation.status,
             "provider_status": donation.provider_status,
-            "amount": donation.amount
+            "amount": donation.amount,
+            "settlement": settlement
+        })))
+    } else if let Some(instruction) = sqlx::query!(
+        "SELECT status, amount, currency FROM payment_instructions WHERE payment_id = $1",
+        payment_id
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        Ok(Json(serde_json::json!({
+            "success": true,
+            "payment_id": payment_id,
+            "status": instruction.status,
+            "amount": instruction.amount,
+            "currency": instruction.currency,
+            "settlement": settlement
         })))
     } else {
         Err(StatusCode::NOT_FOUND)
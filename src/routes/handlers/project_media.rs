@@ -0,0 +1,289 @@
+use axum::{
+    extract::{Multipart, Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::handlers::projects::{project_closed, require_team_member};
+use crate::routes::validation::ValidatedJson;
+use crate::services::project_media::{self, MediaError, NewUpload, PendingUpload, ProjectMedia, KIND_IMAGE, KIND_VIDEO};
+use crate::services::storage::{is_sha256_hex, ObjectStorage};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PresignUploadRequest {
+    #[validate(length(min = 1, max = 255, message = "Filename must be 1 to 255 characters"))]
+    pub filename: String,
+    pub content_type: String,
+    #[validate(range(min = 1, message = "Size must be positive"))]
+    pub size_bytes: i64,
+    /// Hex SHA-256 of the file, checked once it's uploaded
+    pub checksum: Option<String>,
+    #[validate(length(max = 500, message = "Caption must be at most 500 characters"))]
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddMediaLinkRequest {
+    /// `video` (the default) or `image`
+    pub kind: Option<String>,
+    #[validate(url(message = "Enter a valid URL"), length(max = 2000, message = "URL must be at most 2000 characters"))]
+    pub url: String,
+    #[validate(length(max = 500, message = "Caption must be at most 500 characters"))]
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateMediaRequest {
+    #[validate(length(max = 500, message = "Caption must be at most 500 characters"))]
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReorderMediaRequest {
+    /// Every item of the gallery, in its new order
+    #[validate(length(max = 20, message = "A gallery holds at most 20 items"))]
+    pub order: Vec<Uuid>,
+}
+
+fn map_media_error(e: MediaError) -> AppError {
+    match e {
+        MediaError::GalleryFull | MediaError::NotUploaded => AppError::conflict(e.to_string()),
+        MediaError::ChecksumMismatch => AppError::invalid("checksum", e.to_string()),
+        MediaError::UnsupportedType(_) => AppError::invalid("content_type", e.to_string()),
+        MediaError::TooLarge => AppError::invalid("size_bytes", e.to_string()),
+        MediaError::InvalidOrder => AppError::invalid("order", e.to_string()),
+        MediaError::Internal(e) => AppError::Internal(e),
+    }
+}
+
+fn storage(state: &AppState) -> AppResult<&ObjectStorage> {
+    state
+        .storage
+        .as_ref()
+        .ok_or_else(|| AppError::Unavailable("File uploads are not configured".to_string()))
+}
+
+fn caption(caption: Option<String>) -> Option<String> {
+    caption.map(|c| c.trim().to_string()).filter(|c| !c.is_empty())
+}
+
+/// The caller, if they may change the gallery: a team member or admin, on a
+/// project that's still open
+async fn editor(state: &AppState, headers: &HeaderMap, project_id: Uuid) -> AppResult<Uuid> {
+    let status = sqlx::query_scalar!("SELECT status FROM projects WHERE id = $1", project_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project not found"))?;
+    let user_id = require_team_member(state, headers, project_id, false).await?;
+    if matches!(status.as_str(), "completed" | "rejected" | "cancelled") {
+        return Err(project_closed(&status));
+    }
+    Ok(user_id)
+}
+
+async fn log_activity(state: &AppState, user_id: Uuid, action: &str, project_id: Uuid, media_id: Uuid) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        action,
+        project_id,
+        "project",
+        serde_json::json!({"media_id": media_id})
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+/// A project's gallery in order; its team and admins also see uploads that
+/// haven't finished
+pub async fn list_media(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Json<Vec<ProjectMedia>>> {
+    let exists = sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1) as "exists!""#, project_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if !exists {
+        return Err(AppError::not_found("Project not found"));
+    }
+    let include_pending = require_team_member(&state, &headers, project_id, false).await.is_ok();
+    Ok(Json(project_media::list(&state.pool, project_id, include_pending).await?))
+}
+
+/// Upload an image through the API as multipart form data: a `file` part and
+/// an optional `caption`
+pub async fn upload_media(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> AppResult<(StatusCode, Json<ProjectMedia>)> {
+    let user_id = editor(&state, &headers, project_id).await?;
+    let storage = storage(&state)?;
+
+    let mut file: Option<(String, String, Vec<u8>)> = None;
+    let mut caption_text: Option<String> = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::bad_request(format!("Invalid upload: {}", e)))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        match name.as_str() {
+            "file" => {
+                let filename = field.file_name().unwrap_or("upload").to_string();
+                let content_type = field.content_type().unwrap_or_default().to_string();
+                let bytes = field
+                    .bytes()
+                    .await
+                    .map_err(|e| AppError::bad_request(format!("Invalid upload: {}", e)))?;
+                file = Some((filename, content_type, bytes.to_vec()));
+            }
+            "caption" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::bad_request(format!("Invalid upload: {}", e)))?;
+                if text.chars().count() > 500 {
+                    return Err(AppError::invalid("caption", "Caption must be at most 500 characters"));
+                }
+                caption_text = Some(text);
+            }
+            _ => {}
+        }
+    }
+    let (filename, content_type, body) = file.ok_or_else(|| AppError::invalid("file", "Attach the image as `file`"))?;
+
+    let upload = NewUpload {
+        filename,
+        content_type,
+        size_bytes: body.len() as i64,
+        checksum: None,
+        caption: caption(caption_text),
+    };
+    let media = project_media::store_image(&state.pool, storage, project_id, upload, body, user_id)
+        .await
+        .map_err(map_media_error)?;
+    log_activity(&state, user_id, "project_media_added", project_id, media.id).await;
+    Ok((StatusCode::CREATED, Json(media)))
+}
+
+/// Start an upload straight to storage: adds a pending image and returns a
+/// presigned URL to PUT the file to, then confirm with `/complete`
+pub async fn presign_upload(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<PresignUploadRequest>,
+) -> AppResult<(StatusCode, Json<PendingUpload>)> {
+    let user_id = editor(&state, &headers, project_id).await?;
+    let storage = storage(&state)?;
+    let checksum = req.checksum.map(|c| c.trim().to_lowercase());
+    if checksum.as_deref().is_some_and(|c| !is_sha256_hex(c)) {
+        return Err(AppError::invalid("checksum", "Checksum must be a hex SHA-256"));
+    }
+
+    let upload = NewUpload {
+        filename: req.filename,
+        content_type: req.content_type.trim().to_lowercase(),
+        size_bytes: req.size_bytes,
+        checksum,
+        caption: caption(req.caption),
+    };
+    let pending = project_media::begin_upload(&state.pool, storage, project_id, upload, user_id)
+        .await
+        .map_err(map_media_error)?;
+    Ok((StatusCode::CREATED, Json(pending)))
+}
+
+/// Confirm a presigned upload once the file is in storage; one that doesn't
+/// match its checksum is deleted
+pub async fn complete_upload(
+    State(state): State<AppState>,
+    Path((project_id, media_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> AppResult<Json<ProjectMedia>> {
+    let user_id = editor(&state, &headers, project_id).await?;
+    let storage = storage(&state)?;
+
+    let media = project_media::complete_upload(&state.pool, storage, project_id, media_id)
+        .await
+        .map_err(map_media_error)?
+        .ok_or_else(|| AppError::not_found("No pending upload to complete"))?;
+    log_activity(&state, user_id, "project_media_added", project_id, media.id).await;
+    Ok(Json(media))
+}
+
+/// Add a video, or an image hosted elsewhere, by link
+pub async fn add_media_link(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<AddMediaLinkRequest>,
+) -> AppResult<(StatusCode, Json<ProjectMedia>)> {
+    let user_id = editor(&state, &headers, project_id).await?;
+    let kind = match req.kind.as_deref().map(str::trim) {
+        None | Some(KIND_VIDEO) => KIND_VIDEO,
+        Some(KIND_IMAGE) => KIND_IMAGE,
+        Some(_) => return Err(AppError::invalid("kind", "Kind must be video or image")),
+    };
+
+    let caption = caption(req.caption);
+    let media = project_media::add_link(&state.pool, project_id, kind, req.url.trim(), caption.as_deref(), user_id)
+        .await
+        .map_err(map_media_error)?;
+    log_activity(&state, user_id, "project_media_added", project_id, media.id).await;
+    Ok((StatusCode::CREATED, Json(media)))
+}
+
+/// Change an item's caption; leave it out to clear it
+pub async fn update_media(
+    State(state): State<AppState>,
+    Path((project_id, media_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<UpdateMediaRequest>,
+) -> AppResult<Json<ProjectMedia>> {
+    editor(&state, &headers, project_id).await?;
+    let caption = caption(req.caption);
+    let media = project_media::set_caption(&state.pool, project_id, media_id, caption.as_deref())
+        .await?
+        .ok_or_else(|| AppError::not_found("Media not found"))?;
+    Ok(Json(media))
+}
+
+/// Put the gallery in a new order; the first image becomes the cover
+pub async fn reorder_media(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<ReorderMediaRequest>,
+) -> AppResult<Json<Vec<ProjectMedia>>> {
+    editor(&state, &headers, project_id).await?;
+    let media = project_media::reorder(&state.pool, project_id, &req.order)
+        .await
+        .map_err(map_media_error)?;
+    Ok(Json(media))
+}
+
+/// Remove an item from the gallery, deleting any stored file
+pub async fn delete_media(
+    State(state): State<AppState>,
+    Path((project_id, media_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> AppResult<StatusCode> {
+    let user_id = editor(&state, &headers, project_id).await?;
+    if !project_media::remove(&state.pool, state.storage.as_ref(), project_id, media_id).await? {
+        return Err(AppError::not_found("Media not found"));
+    }
+    log_activity(&state, user_id, "project_media_removed", project_id, media_id).await;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,189 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::ValidatedJson;
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::project_members::{self, MemberError, Membership, ProjectMember};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct InviteMemberRequest {
+    pub student_id: Uuid,
+    /// Share of each milestone payout, in basis points
+    #[validate(range(max = 10000, message = "Share must be at most 10000 bps"))]
+    pub share_bps: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetShareRequest {
+    #[validate(range(max = 10000, message = "Share must be at most 10000 bps"))]
+    pub share_bps: u32,
+}
+
+/// The signed-in caller, whether they're an admin and where they stand on
+/// the project
+struct Caller {
+    user_id: Uuid,
+    is_admin: bool,
+    membership: Option<Membership>,
+}
+
+impl Caller {
+    fn is_owner(&self) -> bool {
+        self.membership.as_ref().is_some_and(|m| m.is_owner())
+    }
+
+    fn is_member(&self) -> bool {
+        self.membership.as_ref().is_some_and(|m| m.is_active())
+    }
+}
+
+async fn caller(state: &AppState, headers: &HeaderMap, project_id: Uuid) -> AppResult<Caller> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let exists = sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1) as "exists!""#, project_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if !exists {
+        return Err(AppError::not_found("Project not found"));
+    }
+
+    let is_admin = crate::utils::roles::caller_is_admin(&state.pool, headers).await;
+    let membership = project_members::membership(&state.pool, project_id, user_id).await?;
+    Ok(Caller { user_id, is_admin, membership })
+}
+
+fn map_member_error(e: MemberError) -> AppError {
+    match e {
+        MemberError::StudentNotFound => AppError::not_found(e.to_string()),
+        MemberError::NotVerified => AppError::invalid("student_id", e.to_string()),
+        MemberError::AlreadyMember => AppError::conflict(e.to_string()),
+        MemberError::Owner => AppError::forbidden(e.to_string()),
+        MemberError::SharesExceeded(_) => AppError::invalid("share_bps", e.to_string()),
+        MemberError::Internal(e) => AppError::Internal(e),
+    }
+}
+
+async fn log_activity(state: &AppState, user_id: Uuid, action: &str, project_id: Uuid, student_id: Uuid) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        action,
+        project_id,
+        "project",
+        serde_json::json!({"student_id": student_id})
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+/// A project's team. Its members and admins also see open invitations.
+pub async fn list_members(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Json<Vec<ProjectMember>>> {
+    let include_invited = match caller(&state, &headers, project_id).await {
+        Ok(caller) => caller.is_member() || caller.is_admin,
+        Err(AppError::Unauthorized(_)) => false,
+        Err(e) => return Err(e),
+    };
+    let members = project_members::list(&state.pool, project_id, include_invited).await?;
+    if members.is_empty() {
+        return Err(AppError::not_found("Project not found"));
+    }
+    Ok(Json(members))
+}
+
+/// Invite a student onto the team (owner or admin)
+pub async fn invite_member(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<InviteMemberRequest>,
+) -> AppResult<(StatusCode, Json<ProjectMember>)> {
+    let caller = caller(&state, &headers, project_id).await?;
+    if !caller.is_owner() && !caller.is_admin {
+        return Err(AppError::forbidden("Only the project's owner can invite members"));
+    }
+
+    let member = project_members::invite(&state.pool, project_id, req.student_id, req.share_bps.unwrap_or(0), caller.user_id)
+        .await
+        .map_err(map_member_error)?;
+
+    if let Err(e) = project_members::notify_invited(&state.pool, project_id, req.student_id).await {
+        tracing::warn!("Failed to notify student {} of their invitation: {}", req.student_id, e);
+    }
+    state.notifier.send(Channel::User(member.user_id), NotificationEvent::ProjectInvite { project_id, user_id: Some(member.user_id) });
+    log_activity(&state, caller.user_id, "project_member_invited", project_id, req.student_id).await;
+    Ok((StatusCode::CREATED, Json(member)))
+}
+
+/// Accept the caller's invitation to the team
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> AppResult<Json<ProjectMember>> {
+    let caller = caller(&state, &headers, project_id).await?;
+    let Some(membership) = caller.membership.filter(|m| !m.is_active()) else {
+        return Err(AppError::not_found("No invitation to accept"));
+    };
+
+    let member = project_members::accept(&state.pool, project_id, membership.student_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("No invitation to accept"))?;
+    log_activity(&state, caller.user_id, "project_member_joined", project_id, membership.student_id).await;
+    Ok(Json(member))
+}
+
+/// Change a member's share of the milestone payouts (owner or admin)
+pub async fn set_member_share(
+    State(state): State<AppState>,
+    Path((project_id, student_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<SetShareRequest>,
+) -> AppResult<Json<ProjectMember>> {
+    let caller = caller(&state, &headers, project_id).await?;
+    if !caller.is_owner() && !caller.is_admin {
+        return Err(AppError::forbidden("Only the project's owner can change shares"));
+    }
+
+    let member = project_members::set_share(&state.pool, project_id, student_id, req.share_bps)
+        .await
+        .map_err(map_member_error)?
+        .ok_or_else(|| AppError::not_found("Member not found"))?;
+    log_activity(&state, caller.user_id, "project_member_share_changed", project_id, student_id).await;
+    Ok(Json(member))
+}
+
+/// Remove a member or withdraw an invitation (owner or admin); members can
+/// also leave, or decline an invitation, themselves
+pub async fn remove_member(
+    State(state): State<AppState>,
+    Path((project_id, student_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> AppResult<StatusCode> {
+    let caller = caller(&state, &headers, project_id).await?;
+    let is_self = caller.membership.as_ref().is_some_and(|m| m.student_id == student_id);
+    if !is_self && !caller.is_owner() && !caller.is_admin {
+        return Err(AppError::forbidden("Only the project's owner can remove members"));
+    }
+
+    if !project_members::remove(&state.pool, project_id, student_id).await.map_err(map_member_error)? {
+        return Err(AppError::not_found("Member not found"));
+    }
+    log_activity(&state, caller.user_id, "project_member_removed", project_id, student_id).await;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,106 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::handlers::projects::{map_revision_error, require_team_member};
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::project_revisions::{self, ProjectRevision};
+use crate::state::AppState;
+use crate::utils::pagination::{Page, PageQuery, PageRequest};
+
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct ReviewRevisionRequest {
+    #[validate(length(max = 2000, message = "Note must be at most 2000 characters"))]
+    pub note: Option<String>,
+}
+
+/// A project's edit history with each revision's diff (team or admin)
+pub async fn list_revisions(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+    Query(query): Query<PageQuery>,
+) -> AppResult<Json<Page<ProjectRevision>>> {
+    let exists = sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1) as "exists!""#, project_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if !exists {
+        return Err(AppError::not_found("Project not found"));
+    }
+    require_team_member(&state, &headers, project_id, false).await?;
+
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
+    Ok(Json(project_revisions::list(&state.pool, project_id, &page).await?))
+}
+
+/// Approve a pending revision and apply it. Callers hold projects.publish,
+/// checked by the route's middleware.
+pub async fn approve_revision(
+    State(state): State<AppState>,
+    Path((project_id, revision_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    req: Option<Json<ReviewRevisionRequest>>,
+) -> AppResult<Json<ProjectRevision>> {
+    review(&state, project_id, revision_id, &headers, req, true).await
+}
+
+/// Reject a pending revision; callers hold projects.publish
+pub async fn reject_revision(
+    State(state): State<AppState>,
+    Path((project_id, revision_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    req: Option<Json<ReviewRevisionRequest>>,
+) -> AppResult<Json<ProjectRevision>> {
+    review(&state, project_id, revision_id, &headers, req, false).await
+}
+
+async fn review(
+    state: &AppState,
+    project_id: Uuid,
+    revision_id: Uuid,
+    headers: &HeaderMap,
+    req: Option<Json<ReviewRevisionRequest>>,
+    approve: bool,
+) -> AppResult<Json<ProjectRevision>> {
+    let reviewer = crate::utils::jwt::extract_user_id_from_headers(headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+    let req = req.map(|Json(r)| r).unwrap_or_default();
+    req.validate()?;
+    let note = req.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+
+    let revision = match approve {
+        true => project_revisions::approve(&state.pool, project_id, revision_id, reviewer, note).await,
+        false => project_revisions::reject(&state.pool, project_id, revision_id, reviewer, note).await,
+    }
+    .map_err(map_revision_error)?;
+
+    if let Err(e) = project_revisions::notify_reviewed(&state.pool, &revision).await {
+        tracing::warn!("Failed to notify the owner of project {} of their reviewed revision: {}", project_id, e);
+    }
+    let event = NotificationEvent::ProjectRevision {
+        project_id,
+        revision_id: revision.id,
+        status: revision.status.clone(),
+    };
+    state.notifier.send(Channel::ProjectTeam(project_id), event);
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        reviewer,
+        format!("project_revision_{}", revision.status),
+        project_id,
+        "project",
+        serde_json::json!({"revision_id": revision.id, "note": revision.review_note})
+    )
+    .execute(&state.pool)
+    .await;
+    Ok(Json(revision))
+}
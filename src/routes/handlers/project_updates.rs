@@ -0,0 +1,254 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::{self, ValidatedJson};
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::project_updates::{self, ProjectUpdate, UpdateDraft, UpdateError};
+use crate::state::AppState;
+use crate::utils::pagination::{Page, PageQuery, PageRequest};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ProjectUpdateRequest {
+    #[validate(
+        custom(function = "validation::not_blank"),
+        length(max = 255, message = "Title must be at most 255 characters")
+    )]
+    pub title: String,
+    #[validate(
+        custom(function = "validation::not_blank"),
+        length(max = 20000, message = "Body must be at most 20000 characters")
+    )]
+    pub body: String,
+    #[validate(
+        custom(function = "validation::urls"),
+        length(max = 10, message = "At most 10 media URLs can be attached")
+    )]
+    pub media_urls: Option<Vec<String>>,
+    /// The milestone the update reports on
+    pub milestone_id: Option<Uuid>,
+    /// Keep a new update as a draft instead of publishing it; ignored when
+    /// replacing one
+    pub draft: Option<bool>,
+}
+
+impl ProjectUpdateRequest {
+    fn to_draft(&self) -> UpdateDraft {
+        UpdateDraft::new(&self.title, &self.body, self.media_urls.as_deref().unwrap_or_default(), self.milestone_id)
+    }
+}
+
+/// What the caller may do with a project's updates
+struct Access {
+    user_id: Uuid,
+    is_owner: bool,
+    is_admin: bool,
+}
+
+/// The caller's standing on the project; errors if the project doesn't
+/// exist or the caller isn't signed in
+async fn caller_access(state: &AppState, headers: &HeaderMap, project_id: Uuid) -> AppResult<Access> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let caller = sqlx::query!(
+        r#"
+        SELECT u.role,
+               EXISTS(SELECT 1 FROM projects WHERE id = $2) as "exists!",
+               EXISTS(
+                   SELECT 1 FROM projects p JOIN students s ON s.id = p.student_id
+                   WHERE p.id = $2 AND s.user_id = u.id
+               ) as "is_owner!"
+        FROM users u
+        WHERE u.id = $1
+        "#,
+        user_id,
+        project_id
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::unauthorized("Account no longer exists"))?;
+
+    if !caller.exists {
+        return Err(AppError::not_found("Project not found"));
+    }
+    Ok(Access { user_id, is_owner: caller.is_owner, is_admin: caller.role == "admin" })
+}
+
+/// Whether drafts should be shown: only to the owner and admins
+async fn sees_drafts(state: &AppState, headers: &HeaderMap, project_id: Uuid) -> AppResult<bool> {
+    if crate::utils::jwt::extract_user_id_from_headers(headers).is_err() {
+        return Ok(false);
+    }
+    match caller_access(state, headers, project_id).await {
+        Ok(access) => Ok(access.is_owner || access.is_admin),
+        Err(AppError::Unauthorized(_)) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn map_update_error(e: UpdateError) -> AppError {
+    match e {
+        UpdateError::MilestoneNotFound => AppError::invalid("milestone_id", e.to_string()),
+        UpdateError::Internal(e) => AppError::Internal(e),
+    }
+}
+
+async fn log_activity(state: &AppState, user_id: Uuid, action: &str, update: &ProjectUpdate) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        action,
+        update.id,
+        "project_update",
+        serde_json::json!({"project_id": update.project_id, "title": update.title})
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+/// Let donors and followers know about a freshly published update, in-app
+/// and over SSE
+async fn announce(state: &AppState, update: &ProjectUpdate) {
+    match project_updates::notify_published(&state.pool, update).await {
+        Ok(count) => tracing::debug!("Notified {} donors and followers of update {}", count, update.id),
+        Err(e) => tracing::warn!("Failed to notify the audience of update {}: {}", update.id, e),
+    }
+    let event = NotificationEvent::ProjectUpdate { project_id: update.project_id, update_id: update.id };
+    state.notifier.send(Channel::Project(update.project_id), event);
+}
+
+/// A project's updates, newest first. The owner and admins also see drafts.
+pub async fn list_updates(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+    Query(query): Query<PageQuery>,
+) -> AppResult<Json<Page<ProjectUpdate>>> {
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
+
+    let exists = sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1) as "exists!""#, project_id)
+        .fetch_one(&state.pool)
+        .await?;
+    if !exists {
+        return Err(AppError::not_found("Project not found"));
+    }
+
+    let include_drafts = sees_drafts(&state, &headers, project_id).await?;
+    Ok(Json(project_updates::list(&state.pool, project_id, include_drafts, &page).await?))
+}
+
+pub async fn get_update(
+    State(state): State<AppState>,
+    Path((project_id, update_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> AppResult<Json<ProjectUpdate>> {
+    let update = project_updates::find(&state.pool, project_id, update_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Update not found"))?;
+
+    if update.published_at.is_none() && !sees_drafts(&state, &headers, project_id).await? {
+        return Err(AppError::not_found("Update not found"));
+    }
+    Ok(Json(update))
+}
+
+/// Post an update on your own project. It's published, and donors and
+/// followers told, unless `draft` is set.
+pub async fn create_update(
+    State(state): State<AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<ProjectUpdateRequest>,
+) -> AppResult<(StatusCode, Json<ProjectUpdate>)> {
+    let access = caller_access(&state, &headers, project_id).await?;
+    if !access.is_owner {
+        return Err(AppError::forbidden("Only the project's owner can post updates"));
+    }
+
+    let publish = !req.draft.unwrap_or(false);
+    let update = project_updates::create(&state.pool, project_id, access.user_id, &req.to_draft(), publish)
+        .await
+        .map_err(map_update_error)?;
+
+    if publish {
+        log_activity(&state, access.user_id, "project_update_published", &update).await;
+        announce(&state, &update).await;
+    }
+    Ok((StatusCode::CREATED, Json(update)))
+}
+
+/// Rewrite an update (owner, or an admin moderating it)
+pub async fn replace_update(
+    State(state): State<AppState>,
+    Path((project_id, update_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<ProjectUpdateRequest>,
+) -> AppResult<Json<ProjectUpdate>> {
+    let access = caller_access(&state, &headers, project_id).await?;
+    if !access.is_owner && !access.is_admin {
+        return Err(AppError::forbidden("Only the project's owner can edit its updates"));
+    }
+
+    let update = project_updates::replace(&state.pool, project_id, update_id, &req.to_draft())
+        .await
+        .map_err(map_update_error)?
+        .ok_or_else(|| AppError::not_found("Update not found"))?;
+
+    if !access.is_owner {
+        log_activity(&state, access.user_id, "project_update_edited", &update).await;
+    }
+    Ok(Json(update))
+}
+
+/// Publish a draft and notify donors and followers
+pub async fn publish_update(
+    State(state): State<AppState>,
+    Path((project_id, update_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> AppResult<Json<ProjectUpdate>> {
+    let access = caller_access(&state, &headers, project_id).await?;
+    if !access.is_owner {
+        return Err(AppError::forbidden("Only the project's owner can publish updates"));
+    }
+
+    let Some(update) = project_updates::publish(&state.pool, project_id, update_id).await? else {
+        return match project_updates::find(&state.pool, project_id, update_id).await? {
+            Some(_) => Err(AppError::conflict("Update is already published")),
+            None => Err(AppError::not_found("Update not found")),
+        };
+    };
+
+    log_activity(&state, access.user_id, "project_update_published", &update).await;
+    announce(&state, &update).await;
+    Ok(Json(update))
+}
+
+/// Remove an update (owner, or an admin moderating it)
+pub async fn delete_update(
+    State(state): State<AppState>,
+    Path((project_id, update_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> AppResult<StatusCode> {
+    let access = caller_access(&state, &headers, project_id).await?;
+    if !access.is_owner && !access.is_admin {
+        return Err(AppError::forbidden("Only the project's owner can delete its updates"));
+    }
+
+    let update = project_updates::find(&state.pool, project_id, update_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Update not found"))?;
+    project_updates::delete(&state.pool, project_id, update_id).await?;
+
+    log_activity(&state, access.user_id, "project_update_deleted", &update).await;
+    Ok(StatusCode::NO_CONTENT)
+}
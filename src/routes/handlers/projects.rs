@@ -1,54 +1,151 @@
+use anyhow::Context;
 use axum::{extract::{Json, State, Path, Query}, http::StatusCode};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use sqlx::types::BigDecimal;
+use validator::Validate;
 use chrono::{DateTime, Utc};
 
-use crate::models::{Project, ProjectMilestone, PublicProjectInfo};
+use crate::config::EscrowMode;
+use crate::models::{Project, ProjectComparison, ProjectMilestone, PublicProjectInfo};
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::{self, ValidatedJson};
+use crate::services::analytics_events;
+use crate::services::categories::{self, Category, CategoryError};
+use crate::services::completion::{self, CompletionError, Settlement, SettlementPolicy};
+use crate::services::contract_client::{ContractClient, OnchainProjectStatus};
+use crate::services::email;
+use crate::services::escrow::EscrowService;
+use crate::services::project_media::{self, ProjectMedia, KIND_IMAGE, MAX_MEDIA_PER_PROJECT};
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::project_members;
+use crate::services::project_refunds::{self, ProjectCancellation, ProjectRefund, RefundProgress};
+use crate::services::project_revisions::{self, PlannedMilestone, ProjectRevision, RevisionError};
+use crate::services::project_schedule::{self, Countdown};
+use crate::services::project_updates::{self, ProjectUpdate};
+use crate::utils::money::Stroops;
+use crate::utils::pagination::{Page, PageQuery, PageRequest};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateProjectRequest {
     pub student_id: Uuid,
+    #[validate(
+        custom(function = "validation::not_blank"),
+        length(max = 255, message = "Title must be at most 255 characters")
+    )]
     pub title: String,
+    #[validate(length(max = 10000, message = "Description must be at most 10000 characters"))]
     pub description: String,
+    #[validate(url(message = "Enter a valid URL"), length(max = 255, message = "URL must be at most 255 characters"))]
     pub repo_url: Option<String>,
+    #[validate(custom(function = "validation::urls"))]
     pub media_urls: Option<Vec<String>>,
     pub tags: Vec<String>,
+    #[validate(custom(function = "validation::positive_decimal"))]
     pub funding_goal_xlm: String,
+    /// Hard limit on total deposits; the escrow trims or rejects anything past it
+    #[validate(custom(function = "validation::positive_stroops"))]
+    pub funding_cap_xlm: Option<Stroops>,
+    #[validate(nested)]
     pub milestones: Vec<CreateMilestoneRequest>,
+    /// When donations close; a project short of its goal by then is
+    /// cancelled and its donors refunded
+    pub funding_deadline: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateMilestoneRequest {
+    #[validate(custom(function = "validation::not_blank"))]
     pub title: String,
     pub description: Option<String>,
+    #[validate(custom(function = "validation::positive_xlm"))]
     pub amount_xlm: String,
     pub proof_type: String,
     pub order: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateProjectRequest {
+    #[validate(
+        custom(function = "validation::not_blank"),
+        length(max = 255, message = "Title must be at most 255 characters")
+    )]
     pub title: Option<String>,
+    #[validate(length(max = 10000, message = "Description must be at most 10000 characters"))]
     pub description: Option<String>,
+    #[validate(url(message = "Enter a valid URL"), length(max = 255, message = "URL must be at most 255 characters"))]
     pub repo_url: Option<String>,
+    #[validate(url(message = "Enter a valid URL"))]
     pub media_url: Option<String>,
     pub tags: Option<Vec<String>>,
+    #[validate(custom(function = "validation::positive_decimal"))]
     pub funding_goal_xlm: Option<String>,
+    /// Replaces the milestones that haven't started yet
+    #[validate(nested)]
+    pub milestones: Option<Vec<CreateMilestoneRequest>>,
+}
+
+/// A project after an edit. Changes to a published project's funding goal or
+/// milestones wait in `pending_revision` until an admin approves them.
+#[derive(Debug, Serialize)]
+pub struct ProjectEdit {
+    #[serde(flatten)]
+    pub project: Project,
+    pub pending_revision: Option<ProjectRevision>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetTagsRequest {
+    /// Free-form; normalized to lowercase hyphenated tags without duplicates
+    #[validate(length(max = 50, message = "At most 50 tags can be sent"))]
+    pub tags: Vec<String>,
+    /// Category slugs; leave out to keep the project's categories
+    #[validate(length(max = 3, message = "A project can be in at most 3 categories"))]
+    pub categories: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectTags {
+    pub project_id: Uuid,
+    pub tags: Vec<String>,
+    pub categories: Vec<Category>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryProjects {
+    pub category: Category,
+    #[serde(flatten)]
+    pub projects: Page<ProjectListItem>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ProjectResponse {
     pub project: Project,
+    pub countdown: Countdown,
     pub milestones: Vec<ProjectMilestone>,
+    /// The gallery in order; the first image is the cover
+    pub media: Vec<ProjectMedia>,
+    /// The latest published updates; the rest are under `/updates`
+    pub updates: Vec<ProjectUpdate>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ListProjectsQuery {
+    /// Full-text search over title, description, and tags
+    pub q: Option<String>,
     pub status: Option<String>,
     pub student_id: Option<Uuid>,
+    pub cursor: Option<String>,
     pub limit: Option<i64>,
-    pub offset: Option<i64>,
+}
+
+/// Most projects that can be compared in one request
+const MAX_COMPARE_PROJECTS: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct CompareProjectsQuery {
+    /// Comma-separated project ids
+    pub ids: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -65,37 +162,74 @@ pub struct ProjectListItem {
 
 #[derive(Debug, Deserialize)]
 pub struct PublishProjectRequest {
-    pub admin_id: Uuid,
     pub contract_address: Option<String>,
+    /// Go live at this time instead of now
+    pub publish_at: Option<DateTime<Utc>>,
+    /// Stop taking donations at this time; replaces the one the project was
+    /// created with
+    pub funding_deadline: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetProjectStatusRequest {
+    pub status: OnchainProjectStatus,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CompleteProjectRequest {
+    /// What to do with the leftover balance; admins only, otherwise the
+    /// platform default applies
+    pub settlement: Option<SettlementPolicy>,
+}
+
+#[derive(Debug, Default, Deserialize, Validate)]
+pub struct CancelProjectRequest {
+    #[validate(length(max = 2000, message = "Reason must be at most 2000 characters"))]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectRefundStatus {
+    #[serde(flatten)]
+    pub progress: RefundProgress,
+    /// Refunds that gave up; admins only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_refunds: Option<Vec<ProjectRefund>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectCompletion {
+    pub project: Project,
+    pub settlement: Settlement,
+    /// Donors sent the completion report
+    pub donors_emailed: usize,
 }
 
 pub async fn create_project(
     State(state): State<crate::state::AppState>,
-    Json(req): Json<CreateProjectRequest>,
-) -> Result<(StatusCode, Json<ProjectResponse>), StatusCode> {
+    ValidatedJson(req): ValidatedJson<CreateProjectRequest>,
+) -> AppResult<(StatusCode, Json<ProjectResponse>)> {
+    let funding_goal: BigDecimal = req.funding_goal_xlm.trim().parse().context("Invalid funding goal")?;
+    project_schedule::check_schedule(Utc::now(), None, req.funding_deadline)
+        .map_err(|e| AppError::invalid("funding_deadline", e.to_string()))?;
+
     // Verify student exists and is verified
     let student = sqlx::query!(
         r#"
-        SELECT id, verification_status 
+        SELECT id, user_id, verification_status 
         FROM students 
         WHERE id = $1
         "#,
         req.student_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Student not found"))?;
 
     if student.verification_status != "verified" {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(AppError::forbidden("Only verified students can create projects"));
     }
 
-    // Parse funding goal
-    let funding_goal: BigDecimal = req.funding_goal_xlm
-        .parse()
-        .map_err(|_| StatusCode::BAD_REQUEST)?;
-
     // Create project
     let project_id = Uuid::new_v4();
     let project = sqlx::query_as!(
@@ -103,9 +237,9 @@ pub async fn create_project(
         r#"
         INSERT INTO projects (
             id, student_id, title, description, repo_url, 
-            media_url, tags, funding_goal, status
+            media_url, tags, funding_goal, funding_cap, funding_deadline, status
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 'pending_review')
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, 'pending_review')
         RETURNING id, student_id, title, description, repo_url, 
                   media_url, tags, funding_goal, status, 
                   contract_address, created_at
@@ -116,23 +250,33 @@ pub async fn create_project(
         req.description,
         req.repo_url,
         req.media_urls.as_ref().and_then(|urls| urls.first()).cloned(),
-        Some(&req.tags[..]),
+        Some(&categories::normalize_tags(&req.tags)[..]),
         funding_goal,
+        req.funding_cap_xlm.map(|cap| cap.to_decimal()),
+        req.funding_deadline,
     )
     .fetch_one(&state.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Failed to create project: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .context("Failed to create project")?;
+
+    project_members::add_owner(&state.pool, project_id, req.student_id)
+        .await
+        .context("Failed to add the project's owner")?;
+
+    // The submitted images start the gallery, the first as its cover
+    let mut media = Vec::new();
+    for url in req.media_urls.iter().flatten().take(MAX_MEDIA_PER_PROJECT as usize) {
+        let item = project_media::add_link(&state.pool, project_id, KIND_IMAGE, url, None, student.user_id)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to add project media: {}", e))?;
+        media.push(item);
+    }
 
     // Create milestones
     let mut milestones = Vec::new();
     for milestone_req in req.milestones {
-        let amount_xlm: f64 = milestone_req.amount_xlm
-            .parse()
-            .map_err(|_| StatusCode::BAD_REQUEST)?;
-        let amount_stroops = (amount_xlm * 10_000_000.0) as i64;
+        let amount: Stroops = milestone_req.amount_xlm.parse().context("Invalid milestone amount")?;
+        let amount_stroops = amount.as_stroops();
 
         let milestone_id = Uuid::new_v4();
         sqlx::query!(
@@ -153,7 +297,7 @@ pub async fn create_project(
         )
         .execute(&state.pool)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .context("Failed to create project milestone")?;
 
         let milestone = ProjectMilestone {
             id: milestone_id,
@@ -172,18 +316,34 @@ pub async fn create_project(
         milestones.push(milestone);
     }
 
+    // Mirror the cap into the escrow contract so over-cap deposits are refused on-chain
+    if let Some(cap) = req.funding_cap_xlm {
+        let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+        let result = match contract_client.load_contracts().await {
+            Ok(()) => contract_client.set_funding_cap(project_id, cap.as_stroops()).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to set funding cap for project {}: {}", project_id, e);
+        }
+    }
+
+    let countdown = Countdown::at(Utc::now(), None, req.funding_deadline, true);
     Ok((StatusCode::CREATED, Json(ProjectResponse {
         project,
+        countdown,
         milestones,
+        media,
+        updates: Vec::new(),
     })))
 }
 
 pub async fn list_projects(
     State(state): State<crate::state::AppState>,
     Query(query): Query<ListProjectsQuery>,
-) -> Result<Json<Vec<ProjectListItem>>, StatusCode> {
-    let limit = query.limit.unwrap_or(20);
-    let offset = query.offset.unwrap_or(0);
+) -> AppResult<Json<Page<ProjectListItem>>> {
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
+    let search = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
 
     let projects = if let Some(status) = query.status {
         sqlx::query_as!(
@@ -192,13 +352,17 @@ pub async fn list_projects(
             SELECT id, student_id, title, description, tags, 
                    funding_goal, status, created_at
             FROM projects
-            WHERE status = $1
-            ORDER BY created_at DESC
-            LIMIT $2 OFFSET $3
+            WHERE status = $1 AND hidden_at IS NULL
+            AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3::uuid))
+            AND ($5::text IS NULL OR search_vector @@ websearch_to_tsquery('english', $5))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $4
             "#,
             status,
-            limit,
-            offset
+            page.after_created_at(),
+            page.after_id(),
+            page.fetch_limit(),
+            search
         )
         .fetch_all(&state.pool)
         .await
@@ -209,13 +373,17 @@ pub async fn list_projects(
             SELECT id, student_id, title, description, tags, 
                    funding_goal, status, created_at
             FROM projects
-            WHERE student_id = $1
-            ORDER BY created_at DESC
-            LIMIT $2 OFFSET $3
+            WHERE student_id = $1 AND hidden_at IS NULL
+            AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3::uuid))
+            AND ($5::text IS NULL OR search_vector @@ websearch_to_tsquery('english', $5))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $4
             "#,
             student_id,
-            limit,
-            offset
+            page.after_created_at(),
+            page.after_id(),
+            page.fetch_limit(),
+            search
         )
         .fetch_all(&state.pool)
         .await
@@ -226,24 +394,140 @@ pub async fn list_projects(
             SELECT id, student_id, title, description, tags, 
                    funding_goal, status, created_at
             FROM projects
-            WHERE status IN ('active', 'pending_review')
-            ORDER BY created_at DESC
-            LIMIT $1 OFFSET $2
+            WHERE status IN ('active', 'pending_review') AND hidden_at IS NULL
+            AND ($1::timestamptz IS NULL OR (created_at, id) < ($1, $2::uuid))
+            AND ($4::text IS NULL OR search_vector @@ websearch_to_tsquery('english', $4))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $3
             "#,
-            limit,
-            offset
+            page.after_created_at(),
+            page.after_id(),
+            page.fetch_limit(),
+            search
         )
         .fetch_all(&state.pool)
         .await
     };
 
-    projects.map(Json).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    let projects = projects.context("Failed to list projects")?;
+    Ok(Json(Page::new(projects, &page, |p| (p.created_at, p.id))))
+}
+
+/// Set a project's tags and categories (owner or admin)
+pub async fn set_project_tags(
+    State(state): State<crate::state::AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: axum::http::HeaderMap,
+    ValidatedJson(req): ValidatedJson<SetTagsRequest>,
+) -> AppResult<Json<ProjectTags>> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let caller = sqlx::query!(
+        r#"
+        SELECT u.role,
+               EXISTS(SELECT 1 FROM projects WHERE id = $2) as "exists!",
+               EXISTS(
+                   SELECT 1 FROM projects p JOIN students s ON s.id = p.student_id
+                   WHERE p.id = $2 AND s.user_id = u.id
+               ) as "is_owner!",
+               COALESCE((SELECT status FROM projects WHERE id = $2), '') as "status!"
+        FROM users u
+        WHERE u.id = $1
+        "#,
+        user_id,
+        project_id
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::unauthorized("Account no longer exists"))?;
+
+    if !caller.exists {
+        return Err(AppError::not_found("Project not found"));
+    }
+    if !caller.is_owner && caller.role != "admin" {
+        return Err(AppError::forbidden("Only the project's owner can tag it"));
+    }
+    if caller.status == "completed" {
+        return Err(project_closed(&caller.status));
+    }
+
+    if let Some(slugs) = &req.categories {
+        let mut slugs: Vec<String> = slugs.iter().map(|s| s.trim().to_lowercase()).collect();
+        slugs.sort();
+        slugs.dedup();
+        categories::set_for_project(&state.pool, project_id, &slugs).await.map_err(|e| match e {
+            CategoryError::Unknown(_) => AppError::invalid("categories", e.to_string()),
+            CategoryError::Internal(e) => AppError::Internal(e),
+        })?;
+    }
+
+    let tags = sqlx::query_scalar!(
+        r#"UPDATE projects SET tags = $2 WHERE id = $1 RETURNING tags as "tags!""#,
+        project_id,
+        &categories::normalize_tags(&req.tags)[..]
+    )
+    .fetch_one(&state.pool)
+    .await?;
+
+    Ok(Json(ProjectTags {
+        project_id,
+        tags,
+        categories: categories::for_project(&state.pool, project_id).await?,
+    }))
+}
+
+/// Active projects in a category, newest first
+pub async fn list_category_projects(
+    State(state): State<crate::state::AppState>,
+    Path(slug): Path<String>,
+    Query(query): Query<PageQuery>,
+) -> AppResult<Json<CategoryProjects>> {
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
+    let category = categories::find_by_slug(&state.pool, &slug)
+        .await?
+        .ok_or_else(|| AppError::not_found("Category not found"))?;
+
+    let projects = sqlx::query_as!(
+        ProjectListItem,
+        r#"
+        SELECT p.id, p.student_id, p.title, p.description, p.tags,
+               p.funding_goal, p.status, p.created_at
+        FROM projects p
+        JOIN project_categories pc ON pc.project_id = p.id
+        WHERE pc.category_id = $1 AND p.status = 'active' AND p.hidden_at IS NULL
+        AND ($2::timestamptz IS NULL OR (p.created_at, p.id) < ($2, $3::uuid))
+        ORDER BY p.created_at DESC, p.id DESC
+        LIMIT $4
+        "#,
+        category.id,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(CategoryProjects { category, projects: Page::new(projects, &page, |p| (p.created_at, p.id)) }))
 }
 
 pub async fn get_project(
     State(state): State<crate::state::AppState>,
     Path(project_id): Path<Uuid>,
-) -> Result<Json<ProjectResponse>, StatusCode> {
+    headers: axum::http::HeaderMap,
+) -> AppResult<Json<ProjectResponse>> {
+    // Hidden by moderation: only admins can still look
+    let hidden = sqlx::query_scalar!(
+        r#"SELECT hidden_at IS NOT NULL as "hidden!" FROM projects WHERE id = $1"#,
+        project_id
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .unwrap_or(false);
+    if hidden && !crate::utils::roles::caller_is_admin(&state.pool, &headers).await {
+        return Err(AppError::not_found("Project not found"));
+    }
+
     let project = sqlx::query_as!(
         Project,
         r#"
@@ -256,9 +540,8 @@ pub async fn get_project(
         project_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Project not found"))?;
 
     let milestones = sqlx::query_as!(
         ProjectMilestone,
@@ -273,20 +556,52 @@ pub async fn get_project(
         project_id
     )
     .fetch_all(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
+
+    let updates = project_updates::recent(&state.pool, project_id).await?;
+    let countdown = project_schedule::countdown(&state.pool, project_id).await?;
+    let media = project_media::list(&state.pool, project_id, false).await?;
 
     Ok(Json(ProjectResponse {
         project,
+        countdown,
         milestones,
+        media,
+        updates,
     }))
 }
 
+/// The caller's user id, erroring unless they're on the project's team (the
+/// owner, when `owner_only`) or an admin
+pub(crate) async fn require_team_member(
+    state: &crate::state::AppState,
+    headers: &axum::http::HeaderMap,
+    project_id: Uuid,
+    owner_only: bool,
+) -> AppResult<Uuid> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+    let membership = project_members::membership(&state.pool, project_id, user_id).await?;
+    let allowed = membership.is_some_and(|m| m.is_active() && (m.is_owner() || !owner_only));
+    if !allowed && !crate::utils::roles::caller_is_admin(&state.pool, headers).await {
+        return Err(AppError::forbidden(if owner_only {
+            "Only the project's owner can do this"
+        } else {
+            "Only the project's team can edit it"
+        }));
+    }
+    Ok(user_id)
+}
+
+/// Edit a project. Every edit is kept as a revision; on a published project,
+/// changes to the funding goal or milestones wait for an admin's approval
+/// while the rest go live.
 pub async fn update_project(
     State(state): State<crate::state::AppState>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<UpdateProjectRequest>,
-) -> Result<Json<Project>, StatusCode> {
+    headers: axum::http::HeaderMap,
+    ValidatedJson(req): ValidatedJson<UpdateProjectRequest>,
+) -> AppResult<Json<ProjectEdit>> {
     // Get existing project
     let mut project = sqlx::query_as!(
         Project,
@@ -300,14 +615,18 @@ pub async fn update_project(
         project_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Project not found"))?;
+    let author_id = require_team_member(&state, &headers, project_id, false).await?;
 
-    // Can only update if pending_review or active (not completed/rejected)
-    if project.status == "completed" || project.status == "rejected" {
-        return Err(StatusCode::FORBIDDEN);
+    // Can only update if pending_review or active (not completed/rejected/cancelled)
+    if matches!(project.status.as_str(), "completed" | "rejected" | "cancelled") {
+        return Err(project_closed(&project.status));
     }
+    // Approved for later counts as published: donors may already have seen it
+    let published = matches!(project.status.as_str(), "active" | "scheduled");
+    let mut before = project_revisions::snapshot(&project);
+    let original_goal = project.funding_goal.clone();
 
     // Update fields
     if let Some(title) = req.title {
@@ -323,15 +642,47 @@ pub async fn update_project(
         project.media_url = Some(media_url);
     }
     if let Some(tags) = req.tags {
-        project.tags = tags;
+        project.tags = categories::normalize_tags(&tags);
     }
     if let Some(funding_goal_str) = req.funding_goal_xlm {
-        project.funding_goal = funding_goal_str
-            .parse()
-            .map_err(|_| StatusCode::BAD_REQUEST)?;
+        project.funding_goal = funding_goal_str.trim().parse().context("Invalid funding goal")?;
+    }
+
+    let mut after = project_revisions::snapshot(&project);
+    if let Some(milestones) = req.milestones {
+        let plan = milestones
+            .into_iter()
+            .map(|m| {
+                Ok(PlannedMilestone {
+                    title: m.title,
+                    description: m.description,
+                    amount: m.amount_xlm.parse().context("Invalid milestone amount")?,
+                    proof_type: Some(m.proof_type),
+                    position: m.order,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut conn = state.pool.acquire().await?;
+        before["milestones"] = serde_json::to_value(project_revisions::milestone_plan(&mut conn, project_id).await?)
+            .context("Failed to snapshot milestones")?;
+        after["milestones"] = serde_json::to_value(&plan).context("Failed to snapshot milestones")?;
+    }
+
+    let changes = project_revisions::diff(&before, &after);
+    let (live, review) = match published {
+        true => project_revisions::partition(changes),
+        false => (changes, Vec::new()),
+    };
+    if !review.is_empty() && project_revisions::pending(&state.pool, project_id).await?.is_some() {
+        return Err(revision_pending());
+    }
+    if published {
+        // Held back until the revision is approved
+        project.funding_goal = original_goal;
     }
 
     // Save updates
+    let mut tx = state.pool.begin().await?;
     let updated_project = sqlx::query_as!(
         Project,
         r#"
@@ -351,29 +702,68 @@ pub async fn update_project(
         &project.tags[..],
         project.funding_goal,
     )
-    .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .fetch_one(&mut *tx)
+    .await?;
+    if let Some(plan) = live.iter().find(|c| c.field == "milestones") {
+        let plan: Vec<PlannedMilestone> = serde_json::from_value(plan.after.clone()).context("Invalid milestone plan")?;
+        project_revisions::replace_milestones(&mut tx, project_id, &plan).await?;
+    }
+    tx.commit().await?;
+
+    if !live.is_empty() {
+        project_revisions::record(&state.pool, project_id, author_id, &live, false)
+            .await
+            .map_err(map_revision_error)?;
+    }
+    let pending_revision = match review.is_empty() {
+        true => project_revisions::pending(&state.pool, project_id).await?,
+        false => {
+            let revision = project_revisions::record(&state.pool, project_id, author_id, &review, true)
+                .await
+                .map_err(map_revision_error)?;
+            let _ = sqlx::query!(
+                r#"
+                INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                author_id,
+                "project_revision_submitted",
+                project_id,
+                "project",
+                serde_json::json!({"revision_id": revision.id})
+            )
+            .execute(&state.pool)
+            .await;
+            let event = NotificationEvent::ProjectRevision {
+                project_id,
+                revision_id: revision.id,
+                status: revision.status.clone(),
+            };
+            state.notifier.send(Channel::Admins, event);
+            Some(revision)
+        }
+    };
 
-    Ok(Json(updated_project))
+    Ok(Json(ProjectEdit { project: updated_project, pending_revision }))
 }
 
 pub async fn delete_project(
     State(state): State<crate::state::AppState>,
     Path(project_id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
+    headers: axum::http::HeaderMap,
+) -> AppResult<StatusCode> {
     // Check project exists and is deletable (only pending_review)
     let project = sqlx::query!(
         r#"SELECT status FROM projects WHERE id = $1"#,
         project_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+    .await?
+    .ok_or_else(|| AppError::not_found("Project not found"))?;
+    require_team_member(&state, &headers, project_id, true).await?;
 
     if project.status != "pending_review" {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(AppError::forbidden("Only projects awaiting review can be deleted"));
     }
 
     sqlx::query!(
@@ -381,8 +771,7 @@ pub async fn delete_project(
         project_id
     )
     .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -391,28 +780,66 @@ pub async fn publish_project(
     State(state): State<crate::state::AppState>,
     Path(project_id): Path<Uuid>,
     Json(req): Json<PublishProjectRequest>,
-) -> Result<Json<Project>, StatusCode> {
-    // Verify admin exists
-    let admin = sqlx::query!(
-        r#"SELECT role FROM users WHERE id = $1"#,
-        req.admin_id
-    )
-    .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::FORBIDDEN)?;
+) -> AppResult<Json<Project>> {
+    // Callers hold projects.publish, checked by the route's middleware
+    let now = Utc::now();
+    let publish_at = req.publish_at.filter(|at| *at > now);
+    project_schedule::check_schedule(now, publish_at, req.funding_deadline)
+        .map_err(|e| AppError::invalid("funding_deadline", e.to_string()))?;
 
-    // Admin role check
-    if admin.role != "admin" {
-        return Err(StatusCode::FORBIDDEN);
+    // Approved for later: the project scheduler publishes it when the time comes
+    if let Some(publish_at) = publish_at {
+        let project = sqlx::query_as!(
+            Project,
+            r#"
+            UPDATE projects
+            SET status = 'scheduled', publish_at = $2,
+                funding_deadline = COALESCE($3, funding_deadline),
+                contract_address = COALESCE($4, contract_address)
+            WHERE id = $1 AND status IN ('pending_review', 'scheduled')
+            RETURNING id, student_id, title, description, repo_url, 
+                      media_url, tags, funding_goal, status, 
+                      contract_address, created_at
+            "#,
+            project_id,
+            publish_at,
+            req.funding_deadline,
+            req.contract_address,
+        )
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::conflict("Only projects awaiting review can be scheduled"))?;
+
+        let event = NotificationEvent::ProjectStatus { project_id: project.id, status: "scheduled".to_string() };
+        state.notifier.send(Channel::ProjectTeam(project.id), event);
+        return Ok(Json(project));
+    }
+
+    // In per-project escrow mode, give the project its own escrow account
+    // before it goes live, so it never takes donations without one. A
+    // failed activation below leaves the account for the next attempt.
+    let contract_address = sqlx::query_scalar!("SELECT contract_address FROM projects WHERE id = $1", project_id)
+        .fetch_optional(&state.pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project not found"))?;
+    if state.escrow_mode == EscrowMode::PerProject && req.contract_address.is_none() && contract_address.is_none() {
+        let escrow = EscrowService::new(state.pool.clone(), state.stellar_service.clone());
+        escrow.provision(project_id).await.map_err(|e| {
+            tracing::error!("Failed to provision escrow for project {}: {}", project_id, e);
+            AppError::Upstream("Failed to provision the project's escrow account".to_string())
+        })?;
     }
 
+    // The registry is the source of truth for lifecycle status
+    transition_onchain_status(&state, project_id, OnchainProjectStatus::Active, true, false).await?;
+
     // Update project status to active
     let project = sqlx::query_as!(
         Project,
         r#"
         UPDATE projects
-        SET status = 'active', contract_address = $2
+        SET status = 'active', contract_address = COALESCE($2, contract_address), publish_at = NOW(),
+            funding_deadline = COALESCE($3, funding_deadline)
         WHERE id = $1
         RETURNING id, student_id, title, description, repo_url, 
                   media_url, tags, funding_goal, status, 
@@ -420,17 +847,13 @@ pub async fn publish_project(
         "#,
         project_id,
         req.contract_address,
+        req.funding_deadline,
     )
     .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
-    // Emit SSE notification
-    let _ = state.notifier.send(format!(
-        "project_published:{}:{}",
-        project.student_id,
-        project.id
-    ));
+    let event = NotificationEvent::ProjectStatus { project_id: project.id, status: "active".to_string() };
+    state.notifier.send(Channel::ProjectTeam(project.id), event);
 
     Ok(Json(project))
 }
@@ -438,7 +861,9 @@ pub async fn publish_project(
 pub async fn reject_project(
     State(state): State<crate::state::AppState>,
     Path(project_id): Path<Uuid>,
-) -> Result<Json<Project>, StatusCode> {
+) -> AppResult<Json<Project>> {
+    transition_onchain_status(&state, project_id, OnchainProjectStatus::Cancelled, true, false).await?;
+
     let project = sqlx::query_as!(
         Project,
         r#"
@@ -452,19 +877,468 @@ pub async fn reject_project(
         project_id,
     )
     .fetch_one(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
-    // Emit SSE notification
-    let _ = state.notifier.send(format!(
-        "project_rejected:{}:{}",
-        project.student_id,
-        project.id
-    ));
+    let event = NotificationEvent::ProjectStatus { project_id: project.id, status: "rejected".to_string() };
+    state.notifier.send(Channel::ProjectTeam(project.id), event);
 
     Ok(Json(project))
 }
 
+/// Cancel a project (owner or admin), as `cancel_project` does; activation
+/// goes through `publish_project` and completion through `complete_project`
+pub async fn set_project_status(
+    State(state): State<crate::state::AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<SetProjectStatusRequest>,
+) -> AppResult<Json<Project>> {
+    if req.status == OnchainProjectStatus::Completed {
+        return Err(AppError::invalid("status", "Complete projects with POST /api/projects/:id/complete"));
+    }
+    if req.status != OnchainProjectStatus::Cancelled {
+        return Err(AppError::invalid("status", "Status must be cancelled"));
+    }
+
+    let cancellation = cancel(&state, project_id, &headers, None).await?;
+    Ok(Json(cancellation.project))
+}
+
+/// Cancel a project (owner or admin): it stops taking donations, recurring
+/// gifts to it end, and every donor is refunded in the background
+pub async fn cancel_project(
+    State(state): State<crate::state::AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: axum::http::HeaderMap,
+    req: Option<Json<CancelProjectRequest>>,
+) -> AppResult<(StatusCode, Json<ProjectCancellation>)> {
+    let req = req.map(|Json(r)| r).unwrap_or_default();
+    req.validate()?;
+    let reason = req.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    let cancellation = cancel(&state, project_id, &headers, reason).await?;
+    Ok((StatusCode::ACCEPTED, Json(cancellation)))
+}
+
+async fn cancel(
+    state: &crate::state::AppState,
+    project_id: Uuid,
+    headers: &axum::http::HeaderMap,
+    reason: Option<&str>,
+) -> AppResult<ProjectCancellation> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let caller = sqlx::query!(
+        r#"
+        SELECT u.role, p.status,
+               EXISTS(SELECT 1 FROM students s WHERE s.id = p.student_id AND s.user_id = u.id) as "is_owner!"
+        FROM users u
+        JOIN projects p ON p.id = $2
+        WHERE u.id = $1
+        "#,
+        user_id,
+        project_id
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Project not found"))?;
+
+    let is_admin = caller.role == "admin";
+    if !caller.is_owner && !is_admin {
+        return Err(AppError::forbidden("Only the project's owner can cancel it"));
+    }
+    if matches!(caller.status.as_str(), "completed" | "rejected" | "cancelled") {
+        return Err(AppError::Coded {
+            status: StatusCode::CONFLICT,
+            code: "project_closed",
+            message: format!("A {} project can't be cancelled", caller.status),
+            details: None,
+        });
+    }
+
+    transition_onchain_status(state, project_id, OnchainProjectStatus::Cancelled, is_admin, caller.is_owner).await?;
+
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.context("Failed to load contracts")?;
+    let cancellation = project_refunds::cancel_project(&state.pool, &contract_client, project_id, Some(user_id), reason).await?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        "project_cancelled",
+        project_id,
+        "project",
+        serde_json::json!({
+            "reason": reason,
+            "refunds_planned": cancellation.refunds_planned,
+            "subscriptions_cancelled": cancellation.subscriptions_cancelled
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    let project = &cancellation.project;
+    let event = NotificationEvent::ProjectStatus { project_id: project.id, status: "cancelled".to_string() };
+    state.notifier.send(Channel::ProjectTeam(project.id), event);
+
+    // Start refunding now rather than waiting for the refund processor's next run
+    if cancellation.refunds_planned > 0 {
+        let pool = state.pool.clone();
+        let network = state.network;
+        let payments = state.payment_providers.service();
+        state.worker_control.spawn(async move {
+            if let Err(e) = project_refunds::run_due(&pool, network, &payments, Some(project_id), i64::MAX).await {
+                tracing::error!("Failed to start refunds for project {}: {}", project_id, e);
+            }
+        });
+    }
+
+    Ok(cancellation)
+}
+
+/// How far a cancelled project's donor refunds have got (owner or admin);
+/// admins also see the refunds that gave up
+pub async fn refund_progress(
+    State(state): State<crate::state::AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: axum::http::HeaderMap,
+) -> AppResult<Json<ProjectRefundStatus>> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let caller = sqlx::query!(
+        r#"
+        SELECT u.role,
+               EXISTS(SELECT 1 FROM students s WHERE s.id = p.student_id AND s.user_id = u.id) as "is_owner!"
+        FROM users u
+        JOIN projects p ON p.id = $2
+        WHERE u.id = $1
+        "#,
+        user_id,
+        project_id
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Project not found"))?;
+
+    let is_admin = caller.role == "admin";
+    if !caller.is_owner && !is_admin {
+        return Err(AppError::forbidden("Only the project's owner can see its refunds"));
+    }
+
+    let progress = project_refunds::progress(&state.pool, project_id).await?;
+    let failed_refunds = match is_admin {
+        true => Some(project_refunds::failed(&state.pool, project_id).await?),
+        false => None,
+    };
+    Ok(Json(ProjectRefundStatus { progress, failed_refunds }))
+}
+
+/// Close out an active project (owner or admin). Every milestone must be
+/// released or cancelled. The leftover balance is settled, donors get a
+/// completion report, and the project is frozen.
+pub async fn complete_project(
+    State(state): State<crate::state::AppState>,
+    Path(project_id): Path<Uuid>,
+    headers: axum::http::HeaderMap,
+    req: Option<Json<CompleteProjectRequest>>,
+) -> AppResult<Json<ProjectCompletion>> {
+    let req = req.map(|Json(r)| r).unwrap_or_default();
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let caller = sqlx::query!(
+        r#"
+        SELECT u.role, p.student_id, p.status, p.created_at,
+               EXISTS(SELECT 1 FROM students s WHERE s.id = p.student_id AND s.user_id = u.id) as "is_owner!"
+        FROM users u
+        JOIN projects p ON p.id = $2
+        WHERE u.id = $1
+        "#,
+        user_id,
+        project_id
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Project not found"))?;
+
+    let is_admin = caller.role == "admin";
+    if !caller.is_owner && !is_admin {
+        return Err(AppError::forbidden("Only the project's owner can complete it"));
+    }
+    if req.settlement.is_some() && !is_admin {
+        return Err(AppError::forbidden("Only admins can choose how the remaining balance is settled"));
+    }
+    if caller.status != "active" {
+        return Err(AppError::Coded {
+            status: StatusCode::CONFLICT,
+            code: "project_not_active",
+            message: format!("A {} project can't be completed", caller.status),
+            details: None,
+        });
+    }
+
+    let outstanding = completion::outstanding_milestones(&state.pool, project_id).await?;
+    if !outstanding.is_empty() {
+        return Err(AppError::Coded {
+            status: StatusCode::CONFLICT,
+            code: "milestones_outstanding",
+            message: "Release or cancel every milestone before completing the project".to_string(),
+            details: Some(serde_json::json!({ "milestones": outstanding })),
+        });
+    }
+
+    // Check the registry will take the transition before any money moves
+    check_onchain_transition(&state, project_id, OnchainProjectStatus::Completed, is_admin, caller.is_owner).await?;
+
+    let policy = req.settlement.unwrap_or_else(SettlementPolicy::from_env);
+    let settlement = completion::settle(&state.pool, state.payments.as_ref(), project_id, caller.student_id, policy, user_id)
+        .await
+        .map_err(|e| match e {
+            CompletionError::PaymentsUnavailable => AppError::Unavailable(e.to_string()),
+            CompletionError::NoWallet => AppError::invalid("settlement", e.to_string()),
+            CompletionError::Payout(_) => AppError::Upstream(e.to_string()),
+            CompletionError::Internal(e) => AppError::Internal(e),
+        })?;
+
+    transition_onchain_status(&state, project_id, OnchainProjectStatus::Completed, is_admin, caller.is_owner).await?;
+
+    let project = sqlx::query_as!(
+        Project,
+        r#"
+        UPDATE projects
+        SET status = 'completed', completed_at = NOW()
+        WHERE id = $1
+        RETURNING id, student_id, title, description, repo_url,
+                  media_url, tags, funding_goal, status,
+                  contract_address, created_at
+        "#,
+        project_id
+    )
+    .fetch_one(&state.pool)
+    .await?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        "project_completed",
+        project_id,
+        "project",
+        serde_json::json!({
+            "settlement_id": settlement.id,
+            "policy": settlement.policy,
+            "amount": settlement.amount,
+            "status": settlement.status
+        })
+    )
+    .execute(&state.pool)
+    .await;
+
+    let event = NotificationEvent::ProjectStatus { project_id: project.id, status: "completed".to_string() };
+    state.notifier.send(Channel::ProjectTeam(project.id), event);
+
+    let refunded = settlement.policy == SettlementPolicy::Refund.as_str();
+    let donors_emailed = email::queue_project_completed(&state.pool, project_id, settlement.amount, refunded)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to queue completion reports for project {}: {}", project_id, e);
+            0
+        });
+    if let Err(e) = completion::notify_completed(&state.pool, project_id).await {
+        tracing::warn!("Failed to notify donors of project {} completing: {}", project_id, e);
+    }
+
+    let funds = completion::funds(&state.pool, project_id).await?;
+    let recorded = analytics_events::record(
+        &state.pool,
+        analytics_events::PROJECT_COMPLETED,
+        "project",
+        project_id,
+        serde_json::json!({
+            "raised": funds.raised,
+            "released": funds.released,
+            "milestones_released": funds.milestones_released,
+            "settlement_policy": settlement.policy,
+            "settled_amount": settlement.amount,
+            "days_active": (Utc::now() - caller.created_at).num_days(),
+            "completed_by": if caller.is_owner { "owner" } else { "admin" }
+        }),
+    )
+    .await;
+    if let Err(e) = recorded {
+        tracing::warn!("Failed to record completion analytics for project {}: {}", project_id, e);
+    }
+
+    Ok(Json(ProjectCompletion { project, settlement, donors_emailed }))
+}
+
+/// Drop a milestone that won't be delivered so the project can be completed
+/// (owner or admin)
+pub async fn cancel_milestone(
+    State(state): State<crate::state::AppState>,
+    Path((project_id, milestone_id)): Path<(Uuid, Uuid)>,
+    headers: axum::http::HeaderMap,
+) -> AppResult<StatusCode> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let caller = sqlx::query!(
+        r#"
+        SELECT u.role, p.status,
+               EXISTS(SELECT 1 FROM students s WHERE s.id = p.student_id AND s.user_id = u.id) as "is_owner!"
+        FROM users u
+        JOIN projects p ON p.id = $2
+        WHERE u.id = $1
+        "#,
+        user_id,
+        project_id
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Project not found"))?;
+
+    if !caller.is_owner && caller.role != "admin" {
+        return Err(AppError::forbidden("Only the project's owner can cancel its milestones"));
+    }
+    if caller.status == "completed" {
+        return Err(project_closed(&caller.status));
+    }
+
+    let milestone = sqlx::query!(
+        "SELECT title, released, cancelled_at FROM milestones WHERE id = $1 AND project_id = $2",
+        milestone_id,
+        project_id
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Milestone not found"))?;
+
+    if milestone.released.unwrap_or(false) {
+        return Err(AppError::conflict("A released milestone can't be cancelled"));
+    }
+    if milestone.cancelled_at.is_some() {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    sqlx::query!("UPDATE milestones SET cancelled_at = NOW() WHERE id = $1", milestone_id)
+        .execute(&state.pool)
+        .await?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        "milestone_cancelled",
+        milestone_id,
+        "milestone",
+        serde_json::json!({"project_id": project_id, "title": milestone.title})
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Edits refused once a project is closed
+pub(crate) fn project_closed(status: &str) -> AppError {
+    AppError::Coded {
+        status: StatusCode::FORBIDDEN,
+        code: "project_closed",
+        message: format!("A {} project can't be edited", status),
+        details: None,
+    }
+}
+
+fn revision_pending() -> AppError {
+    AppError::Coded {
+        status: StatusCode::CONFLICT,
+        code: "revision_pending",
+        message: "The project already has changes awaiting review".to_string(),
+        details: None,
+    }
+}
+
+pub(crate) fn map_revision_error(e: RevisionError) -> AppError {
+    match e {
+        RevisionError::AlreadyPending => revision_pending(),
+        RevisionError::NotPending => AppError::not_found(e.to_string()),
+        RevisionError::Stale(_) => AppError::Coded {
+            status: StatusCode::CONFLICT,
+            code: "revision_stale",
+            message: e.to_string(),
+            details: None,
+        },
+        RevisionError::Internal(e) => AppError::Internal(e),
+    }
+}
+
+fn transition_refused(status: OnchainProjectStatus) -> AppError {
+    AppError::Coded {
+        status: StatusCode::CONFLICT,
+        code: "invalid_status_transition",
+        message: format!("The project can't move to {}", status.as_str()),
+        details: None,
+    }
+}
+
+/// Whether the project registry would accept a lifecycle transition,
+/// without applying it
+async fn check_onchain_transition(
+    state: &crate::state::AppState,
+    project_id: Uuid,
+    status: OnchainProjectStatus,
+    is_admin: bool,
+    is_owner: bool,
+) -> AppResult<()> {
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.context("Failed to load contracts")?;
+
+    let current = contract_client
+        .get_project_status(project_id)
+        .await
+        .context("Failed to read project registry")?
+        .ok_or_else(|| AppError::not_found("Project is not in the registry"))?;
+
+    current.can_transition(status, is_admin, is_owner).map_err(|e| {
+        tracing::warn!("Rejected status change for project {}: {}", project_id, e);
+        transition_refused(status)
+    })
+}
+
+/// Apply a lifecycle transition through the project registry
+async fn transition_onchain_status(
+    state: &crate::state::AppState,
+    project_id: Uuid,
+    status: OnchainProjectStatus,
+    is_admin: bool,
+    is_owner: bool,
+) -> AppResult<()> {
+    let mut contract_client = ContractClient::new(state.pool.clone(), state.network);
+    contract_client.load_contracts().await.context("Failed to load contracts")?;
+
+    contract_client
+        .get_project_status(project_id)
+        .await
+        .context("Failed to read project registry")?
+        .ok_or_else(|| AppError::not_found("Project is not in the registry"))?;
+
+    contract_client
+        .set_project_status(project_id, status, is_admin, is_owner)
+        .await
+        .map_err(|e| {
+            tracing::warn!("Rejected status change for project {}: {}", project_id, e);
+            transition_refused(status)
+        })
+}
+
 /// Get public project information (limited for guests and non-authenticated users)
 #[utoipa::path(
     get,
@@ -477,7 +1351,7 @@ pub async fn reject_project(
 )]
 pub async fn get_public_projects(
     State(state): State<crate::state::AppState>,
-) -> Result<Json<Vec<PublicProjectInfo>>, (StatusCode, Json<serde_json::Value>)> {
+) -> AppResult<Json<Vec<PublicProjectInfo>>> {
     let projects = sqlx::query_as!(
         PublicProjectInfo,
         r#"
@@ -489,23 +1363,147 @@ pub async fn get_public_projects(
             p.funding_goal as "funding_goal!: sqlx::types::BigDecimal",
             COALESCE(SUM(d.amount), 0) as "current_funding!: sqlx::types::BigDecimal",
             p.tags,
-            p.created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+            p.created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+            p.funding_deadline,
+            GREATEST(CEIL(EXTRACT(EPOCH FROM p.funding_deadline - NOW())), 0)::BIGINT as seconds_until_deadline,
+            (p.funding_closed_at IS NULL AND (p.funding_deadline IS NULL OR p.funding_deadline > NOW())) as "funding_open!",
+            COALESCE(
+                (SELECT json_agg(json_build_object('kind', m.kind, 'url', m.url, 'caption', m.caption) ORDER BY m.position)
+                 FROM project_media m
+                 WHERE m.project_id = p.id AND m.status = 'ready'),
+                '[]'
+            ) as "media!: sqlx::types::Json<Vec<crate::models::PublicMedia>>"
         FROM projects p
         LEFT JOIN donations d ON p.id = d.project_id AND d.status = 'confirmed'
-        WHERE p.visibility = 'public' AND p.status = 'active'
+        WHERE p.visibility = 'public' AND p.status = 'active' AND p.hidden_at IS NULL
         GROUP BY p.id, p.title, p.description, p.media_url, p.funding_goal, p.tags, p.created_at
         ORDER BY p.created_at DESC
         "#
     )
     .fetch_all(&state.pool)
     .await
-    .map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": "Failed to fetch public projects"})),
-        )
-    })?;
+    .context("Failed to fetch public projects")?;
 
     Ok(Json(projects))
 }
 
+/// Side-by-side funding, milestone, and donor stats for up to
+/// `MAX_COMPARE_PROJECTS` public projects, in the order requested
+pub async fn compare_projects(
+    State(state): State<crate::state::AppState>,
+    Query(query): Query<CompareProjectsQuery>,
+) -> AppResult<Json<Vec<ProjectComparison>>> {
+    let mut ids = Vec::new();
+    for raw in query.ids.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let id: Uuid = raw.parse().map_err(|_| AppError::invalid("ids", format!("{} is not a project id", raw)))?;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.is_empty() {
+        return Err(AppError::invalid("ids", "At least one project id is required"));
+    }
+    if ids.len() > MAX_COMPARE_PROJECTS {
+        return Err(AppError::invalid(
+            "ids",
+            format!("At most {} projects can be compared", MAX_COMPARE_PROJECTS),
+        ));
+    }
+
+    let mut cache_key: Vec<String> = ids.iter().map(Uuid::to_string).collect();
+    cache_key.sort();
+    let cache_key = cache_key.join(",");
+
+    let rows = match state.compare_cache.get(&cache_key) {
+        Some(rows) => rows,
+        None => {
+            let rows = fetch_project_comparisons(&state.pool, &ids)
+                .await
+                .context("Failed to compare projects")?;
+            state.compare_cache.insert(cache_key, rows.clone());
+            rows
+        }
+    };
+
+    // Unknown or non-public ids are simply left out
+    let ordered = ids
+        .iter()
+        .filter_map(|id| rows.iter().find(|row| row.id == *id).cloned())
+        .collect();
+
+    Ok(Json(ordered))
+}
+
+async fn fetch_project_comparisons(
+    pool: &sqlx::PgPool,
+    ids: &[Uuid],
+) -> Result<Vec<ProjectComparison>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            p.id,
+            p.title,
+            p.status,
+            p.funding_goal as "funding_goal!: Stroops",
+            p.created_at as "created_at!: DateTime<Utc>",
+            COALESCE(d.total, 0) as "current_funding!: Stroops",
+            COALESCE(d.donor_count, 0) as "donor_count!",
+            d.first_donation_at,
+            d.last_donation_at,
+            COALESCE(m.total, 0) as "milestones_total!",
+            COALESCE(m.completed, 0) as "milestones_completed!",
+            m.last_completed_at
+        FROM projects p
+        LEFT JOIN (
+            SELECT project_id,
+                   SUM(amount) as total,
+                   COUNT(DISTINCT COALESCE(donor_id::TEXT, tx_hash, id::TEXT)) as donor_count,
+                   MIN(confirmed_at) as first_donation_at,
+                   MAX(confirmed_at) as last_donation_at
+            FROM donations
+            WHERE status = 'confirmed' AND project_id = ANY($1)
+            GROUP BY project_id
+        ) d ON d.project_id = p.id
+        LEFT JOIN (
+            SELECT project_id,
+                   COUNT(*) as total,
+                   COUNT(*) FILTER (WHERE status IN ('completed', 'claimed')) as completed,
+                   MAX(completed_at) as last_completed_at
+            FROM project_milestones
+            WHERE project_id = ANY($1)
+            GROUP BY project_id
+        ) m ON m.project_id = p.id
+        WHERE p.id = ANY($1) AND p.visibility = 'public'
+        "#,
+        ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let funding_progress_pct = if row.funding_goal.is_positive() {
+                (row.current_funding.to_f64() / row.funding_goal.to_f64() * 100.0).min(100.0)
+            } else {
+                0.0
+            };
+
+            ProjectComparison {
+                id: row.id,
+                title: row.title,
+                status: row.status,
+                funding_goal: row.funding_goal,
+                current_funding: row.current_funding,
+                funding_progress_pct,
+                donor_count: row.donor_count,
+                milestones_total: row.milestones_total,
+                milestones_completed: row.milestones_completed,
+                created_at: row.created_at,
+                first_donation_at: row.first_donation_at,
+                last_donation_at: row.last_donation_at,
+                last_milestone_completed_at: row.last_completed_at,
+            }
+        })
+        .collect())
+}
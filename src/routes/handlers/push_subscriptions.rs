@@ -0,0 +1,87 @@
+use axum::{extract::State, http::HeaderMap, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::ValidatedJson;
+use crate::services::web_push::{self, PushSubscription, WebPush};
+use crate::state::AppState;
+
+/// A browser's `PushSubscription.toJSON()`
+#[derive(Debug, Deserialize, Validate)]
+pub struct SubscribeRequest {
+    #[validate(url(message = "Endpoint must be a URL"), length(max = 1000, message = "Endpoint is too long"))]
+    pub endpoint: String,
+    #[validate(nested)]
+    pub keys: PushKeys,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PushKeys {
+    #[validate(length(min = 1, max = 200, message = "p256dh must be 1 to 200 characters"))]
+    pub p256dh: String,
+    #[validate(length(min = 1, max = 200, message = "auth must be 1 to 200 characters"))]
+    pub auth: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UnsubscribeRequest {
+    #[validate(length(min = 1, max = 1000, message = "Endpoint must be 1 to 1000 characters"))]
+    pub endpoint: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublicKeyResponse {
+    /// For `pushManager.subscribe({ applicationServerKey })`
+    pub public_key: String,
+}
+
+fn caller(headers: &HeaderMap) -> AppResult<Uuid> {
+    crate::utils::jwt::extract_user_id_from_headers(headers).map_err(|_| AppError::unauthorized("Authentication required"))
+}
+
+fn web_push(state: &AppState) -> AppResult<&WebPush> {
+    state
+        .push
+        .as_ref()
+        .ok_or_else(|| AppError::Unavailable("Push notifications are not configured".to_string()))
+}
+
+/// The server's VAPID public key, which browsers subscribe with
+pub async fn public_key(State(state): State<AppState>) -> AppResult<Json<PublicKeyResponse>> {
+    let push = web_push(&state)?;
+    Ok(Json(PublicKeyResponse { public_key: push.public_key().to_string() }))
+}
+
+/// Register this browser for push notifications of verification approvals,
+/// milestone releases and donations received
+pub async fn subscribe(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<SubscribeRequest>,
+) -> AppResult<(StatusCode, Json<PushSubscription>)> {
+    let user_id = caller(&headers)?;
+    web_push(&state)?;
+    if !req.endpoint.starts_with("https://") {
+        return Err(AppError::invalid("endpoint", "Push endpoints must use HTTPS"));
+    }
+
+    let user_agent = headers.get(axum::http::header::USER_AGENT).and_then(|v| v.to_str().ok());
+    let subscription =
+        web_push::subscribe(&state.pool, user_id, &req.endpoint, &req.keys.p256dh, &req.keys.auth, user_agent).await?;
+    Ok((StatusCode::CREATED, Json(subscription)))
+}
+
+/// Stop pushing to this browser
+pub async fn unsubscribe(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<UnsubscribeRequest>,
+) -> AppResult<StatusCode> {
+    let user_id = caller(&headers)?;
+    if !web_push::unsubscribe(&state.pool, user_id, &req.endpoint).await? {
+        return Err(AppError::not_found("Push subscription not found"));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
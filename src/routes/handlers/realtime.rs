@@ -0,0 +1,277 @@
+use axum::{
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+    response::{IntoResponse, Response},
+};
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Instant;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamMap;
+use uuid::Uuid;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::services::notification_preferences;
+use crate::services::notifications::{self, Channel, EventPayload, MAX_PROJECT_TOPICS};
+use crate::state::{AppState, Notifier, StreamedEvent};
+use crate::utils::jwt;
+use crate::utils::roles::bearer_from_auth;
+
+#[derive(Debug, Deserialize)]
+pub struct StreamQuery {
+    /// For clients that can't set headers, such as `EventSource` and browser
+    /// WebSockets
+    pub token: Option<String>,
+    /// Where a WebSocket client resumes from; SSE clients send the
+    /// `Last-Event-ID` header instead
+    pub last_event_id: Option<u64>,
+}
+
+/// Who a stream is for
+struct Listener {
+    user_id: Uuid,
+    channels: Vec<Channel>,
+    /// Event types the user turned off in-app
+    muted: HashSet<String>,
+}
+
+impl Listener {
+    fn wants(&self, event: &StreamedEvent) -> bool {
+        !self.muted.contains(event.payload.event.name())
+    }
+}
+
+/// The caller, the channels their stream listens on and what they've muted
+async fn authorize(state: &AppState, headers: &HeaderMap, query: &StreamQuery) -> AppResult<Listener> {
+    let auth = headers.get("authorization").and_then(|v| v.to_str().ok());
+    let token = bearer_from_auth(auth)
+        .or(query.token.as_deref())
+        .ok_or_else(|| AppError::unauthorized("Authentication required"))?;
+    let claims = jwt::verify_token(token).map_err(|_| AppError::unauthorized("Invalid or expired token"))?;
+    let channels = notifications::channels_for(&state.pool, claims.sub, claims.is_admin()).await?;
+    let muted = notification_preferences::muted_in_app(&state.pool, claims.sub).await?.into_iter().collect();
+    Ok(Listener { user_id: claims.sub, channels, muted })
+}
+
+/// Listen on each channel, returning the receivers and the buffered events
+/// after `last_event_id`. An event sent on several of the channels is
+/// returned once.
+fn subscribe_all(
+    notifier: &Notifier,
+    channels: Vec<Channel>,
+    last_event_id: Option<u64>,
+) -> (Vec<(Channel, broadcast::Receiver<StreamedEvent>)>, Vec<StreamedEvent>) {
+    let mut receivers = Vec::with_capacity(channels.len());
+    let mut missed = Vec::new();
+    for channel in channels {
+        let (receiver, buffered) = notifier.subscribe(channel, last_event_id);
+        receivers.push((channel, receiver));
+        missed.extend(buffered);
+    }
+    missed.sort_by_key(|e| e.id);
+    missed.dedup_by_key(|e| e.id);
+    (receivers, missed)
+}
+
+fn sse_event(event: StreamedEvent) -> Option<Result<Event, std::convert::Infallible>> {
+    // Each event is named for its kind and carries the same payload the
+    // notifications list does
+    let sse = Event::default()
+        .id(event.id.to_string())
+        .event(event.payload.event.name())
+        .json_data(&event.payload)
+        .ok()?;
+    Some(Ok(sse))
+}
+
+/// Stream the caller's notification events: their own, those of projects
+/// they're on, follow or gave to, and admin alerts for admins, less the
+/// event types they turned off in-app. Clients
+/// reconnecting with `Last-Event-ID` first get the buffered events they
+/// missed.
+pub async fn sse_notifications(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<StreamQuery>,
+) -> AppResult<impl IntoResponse> {
+    let listener = authorize(&state, &headers, &query).await?;
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+
+    let (receivers, missed) = subscribe_all(&state.notifier, listener.channels.clone(), last_event_id);
+    let replayed: HashSet<u64> = missed.iter().map(|e| e.id).collect();
+    let live = futures::stream::select_all(receivers.into_iter().map(|(_, receiver)| BroadcastStream::new(receiver)))
+        .filter_map(move |msg| futures::future::ready(msg.ok().filter(|e| !replayed.contains(&e.id))));
+    let stream = futures::stream::iter(missed)
+        .chain(live)
+        .filter(move |event| futures::future::ready(listener.wants(event)))
+        .filter_map(|event| futures::future::ready(sse_event(event)));
+    Ok(Sse::new(stream).keep_alive(KeepAlive::new().interval(crate::config::sse_keep_alive())))
+}
+
+/// What WebSocket clients send
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Follow a project's public activity on this connection
+    Subscribe { project_id: Uuid },
+    Unsubscribe { project_id: Uuid },
+    /// Read receipt for one of the caller's notifications
+    MarkRead { notification_id: Uuid },
+}
+
+/// What WebSocket clients are sent
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// A notification event, shaped as over SSE
+    Event {
+        id: u64,
+        #[serde(flatten)]
+        payload: EventPayload,
+    },
+    Subscribed { project_id: Uuid },
+    Unsubscribed { project_id: Uuid },
+    Read { notification_id: Uuid },
+    Error { message: String },
+}
+
+impl From<StreamedEvent> for ServerMessage {
+    fn from(event: StreamedEvent) -> Self {
+        ServerMessage::Event { id: event.id, payload: event.payload }
+    }
+}
+
+type Streams = StreamMap<Channel, BroadcastStream<StreamedEvent>>;
+
+/// The caller's notification events over a WebSocket, the same ones SSE
+/// carries. Clients can also follow and unfollow projects for the life of
+/// the connection and mark notifications read. Connections that stop
+/// answering pings are closed.
+pub async fn notifications_ws(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<StreamQuery>,
+    ws: WebSocketUpgrade,
+) -> AppResult<Response> {
+    let listener = authorize(&state, &headers, &query).await?;
+    let last_event_id = query.last_event_id;
+    Ok(ws.on_upgrade(move |socket| run_socket(socket, state, listener, last_event_id)))
+}
+
+async fn send_message(socket: &mut futures::stream::SplitSink<WebSocket, Message>, message: &ServerMessage) -> bool {
+    match serde_json::to_string(message) {
+        Ok(text) => socket.send(Message::Text(text)).await.is_ok(),
+        Err(e) => {
+            tracing::error!("Failed to serialize a WebSocket message: {}", e);
+            true
+        }
+    }
+}
+
+async fn run_socket(socket: WebSocket, state: AppState, listener: Listener, last_event_id: Option<u64>) {
+    let user_id = listener.user_id;
+    let (mut outgoing, mut incoming) = socket.split();
+    let (receivers, missed) = subscribe_all(&state.notifier, listener.channels.clone(), last_event_id);
+    let mut streams = Streams::new();
+    for (channel, receiver) in receivers {
+        streams.insert(channel, BroadcastStream::new(receiver));
+    }
+    let replayed: HashSet<u64> = missed.iter().map(|e| e.id).collect();
+    for event in missed.into_iter().filter(|e| listener.wants(e)) {
+        if !send_message(&mut outgoing, &event.into()).await {
+            return;
+        }
+    }
+
+    let interval = crate::config::ws_ping_interval();
+    let mut ping = tokio::time::interval(interval);
+    let mut last_heard = Instant::now();
+    loop {
+        let reply = tokio::select! {
+            next = streams.next() => match next {
+                Some((_, Ok(event))) if !replayed.contains(&event.id) && listener.wants(&event) => ServerMessage::from(event),
+                // Replayed already, muted, or missed because this connection fell behind
+                Some(_) => continue,
+                // Every channel was dropped, so the client should reconnect
+                None => break,
+            },
+            message = incoming.next() => {
+                last_heard = Instant::now();
+                match message {
+                    Some(Ok(Message::Text(text))) => handle_message(&state, user_id, &mut streams, &text).await,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    // Pongs and pings only show the client is alive
+                    Some(Ok(_)) => continue,
+                }
+            },
+            _ = ping.tick() => {
+                if last_heard.elapsed() > interval * 2 {
+                    tracing::debug!("Closing the WebSocket of user {} after missed pings", user_id);
+                    break;
+                }
+                if outgoing.send(Message::Ping(Vec::new())).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+        };
+        if !send_message(&mut outgoing, &reply).await {
+            break;
+        }
+    }
+    let _ = outgoing.close().await;
+}
+
+async fn handle_message(state: &AppState, user_id: Uuid, streams: &mut Streams, text: &str) -> ServerMessage {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(e) => return ServerMessage::Error { message: format!("Invalid message: {}", e) },
+    };
+    match message {
+        ClientMessage::Subscribe { project_id } => {
+            let channel = Channel::Project(project_id);
+            if streams.contains_key(&channel) {
+                return ServerMessage::Subscribed { project_id };
+            }
+            let topics = streams.keys().filter(|c| matches!(c, Channel::Project(_))).count();
+            if topics as i64 >= MAX_PROJECT_TOPICS {
+                return ServerMessage::Error {
+                    message: format!("At most {} projects can be followed per connection", MAX_PROJECT_TOPICS),
+                };
+            }
+            match notifications::is_watchable(&state.pool, project_id).await {
+                Ok(true) => {
+                    let (receiver, _) = state.notifier.subscribe(channel, None);
+                    streams.insert(channel, BroadcastStream::new(receiver));
+                    ServerMessage::Subscribed { project_id }
+                }
+                Ok(false) => ServerMessage::Error { message: "Project not found".to_string() },
+                Err(e) => {
+                    tracing::error!("Failed to subscribe user {} to project {}: {}", user_id, project_id, e);
+                    ServerMessage::Error { message: "Could not subscribe to the project".to_string() }
+                }
+            }
+        }
+        ClientMessage::Unsubscribe { project_id } => {
+            streams.remove(&Channel::Project(project_id));
+            ServerMessage::Unsubscribed { project_id }
+        }
+        ClientMessage::MarkRead { notification_id } => {
+            match notifications::mark_read(&state.pool, user_id, notification_id).await {
+                Ok(true) => ServerMessage::Read { notification_id },
+                Ok(false) => ServerMessage::Error { message: "Notification not found".to_string() },
+                Err(e) => {
+                    tracing::error!("Failed to mark notification {} read: {}", notification_id, e);
+                    ServerMessage::Error { message: "Could not mark the notification read".to_string() }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use axum::{extract::{Path, Query, State}, http::{HeaderMap, StatusCode}, response::{IntoResponse, Response}, Json};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::routes::handlers::approvals::{dual_control, ApprovalQuery};
+use crate::services::approvals;
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::refunds::{self, Refund, RefundError};
+use crate::utils::money::{Cents, Stroops};
+
+type RefundResult<T> = Result<Json<T>, (StatusCode, Json<serde_json::Value>)>;
+
+fn refund_error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({"error": message})))
+}
+
+#[derive(Deserialize)]
+pub struct CreateRefundRequest {
+    pub payment_id: String,
+    /// Defaults to whatever has not been refunded yet
+    pub amount: Option<Cents>,
+    pub reason: String,
+}
+
+#[derive(Deserialize)]
+pub struct RefundsQuery {
+    pub status: Option<String>,
+    pub payment_id: Option<String>,
+}
+
+/// Refund a completed fiat payment through the provider that took it. Needs
+/// a second admin: the first call opens an approval request.
+pub async fn create_refund(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Query(approval): Query<ApprovalQuery>,
+    Json(req): Json<CreateRefundRequest>,
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+    if req.reason.trim().is_empty() {
+        return Err(refund_error(StatusCode::BAD_REQUEST, "A reason is required"));
+    }
+
+    let payload = serde_json::json!({"payment_id": req.payment_id, "amount": req.amount, "reason": req.reason.trim()});
+    if let Some(pending) = dual_control(&state, &headers, approvals::REFUND, approval.approval_id, payload).await? {
+        return Ok(pending);
+    }
+
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers).ok();
+    let payments = state.payment_providers.service();
+    let refund = refunds::request_refund(&state.pool, &payments, &req.payment_id, req.amount, req.reason.trim(), admin_id)
+        .await
+        .map_err(|e| match e {
+            RefundError::NotFound => refund_error(StatusCode::NOT_FOUND, "Payment not found"),
+            RefundError::NotRefundable(message) => refund_error(StatusCode::CONFLICT, &message),
+            RefundError::Provider(message) => {
+                tracing::warn!("Provider refused refund of {}: {}", req.payment_id, message);
+                refund_error(StatusCode::BAD_GATEWAY, &message)
+            }
+            RefundError::Internal(e) => {
+                tracing::error!("Failed to refund {}: {}", req.payment_id, e);
+                refund_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to process refund")
+            }
+        })?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        "refund_requested",
+        refund.id,
+        "refund",
+        serde_json::json!({
+            "payment_id": refund.payment_id,
+            "provider": refund.provider,
+            "amount": refund.amount,
+            "currency": refund.currency,
+            "reason": refund.reason,
+            "status": refund.status
+        })
+    )
+    .execute(&state.pool)
+    .await;
+    state.notifier.send(Channel::Admins, NotificationEvent::RefundStatus { refund_id: refund.id, status: refund.status.clone() });
+
+    Ok(Json(refund).into_response())
+}
+
+/// Refunds, newest first, optionally by status or payment
+pub async fn list_refunds(
+    State(state): State<crate::state::AppState>,
+    Query(query): Query<RefundsQuery>,
+) -> RefundResult<Vec<Refund>> {
+    let rows = sqlx::query_as!(
+        Refund,
+        r#"
+        SELECT id, payment_id, provider, refund_id, amount as "amount: Cents", currency, reason, status,
+               project_id, donation_id, xlm_amount as "xlm_amount: Stroops", failure_reason, requested_by,
+               created_at, submitted_at, processed_at
+        FROM refunds
+        WHERE ($1::TEXT IS NULL OR status = $1) AND ($2::TEXT IS NULL OR payment_id = $2)
+        ORDER BY created_at DESC
+        LIMIT 200
+        "#,
+        query.status,
+        query.payment_id
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to list refunds: {}", e);
+        refund_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to list refunds")
+    })?;
+
+    Ok(Json(rows))
+}
+
+pub async fn get_refund(
+    State(state): State<crate::state::AppState>,
+    Path(id): Path<Uuid>,
+) -> RefundResult<Refund> {
+    refunds::get_refund(&state.pool, id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load refund {}: {}", id, e);
+            refund_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to load refund")
+        })?
+        .map(Json)
+        .ok_or_else(|| refund_error(StatusCode::NOT_FOUND, "Refund not found"))
+}
@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::ValidatedJson;
+use crate::services::content_reports::{
+    self, Action, NewReport, QueuedReport, Report, ReportError, Resolution, REASONS, STATUSES, TARGET_TYPES,
+};
+use crate::state::AppState;
+use crate::utils::pagination::{Page, PageRequest};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateReportRequest {
+    /// `project`, `comment` or `profile` (by user id)
+    pub target_type: String,
+    pub target_id: Uuid,
+    /// `spam`, `abuse`, `fraud`, `inappropriate` or `other`
+    pub reason: String,
+    #[validate(length(max = 2000, message = "Details must be at most 2000 characters"))]
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateReportResponse {
+    #[serde(flatten)]
+    pub report: Report,
+    /// Whether this report took the content out of view pending review
+    pub content_hidden: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportsQuery {
+    /// `pending` (the default), `dismissed` or `actioned`
+    pub status: Option<String>,
+    pub target_type: Option<String>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResolveReportRequest {
+    pub action: Action,
+    #[validate(length(max = 2000, message = "Note must be at most 2000 characters"))]
+    pub note: Option<String>,
+}
+
+fn map_report_error(e: ReportError) -> AppError {
+    match e {
+        ReportError::TargetNotFound => AppError::not_found(e.to_string()),
+        ReportError::OwnContent => AppError::forbidden(e.to_string()),
+        ReportError::AlreadyReported | ReportError::NotPending => AppError::conflict(e.to_string()),
+        ReportError::Internal(e) => AppError::Internal(e),
+    }
+}
+
+fn one_of(field: &str, value: &str, allowed: &[&str]) -> AppResult<()> {
+    if allowed.contains(&value) {
+        return Ok(());
+    }
+    Err(AppError::invalid(field, format!("Must be one of {}", allowed.join(", "))))
+}
+
+fn caller(headers: &HeaderMap) -> AppResult<Uuid> {
+    crate::utils::jwt::extract_user_id_from_headers(headers).map_err(|_| AppError::unauthorized("Authentication required"))
+}
+
+/// Flag a project, comment or profile for moderators to review
+pub async fn create_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<CreateReportRequest>,
+) -> AppResult<(StatusCode, Json<CreateReportResponse>)> {
+    let user_id = caller(&headers)?;
+    let target_type = req.target_type.trim();
+    let reason = req.reason.trim();
+    one_of("target_type", target_type, TARGET_TYPES)?;
+    one_of("reason", reason, REASONS)?;
+
+    let details = req.details.as_deref().map(str::trim).filter(|d| !d.is_empty());
+    let new = NewReport { target_type, target_id: req.target_id, reason, details };
+    let (report, content_hidden) = content_reports::create(&state.pool, user_id, &new)
+        .await
+        .map_err(map_report_error)?;
+    if content_hidden {
+        tracing::info!("Hid {} {} after repeated reports", target_type, req.target_id);
+    }
+    Ok((StatusCode::CREATED, Json(CreateReportResponse { report, content_hidden })))
+}
+
+/// The moderation queue, newest first (admin or moderator)
+pub async fn list_reports(
+    State(state): State<AppState>,
+    Query(query): Query<ReportsQuery>,
+) -> AppResult<Json<Page<QueuedReport>>> {
+    let status = query.status.as_deref().map(str::trim).filter(|s| !s.is_empty()).unwrap_or("pending");
+    one_of("status", status, STATUSES)?;
+    let target_type = query.target_type.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    if let Some(target_type) = target_type {
+        one_of("target_type", target_type, TARGET_TYPES)?;
+    }
+    let page = PageRequest::new(query.cursor.as_deref(), query.limit)?;
+    Ok(Json(content_reports::list(&state.pool, Some(status), target_type, &page).await?))
+}
+
+/// Dismiss a report, hide what it's about, or also suspend whoever posted
+/// it; every open report on the same content is resolved with it (admin or
+/// moderator)
+pub async fn resolve_report(
+    State(state): State<AppState>,
+    Path(report_id): Path<Uuid>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<ResolveReportRequest>,
+) -> AppResult<Json<Resolution>> {
+    let moderator_id = caller(&headers)?;
+    let note = req.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    let resolution = content_reports::resolve(&state.pool, report_id, req.action, moderator_id, note)
+        .await
+        .map_err(map_report_error)?
+        .ok_or_else(|| AppError::not_found("Report not found"))?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        moderator_id,
+        "content_report_resolved",
+        report_id,
+        "content_report",
+        serde_json::json!({
+            "action": req.action,
+            "target_type": resolution.target_type,
+            "target_id": resolution.target_id,
+            "reports_closed": resolution.reports_closed,
+            "suspended_user_id": resolution.suspended_user_id,
+        })
+    )
+    .execute(&state.pool)
+    .await;
+    Ok(Json(resolution))
+}
@@ -0,0 +1,89 @@
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, Json};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::services::rbac::{self, RbacError, RoleInfo, UserRoles};
+
+type RolesResult<T> = Result<Json<T>, (StatusCode, Json<serde_json::Value>)>;
+
+fn roles_error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({"error": message})))
+}
+
+fn map_error(e: RbacError) -> (StatusCode, Json<serde_json::Value>) {
+    match e {
+        RbacError::UnknownRole | RbacError::AdminNotAssignable => roles_error(StatusCode::BAD_REQUEST, &e.to_string()),
+        RbacError::UserNotFound => roles_error(StatusCode::NOT_FOUND, &e.to_string()),
+        RbacError::Internal(e) => internal(e),
+    }
+}
+
+fn internal(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    tracing::error!("Role management error: {}", e);
+    roles_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to process role request")
+}
+
+async fn log_activity(state: &crate::state::AppState, admin_id: Uuid, action: &str, user_id: Uuid, role: &str) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        action,
+        user_id,
+        "user",
+        serde_json::json!({"role": role})
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+#[derive(Deserialize)]
+pub struct AssignRoleRequest {
+    pub role: String,
+}
+
+/// Roles and the permissions each grants
+pub async fn list_roles(State(state): State<crate::state::AppState>) -> RolesResult<Vec<RoleInfo>> {
+    rbac::list_roles(&state.pool).await.map(Json).map_err(internal)
+}
+
+pub async fn get_user_roles(
+    State(state): State<crate::state::AppState>,
+    Path(user_id): Path<Uuid>,
+) -> RolesResult<UserRoles> {
+    rbac::user_roles(&state.pool, user_id).await.map(Json).map_err(map_error)
+}
+
+/// Takes effect when the user's access token is next refreshed
+pub async fn assign_role(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Path(user_id): Path<Uuid>,
+    Json(req): Json<AssignRoleRequest>,
+) -> RolesResult<UserRoles> {
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| roles_error(StatusCode::UNAUTHORIZED, "Authentication required"))?;
+    let role = req.role.trim().to_lowercase();
+
+    if rbac::assign(&state.pool, user_id, &role, admin_id).await.map_err(map_error)? {
+        log_activity(&state, admin_id, "role_assigned", user_id, &role).await;
+    }
+    rbac::user_roles(&state.pool, user_id).await.map(Json).map_err(map_error)
+}
+
+pub async fn revoke_role(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Path((user_id, role)): Path<(Uuid, String)>,
+) -> RolesResult<UserRoles> {
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| roles_error(StatusCode::UNAUTHORIZED, "Authentication required"))?;
+
+    if !rbac::revoke(&state.pool, user_id, &role).await.map_err(internal)? {
+        return Err(roles_error(StatusCode::NOT_FOUND, "User does not have that role"));
+    }
+    log_activity(&state, admin_id, "role_revoked", user_id, &role).await;
+    rbac::user_roles(&state.pool, user_id).await.map(Json).map_err(map_error)
+}
@@ -0,0 +1,74 @@
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, Json};
+use uuid::Uuid;
+
+use crate::services::sessions::{self, SessionInfo};
+use crate::utils::jwt::{self, Claims};
+
+type SessionResult<T> = Result<Json<T>, (StatusCode, Json<serde_json::Value>)>;
+
+fn session_error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({"error": message})))
+}
+
+fn caller(headers: &HeaderMap) -> Result<Claims, (StatusCode, Json<serde_json::Value>)> {
+    jwt::extract_claims_from_headers(headers)
+        .map_err(|_| session_error(StatusCode::UNAUTHORIZED, "Authentication required"))
+}
+
+fn internal(e: anyhow::Error) -> (StatusCode, Json<serde_json::Value>) {
+    tracing::error!("Session error: {}", e);
+    session_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to process session request")
+}
+
+async fn log_activity(state: &crate::state::AppState, user_id: Uuid, action: &str, target_id: Uuid) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        action,
+        target_id,
+        "session"
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+/// Devices the caller is signed in on
+pub async fn list_sessions(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+) -> SessionResult<Vec<SessionInfo>> {
+    let claims = caller(&headers)?;
+    sessions::list(&state.pool, claims.sub, claims.sid).await.map(Json).map_err(internal)
+}
+
+/// Sign out of one device
+pub async fn revoke_session(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Path(session_id): Path<Uuid>,
+) -> SessionResult<serde_json::Value> {
+    let claims = caller(&headers)?;
+    if !sessions::revoke(&state.pool, claims.sub, session_id, "user_revoked").await.map_err(internal)? {
+        return Err(session_error(StatusCode::NOT_FOUND, "Session not found"));
+    }
+    log_activity(&state, claims.sub, "session_revoked", session_id).await;
+
+    Ok(Json(serde_json::json!({"revoked": session_id})))
+}
+
+/// Sign out everywhere except the device making the request
+pub async fn revoke_other_sessions(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+) -> SessionResult<serde_json::Value> {
+    let claims = caller(&headers)?;
+    let revoked = sessions::revoke_all(&state.pool, claims.sub, claims.sid, "user_revoked_all")
+        .await
+        .map_err(internal)?;
+    log_activity(&state, claims.sub, "sessions_revoked", claims.sub).await;
+
+    Ok(Json(serde_json::json!({"revoked": revoked})))
+}
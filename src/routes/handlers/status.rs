@@ -0,0 +1,74 @@
+use axum::{extract::State, http::{header, StatusCode}, response::IntoResponse, Json};
+
+use crate::services::worker_heartbeats::{self, WorkerStatus};
+use crate::state::AppState;
+use crate::workers::control::WORKER_NAMES;
+
+/// Number of routes and queries reported by the status endpoints
+const TOP_N: usize = 10;
+
+/// Prometheus-style metrics for route latency and slow queries
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let mut out = String::new();
+
+    out.push_str("# HELP fundhub_route_latency_ms Request latency percentiles per route\n");
+    out.push_str("# TYPE fundhub_route_latency_ms summary\n");
+    for route in state.latency.slowest_routes(usize::MAX) {
+        for (quantile, value) in [("0.5", route.p50_ms), ("0.95", route.p95_ms), ("0.99", route.p99_ms)] {
+            out.push_str(&format!(
+                "fundhub_route_latency_ms{{route=\"{}\",quantile=\"{}\"}} {}\n",
+                route.route, quantile, value
+            ));
+        }
+        out.push_str(&format!("fundhub_route_latency_ms_count{{route=\"{}\"}} {}\n", route.route, route.count));
+        out.push_str(&format!("fundhub_route_over_budget_total{{route=\"{}\"}} {}\n", route.route, route.over_budget));
+    }
+
+    out.push_str("# HELP fundhub_slow_queries_total Statements slower than the slow-query threshold\n");
+    out.push_str("# TYPE fundhub_slow_queries_total counter\n");
+    for query in state.latency.slowest_queries(TOP_N) {
+        out.push_str(&format!(
+            "fundhub_slow_queries_total{{fingerprint=\"{}\"}} {}\n",
+            query.fingerprint.replace('\\', "\\\\").replace('"', "\\\""),
+            query.count
+        ));
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out)
+}
+
+/// Each background worker's last run, last success and error, items
+/// processed, and how long the oldest item in its queue has waited
+pub async fn worker_status(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<WorkerStatus>>, StatusCode> {
+    worker_heartbeats::status(&state.pool, &state.worker_control)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("Failed to load worker status: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Admin status: slowest routes and queries, latency budget, and worker state
+pub async fn admin_status(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let workers: Vec<serde_json::Value> = WORKER_NAMES
+        .iter()
+        .map(|name| serde_json::json!({"name": name, "paused": state.worker_control.is_paused(name)}))
+        .collect();
+
+    Ok(Json(serde_json::json!({
+        "latency_budget_ms": state.latency.budget().as_millis() as u64,
+        "slowest_routes": state.latency.slowest_routes(TOP_N),
+        "slowest_queries": state.latency.slowest_queries(TOP_N),
+        "workers": workers,
+        "db_pool": {
+            "size": state.pool.size(),
+            "idle": state.pool.num_idle(),
+        },
+        "worker_dry_run": state.worker_dry_run,
+    })))
+}
@@ -2,23 +2,33 @@ use axum::{extract::{Json, State, Path, Multipart}, http::StatusCode};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::Utc;
+use validator::Validate;
 
 use crate::models::{
     Student, StudentVerification, StudentVerificationRequest, EnhancedStudentVerificationRequest,
     VerificationStatus, VerificationStatusResponse, StudentProfile, VerificationHistory,
     ApproveVerificationRequest, RejectVerificationRequest, VerificationResponse
 };
+use crate::routes::validation::{self, ValidatedJson};
+use crate::services::storage::{self, NewFile, StoredFile, StoredObject};
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::school_email_otp::{self, CodeError, OtpError};
+use crate::services::university_domains;
+use crate::services::{preapproved_admissions, verification_decisions};
 
 #[derive(Serialize)]
 pub struct ApiMessage { 
     pub message: String 
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct RegisterRequest { 
     pub user_id: Uuid,
+    #[validate(email(message = "Enter a valid email address"))]
     pub school_email: String,
+    #[validate(custom(function = "validation::not_blank"))]
     pub admission_number: String,
+    #[validate(custom(function = "validation::urls"))]
     pub document_urls: Option<Vec<String>>, // For pre-uploaded S3/MinIO URLs
 }
 
@@ -43,11 +53,11 @@ pub struct UploadDocumentsRequest {
 
 pub async fn register(
     State(state): State<crate::state::AppState>,
-    Json(req): Json<RegisterRequest>,
+    ValidatedJson(req): ValidatedJson<RegisterRequest>,
 ) -> Result<(StatusCode, Json<RegisterResponse>), StatusCode> {
     // Check if user exists
     let user = sqlx::query!(
-        r#"SELECT id FROM users WHERE id = $1"#,
+        r#"SELECT id, status FROM users WHERE id = $1"#,
         req.user_id
     )
     .fetch_optional(&state.pool)
@@ -55,6 +65,11 @@ pub async fn register(
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
     .ok_or(StatusCode::NOT_FOUND)?;
 
+    // Becoming a student needs a confirmed email
+    if user.status == "pending_email_verification" {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     // Check if student already exists
     let existing = sqlx::query!(
         r#"SELECT id FROM students WHERE user_id = $1"#,
@@ -157,74 +172,158 @@ pub async fn update(
     })
 }
 
-// Upload document endpoint (multipart form data)
+/// Largest verification document that can be uploaded
+pub const MAX_DOCUMENT_BYTES: i64 = 20 * 1024 * 1024;
+
+/// A document streamed to storage, not yet recorded
+struct DocumentUpload {
+    key: String,
+    filename: String,
+    mime_type: String,
+    stored: StoredObject,
+}
+
+/// Upload a verification document as multipart form data: `student_id` (or,
+/// while an application is pending, `verification_id`), `document_type` and
+/// the `file`, which is streamed to object storage as it arrives. Students
+/// upload their own; admins anyone's.
 pub async fn upload_document(
     State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
     mut multipart: Multipart,
-) -> Result<Json<ApiMessage>, StatusCode> {
-    let mut student_id: Option<Uuid> = None;
+) -> Result<(StatusCode, Json<StoredFile>), StatusCode> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    let storage = state.storage.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let mut student_id: Option<String> = None;
+    let mut verification_id: Option<String> = None;
     let mut document_type: Option<String> = None;
-    let mut file_data: Vec<u8> = Vec::new();
-    let mut filename: Option<String> = None;
+    let mut upload: Option<DocumentUpload> = None;
 
     // Parse multipart form
-    while let Some(field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+    while let Some(mut field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
         let field_name = field.name().unwrap_or("").to_string();
-        
+
         match field_name.as_str() {
             "student_id" => {
-                let text = field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?;
-                student_id = Some(text.parse().map_err(|_| StatusCode::BAD_REQUEST)?);
+                student_id = Some(field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?);
+            }
+            "verification_id" => {
+                verification_id = Some(field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?);
             }
             "document_type" => {
                 document_type = Some(field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?);
             }
-            "file" => {
-                filename = field.file_name().map(|s| s.to_string());
-                file_data = field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?.to_vec();
+            "file" if upload.is_none() => {
+                let filename = field.file_name().unwrap_or("document").to_string();
+                let mime_type = field.content_type().unwrap_or("application/octet-stream").to_string();
+                let key = storage::object_key("students/documents", Uuid::new_v4(), &filename);
+
+                let mut writer = storage.writer(&key, &mime_type);
+                loop {
+                    let chunk = match field.chunk().await {
+                        Ok(Some(chunk)) => chunk,
+                        Ok(None) => break,
+                        Err(_) => {
+                            writer.abort().await;
+                            return Err(StatusCode::BAD_REQUEST);
+                        }
+                    };
+                    if writer.size() + chunk.len() as i64 > MAX_DOCUMENT_BYTES {
+                        writer.abort().await;
+                        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+                    }
+                    writer.write(&chunk).await.map_err(|e| {
+                        tracing::error!("Failed to upload document {}: {}", key, e);
+                        StatusCode::BAD_GATEWAY
+                    })?;
+                }
+                let stored = writer.finish().await.map_err(|e| {
+                    tracing::error!("Failed to upload document {}: {}", key, e);
+                    StatusCode::BAD_GATEWAY
+                })?;
+                upload = Some(DocumentUpload { key, filename, mime_type, stored });
             }
             _ => {}
         }
     }
 
-    let student_id = student_id.ok_or(StatusCode::BAD_REQUEST)?;
-    let document_type = document_type.ok_or(StatusCode::BAD_REQUEST)?;
-    let filename = filename.ok_or(StatusCode::BAD_REQUEST)?;
+    let upload = upload.ok_or(StatusCode::BAD_REQUEST)?;
+    let recorded = record_document(&state, &headers, user_id, student_id, verification_id, document_type, &upload).await;
+    if recorded.is_err() {
+        // Don't keep what can't be recorded
+        if let Err(e) = storage.delete(&upload.key).await {
+            tracing::warn!("Failed to delete unrecorded document {}: {}", upload.key, e);
+        }
+    }
+    Ok((StatusCode::CREATED, Json(recorded?)))
+}
 
-    // TODO: Upload to MinIO/S3
-    // For now, store file path as local reference
-    let file_path = format!("/uploads/students/{}/{}", student_id, filename);
-    
-    // Get student user_id
-    let student = sqlx::query!(
-        r#"SELECT user_id FROM students WHERE id = $1"#,
-        student_id
-    )
-    .fetch_optional(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-    .ok_or(StatusCode::NOT_FOUND)?;
+/// Check the uploader may add the student's document and record it in `files`,
+/// attached to the student or their verification
+async fn record_document(
+    state: &crate::state::AppState,
+    headers: &axum::http::HeaderMap,
+    user_id: Uuid,
+    student_id: Option<String>,
+    verification_id: Option<String>,
+    document_type: Option<String>,
+    upload: &DocumentUpload,
+) -> Result<StoredFile, StatusCode> {
+    let parse = |id: Option<String>| id.map(|id| id.trim().parse::<Uuid>().map_err(|_| StatusCode::BAD_REQUEST)).transpose();
+    let (student_id, verification_id) = (parse(student_id)?, parse(verification_id)?);
+    let document_type = document_type
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty() && t.len() <= 50)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    // Whose document it is, and what it's attached to
+    let (owner_id, entity_id) = match (student_id, verification_id) {
+        (Some(student_id), None) => {
+            let owner_id = sqlx::query_scalar!(r#"SELECT user_id FROM students WHERE id = $1"#, student_id)
+                .fetch_optional(&state.pool)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::NOT_FOUND)?;
+            (owner_id, student_id)
+        }
+        (None, Some(verification_id)) => {
+            let owner_id = sqlx::query_scalar!(
+                r#"SELECT user_id FROM student_verifications WHERE id = $1"#,
+                verification_id
+            )
+            .fetch_optional(&state.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+            (owner_id, verification_id)
+        }
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
 
-    // Save file record
-    sqlx::query!(
-        r#"
-        INSERT INTO files (owner_id, entity_type, entity_id, path, filename, size_bytes)
-        VALUES ($1, $2, $3, $4, $5, $6)
-        "#,
-        student.user_id,
-        document_type,
-        student_id,
-        file_path,
-        filename,
-        file_data.len() as i64
-    )
-    .execute(&state.pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if owner_id != user_id && !crate::utils::roles::caller_is_admin(&state.pool, headers).await {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
-    Ok(Json(ApiMessage {
-        message: "Document uploaded successfully".to_string(),
-    }))
+    // Save file record
+    let file = NewFile {
+        owner_id,
+        entity_type: &document_type,
+        entity_id,
+        key: &upload.key,
+        filename: &upload.filename,
+        mime_type: &upload.mime_type,
+        size_bytes: upload.stored.size_bytes,
+        checksum: Some(&upload.stored.checksum),
+    };
+    let file_id = storage::record_file(&state.pool, &file)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    storage::find_file(&state.pool, file_id)
+        .await
+        .ok()
+        .flatten()
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)
 }
 
 /// Apply for enhanced student verification
@@ -235,6 +334,7 @@ pub async fn upload_document(
     responses(
         (status = 201, description = "Verification application submitted successfully", body = StudentVerification),
         (status = 400, description = "Invalid request data"),
+        (status = 422, description = "School email isn't at a recognised university domain"),
         (status = 409, description = "Verification already exists"),
         (status = 500, description = "Internal server error")
     ),
@@ -243,7 +343,7 @@ pub async fn upload_document(
 pub async fn apply_verification(
     State(state): State<crate::state::AppState>,
     headers: axum::http::HeaderMap,
-    Json(payload): Json<EnhancedStudentVerificationRequest>,
+    ValidatedJson(payload): ValidatedJson<EnhancedStudentVerificationRequest>,
 ) -> Result<(StatusCode, Json<StudentVerification>), (StatusCode, Json<serde_json::Value>)> {
     // Get user ID from the authenticated user (extracted from JWT token)
     let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
@@ -253,11 +353,19 @@ pub async fn apply_verification(
                 Json(serde_json::json!({"error": "Invalid or missing authentication token"})),
             )
         })?;
-    // Validate school email format
-    if !payload.school_email.ends_with(".edu") && !payload.school_email.ends_with(".ac.ke") {
+    let status = sqlx::query_scalar!("SELECT status FROM users WHERE id = $1", user_id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": "Database error"})),
+            )
+        })?;
+    if status.as_deref() == Some("pending_email_verification") {
         return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": "School email must end with .edu or .ac.ke"})),
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "Verify your email address before applying"})),
         ));
     }
 
@@ -284,12 +392,32 @@ pub async fn apply_verification(
         ));
     }
 
+    // The school's domain has to be on the list
+    university_domains::lookup(&state.pool, &payload.school_email)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": "Database error"})),
+            )
+        })?
+        .filter(|d| d.policy != university_domains::POLICY_BLOCKED)
+        .ok_or_else(|| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(serde_json::json!({
+                    "error": "School email must be at a recognised university domain",
+                    "field": "school_email"
+                })),
+            )
+        })?;
+
     // Create verification request with enhanced fields
     let verification = sqlx::query_as!(
         StudentVerification,
         r#"
-        INSERT INTO student_verifications (user_id, school_email, full_name, school_name, student_bio, motivation_text, status, submitted_at)
-        VALUES ($1, $2, $3, $4, $5, $6, 'pending', CURRENT_TIMESTAMP)
+        INSERT INTO student_verifications (user_id, school_email, full_name, school_name, student_bio, motivation_text, admission_number, status, submitted_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, 'pending', CURRENT_TIMESTAMP)
         RETURNING id, user_id, school_email, full_name, school_name, student_bio, motivation_text, status as "status!: VerificationStatus", admin_message, approved_at, submitted_at, created_at as "created_at!: chrono::DateTime<chrono::Utc>"
         "#,
         user_id,
@@ -297,7 +425,8 @@ pub async fn apply_verification(
         payload.full_name,
         payload.school_name,
         payload.student_bio,
-        payload.motivation_text
+        payload.motivation_text,
+        payload.admission_number.as_deref().map(str::trim).filter(|n| !n.is_empty())
     )
     .fetch_one(&state.pool)
     .await
@@ -374,9 +503,155 @@ pub async fn apply_verification(
     .execute(&state.pool)
     .await;
 
+    // It waits for the applicant to confirm they own the school address
+    if let Err(e) = school_email_otp::send(&state.pool, verification.id).await {
+        tracing::error!("Failed to send a school email code for {}: {}", verification.id, e);
+    }
+
     Ok((StatusCode::CREATED, Json(verification)))
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct ConfirmSchoolEmailRequest {
+    #[validate(length(min = 1, max = 12, message = "Enter the code from the email"))]
+    pub code: String,
+}
+
+fn otp_error(e: OtpError) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match &e {
+        OtpError::NotFound => StatusCode::NOT_FOUND,
+        OtpError::CoolingDown(_) => StatusCode::TOO_MANY_REQUESTS,
+        OtpError::Code(CodeError::AlreadyConfirmed) => StatusCode::CONFLICT,
+        OtpError::Code(CodeError::TooManyAttempts) => StatusCode::TOO_MANY_REQUESTS,
+        OtpError::Code(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        OtpError::Internal(e) => {
+            tracing::error!("School email confirmation failed: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "Internal error"})));
+        }
+    };
+    (status, Json(serde_json::json!({"error": e.to_string()})))
+}
+
+/// The caller's latest verification application
+async fn own_verification(
+    state: &crate::state::AppState,
+    headers: &axum::http::HeaderMap,
+) -> Result<StudentVerification, (StatusCode, Json<serde_json::Value>)> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(headers).map_err(|_| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "Invalid or missing authentication token"})),
+        )
+    })?;
+    sqlx::query_as!(
+        StudentVerification,
+        r#"
+        SELECT id, user_id, school_email, full_name, school_name, student_bio, motivation_text,
+               status as "status!: VerificationStatus", admin_message, approved_at, submitted_at,
+               created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM student_verifications
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        user_id
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({"error": "Database error"}))))?
+    .ok_or_else(|| otp_error(OtpError::NotFound))
+}
+
+/// Approve a confirmed application straight away when the school vouched
+/// for its admission number, or its school's domain is trusted to
+async fn auto_verify_if_trusted(state: &crate::state::AppState, verification: &mut StudentVerification) {
+    let approved = match preapproved_admissions::match_verification(&state.pool, verification.id).await {
+        Ok(Some(_)) => Some(preapproved_admissions::MATCH_MESSAGE.to_string()),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::error!("Failed to match {} against pre-approved admissions: {}", verification.id, e);
+            None
+        }
+    };
+    let approved = match approved {
+        Some(message) => message,
+        None => {
+            let domain = match university_domains::lookup(&state.pool, &verification.school_email).await {
+                Ok(Some(domain)) if domain.policy == university_domains::POLICY_AUTO_VERIFY => domain,
+                Ok(_) => return,
+                Err(e) => {
+                    tracing::error!("Failed to look up the school domain for {}: {}", verification.id, e);
+                    return;
+                }
+            };
+            let message = format!("Verified automatically as a student of {}", domain.domain);
+            match university_domains::auto_verify(&state.pool, verification.id, &message).await {
+                Ok(true) => message,
+                Ok(false) => return,
+                Err(e) => {
+                    // Left in the admin queue
+                    tracing::error!("Failed to auto-verify {}: {}", verification.id, e);
+                    return;
+                }
+            }
+        }
+    };
+
+    verification.status = VerificationStatus::Verified;
+    verification.admin_message = Some(approved.clone());
+    verification.approved_at = Some(Utc::now());
+    let event = NotificationEvent::VerificationStatus {
+        user_id: verification.user_id,
+        status: "verified".to_string(),
+        reason: None,
+    };
+    state.notifier.send(Channel::User(verification.user_id), event);
+    verification_decisions::announce(&state.pool, verification.id, verification.user_id, true, Some(approved)).await;
+}
+
+/// Confirm the school email with the emailed code, which puts the
+/// application in the admin queue, or approves it if the school's domain is
+/// set to auto-verify
+pub async fn confirm_school_email(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+    ValidatedJson(req): ValidatedJson<ConfirmSchoolEmailRequest>,
+) -> Result<Json<StudentVerification>, (StatusCode, Json<serde_json::Value>)> {
+    let mut verification = own_verification(&state, &headers).await?;
+    school_email_otp::confirm(&state.pool, verification.id, &req.code)
+        .await
+        .map_err(otp_error)?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        verification.user_id,
+        "school_email_confirmed",
+        verification.id,
+        "student_verification",
+        serde_json::json!({"school_email": verification.school_email})
+    )
+    .execute(&state.pool)
+    .await;
+
+    auto_verify_if_trusted(&state, &mut verification).await;
+    Ok(Json(verification))
+}
+
+/// Email a new school email code, at most once a minute
+pub async fn resend_school_email_code(
+    State(state): State<crate::state::AppState>,
+    headers: axum::http::HeaderMap,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    let verification = own_verification(&state, &headers).await?;
+    school_email_otp::send(&state.pool, verification.id)
+        .await
+        .map_err(otp_error)?;
+    Ok(StatusCode::ACCEPTED)
+}
+
 /// Get enhanced verification status for a user
 #[utoipa::path(
     get,
@@ -474,7 +749,7 @@ pub async fn get_student_profile(
                created_at as "created_at!: chrono::DateTime<chrono::Utc>",
                updated_at as "updated_at!: chrono::DateTime<chrono::Utc>"
         FROM student_profiles
-        WHERE user_id = $1
+        WHERE user_id = $1 AND hidden_at IS NULL
         "#,
         user_id
     )
@@ -511,7 +786,7 @@ pub async fn get_student_profile(
 pub async fn update_student_profile(
     State(state): State<crate::state::AppState>,
     Path(user_id): Path<Uuid>,
-    Json(payload): Json<StudentProfile>,
+    ValidatedJson(payload): ValidatedJson<StudentProfile>,
 ) -> Result<Json<StudentProfile>, (StatusCode, Json<serde_json::Value>)> {
     let profile = sqlx::query_as!(
         StudentProfile,
@@ -0,0 +1,144 @@
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, Json};
+use bigdecimal::BigDecimal;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::services::flags::RecurringDonations;
+use crate::services::subscriptions::{
+    self, CreatedSubscription, NewSubscription, ProjectSubscriptionAnalytics, Subscription, SubscriptionError,
+};
+use crate::utils::feature_gate::RequireFlag;
+
+type SubscriptionResult<T> = Result<Json<T>, (StatusCode, Json<serde_json::Value>)>;
+
+fn subscription_error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({"error": message})))
+}
+
+fn caller(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<serde_json::Value>)> {
+    crate::utils::jwt::extract_user_id_from_headers(headers)
+        .map_err(|_| subscription_error(StatusCode::UNAUTHORIZED, "Authentication required"))
+}
+
+fn map_error(e: SubscriptionError) -> (StatusCode, Json<serde_json::Value>) {
+    match e {
+        SubscriptionError::NotFound => subscription_error(StatusCode::NOT_FOUND, "Subscription not found"),
+        SubscriptionError::Invalid(message) => subscription_error(StatusCode::BAD_REQUEST, &message),
+        SubscriptionError::Provider(message) => {
+            tracing::warn!("Provider refused subscription setup: {}", message);
+            subscription_error(StatusCode::BAD_GATEWAY, &message)
+        }
+        SubscriptionError::Internal(e) => {
+            tracing::error!("Subscription error: {}", e);
+            subscription_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to process subscription")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateSubscriptionRequest {
+    pub project_id: Uuid,
+    /// Monthly amount: fiat for `stripe`, XLM for `stellar`
+    pub amount: BigDecimal,
+    pub currency: String,
+    /// `stripe` or `stellar`
+    pub payment_method: String,
+    pub donor_email: Option<String>,
+}
+
+/// Start a monthly gift; card subscriptions return a page that saves the card
+pub async fn create_subscription(
+    State(state): State<crate::state::AppState>,
+    _: RequireFlag<RecurringDonations>,
+    headers: HeaderMap,
+    Json(req): Json<CreateSubscriptionRequest>,
+) -> Result<(StatusCode, Json<CreatedSubscription>), (StatusCode, Json<serde_json::Value>)> {
+    let donor_id = caller(&headers)?;
+    let created = subscriptions::create(
+        &state.pool,
+        &state.payment_providers,
+        donor_id,
+        NewSubscription {
+            project_id: req.project_id,
+            amount: req.amount,
+            currency: req.currency,
+            payment_method: req.payment_method,
+            donor_email: req.donor_email,
+        },
+    )
+    .await
+    .map_err(map_error)?;
+
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// The caller's subscriptions
+pub async fn list_subscriptions(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+) -> SubscriptionResult<Vec<Subscription>> {
+    let donor_id = caller(&headers)?;
+    subscriptions::list_for_donor(&state.pool, donor_id)
+        .await
+        .map(Json)
+        .map_err(|e| map_error(e.into()))
+}
+
+pub async fn cancel_subscription(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SubscriptionResult<Subscription> {
+    let donor_id = caller(&headers)?;
+    let subscription = subscriptions::cancel(&state.pool, donor_id, id).await.map_err(map_error)?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        donor_id,
+        "subscription_cancelled",
+        subscription.id,
+        "donation_subscription",
+        serde_json::json!({ "project_id": subscription.project_id })
+    )
+    .execute(&state.pool)
+    .await;
+
+    Ok(Json(subscription))
+}
+
+/// Recurring support for a project (owner or admin)
+pub async fn project_subscription_analytics(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Path(project_id): Path<Uuid>,
+) -> SubscriptionResult<ProjectSubscriptionAnalytics> {
+    let user_id = caller(&headers)?;
+    let access = sqlx::query!(
+        r#"
+        SELECT u.role,
+               EXISTS(
+                   SELECT 1 FROM projects p JOIN students s ON s.id = p.student_id
+                   WHERE p.id = $2 AND s.user_id = u.id
+               ) as "is_owner!"
+        FROM users u
+        WHERE u.id = $1
+        "#,
+        user_id,
+        project_id
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| map_error(SubscriptionError::from(e)))?
+    .ok_or_else(|| subscription_error(StatusCode::UNAUTHORIZED, "Authentication required"))?;
+    if access.role != "admin" && !access.is_owner {
+        return Err(subscription_error(StatusCode::FORBIDDEN, "Only the project owner can view its subscriptions"));
+    }
+
+    subscriptions::project_analytics(&state.pool, project_id)
+        .await
+        .map(Json)
+        .map_err(|e| map_error(e.into()))
+}
@@ -0,0 +1,125 @@
+use axum::{extract::{ConnectInfo, State}, http::{HeaderMap, StatusCode}, Json};
+use std::net::SocketAddr;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::auth::{issue_session, AuthResponse};
+use crate::services::two_factor::{self, TwoFactorError, TwoFactorSetup, TwoFactorStatus};
+
+type TwoFactorResult<T> = Result<Json<T>, (StatusCode, Json<serde_json::Value>)>;
+
+fn two_factor_error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({"error": message})))
+}
+
+fn caller(headers: &HeaderMap) -> Result<Uuid, (StatusCode, Json<serde_json::Value>)> {
+    crate::utils::jwt::extract_user_id_from_headers(headers)
+        .map_err(|_| two_factor_error(StatusCode::UNAUTHORIZED, "Authentication required"))
+}
+
+fn map_error(e: TwoFactorError) -> (StatusCode, Json<serde_json::Value>) {
+    match e {
+        TwoFactorError::NotAllowed => two_factor_error(StatusCode::FORBIDDEN, &e.to_string()),
+        TwoFactorError::AlreadyEnabled | TwoFactorError::NotEnrolled => {
+            two_factor_error(StatusCode::CONFLICT, &e.to_string())
+        }
+        TwoFactorError::InvalidCode | TwoFactorError::InvalidChallenge => {
+            two_factor_error(StatusCode::UNAUTHORIZED, &e.to_string())
+        }
+        TwoFactorError::Internal(e) => {
+            tracing::error!("Two-factor error: {}", e);
+            two_factor_error(StatusCode::INTERNAL_SERVER_ERROR, "Failed to process two-factor request")
+        }
+    }
+}
+
+async fn log_activity(state: &crate::state::AppState, user_id: Uuid, action: &str) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        action,
+        user_id,
+        "user"
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+#[derive(Deserialize)]
+pub struct CodeRequest {
+    /// Current authenticator code, or a backup code where accepted
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyLoginRequest {
+    pub pre_auth_token: String,
+    /// Authenticator code or a backup code
+    pub code: String,
+}
+
+pub async fn two_factor_status(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+) -> TwoFactorResult<TwoFactorStatus> {
+    let user_id = caller(&headers)?;
+    two_factor::status(&state.pool, user_id).await.map(Json).map_err(map_error)
+}
+
+/// Begin enrollment: a new secret as an otpauth URI and QR code
+pub async fn setup_two_factor(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+) -> TwoFactorResult<TwoFactorSetup> {
+    let user_id = caller(&headers)?;
+    two_factor::setup(&state.pool, user_id).await.map(Json).map_err(map_error)
+}
+
+/// Confirm enrollment with a code; returns the one-time view of the backup codes
+pub async fn enable_two_factor(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CodeRequest>,
+) -> TwoFactorResult<serde_json::Value> {
+    let user_id = caller(&headers)?;
+    let backup_codes = two_factor::enable(&state.pool, user_id, &req.code).await.map_err(map_error)?;
+    log_activity(&state, user_id, "two_factor_enabled").await;
+
+    Ok(Json(serde_json::json!({
+        "enabled": true,
+        "backup_codes": backup_codes
+    })))
+}
+
+pub async fn disable_two_factor(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CodeRequest>,
+) -> TwoFactorResult<serde_json::Value> {
+    let user_id = caller(&headers)?;
+    two_factor::disable(&state.pool, user_id, &req.code).await.map_err(map_error)?;
+    log_activity(&state, user_id, "two_factor_disabled").await;
+
+    Ok(Json(serde_json::json!({"enabled": false})))
+}
+
+/// Second step of login: trade the pre-auth token and a code for a session
+pub async fn verify_two_factor_login(
+    State(state): State<crate::state::AppState>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(req): Json<VerifyLoginRequest>,
+) -> TwoFactorResult<AuthResponse> {
+    let user_id = two_factor::complete_challenge(&state.pool, &req.pre_auth_token, &req.code)
+        .await
+        .map_err(map_error)?;
+
+    let client = crate::services::sessions::ClientInfo::from_request(&headers, peer.map(|ConnectInfo(addr)| addr));
+    issue_session(&state.pool, user_id, &client).await.map(Json).map_err(|e| {
+        tracing::error!("Failed to issue session after two-factor login for {}: {:?}", user_id, e);
+        two_factor_error(e.status(), "Failed to sign in")
+    })
+}
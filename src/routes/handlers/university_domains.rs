@@ -0,0 +1,117 @@
+use axum::{extract::{Path, State}, http::{HeaderMap, StatusCode}, Json};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::ValidatedJson;
+use crate::services::university_domains::{self, DomainChange, UniversityDomain, POLICIES, POLICY_MANUAL};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UniversityDomainRequest {
+    /// Exact, like `uonbi.ac.ke`, or `*.` and a parent to cover its subdomains
+    pub domain: String,
+    #[validate(length(max = 255, message = "University name must be at most 255 characters"))]
+    pub university_name: Option<String>,
+    /// `manual` (the default), `auto_verify` or `blocked`
+    pub policy: Option<String>,
+    pub is_active: Option<bool>,
+}
+
+/// Check a request and turn it into a change, normalizing the domain
+fn change<'a>(req: &'a UniversityDomainRequest, domain: &'a str) -> AppResult<DomainChange<'a>> {
+    let policy = req.policy.as_deref().map(str::trim).unwrap_or(POLICY_MANUAL);
+    let policy = POLICIES
+        .iter()
+        .find(|p| **p == policy)
+        .ok_or_else(|| AppError::invalid("policy", format!("Policy must be one of {}", POLICIES.join(", "))))?;
+    Ok(DomainChange {
+        domain,
+        university_name: req.university_name.as_deref().map(str::trim).filter(|n| !n.is_empty()),
+        policy,
+        is_active: req.is_active.unwrap_or(true),
+    })
+}
+
+fn normalized(req: &UniversityDomainRequest) -> AppResult<String> {
+    university_domains::normalize_pattern(&req.domain)
+        .ok_or_else(|| AppError::invalid("domain", "Enter a domain like uonbi.ac.ke, or *.ac.ke for its subdomains"))
+}
+
+async fn log_activity(state: &crate::state::AppState, admin_id: Uuid, action: &str, domain: &UniversityDomain) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        action,
+        domain.id,
+        "university_domain",
+        serde_json::json!({"domain": domain.domain, "policy": domain.policy, "is_active": domain.is_active})
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+fn admin(headers: &HeaderMap) -> AppResult<Uuid> {
+    crate::utils::jwt::extract_user_id_from_headers(headers).map_err(|_| AppError::unauthorized("Authentication required"))
+}
+
+/// Every school domain, active or not, alphabetically
+pub async fn list_university_domains(
+    State(state): State<crate::state::AppState>,
+) -> AppResult<Json<Vec<UniversityDomain>>> {
+    Ok(Json(university_domains::list(&state.pool).await?))
+}
+
+pub async fn create_university_domain(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<UniversityDomainRequest>,
+) -> AppResult<(StatusCode, Json<UniversityDomain>)> {
+    let admin_id = admin(&headers)?;
+    let domain = normalized(&req)?;
+    let created = university_domains::create(&state.pool, &change(&req, &domain)?, admin_id)
+        .await?
+        .ok_or_else(|| AppError::conflict(format!("{} is already listed", domain)))?;
+
+    log_activity(&state, admin_id, "university_domain_created", &created).await;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// Replace an entry; fields left out go back to their defaults
+pub async fn update_university_domain(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    ValidatedJson(req): ValidatedJson<UniversityDomainRequest>,
+) -> AppResult<Json<UniversityDomain>> {
+    let admin_id = admin(&headers)?;
+    let domain = normalized(&req)?;
+    let updated = match university_domains::update(&state.pool, id, &change(&req, &domain)?).await {
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(AppError::conflict(format!("{} is already listed", domain)))
+        }
+        result => result?,
+    }
+    .ok_or_else(|| AppError::not_found("University domain not found"))?;
+
+    log_activity(&state, admin_id, "university_domain_updated", &updated).await;
+    Ok(Json(updated))
+}
+
+/// Applications already made from the domain are unaffected
+pub async fn delete_university_domain(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<UniversityDomain>> {
+    let admin_id = admin(&headers)?;
+    let deleted = university_domains::delete(&state.pool, id)
+        .await?
+        .ok_or_else(|| AppError::not_found("University domain not found"))?;
+
+    log_activity(&state, admin_id, "university_domain_deleted", &deleted).await;
+    Ok(Json(deleted))
+}
@@ -0,0 +1,197 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::state::AppState;
+use crate::utils::usage::RateTier;
+
+/// Longest window a usage report can cover
+const MAX_USAGE_DAYS: i64 = 90;
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    /// Days of history to include, counting today (default 7)
+    pub days: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EndpointUsage {
+    pub endpoint: String,
+    pub requests: i64,
+    pub errors: i64,
+    pub avg_latency_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyUsage {
+    pub day: NaiveDate,
+    pub requests: i64,
+    pub errors: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageReport {
+    pub user_id: Uuid,
+    pub days: i64,
+    pub requests: i64,
+    pub errors: i64,
+    pub error_rate: f64,
+    pub suggested_tier: RateTier,
+    pub endpoints: Vec<EndpointUsage>,
+    pub daily: Vec<DailyUsage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserUsageSummary {
+    pub user_id: Uuid,
+    pub username: Option<String>,
+    pub requests: i64,
+    pub errors: i64,
+    pub error_rate: f64,
+    pub suggested_tier: RateTier,
+}
+
+fn usage_days(query: &UsageQuery) -> i64 {
+    query.days.unwrap_or(7).clamp(1, MAX_USAGE_DAYS)
+}
+
+fn error_rate(requests: i64, errors: i64) -> f64 {
+    if requests > 0 {
+        errors as f64 / requests as f64
+    } else {
+        0.0
+    }
+}
+
+async fn usage_report(pool: &sqlx::PgPool, user_id: Uuid, days: i64) -> Result<UsageReport, sqlx::Error> {
+    let since = Utc::now().date_naive() - chrono::Duration::days(days - 1);
+
+    let endpoints = sqlx::query!(
+        r#"
+        SELECT endpoint,
+               SUM(requests)::BIGINT as "requests!",
+               SUM(errors)::BIGINT as "errors!",
+               SUM(total_latency_ms)::BIGINT as "total_latency_ms!"
+        FROM api_usage_daily
+        WHERE user_id = $1 AND day >= $2
+        GROUP BY endpoint
+        ORDER BY 2 DESC
+        "#,
+        user_id,
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let daily = sqlx::query_as!(
+        DailyUsage,
+        r#"
+        SELECT day, SUM(requests)::BIGINT as "requests!", SUM(errors)::BIGINT as "errors!"
+        FROM api_usage_daily
+        WHERE user_id = $1 AND day >= $2
+        GROUP BY day
+        ORDER BY day
+        "#,
+        user_id,
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let requests: i64 = daily.iter().map(|d| d.requests).sum();
+    let errors: i64 = daily.iter().map(|d| d.errors).sum();
+
+    Ok(UsageReport {
+        user_id,
+        days,
+        requests,
+        errors,
+        error_rate: error_rate(requests, errors),
+        suggested_tier: RateTier::suggest(requests, errors, days),
+        endpoints: endpoints
+            .into_iter()
+            .map(|e| EndpointUsage {
+                avg_latency_ms: if e.requests > 0 { e.total_latency_ms as f64 / e.requests as f64 } else { 0.0 },
+                endpoint: e.endpoint,
+                requests: e.requests,
+                errors: e.errors,
+            })
+            .collect(),
+        daily,
+    })
+}
+
+/// The caller's own API usage
+pub async fn my_usage(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageReport>, StatusCode> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    usage_report(&state.pool, user_id, usage_days(&query))
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Busiest API users with their suggested rate-limit tier (admin only)
+pub async fn list_usage(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<Vec<UserUsageSummary>>, StatusCode> {
+    let days = usage_days(&query);
+    let since = Utc::now().date_naive() - chrono::Duration::days(days - 1);
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT a.user_id, u.username as "username?",
+               SUM(a.requests)::BIGINT as "requests!",
+               SUM(a.errors)::BIGINT as "errors!"
+        FROM api_usage_daily a
+        LEFT JOIN users u ON u.id = a.user_id
+        WHERE a.day >= $1
+        GROUP BY a.user_id, u.username
+        ORDER BY 3 DESC
+        LIMIT $2
+        "#,
+        since,
+        limit
+    )
+    .fetch_all(&state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(
+        rows.into_iter()
+            .map(|r| UserUsageSummary {
+                user_id: r.user_id,
+                username: r.username,
+                requests: r.requests,
+                errors: r.errors,
+                error_rate: error_rate(r.requests, r.errors),
+                suggested_tier: RateTier::suggest(r.requests, r.errors, days),
+            })
+            .collect(),
+    ))
+}
+
+/// Full usage report for one user (admin only)
+pub async fn user_usage(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageReport>, StatusCode> {
+    usage_report(&state.pool, user_id, usage_days(&query))
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
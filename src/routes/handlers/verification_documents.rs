@@ -0,0 +1,104 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::ValidatedJson;
+use crate::services::storage::PresignedUrl;
+use crate::services::verification_documents::{self, Decision, ReviewError, VerificationDocument};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReviewDocumentRequest {
+    pub decision: Decision,
+    #[validate(length(max = 1000, message = "Note must be at most 1000 characters"))]
+    pub note: Option<String>,
+}
+
+/// A document with a link to view it, when it's in storage and scanned clean
+#[derive(Debug, Serialize)]
+pub struct ReviewableDocument {
+    #[serde(flatten)]
+    pub document: VerificationDocument,
+    pub download: Option<PresignedUrl>,
+}
+
+fn reviewable(state: &AppState, document: VerificationDocument) -> ReviewableDocument {
+    let download = match (&state.storage, document.storage.as_str(), document.scan_status.as_str()) {
+        (Some(storage), "object", "clean") => Some(storage.download_url(&document.key, &document.filename)),
+        _ => None,
+    };
+    ReviewableDocument { document, download }
+}
+
+async fn applicant(state: &AppState, verification_id: Uuid) -> AppResult<Uuid> {
+    verification_documents::applicant(&state.pool, verification_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Verification not found"))
+}
+
+/// Approving a student needs at least one of their documents accepted
+pub(crate) async fn require_accepted_document(state: &AppState, user_id: Uuid) -> AppResult<()> {
+    if verification_documents::has_accepted(&state.pool, user_id).await? {
+        return Ok(());
+    }
+    Err(AppError::Coded {
+        status: StatusCode::CONFLICT,
+        code: "documents_not_accepted",
+        message: "Accept at least one of the student's documents before approving".to_string(),
+        details: None,
+    })
+}
+
+/// The documents a student submitted for verification, with their reviews
+/// and links to view them (admin)
+pub async fn list_verification_documents(
+    State(state): State<AppState>,
+    Path(verification_id): Path<Uuid>,
+) -> AppResult<Json<Vec<ReviewableDocument>>> {
+    let user_id = applicant(&state, verification_id).await?;
+    let documents = verification_documents::documents(&state.pool, user_id).await?;
+    Ok(Json(documents.into_iter().map(|d| reviewable(&state, d)).collect()))
+}
+
+/// Accept or reject one of a student's documents, with an optional note;
+/// only documents scanned clean can be accepted (admin)
+pub async fn review_verification_document(
+    State(state): State<AppState>,
+    Path((verification_id, file_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<ReviewDocumentRequest>,
+) -> AppResult<Json<ReviewableDocument>> {
+    let admin_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+    let user_id = applicant(&state, verification_id).await?;
+    let note = req.note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+
+    let document = verification_documents::review(&state.pool, user_id, file_id, req.decision, note.as_deref(), admin_id)
+        .await
+        .map_err(|e| match e {
+            ReviewError::NotClean => AppError::conflict(e.to_string()),
+            ReviewError::Internal(e) => AppError::Internal(e),
+        })?
+        .ok_or_else(|| AppError::not_found("Document not found for this verification"))?;
+
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        admin_id,
+        "verification_document_reviewed",
+        verification_id,
+        "student_verification",
+        serde_json::json!({"file_id": file_id, "decision": req.decision.as_str()})
+    )
+    .execute(&state.pool)
+    .await;
+    Ok(Json(reviewable(&state, document)))
+}
@@ -9,6 +9,7 @@ use uuid::Uuid;
 use sqlx::types::BigDecimal;
 use std::str::FromStr;
 use crate::{
+    config::StellarNetwork,
     services::{NewStellarService, WalletInfo, BalanceInfo, TransactionInfo},
     state::AppState,
 };
@@ -59,6 +60,8 @@ pub struct TransactionResponse {
 pub struct PlatformInfoResponse {
     pub success: bool,
     pub public_key: String,
+    pub network: StellarNetwork,
+    pub network_passphrase: String,
     pub message: String,
 }
 
@@ -163,9 +166,20 @@ pub async fn send_payment(
     State(app_state): State<AppState>,
     Json(payload): Json<SendPaymentRequest>,
 ) -> Result<Json<TransactionResponse>, StatusCode> {
+    let destination = match app_state.stellar.resolve_address(&payload.to_public).await {
+        Ok(resolved) => resolved.address(),
+        Err(e) => {
+            return Ok(Json(TransactionResponse {
+                success: false,
+                hash: None,
+                message: format!("Invalid destination: {}", e),
+            }))
+        }
+    };
+
     match app_state.stellar_service.send_payment(
         &payload.from_secret,
-        &payload.to_public,
+        &destination,
         &payload.amount,
         payload.memo.as_deref(),
     ).await {
@@ -174,7 +188,7 @@ pub async fn send_payment(
             if let Err(e) = sqlx::query!(
                 "INSERT INTO transactions (sender_address, receiver_address, amount, tx_hash, created_at) VALUES ($1, $2, $3, $4, NOW())",
                 payload.from_secret, // This should be the public key, not secret
-                payload.to_public,
+                destination,
                 BigDecimal::from_str(&payload.amount).unwrap_or_default(),
                 hash
             )
@@ -225,6 +239,8 @@ pub async fn get_platform_info(
     Ok(Json(PlatformInfoResponse {
         success: true,
         public_key: platform_public,
+        network: app_state.network,
+        network_passphrase: app_state.network.network_passphrase(),
         message: "Platform wallet info retrieved".to_string(),
     }))
 }
@@ -234,6 +250,10 @@ pub async fn fund_with_friendbot(
     State(app_state): State<AppState>,
     Path(address): Path<String>,
 ) -> Result<Json<TransactionResponse>, StatusCode> {
+    if app_state.network == StellarNetwork::Mainnet {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
     match app_state.stellar_service.fund_with_friendbot(&address).await {
         Ok(_) => {
             Ok(Json(TransactionResponse {
@@ -1,8 +1,11 @@
-use axum::{extract::{Path, State}, Json, http::{StatusCode, HeaderMap}};
+use anyhow::Context;
+use axum::{extract::{Path, Query, State}, Json, http::HeaderMap};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use sqlx::types::BigDecimal;
 
+use crate::routes::error::{AppError, AppResult};
+
 #[derive(Serialize)]
 pub struct ApiMessage { 
     pub message: String 
@@ -32,105 +35,238 @@ pub async fn connect(
     State(state): State<crate::state::AppState>, 
     headers: HeaderMap,
     Json(payload): Json<ConnectRequest>
-) -> Result<Json<ConnectResponse>, StatusCode> {
+) -> AppResult<Json<ConnectResponse>> {
     tracing::info!("Wallet connect request for public key: {}", payload.public_key);
     
     // Extract user ID from JWT token
     let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
-        .map_err(|e| {
-            tracing::error!("JWT extraction failed: {:?}", e);
-            StatusCode::UNAUTHORIZED
-        })?;
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
     
     tracing::info!("User ID extracted: {}", user_id);
 
+    // Federation and muxed addresses are stored as the address to pay
+    let resolved = state.stellar.resolve_address(&payload.public_key).await.map_err(|e| {
+        tracing::warn!("Cannot resolve wallet address {}: {}", payload.public_key, e);
+        AppError::invalid("public_key", e.to_string())
+    })?;
+
     // Validate wallet exists on Stellar network
-    tracing::info!("Validating Stellar wallet: {}", payload.public_key);
-    let is_valid = state.stellar
-        .validate_wallet(&payload.public_key)
+    tracing::info!("Validating Stellar wallet: {}", resolved.account_id);
+    let is_valid = state.stellar_api
+        .validate_wallet(&resolved.account_id)
         .await
         .unwrap_or(false);
     
     if !is_valid {
         tracing::warn!("Invalid Stellar wallet: {}", payload.public_key);
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(AppError::invalid("public_key", "Account doesn't exist on the Stellar network"));
     }
     tracing::info!("Stellar wallet validation passed");
 
-    // Check if user already has a wallet
-    tracing::info!("Checking for existing wallet for user: {}", user_id);
+    let (wallet_id, status) = save_wallet(&state.pool, user_id, &resolved.address(), false)
+        .await
+        .context("Failed to save wallet")?;
+
+    tracing::info!("Wallet {} saved for user {} with status {}", wallet_id, user_id, status);
+
+    Ok(Json(ConnectResponse { wallet_id, status }))
+}
+
+/// Create or update the user's wallet. A new key stays `unverified` until a
+/// SEP-10 challenge for it is verified; re-submitting an already verified key
+/// keeps it `connected`.
+async fn save_wallet(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    public_key: &str,
+    verified: bool,
+) -> Result<(Uuid, String), sqlx::Error> {
     let existing_wallet = sqlx::query!(
         r#"SELECT id FROM wallets WHERE user_id = $1"#,
         user_id
     )
-    .fetch_optional(&state.pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("Database error checking wallet: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .fetch_optional(pool)
+    .await?;
 
     if let Some(wallet) = existing_wallet {
-        tracing::info!("Found existing wallet: {}", wallet.id);
-        // Update existing wallet
-        sqlx::query!(
+        let updated = sqlx::query!(
             r#"
             UPDATE wallets
-            SET public_key = $2, status = 'connected', last_synced_at = NOW()
+            SET status = CASE
+                    WHEN $3 OR (public_key = $2 AND status = 'connected') THEN 'connected'
+                    ELSE 'unverified'
+                END,
+                public_key = $2,
+                last_synced_at = NOW()
             WHERE id = $1
+            RETURNING status
             "#,
             wallet.id,
-            payload.public_key
+            public_key,
+            verified
         )
-        .execute(&state.pool)
-        .await
-        .map_err(|e| {
-            tracing::error!("Error updating wallet: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-        
-        tracing::info!("Wallet updated successfully");
-        
-        return Ok(Json(ConnectResponse {
-            wallet_id: wallet.id,
-            status: "connected".to_string(),
-        }));
+        .fetch_one(pool)
+        .await?;
+
+        return Ok((wallet.id, updated.status));
     }
 
-    // Create new wallet for user
     let new_wallet_id = Uuid::new_v4();
-    tracing::info!("Creating new wallet: {}", new_wallet_id);
-    
+    let status = if verified { "connected" } else { "unverified" };
+
     sqlx::query!(
         r#"
         INSERT INTO wallets (id, user_id, public_key, status, balance, last_synced_at)
-        VALUES ($1, $2, $3, 'connected', 0, NOW())
+        VALUES ($1, $2, $3, $4, 0, NOW())
         "#,
         new_wallet_id,
         user_id,
-        payload.public_key
+        public_key,
+        status
+    )
+    .execute(pool)
+    .await?;
+
+    Ok((new_wallet_id, status.to_string()))
+}
+
+#[derive(Deserialize)]
+pub struct ChallengeRequest {
+    pub public_key: String,
+}
+
+#[derive(Serialize)]
+pub struct ChallengeResponse {
+    /// Base64 XDR transaction for the wallet to co-sign
+    pub transaction: String,
+    pub network_passphrase: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyChallengeRequest {
+    pub public_key: String,
+    /// The challenge transaction signed by the wallet
+    pub transaction: String,
+}
+
+/// Issue a SEP-10 challenge the wallet must sign to prove ownership
+pub async fn challenge(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ChallengeRequest>,
+) -> AppResult<Json<ChallengeResponse>> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let web_auth = state
+        .web_auth
+        .as_ref()
+        .ok_or_else(|| AppError::Unavailable("Wallet verification is not configured".to_string()))?;
+
+    // Muxed and federated addresses are proven by signing with their account's key
+    let resolved = state.stellar.resolve_address(&payload.public_key).await.map_err(|e| {
+        tracing::warn!("Cannot resolve wallet address {}: {}", payload.public_key, e);
+        AppError::invalid("public_key", e.to_string())
+    })?;
+
+    let challenge = web_auth.challenge(&resolved.account_id).map_err(|e| {
+        tracing::warn!("Cannot issue challenge for {}: {}", payload.public_key, e);
+        AppError::invalid("public_key", e.to_string())
+    })?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO wallet_challenges (user_id, public_key, nonce, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        resolved.address(),
+        challenge.nonce,
+        challenge.expires_at
     )
     .execute(&state.pool)
     .await
-    .map_err(|e| {
-        tracing::error!("Error creating wallet: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+    .context("Failed to store wallet challenge")?;
+
+    Ok(Json(ChallengeResponse {
+        transaction: challenge.transaction,
+        network_passphrase: web_auth.network_passphrase().to_string(),
+        expires_at: challenge.expires_at,
+    }))
+}
+
+/// Verify a signed SEP-10 challenge and mark the wallet connected
+pub async fn verify_challenge(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<VerifyChallengeRequest>,
+) -> AppResult<Json<ConnectResponse>> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let web_auth = state
+        .web_auth
+        .as_ref()
+        .ok_or_else(|| AppError::Unavailable("Wallet verification is not configured".to_string()))?;
+
+    let resolved = state.stellar.resolve_address(&payload.public_key).await.map_err(|e| {
+        tracing::warn!("Cannot resolve wallet address {}: {}", payload.public_key, e);
+        AppError::invalid("public_key", e.to_string())
     })?;
-    
-    tracing::info!("Wallet created successfully");
+    let address = resolved.address();
 
-    tracing::info!("Wallet connected successfully for user: {}", user_id);
+    let nonce = web_auth.verify(&payload.transaction, &resolved.account_id).map_err(|e| {
+        tracing::warn!("Rejected challenge for {}: {}", payload.public_key, e);
+        AppError::Coded {
+            status: axum::http::StatusCode::UNAUTHORIZED,
+            code: "invalid_challenge",
+            message: e.to_string(),
+            details: None,
+        }
+    })?;
 
-    Ok(Json(ConnectResponse {
-        wallet_id: new_wallet_id,
-        status: "connected".to_string(),
-    }))
+    // Each challenge can be redeemed once, by the user it was issued to
+    let redeemed = sqlx::query!(
+        r#"
+        UPDATE wallet_challenges
+        SET used_at = NOW()
+        WHERE nonce = $1 AND user_id = $2 AND public_key = $3
+        AND used_at IS NULL AND expires_at > NOW()
+        RETURNING id
+        "#,
+        nonce,
+        user_id,
+        address
+    )
+    .fetch_optional(&state.pool)
+    .await
+    .context("Failed to redeem wallet challenge")?;
+
+    if redeemed.is_none() {
+        tracing::warn!("Challenge for {} was not issued to user {} or already used", payload.public_key, user_id);
+        return Err(AppError::Coded {
+            status: axum::http::StatusCode::UNAUTHORIZED,
+            code: "invalid_challenge",
+            message: "Challenge has expired, was already used or wasn't issued to you".to_string(),
+            details: None,
+        });
+    }
+
+    let (wallet_id, status) = save_wallet(&state.pool, user_id, &address, true)
+        .await
+        .context("Failed to save wallet")?;
+
+    tracing::info!("Wallet {} verified for user {}", wallet_id, user_id);
+
+    Ok(Json(ConnectResponse { wallet_id, status }))
 }
+
 pub async fn get_balance(State(state): State<crate::state::AppState>, Path(wallet_id): Path<Uuid>) -> Json<serde_json::Value> {
     let rec = sqlx::query!("SELECT public_key FROM wallets WHERE id = $1", wallet_id)
         .fetch_optional(&state.pool).await.ok().flatten();
     if let Some(r) = rec {
-        if let Ok(b) = state.stellar.fetch_wallet_balance(&r.public_key).await {
+        if let Ok(b) = state.stellar_api.fetch_balance(&r.public_key).await {
             return Json(serde_json::json!({"xlm": b.xlm, "usdc": b.usdc}));
         }
     }
@@ -141,15 +277,12 @@ pub async fn get_wallet_details(
     State(state): State<crate::state::AppState>,
     headers: HeaderMap,
     Path(wallet_id): Path<Uuid>
-) -> Result<Json<WalletDetails>, StatusCode> {
+) -> AppResult<Json<WalletDetails>> {
     tracing::info!("Getting wallet details for wallet_id: {}", wallet_id);
 
     // Extract user ID from JWT token
     let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
-        .map_err(|e| {
-            tracing::error!("JWT extraction failed: {:?}", e);
-            StatusCode::UNAUTHORIZED
-        })?;
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
 
     // Get wallet details from database
     let wallet = sqlx::query!(
@@ -162,11 +295,7 @@ pub async fn get_wallet_details(
         user_id
     )
     .fetch_optional(&state.pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("Database error fetching wallet: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .await?;
 
     match wallet {
         Some(wallet) => {
@@ -181,7 +310,7 @@ pub async fn get_wallet_details(
         }
         None => {
             tracing::warn!("Wallet not found: {}", wallet_id);
-            Err(StatusCode::NOT_FOUND)
+            Err(AppError::not_found("Wallet not found"))
         }
     }
 }
@@ -195,20 +324,17 @@ pub async fn get_user_wallet(
     State(state): State<crate::state::AppState>,
     headers: HeaderMap,
     Path(user_id): Path<Uuid>
-) -> Result<Json<Vec<WalletDetails>>, StatusCode> {
+) -> AppResult<Json<Vec<WalletDetails>>> {
     tracing::info!("Getting wallet for user: {}", user_id);
 
     // Extract user ID from JWT token
     let authenticated_user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
-        .map_err(|e| {
-            tracing::error!("JWT extraction failed: {:?}", e);
-            StatusCode::UNAUTHORIZED
-        })?;
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
 
     // Users can only access their own wallet
     if authenticated_user_id != user_id {
         tracing::warn!("User {} attempted to access wallet for user {}", authenticated_user_id, user_id);
-        return Err(StatusCode::FORBIDDEN);
+        return Err(AppError::forbidden("You can only view your own wallets"));
     }
 
     // Get user's wallet from database
@@ -222,11 +348,7 @@ pub async fn get_user_wallet(
         user_id
     )
     .fetch_all(&state.pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("Database error fetching user wallet: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    .await?;
 
     let wallet_details: Vec<WalletDetails> = wallets.into_iter().map(|wallet| WalletDetails {
         id: wallet.id,
@@ -243,7 +365,7 @@ pub async fn get_transactions(State(state): State<crate::state::AppState>, Path(
     let rec = sqlx::query!("SELECT public_key FROM wallets WHERE id = $1", wallet_id)
         .fetch_optional(&state.pool).await.ok().flatten();
     if let Some(r) = rec {
-        if let Ok(txs) = state.stellar.fetch_wallet_transactions(&r.public_key).await {
+        if let Ok(txs) = state.stellar_api.fetch_transactions(&r.public_key).await {
             let json: Vec<_> = txs.into_iter().map(|t| serde_json::json!({
                 "hash": t.hash,
                 "amount": t.amount,
@@ -258,6 +380,101 @@ pub async fn get_transactions(State(state): State<crate::state::AppState>, Path(
     Json(serde_json::json!([]))
 }
 
+/// Ledger history page size when none is given, and the most allowed
+const LEDGER_HISTORY_DEFAULT_LIMIT: i64 = 50;
+const LEDGER_HISTORY_MAX_LIMIT: i64 = 200;
+
+#[derive(Deserialize)]
+pub struct LedgerHistoryQuery {
+    pub limit: Option<i64>,
+    /// Operation id from a previous page's `next_cursor`
+    pub cursor: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct LedgerEntry {
+    pub operation_id: i64,
+    pub tx_hash: String,
+    pub operation_type: Option<String>,
+    pub source_account: Option<String>,
+    pub destination_account: Option<String>,
+    pub amount_stroops: Option<i64>,
+    pub asset: Option<String>,
+    pub memo: Option<String>,
+    pub memo_type: Option<String>,
+    pub ledger: Option<i32>,
+    pub successful: Option<bool>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Indexed operations touching the caller's wallet, newest first, served from
+/// `onchain_transactions` rather than Horizon
+pub async fn get_ledger_history(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+    Path(wallet_id): Path<Uuid>,
+    Query(query): Query<LedgerHistoryQuery>,
+) -> AppResult<Json<serde_json::Value>> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    let wallet = sqlx::query!(
+        "SELECT public_key FROM wallets WHERE id = $1 AND user_id = $2",
+        wallet_id,
+        user_id
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Wallet not found"))?;
+
+    let account = crate::services::stellar::base_account(&wallet.public_key);
+    let limit = query
+        .limit
+        .unwrap_or(LEDGER_HISTORY_DEFAULT_LIMIT)
+        .clamp(1, LEDGER_HISTORY_MAX_LIMIT);
+
+    let entries = sqlx::query_as!(
+        LedgerEntry,
+        r#"
+        SELECT operation_id as "operation_id!", tx_hash, operation_type, source_account,
+               destination_account, amount_stroops, asset, memo, memo_type, ledger,
+               successful, created_at
+        FROM onchain_transactions
+        WHERE (source_account = $1 OR destination_account = $1)
+        AND operation_id IS NOT NULL
+        AND ($2::BIGINT IS NULL OR operation_id < $2)
+        ORDER BY operation_id DESC
+        LIMIT $3
+        "#,
+        account,
+        query.cursor,
+        limit
+    )
+    .fetch_all(&state.pool)
+    .await
+    .context("Failed to load ledger history")?;
+
+    // Only platform and project wallets are indexed; others have no checkpoint
+    let indexed_at = sqlx::query_scalar!(
+        "SELECT updated_at FROM indexer_cursors WHERE name = $1",
+        crate::workers::ledger_indexer::cursor_name(&account)
+    )
+    .fetch_optional(&state.pool)
+    .await?
+    .flatten();
+
+    let next_cursor = (entries.len() as i64 == limit).then(|| entries.last().map(|e| e.operation_id)).flatten();
+
+    Ok(Json(serde_json::json!({
+        "wallet_id": wallet_id,
+        "account": account,
+        "indexed": indexed_at.is_some(),
+        "indexed_at": indexed_at,
+        "entries": entries,
+        "next_cursor": next_cursor,
+    })))
+}
+
 #[derive(Deserialize)]
 pub struct VerifyTransactionRequest {
     pub tx_hash: String,
@@ -279,13 +496,13 @@ pub struct VerifyTransactionResponse {
 pub async fn verify_transaction(
     State(state): State<crate::state::AppState>,
     Json(payload): Json<VerifyTransactionRequest>
-) -> Result<Json<VerifyTransactionResponse>, StatusCode> {
+) -> AppResult<Json<VerifyTransactionResponse>> {
     tracing::info!("Verifying transaction: {}", payload.tx_hash);
     
     // Validate transaction hash format
     if payload.tx_hash.len() != 64 || !payload.tx_hash.chars().all(|c| c.is_ascii_hexdigit()) {
         tracing::warn!("Invalid transaction hash format: {}", payload.tx_hash);
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(AppError::invalid("tx_hash", "Transaction hash must be 64 hex characters"));
     }
     
     // Fetch transaction details from Stellar network
@@ -302,14 +519,114 @@ pub async fn verify_transaction(
                 operation_count: tx_details.operation_count,
                 memo: tx_details.memo,
                 source_account: tx_details.source_account,
-                network: "testnet".to_string(), // For now, assume testnet
+                network: state.stellar.network().to_string(),
             }))
         }
         Err(e) => {
             tracing::error!("Failed to verify transaction {}: {}", payload.tx_hash, e);
-            Err(StatusCode::NOT_FOUND)
+            Err(AppError::not_found("Transaction not found"))
         }
     }
 }
 
 
+
+#[derive(Serialize)]
+pub struct ResolvedAddressResponse {
+    pub address: String,
+    pub account_id: String,
+    pub muxed_id: Option<u64>,
+}
+
+/// Resolve a federation (`name*domain.com`) or muxed (`M...`) address
+pub async fn resolve_address(
+    State(state): State<crate::state::AppState>,
+    Path(address): Path<String>,
+) -> AppResult<Json<ResolvedAddressResponse>> {
+    let resolved = state
+        .stellar
+        .resolve_address(&address)
+        .await
+        .map_err(|e| AppError::invalid("address", e.to_string()))?;
+
+    Ok(Json(ResolvedAddressResponse {
+        address: resolved.address(),
+        account_id: resolved.account_id,
+        muxed_id: resolved.muxed_id,
+    }))
+}
+
+#[derive(Serialize)]
+pub struct ClaimableBalanceInfo {
+    pub balance_id: String,
+    pub claimant: String,
+    pub amount_xlm: crate::utils::money::Stroops,
+    pub source_type: String,
+    pub source_id: Option<Uuid>,
+    pub tx_hash: String,
+    pub status: String,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Claimable balances the platform created for the caller
+pub async fn get_claimable_balances(
+    State(state): State<crate::state::AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<Vec<ClaimableBalanceInfo>>> {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(&headers)
+        .map_err(|_| AppError::unauthorized("Authentication required"))?;
+
+    // Balances Horizon no longer lists have been claimed (or reclaimed)
+    let unclaimed = sqlx::query!(
+        r#"
+        SELECT DISTINCT cb.claimant
+        FROM claimable_balances cb
+        JOIN students s ON s.id = cb.student_id
+        WHERE s.user_id = $1 AND cb.status = 'unclaimed'
+        "#,
+        user_id
+    )
+    .fetch_all(&state.pool)
+    .await
+    .context("Failed to load claimable balances")?;
+
+    for row in unclaimed {
+        let live: Vec<String> = match state.stellar.fetch_claimable_balances(&row.claimant).await {
+            Ok(balances) => balances.into_iter().map(|b| b.id).collect(),
+            Err(e) => {
+                tracing::warn!("Failed to fetch claimable balances for {}: {}", row.claimant, e);
+                continue;
+            }
+        };
+
+        let _ = sqlx::query!(
+            r#"
+            UPDATE claimable_balances
+            SET status = 'claimed', settled_at = NOW()
+            WHERE claimant = $1 AND status = 'unclaimed' AND NOT (balance_id = ANY($2))
+            "#,
+            row.claimant,
+            &live
+        )
+        .execute(&state.pool)
+        .await;
+    }
+
+    let balances = sqlx::query_as!(
+        ClaimableBalanceInfo,
+        r#"
+        SELECT cb.balance_id, cb.claimant, cb.amount as "amount_xlm: crate::utils::money::Stroops",
+               cb.source_type, cb.source_id, cb.tx_hash, cb.status, cb.created_at
+        FROM claimable_balances cb
+        JOIN students s ON s.id = cb.student_id
+        WHERE s.user_id = $1
+        ORDER BY cb.created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(&state.pool)
+    .await
+    .context("Failed to load claimable balances")?;
+
+    Ok(Json(balances))
+}
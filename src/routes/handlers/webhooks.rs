@@ -0,0 +1,381 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::routes::error::{AppError, AppResult};
+use crate::routes::validation::{self, ValidatedJson};
+use crate::services::outgoing_webhooks::{self, CreatedEndpoint, NewEndpoint, WebhookEndpoint, WebhookEvent};
+use crate::services::webhook_deliveries::{self, NewDelivery, WebhookDelivery};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ListDeliveriesQuery {
+    pub status: Option<String>,
+    pub provider: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// List recorded webhook deliveries, newest first
+pub async fn list_deliveries(
+    State(state): State<AppState>,
+    Query(query): Query<ListDeliveriesQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>, StatusCode> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    webhook_deliveries::list(
+        &state.pool,
+        query.status.as_deref(),
+        query.provider.as_deref(),
+        limit,
+        offset,
+    )
+    .await
+    .map(Json)
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Get a single delivery with its request and response bodies
+pub async fn get_delivery(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WebhookDelivery>, StatusCode> {
+    webhook_deliveries::get(&state.pool, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Replay a recorded delivery. Inbound provider callbacks are re-processed;
+/// outbound webhooks are re-sent to their target URL. The attempt is recorded
+/// as a new delivery linked to the original.
+pub async fn replay_delivery(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let delivery = webhook_deliveries::get(&state.pool, id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": "Failed to load delivery"})),
+            )
+        })?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Delivery not found"})),
+        ))?;
+
+    let result = match delivery.direction.as_str() {
+        "inbound" if delivery.provider == "mpesa_b2c" => {
+            super::payments::handle_b2c_callback(
+                &state,
+                delivery.event_type.as_deref().unwrap_or("result"),
+                &delivery.request_body,
+                Some(delivery.id),
+            )
+            .await
+        }
+        "inbound" if delivery.provider == "mpesa_reversal" => {
+            super::payments::handle_reversal_callback(
+                &state,
+                delivery.event_type.as_deref().unwrap_or("result"),
+                &delivery.request_body,
+                Some(delivery.id),
+            )
+            .await
+        }
+        "inbound" => {
+            let signature = delivery
+                .request_headers
+                .as_ref()
+                .and_then(|h| h["signature"].as_str())
+                .map(|s| s.to_string());
+            super::payments::handle_provider_callback(
+                &state,
+                &delivery.provider,
+                &delivery.request_body,
+                signature,
+                delivery.created_at.unwrap_or_else(chrono::Utc::now),
+                Some(delivery.id),
+            )
+            .await
+        }
+        "outbound" => resend(&state, &delivery).await,
+        _ => Err("Unknown delivery direction".to_string()),
+    };
+
+    match result {
+        Ok(response) => Ok(Json(serde_json::json!({
+            "success": true,
+            "replay_of": delivery.id,
+            "response": response
+        }))),
+        Err(e) => Err((
+            StatusCode::BAD_GATEWAY,
+            Json(serde_json::json!({"success": false, "replay_of": delivery.id, "error": e})),
+        )),
+    }
+}
+
+async fn resend(state: &AppState, delivery: &WebhookDelivery) -> Result<serde_json::Value, String> {
+    let target_url = delivery
+        .target_url
+        .as_deref()
+        .ok_or_else(|| "Delivery has no target URL".to_string())?;
+
+    let response = reqwest::Client::new()
+        .post(target_url)
+        .header("content-type", "application/json")
+        .header("x-fundhub-replay-of", delivery.id.to_string())
+        .body(delivery.request_body.clone())
+        .timeout(std::time::Duration::from_secs(15))
+        .send()
+        .await;
+
+    let (status, body, error) = match response {
+        Ok(resp) => {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            let error = (!status.is_success()).then(|| format!("Subscriber returned {}", status));
+            (Some(status.as_u16() as i32), Some(body), error)
+        }
+        Err(e) => (None, None, Some(e.to_string())),
+    };
+
+    webhook_deliveries::record(&state.pool, NewDelivery {
+        direction: "outbound",
+        provider: &delivery.provider,
+        event_type: delivery.event_type.as_deref(),
+        target_url: Some(target_url),
+        request_headers: delivery.request_headers.clone(),
+        request_body: &delivery.request_body,
+        response_status: status,
+        response_body: body.clone(),
+        error: error.clone(),
+        replay_of: Some(delivery.id),
+    })
+    .await;
+
+    match error {
+        None => Ok(serde_json::json!({"status": status, "body": body})),
+        Some(e) => Err(e),
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateEndpointRequest {
+    #[validate(
+        custom(function = "validation::not_blank"),
+        length(max = 100, message = "Name must be at most 100 characters")
+    )]
+    pub name: String,
+    #[validate(custom(function = "validation::https_url"))]
+    pub url: String,
+    #[validate(length(min = 1, message = "Subscribe to at least one event"))]
+    pub events: Vec<String>,
+    /// Only hear about this project; owners' endpoints only
+    pub project_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListEventsQuery {
+    pub status: Option<String>,
+    pub endpoint_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+fn caller_id(headers: &HeaderMap) -> AppResult<Uuid> {
+    crate::utils::jwt::extract_user_id_from_headers(headers).map_err(|_| AppError::unauthorized("Authentication required"))
+}
+
+fn check_events(events: &[String]) -> AppResult<()> {
+    let unknown = outgoing_webhooks::unknown_events(events);
+    if !unknown.is_empty() {
+        return Err(AppError::invalid(
+            "events",
+            format!("Unknown events {}; use {}", unknown.join(", "), outgoing_webhooks::ALL_EVENTS.join(", ")),
+        ));
+    }
+    Ok(())
+}
+
+async fn log_activity(state: &AppState, user_id: Uuid, action: &str, target_id: Uuid, metadata: serde_json::Value) {
+    let _ = sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        action,
+        target_id,
+        "webhook_endpoint",
+        metadata
+    )
+    .execute(&state.pool)
+    .await;
+}
+
+async fn create(state: &AppState, req: &CreateEndpointRequest, owner: Option<Uuid>, created_by: Uuid) -> AppResult<CreatedEndpoint> {
+    let created = outgoing_webhooks::create_endpoint(
+        &state.pool,
+        &NewEndpoint {
+            owner_user_id: owner,
+            project_id: req.project_id,
+            name: req.name.trim(),
+            url: &req.url,
+            events: &req.events,
+            created_by,
+        },
+    )
+    .await?;
+
+    let endpoint = &created.endpoint;
+    log_activity(
+        state,
+        created_by,
+        "webhook_endpoint_created",
+        endpoint.id,
+        serde_json::json!({"name": endpoint.name, "url": endpoint.url, "events": endpoint.events, "partner": owner.is_none()}),
+    )
+    .await;
+    Ok(created)
+}
+
+/// The caller's webhook endpoints
+pub async fn list_my_endpoints(State(state): State<AppState>, headers: HeaderMap) -> AppResult<Json<Vec<WebhookEndpoint>>> {
+    let user_id = caller_id(&headers)?;
+    Ok(Json(outgoing_webhooks::list_endpoints(&state.pool, Some(user_id)).await?))
+}
+
+/// Register an endpoint for events about the caller's projects and
+/// verification. The signing secret is only shown in this response.
+pub async fn create_my_endpoint(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<CreateEndpointRequest>,
+) -> AppResult<(StatusCode, Json<CreatedEndpoint>)> {
+    let user_id = caller_id(&headers)?;
+    check_events(&req.events)?;
+
+    let caller = sqlx::query!(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM students WHERE user_id = $1) as "is_student!",
+               EXISTS(
+                   SELECT 1 FROM projects p JOIN students s ON s.id = p.student_id
+                   WHERE p.id = $2 AND s.user_id = $1
+               ) as "owns_project!"
+        "#,
+        user_id,
+        req.project_id
+    )
+    .fetch_one(&state.pool)
+    .await?;
+
+    if !caller.is_student {
+        return Err(AppError::forbidden("Only project owners can register webhooks"));
+    }
+    if req.project_id.is_some() && !caller.owns_project {
+        return Err(AppError::forbidden("You can only register webhooks for your own projects"));
+    }
+
+    let created = create(&state, &req, Some(user_id), user_id).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// Stop deliveries to one of the caller's endpoints
+pub async fn disable_my_endpoint(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<WebhookEndpoint>> {
+    let user_id = caller_id(&headers)?;
+    let endpoint = outgoing_webhooks::disable_endpoint(&state.pool, id, Some(user_id))
+        .await?
+        .ok_or_else(|| AppError::not_found("Webhook endpoint not found or already disabled"))?;
+
+    log_activity(&state, user_id, "webhook_endpoint_disabled", endpoint.id, serde_json::json!({"name": endpoint.name})).await;
+    Ok(Json(endpoint))
+}
+
+/// Every registered endpoint, owners' and partners'
+pub async fn list_endpoints(State(state): State<AppState>) -> AppResult<Json<Vec<WebhookEndpoint>>> {
+    Ok(Json(outgoing_webhooks::list_endpoints(&state.pool, None).await?))
+}
+
+/// Register a partner endpoint, which receives every event it subscribes to
+pub async fn create_partner_endpoint(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<CreateEndpointRequest>,
+) -> AppResult<(StatusCode, Json<CreatedEndpoint>)> {
+    let admin_id = caller_id(&headers)?;
+    check_events(&req.events)?;
+    if req.project_id.is_some() {
+        return Err(AppError::invalid("project_id", "Partner endpoints receive events for every project"));
+    }
+
+    let created = create(&state, &req, None, admin_id).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// Disable any endpoint, e.g. one that keeps failing
+pub async fn disable_endpoint(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<WebhookEndpoint>> {
+    let admin_id = caller_id(&headers)?;
+    let endpoint = outgoing_webhooks::disable_endpoint(&state.pool, id, None)
+        .await?
+        .ok_or_else(|| AppError::not_found("Webhook endpoint not found or already disabled"))?;
+
+    log_activity(&state, admin_id, "webhook_endpoint_disabled", endpoint.id, serde_json::json!({"name": endpoint.name})).await;
+    Ok(Json(endpoint))
+}
+
+/// Queued, delivered and dead-lettered events, newest first
+pub async fn list_events(
+    State(state): State<AppState>,
+    Query(query): Query<ListEventsQuery>,
+) -> AppResult<Json<Vec<WebhookEvent>>> {
+    if query.status.as_deref().is_some_and(|s| !matches!(s, "pending" | "delivered" | "dead")) {
+        return Err(AppError::invalid("status", "Status must be pending, delivered or dead"));
+    }
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let events =
+        outgoing_webhooks::list_events(&state.pool, query.status.as_deref(), query.endpoint_id, limit, offset).await?;
+    Ok(Json(events))
+}
+
+/// Requeue a dead-lettered event with a fresh set of attempts
+pub async fn redeliver_event(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<WebhookEvent>> {
+    let admin_id = caller_id(&headers)?;
+    let event = outgoing_webhooks::redeliver(&state.pool, id)
+        .await?
+        .ok_or_else(|| AppError::conflict("Only dead-lettered events of active endpoints can be redelivered"))?;
+
+    log_activity(
+        &state,
+        admin_id,
+        "webhook_event_redelivered",
+        event.endpoint_id,
+        serde_json::json!({"event_id": event.id, "event_type": event.event_type}),
+    )
+    .await;
+    Ok(Json(event))
+}
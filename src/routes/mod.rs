@@ -2,16 +2,20 @@ use axum::{
     routing::{get, post},
     Router,
     extract::State,
-    response::sse::{Sse, Event},
     response::IntoResponse,
     middleware,
 };
 use crate::state::AppState;
+pub mod error; // AppError, the handlers' error type
 pub mod handlers; // expose handlers module in this module tree
 pub mod payments; // expose payments module
-use tokio_stream::wrappers::BroadcastStream;
-use futures::StreamExt;
-use crate::utils::roles::{require_admin_mw, require_verified_student_mw, require_auth_mw};
+pub mod validation; // ValidatedJson and shared field rules
+use axum::handler::Handler;
+use crate::services::rbac;
+use crate::utils::roles::{
+    require_admin_mw, require_finance_mw, require_moderator_mw, require_permission_mw, require_verified_student_mw,
+    require_auth_mw,
+};
 
 pub fn auth_routes() -> Router<AppState> {
     Router::new()
@@ -20,7 +24,21 @@ pub fn auth_routes() -> Router<AppState> {
         .route("/logout", post(handlers::auth::logout))
         .route("/refresh", post(handlers::auth::refresh))
         .route("/verify-email", get(handlers::auth::verify_email))
+        .route("/resend-verification", post(handlers::auth::resend_verification))
+        .route("/forgot-password", post(handlers::auth::forgot_password))
+        .route("/reset-password", post(handlers::auth::reset_password))
+        .route("/2fa", get(handlers::two_factor::two_factor_status))
+        .route("/2fa/setup", post(handlers::two_factor::setup_two_factor))
+        .route("/2fa/enable", post(handlers::two_factor::enable_two_factor))
+        .route("/2fa/disable", post(handlers::two_factor::disable_two_factor))
+        .route("/2fa/verify", post(handlers::two_factor::verify_two_factor_login))
+        .route(
+            "/sessions",
+            get(handlers::sessions::list_sessions).delete(handlers::sessions::revoke_other_sessions),
+        )
+        .route("/sessions/:session_id", axum::routing::delete(handlers::sessions::revoke_session))
         .route("/me", get(handlers::auth::get_me))
+        .route("/me/usage", get(handlers::usage::my_usage))
         .route("/profile/:user_id", get(handlers::auth::get_profile))
         .route("/student-status", get(handlers::auth::get_student_status))
 }
@@ -31,19 +49,59 @@ pub fn student_routes() -> Router<AppState> {
         .route("/status/:user_id", get(self::handlers::students::get_status))
         .route("/update", post(self::handlers::students::update))
         .route("/apply-verification", post(self::handlers::students::apply_verification).layer(middleware::from_fn(require_auth_mw)))
+        .route("/verification/confirm-email", post(self::handlers::students::confirm_school_email).layer(middleware::from_fn(require_auth_mw)))
+        .route("/verification/resend-code", post(self::handlers::students::resend_school_email_code).layer(middleware::from_fn(require_auth_mw)))
+        .route(
+            "/documents",
+            post(self::handlers::students::upload_document)
+                .route_layer(middleware::from_fn(require_auth_mw))
+                .route_layer(axum::extract::DefaultBodyLimit::max(
+                    self::handlers::students::MAX_DOCUMENT_BYTES as usize + 64 * 1024,
+                )),
+        )
         .route("/verification-status/:user_id", get(self::handlers::students::get_verification_status))
         .route("/profile/:user_id", get(self::handlers::students::get_student_profile))
         .route("/profile/:user_id", axum::routing::put(self::handlers::students::update_student_profile))
+        .route(
+            "/:student_id/follow",
+            post(self::handlers::follows::follow_student).delete(self::handlers::follows::unfollow_student),
+        )
+}
+
+/// Files kept in object storage
+pub fn file_routes() -> Router<AppState> {
+    Router::new()
+        .route("/:id/download", get(self::handlers::files::download_file))
+        .route_layer(middleware::from_fn(require_auth_mw))
+}
+
+/// Flagging content for moderators
+pub fn report_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", post(self::handlers::reports::create_report))
+        .route_layer(middleware::from_fn(require_auth_mw))
+}
+
+/// The signed-in user's own resources
+pub fn user_routes() -> Router<AppState> {
+    Router::new()
+        .route("/me/following", get(self::handlers::follows::my_following))
+        .route_layer(middleware::from_fn(require_auth_mw))
 }
 
 pub fn wallet_routes() -> Router<AppState> {
     Router::new()
         .route("/test", get(self::handlers::wallets::test_connection))
         .route("/connect", post(self::handlers::wallets::connect))
+        .route("/challenge", post(self::handlers::wallets::challenge))
+        .route("/verify-challenge", post(self::handlers::wallets::verify_challenge))
+        .route("/claimable", get(self::handlers::wallets::get_claimable_balances))
+        .route("/resolve/:address", get(self::handlers::wallets::resolve_address))
         .route("/user/:user_id", get(self::handlers::wallets::get_user_wallet))
         .route("/details/:wallet_id", get(self::handlers::wallets::get_wallet_details))
         .route("/balance/:wallet_id", get(self::handlers::wallets::get_balance))
         .route("/transactions/:wallet_id", get(self::handlers::wallets::get_transactions))
+        .route("/:wallet_id/ledger-history", get(self::handlers::wallets::get_ledger_history))
         .route("/verify-transaction", post(self::handlers::wallets::verify_transaction))
         // New Stellar wallet routes
         .route("/stellar/create", post(self::handlers::wallet::create_wallet))
@@ -63,11 +121,90 @@ pub fn project_routes() -> Router<AppState> {
         .route("/", post(self::handlers::projects::create_project))
         .route("/", get(self::handlers::projects::list_projects))
         .route("/public", get(self::handlers::projects::get_public_projects))
+        .route("/compare", get(self::handlers::projects::compare_projects))
+        .route("/featured", get(self::handlers::features::featured_projects))
+        .route("/category/:slug", get(self::handlers::projects::list_category_projects))
         .route("/:id", get(self::handlers::projects::get_project))
         .route("/:id", axum::routing::put(self::handlers::projects::update_project))
         .route("/:id", axum::routing::delete(self::handlers::projects::delete_project))
-        .route("/:id/publish", post(self::handlers::projects::publish_project))
-        .route("/:id/reject", post(self::handlers::projects::reject_project))
+        .route(
+            "/:id/publish",
+            post(self::handlers::projects::publish_project)
+                .layer(middleware::from_fn(|req, next| require_permission_mw(rbac::PROJECTS_PUBLISH, req, next))),
+        )
+        .route(
+            "/:id/reject",
+            post(self::handlers::projects::reject_project)
+                .layer(middleware::from_fn(|req, next| require_permission_mw(rbac::PROJECTS_PUBLISH, req, next))),
+        )
+        .route("/:id/status", post(self::handlers::projects::set_project_status))
+        .route("/:id/tags", axum::routing::put(self::handlers::projects::set_project_tags))
+        .route("/:id/complete", post(self::handlers::projects::complete_project))
+        .route("/:id/cancel", post(self::handlers::projects::cancel_project))
+        .route("/:id/refunds", get(self::handlers::projects::refund_progress))
+        .route("/:id/revisions", get(self::handlers::project_revisions::list_revisions))
+        .route(
+            "/:id/media",
+            get(self::handlers::project_media::list_media).post(self::handlers::project_media::upload_media).layer(
+                axum::extract::DefaultBodyLimit::max(crate::services::project_media::MAX_IMAGE_BYTES as usize + 64 * 1024),
+            ),
+        )
+        .route("/:id/media/uploads", post(self::handlers::project_media::presign_upload))
+        .route("/:id/media/links", post(self::handlers::project_media::add_media_link))
+        .route("/:id/media/order", axum::routing::put(self::handlers::project_media::reorder_media))
+        .route(
+            "/:id/media/:media_id",
+            axum::routing::put(self::handlers::project_media::update_media).delete(self::handlers::project_media::delete_media),
+        )
+        .route("/:id/media/:media_id/complete", post(self::handlers::project_media::complete_upload))
+        .route(
+            "/:id/revisions/:revision_id/approve",
+            post(self::handlers::project_revisions::approve_revision)
+                .layer(middleware::from_fn(|req, next| require_permission_mw(rbac::PROJECTS_PUBLISH, req, next))),
+        )
+        .route(
+            "/:id/revisions/:revision_id/reject",
+            post(self::handlers::project_revisions::reject_revision)
+                .layer(middleware::from_fn(|req, next| require_permission_mw(rbac::PROJECTS_PUBLISH, req, next))),
+        )
+        .route("/:id/milestones/:milestone_id/cancel", post(self::handlers::projects::cancel_milestone))
+        .route(
+            "/:id/comments",
+            get(self::handlers::comments::list_comments).post(self::handlers::comments::create_comment),
+        )
+        .route(
+            "/:id/comments/:comment_id",
+            axum::routing::delete(self::handlers::comments::delete_comment),
+        )
+        .route(
+            "/:id/updates",
+            get(self::handlers::project_updates::list_updates).post(self::handlers::project_updates::create_update),
+        )
+        .route(
+            "/:id/updates/:update_id",
+            get(self::handlers::project_updates::get_update)
+                .put(self::handlers::project_updates::replace_update)
+                .delete(self::handlers::project_updates::delete_update),
+        )
+        .route("/:id/updates/:update_id/publish", post(self::handlers::project_updates::publish_update))
+        .route(
+            "/:id/members",
+            get(self::handlers::project_members::list_members).post(self::handlers::project_members::invite_member),
+        )
+        .route("/:id/members/accept", post(self::handlers::project_members::accept_invitation))
+        .route(
+            "/:id/members/:student_id",
+            axum::routing::put(self::handlers::project_members::set_member_share)
+                .delete(self::handlers::project_members::remove_member),
+        )
+        .route(
+            "/:id/follow",
+            post(self::handlers::follows::follow_project).delete(self::handlers::follows::unfollow_project),
+        )
+}
+
+pub fn category_routes() -> Router<AppState> {
+    Router::new().route("/", get(self::handlers::categories::list_categories))
 }
 
 pub fn donation_routes() -> Router<AppState> {
@@ -77,13 +214,42 @@ pub fn donation_routes() -> Router<AppState> {
         .route("/platform/initiate", post(self::handlers::donations::initiate_platform_donation))
         .route("/project/:project_id", get(self::handlers::donations::get_project_donations))
         .route("/student/:student_id", get(self::handlers::donations::get_student_donations))
+        .route("/mine", get(self::handlers::donors::my_donations))
+        .route("/:id/receipt", get(self::handlers::donors::donation_receipt))
+        .route("/receipts/annual/:year", get(self::handlers::donors::annual_receipt))
+        .route(
+            "/subscriptions",
+            get(self::handlers::subscriptions::list_subscriptions).post(self::handlers::subscriptions::create_subscription),
+        )
+        .route("/subscriptions/:id", axum::routing::delete(self::handlers::subscriptions::cancel_subscription))
+        .route(
+            "/subscriptions/project/:project_id",
+            get(self::handlers::subscriptions::project_subscription_analytics),
+        )
+}
+
+pub fn donor_routes() -> Router<AppState> {
+    Router::new()
+        .route("/me/tax-summary", get(self::handlers::donors::tax_summary))
+        .route(
+            "/projects/:project_id/milestones/:milestone_id/votes",
+            get(self::handlers::contracts::get_milestone_votes),
+        )
+        .route(
+            "/projects/:project_id/milestones/:milestone_id/votes",
+            post(self::handlers::contracts::cast_milestone_vote).layer(middleware::from_fn(require_auth_mw)),
+        )
 }
 
 pub fn campaign_routes() -> Router<AppState> {
     Router::new()
         .route("/", get(self::handlers::campaigns::list))
         .route("/create", post(self::handlers::campaigns::create))
-        .route("/execute", post(self::handlers::campaigns::execute))
+        .route(
+            "/execute",
+            post(self::handlers::campaigns::execute)
+                .layer(middleware::from_fn(|req, next| require_permission_mw(rbac::CAMPAIGNS_EXECUTE, req, next))),
+        )
         .route("/active", get(self::handlers::campaigns::list))
         .route("/stats", get(self::handlers::campaigns::stats))
         .route("/:id", get(self::handlers::campaigns::get_by_id))
@@ -91,9 +257,119 @@ pub fn campaign_routes() -> Router<AppState> {
         .route("/:id", axum::routing::delete(self::handlers::campaigns::delete))
         .route("/:id/pause", post(self::handlers::campaigns::pause))
         .route("/:id/resume", post(self::handlers::campaigns::resume))
+        .route(
+            "/:id/matching-pool",
+            post(self::handlers::campaigns::create_matching_pool)
+                .layer(middleware::from_fn(|req, next| require_permission_mw(rbac::CAMPAIGNS_EXECUTE, req, next))),
+        )
+        .route("/:id/matching-pool", get(self::handlers::campaigns::get_matching_pool))
 }
 
+/// Admin API. Moderators and finance staff each reach their own group;
+/// admins reach everything.
 pub fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .merge(admin_moderation_routes())
+        .merge(admin_finance_routes())
+        .merge(admin_only_routes())
+}
+
+/// Platform settings, roles and operations
+fn admin_only_routes() -> Router<AppState> {
+    Router::new()
+        .route("/payment-providers", get(self::handlers::payment_providers::list_payment_providers))
+        .route("/payment-providers/:name", axum::routing::put(self::handlers::payment_providers::update_payment_provider))
+        // Role assignments
+        .route("/roles", get(self::handlers::roles::list_roles))
+        .route(
+            "/users/:user_id/roles",
+            get(self::handlers::roles::get_user_roles).post(
+                self::handlers::roles::assign_role
+                    .layer(middleware::from_fn(|req, next| require_permission_mw(rbac::ROLES_MANAGE, req, next))),
+            ),
+        )
+        .route(
+            "/users/:user_id/roles/:role",
+            axum::routing::delete(
+                self::handlers::roles::revoke_role
+                    .layer(middleware::from_fn(|req, next| require_permission_mw(rbac::ROLES_MANAGE, req, next))),
+            ),
+        )
+        // API keys for partner integrations
+        .route(
+            "/api-keys",
+            get(self::handlers::api_keys::list_api_keys).post(self::handlers::api_keys::create_api_key),
+        )
+        .route("/api-keys/:id", axum::routing::delete(self::handlers::api_keys::revoke_api_key))
+        .route("/logs", get(self::handlers::admin::get_activity_logs))
+        .route("/overview", get(self::handlers::admin::get_admin_overview))
+        .route("/status", get(self::handlers::status::admin_status))
+        .route("/workers", get(self::handlers::status::worker_status))
+        .route("/usage", get(self::handlers::usage::list_usage))
+        .route("/usage/:user_id", get(self::handlers::usage::user_usage))
+        .route("/features", get(self::handlers::features::list_features))
+        .route("/features", post(self::handlers::features::schedule_feature))
+        .route("/features/:id", axum::routing::delete(self::handlers::features::cancel_feature))
+        // Ops runbook actions
+        .route("/ops/workers", get(self::handlers::ops::list_workers))
+        .route("/ops/workers/:name/pause", post(self::handlers::ops::pause_worker))
+        .route("/ops/workers/:name/resume", post(self::handlers::ops::resume_worker))
+        .route("/ops/analytics/flush", post(self::handlers::ops::flush_analytics_cache))
+        .route("/ops/search/reindex", post(self::handlers::ops::reindex_search))
+        .route("/ops/sse/rotate", post(self::handlers::ops::rotate_sse_channel))
+        .route("/ops/contracts/reload", post(self::handlers::ops::reload_contracts))
+        .route("/ops/jobs", get(self::handlers::ops::list_jobs))
+        .route("/ops/jobs/:id/retry", post(self::handlers::ops::retry_job))
+        // Webhook delivery inspection
+        .route("/webhooks/deliveries", get(self::handlers::webhooks::list_deliveries))
+        .route("/webhooks/deliveries/:id", get(self::handlers::webhooks::get_delivery))
+        .route("/webhooks/deliveries/:id/replay", post(self::handlers::webhooks::replay_delivery))
+        // Outgoing webhooks: partner endpoints and the dead-letter queue
+        .route(
+            "/webhooks/endpoints",
+            get(self::handlers::webhooks::list_endpoints).post(self::handlers::webhooks::create_partner_endpoint),
+        )
+        .route("/webhooks/endpoints/:id", axum::routing::delete(self::handlers::webhooks::disable_endpoint))
+        .route("/webhooks/events", get(self::handlers::webhooks::list_events))
+        .route("/webhooks/events/:id/redeliver", post(self::handlers::webhooks::redeliver_event))
+        .route("/files/scans", get(self::handlers::files::list_file_scans))
+        .route("/files/:id/verify", post(self::handlers::files::verify_file))
+        .route("/files/:id/rescan", post(self::handlers::files::rescan_file))
+        // School email domains accepted for verification
+        .route(
+            "/university-domains",
+            get(self::handlers::university_domains::list_university_domains)
+                .post(self::handlers::university_domains::create_university_domain),
+        )
+        .route(
+            "/university-domains/:id",
+            axum::routing::put(self::handlers::university_domains::update_university_domain)
+                .delete(self::handlers::university_domains::delete_university_domain),
+        )
+        // Announcements to users
+        .route(
+            "/announcements",
+            get(self::handlers::announcements::list_announcements)
+                .post(self::handlers::announcements::create_announcement),
+        )
+        .route("/announcements/:id", get(self::handlers::announcements::get_announcement))
+        .route("/announcements/:id/cancel", post(self::handlers::announcements::cancel_announcement))
+        // Feature flags
+        .route(
+            "/feature-flags",
+            get(self::handlers::feature_flags::list_feature_flags)
+                .post(self::handlers::feature_flags::create_feature_flag),
+        )
+        .route(
+            "/feature-flags/:name",
+            axum::routing::put(self::handlers::feature_flags::update_feature_flag)
+                .delete(self::handlers::feature_flags::delete_feature_flag),
+        )
+        .route_layer(middleware::from_fn(require_admin_mw))
+}
+
+/// Student verification review and reported content
+fn admin_moderation_routes() -> Router<AppState> {
     Router::new()
         .route("/students", get(self::handlers::admin::list_students))
         .route("/verifications", get(self::handlers::admin::list_pending_verifications))
@@ -101,17 +377,55 @@ pub fn admin_routes() -> Router<AppState> {
         .route("/verifications/approved", get(self::handlers::admin::list_approved_verifications))
         .route("/verifications/rejected", get(self::handlers::admin::list_rejected_verifications))
         .route("/verifications/enhanced", get(self::handlers::admin::get_enhanced_verifications))
+        .route("/verifications/bulk", post(self::handlers::bulk_verifications::bulk_verifications))
+        .route(
+            "/verifications/preapproved",
+            get(self::handlers::bulk_verifications::list_preapproved)
+                .post(self::handlers::bulk_verifications::import_preapproved),
+        )
         .route("/verifications/:id/details", get(self::handlers::admin::get_verification_details))
+        .route("/verifications/:id/documents", get(self::handlers::verification_documents::list_verification_documents))
+        .route("/verifications/:id/documents/:file_id", axum::routing::put(self::handlers::verification_documents::review_verification_document))
         .route("/verifications/:id/approve", post(self::handlers::admin::approve_verification))
         .route("/verifications/:id/reject", post(self::handlers::admin::reject_verification))
         .route("/verifications/:id/approve-enhanced", post(self::handlers::admin::approve_verification_enhanced))
         .route("/verifications/:id/reject-enhanced", post(self::handlers::admin::reject_verification_enhanced))
         .route("/approve-student/:verification_id", post(self::handlers::admin::approve_student_verification))
         .route("/verify-student", post(self::handlers::admin::verify_student))
+        // Reported content
+        .route("/reports", get(self::handlers::reports::list_reports))
+        .route("/reports/:id/resolve", post(self::handlers::reports::resolve_report))
+        // Project category taxonomy
+        .route("/categories", post(self::handlers::categories::create_category))
+        .route("/categories/:slug", axum::routing::delete(self::handlers::categories::delete_category))
+        .route_layer(middleware::from_fn(require_moderator_mw))
+}
+
+/// Payouts, ledger and refunds
+fn admin_finance_routes() -> Router<AppState> {
+    Router::new()
         .route("/fund-student", post(self::handlers::admin::fund_student))
-        .route("/logs", get(self::handlers::admin::get_activity_logs))
-        .route("/overview", get(self::handlers::admin::get_admin_overview))
-        .route_layer(middleware::from_fn(require_admin_mw))
+        .route("/payouts", get(self::handlers::admin::list_mobile_payouts))
+        .route("/payouts/:id/approve", post(self::handlers::admin::approve_mobile_payout))
+        .route("/payouts/:id/reject", post(self::handlers::admin::reject_mobile_payout))
+        .route("/ledger/trial-balance", get(self::handlers::ledger::trial_balance))
+        .route("/ledger/accounts/:account/statement", get(self::handlers::ledger::account_statement))
+        .route("/fees/report", get(self::handlers::ledger::fee_report))
+        // Donor refunds
+        .route(
+            "/refunds",
+            get(self::handlers::refunds::list_refunds).post(
+                self::handlers::refunds::create_refund
+                    .layer(middleware::from_fn(|req, next| require_permission_mw(rbac::PAYMENTS_REFUND, req, next))),
+            ),
+        )
+        .route("/refunds/:id", get(self::handlers::refunds::get_refund))
+        // Dual control; who may decide depends on the action
+        .route("/approvals", get(self::handlers::approvals::list_approvals))
+        .route("/approvals/:id", get(self::handlers::approvals::get_approval))
+        .route("/approvals/:id/approve", post(self::handlers::approvals::approve_request))
+        .route("/approvals/:id/reject", post(self::handlers::approvals::reject_request))
+        .route_layer(middleware::from_fn(require_finance_mw))
 }
 
 pub fn analytics_routes() -> Router<AppState> {
@@ -119,8 +433,10 @@ pub fn analytics_routes() -> Router<AppState> {
         .route("/platform/stats", get(self::handlers::analytics::platform_stats))
         .route("/projects/top", get(self::handlers::analytics::top_projects))
         .route("/students/top", get(self::handlers::analytics::top_students))
+        .route("/donors/top", get(self::handlers::analytics::top_donors))
         .route("/campaigns/performance", get(self::handlers::analytics::campaign_performance))
         .route("/donations/trends", get(self::handlers::analytics::donation_trends))
+        .route("/featured/uplift", get(self::handlers::features::feature_uplift))
         .route("/projects/:id", get(self::handlers::analytics::project_analytics))
         .route("/students/:id", get(self::handlers::analytics::student_analytics))
 }
@@ -129,6 +445,7 @@ pub fn guest_routes() -> Router<AppState> {
     Router::new()
         .route("/fund", post(self::handlers::guest::create_guest_donation))
         .route("/verify", post(self::handlers::guest::verify_guest_donation))
+        .route("/claim", post(self::handlers::guest::claim_guest_donations))
         .route("/projects", get(self::handlers::guest::get_public_projects))
 }
 
@@ -136,8 +453,13 @@ pub fn milestone_routes() -> Router<AppState> {
     Router::new()
         .route("/projects/:project_id/milestones", post(self::handlers::milestones::create_milestone))
         .route("/projects/:project_id/milestones", get(self::handlers::milestones::get_project_milestones))
-        .route("/projects/:project_id/milestones/:milestone_id/release", post(self::handlers::milestones::release_milestone))
+        .route("/projects/:project_id/milestones/:milestone_id/proof", post(self::handlers::milestones::submit_proof))
         .route_layer(middleware::from_fn(require_verified_student_mw))
+        // Releases move platform funds, so only admins may trigger them
+        .route(
+            "/projects/:project_id/milestones/:milestone_id/release",
+            post(self::handlers::milestones::release_milestone).layer(middleware::from_fn(require_admin_mw)),
+        )
 }
 
 pub fn contract_routes() -> Router<AppState> {
@@ -145,10 +467,13 @@ pub fn contract_routes() -> Router<AppState> {
         .route("/deploy", post(self::handlers::contracts::deploy_contracts))
         .route("/milestones/register", post(self::handlers::contracts::register_milestone))
         .route("/milestones/release", post(self::handlers::contracts::release_milestone))
+        .route("/milestones/vote/open", post(self::handlers::contracts::open_milestone_vote))
         .route("/deposits/record", post(self::handlers::contracts::record_deposit))
         .route("/projects/:project_id/balance", get(self::handlers::contracts::get_project_balance))
+        .route("/projects/:project_id/donors/:donor_address/total", get(self::handlers::contracts::get_donor_total))
         .route("/projects/:project_id/milestones", get(self::handlers::contracts::get_project_milestones))
         .route("/addresses", get(self::handlers::contracts::get_contract_addresses))
+        .route("/events", get(self::handlers::contracts::get_onchain_events))
         .route_layer(middleware::from_fn(require_admin_mw))
 }
 
@@ -156,9 +481,16 @@ pub fn payment_routes() -> Router<AppState> {
     Router::new()
         .route("/initiate", post(self::handlers::payments::initiate_payment))
         .route("/mpesa/webhook", post(self::handlers::payments::mpesa_webhook))
+        .route("/mpesa/b2c/result", post(self::handlers::payments::mpesa_b2c_result))
+        .route("/mpesa/b2c/timeout", post(self::handlers::payments::mpesa_b2c_timeout))
+        .route("/mpesa/reversal/result", post(self::handlers::payments::mpesa_reversal_result))
+        .route("/mpesa/reversal/timeout", post(self::handlers::payments::mpesa_reversal_timeout))
+        .route("/airtel/callback", post(self::handlers::payments::airtel_callback))
         .route("/stripe/webhook", post(self::handlers::payments::stripe_webhook))
-        .route("/refund", post(self::handlers::payments::process_refund))
+        .route("/flutterwave/webhook", post(self::handlers::payments::flutterwave_webhook))
+        .route("/paypal/webhook", post(self::handlers::payments::paypal_webhook))
         .route("/providers", get(self::handlers::payments::get_providers))
+        .route("/quote", get(self::handlers::payments::get_quote))
         .route("/status", get(self::handlers::payments::get_payment_status))
 }
 
@@ -170,6 +502,38 @@ pub fn notification_routes() -> Router<AppState> {
         .route("/:id/read", axum::routing::put(self::handlers::notifications::mark_notification_read))
         .route("/:id", axum::routing::delete(self::handlers::notifications::delete_notification))
         .route("/create", post(self::handlers::notifications::create_notification))
+        .route("/push/public-key", get(self::handlers::push_subscriptions::public_key))
+        .route(
+            "/push/subscribe",
+            post(self::handlers::push_subscriptions::subscribe).delete(self::handlers::push_subscriptions::unsubscribe),
+        )
+        .route(
+            "/preferences",
+            get(self::handlers::notification_preferences::get_preferences)
+                .put(self::handlers::notification_preferences::update_preferences),
+        )
+        .route(
+            "/digest",
+            get(self::handlers::notification_preferences::get_digest)
+                .put(self::handlers::notification_preferences::update_digest),
+        )
+        // Linked from digest emails; POST for one-click unsubscribe
+        .route(
+            "/digest/unsubscribe",
+            get(self::handlers::notification_preferences::unsubscribe_digest)
+                .post(self::handlers::notification_preferences::unsubscribe_digest),
+        )
+}
+
+/// Project owners' outgoing webhook endpoints
+pub fn webhook_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/endpoints",
+            get(self::handlers::webhooks::list_my_endpoints).post(self::handlers::webhooks::create_my_endpoint),
+        )
+        .route("/endpoints/:id", axum::routing::delete(self::handlers::webhooks::disable_my_endpoint))
+        .route_layer(middleware::from_fn(require_auth_mw))
 }
 
 pub fn docs_routes() -> Router<AppState> {
@@ -179,17 +543,6 @@ pub fn docs_routes() -> Router<AppState> {
         .route("/health", get(handlers::docs::health_check))
 }
 
-pub async fn sse_notifications(State(state): State<AppState>) -> impl IntoResponse {
-    let rx = state.notifier.subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(|msg| async move {
-        match msg {
-            Ok(s) => Some(Ok::<Event, std::convert::Infallible>(Event::default().data(s))),
-            Err(_) => None,
-        }
-    });
-    Sse::new(stream)
-}
-
 // Analytics endpoints
 pub async fn analytics_top_projects(State(state): State<AppState>) -> impl IntoResponse {
     let rows = sqlx::query!(
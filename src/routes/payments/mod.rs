@@ -1,4 +1,7 @@
+pub mod airtel;
+pub mod flutterwave;
 pub mod mpesa;
+pub mod paypal;
 pub mod stripe;
 pub mod provider;
 
@@ -1,4 +1,5 @@
 use super::provider::*;
+use crate::utils::money::Cents;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -73,6 +74,169 @@ struct MpesaCallbackItem {
     value: String,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct MpesaStkQueryRequest {
+    business_short_code: String,
+    password: String,
+    timestamp: String,
+    #[serde(rename = "CheckoutRequestID")]
+    checkout_request_id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct MpesaB2cRequest {
+    #[serde(rename = "OriginatorConversationID")]
+    originator_conversation_id: String,
+    initiator_name: String,
+    security_credential: String,
+    #[serde(rename = "CommandID")]
+    command_id: String,
+    amount: u32,
+    party_a: String,
+    party_b: String,
+    remarks: String,
+    #[serde(rename = "QueueTimeOutURL")]
+    queue_timeout_url: String,
+    #[serde(rename = "ResultURL")]
+    result_url: String,
+    occasion: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct MpesaReversalRequest {
+    initiator: String,
+    security_credential: String,
+    #[serde(rename = "CommandID")]
+    command_id: String,
+    #[serde(rename = "TransactionID")]
+    transaction_id: String,
+    amount: u32,
+    receiver_party: String,
+    // Daraja's spelling
+    #[serde(rename = "RecieverIdentifierType")]
+    receiver_identifier_type: String,
+    #[serde(rename = "ResultURL")]
+    result_url: String,
+    #[serde(rename = "QueueTimeOutURL")]
+    queue_timeout_url: String,
+    remarks: String,
+    occasion: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct MpesaB2cResponse {
+    #[serde(rename = "ConversationID")]
+    conversation_id: String,
+    response_code: String,
+    response_description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MpesaB2cCallback {
+    #[serde(rename = "Result")]
+    result: MpesaB2cCallbackResult,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct MpesaB2cCallbackResult {
+    result_code: i64,
+    result_desc: String,
+    #[serde(rename = "OriginatorConversationID")]
+    originator_conversation_id: String,
+    #[serde(rename = "ConversationID")]
+    conversation_id: String,
+    #[serde(rename = "TransactionID")]
+    transaction_id: Option<String>,
+}
+
+/// A B2C payout accepted for processing; the outcome arrives on the result URL
+#[derive(Debug, Clone)]
+pub struct B2cAccepted {
+    pub conversation_id: String,
+    pub description: String,
+}
+
+/// Why a B2C payout request didn't go through
+#[derive(Debug, Clone, PartialEq)]
+pub enum B2cSendError {
+    /// Never sent, or refused by M-Pesa; no money moved
+    Rejected(String),
+    /// The request may have reached M-Pesa, so the payout can still happen;
+    /// its result callback settles it
+    Unknown(String),
+}
+
+impl std::fmt::Display for B2cSendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            B2cSendError::Rejected(reason) | B2cSendError::Unknown(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// Outcome reported on the B2C result URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct B2cResult {
+    /// The id we sent as OriginatorConversationID
+    pub originator_conversation_id: String,
+    pub conversation_id: String,
+    pub successful: bool,
+    /// M-Pesa receipt for successful payouts
+    pub receipt: Option<String>,
+    pub description: String,
+}
+
+impl B2cResult {
+    /// Parse the body Safaricom posts to the B2C or reversal result URL;
+    /// both share the same shape
+    pub fn from_callback(body: &serde_json::Value) -> Result<Self, String> {
+        let callback: MpesaB2cCallback = serde_json::from_value(body.clone())
+            .map_err(|e| format!("Failed to parse M-Pesa B2C result: {}", e))?;
+        let result = callback.result;
+        Ok(Self {
+            originator_conversation_id: result.originator_conversation_id,
+            conversation_id: result.conversation_id,
+            successful: result.result_code == 0,
+            receipt: result.transaction_id.filter(|id| !id.is_empty()),
+            description: result.result_desc,
+        })
+    }
+}
+
+/// Map an STK push query response to a payment status. Daraja answers a push
+/// that is still in flight with an HTTP error carrying "being processed", so
+/// that case is `Pending` rather than an error.
+fn stk_query_status(success: bool, body: &serde_json::Value) -> Result<PaymentStatus, String> {
+    if !success {
+        let code = body["errorCode"].as_str().unwrap_or_default();
+        let message = body["errorMessage"].as_str().unwrap_or_default();
+        if code == "500.001.1001" || message.contains("being processed") {
+            return Ok(PaymentStatus::Pending);
+        }
+        return Err(format!("STK query rejected: {} {}", code, message).trim_end().to_string());
+    }
+
+    // ResultCode comes back as a string, though older responses used a number
+    let result_code = match &body["ResultCode"] {
+        serde_json::Value::String(code) => code.clone(),
+        serde_json::Value::Number(code) => code.to_string(),
+        _ => return Err("STK query response has no ResultCode".to_string()),
+    };
+
+    Ok(match result_code.as_str() {
+        "0" => PaymentStatus::Completed,
+        "1032" => PaymentStatus::Cancelled,
+        "1019" | "1037" => PaymentStatus::Expired,
+        "4999" => PaymentStatus::Pending,
+        _ => PaymentStatus::Failed,
+    })
+}
+
 impl MpesaProvider {
     pub fn new(config: MpesaConfig) -> Self {
         Self {
@@ -123,8 +287,8 @@ impl MpesaProvider {
         Ok(token_response.access_token)
     }
 
-    fn generate_password(&self) -> String {
-        let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S").to_string();
+    /// Daraja password for a request sent with `timestamp`
+    fn generate_password(&self, timestamp: &str) -> String {
         let password_string = format!("{}{}{}", 
             self.config.business_short_code, 
             self.config.passkey, 
@@ -133,6 +297,115 @@ impl MpesaProvider {
         base64::encode(password_string)
     }
 
+    /// Send KES to a phone number from the B2C short code. `reference` comes
+    /// back as the originator conversation id on the result callback.
+    pub async fn send_b2c_payment(
+        &self,
+        reference: &str,
+        phone: &str,
+        amount: Cents,
+        remarks: &str,
+    ) -> Result<B2cAccepted, B2cSendError> {
+        let b2c = self
+            .config
+            .b2c
+            .as_ref()
+            .ok_or_else(|| B2cSendError::Rejected("M-Pesa B2C payouts are not configured".to_string()))?;
+        let mut provider = self.clone();
+        let access_token = provider.get_access_token().await.map_err(B2cSendError::Rejected)?;
+
+        let request = MpesaB2cRequest {
+            originator_conversation_id: reference.to_string(),
+            initiator_name: b2c.initiator_name.clone(),
+            security_credential: b2c.security_credential.clone(),
+            command_id: "BusinessPayment".to_string(),
+            // B2C takes whole shillings; any cents are dropped
+            amount: u32::try_from(amount.as_cents() / 100)
+                .map_err(|_| B2cSendError::Rejected("Payout amount out of range".to_string()))?,
+            party_a: b2c.short_code.clone(),
+            party_b: self.format_phone_number(phone),
+            remarks: remarks.to_string(),
+            queue_timeout_url: b2c.tokened_url(&b2c.timeout_url),
+            result_url: b2c.tokened_url(&b2c.result_url),
+            occasion: "FundHub milestone payout".to_string(),
+        };
+
+        let url = if self.config.environment == "production" {
+            "https://api.safaricom.co.ke/mpesa/b2c/v3/paymentrequest"
+        } else {
+            "https://sandbox.safaricom.co.ke/mpesa/b2c/v3/paymentrequest"
+        };
+
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| B2cSendError::Unknown(format!("Failed to send B2C payment: {}", e)))?;
+
+        let status = response.status();
+        if status.is_server_error() {
+            return Err(B2cSendError::Unknown(format!("B2C payment request failed: {}", status)));
+        }
+        if !status.is_success() {
+            return Err(B2cSendError::Rejected(format!("B2C payment request rejected: {}", status)));
+        }
+
+        let b2c_response: MpesaB2cResponse = response
+            .json()
+            .await
+            .map_err(|e| B2cSendError::Unknown(format!("Failed to parse B2C response: {}", e)))?;
+
+        if b2c_response.response_code != "0" {
+            return Err(B2cSendError::Rejected(format!("B2C payment failed: {}", b2c_response.response_description)));
+        }
+
+        Ok(B2cAccepted {
+            conversation_id: b2c_response.conversation_id,
+            description: b2c_response.response_description,
+        })
+    }
+
+    /// Ask M-Pesa for the outcome of an STK push, for when its callback never
+    /// arrived. Pushes still waiting on the customer report `Pending`.
+    pub async fn query_stk_status(&self, checkout_request_id: &str) -> Result<PaymentStatus, String> {
+        let mut provider = self.clone();
+        let access_token = provider.get_access_token().await?;
+
+        let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S").to_string();
+        let request = MpesaStkQueryRequest {
+            business_short_code: self.config.business_short_code.clone(),
+            password: self.generate_password(&timestamp),
+            timestamp,
+            checkout_request_id: checkout_request_id.to_string(),
+        };
+
+        let url = if self.config.environment == "production" {
+            "https://api.safaricom.co.ke/mpesa/stkpushquery/v1/query"
+        } else {
+            "https://sandbox.safaricom.co.ke/mpesa/stkpushquery/v1/query"
+        };
+
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to query STK push status: {}", e))?;
+
+        let status = response.status();
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse STK query response: {}", e))?;
+
+        stk_query_status(status.is_success(), &body)
+    }
+
     fn format_phone_number(&self, phone: &str) -> String {
         let mut formatted = phone.replace("+", "").replace(" ", "");
         if formatted.starts_with("0") {
@@ -156,14 +429,14 @@ impl PaymentProvider for MpesaProvider {
         
         let formatted_phone = self.format_phone_number(phone);
         let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S").to_string();
-        let password = self.generate_password();
+        let password = self.generate_password(&timestamp);
         
         let stk_push_request = MpesaStkPushRequest {
             business_short_code: self.config.business_short_code.clone(),
             password,
             timestamp: timestamp.clone(),
             transaction_type: "CustomerPayBillOnline".to_string(),
-            amount: (request.amount * 100.0) as u32, // Convert to cents
+            amount: request.amount.as_u32().map_err(|e| e.to_string())?, // Minor units
             party_a: formatted_phone.clone(),
             party_b: self.config.business_short_code.clone(),
             phone_number: formatted_phone,
@@ -230,37 +503,105 @@ impl PaymentProvider for MpesaProvider {
             _ => PaymentStatus::Failed,
         };
 
-        let amount = if let Some(metadata) = stk_callback.callback_metadata {
+        let amount = if let Some(metadata) = &stk_callback.callback_metadata {
             if let Some(amount_item) = metadata.item.iter().find(|item| item.name == "Amount") {
-                amount_item.value.parse::<f64>().unwrap_or(0.0) / 100.0 // Convert from cents
+                // Reported in minor units
+                Cents::from_cents(amount_item.value.parse::<f64>().map(|v| v.round() as i64).unwrap_or(0))
             } else {
-                0.0
+                Cents::ZERO
             }
         } else {
-            0.0
+            Cents::ZERO
         };
 
+        // The receipt is what reversals are made against
+        let receipt = stk_callback
+            .callback_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.item.iter().find(|item| item.name == "MpesaReceiptNumber"))
+            .map(|item| item.value.clone());
+
         Ok(VerificationResult {
             payment_id: stk_callback.checkout_request_id,
             status,
             amount,
             currency: "KES".to_string(),
-            transaction_id: Some(stk_callback.merchant_request_id),
+            transaction_id: receipt.or(Some(stk_callback.merchant_request_id)),
             provider_response: webhook.raw_data,
         })
     }
 
-    async fn refund(&self, _request: RefundRequest) -> Result<String, String> {
-        Err("M-Pesa refunds not implemented yet".to_string())
+    /// Reverse the donor's payment. Reversals run asynchronously: the
+    /// conversation id returned here comes back on the reversal result URL.
+    async fn refund(&self, request: RefundRequest) -> Result<RefundResult, String> {
+        let initiator = self
+            .config
+            .b2c
+            .as_ref()
+            .ok_or("M-Pesa reversals need the B2C initiator to be configured")?;
+        let receipt = request
+            .provider_reference
+            .as_deref()
+            .ok_or("M-Pesa reversals need the payment's M-Pesa receipt")?;
+        let amount = request.amount.ok_or("Amount is required for M-Pesa reversals")?;
+        let mut provider = self.clone();
+        let access_token = provider.get_access_token().await?;
+
+        let reversal = MpesaReversalRequest {
+            initiator: initiator.initiator_name.clone(),
+            security_credential: initiator.security_credential.clone(),
+            command_id: "TransactionReversal".to_string(),
+            transaction_id: receipt.to_string(),
+            // Reversals take whole shillings; any cents are dropped
+            amount: u32::try_from(amount.as_cents() / 100).map_err(|_| "Refund amount out of range")?,
+            receiver_party: self.config.business_short_code.clone(),
+            receiver_identifier_type: "11".to_string(),
+            result_url: initiator.tokened_url(&initiator.reversal_result_url),
+            queue_timeout_url: initiator.tokened_url(&initiator.reversal_timeout_url),
+            remarks: request.reason.chars().take(100).collect(),
+            occasion: "FundHub donation refund".to_string(),
+        };
+
+        let url = if self.config.environment == "production" {
+            "https://api.safaricom.co.ke/mpesa/reversal/v1/request"
+        } else {
+            "https://sandbox.safaricom.co.ke/mpesa/reversal/v1/request"
+        };
+
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .json(&reversal)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to request reversal: {}", e))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("M-Pesa reversal rejected: {}", error_text));
+        }
+
+        let accepted: MpesaB2cResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse reversal response: {}", e))?;
+
+        if accepted.response_code != "0" {
+            return Err(format!("M-Pesa reversal failed: {}", accepted.response_description));
+        }
+
+        Ok(RefundResult {
+            refund_id: accepted.conversation_id,
+            status: RefundStatus::Pending,
+        })
     }
 
-    async fn get_payment_status(&self, _payment_id: &str) -> Result<PaymentStatus, String> {
-        // M-Pesa doesn't provide a direct status check API
-        // Status is typically determined through webhooks
-        Ok(PaymentStatus::Pending)
+    async fn get_payment_status(&self, payment_id: &str) -> Result<PaymentStatus, String> {
+        self.query_stk_status(payment_id).await
     }
 
-    fn validate_webhook(&self, _payload: &str, _signature: &str) -> bool {
+    async fn validate_webhook(&self, _payload: &str, _signature: &str, _received_at: chrono::DateTime<chrono::Utc>) -> bool {
         // M-Pesa webhook validation would require HMAC verification
         // For now, we'll trust the webhook (in production, implement proper validation)
         true
@@ -271,3 +612,67 @@ impl PaymentProvider for MpesaProvider {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stk_query_status() {
+        let status = |code: serde_json::Value| {
+            stk_query_status(true, &serde_json::json!({ "ResponseCode": "0", "ResultCode": code })).unwrap()
+        };
+        assert!(matches!(status("0".into()), PaymentStatus::Completed));
+        assert!(matches!(status(1032.into()), PaymentStatus::Cancelled));
+        assert!(matches!(status("1037".into()), PaymentStatus::Expired));
+        assert!(matches!(status("2001".into()), PaymentStatus::Failed));
+        assert!(matches!(status("4999".into()), PaymentStatus::Pending));
+
+        let processing = serde_json::json!({
+            "requestId": "4153-5839071-1",
+            "errorCode": "500.001.1001",
+            "errorMessage": "The transaction is being processed"
+        });
+        assert!(matches!(stk_query_status(false, &processing).unwrap(), PaymentStatus::Pending));
+
+        let invalid = serde_json::json!({ "errorCode": "400.002.02", "errorMessage": "Bad Request - Invalid CheckoutRequestID" });
+        assert!(stk_query_status(false, &invalid).is_err());
+    }
+
+    #[test]
+    fn test_reversal_result_parses_like_b2c() {
+        let body = serde_json::json!({
+            "Result": {
+                "ResultType": 0,
+                "ResultCode": 0,
+                "ResultDesc": "The service request is processed successfully.",
+                "OriginatorConversationID": "8521-4298025-1",
+                "ConversationID": "AG_20181005_00004d7ee675c0c7ee0b",
+                "TransactionID": "MJ561H6X5O"
+            }
+        });
+        let result = B2cResult::from_callback(&body).unwrap();
+        assert!(result.successful);
+        assert_eq!(result.conversation_id, "AG_20181005_00004d7ee675c0c7ee0b");
+        assert_eq!(result.receipt.as_deref(), Some("MJ561H6X5O"));
+    }
+
+    #[test]
+    fn test_b2c_callback_token() {
+        let b2c = MpesaB2cConfig {
+            initiator_name: "fundhub".to_string(),
+            security_credential: "credential".to_string(),
+            short_code: "600000".to_string(),
+            result_url: "https://example.com/api/payments/mpesa/b2c/result".to_string(),
+            timeout_url: "https://example.com/api/payments/mpesa/b2c/timeout?v=1".to_string(),
+            reversal_result_url: "https://example.com/api/payments/mpesa/reversal/result".to_string(),
+            reversal_timeout_url: "https://example.com/api/payments/mpesa/reversal/timeout".to_string(),
+            callback_token: "s3cret".to_string(),
+        };
+
+        assert_eq!(b2c.tokened_url(&b2c.result_url), "https://example.com/api/payments/mpesa/b2c/result?token=s3cret");
+        assert_eq!(b2c.tokened_url(&b2c.timeout_url), "https://example.com/api/payments/mpesa/b2c/timeout?v=1&token=s3cret");
+        assert!(b2c.accepts_callback_token(Some("s3cret")));
+        assert!(!b2c.accepts_callback_token(Some("s3cre")));
+        assert!(!b2c.accepts_callback_token(None));
+    }
+}
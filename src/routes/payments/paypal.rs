@@ -0,0 +1,564 @@
+use super::provider::*;
+use crate::utils::money::Cents;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct PaypalProvider {
+    config: PaypalConfig,
+    client: Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaypalTokenResponse {
+    access_token: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PaypalOrderRequest {
+    intent: String,
+    purchase_units: Vec<PaypalPurchaseUnit>,
+    payment_source: PaypalPaymentSource,
+}
+
+#[derive(Debug, Serialize)]
+struct PaypalPurchaseUnit {
+    reference_id: String,
+    custom_id: String,
+    description: String,
+    amount: PaypalAmount,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PaypalAmount {
+    currency_code: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PaypalPaymentSource {
+    paypal: PaypalWallet,
+}
+
+#[derive(Debug, Serialize)]
+struct PaypalWallet {
+    experience_context: PaypalExperienceContext,
+}
+
+#[derive(Debug, Serialize)]
+struct PaypalExperienceContext {
+    brand_name: String,
+    shipping_preference: String,
+    user_action: String,
+    return_url: String,
+    cancel_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaypalOrder {
+    id: String,
+    /// CREATED, SAVED, APPROVED, VOIDED, COMPLETED, or PAYER_ACTION_REQUIRED
+    status: String,
+    #[serde(default)]
+    links: Vec<PaypalLink>,
+    #[serde(default)]
+    purchase_units: Vec<PaypalOrderUnit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaypalLink {
+    href: String,
+    rel: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaypalOrderUnit {
+    payments: Option<PaypalUnitPayments>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaypalUnitPayments {
+    #[serde(default)]
+    captures: Vec<PaypalCapture>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaypalCapture {
+    id: String,
+    /// COMPLETED, DECLINED, PARTIALLY_REFUNDED, PENDING, REFUNDED, or FAILED
+    status: String,
+    amount: PaypalAmount,
+}
+
+#[derive(Debug, Deserialize)]
+struct PaypalWebhookEvent {
+    id: String,
+    event_type: String,
+    resource: serde_json::Value,
+}
+
+/// Transmission headers PayPal signs each webhook with. The handler packs
+/// them into the webhook's signature string as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaypalTransmission {
+    pub transmission_id: String,
+    pub transmission_time: String,
+    pub transmission_sig: String,
+    pub cert_url: String,
+    pub auth_algo: String,
+}
+
+impl PaypalTransmission {
+    pub fn from_headers(headers: &axum::http::HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name)?.to_str().ok().map(|v| v.to_string());
+        Some(Self {
+            transmission_id: header("paypal-transmission-id")?,
+            transmission_time: header("paypal-transmission-time")?,
+            transmission_sig: header("paypal-transmission-sig")?,
+            cert_url: header("paypal-cert-url")?,
+            auth_algo: header("paypal-auth-algo")?,
+        })
+    }
+
+    /// The string PayPal signs: transmission id, time, our webhook id, and the
+    /// CRC32 of the raw body, joined by pipes
+    fn signed_message(&self, webhook_id: &str, payload: &str) -> String {
+        format!(
+            "{}|{}|{}|{}",
+            self.transmission_id,
+            self.transmission_time,
+            webhook_id,
+            crc32fast::hash(payload.as_bytes())
+        )
+    }
+}
+
+/// Only fetch signing certificates from PayPal's own API hosts
+fn trusted_cert_url(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else { return false };
+    url.scheme() == "https"
+        && matches!(url.host_str(), Some("api.paypal.com" | "api-m.paypal.com" | "api.sandbox.paypal.com" | "api-m.sandbox.paypal.com"))
+}
+
+fn order_status(status: &str) -> PaymentStatus {
+    match status {
+        "COMPLETED" => PaymentStatus::Completed,
+        "APPROVED" => PaymentStatus::Processing,
+        "VOIDED" => PaymentStatus::Cancelled,
+        _ => PaymentStatus::Pending,
+    }
+}
+
+fn capture_status(status: &str) -> PaymentStatus {
+    match status {
+        "COMPLETED" => PaymentStatus::Completed,
+        "PENDING" => PaymentStatus::Processing,
+        _ => PaymentStatus::Failed,
+    }
+}
+
+/// PayPal amounts are decimal strings in major units
+fn to_cents(amount: &PaypalAmount) -> Cents {
+    amount.value.parse().unwrap_or(Cents::ZERO)
+}
+
+impl PaypalProvider {
+    pub fn new(config: PaypalConfig) -> Self {
+        Self {
+            config,
+            client: Client::new(),
+        }
+    }
+
+    fn api_base(&self) -> &'static str {
+        if self.config.environment == "live" {
+            "https://api-m.paypal.com"
+        } else {
+            "https://api-m.sandbox.paypal.com"
+        }
+    }
+
+    async fn get_access_token(&self) -> Result<String, String> {
+        let response = self
+            .client
+            .post(format!("{}/v1/oauth2/token", self.api_base()))
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[("grant_type", "client_credentials")])
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get PayPal access token: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err("Failed to get PayPal access token".to_string());
+        }
+
+        let token: PaypalTokenResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse PayPal token response: {}", e))?;
+
+        Ok(token.access_token)
+    }
+
+    async fn get_order(&self, order_id: &str) -> Result<PaypalOrder, String> {
+        let access_token = self.get_access_token().await?;
+        let response = self
+            .client
+            .get(format!("{}/v2/checkout/orders/{}", self.api_base(), order_id))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get PayPal order: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Failed to get PayPal order: {}", response.status()));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse PayPal order: {}", e))
+    }
+
+    /// Capture an approved order. The order id doubles as the request id, so
+    /// retried captures of the same order are answered rather than repeated.
+    pub async fn capture_order(&self, order_id: &str) -> Result<PaypalOrderCapture, String> {
+        let access_token = self.get_access_token().await?;
+        let response = self
+            .client
+            .post(format!("{}/v2/checkout/orders/{}/capture", self.api_base(), order_id))
+            .bearer_auth(access_token)
+            .header("PayPal-Request-Id", format!("capture-{}", order_id))
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .map_err(|e| format!("Failed to capture PayPal order: {}", e))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("PayPal capture error: {}", error_text));
+        }
+
+        let order: PaypalOrder = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse PayPal capture: {}", e))?;
+
+        let capture = order
+            .purchase_units
+            .into_iter()
+            .filter_map(|unit| unit.payments)
+            .flat_map(|payments| payments.captures)
+            .next()
+            .ok_or("PayPal capture response has no capture")?;
+
+        Ok(PaypalOrderCapture {
+            capture_id: capture.id,
+            status: capture_status(&capture.status),
+            amount: to_cents(&capture.amount),
+            currency: capture.amount.currency_code,
+        })
+    }
+
+    /// Check a webhook's RSA signature against the certificate PayPal points to
+    async fn verify_transmission(&self, transmission: &PaypalTransmission, payload: &str) -> Result<(), String> {
+        use base64::Engine;
+        use rsa::pkcs1v15::{Signature, VerifyingKey};
+        use rsa::pkcs8::DecodePublicKey;
+        use rsa::signature::Verifier;
+        use x509_cert::der::{DecodePem, Encode};
+
+        if transmission.auth_algo != "SHA256withRSA" {
+            return Err(format!("Unsupported PayPal auth algorithm '{}'", transmission.auth_algo));
+        }
+        if !trusted_cert_url(&transmission.cert_url) {
+            return Err("PayPal certificate URL is not a PayPal host".to_string());
+        }
+
+        let pem = self
+            .client
+            .get(&transmission.cert_url)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch PayPal certificate: {}", e))?
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read PayPal certificate: {}", e))?;
+
+        let certificate = x509_cert::Certificate::from_pem(pem.as_bytes())
+            .map_err(|e| format!("Invalid PayPal certificate: {}", e))?;
+        let validity = &certificate.tbs_certificate.validity;
+        let now = std::time::SystemTime::now();
+        if now < validity.not_before.to_system_time() || now > validity.not_after.to_system_time() {
+            return Err("PayPal certificate is not currently valid".to_string());
+        }
+
+        let spki = certificate
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(|e| format!("Invalid PayPal certificate key: {}", e))?;
+        let public_key = rsa::RsaPublicKey::from_public_key_der(&spki)
+            .map_err(|e| format!("Invalid PayPal certificate key: {}", e))?;
+
+        let signature = base64::engine::general_purpose::STANDARD
+            .decode(&transmission.transmission_sig)
+            .map_err(|e| format!("Invalid PayPal signature encoding: {}", e))?;
+        let signature = Signature::try_from(signature.as_slice())
+            .map_err(|e| format!("Invalid PayPal signature: {}", e))?;
+
+        let message = transmission.signed_message(&self.config.webhook_id, payload);
+        VerifyingKey::<sha2::Sha256>::new(public_key)
+            .verify(message.as_bytes(), &signature)
+            .map_err(|_| "PayPal signature does not match".to_string())
+    }
+}
+
+/// Outcome of capturing an approved order
+#[derive(Debug, Clone)]
+pub struct PaypalOrderCapture {
+    pub capture_id: String,
+    pub status: PaymentStatus,
+    pub amount: Cents,
+    pub currency: String,
+}
+
+#[async_trait]
+impl PaymentProvider for PaypalProvider {
+    async fn initiate_payment(&self, request: InitiatePaymentRequest) -> Result<PaymentInstruction, String> {
+        let access_token = self.get_access_token().await?;
+
+        let order_request = PaypalOrderRequest {
+            intent: "CAPTURE".to_string(),
+            purchase_units: vec![PaypalPurchaseUnit {
+                reference_id: request.project_id.to_string(),
+                custom_id: request.project_id.to_string(),
+                description: request.memo.clone().unwrap_or_else(|| "FundHub Donation".to_string()),
+                amount: PaypalAmount {
+                    currency_code: request.currency.to_uppercase(),
+                    value: request.amount.to_string(),
+                },
+            }],
+            payment_source: PaypalPaymentSource {
+                paypal: PaypalWallet {
+                    experience_context: PaypalExperienceContext {
+                        brand_name: "FundHub".to_string(),
+                        shipping_preference: "NO_SHIPPING".to_string(),
+                        user_action: "PAY_NOW".to_string(),
+                        return_url: self.config.return_url.clone(),
+                        cancel_url: self.config.cancel_url.clone(),
+                    },
+                },
+            },
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/v2/checkout/orders", self.api_base()))
+            .bearer_auth(access_token)
+            .header("Content-Type", "application/json")
+            .json(&order_request)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to create PayPal order: {}", e))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("PayPal API error: {}", error_text));
+        }
+
+        let order: PaypalOrder = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse PayPal order: {}", e))?;
+
+        let approve_url = order
+            .links
+            .iter()
+            .find(|link| link.rel == "payer-action" || link.rel == "approve")
+            .map(|link| link.href.clone())
+            .ok_or("PayPal order has no approval link")?;
+
+        let mut instructions = HashMap::new();
+        instructions.insert("order_id".to_string(), order.id.clone());
+        instructions.insert("checkout_url".to_string(), approve_url.clone());
+
+        Ok(PaymentInstruction {
+            payment_id: order.id,
+            checkout_url: Some(approve_url),
+            payment_method: "paypal".to_string(),
+            instructions,
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(3), // Unapproved orders lapse after 3 hours
+        })
+    }
+
+    async fn verify_payment(&self, webhook: ProviderWebhook) -> Result<VerificationResult, String> {
+        if webhook.provider != "paypal" {
+            return Err("Invalid provider for PayPal webhook".to_string());
+        }
+
+        let event: PaypalWebhookEvent = serde_json::from_value(webhook.raw_data.clone())
+            .map_err(|e| format!("Failed to parse PayPal webhook: {}", e))?;
+
+        match event.event_type.as_str() {
+            // The donor approved the order; take the money
+            "CHECKOUT.ORDER.APPROVED" => {
+                let order_id = event.resource["id"].as_str().ok_or("PayPal order event has no order id")?;
+                let capture = self.capture_order(order_id).await?;
+                Ok(VerificationResult {
+                    payment_id: order_id.to_string(),
+                    status: capture.status,
+                    amount: capture.amount,
+                    currency: capture.currency,
+                    transaction_id: Some(capture.capture_id),
+                    provider_response: webhook.raw_data,
+                })
+            }
+            "PAYMENT.CAPTURE.COMPLETED" | "PAYMENT.CAPTURE.PENDING" | "PAYMENT.CAPTURE.DENIED" | "PAYMENT.CAPTURE.DECLINED" => {
+                let capture: PaypalCapture = serde_json::from_value(event.resource.clone())
+                    .map_err(|e| format!("Failed to parse PayPal capture: {}", e))?;
+                let order_id = event.resource["supplementary_data"]["related_ids"]["order_id"]
+                    .as_str()
+                    .ok_or("PayPal capture event has no order id")?;
+                Ok(VerificationResult {
+                    payment_id: order_id.to_string(),
+                    status: capture_status(&capture.status),
+                    amount: to_cents(&capture.amount),
+                    currency: capture.amount.currency_code,
+                    transaction_id: Some(capture.id),
+                    provider_response: webhook.raw_data,
+                })
+            }
+            other => Err(format!("Unhandled PayPal event '{}' ({})", other, event.id)),
+        }
+    }
+
+    async fn refund(&self, request: RefundRequest) -> Result<RefundResult, String> {
+        let order = self.get_order(&request.payment_id).await?;
+        let capture = order
+            .purchase_units
+            .into_iter()
+            .filter_map(|unit| unit.payments)
+            .flat_map(|payments| payments.captures)
+            .next()
+            .ok_or("PayPal order has no capture to refund")?;
+
+        // Leaving the amount out refunds the full capture
+        let mut refund_data = serde_json::json!({ "note_to_payer": request.reason });
+        if let Some(amount) = request.amount {
+            refund_data["amount"] = serde_json::json!({
+                "currency_code": capture.amount.currency_code,
+                "value": amount.to_string()
+            });
+        }
+
+        let access_token = self.get_access_token().await?;
+        let response = self
+            .client
+            .post(format!("{}/v2/payments/captures/{}/refund", self.api_base(), capture.id))
+            .bearer_auth(access_token)
+            .json(&refund_data)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to create refund: {}", e))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("PayPal refund error: {}", error_text));
+        }
+
+        let refund: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse refund response: {}", e))?;
+
+        Ok(RefundResult {
+            refund_id: refund["id"].as_str().unwrap_or("unknown").to_string(),
+            status: match refund["status"].as_str().unwrap_or_default() {
+                "COMPLETED" => RefundStatus::Succeeded,
+                "FAILED" | "CANCELLED" => RefundStatus::Failed,
+                _ => RefundStatus::Pending,
+            },
+        })
+    }
+
+    async fn get_payment_status(&self, payment_id: &str) -> Result<PaymentStatus, String> {
+        let order = self.get_order(payment_id).await?;
+        Ok(order_status(&order.status))
+    }
+
+    async fn validate_webhook(&self, payload: &str, signature: &str, received_at: chrono::DateTime<chrono::Utc>) -> bool {
+        let Ok(transmission) = serde_json::from_str::<PaypalTransmission>(signature) else {
+            tracing::warn!("Rejected PayPal webhook: missing transmission headers");
+            return false;
+        };
+
+        // Same replay window as other providers' signed timestamps
+        let fresh = chrono::DateTime::parse_from_rfc3339(&transmission.transmission_time)
+            .map(|sent| (received_at - sent.with_timezone(&chrono::Utc)).num_seconds().abs() <= self.config.webhook_tolerance_secs)
+            .unwrap_or(false);
+        if !fresh {
+            tracing::warn!("Rejected PayPal webhook: transmission time outside the tolerance window");
+            return false;
+        }
+
+        match self.verify_transmission(&transmission, payload).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Rejected PayPal webhook: {}", e);
+                false
+            }
+        }
+    }
+
+    fn get_provider_name(&self) -> &str {
+        "paypal"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_message_uses_body_crc32() {
+        let transmission = PaypalTransmission {
+            transmission_id: "69cd13f0-d67a-11e5-baa3-778b53f4ae55".to_string(),
+            transmission_time: "2025-10-21T20:19:09Z".to_string(),
+            transmission_sig: String::new(),
+            cert_url: String::new(),
+            auth_algo: "SHA256withRSA".to_string(),
+        };
+        assert_eq!(
+            transmission.signed_message("WH-1", "hello"),
+            format!("69cd13f0-d67a-11e5-baa3-778b53f4ae55|2025-10-21T20:19:09Z|WH-1|{}", 907060870u32)
+        );
+    }
+
+    #[test]
+    fn test_trusted_cert_url() {
+        assert!(trusted_cert_url("https://api.paypal.com/v1/notifications/certs/CERT-360caa42-fca2a594-1d93a270"));
+        assert!(trusted_cert_url("https://api.sandbox.paypal.com/v1/notifications/certs/CERT-1"));
+        assert!(!trusted_cert_url("http://api.paypal.com/v1/notifications/certs/CERT-1"));
+        assert!(!trusted_cert_url("https://api.paypal.com.evil.example/certs/CERT-1"));
+        assert!(!trusted_cert_url("not a url"));
+    }
+
+    #[test]
+    fn test_capture_event_parsing() {
+        let resource = serde_json::json!({
+            "id": "2GG279541U471931P",
+            "status": "COMPLETED",
+            "amount": { "currency_code": "USD", "value": "25.00" },
+            "supplementary_data": { "related_ids": { "order_id": "5O190127TN364715T" } }
+        });
+        let capture: PaypalCapture = serde_json::from_value(resource).unwrap();
+        assert_eq!(to_cents(&capture.amount), Cents::from_cents(2_500));
+        assert!(matches!(capture_status(&capture.status), PaymentStatus::Completed));
+        assert!(matches!(capture_status("DECLINED"), PaymentStatus::Failed));
+        assert!(matches!(order_status("APPROVED"), PaymentStatus::Processing));
+        assert!(matches!(order_status("PAYER_ACTION_REQUIRED"), PaymentStatus::Pending));
+    }
+}
@@ -2,9 +2,11 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::utils::money::Cents;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InitiatePaymentRequest {
-    pub amount: f64,
+    pub amount: Cents,
     pub currency: String,
     pub donor_email: String,
     pub donor_phone: Option<String>,
@@ -24,9 +26,11 @@ pub struct PaymentInstruction {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderWebhook {
     pub provider: String,
+    /// Provider's id for this event, used to skip redelivered events
+    pub event_id: Option<String>,
     pub event_type: String,
     pub payment_id: String,
-    pub amount: f64,
+    pub amount: Cents,
     pub currency: String,
     pub status: String,
     pub raw_data: serde_json::Value,
@@ -37,7 +41,7 @@ pub struct ProviderWebhook {
 pub struct VerificationResult {
     pub payment_id: String,
     pub status: PaymentStatus,
-    pub amount: f64,
+    pub amount: Cents,
     pub currency: String,
     pub transaction_id: Option<String>,
     pub provider_response: serde_json::Value,
@@ -56,8 +60,26 @@ pub enum PaymentStatus {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RefundRequest {
     pub payment_id: String,
-    pub amount: Option<f64>,
+    pub amount: Option<Cents>,
     pub reason: String,
+    /// Provider's own reference for the charge (Stripe PaymentIntent, M-Pesa
+    /// receipt), for refund APIs that don't take our payment id
+    pub provider_reference: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundResult {
+    pub refund_id: String,
+    pub status: RefundStatus,
+}
+
+/// Where a provider says a refund stands. `Pending` refunds settle later
+/// through a callback or a status check.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RefundStatus {
+    Pending,
+    Succeeded,
+    Failed,
 }
 
 #[async_trait]
@@ -68,14 +90,14 @@ pub trait PaymentProvider: Send + Sync {
     /// Verify a payment from webhook notification
     async fn verify_payment(&self, webhook: ProviderWebhook) -> Result<VerificationResult, String>;
     
-    /// Process a refund
-    async fn refund(&self, request: RefundRequest) -> Result<String, String>;
+    /// Ask the provider to return a payment to the donor
+    async fn refund(&self, request: RefundRequest) -> Result<RefundResult, String>;
     
     /// Get payment status
     async fn get_payment_status(&self, payment_id: &str) -> Result<PaymentStatus, String>;
     
     /// Validate webhook signature
-    fn validate_webhook(&self, payload: &str, signature: &str) -> bool;
+    async fn validate_webhook(&self, payload: &str, signature: &str, received_at: chrono::DateTime<chrono::Utc>) -> bool;
     
     /// Get provider name
     fn get_provider_name(&self) -> &str;
@@ -92,6 +114,18 @@ impl PaymentProviderFactory {
     pub fn create_stripe_provider(config: StripeConfig) -> Box<dyn PaymentProvider> {
         Box::new(crate::routes::payments::stripe::StripeProvider::new(config))
     }
+
+    pub fn create_flutterwave_provider(config: FlutterwaveConfig) -> Box<dyn PaymentProvider> {
+        Box::new(crate::routes::payments::flutterwave::FlutterwaveProvider::new(config))
+    }
+
+    pub fn create_paypal_provider(config: PaypalConfig) -> Box<dyn PaymentProvider> {
+        Box::new(crate::routes::payments::paypal::PaypalProvider::new(config))
+    }
+
+    pub fn create_airtel_provider(config: AirtelConfig) -> Box<dyn PaymentProvider> {
+        Box::new(crate::routes::payments::airtel::AirtelMoneyProvider::new(config))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -102,6 +136,46 @@ pub struct MpesaConfig {
     pub passkey: String,
     pub callback_url: String,
     pub environment: String, // sandbox or production
+    /// Business-to-customer payouts and reversals; `None` when no initiator is configured
+    pub b2c: Option<MpesaB2cConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MpesaB2cConfig {
+    pub initiator_name: String,
+    /// Initiator password encrypted with the M-Pesa public certificate
+    pub security_credential: String,
+    pub short_code: String,
+    pub result_url: String,
+    pub timeout_url: String,
+    /// Callbacks for transaction reversals, which use the same initiator
+    pub reversal_result_url: String,
+    pub reversal_timeout_url: String,
+    /// Secret sent as `?token=` on every result and timeout URL, since
+    /// Safaricom doesn't sign its callbacks
+    pub callback_token: String,
+}
+
+impl MpesaB2cConfig {
+    /// `url` with the callback token added to its query string
+    pub fn tokened_url(&self, url: &str) -> String {
+        match reqwest::Url::parse(url) {
+            Ok(mut parsed) => {
+                parsed.query_pairs_mut().append_pair("token", &self.callback_token);
+                parsed.to_string()
+            }
+            Err(_) => url.to_string(),
+        }
+    }
+
+    /// Whether a callback carried the configured token
+    pub fn accepts_callback_token(&self, token: Option<&str>) -> bool {
+        let expected = self.callback_token.as_bytes();
+        let given = token.unwrap_or_default().as_bytes();
+        !expected.is_empty()
+            && expected.len() == given.len()
+            && expected.iter().zip(given).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -109,6 +183,39 @@ pub struct StripeConfig {
     pub secret_key: String,
     pub publishable_key: String,
     pub webhook_secret: String,
+    /// Largest gap allowed between a webhook's signed timestamp and its receipt
+    pub webhook_tolerance_secs: i64,
     pub success_url: String,
     pub cancel_url: String,
+    /// Checkout payment methods, used when no payment method configuration is set
+    pub payment_method_types: Vec<String>,
+    /// Dashboard payment method configuration (`pmc_...`) for this environment
+    pub payment_method_configuration: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FlutterwaveConfig {
+    pub secret_key: String,
+    /// Secret hash set in the dashboard, echoed in each webhook's `verif-hash` header
+    pub secret_hash: String,
+    pub redirect_url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PaypalConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Id of the webhook registered for this app; part of every signed message
+    pub webhook_id: String,
+    pub webhook_tolerance_secs: i64,
+    pub environment: String, // sandbox or live
+    pub return_url: String,
+    pub cancel_url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AirtelConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub environment: String, // sandbox or production
 }
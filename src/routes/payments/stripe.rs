@@ -1,4 +1,5 @@
 use super::provider::*;
+use crate::utils::money::Cents;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -11,39 +12,11 @@ pub struct StripeProvider {
     client: Client,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct StripeSessionRequest {
-    payment_method_types: Vec<String>,
-    line_items: Vec<StripeLineItem>,
-    mode: String,
-    success_url: String,
-    cancel_url: String,
-    metadata: HashMap<String, String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct StripeLineItem {
-    price_data: StripePriceData,
-    quantity: u32,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct StripePriceData {
-    currency: String,
-    product_data: StripeProductData,
-    unit_amount: u32,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct StripeProductData {
-    name: String,
-    description: Option<String>,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 struct StripeSessionResponse {
     id: String,
     url: String,
+    expires_at: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,7 +29,7 @@ struct StripeWebhookEvent {
 
 #[derive(Debug, Serialize, Deserialize)]
 struct StripeWebhookData {
-    object: StripePaymentIntent,
+    object: serde_json::Value,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -65,9 +38,89 @@ struct StripePaymentIntent {
     amount: u32,
     currency: String,
     status: String,
+    #[serde(default)]
     metadata: HashMap<String, String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct StripeCheckoutSession {
+    id: String,
+    amount_total: Option<i64>,
+    currency: Option<String>,
+    /// `open`, `complete`, or `expired`
+    status: Option<String>,
+    /// `paid`, `unpaid`, or `no_payment_required`
+    payment_status: String,
+    payment_intent: Option<String>,
+}
+
+fn payment_intent_status(status: &str) -> PaymentStatus {
+    match status {
+        "succeeded" => PaymentStatus::Completed,
+        "processing" => PaymentStatus::Processing,
+        "requires_payment_method" | "requires_confirmation" | "requires_action" => PaymentStatus::Pending,
+        "canceled" => PaymentStatus::Cancelled,
+        _ => PaymentStatus::Failed,
+    }
+}
+
+/// Status of a Checkout Session. A `complete` session isn't necessarily paid:
+/// bank debits and other delayed methods settle later through
+/// `checkout.session.async_payment_*` events.
+fn checkout_session_status(event_type: Option<&str>, session: &StripeCheckoutSession) -> PaymentStatus {
+    match (event_type, session.payment_status.as_str(), session.status.as_deref()) {
+        (Some("checkout.session.async_payment_failed"), _, _) => PaymentStatus::Failed,
+        (Some("checkout.session.expired"), _, _) | (_, _, Some("expired")) => PaymentStatus::Expired,
+        (_, "paid" | "no_payment_required", _) => PaymentStatus::Completed,
+        (_, _, Some("complete")) => PaymentStatus::Processing,
+        _ => PaymentStatus::Pending,
+    }
+}
+
+/// A card saved through a setup-mode Checkout Session
+#[derive(Debug, Clone)]
+pub struct SavedCard {
+    pub customer: String,
+    pub payment_method: String,
+}
+
+/// Where a setup-mode Checkout Session stands
+#[derive(Debug, Clone)]
+pub enum SetupOutcome {
+    Open,
+    Saved(SavedCard),
+    Expired,
+}
+
+/// Read a setup-mode Checkout Session expanded with its SetupIntent
+fn setup_outcome(session: &serde_json::Value) -> SetupOutcome {
+    match session["status"].as_str() {
+        Some("expired") => SetupOutcome::Expired,
+        Some("complete") => {
+            let customer = session["customer"].as_str();
+            let payment_method = session["setup_intent"]["payment_method"].as_str();
+            match (customer, payment_method, session["setup_intent"]["status"].as_str()) {
+                (Some(customer), Some(payment_method), Some("succeeded")) => SetupOutcome::Saved(SavedCard {
+                    customer: customer.to_string(),
+                    payment_method: payment_method.to_string(),
+                }),
+                _ => SetupOutcome::Open,
+            }
+        }
+        _ => SetupOutcome::Open,
+    }
+}
+
+/// Status of a Stripe refund. `requires_action` refunds wait on the customer
+/// and finish through the dashboard, so they stay pending.
+fn refund_status(status: &str) -> RefundStatus {
+    match status {
+        "succeeded" => RefundStatus::Succeeded,
+        "failed" | "canceled" => RefundStatus::Failed,
+        _ => RefundStatus::Pending,
+    }
+}
+
 impl StripeProvider {
     pub fn new(config: StripeConfig) -> Self {
         Self {
@@ -79,47 +132,260 @@ impl StripeProvider {
     fn get_auth_header(&self) -> String {
         format!("Bearer {}", self.config.secret_key)
     }
+
+    /// Start a setup-mode Checkout Session that saves a card for later
+    /// off-session charges. Returns the session id and its URL.
+    pub async fn create_setup_session(
+        &self,
+        donor_email: &str,
+        currency: &str,
+        metadata: &[(&str, String)],
+    ) -> Result<(String, String), String> {
+        let response = self
+            .client
+            .post("https://api.stripe.com/v1/customers")
+            .header("Authorization", self.get_auth_header())
+            .form(&[("email", donor_email)])
+            .send()
+            .await
+            .map_err(|e| format!("Failed to create Stripe customer: {}", e))?;
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("Stripe API error: {}", error_text));
+        }
+        let customer: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Stripe customer: {}", e))?;
+        let customer = customer["id"].as_str().ok_or("Stripe customer has no id")?.to_string();
+
+        let mut form = vec![
+            ("mode".to_string(), "setup".to_string()),
+            ("customer".to_string(), customer),
+            ("currency".to_string(), currency.to_lowercase()),
+            ("payment_method_types[0]".to_string(), "card".to_string()),
+            ("success_url".to_string(), format!("{}?session_id={{CHECKOUT_SESSION_ID}}", self.config.success_url)),
+            ("cancel_url".to_string(), self.config.cancel_url.clone()),
+        ];
+        for (key, value) in metadata {
+            form.push((format!("metadata[{}]", key), value.clone()));
+        }
+
+        let response = self
+            .client
+            .post("https://api.stripe.com/v1/checkout/sessions")
+            .header("Authorization", self.get_auth_header())
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to create Stripe session: {}", e))?;
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("Stripe API error: {}", error_text));
+        }
+        let session: StripeSessionResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Stripe response: {}", e))?;
+
+        Ok((session.id, session.url))
+    }
+
+    /// Whether the donor has finished a setup session, and the card it saved
+    pub async fn setup_outcome(&self, session_id: &str) -> Result<SetupOutcome, String> {
+        let response = self
+            .client
+            .get(format!("https://api.stripe.com/v1/checkout/sessions/{}", session_id))
+            .query(&[("expand[]", "setup_intent")])
+            .header("Authorization", self.get_auth_header())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get checkout session: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Failed to get checkout session: {}", response.status()));
+        }
+        let session: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse checkout session: {}", e))?;
+
+        Ok(setup_outcome(&session))
+    }
+
+    /// Charge a saved card without the donor present. `idempotency_key`
+    /// makes retries of the same charge safe. Returns the PaymentIntent id
+    /// and its status; declines come back as errors.
+    pub async fn charge_saved_card(
+        &self,
+        card: &SavedCard,
+        amount: Cents,
+        currency: &str,
+        metadata: &[(&str, String)],
+        idempotency_key: &str,
+    ) -> Result<(String, PaymentStatus), String> {
+        let mut form = vec![
+            ("amount".to_string(), amount.as_u32().map_err(|e| e.to_string())?.to_string()),
+            ("currency".to_string(), currency.to_lowercase()),
+            ("customer".to_string(), card.customer.clone()),
+            ("payment_method".to_string(), card.payment_method.clone()),
+            ("off_session".to_string(), "true".to_string()),
+            ("confirm".to_string(), "true".to_string()),
+        ];
+        for (key, value) in metadata {
+            form.push((format!("metadata[{}]", key), value.clone()));
+        }
+
+        let response = self
+            .client
+            .post("https://api.stripe.com/v1/payment_intents")
+            .header("Authorization", self.get_auth_header())
+            .header("Idempotency-Key", idempotency_key)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to charge saved card: {}", e))?;
+        if !response.status().is_success() {
+            let body: serde_json::Value = response.json().await.unwrap_or_default();
+            return Err(format!(
+                "Stripe declined the charge: {}",
+                body["error"]["message"].as_str().unwrap_or("unknown error")
+            ));
+        }
+        let payment_intent: StripePaymentIntent = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse payment intent: {}", e))?;
+
+        Ok((payment_intent.id, payment_intent_status(&payment_intent.status)))
+    }
+
+    async fn get_checkout_session(&self, session_id: &str) -> Result<StripeCheckoutSession, String> {
+        let response = self
+            .client
+            .get(format!("https://api.stripe.com/v1/checkout/sessions/{}", session_id))
+            .header("Authorization", self.get_auth_header())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get checkout session: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Failed to get checkout session: {}", response.status()));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse checkout session: {}", e))
+    }
+
+    /// Form fields for creating a Checkout Session. Stripe's API takes nested
+    /// parameters in bracket notation, which serde_urlencoded can't produce.
+    fn session_form(&self, request: &InitiatePaymentRequest) -> Result<Vec<(String, String)>, String> {
+        let mut form = vec![
+            ("mode".to_string(), "payment".to_string()),
+            ("success_url".to_string(), format!("{}?session_id={{CHECKOUT_SESSION_ID}}", self.config.success_url)),
+            ("cancel_url".to_string(), self.config.cancel_url.clone()),
+            ("customer_email".to_string(), request.donor_email.clone()),
+            ("client_reference_id".to_string(), request.project_id.to_string()),
+            ("line_items[0][quantity]".to_string(), "1".to_string()),
+            ("line_items[0][price_data][currency]".to_string(), request.currency.to_lowercase()),
+            (
+                "line_items[0][price_data][unit_amount]".to_string(),
+                request.amount.as_u32().map_err(|e| e.to_string())?.to_string(),
+            ),
+            ("line_items[0][price_data][product_data][name]".to_string(), "FundHub Donation".to_string()),
+        ];
+        if let Some(memo) = &request.memo {
+            form.push(("line_items[0][price_data][product_data][description]".to_string(), memo.clone()));
+        }
+
+        // A payment method configuration picks methods per environment from the
+        // dashboard; otherwise list them. Apple Pay and Google Pay are offered
+        // wherever `card` is enabled and the domain is registered with Stripe.
+        match &self.config.payment_method_configuration {
+            Some(configuration) => form.push(("payment_method_configuration".to_string(), configuration.clone())),
+            None => {
+                for (i, method) in self.config.payment_method_types.iter().enumerate() {
+                    form.push((format!("payment_method_types[{}]", i), method.clone()));
+                }
+            }
+        }
+
+        let mut metadata = vec![
+            ("project_id", request.project_id.to_string()),
+            ("donor_email", request.donor_email.clone()),
+        ];
+        if let Some(phone) = &request.donor_phone {
+            metadata.push(("donor_phone", phone.clone()));
+        }
+        if let Some(memo) = &request.memo {
+            metadata.push(("memo", memo.clone()));
+        }
+        // Copied onto the PaymentIntent too, so its own events carry the project
+        for (key, value) in metadata {
+            form.push((format!("metadata[{}]", key), value.clone()));
+            form.push((format!("payment_intent_data[metadata][{}]", key), value));
+        }
+
+        Ok(form)
+    }
+}
+
+/// Check a `Stripe-Signature` header (`t=<unix>,v1=<hex>[,v1=...]`) against
+/// the raw request body. The signed timestamp must be within `tolerance_secs`
+/// of `received_at`, which stops captured deliveries from being replayed later.
+pub fn verify_signature(
+    payload: &str,
+    header: &str,
+    secret: &str,
+    tolerance_secs: i64,
+    received_at: chrono::DateTime<chrono::Utc>,
+) -> Result<(), String> {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or("Signature header has no timestamp")?;
+    if signatures.is_empty() {
+        return Err("Signature header has no v1 signature".to_string());
+    }
+    if (received_at.timestamp() - timestamp).abs() > tolerance_secs {
+        return Err("Signature timestamp is outside the tolerance window".to_string());
+    }
+
+    let signed_payload = format!("{}.{}", timestamp, payload);
+    let matches = signatures.iter().any(|signature| {
+        let Ok(expected) = hex::decode(signature) else { return false };
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length")
+            .chain_update(signed_payload.as_bytes())
+            .verify_slice(&expected)
+            .is_ok()
+    });
+    if !matches {
+        return Err("No signature matches the payload".to_string());
+    }
+    Ok(())
 }
 
 #[async_trait]
 impl PaymentProvider for StripeProvider {
     async fn initiate_payment(&self, request: InitiatePaymentRequest) -> Result<PaymentInstruction, String> {
-        let session_request = StripeSessionRequest {
-            payment_method_types: vec!["card".to_string()],
-            line_items: vec![StripeLineItem {
-                price_data: StripePriceData {
-                    currency: request.currency.clone(),
-                    product_data: StripeProductData {
-                        name: "FundHub Donation".to_string(),
-                        description: request.memo.clone(),
-                    },
-                    unit_amount: (request.amount * 100.0) as u32, // Convert to cents
-                },
-                quantity: 1,
-            }],
-            mode: "payment".to_string(),
-            success_url: format!("{}?session_id={{CHECKOUT_SESSION_ID}}", self.config.success_url),
-            cancel_url: self.config.cancel_url.clone(),
-            metadata: {
-                let mut metadata = HashMap::new();
-                metadata.insert("project_id".to_string(), request.project_id.to_string());
-                metadata.insert("donor_email".to_string(), request.donor_email.clone());
-                if let Some(phone) = request.donor_phone {
-                    metadata.insert("donor_phone".to_string(), phone);
-                }
-                if let Some(memo) = request.memo {
-                    metadata.insert("memo".to_string(), memo);
-                }
-                metadata
-            },
-        };
+        let form = self.session_form(&request)?;
 
         let response = self
             .client
             .post("https://api.stripe.com/v1/checkout/sessions")
             .header("Authorization", self.get_auth_header())
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .form(&session_request)
+            .form(&form)
             .send()
             .await
             .map_err(|e| format!("Failed to create Stripe session: {}", e))?;
@@ -143,7 +409,11 @@ impl PaymentProvider for StripeProvider {
             checkout_url: Some(session_response.url),
             payment_method: "stripe".to_string(),
             instructions,
-            expires_at: chrono::Utc::now() + chrono::Duration::hours(24), // Stripe sessions expire in 24 hours
+            // Stripe sessions expire in 24 hours unless told otherwise
+            expires_at: session_response
+                .expires_at
+                .and_then(|at| chrono::DateTime::from_timestamp(at, 0))
+                .unwrap_or_else(|| chrono::Utc::now() + chrono::Duration::hours(24)),
         })
     }
 
@@ -155,44 +425,64 @@ impl PaymentProvider for StripeProvider {
         let event: StripeWebhookEvent = serde_json::from_value(webhook.raw_data.clone())
             .map_err(|e| format!("Failed to parse Stripe webhook: {}", e))?;
 
-        let payment_intent = event.data.object;
-        
-        let status = match payment_intent.status.as_str() {
-            "succeeded" => PaymentStatus::Completed,
-            "processing" => PaymentStatus::Processing,
-            "requires_payment_method" | "requires_confirmation" | "requires_action" => PaymentStatus::Pending,
-            "canceled" => PaymentStatus::Cancelled,
-            _ => PaymentStatus::Failed,
-        };
+        if event.event_type.starts_with("checkout.session.") {
+            let session: StripeCheckoutSession = serde_json::from_value(event.data.object)
+                .map_err(|e| format!("Failed to parse Stripe checkout session: {}", e))?;
+
+            return Ok(VerificationResult {
+                payment_id: session.id.clone(),
+                status: checkout_session_status(Some(&event.event_type), &session),
+                amount: Cents::from_cents(session.amount_total.unwrap_or(0)),
+                currency: session.currency.unwrap_or_default(),
+                transaction_id: session.payment_intent,
+                provider_response: webhook.raw_data,
+            });
+        }
+
+        let payment_intent: StripePaymentIntent = serde_json::from_value(event.data.object)
+            .map_err(|e| format!("Failed to parse Stripe payment intent: {}", e))?;
 
         let payment_id = payment_intent.id.clone();
         Ok(VerificationResult {
             payment_id,
-            status,
-            amount: payment_intent.amount as f64 / 100.0, // Convert from cents
+            status: payment_intent_status(&payment_intent.status),
+            amount: Cents::from_cents(payment_intent.amount as i64),
             currency: payment_intent.currency,
             transaction_id: Some(payment_intent.id),
             provider_response: webhook.raw_data,
         })
     }
 
-    async fn refund(&self, request: RefundRequest) -> Result<String, String> {
-        let refund_data = serde_json::json!({
-            "payment_intent": request.payment_id,
-            "amount": if let Some(amount) = request.amount {
-                (amount * 100.0) as u32
-            } else {
-                return Err("Amount is required for refund".to_string());
-            },
-            "reason": request.reason
-        });
+    async fn refund(&self, request: RefundRequest) -> Result<RefundResult, String> {
+        // Refunds are made against the PaymentIntent, which a Checkout Session
+        // only names once it has been paid
+        let payment_intent = match request.provider_reference.clone() {
+            Some(reference) => reference,
+            None if request.payment_id.starts_with("cs_") => self
+                .get_checkout_session(&request.payment_id)
+                .await?
+                .payment_intent
+                .ok_or("Stripe checkout session has no payment intent to refund")?,
+            None => request.payment_id.clone(),
+        };
+
+        // `reason` only takes Stripe's own codes, so ours goes in metadata.
+        // Leaving the amount out refunds whatever is left of the charge.
+        let mut form = vec![
+            ("payment_intent".to_string(), payment_intent),
+            ("reason".to_string(), "requested_by_customer".to_string()),
+            ("metadata[reason]".to_string(), request.reason.clone()),
+            ("metadata[payment_id]".to_string(), request.payment_id.clone()),
+        ];
+        if let Some(amount) = request.amount {
+            form.push(("amount".to_string(), amount.as_u32().map_err(|e| e.to_string())?.to_string()));
+        }
 
         let response = self
             .client
             .post("https://api.stripe.com/v1/refunds")
             .header("Authorization", self.get_auth_header())
-            .header("Content-Type", "application/json")
-            .json(&refund_data)
+            .form(&form)
             .send()
             .await
             .map_err(|e| format!("Failed to create refund: {}", e))?;
@@ -207,13 +497,23 @@ impl PaymentProvider for StripeProvider {
             .await
             .map_err(|e| format!("Failed to parse refund response: {}", e))?;
 
-        Ok(refund_response["id"].as_str().unwrap_or("unknown").to_string())
+        Ok(RefundResult {
+            refund_id: refund_response["id"].as_str().unwrap_or("unknown").to_string(),
+            status: refund_status(refund_response["status"].as_str().unwrap_or_default()),
+        })
     }
 
     async fn get_payment_status(&self, payment_id: &str) -> Result<PaymentStatus, String> {
+        // Hosted checkout payments are tracked by their session id
+        let url = if payment_id.starts_with("cs_") {
+            format!("https://api.stripe.com/v1/checkout/sessions/{}", payment_id)
+        } else {
+            format!("https://api.stripe.com/v1/payment_intents/{}", payment_id)
+        };
+
         let response = self
             .client
-            .get(&format!("https://api.stripe.com/v1/payment_intents/{}", payment_id))
+            .get(&url)
             .header("Authorization", self.get_auth_header())
             .send()
             .await
@@ -223,39 +523,170 @@ impl PaymentProvider for StripeProvider {
             return Err("Failed to get payment status".to_string());
         }
 
+        if payment_id.starts_with("cs_") {
+            let session: StripeCheckoutSession = response
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse checkout session: {}", e))?;
+            return Ok(checkout_session_status(None, &session));
+        }
+
         let payment_intent: StripePaymentIntent = response
             .json()
             .await
             .map_err(|e| format!("Failed to parse payment status: {}", e))?;
 
-        let status = match payment_intent.status.as_str() {
-            "succeeded" => PaymentStatus::Completed,
-            "processing" => PaymentStatus::Processing,
-            "requires_payment_method" | "requires_confirmation" | "requires_action" => PaymentStatus::Pending,
-            "canceled" => PaymentStatus::Cancelled,
-            _ => PaymentStatus::Failed,
+        Ok(payment_intent_status(&payment_intent.status))
+    }
+
+    async fn validate_webhook(&self, payload: &str, signature: &str, received_at: chrono::DateTime<chrono::Utc>) -> bool {
+        match verify_signature(payload, signature, &self.config.webhook_secret, self.config.webhook_tolerance_secs, received_at) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Rejected Stripe webhook: {}", e);
+                false
+            }
+        }
+    }
+
+    fn get_provider_name(&self) -> &str {
+        "stripe"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    const SECRET: &str = "whsec_test";
+
+    fn sign(timestamp: i64, payload: &str) -> String {
+        let signature = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes())
+            .unwrap()
+            .chain_update(format!("{}.{}", timestamp, payload).as_bytes())
+            .finalize()
+            .into_bytes();
+        hex::encode(signature)
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let now = chrono::Utc::now();
+        let t = now.timestamp();
+        let payload = r#"{"id":"evt_1","type":"payment_intent.succeeded"}"#;
+
+        let header = format!("t={},v1={}", t, sign(t, payload));
+        assert!(verify_signature(payload, &header, SECRET, 300, now).is_ok());
+
+        // Any of several v1 signatures may match, e.g. during secret rotation
+        let rotated = format!("t={},v1={},v1={},v0=ignored", t, "00".repeat(32), sign(t, payload));
+        assert!(verify_signature(payload, &rotated, SECRET, 300, now).is_ok());
+
+        let tampered = payload.replace("evt_1", "evt_2");
+        assert!(verify_signature(&tampered, &header, SECRET, 300, now).is_err());
+        assert!(verify_signature(payload, &header, "whsec_other", 300, now).is_err());
+        assert!(verify_signature(payload, "v1=abc", SECRET, 300, now).is_err());
+    }
+
+    fn provider(payment_method_configuration: Option<&str>) -> StripeProvider {
+        StripeProvider::new(StripeConfig {
+            secret_key: "sk_test".to_string(),
+            publishable_key: "pk_test".to_string(),
+            webhook_secret: SECRET.to_string(),
+            webhook_tolerance_secs: 300,
+            success_url: "https://fundhub.test/success".to_string(),
+            cancel_url: "https://fundhub.test/cancel".to_string(),
+            payment_method_types: vec!["card".to_string(), "link".to_string()],
+            payment_method_configuration: payment_method_configuration.map(|c| c.to_string()),
+        })
+    }
+
+    #[test]
+    fn test_session_form_uses_bracket_notation() {
+        let request = InitiatePaymentRequest {
+            amount: Cents::from_cents(2_500),
+            currency: "USD".to_string(),
+            donor_email: "donor@example.com".to_string(),
+            donor_phone: None,
+            project_id: Uuid::nil(),
+            memo: Some("Good luck".to_string()),
         };
+        let form = provider(None).session_form(&request).unwrap();
+        let field = |key: &str| form.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+        assert_eq!(field("line_items[0][price_data][unit_amount]"), Some("2500"));
+        assert_eq!(field("line_items[0][price_data][currency]"), Some("usd"));
+        assert_eq!(field("payment_method_types[1]"), Some("link"));
+        assert_eq!(field("payment_intent_data[metadata][project_id]"), Some(Uuid::nil().to_string().as_str()));
+        assert_eq!(field("payment_method_configuration"), None);
+
+        let form = provider(Some("pmc_123")).session_form(&request).unwrap();
+        assert!(form.iter().any(|(k, v)| k == "payment_method_configuration" && v == "pmc_123"));
+        assert!(!form.iter().any(|(k, _)| k.starts_with("payment_method_types")));
+    }
 
-        Ok(status)
+    #[test]
+    fn test_checkout_session_status() {
+        let session = |status: &str, payment_status: &str| StripeCheckoutSession {
+            id: "cs_test".to_string(),
+            amount_total: Some(2_500),
+            currency: Some("usd".to_string()),
+            status: Some(status.to_string()),
+            payment_status: payment_status.to_string(),
+            payment_intent: Some("pi_test".to_string()),
+        };
+        let completed = Some("checkout.session.completed");
+        assert!(matches!(checkout_session_status(completed, &session("complete", "paid")), PaymentStatus::Completed));
+        assert!(matches!(checkout_session_status(completed, &session("complete", "unpaid")), PaymentStatus::Processing));
+        assert!(matches!(
+            checkout_session_status(Some("checkout.session.async_payment_failed"), &session("complete", "unpaid")),
+            PaymentStatus::Failed
+        ));
+        assert!(matches!(checkout_session_status(None, &session("expired", "unpaid")), PaymentStatus::Expired));
+        assert!(matches!(checkout_session_status(None, &session("open", "unpaid")), PaymentStatus::Pending));
     }
 
-    fn validate_webhook(&self, payload: &str, signature: &str) -> bool {
-        use hmac::{Hmac, Mac};
-        use sha2::Sha256;
+    #[test]
+    fn test_setup_outcome() {
+        let session = serde_json::json!({
+            "status": "complete",
+            "customer": "cus_123",
+            "setup_intent": { "status": "succeeded", "payment_method": "pm_123" }
+        });
+        match setup_outcome(&session) {
+            SetupOutcome::Saved(card) => {
+                assert_eq!(card.customer, "cus_123");
+                assert_eq!(card.payment_method, "pm_123");
+            }
+            other => panic!("expected a saved card, got {:?}", other),
+        }
 
-        let expected_signature = format!("t={},v1={}", 
-            chrono::Utc::now().timestamp(), 
-            hex::encode(Hmac::<Sha256>::new_from_slice(self.config.webhook_secret.as_bytes())
-                .unwrap()
-                .chain_update(payload)
-                .finalize()
-                .into_bytes())
-        );
+        let pending = serde_json::json!({
+            "status": "complete",
+            "customer": "cus_123",
+            "setup_intent": { "status": "processing", "payment_method": "pm_123" }
+        });
+        assert!(matches!(setup_outcome(&pending), SetupOutcome::Open));
+        assert!(matches!(setup_outcome(&serde_json::json!({"status": "expired"})), SetupOutcome::Expired));
+    }
 
-        signature == expected_signature
+    #[test]
+    fn test_refund_status() {
+        assert_eq!(refund_status("succeeded"), RefundStatus::Succeeded);
+        assert_eq!(refund_status("pending"), RefundStatus::Pending);
+        assert_eq!(refund_status("requires_action"), RefundStatus::Pending);
+        assert_eq!(refund_status("canceled"), RefundStatus::Failed);
     }
 
-    fn get_provider_name(&self) -> &str {
-        "stripe"
+    #[test]
+    fn test_verify_signature_rejects_stale_timestamps() {
+        let now = chrono::Utc::now();
+        let payload = r#"{"id":"evt_1"}"#;
+        let old = now.timestamp() - 600;
+        let header = format!("t={},v1={}", old, sign(old, payload));
+        assert!(verify_signature(payload, &header, SECRET, 300, now).is_err());
+        assert!(verify_signature(payload, &header, SECRET, 900, now).is_ok());
     }
 }
@@ -0,0 +1,225 @@
+use std::borrow::Cow;
+use std::net::IpAddr;
+
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use sqlx::types::BigDecimal;
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::routes::error::{AppError, FieldError};
+use crate::services::{donation_memo::MAX_TEXT_MEMO_BYTES, password_reset};
+use crate::utils::money::{Cents, Stroops};
+
+/// `Json<T>` that also runs `T`'s `#[validate(...)]` rules, answering 422
+/// with every failing field
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await.map_err(rejected)?;
+        value.validate()?;
+        Ok(ValidatedJson(value))
+    }
+}
+
+fn rejected(rejection: JsonRejection) -> AppError {
+    AppError::Coded {
+        status: rejection.status(),
+        code: "invalid_json",
+        message: rejection.body_text(),
+        details: None,
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut fields = Vec::new();
+        flatten("", &errors, &mut fields);
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        AppError::Validation(fields)
+    }
+}
+
+/// Nested structs and lists report as `milestones[0].amount_xlm`
+fn flatten(prefix: &str, errors: &ValidationErrors, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() { field.to_string() } else { format!("{}.{}", prefix, field) };
+        match kind {
+            ValidationErrorsKind::Field(errors) => out.extend(errors.iter().map(|e| FieldError {
+                field: path.clone(),
+                message: e.message.as_deref().map(str::to_string).unwrap_or_else(|| format!("Failed the {} check", e.code)),
+            })),
+            ValidationErrorsKind::Struct(inner) => flatten(&path, inner, out),
+            ValidationErrorsKind::List(items) => {
+                for (i, inner) in items {
+                    flatten(&format!("{}[{}]", path, i), inner, out);
+                }
+            }
+        }
+    }
+}
+
+fn rule(code: &'static str, message: &'static str) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(Cow::Borrowed(message));
+    error
+}
+
+/// Rejects empty and whitespace-only strings
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(rule("not_blank", "Must not be blank"));
+    }
+    Ok(())
+}
+
+/// An XLM amount written as a decimal string, e.g. `"12.5"`
+pub fn positive_xlm(value: &str) -> Result<(), ValidationError> {
+    match value.parse::<Stroops>() {
+        Ok(amount) if amount.is_positive() => Ok(()),
+        _ => Err(rule("positive_xlm", "Amount must be a positive XLM value")),
+    }
+}
+
+pub fn positive_stroops(value: &Stroops) -> Result<(), ValidationError> {
+    if !value.is_positive() {
+        return Err(rule("positive", "Amount must be positive"));
+    }
+    Ok(())
+}
+
+pub fn positive_cents(value: &Cents) -> Result<(), ValidationError> {
+    if !value.is_positive() {
+        return Err(rule("positive", "Amount must be positive"));
+    }
+    Ok(())
+}
+
+/// A positive decimal, for funding goals stored as NUMERIC
+pub fn positive_decimal(value: &str) -> Result<(), ValidationError> {
+    match value.trim().parse::<BigDecimal>() {
+        Ok(amount) if amount > BigDecimal::from(0) => Ok(()),
+        _ => Err(rule("positive_decimal", "Must be a positive number")),
+    }
+}
+
+/// International or local mobile number: an optional `+`, then 9 to 15
+/// digits, spaces allowed
+pub fn phone_number(value: &str) -> Result<(), ValidationError> {
+    let digits = value.strip_prefix('+').unwrap_or(value).replace(' ', "");
+    if !(9..=15).contains(&digits.len()) || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(rule("phone_number", "Enter a valid phone number"));
+    }
+    Ok(())
+}
+
+/// Stellar text memos are measured in bytes, not characters
+pub fn text_memo(value: &str) -> Result<(), ValidationError> {
+    if value.len() > MAX_TEXT_MEMO_BYTES {
+        return Err(rule("text_memo", "Memo must be at most 28 bytes"));
+    }
+    Ok(())
+}
+
+pub fn password(value: &str) -> Result<(), ValidationError> {
+    password_reset::validate_password(value).map_err(|_| rule("password", "Password must be at least 8 characters"))
+}
+
+pub fn urls(values: &[String]) -> Result<(), ValidationError> {
+    if !values.iter().all(validator::ValidateUrl::validate_url) {
+        return Err(rule("url", "Every entry must be a valid URL"));
+    }
+    Ok(())
+}
+
+/// An `https://` URL on a public host, for callbacks the server will POST to
+pub fn https_url(value: &str) -> Result<(), ValidationError> {
+    let Ok(url) = reqwest::Url::parse(value) else {
+        return Err(rule("https_url", "Must be a valid URL"));
+    };
+    let host = url.host_str().unwrap_or_default().trim_start_matches('[').trim_end_matches(']');
+    let public_host = match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => !(ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified()),
+        Ok(IpAddr::V6(ip)) => !(ip.is_loopback() || ip.is_unspecified()),
+        Err(_) => !host.is_empty() && host != "localhost" && !host.ends_with(".localhost") && !host.ends_with(".internal"),
+    };
+    if url.scheme() != "https" || !public_host {
+        return Err(rule("https_url", "Must be an https:// URL on a public host"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Validate)]
+    struct Milestone {
+        #[validate(custom(function = "positive_xlm"))]
+        amount_xlm: String,
+    }
+
+    #[derive(Validate)]
+    struct Project {
+        #[validate(custom(function = "not_blank"))]
+        title: String,
+        #[validate(email(message = "Enter a valid email address"))]
+        email: String,
+        #[validate(nested)]
+        milestones: Vec<Milestone>,
+    }
+
+    #[test]
+    fn test_errors_flatten_to_field_paths() {
+        let project = Project {
+            title: "  ".to_string(),
+            email: "student@uni.ac.ke".to_string(),
+            milestones: vec![
+                Milestone { amount_xlm: "10".to_string() },
+                Milestone { amount_xlm: "-1".to_string() },
+            ],
+        };
+        match AppError::from(project.validate().unwrap_err()) {
+            AppError::Validation(fields) => {
+                let paths: Vec<_> = fields.iter().map(|f| f.field.as_str()).collect();
+                assert_eq!(paths, ["milestones[1].amount_xlm", "title"]);
+                assert_eq!(fields[1].message, "Must not be blank");
+            }
+            other => panic!("expected validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        assert!(positive_xlm("0.5").is_ok());
+        assert!(positive_xlm("0").is_err());
+        assert!(positive_xlm("ten").is_err());
+        assert!(positive_decimal("1000").is_ok());
+        assert!(positive_decimal("-5").is_err());
+        assert!(phone_number("+254 712 345 678").is_ok());
+        assert!(phone_number("0712345678").is_ok());
+        assert!(phone_number("12345").is_err());
+        assert!(phone_number("+254-712-345").is_err());
+        assert!(urls(&["https://example.com/a.png".to_string()]).is_ok());
+        assert!(urls(&["not a url".to_string()]).is_err());
+        assert!(https_url("https://partner.example.org/hooks/fundhub").is_ok());
+        assert!(https_url("http://partner.example.org/hooks").is_err());
+        assert!(https_url("https://localhost:8443/hooks").is_err());
+        assert!(https_url("https://10.0.0.5/hooks").is_err());
+        assert!(https_url("not a url").is_err());
+        assert!(text_memo("ééééééééééééééé").is_err());
+        assert!(password("short").is_err());
+    }
+}
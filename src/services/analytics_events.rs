@@ -0,0 +1,28 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub const PROJECT_COMPLETED: &str = "project.completed";
+
+/// Record a product analytics event about an entity
+pub async fn record(
+    pool: &PgPool,
+    event_type: &str,
+    entity_type: &str,
+    entity_id: Uuid,
+    properties: serde_json::Value,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO analytics_events (event_type, entity_type, entity_id, properties)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        event_type,
+        entity_type,
+        entity_id,
+        properties
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,457 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::email::{self, EmailTemplate};
+use crate::services::notifications::NotificationEvent;
+use crate::utils::pagination::{Page, PageRequest};
+
+pub const KINDS: &[&str] = &["general", "maintenance", "campaign"];
+/// Roles an announcement can be aimed at
+pub const ROLES: &[&str] = &["user", "student", "admin", "moderator", "finance"];
+/// Narrower audiences within the roles
+pub const COHORTS: &[&str] = &["verified_students", "unverified_students", "donors", "project_owners"];
+/// Most announcements in one digest email; older ones wait for the next
+pub const MAX_DIGEST_ITEMS: usize = 20;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AnnouncementError {
+    #[error("Only scheduled announcements can be changed")]
+    NotScheduled,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for AnnouncementError {
+    fn from(e: sqlx::Error) -> Self {
+        AnnouncementError::Internal(e.into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Announcement {
+    pub id: Uuid,
+    pub title: String,
+    pub body: String,
+    /// `general`, `maintenance` or `campaign`
+    pub kind: String,
+    /// Empty for every role
+    pub audience_roles: Vec<String>,
+    pub cohort: Option<String>,
+    pub email_digest: bool,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    /// `scheduled`, `sent` or `cancelled`
+    pub status: String,
+    pub publish_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub recipients: i32,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An announcement as composed by an admin
+#[derive(Debug, Clone)]
+pub struct NewAnnouncement<'a> {
+    pub title: &'a str,
+    pub body: &'a str,
+    pub kind: &'a str,
+    pub audience_roles: &'a [String],
+    pub cohort: Option<&'a str>,
+    pub email_digest: bool,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    /// `None` to send straight away
+    pub publish_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnnouncementStats {
+    pub recipients: i64,
+    pub read: i64,
+    /// Share of recipients who've read it, 0 to 1
+    pub read_rate: f64,
+    /// Recipients it reached by email digest
+    pub emailed: i64,
+}
+
+impl AnnouncementStats {
+    pub fn new(recipients: i64, read: i64, emailed: i64) -> Self {
+        let read_rate = if recipients > 0 { read as f64 / recipients as f64 } else { 0.0 };
+        Self { recipients, read, read_rate, emailed }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnnouncementWithStats {
+    #[serde(flatten)]
+    pub announcement: Announcement,
+    pub stats: AnnouncementStats,
+}
+
+/// The notification type announcements are delivered as
+pub fn notification_type(kind: &str) -> &'static str {
+    match kind {
+        "campaign" => "campaign",
+        _ => "system",
+    }
+}
+
+/// What goes into the notification: the body, then the window for
+/// maintenance announcements
+pub fn notification_message(announcement: &Announcement) -> String {
+    match (announcement.starts_at, announcement.ends_at) {
+        (Some(starts), Some(ends)) => format!(
+            "{}\n\nFrom {} to {} UTC.",
+            announcement.body,
+            starts.format("%B %-d, %Y %H:%M"),
+            ends.format("%B %-d, %Y %H:%M")
+        ),
+        (Some(starts), None) => format!("{}\n\nFrom {} UTC.", announcement.body, starts.format("%B %-d, %Y %H:%M")),
+        _ => announcement.body.clone(),
+    }
+}
+
+pub async fn create(pool: &PgPool, new: &NewAnnouncement<'_>, admin_id: Uuid) -> Result<Announcement> {
+    let announcement = sqlx::query_as!(
+        Announcement,
+        r#"
+        INSERT INTO announcements
+            (title, body, kind, audience_roles, cohort, email_digest, starts_at, ends_at, publish_at, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, COALESCE($9, NOW()), $10)
+        RETURNING id, title, body, kind, audience_roles, cohort, email_digest, starts_at, ends_at,
+                  status, publish_at, sent_at, recipients, created_by, created_at, updated_at
+        "#,
+        new.title,
+        new.body,
+        new.kind,
+        new.audience_roles,
+        new.cohort,
+        new.email_digest,
+        new.starts_at,
+        new.ends_at,
+        new.publish_at,
+        admin_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(announcement)
+}
+
+async fn stats(pool: &PgPool, announcement_id: Uuid) -> Result<AnnouncementStats> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "recipients!",
+               COUNT(*) FILTER (WHERE is_read) as "read!",
+               COUNT(*) FILTER (WHERE emailed_at IS NOT NULL) as "emailed!"
+        FROM notifications
+        WHERE announcement_id = $1
+        "#,
+        announcement_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(AnnouncementStats::new(row.recipients, row.read, row.emailed))
+}
+
+/// An announcement and how it's been read; `None` if there's no such one
+pub async fn get(pool: &PgPool, id: Uuid) -> Result<Option<AnnouncementWithStats>> {
+    let Some(announcement) = sqlx::query_as!(
+        Announcement,
+        r#"
+        SELECT id, title, body, kind, audience_roles, cohort, email_digest, starts_at, ends_at,
+               status, publish_at, sent_at, recipients, created_by, created_at, updated_at
+        FROM announcements
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let stats = stats(pool, id).await?;
+    Ok(Some(AnnouncementWithStats { announcement, stats }))
+}
+
+/// Users a sent announcement was delivered to
+pub async fn recipients(pool: &PgPool, id: Uuid) -> Result<Vec<Uuid>> {
+    let users = sqlx::query_scalar!("SELECT user_id FROM notifications WHERE announcement_id = $1", id)
+        .fetch_all(pool)
+        .await?;
+    Ok(users)
+}
+
+/// Announcements, newest first, with their read rates
+pub async fn list(pool: &PgPool, status: Option<&str>, page: &PageRequest) -> Result<Page<AnnouncementWithStats>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT a.id, a.title, a.body, a.kind, a.audience_roles, a.cohort, a.email_digest, a.starts_at, a.ends_at,
+               a.status, a.publish_at, a.sent_at, a.recipients, a.created_by, a.created_at, a.updated_at,
+               COUNT(n.id) as "delivered!",
+               COUNT(n.id) FILTER (WHERE n.is_read) as "read!",
+               COUNT(n.id) FILTER (WHERE n.emailed_at IS NOT NULL) as "emailed!"
+        FROM announcements a
+        LEFT JOIN notifications n ON n.announcement_id = a.id
+        WHERE ($1::text IS NULL OR a.status = $1)
+          AND ($2::timestamptz IS NULL OR (a.created_at, a.id) < ($2, $3::uuid))
+        GROUP BY a.id
+        ORDER BY a.created_at DESC, a.id DESC
+        LIMIT $4
+        "#,
+        status,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let announcements = rows
+        .into_iter()
+        .map(|r| AnnouncementWithStats {
+            stats: AnnouncementStats::new(r.delivered, r.read, r.emailed),
+            announcement: Announcement {
+                id: r.id,
+                title: r.title,
+                body: r.body,
+                kind: r.kind,
+                audience_roles: r.audience_roles,
+                cohort: r.cohort,
+                email_digest: r.email_digest,
+                starts_at: r.starts_at,
+                ends_at: r.ends_at,
+                status: r.status,
+                publish_at: r.publish_at,
+                sent_at: r.sent_at,
+                recipients: r.recipients,
+                created_by: r.created_by,
+                created_at: r.created_at,
+                updated_at: r.updated_at,
+            },
+        })
+        .collect();
+    Ok(Page::new(announcements, page, |a| (Some(a.announcement.created_at), a.announcement.id)))
+}
+
+/// Call off a scheduled announcement; `None` if there's no such one
+pub async fn cancel(pool: &PgPool, id: Uuid) -> Result<Option<Announcement>, AnnouncementError> {
+    let cancelled = sqlx::query_as!(
+        Announcement,
+        r#"
+        UPDATE announcements
+        SET status = 'cancelled', updated_at = NOW()
+        WHERE id = $1 AND status = 'scheduled'
+        RETURNING id, title, body, kind, audience_roles, cohort, email_digest, starts_at, ends_at,
+                  status, publish_at, sent_at, recipients, created_by, created_at, updated_at
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+    if cancelled.is_some() {
+        return Ok(cancelled);
+    }
+    let exists = sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM announcements WHERE id = $1) as "exists!""#, id)
+        .fetch_one(pool)
+        .await?;
+    if exists {
+        return Err(AnnouncementError::NotScheduled);
+    }
+    Ok(None)
+}
+
+/// Scheduled announcements whose time has come, earliest first
+pub async fn due(pool: &PgPool, limit: i64) -> Result<Vec<Uuid>> {
+    let ids = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM announcements
+        WHERE status = 'scheduled' AND publish_at <= NOW()
+        ORDER BY publish_at
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ids)
+}
+
+/// Send an announcement: a notification for every active user in its
+/// audience. `None` if it was no longer scheduled, e.g. already sent.
+pub async fn deliver(pool: &PgPool, id: Uuid) -> Result<Option<Announcement>> {
+    let mut tx = pool.begin().await?;
+    let Some(announcement) = sqlx::query_as!(
+        Announcement,
+        r#"
+        SELECT id, title, body, kind, audience_roles, cohort, email_digest, starts_at, ends_at,
+               status, publish_at, sent_at, recipients, created_by, created_at, updated_at
+        FROM announcements
+        WHERE id = $1 AND status = 'scheduled'
+        FOR UPDATE SKIP LOCKED
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let recipients = sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, announcement_id, event)
+        SELECT u.id, $2, $3, $4, $5::jsonb, $1, $8::jsonb
+        FROM users u
+        WHERE u.status = 'active'
+          AND (cardinality($6::text[]) = 0 OR u.role = ANY($6))
+          AND CASE $7::text
+                WHEN 'verified_students' THEN EXISTS (
+                    SELECT 1 FROM students s WHERE s.user_id = u.id AND s.verification_status = 'verified')
+                WHEN 'unverified_students' THEN u.role = 'student' AND NOT EXISTS (
+                    SELECT 1 FROM students s WHERE s.user_id = u.id AND s.verification_status = 'verified')
+                WHEN 'donors' THEN EXISTS (
+                    SELECT 1 FROM donations d WHERE d.donor_id = u.id AND d.status = 'confirmed')
+                WHEN 'project_owners' THEN EXISTS (
+                    SELECT 1 FROM students s JOIN projects p ON p.student_id = s.id
+                    WHERE s.user_id = u.id AND p.status = 'active')
+                ELSE TRUE
+              END
+        "#,
+        id,
+        notification_type(&announcement.kind),
+        announcement.title,
+        notification_message(&announcement),
+        serde_json::json!({"announcement_id": id, "kind": announcement.kind}),
+        &announcement.audience_roles,
+        announcement.cohort,
+        NotificationEvent::Announcement { announcement_id: id }.to_json()
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let announcement = sqlx::query_as!(
+        Announcement,
+        r#"
+        UPDATE announcements
+        SET status = 'sent', sent_at = NOW(), recipients = $2, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, title, body, kind, audience_roles, cohort, email_digest, starts_at, ends_at,
+                  status, publish_at, sent_at, recipients, created_by, created_at, updated_at
+        "#,
+        id,
+        i32::try_from(recipients).unwrap_or(i32::MAX)
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(Some(announcement))
+}
+
+/// Queue one digest email per user with announcements they haven't read in
+/// the app and haven't been emailed, marking those as emailed. Returns how
+/// many emails were queued.
+pub async fn queue_digests(pool: &PgPool) -> Result<usize> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT n.id, n.user_id, n.title, n.message, u.username, u.email
+        FROM notifications n
+        JOIN announcements a ON a.id = n.announcement_id
+        JOIN users u ON u.id = n.user_id
+        WHERE a.email_digest AND NOT n.is_read AND n.emailed_at IS NULL
+        ORDER BY n.user_id, n.created_at, n.id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Rows come ordered by user, so each user's run becomes one digest
+    let mut by_user: Vec<Vec<_>> = Vec::new();
+    let mut current_user = None;
+    for row in rows {
+        if current_user != Some(row.user_id) {
+            current_user = Some(row.user_id);
+            by_user.push(Vec::new());
+        }
+        by_user.last_mut().expect("pushed above").push(row);
+    }
+
+    let mut queued = 0;
+    for mut items in by_user {
+        items.truncate(MAX_DIGEST_ITEMS);
+        let first = &items[0];
+        let ids: Vec<Uuid> = items.iter().map(|n| n.id).collect();
+        let template = EmailTemplate::AnnouncementDigest {
+            username: first.username.clone(),
+            announcements: items.iter().map(|n| (n.title.clone(), n.message.clone())).collect(),
+        };
+        // The newest item in the digest keys it, so a retry after a failure
+        // to mark them doesn't send it twice
+        let key = format!("announcement_digest:{}:{}", first.user_id, ids[ids.len() - 1]);
+        if email::queue(pool, &first.email, &template, Some(&key)).await?.is_some() {
+            queued += 1;
+        }
+        sqlx::query!("UPDATE notifications SET emailed_at = NOW() WHERE id = ANY($1)", &ids)
+            .execute(pool)
+            .await?;
+    }
+    Ok(queued)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn announcement(starts_at: Option<DateTime<Utc>>, ends_at: Option<DateTime<Utc>>) -> Announcement {
+        let now = Utc::now();
+        Announcement {
+            id: Uuid::new_v4(),
+            title: "Scheduled maintenance".to_string(),
+            body: "Donations will be paused.".to_string(),
+            kind: "maintenance".to_string(),
+            audience_roles: Vec::new(),
+            cohort: None,
+            email_digest: false,
+            starts_at,
+            ends_at,
+            status: "scheduled".to_string(),
+            publish_at: now,
+            sent_at: None,
+            recipients: 0,
+            created_by: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_notification_message() {
+        let starts = Utc.with_ymd_and_hms(2025, 11, 1, 22, 0, 0).unwrap();
+        let ends = Utc.with_ymd_and_hms(2025, 11, 2, 2, 30, 0).unwrap();
+        assert_eq!(notification_message(&announcement(None, None)), "Donations will be paused.");
+        assert_eq!(
+            notification_message(&announcement(Some(starts), Some(ends))),
+            "Donations will be paused.\n\nFrom November 1, 2025 22:00 to November 2, 2025 02:30 UTC."
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = AnnouncementStats::new(200, 50, 120);
+        assert_eq!(stats.read_rate, 0.25);
+        assert_eq!(AnnouncementStats::new(0, 0, 0).read_rate, 0.0);
+    }
+
+    #[test]
+    fn test_notification_type() {
+        assert_eq!(notification_type("campaign"), "campaign");
+        assert_eq!(notification_type("maintenance"), "system");
+        assert_eq!(notification_type("general"), "system");
+    }
+}
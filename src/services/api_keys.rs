@@ -0,0 +1,216 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::email_verification::new_token;
+
+/// Read projects, milestones and public project stats
+pub const PROJECTS_READ: &str = "projects:read";
+/// Read donations; donor details stay redacted as for any non-admin caller
+pub const DONATIONS_READ: &str = "donations:read";
+
+pub const ALL_SCOPES: [&str; 2] = [PROJECTS_READ, DONATIONS_READ];
+
+/// Marks FundHub keys so they're easy to spot in partner configs and secret scanners
+const KEY_PREFIX: &str = "fhk_";
+/// Characters of the key stored in the clear for telling keys apart
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+pub const DEFAULT_RATE_LIMIT_PER_MINUTE: i32 = 60;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub rate_limit_per_minute: i32,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires| expires > now)
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+/// A newly issued key; `key` is never stored and can't be shown again
+#[derive(Debug, Serialize)]
+pub struct IssuedKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+pub struct NewKey<'a> {
+    pub name: &'a str,
+    pub scopes: &'a [String],
+    pub rate_limit_per_minute: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_by: Uuid,
+}
+
+/// Keys are 256-bit random, so an unsalted SHA-256 is enough to keep a
+/// database leak from exposing usable keys
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Scopes that aren't known, if any
+pub fn unknown_scopes(scopes: &[String]) -> Vec<&str> {
+    scopes
+        .iter()
+        .map(String::as_str)
+        .filter(|s| !ALL_SCOPES.contains(s))
+        .collect()
+}
+
+pub async fn issue(pool: &PgPool, new: &NewKey<'_>) -> Result<IssuedKey> {
+    let key = format!("{}{}", KEY_PREFIX, new_token());
+    let api_key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        INSERT INTO api_keys (name, key_prefix, key_hash, scopes, rate_limit_per_minute, created_by, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, name, key_prefix, scopes, rate_limit_per_minute, created_by,
+                  created_at, expires_at, revoked_at, last_used_at
+        "#,
+        new.name,
+        &key[..DISPLAY_PREFIX_LEN],
+        hash_key(&key),
+        new.scopes,
+        new.rate_limit_per_minute.unwrap_or(DEFAULT_RATE_LIMIT_PER_MINUTE),
+        new.created_by,
+        new.expires_at
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(IssuedKey { api_key, key })
+}
+
+/// Keys newest first, revoked and expired ones included
+pub async fn list(pool: &PgPool) -> Result<Vec<ApiKey>> {
+    let keys = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, name, key_prefix, scopes, rate_limit_per_minute, created_by,
+               created_at, expires_at, revoked_at, last_used_at
+        FROM api_keys
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(keys)
+}
+
+/// Revoke a key; `None` if it doesn't exist or was already revoked
+pub async fn revoke(pool: &PgPool, id: Uuid, revoked_by: Uuid) -> Result<Option<ApiKey>> {
+    let key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        UPDATE api_keys
+        SET revoked_at = NOW(), revoked_by = $2
+        WHERE id = $1 AND revoked_at IS NULL
+        RETURNING id, name, key_prefix, scopes, rate_limit_per_minute, created_by,
+                  created_at, expires_at, revoked_at, last_used_at
+        "#,
+        id,
+        revoked_by
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(key)
+}
+
+/// The active key matching `key`, recording that it was used
+pub async fn authenticate(pool: &PgPool, key: &str) -> Result<Option<ApiKey>> {
+    if !key.starts_with(KEY_PREFIX) {
+        return Ok(None);
+    }
+    let found = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, name, key_prefix, scopes, rate_limit_per_minute, created_by,
+               created_at, expires_at, revoked_at, last_used_at
+        FROM api_keys
+        WHERE key_hash = $1
+        "#,
+        hash_key(key)
+    )
+    .fetch_optional(pool)
+    .await?
+    .filter(|k| k.is_active(Utc::now()));
+
+    if let Some(api_key) = &found {
+        // At most one write a minute per key
+        sqlx::query!(
+            r#"
+            UPDATE api_keys SET last_used_at = NOW()
+            WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')
+            "#,
+            api_key.id
+        )
+        .execute(pool)
+        .await?;
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn key(revoked: bool, expires_in: Option<i64>) -> ApiKey {
+        let now = Utc::now();
+        ApiKey {
+            id: Uuid::new_v4(),
+            name: "University of Nairobi".to_string(),
+            key_prefix: "fhk_0123abcd".to_string(),
+            scopes: vec![PROJECTS_READ.to_string()],
+            rate_limit_per_minute: 60,
+            created_by: None,
+            created_at: now,
+            expires_at: expires_in.map(|secs| now + Duration::seconds(secs)),
+            revoked_at: revoked.then_some(now),
+            last_used_at: None,
+        }
+    }
+
+    #[test]
+    fn test_is_active() {
+        let now = Utc::now();
+        assert!(key(false, None).is_active(now));
+        assert!(key(false, Some(3600)).is_active(now));
+        assert!(!key(false, Some(-1)).is_active(now));
+        assert!(!key(true, None).is_active(now));
+    }
+
+    #[test]
+    fn test_scopes() {
+        assert!(key(false, None).has_scope(PROJECTS_READ));
+        assert!(!key(false, None).has_scope(DONATIONS_READ));
+        let requested = vec![DONATIONS_READ.to_string(), "donations:write".to_string()];
+        assert_eq!(unknown_scopes(&requested), ["donations:write"]);
+    }
+
+    #[test]
+    fn test_hash_key_is_stable_hex() {
+        let hash = hash_key("fhk_example");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_key("fhk_example"));
+        assert_ne!(hash, hash_key("fhk_example2"));
+    }
+}
@@ -0,0 +1,337 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::env_u32;
+use crate::services::rbac;
+use crate::utils::jwt::Claims;
+
+/// Fund-moving actions that need a second admin
+pub const MILESTONE_RELEASE: &str = "milestone_release";
+pub const REFUND: &str = "refund";
+pub const CAMPAIGN_EXECUTE: &str = "campaign_execute";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApprovalError {
+    #[error("Approval request not found")]
+    NotFound,
+    #[error("The admin who initiated a request can't approve it")]
+    SelfApproval,
+    #[error("You may not approve this kind of action")]
+    NotPermitted,
+    #[error("Approval request is {0}")]
+    NotOpen(String),
+    #[error("Approval request was for a different action or payload")]
+    Mismatch,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for ApprovalError {
+    fn from(e: sqlx::Error) -> Self {
+        ApprovalError::Internal(e.into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalRequest {
+    pub id: Uuid,
+    pub action: String,
+    /// The exact request that was approved
+    pub payload: serde_json::Value,
+    /// pending, approved, rejected, expired or executed
+    pub status: String,
+    pub initiated_by: Uuid,
+    pub decided_by: Option<Uuid>,
+    pub decision_note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub executed_at: Option<DateTime<Utc>>,
+}
+
+/// How long a request stays usable, pending or approved
+pub fn ttl() -> Duration {
+    Duration::minutes(env_u32("APPROVAL_TTL_MINUTES", 60) as i64)
+}
+
+/// Whether the caller may approve (or reject) this kind of action. The
+/// approver needs the same access as whoever runs the action.
+pub fn may_decide(claims: &Claims, action: &str) -> bool {
+    match action {
+        MILESTONE_RELEASE => claims.is_admin(),
+        REFUND => claims.has_permission(rbac::PAYMENTS_REFUND),
+        CAMPAIGN_EXECUTE => claims.has_permission(rbac::CAMPAIGNS_EXECUTE),
+        _ => false,
+    }
+}
+
+fn check_open(request: &ApprovalRequest, expected: &str, now: DateTime<Utc>) -> Result<(), ApprovalError> {
+    if request.status != expected {
+        return Err(ApprovalError::NotOpen(request.status.clone()));
+    }
+    if request.expires_at <= now {
+        return Err(ApprovalError::NotOpen("expired".to_string()));
+    }
+    Ok(())
+}
+
+/// A pending request the caller may decide on
+pub fn check_decision(request: &ApprovalRequest, claims: &Claims, now: DateTime<Utc>) -> Result<(), ApprovalError> {
+    check_open(request, "pending", now)?;
+    if request.initiated_by == claims.sub {
+        return Err(ApprovalError::SelfApproval);
+    }
+    if !may_decide(claims, &request.action) {
+        return Err(ApprovalError::NotPermitted);
+    }
+    Ok(())
+}
+
+/// An approved request that covers exactly this action
+pub fn check_execution(
+    request: &ApprovalRequest,
+    action: &str,
+    payload: &serde_json::Value,
+    now: DateTime<Utc>,
+) -> Result<(), ApprovalError> {
+    check_open(request, "approved", now)?;
+    if request.action != action || &request.payload != payload {
+        return Err(ApprovalError::Mismatch);
+    }
+    Ok(())
+}
+
+/// Mark open requests past their deadline as expired
+pub async fn expire_stale(pool: &PgPool) -> Result<u64> {
+    let expired = sqlx::query!(
+        r#"
+        UPDATE approval_requests
+        SET status = 'expired'
+        WHERE status IN ('pending', 'approved') AND expires_at <= NOW()
+        "#
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(expired)
+}
+
+pub async fn initiate(
+    pool: &PgPool,
+    action: &str,
+    payload: &serde_json::Value,
+    initiated_by: Uuid,
+) -> Result<ApprovalRequest> {
+    let request = sqlx::query_as!(
+        ApprovalRequest,
+        r#"
+        INSERT INTO approval_requests (action, payload, initiated_by, expires_at)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, action, payload, status, initiated_by, decided_by, decision_note,
+                  created_at, expires_at, decided_at, executed_at
+        "#,
+        action,
+        payload,
+        initiated_by,
+        Utc::now() + ttl()
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(request)
+}
+
+pub async fn get(pool: &PgPool, id: Uuid) -> Result<Option<ApprovalRequest>> {
+    expire_stale(pool).await?;
+    let request = sqlx::query_as!(
+        ApprovalRequest,
+        r#"
+        SELECT id, action, payload, status, initiated_by, decided_by, decision_note,
+               created_at, expires_at, decided_at, executed_at
+        FROM approval_requests
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(request)
+}
+
+/// Requests, newest first, optionally by status
+pub async fn list(pool: &PgPool, status: Option<&str>) -> Result<Vec<ApprovalRequest>> {
+    expire_stale(pool).await?;
+    let requests = sqlx::query_as!(
+        ApprovalRequest,
+        r#"
+        SELECT id, action, payload, status, initiated_by, decided_by, decision_note,
+               created_at, expires_at, decided_at, executed_at
+        FROM approval_requests
+        WHERE ($1::TEXT IS NULL OR status = $1)
+        ORDER BY created_at DESC
+        LIMIT 200
+        "#,
+        status
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(requests)
+}
+
+/// Approve or reject a pending request as a second admin
+pub async fn decide(
+    pool: &PgPool,
+    id: Uuid,
+    claims: &Claims,
+    approve: bool,
+    note: Option<&str>,
+) -> Result<ApprovalRequest, ApprovalError> {
+    let mut tx = pool.begin().await?;
+    let request = lock(&mut tx, id).await?;
+    check_decision(&request, claims, Utc::now())?;
+
+    let request = sqlx::query_as!(
+        ApprovalRequest,
+        r#"
+        UPDATE approval_requests
+        SET status = $2, decided_by = $3, decision_note = $4, decided_at = NOW()
+        WHERE id = $1
+        RETURNING id, action, payload, status, initiated_by, decided_by, decision_note,
+                  created_at, expires_at, decided_at, executed_at
+        "#,
+        id,
+        if approve { "approved" } else { "rejected" },
+        claims.sub,
+        note
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(request)
+}
+
+/// Use up an approval before running its action. It is spent even if the
+/// action then fails, so an approval can never move funds twice.
+pub async fn consume(
+    pool: &PgPool,
+    id: Uuid,
+    action: &str,
+    payload: &serde_json::Value,
+) -> Result<ApprovalRequest, ApprovalError> {
+    let mut tx = pool.begin().await?;
+    let request = lock(&mut tx, id).await?;
+    check_execution(&request, action, payload, Utc::now())?;
+
+    let request = sqlx::query_as!(
+        ApprovalRequest,
+        r#"
+        UPDATE approval_requests
+        SET status = 'executed', executed_at = NOW()
+        WHERE id = $1
+        RETURNING id, action, payload, status, initiated_by, decided_by, decision_note,
+                  created_at, expires_at, decided_at, executed_at
+        "#,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(request)
+}
+
+async fn lock(conn: &mut sqlx::PgConnection, id: Uuid) -> Result<ApprovalRequest, ApprovalError> {
+    sqlx::query_as!(
+        ApprovalRequest,
+        r#"
+        SELECT id, action, payload, status, initiated_by, decided_by, decision_note,
+               created_at, expires_at, decided_at, executed_at
+        FROM approval_requests
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(ApprovalError::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(role: &str, permissions: &[&str]) -> Claims {
+        Claims {
+            sub: Uuid::new_v4(),
+            exp: 0,
+            iat: 0,
+            iss: "fundhub".to_string(),
+            role: role.to_string(),
+            base_role: "base_user".to_string(),
+            verification_status: None,
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            sid: None,
+        }
+    }
+
+    fn request(action: &str, status: &str, initiated_by: Uuid) -> ApprovalRequest {
+        let now = Utc::now();
+        ApprovalRequest {
+            id: Uuid::new_v4(),
+            action: action.to_string(),
+            payload: serde_json::json!({"payment_id": "pi_1", "amount": 500}),
+            status: status.to_string(),
+            initiated_by,
+            decided_by: None,
+            decision_note: None,
+            created_at: now,
+            expires_at: now + Duration::minutes(60),
+            decided_at: None,
+            executed_at: None,
+        }
+    }
+
+    #[test]
+    fn test_may_decide_follows_action_access() {
+        let finance = claims("finance", &[rbac::PAYMENTS_REFUND, rbac::CAMPAIGNS_EXECUTE]);
+        assert!(may_decide(&finance, REFUND));
+        assert!(may_decide(&finance, CAMPAIGN_EXECUTE));
+        assert!(!may_decide(&finance, MILESTONE_RELEASE));
+        assert!(may_decide(&claims("admin", &[]), MILESTONE_RELEASE));
+        assert!(!may_decide(&claims("admin", &[]), "unknown"));
+    }
+
+    #[test]
+    fn test_check_decision() {
+        let now = Utc::now();
+        let approver = claims("admin", &[]);
+        let pending = request(REFUND, "pending", Uuid::new_v4());
+        assert!(check_decision(&pending, &approver, now).is_ok());
+
+        let own = request(REFUND, "pending", approver.sub);
+        assert!(matches!(check_decision(&own, &approver, now), Err(ApprovalError::SelfApproval)));
+
+        let decided = request(REFUND, "rejected", Uuid::new_v4());
+        assert!(matches!(check_decision(&decided, &approver, now), Err(ApprovalError::NotOpen(s)) if s == "rejected"));
+
+        let late = now + Duration::minutes(61);
+        assert!(matches!(check_decision(&pending, &approver, late), Err(ApprovalError::NotOpen(s)) if s == "expired"));
+    }
+
+    #[test]
+    fn test_check_execution_requires_same_payload() {
+        let now = Utc::now();
+        let approved = request(REFUND, "approved", Uuid::new_v4());
+        let payload = approved.payload.clone();
+        assert!(check_execution(&approved, REFUND, &payload, now).is_ok());
+        assert!(matches!(check_execution(&approved, CAMPAIGN_EXECUTE, &payload, now), Err(ApprovalError::Mismatch)));
+
+        let changed = serde_json::json!({"payment_id": "pi_1", "amount": 5000});
+        assert!(matches!(check_execution(&approved, REFUND, &changed, now), Err(ApprovalError::Mismatch)));
+
+        let pending = request(REFUND, "pending", Uuid::new_v4());
+        assert!(matches!(check_execution(&pending, REFUND, &payload, now), Err(ApprovalError::NotOpen(_))));
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::university_domains;
+use crate::utils::money::Stroops;
+
+/// Criteria values from before the rules engine, kept working
+const LEGACY_ACTIVE_PROJECTS: &str = "active_projects";
+/// Project age the legacy `active_projects` criteria meant
+const LEGACY_ACTIVE_PROJECT_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RulesError {
+    #[error("Invalid campaign criteria: {0}")]
+    InvalidCriteria(String),
+    #[error("{0} can't be more than {1}")]
+    InvertedRange(&'static str, &'static str),
+    #[error("Invalid school pattern: {0}")]
+    InvalidSchool(String),
+    #[error("The per-head amount must be positive")]
+    NonPositiveAmount,
+}
+
+fn verified() -> Vec<String> {
+    vec!["verified".to_string()]
+}
+
+/// Which students a campaign pays, stored as JSON in `campaigns.criteria`.
+/// Every filter set must hold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Criteria {
+    /// Student verification statuses; verified students only by default
+    #[serde(default = "verified")]
+    pub verification_status: Vec<String>,
+    /// Has a project created at least this many days ago
+    #[serde(default)]
+    pub min_project_age_days: Option<i64>,
+    /// Has a project created within this many days
+    #[serde(default)]
+    pub max_project_age_days: Option<i64>,
+    /// Confirmed donations across the student's projects
+    #[serde(default)]
+    pub min_funds_raised_xlm: Option<Stroops>,
+    #[serde(default)]
+    pub max_funds_raised_xlm: Option<Stroops>,
+    /// School email domains, exact like `uonbi.ac.ke` or wildcards like
+    /// `*.ac.ke`; any school when empty
+    #[serde(default)]
+    pub schools: Vec<String>,
+}
+
+impl Default for Criteria {
+    fn default() -> Self {
+        Self {
+            verification_status: verified(),
+            min_project_age_days: None,
+            max_project_age_days: None,
+            min_funds_raised_xlm: None,
+            max_funds_raised_xlm: None,
+            schools: Vec::new(),
+        }
+    }
+}
+
+impl Criteria {
+    /// Read stored criteria: a JSON object, or one of the plain keywords
+    /// campaigns used before (`verified_students`, `active_projects`)
+    pub fn parse(raw: &str) -> Result<Self, RulesError> {
+        let raw = raw.trim();
+        let criteria = if raw.starts_with('{') {
+            serde_json::from_str(raw).map_err(|e| RulesError::InvalidCriteria(e.to_string()))?
+        } else if raw.contains(LEGACY_ACTIVE_PROJECTS) {
+            Self { max_project_age_days: Some(LEGACY_ACTIVE_PROJECT_DAYS), ..Self::default() }
+        } else {
+            Self::default()
+        };
+        criteria.check()?;
+        Ok(criteria)
+    }
+
+    fn check(&self) -> Result<(), RulesError> {
+        if let (Some(min), Some(max)) = (self.min_project_age_days, self.max_project_age_days) {
+            if min > max {
+                return Err(RulesError::InvertedRange("min_project_age_days", "max_project_age_days"));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_funds_raised_xlm, self.max_funds_raised_xlm) {
+            if min > max {
+                return Err(RulesError::InvertedRange("min_funds_raised_xlm", "max_funds_raised_xlm"));
+            }
+        }
+        if let Some(school) = self.schools.iter().find(|s| university_domains::normalize_pattern(s).is_none()) {
+            return Err(RulesError::InvalidSchool(school.clone()));
+        }
+        Ok(())
+    }
+
+    /// Whether a school email falls under one of the `schools` patterns
+    pub fn matches_school(&self, school_email: &str) -> bool {
+        if self.schools.is_empty() {
+            return true;
+        }
+        let Some(domain) = university_domains::email_domain(school_email) else {
+            return false;
+        };
+        let candidates = university_domains::candidates(&domain);
+        self.schools
+            .iter()
+            .filter_map(|s| university_domains::normalize_pattern(s))
+            .any(|pattern| candidates.contains(&pattern))
+    }
+}
+
+/// How a campaign's reward pool is split, stored in
+/// `campaigns.distribution_strategy`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Strategy {
+    /// The pool split evenly
+    #[default]
+    Equal,
+    /// The pool split in proportion to what each student has raised
+    WeightedByFunds,
+    /// A set amount each, until the pool or `max_recipients` runs out
+    FixedPerHead {
+        amount_xlm: Stroops,
+        #[serde(default)]
+        max_recipients: Option<usize>,
+    },
+}
+
+impl Strategy {
+    pub fn check(&self) -> Result<(), RulesError> {
+        match self {
+            Strategy::FixedPerHead { amount_xlm, .. } if amount_xlm.as_stroops() <= 0 => Err(RulesError::NonPositiveAmount),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A student the criteria selected
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub student_id: Uuid,
+    pub school_email: String,
+    pub funds_raised: Stroops,
+}
+
+/// Students matching `criteria`, longest-registered first
+pub async fn eligible(pool: &PgPool, criteria: &Criteria, now: DateTime<Utc>) -> Result<Vec<Candidate>> {
+    let created_before = criteria.min_project_age_days.map(|days| now - Duration::days(days));
+    let created_after = criteria.max_project_age_days.map(|days| now - Duration::days(days));
+
+    let rows = sqlx::query!(
+        r#"
+        WITH raised AS (
+            SELECT p.student_id, SUM(d.amount) as raised
+            FROM donations d
+            JOIN projects p ON p.id = d.project_id
+            WHERE d.status = 'confirmed'
+            GROUP BY p.student_id
+        )
+        SELECT s.id as student_id, s.school_email,
+               COALESCE(r.raised, 0) as "funds_raised!: Stroops"
+        FROM students s
+        LEFT JOIN raised r ON r.student_id = s.id
+        WHERE s.verification_status = ANY($1)
+          AND (($2::timestamptz IS NULL AND $3::timestamptz IS NULL) OR EXISTS (
+                SELECT 1 FROM projects p
+                WHERE p.student_id = s.id
+                  AND ($2::timestamptz IS NULL OR p.created_at <= $2)
+                  AND ($3::timestamptz IS NULL OR p.created_at >= $3)
+          ))
+          AND ($4::numeric IS NULL OR COALESCE(r.raised, 0) >= $4)
+          AND ($5::numeric IS NULL OR COALESCE(r.raised, 0) <= $5)
+        ORDER BY s.created_at, s.id
+        "#,
+        &criteria.verification_status,
+        created_before,
+        created_after,
+        criteria.min_funds_raised_xlm.map(Stroops::to_decimal),
+        criteria.max_funds_raised_xlm.map(Stroops::to_decimal)
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| Candidate { student_id: r.student_id, school_email: r.school_email, funds_raised: r.funds_raised })
+        .filter(|c| criteria.matches_school(&c.school_email))
+        .collect())
+}
+
+/// Each candidate's share of `pool` under `strategy`, in candidate order.
+/// Shares never add up to more than the pool; candidates left with nothing
+/// are dropped.
+pub fn allocate(pool: Stroops, strategy: &Strategy, candidates: &[Candidate]) -> Vec<(Uuid, Stroops)> {
+    let shares = match strategy {
+        Strategy::Equal => pool.split_even(candidates.len()),
+        Strategy::WeightedByFunds => {
+            let weights: Vec<i64> = candidates.iter().map(|c| c.funds_raised.as_stroops().max(0)).collect();
+            split_weighted(pool, &weights)
+        }
+        Strategy::FixedPerHead { amount_xlm, max_recipients } => {
+            let per_head = amount_xlm.as_stroops();
+            let affordable = if per_head > 0 { (pool.as_stroops().max(0) / per_head) as usize } else { 0 };
+            let heads = candidates.len().min(affordable).min(max_recipients.unwrap_or(usize::MAX));
+            (0..candidates.len())
+                .map(|i| if i < heads { *amount_xlm } else { Stroops::default() })
+                .collect()
+        }
+    };
+
+    candidates
+        .iter()
+        .zip(shares)
+        .filter(|(_, share)| share.as_stroops() > 0)
+        .map(|(c, share)| (c.student_id, share))
+        .collect()
+}
+
+/// Split `pool` in proportion to `weights`, to the stroop; leftover stroops
+/// go one each to the first weighted shares. Evenly when nothing is weighted.
+fn split_weighted(pool: Stroops, weights: &[i64]) -> Vec<Stroops> {
+    let total: i128 = weights.iter().map(|w| *w as i128).sum();
+    if total == 0 {
+        return pool.split_even(weights.len());
+    }
+
+    let pool = pool.as_stroops() as i128;
+    let mut shares: Vec<i64> = weights.iter().map(|w| (pool * *w as i128 / total) as i64).collect();
+    let mut leftover = (pool - shares.iter().map(|s| *s as i128).sum::<i128>()) as i64;
+    for (share, weight) in shares.iter_mut().zip(weights) {
+        if leftover == 0 {
+            break;
+        }
+        if *weight > 0 {
+            *share += 1;
+            leftover -= 1;
+        }
+    }
+    shares.into_iter().map(Stroops::from_stroops).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(raised: i64) -> Candidate {
+        Candidate { student_id: Uuid::new_v4(), school_email: "a@uonbi.ac.ke".to_string(), funds_raised: Stroops::from_stroops(raised) }
+    }
+
+    #[test]
+    fn test_parse_legacy_keywords() {
+        assert_eq!(Criteria::parse("verified_students").unwrap(), Criteria::default());
+        assert_eq!(Criteria::parse("active_projects").unwrap().max_project_age_days, Some(30));
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let criteria = Criteria::parse(r#"{"min_funds_raised_xlm": "10", "schools": ["*.ac.ke"]}"#).unwrap();
+        assert_eq!(criteria.verification_status, vec!["verified".to_string()]);
+        assert_eq!(criteria.min_funds_raised_xlm, Some(Stroops::from_stroops(100_000_000)));
+        assert!(criteria.matches_school("student@uonbi.ac.ke"));
+        assert!(!criteria.matches_school("student@example.com"));
+
+        assert!(Criteria::parse(r#"{"school": "uonbi.ac.ke"}"#).is_err());
+        assert_eq!(
+            Criteria::parse(r#"{"min_project_age_days": 60, "max_project_age_days": 30}"#),
+            Err(RulesError::InvertedRange("min_project_age_days", "max_project_age_days"))
+        );
+    }
+
+    #[test]
+    fn test_allocate_equal() {
+        let candidates = vec![candidate(0), candidate(0), candidate(0)];
+        let shares = allocate(Stroops::from_stroops(100), &Strategy::Equal, &candidates);
+        assert_eq!(shares.iter().map(|(_, s)| s.as_stroops()).collect::<Vec<_>>(), vec![34, 33, 33]);
+    }
+
+    #[test]
+    fn test_allocate_weighted_by_funds() {
+        let candidates = vec![candidate(300), candidate(100), candidate(0)];
+        let shares = allocate(Stroops::from_stroops(101), &Strategy::WeightedByFunds, &candidates);
+        assert_eq!(shares.iter().map(|(_, s)| s.as_stroops()).collect::<Vec<_>>(), vec![76, 25]);
+        assert_eq!(shares.iter().map(|(_, s)| *s).sum::<Stroops>(), Stroops::from_stroops(101));
+    }
+
+    #[test]
+    fn test_allocate_fixed_per_head_caps() {
+        let candidates = vec![candidate(0), candidate(0), candidate(0), candidate(0)];
+        let strategy = Strategy::FixedPerHead { amount_xlm: Stroops::from_stroops(30), max_recipients: None };
+        assert_eq!(allocate(Stroops::from_stroops(100), &strategy, &candidates).len(), 3);
+
+        let strategy = Strategy::FixedPerHead { amount_xlm: Stroops::from_stroops(30), max_recipients: Some(2) };
+        assert_eq!(allocate(Stroops::from_stroops(100), &strategy, &candidates).len(), 2);
+    }
+
+    #[test]
+    fn test_strategy_json() {
+        let strategy: Strategy = serde_json::from_str(r#"{"type": "fixed_per_head", "amount_xlm": "5"}"#).unwrap();
+        assert_eq!(strategy, Strategy::FixedPerHead { amount_xlm: Stroops::from_stroops(50_000_000), max_recipients: None });
+        assert_eq!(serde_json::from_str::<Strategy>(r#"{"type": "equal"}"#).unwrap(), Strategy::Equal);
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::jobs::{self, Job};
+
+/// How an active campaign's distribution starts once its end date passes
+pub const TRIGGERS: &[&str] = &["end_date", "manual"];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("The end date has already passed")]
+    EndPassed,
+    #[error("The end date must fall after the start date")]
+    EndBeforeStart,
+    #[error("Distribution trigger must be one of: end_date, manual")]
+    UnknownTrigger,
+}
+
+/// Check a new campaign's dates and trigger
+pub fn check_schedule(
+    now: DateTime<Utc>,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+    trigger: &str,
+) -> Result<(), ScheduleError> {
+    if !TRIGGERS.contains(&trigger) {
+        return Err(ScheduleError::UnknownTrigger);
+    }
+    let Some(end_date) = end_date else { return Ok(()) };
+    if end_date <= now {
+        return Err(ScheduleError::EndPassed);
+    }
+    if start_date.is_some_and(|start| end_date <= start) {
+        return Err(ScheduleError::EndBeforeStart);
+    }
+    Ok(())
+}
+
+/// Status a new campaign starts in: `scheduled` until a future start date
+pub fn initial_status(now: DateTime<Utc>, start_date: Option<DateTime<Utc>>) -> &'static str {
+    match start_date {
+        Some(start) if start > now => "scheduled",
+        _ => "active",
+    }
+}
+
+/// Status an active campaign moves to at its end date: `distributing` while
+/// its own payout job runs, or `ended` until an admin executes it
+pub fn status_at_end(trigger: &str) -> &'static str {
+    match trigger {
+        "end_date" => "distributing",
+        _ => "ended",
+    }
+}
+
+/// Scheduled campaigns whose start date has come
+pub async fn due_starts(pool: &PgPool, limit: i64) -> Result<Vec<Uuid>> {
+    Ok(sqlx::query_scalar!(
+        r#"
+        SELECT id FROM campaigns
+        WHERE status = 'scheduled' AND start_date <= NOW()
+        ORDER BY start_date
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Active campaigns whose end date has passed, with their trigger
+pub async fn due_ends(pool: &PgPool, limit: i64) -> Result<Vec<(Uuid, String)>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, distribution_trigger FROM campaigns
+        WHERE status = 'active' AND end_date <= NOW()
+        ORDER BY end_date
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| (r.id, r.distribution_trigger)).collect())
+}
+
+/// Record a campaign's status change in the activity log
+pub async fn record_transition<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    campaign_id: Uuid,
+    from: &str,
+    to: &str,
+    reason: &str,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        "campaign_status_changed",
+        campaign_id,
+        "campaign",
+        serde_json::json!({ "from": from, "to": to, "reason": reason })
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Make a scheduled campaign active; false if it no longer was scheduled
+pub async fn activate(pool: &PgPool, campaign_id: Uuid) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let updated = sqlx::query!(
+        "UPDATE campaigns SET status = 'active', updated_at = NOW() WHERE id = $1 AND status = 'scheduled'",
+        campaign_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if updated == 0 {
+        return Ok(false);
+    }
+    record_transition(&mut *tx, campaign_id, "scheduled", "active", "start_date").await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// End an active campaign, queueing its distribution if its trigger is its
+/// end date. Returns the status it moved to, or `None` if it no longer was
+/// active.
+pub async fn end(pool: &PgPool, campaign_id: Uuid, trigger: &str) -> Result<Option<&'static str>> {
+    let status = status_at_end(trigger);
+    let mut tx = pool.begin().await?;
+    let updated = sqlx::query!(
+        "UPDATE campaigns SET status = $2, updated_at = NOW() WHERE id = $1 AND status = 'active'",
+        campaign_id,
+        status
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if updated == 0 {
+        return Ok(None);
+    }
+
+    if status == "distributing" {
+        let job = Job::DistributeCampaignFunds { requested_by: None, campaign_id: Some(campaign_id) };
+        let key = format!("campaign_end:{}", campaign_id);
+        jobs::enqueue(&mut *tx, &job, Utc::now(), Some(&key)).await?;
+    }
+    record_transition(&mut *tx, campaign_id, "active", status, "end_date").await?;
+    tx.commit().await?;
+    Ok(Some(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_check_schedule() {
+        let now = Utc::now();
+        assert_eq!(check_schedule(now, None, None, "end_date"), Ok(()));
+        assert_eq!(check_schedule(now, Some(now + Duration::days(1)), Some(now + Duration::days(7)), "manual"), Ok(()));
+        assert_eq!(check_schedule(now, None, Some(now - Duration::hours(1)), "end_date"), Err(ScheduleError::EndPassed));
+        assert_eq!(
+            check_schedule(now, Some(now + Duration::days(7)), Some(now + Duration::days(3)), "end_date"),
+            Err(ScheduleError::EndBeforeStart)
+        );
+        assert_eq!(check_schedule(now, None, None, "weekly"), Err(ScheduleError::UnknownTrigger));
+    }
+
+    #[test]
+    fn test_initial_status() {
+        let now = Utc::now();
+        assert_eq!(initial_status(now, None), "active");
+        assert_eq!(initial_status(now, Some(now - Duration::hours(1))), "active");
+        assert_eq!(initial_status(now, Some(now + Duration::hours(1))), "scheduled");
+    }
+
+    #[test]
+    fn test_status_at_end() {
+        assert_eq!(status_at_end("end_date"), "distributing");
+        assert_eq!(status_at_end("manual"), "ended");
+    }
+}
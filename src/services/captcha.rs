@@ -0,0 +1,76 @@
+use serde::Deserialize;
+
+/// Server-side check of a CAPTCHA token. Turnstile, hCaptcha and reCAPTCHA
+/// share the same siteverify contract, so one client covers all three.
+#[derive(Clone)]
+pub struct CaptchaVerifier {
+    client: reqwest::Client,
+    provider: &'static str,
+    verify_url: &'static str,
+    secret: String,
+}
+
+#[derive(Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+}
+
+impl CaptchaVerifier {
+    pub fn new(provider: &str, secret: String) -> Result<Self, String> {
+        let (provider, verify_url) = match provider.trim().to_lowercase().as_str() {
+            "turnstile" => ("turnstile", "https://challenges.cloudflare.com/turnstile/v0/siteverify"),
+            "hcaptcha" => ("hcaptcha", "https://api.hcaptcha.com/siteverify"),
+            "recaptcha" => ("recaptcha", "https://www.google.com/recaptcha/api/siteverify"),
+            other => return Err(format!("Unknown CAPTCHA_PROVIDER {}", other)),
+        };
+        Ok(Self { client: reqwest::Client::new(), provider, verify_url, secret })
+    }
+
+    /// `CAPTCHA_PROVIDER` and `CAPTCHA_SECRET`; `None` leaves CAPTCHA off
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(provider) = std::env::var("CAPTCHA_PROVIDER").ok().filter(|p| !p.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let secret = std::env::var("CAPTCHA_SECRET").map_err(|_| "CAPTCHA_SECRET must be set".to_string())?;
+        Self::new(&provider, secret).map(Some)
+    }
+
+    pub fn provider(&self) -> &'static str {
+        self.provider
+    }
+
+    pub async fn verify(&self, token: &str, remote_ip: Option<&str>) -> Result<bool, String> {
+        let mut form = vec![("secret", self.secret.as_str()), ("response", token)];
+        if let Some(ip) = remote_ip {
+            form.push(("remoteip", ip));
+        }
+
+        let response = self
+            .client
+            .post(self.verify_url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| format!("{} request failed: {}", self.provider, e))?;
+        if !response.status().is_success() {
+            return Err(format!("{} returned {}", self.provider, response.status()));
+        }
+        let body: SiteVerifyResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("{} returned an invalid response: {}", self.provider, e))?;
+        Ok(body.success)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_providers() {
+        assert_eq!(CaptchaVerifier::new("Turnstile", "s".into()).unwrap().provider(), "turnstile");
+        assert_eq!(CaptchaVerifier::new("hcaptcha", "s".into()).unwrap().provider(), "hcaptcha");
+        assert!(CaptchaVerifier::new("funcaptcha", "s".into()).is_err());
+    }
+}
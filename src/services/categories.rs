@@ -0,0 +1,212 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Tags kept per project
+pub const MAX_TAGS: usize = 10;
+/// Longest tag or category slug, in characters
+pub const MAX_TAG_LEN: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CategoryError {
+    #[error("Unknown categories: {}", .0.join(", "))]
+    Unknown(Vec<String>),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for CategoryError {
+    fn from(e: sqlx::Error) -> Self {
+        CategoryError::Internal(e.into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Category {
+    pub id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategorySummary {
+    #[serde(flatten)]
+    pub category: Category,
+    /// Active projects filed under the category
+    pub project_count: i64,
+}
+
+/// Lowercase, with every run of other characters turned into one hyphen,
+/// e.g. `"Machine Learning!"` becomes `"machine-learning"`. `None` when
+/// nothing is left. Matches the clean-up the categories migration ran on
+/// existing tags.
+pub fn normalize_tag(raw: &str) -> Option<String> {
+    let mut tag = String::with_capacity(raw.len());
+    for c in raw.trim().to_lowercase().chars() {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            tag.push(c);
+        } else if !tag.ends_with('-') {
+            tag.push('-');
+        }
+    }
+    tag.truncate(MAX_TAG_LEN);
+    let tag = tag.trim_matches('-');
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+/// Normalized tags in the order given, without blanks or duplicates, and at
+/// most `MAX_TAGS` of them
+pub fn normalize_tags(raw: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in raw.iter().filter_map(|t| normalize_tag(t)) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+        if tags.len() == MAX_TAGS {
+            break;
+        }
+    }
+    tags
+}
+
+/// Categories by name, with how many active projects each has
+pub async fn list(pool: &PgPool) -> Result<Vec<CategorySummary>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT c.id, c.slug, c.name, c.description, c.created_at,
+               COUNT(p.id) as "project_count!"
+        FROM categories c
+        LEFT JOIN project_categories pc ON pc.category_id = c.id
+        LEFT JOIN projects p ON p.id = pc.project_id AND p.status = 'active'
+        GROUP BY c.id
+        ORDER BY c.name
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| CategorySummary {
+            category: Category { id: r.id, slug: r.slug, name: r.name, description: r.description, created_at: r.created_at },
+            project_count: r.project_count,
+        })
+        .collect())
+}
+
+pub async fn find_by_slug(pool: &PgPool, slug: &str) -> Result<Option<Category>> {
+    let category = sqlx::query_as!(
+        Category,
+        "SELECT id, slug, name, description, created_at FROM categories WHERE slug = $1",
+        slug
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(category)
+}
+
+/// Add a category; `None` if the slug is taken
+pub async fn create(pool: &PgPool, slug: &str, name: &str, description: Option<&str>) -> Result<Option<Category>> {
+    let category = sqlx::query_as!(
+        Category,
+        r#"
+        INSERT INTO categories (slug, name, description)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (slug) DO NOTHING
+        RETURNING id, slug, name, description, created_at
+        "#,
+        slug,
+        name,
+        description
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(category)
+}
+
+/// Remove a category and take its projects out of it
+pub async fn delete(pool: &PgPool, slug: &str) -> Result<Option<Category>> {
+    let category = sqlx::query_as!(
+        Category,
+        "DELETE FROM categories WHERE slug = $1 RETURNING id, slug, name, description, created_at",
+        slug
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(category)
+}
+
+/// A project's categories, by name
+pub async fn for_project(pool: &PgPool, project_id: Uuid) -> Result<Vec<Category>> {
+    let categories = sqlx::query_as!(
+        Category,
+        r#"
+        SELECT c.id, c.slug, c.name, c.description, c.created_at
+        FROM categories c
+        JOIN project_categories pc ON pc.category_id = c.id
+        WHERE pc.project_id = $1
+        ORDER BY c.name
+        "#,
+        project_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(categories)
+}
+
+/// Replace a project's categories with those named by `slugs`; nothing
+/// changes if any of them don't exist
+pub async fn set_for_project(pool: &PgPool, project_id: Uuid, slugs: &[String]) -> Result<(), CategoryError> {
+    let found = sqlx::query!("SELECT id, slug FROM categories WHERE slug = ANY($1)", slugs)
+        .fetch_all(pool)
+        .await?;
+    let unknown: Vec<String> = slugs.iter().filter(|s| !found.iter().any(|c| &c.slug == *s)).cloned().collect();
+    if !unknown.is_empty() {
+        return Err(CategoryError::Unknown(unknown));
+    }
+
+    let ids: Vec<Uuid> = found.into_iter().map(|c| c.id).collect();
+    let mut tx = pool.begin().await?;
+    sqlx::query!("DELETE FROM project_categories WHERE project_id = $1", project_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO project_categories (project_id, category_id)
+        SELECT $1, unnest($2::uuid[])
+        "#,
+        project_id,
+        &ids
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag("  Machine Learning! ").as_deref(), Some("machine-learning"));
+        assert_eq!(normalize_tag("C++ / Rust").as_deref(), Some("c-rust"));
+        assert_eq!(normalize_tag("web3").as_deref(), Some("web3"));
+        assert_eq!(normalize_tag("--- ").as_deref(), None);
+        assert_eq!(normalize_tag(&"a".repeat(40)).map(|t| t.len()), Some(MAX_TAG_LEN));
+    }
+
+    #[test]
+    fn test_normalize_tags_dedupes_in_order() {
+        let raw: Vec<String> = ["AI", "ai ", "Clean Energy", "", "clean_energy"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(normalize_tags(&raw), ["ai", "clean-energy"]);
+
+        let many: Vec<String> = (0..20).map(|i| format!("tag{}", i)).collect();
+        assert_eq!(normalize_tags(&many).len(), MAX_TAGS);
+    }
+}
@@ -0,0 +1,371 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::services::notifications::NotificationEvent;
+use crate::utils::pagination::{Page, PageRequest};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CommentError {
+    #[error("The comment being replied to doesn't exist on this project")]
+    ParentNotFound,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for CommentError {
+    fn from(e: sqlx::Error) -> Self {
+        CommentError::Internal(e.into())
+    }
+}
+
+/// Who wrote a comment, with the badges shown next to their name
+#[derive(Debug, Clone, Serialize)]
+pub struct Commenter {
+    pub user_id: Uuid,
+    pub username: String,
+    /// Owns the project being discussed
+    pub is_owner: bool,
+    /// Has a confirmed donation to the project
+    pub is_donor: bool,
+    /// A verified student
+    pub is_verified: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Comment {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    /// `None` once deleted
+    pub author: Option<Commenter>,
+    /// `None` once deleted
+    pub body: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub deleted: bool,
+    /// Oldest first; always empty on replies
+    pub replies: Vec<Comment>,
+}
+
+/// A comment with its author's badges, as loaded
+#[derive(Debug, Clone)]
+pub struct CommentRow {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub username: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub is_owner: bool,
+    pub is_donor: bool,
+    pub is_verified: bool,
+}
+
+impl From<CommentRow> for Comment {
+    fn from(row: CommentRow) -> Self {
+        let deleted = row.deleted_at.is_some();
+        Self {
+            id: row.id,
+            project_id: row.project_id,
+            parent_id: row.parent_id,
+            author: (!deleted).then_some(Commenter {
+                user_id: row.user_id,
+                username: row.username,
+                is_owner: row.is_owner,
+                is_donor: row.is_donor,
+                is_verified: row.is_verified,
+            }),
+            body: (!deleted).then_some(row.body),
+            created_at: row.created_at,
+            deleted,
+            replies: Vec::new(),
+        }
+    }
+}
+
+/// Who may remove a comment: its author, the project's owner or an admin
+#[derive(Debug)]
+pub struct CommentAccess {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub author_id: Uuid,
+    pub owner_user_id: Uuid,
+}
+
+/// Assemble top-level comments, in the order of `roots`, with their replies.
+/// Replies whose top-level comment isn't among `roots` are dropped.
+pub fn build_threads(roots: &[Uuid], rows: Vec<CommentRow>) -> Vec<Comment> {
+    let mut threads: HashMap<Uuid, Comment> = HashMap::new();
+    let mut replies: Vec<CommentRow> = Vec::new();
+    for row in rows {
+        match row.parent_id {
+            None => {
+                threads.insert(row.id, row.into());
+            }
+            Some(_) => replies.push(row),
+        }
+    }
+
+    replies.sort_by_key(|r| (r.created_at, r.id));
+    for reply in replies {
+        if let Some(thread) = reply.parent_id.and_then(|p| threads.get_mut(&p)) {
+            thread.replies.push(reply.into());
+        }
+    }
+
+    roots.iter().filter_map(|id| threads.remove(id)).collect()
+}
+
+/// The given comments and the live replies beneath them, with badges
+async fn load_rows(pool: &PgPool, ids: &[Uuid]) -> Result<Vec<CommentRow>> {
+    let rows = sqlx::query_as!(
+        CommentRow,
+        r#"
+        SELECT c.id, c.project_id, c.parent_id, c.user_id, u.username, c.body,
+               c.created_at, COALESCE(c.deleted_at, c.hidden_at) as deleted_at,
+               EXISTS(
+                   SELECT 1 FROM projects p JOIN students s ON s.id = p.student_id
+                   WHERE p.id = c.project_id AND s.user_id = c.user_id
+               ) as "is_owner!",
+               EXISTS(
+                   SELECT 1 FROM donations d
+                   WHERE d.project_id = c.project_id AND d.donor_id = c.user_id AND d.status = 'confirmed'
+               ) as "is_donor!",
+               EXISTS(
+                   SELECT 1 FROM students s
+                   WHERE s.user_id = c.user_id AND s.verification_status = 'verified'
+               ) as "is_verified!"
+        FROM project_comments c
+        JOIN users u ON u.id = c.user_id
+        WHERE c.id = ANY($1)
+           OR (c.parent_id = ANY($1) AND c.deleted_at IS NULL AND c.hidden_at IS NULL)
+        "#,
+        ids
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// A page of a project's threads, newest first. Deleted comments, and those
+/// hidden by moderation, are left out unless they still have replies, in
+/// which case they stay as placeholders.
+pub async fn list_threads(pool: &PgPool, project_id: Uuid, page: &PageRequest) -> Result<Page<Comment>> {
+    let roots = sqlx::query!(
+        r#"
+        SELECT c.id, c.created_at
+        FROM project_comments c
+        WHERE c.project_id = $1
+          AND c.parent_id IS NULL
+          AND (
+              (c.deleted_at IS NULL AND c.hidden_at IS NULL)
+              OR EXISTS(
+                  SELECT 1 FROM project_comments r
+                  WHERE r.parent_id = c.id AND r.deleted_at IS NULL AND r.hidden_at IS NULL
+              )
+          )
+          AND ($2::timestamptz IS NULL OR (c.created_at, c.id) < ($2, $3::uuid))
+        ORDER BY c.created_at DESC, c.id DESC
+        LIMIT $4
+        "#,
+        project_id,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let roots = Page::new(roots, page, |r| (Some(r.created_at), r.id));
+    let ids: Vec<Uuid> = roots.items.iter().map(|r| r.id).collect();
+    let rows = if ids.is_empty() { Vec::new() } else { load_rows(pool, &ids).await? };
+
+    Ok(Page {
+        items: build_threads(&ids, rows),
+        next_cursor: roots.next_cursor,
+        has_more: roots.has_more,
+    })
+}
+
+/// Post a comment, or a reply when `parent_id` is set. A reply to a reply
+/// joins the same thread.
+pub async fn create(
+    pool: &PgPool,
+    project_id: Uuid,
+    user_id: Uuid,
+    parent_id: Option<Uuid>,
+    body: &str,
+) -> Result<Comment, CommentError> {
+    let parent_id = match parent_id {
+        Some(parent_id) => {
+            let parent = sqlx::query!(
+                r#"
+                SELECT id, parent_id FROM project_comments
+                WHERE id = $1 AND project_id = $2 AND deleted_at IS NULL AND hidden_at IS NULL
+                "#,
+                parent_id,
+                project_id
+            )
+            .fetch_optional(pool)
+            .await?
+            .ok_or(CommentError::ParentNotFound)?;
+            Some(parent.parent_id.unwrap_or(parent.id))
+        }
+        None => None,
+    };
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO project_comments (project_id, user_id, parent_id, body)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+        project_id,
+        user_id,
+        parent_id,
+        body
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let row = load_rows(pool, &[id])
+        .await?
+        .into_iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| anyhow::anyhow!("Comment {} vanished after insert", id))?;
+    Ok(row.into())
+}
+
+/// The comment's author and project owner, for checking who may delete it;
+/// `None` if it doesn't exist or is already deleted
+pub async fn access(pool: &PgPool, project_id: Uuid, comment_id: Uuid) -> Result<Option<CommentAccess>> {
+    let access = sqlx::query_as!(
+        CommentAccess,
+        r#"
+        SELECT c.id, c.project_id, c.user_id as author_id, s.user_id as owner_user_id
+        FROM project_comments c
+        JOIN projects p ON p.id = c.project_id
+        JOIN students s ON s.id = p.student_id
+        WHERE c.id = $1 AND c.project_id = $2 AND c.deleted_at IS NULL
+        "#,
+        comment_id,
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(access)
+}
+
+/// Soft-delete a comment; its replies stay visible under a placeholder
+pub async fn delete(pool: &PgPool, comment_id: Uuid, deleted_by: Uuid) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE project_comments
+        SET deleted_at = NOW(), deleted_by = $2
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        comment_id,
+        deleted_by
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Tell the project's owner about a new comment in-app, unless they wrote
+/// it. Returns the owner's user id when a notification was stored.
+pub async fn notify_owner(pool: &PgPool, comment: &Comment) -> Result<Option<Uuid>> {
+    let Some(author) = &comment.author else { return Ok(None) };
+    let owner = sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+        SELECT s.user_id, 'project', $3, $4, $5, $6
+        FROM projects p
+        JOIN students s ON s.id = p.student_id
+        WHERE p.id = $1 AND s.user_id <> $2
+        RETURNING user_id
+        "#,
+        comment.project_id,
+        author.user_id,
+        if comment.parent_id.is_some() { "New reply on your project" } else { "New comment on your project" },
+        format!("{} commented: {}", author.username, preview(comment.body.as_deref().unwrap_or_default())),
+        serde_json::json!({
+            "project_id": comment.project_id,
+            "comment_id": comment.id,
+            "parent_id": comment.parent_id,
+            "author_id": author.user_id
+        }),
+        NotificationEvent::ProjectComment { project_id: comment.project_id, comment_id: comment.id }.to_json()
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(owner.map(|o| o.user_id))
+}
+
+/// The start of a comment for notification text
+fn preview(body: &str) -> String {
+    const PREVIEW_CHARS: usize = 80;
+    let body = body.trim();
+    match body.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &body[..end]),
+        None => body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: Uuid, parent_id: Option<Uuid>, secs: i64, deleted: bool) -> CommentRow {
+        CommentRow {
+            id,
+            project_id: Uuid::nil(),
+            parent_id,
+            user_id: Uuid::new_v4(),
+            username: "ada".to_string(),
+            body: "Is the prototype open source?".to_string(),
+            created_at: DateTime::from_timestamp(1_729_600_000 + secs, 0).unwrap(),
+            deleted_at: deleted.then(Utc::now),
+            is_owner: false,
+            is_donor: true,
+            is_verified: false,
+        }
+    }
+
+    #[test]
+    fn test_build_threads_keeps_root_order_and_sorts_replies() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let (r1, r2) = (Uuid::new_v4(), Uuid::new_v4());
+        let rows = vec![row(r2, Some(a), 30, false), row(a, None, 0, false), row(r1, Some(a), 20, false), row(b, None, 10, false)];
+
+        let threads = build_threads(&[b, a], rows);
+        assert_eq!(threads.iter().map(|t| t.id).collect::<Vec<_>>(), [b, a]);
+        assert!(threads[0].replies.is_empty());
+        assert_eq!(threads[1].replies.iter().map(|r| r.id).collect::<Vec<_>>(), [r1, r2]);
+        assert!(threads[1].replies[0].author.as_ref().unwrap().is_donor);
+    }
+
+    #[test]
+    fn test_build_threads_hides_deleted_content() {
+        let (a, r) = (Uuid::new_v4(), Uuid::new_v4());
+        let orphan = row(Uuid::new_v4(), Some(Uuid::new_v4()), 5, false);
+        let threads = build_threads(&[a], vec![row(a, None, 0, true), row(r, Some(a), 1, false), orphan]);
+
+        assert_eq!(threads.len(), 1);
+        assert!(threads[0].deleted);
+        assert!(threads[0].author.is_none() && threads[0].body.is_none());
+        assert_eq!(threads[0].replies.len(), 1);
+    }
+
+    #[test]
+    fn test_preview_truncates_on_char_boundary() {
+        assert_eq!(preview("  short  "), "short");
+        let long = "é".repeat(100);
+        assert_eq!(preview(&long).chars().count(), 81);
+    }
+}
@@ -0,0 +1,314 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::notifications::NotificationEvent;
+use crate::services::payouts::{self, Payout};
+use crate::services::stellar_tx::TxSubmitter;
+use crate::utils::money::Stroops;
+
+/// What happens to a completed project's leftover balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettlementPolicy {
+    /// Paid out to the student
+    Payout,
+    /// Returned to donors in proportion to their donations
+    Refund,
+}
+
+impl SettlementPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SettlementPolicy::Payout => "payout",
+            SettlementPolicy::Refund => "refund",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "payout" => Some(SettlementPolicy::Payout),
+            "refund" => Some(SettlementPolicy::Refund),
+            _ => None,
+        }
+    }
+
+    /// The platform default, from `PROJECT_SETTLEMENT_POLICY`; payout when unset
+    pub fn from_env() -> Self {
+        std::env::var("PROJECT_SETTLEMENT_POLICY")
+            .ok()
+            .and_then(|v| Self::parse(&v))
+            .unwrap_or(SettlementPolicy::Payout)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CompletionError {
+    #[error("Platform payments are not configured")]
+    PaymentsUnavailable,
+    #[error("Student has no wallet to pay the remaining balance to")]
+    NoWallet,
+    #[error("Paying out the remaining balance failed: {0}")]
+    Payout(String),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for CompletionError {
+    fn from(e: sqlx::Error) -> Self {
+        CompletionError::Internal(e.into())
+    }
+}
+
+/// How a completed project's leftover balance was, or is being, settled
+#[derive(Debug, Clone, Serialize)]
+pub struct Settlement {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub policy: String,
+    pub amount: Stroops,
+    /// `pending`, `settled` or `failed`
+    pub status: String,
+    pub tx_hash: Option<String>,
+    pub claimable_balance_id: Option<String>,
+    pub error: Option<String>,
+    pub requested_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub settled_at: Option<DateTime<Utc>>,
+}
+
+/// A milestone neither released nor cancelled
+#[derive(Debug, Clone, Serialize)]
+pub struct OutstandingMilestone {
+    pub id: Uuid,
+    pub title: String,
+    pub target_amount: Stroops,
+}
+
+/// Confirmed donations against released milestone funds
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectFunds {
+    pub raised: Stroops,
+    pub released: Stroops,
+    pub milestones_released: i64,
+}
+
+impl ProjectFunds {
+    /// What's left to settle; never negative
+    pub fn remaining(&self) -> Stroops {
+        let remaining = self.raised - self.released;
+        if remaining.is_positive() {
+            remaining
+        } else {
+            Stroops::ZERO
+        }
+    }
+}
+
+pub async fn outstanding_milestones(pool: &PgPool, project_id: Uuid) -> Result<Vec<OutstandingMilestone>> {
+    let milestones = sqlx::query_as!(
+        OutstandingMilestone,
+        r#"
+        SELECT id, title, target_amount as "target_amount!: Stroops"
+        FROM milestones
+        WHERE project_id = $1 AND NOT COALESCE(released, FALSE) AND cancelled_at IS NULL
+        ORDER BY created_at
+        "#,
+        project_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(milestones)
+}
+
+pub async fn funds(pool: &PgPool, project_id: Uuid) -> Result<ProjectFunds> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COALESCE((SELECT SUM(amount) FROM donations
+                         WHERE project_id = $1 AND status = 'confirmed'), 0) as "raised!: Stroops",
+               COALESCE((SELECT SUM(target_amount) FROM milestones
+                         WHERE project_id = $1 AND released), 0) as "released!: Stroops",
+               (SELECT COUNT(*) FROM milestones WHERE project_id = $1 AND released) as "milestones_released!"
+        "#,
+        project_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(ProjectFunds { raised: row.raised, released: row.released, milestones_released: row.milestones_released })
+}
+
+pub async fn find_settlement(pool: &PgPool, project_id: Uuid) -> Result<Option<Settlement>> {
+    let settlement = sqlx::query_as!(
+        Settlement,
+        r#"
+        SELECT id, project_id, policy, amount as "amount: Stroops", status, tx_hash,
+               claimable_balance_id, error, requested_by, created_at, settled_at
+        FROM project_settlements
+        WHERE project_id = $1
+        "#,
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(settlement)
+}
+
+/// Start a settlement, or restart a failed one. `None` if one is already
+/// under way or done.
+async fn begin(
+    pool: &PgPool,
+    project_id: Uuid,
+    policy: SettlementPolicy,
+    amount: Stroops,
+    requested_by: Uuid,
+) -> Result<Option<Settlement>> {
+    let settlement = sqlx::query_as!(
+        Settlement,
+        r#"
+        INSERT INTO project_settlements (project_id, policy, amount, requested_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (project_id) DO UPDATE
+        SET policy = EXCLUDED.policy, amount = EXCLUDED.amount, requested_by = EXCLUDED.requested_by,
+            status = 'pending', error = NULL
+        WHERE project_settlements.status = 'failed'
+        RETURNING id, project_id, policy, amount as "amount: Stroops", status, tx_hash,
+                  claimable_balance_id, error, requested_by, created_at, settled_at
+        "#,
+        project_id,
+        policy.as_str(),
+        amount.to_decimal(),
+        requested_by
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(settlement)
+}
+
+async fn mark_settled(pool: &PgPool, id: Uuid, payout: Option<&Payout>) -> Result<Settlement> {
+    let (tx_hash, balance_id) = match payout {
+        Some(Payout::Paid { tx_hash, .. }) => (Some(tx_hash.as_str()), None),
+        Some(Payout::Claimable { tx_hash, balance_id, .. }) => (Some(tx_hash.as_str()), Some(balance_id.as_str())),
+        None => (None, None),
+    };
+    let settlement = sqlx::query_as!(
+        Settlement,
+        r#"
+        UPDATE project_settlements
+        SET status = 'settled', tx_hash = $2, claimable_balance_id = $3, settled_at = NOW()
+        WHERE id = $1
+        RETURNING id, project_id, policy, amount as "amount: Stroops", status, tx_hash,
+                  claimable_balance_id, error, requested_by, created_at, settled_at
+        "#,
+        id,
+        tx_hash,
+        balance_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(settlement)
+}
+
+async fn mark_failed(pool: &PgPool, id: Uuid, error: &str) -> Result<()> {
+    sqlx::query!(
+        "UPDATE project_settlements SET status = 'failed', error = $2 WHERE id = $1",
+        id,
+        error
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Settle what's left of a project's balance, at most once. Payouts go to
+/// the student straight away; refunds stay pending for the donors to be
+/// repaid. Calling again after a failure retries it.
+pub async fn settle(
+    pool: &PgPool,
+    payments: Option<&TxSubmitter>,
+    project_id: Uuid,
+    student_id: Uuid,
+    policy: SettlementPolicy,
+    requested_by: Uuid,
+) -> Result<Settlement, CompletionError> {
+    let remaining = funds(pool, project_id).await?.remaining();
+    let Some(settlement) = begin(pool, project_id, policy, remaining, requested_by).await? else {
+        return find_settlement(pool, project_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Settlement for project {} vanished", project_id).into());
+    };
+
+    if !remaining.is_positive() {
+        return Ok(mark_settled(pool, settlement.id, None).await?);
+    }
+    if policy == SettlementPolicy::Refund {
+        return Ok(settlement);
+    }
+
+    let Some(payments) = payments else {
+        mark_failed(pool, settlement.id, "Platform payments are not configured").await?;
+        return Err(CompletionError::PaymentsUnavailable);
+    };
+    let memo = format!("settle:{}", &project_id.simple().to_string()[..20]);
+    match payouts::pay_student(pool, payments, student_id, remaining, Some(&memo), "project_settlement", Some(settlement.id)).await {
+        Ok(Some(payout)) => Ok(mark_settled(pool, settlement.id, Some(&payout)).await?),
+        Ok(None) => {
+            mark_failed(pool, settlement.id, "Student has no wallet").await?;
+            Err(CompletionError::NoWallet)
+        }
+        Err(e) => {
+            mark_failed(pool, settlement.id, &e.to_string()).await?;
+            Err(CompletionError::Payout(e.to_string()))
+        }
+    }
+}
+
+/// Tell donors and followers in-app that the project is complete
+pub async fn notify_completed(pool: &PgPool, project_id: Uuid) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+        SELECT audience.user_id, 'project', 'Project completed', p.title || ' is complete', $2::jsonb, $3::jsonb
+        FROM projects p
+        JOIN (
+            SELECT d.donor_id as user_id FROM donations d
+            WHERE d.project_id = $1 AND d.status = 'confirmed' AND d.donor_id IS NOT NULL
+            UNION
+            SELECT f.follower_id FROM follows f JOIN projects fp ON fp.id = $1
+            WHERE f.project_id = fp.id OR f.student_id = fp.student_id
+        ) audience ON TRUE
+        WHERE p.id = $1
+        "#,
+        project_id,
+        serde_json::json!({"project_id": project_id}),
+        NotificationEvent::ProjectStatus { project_id, status: "completed".to_string() }.to_json()
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_parse() {
+        assert_eq!(SettlementPolicy::parse(" Refund "), Some(SettlementPolicy::Refund));
+        assert_eq!(SettlementPolicy::parse("payout"), Some(SettlementPolicy::Payout));
+        assert_eq!(SettlementPolicy::parse("burn"), None);
+    }
+
+    #[test]
+    fn test_remaining_never_negative() {
+        let funds = |raised, released| ProjectFunds {
+            raised: Stroops::from_xlm(raised).unwrap(),
+            released: Stroops::from_xlm(released).unwrap(),
+            milestones_released: 0,
+        };
+        assert_eq!(funds(100, 60).remaining(), Stroops::from_xlm(40).unwrap());
+        assert_eq!(funds(100, 100).remaining(), Stroops::ZERO);
+        assert_eq!(funds(50, 80).remaining(), Stroops::ZERO);
+    }
+}
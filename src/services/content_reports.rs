@@ -0,0 +1,448 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::services::notifications::NotificationEvent;
+use crate::services::sessions;
+use crate::utils::pagination::{Page, PageRequest};
+
+/// What can be reported; profiles are keyed by their user's id
+pub const TARGET_TYPES: &[&str] = &["project", "comment", "profile"];
+pub const REASONS: &[&str] = &["spam", "abuse", "fraud", "inappropriate", "other"];
+pub const STATUSES: &[&str] = &["pending", "dismissed", "actioned"];
+/// Distinct reporters with open reports that hide content until reviewed,
+/// unless `REPORT_AUTO_HIDE_THRESHOLD` says otherwise
+pub const DEFAULT_AUTO_HIDE_THRESHOLD: i64 = 3;
+
+/// What a moderator does about a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Nothing wrong: closes the reports and restores content hidden by them
+    Dismiss,
+    /// Takes the content out of public view
+    Hide,
+    /// Hides the content and suspends whoever posted it
+    Suspend,
+}
+
+impl Action {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Action::Dismiss => "dismiss",
+            Action::Hide => "hide",
+            Action::Suspend => "suspend",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error("There's nothing to report there")]
+    TargetNotFound,
+    #[error("You can't report your own content")]
+    OwnContent,
+    #[error("You've already reported this; it's waiting for review")]
+    AlreadyReported,
+    #[error("The report has already been resolved")]
+    NotPending,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for ReportError {
+    fn from(e: sqlx::Error) -> Self {
+        ReportError::Internal(e.into())
+    }
+}
+
+/// `REPORT_AUTO_HIDE_THRESHOLD`, at least 1
+pub fn auto_hide_threshold() -> i64 {
+    std::env::var("REPORT_AUTO_HIDE_THRESHOLD")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .map(|n| n.max(1))
+        .unwrap_or(DEFAULT_AUTO_HIDE_THRESHOLD)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub id: Uuid,
+    pub reporter_id: Uuid,
+    pub target_type: String,
+    pub target_id: Uuid,
+    pub reason: String,
+    pub details: Option<String>,
+    pub status: String,
+    pub resolution: Option<String>,
+    pub resolution_note: Option<String>,
+    pub resolved_by: Option<Uuid>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A report in the moderation queue, with the state of what it's about
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedReport {
+    #[serde(flatten)]
+    pub report: Report,
+    /// Open reports on the same content, from anyone
+    pub open_reports: i64,
+    pub target_hidden: bool,
+}
+
+/// A report as filed
+#[derive(Debug, Clone)]
+pub struct NewReport<'a> {
+    pub target_type: &'a str,
+    pub target_id: Uuid,
+    pub reason: &'a str,
+    pub details: Option<&'a str>,
+}
+
+/// What resolving a report did
+#[derive(Debug, Clone, Serialize)]
+pub struct Resolution {
+    pub action: Action,
+    pub target_type: String,
+    pub target_id: Uuid,
+    /// Open reports on the content closed along with this one
+    pub reports_closed: u64,
+    pub target_hidden: bool,
+    pub suspended_user_id: Option<Uuid>,
+}
+
+/// Who posted a piece of content; `None` if it doesn't exist
+pub async fn target_owner(pool: &PgPool, target_type: &str, target_id: Uuid) -> Result<Option<Uuid>> {
+    let owner = match target_type {
+        "project" => {
+            sqlx::query_scalar!(
+                "SELECT s.user_id FROM projects p JOIN students s ON s.id = p.student_id WHERE p.id = $1",
+                target_id
+            )
+            .fetch_optional(pool)
+            .await?
+        }
+        "comment" => {
+            sqlx::query_scalar!(
+                "SELECT user_id FROM project_comments WHERE id = $1 AND deleted_at IS NULL",
+                target_id
+            )
+            .fetch_optional(pool)
+            .await?
+        }
+        "profile" => {
+            sqlx::query_scalar!("SELECT user_id FROM student_profiles WHERE user_id = $1", target_id)
+                .fetch_optional(pool)
+                .await?
+        }
+        _ => None,
+    };
+    Ok(owner)
+}
+
+/// Hide content, or bring it back with `hidden_by` of `None` and `hide`
+/// false. Returns whether anything changed.
+async fn set_hidden(
+    conn: &mut PgConnection,
+    target_type: &str,
+    target_id: Uuid,
+    hide: bool,
+    hidden_by: Option<Uuid>,
+) -> Result<bool> {
+    let changed = match (target_type, hide) {
+        ("project", true) => sqlx::query!(
+            "UPDATE projects SET hidden_at = NOW(), hidden_by = $2 WHERE id = $1 AND hidden_at IS NULL",
+            target_id,
+            hidden_by
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected(),
+        ("project", false) => sqlx::query!(
+            "UPDATE projects SET hidden_at = NULL, hidden_by = NULL WHERE id = $1 AND hidden_at IS NOT NULL",
+            target_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected(),
+        ("comment", true) => sqlx::query!(
+            "UPDATE project_comments SET hidden_at = NOW(), hidden_by = $2 WHERE id = $1 AND hidden_at IS NULL",
+            target_id,
+            hidden_by
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected(),
+        ("comment", false) => sqlx::query!(
+            "UPDATE project_comments SET hidden_at = NULL, hidden_by = NULL WHERE id = $1 AND hidden_at IS NOT NULL",
+            target_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected(),
+        ("profile", true) => sqlx::query!(
+            "UPDATE student_profiles SET hidden_at = NOW(), hidden_by = $2 WHERE user_id = $1 AND hidden_at IS NULL",
+            target_id,
+            hidden_by
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected(),
+        ("profile", false) => sqlx::query!(
+            "UPDATE student_profiles SET hidden_at = NULL, hidden_by = NULL WHERE user_id = $1 AND hidden_at IS NOT NULL",
+            target_id
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected(),
+        _ => 0,
+    };
+    Ok(changed > 0)
+}
+
+/// Whether content is hidden, and whether that was automatic
+async fn hidden_state(conn: &mut PgConnection, target_type: &str, target_id: Uuid) -> Result<Option<bool>> {
+    let state = match target_type {
+        "project" => sqlx::query!("SELECT hidden_at, hidden_by FROM projects WHERE id = $1", target_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .and_then(|r| r.hidden_at.map(|_| r.hidden_by.is_none())),
+        "comment" => sqlx::query!("SELECT hidden_at, hidden_by FROM project_comments WHERE id = $1", target_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .and_then(|r| r.hidden_at.map(|_| r.hidden_by.is_none())),
+        "profile" => sqlx::query!("SELECT hidden_at, hidden_by FROM student_profiles WHERE user_id = $1", target_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .and_then(|r| r.hidden_at.map(|_| r.hidden_by.is_none())),
+        _ => None,
+    };
+    Ok(state)
+}
+
+async fn notify_moderators(pool: &PgPool, target_type: &str, target_id: Uuid, reports: i64) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+        SELECT id, 'system', 'Content hidden pending review',
+               'A ' || $1 || ' was hidden after ' || $2 || ' reports', $3::jsonb, $4::jsonb
+        FROM users
+        WHERE role = 'admin'
+        "#,
+        target_type,
+        reports.to_string(),
+        serde_json::json!({"target_type": target_type, "target_id": target_id}),
+        NotificationEvent::ContentHidden { target_type: target_type.to_string(), target_id }.to_json()
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// File a report. Content drawing open reports from enough distinct users is
+/// hidden until a moderator reviews it; returns the report and whether that
+/// happened.
+pub async fn create(pool: &PgPool, reporter_id: Uuid, new: &NewReport<'_>) -> Result<(Report, bool), ReportError> {
+    let owner = target_owner(pool, new.target_type, new.target_id)
+        .await?
+        .ok_or(ReportError::TargetNotFound)?;
+    if owner == reporter_id {
+        return Err(ReportError::OwnContent);
+    }
+
+    let report = sqlx::query_as!(
+        Report,
+        r#"
+        INSERT INTO content_reports (reporter_id, target_type, target_id, reason, details)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, reporter_id, target_type, target_id, reason, details, status, resolution,
+                  resolution_note, resolved_by, resolved_at, created_at
+        "#,
+        reporter_id,
+        new.target_type,
+        new.target_id,
+        new.reason,
+        new.details
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => ReportError::AlreadyReported,
+        e => e.into(),
+    })?;
+
+    let reporters = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(DISTINCT reporter_id) as "count!"
+        FROM content_reports
+        WHERE target_type = $1 AND target_id = $2 AND status = 'pending'
+        "#,
+        new.target_type,
+        new.target_id
+    )
+    .fetch_one(pool)
+    .await?;
+    let mut conn = pool.acquire().await?;
+    let hidden = reporters >= auto_hide_threshold() && set_hidden(&mut conn, new.target_type, new.target_id, true, None).await?;
+    if hidden {
+        notify_moderators(pool, new.target_type, new.target_id, reporters).await?;
+    }
+    Ok((report, hidden))
+}
+
+/// The moderation queue, newest first, optionally narrowed by status and
+/// type of content
+pub async fn list(
+    pool: &PgPool,
+    status: Option<&str>,
+    target_type: Option<&str>,
+    page: &PageRequest,
+) -> Result<Page<QueuedReport>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT r.id, r.reporter_id, r.target_type, r.target_id, r.reason, r.details, r.status, r.resolution,
+               r.resolution_note, r.resolved_by, r.resolved_at, r.created_at,
+               (SELECT COUNT(*) FROM content_reports o
+                WHERE o.target_type = r.target_type AND o.target_id = r.target_id AND o.status = 'pending') as "open_reports!",
+               CASE r.target_type
+                   WHEN 'project' THEN EXISTS(SELECT 1 FROM projects WHERE id = r.target_id AND hidden_at IS NOT NULL)
+                   WHEN 'comment' THEN EXISTS(SELECT 1 FROM project_comments WHERE id = r.target_id AND hidden_at IS NOT NULL)
+                   ELSE EXISTS(SELECT 1 FROM student_profiles WHERE user_id = r.target_id AND hidden_at IS NOT NULL)
+               END as "target_hidden!"
+        FROM content_reports r
+        WHERE ($1::text IS NULL OR r.status = $1)
+          AND ($2::text IS NULL OR r.target_type = $2)
+          AND ($3::timestamptz IS NULL OR (r.created_at, r.id) < ($3, $4::uuid))
+        ORDER BY r.created_at DESC, r.id DESC
+        LIMIT $5
+        "#,
+        status,
+        target_type,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let reports = rows
+        .into_iter()
+        .map(|r| QueuedReport {
+            report: Report {
+                id: r.id,
+                reporter_id: r.reporter_id,
+                target_type: r.target_type,
+                target_id: r.target_id,
+                reason: r.reason,
+                details: r.details,
+                status: r.status,
+                resolution: r.resolution,
+                resolution_note: r.resolution_note,
+                resolved_by: r.resolved_by,
+                resolved_at: r.resolved_at,
+                created_at: r.created_at,
+            },
+            open_reports: r.open_reports,
+            target_hidden: r.target_hidden,
+        })
+        .collect();
+    Ok(Page::new(reports, page, |r| (Some(r.report.created_at), r.report.id)))
+}
+
+/// Act on a report, closing every open report on the same content; `None`
+/// if there's no such report
+pub async fn resolve(
+    pool: &PgPool,
+    report_id: Uuid,
+    action: Action,
+    moderator_id: Uuid,
+    note: Option<&str>,
+) -> Result<Option<Resolution>, ReportError> {
+    let mut tx = pool.begin().await?;
+    let Some(report) = sqlx::query!(
+        "SELECT target_type, target_id, status FROM content_reports WHERE id = $1 FOR UPDATE",
+        report_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+    if report.status != "pending" {
+        return Err(ReportError::NotPending);
+    }
+    let (target_type, target_id) = (report.target_type, report.target_id);
+
+    let mut suspended_user_id = None;
+    match action {
+        Action::Dismiss => {
+            // Only undo hiding the reports caused, not a moderator's
+            if hidden_state(&mut tx, &target_type, target_id).await? == Some(true) {
+                set_hidden(&mut tx, &target_type, target_id, false, None).await?;
+            }
+        }
+        Action::Hide => {
+            set_hidden(&mut tx, &target_type, target_id, true, Some(moderator_id)).await?;
+        }
+        Action::Suspend => {
+            set_hidden(&mut tx, &target_type, target_id, true, Some(moderator_id)).await?;
+            let owner = target_owner(pool, &target_type, target_id).await?;
+            if let Some(owner) = owner {
+                sqlx::query!("UPDATE users SET status = 'suspended' WHERE id = $1", owner)
+                    .execute(&mut *tx)
+                    .await?;
+                sessions::revoke_all_in(&mut tx, owner, None, "suspended").await?;
+                suspended_user_id = Some(owner);
+            }
+        }
+    }
+
+    let status = if action == Action::Dismiss { "dismissed" } else { "actioned" };
+    let reports_closed = sqlx::query!(
+        r#"
+        UPDATE content_reports
+        SET status = $3, resolution = $4, resolution_note = $5, resolved_by = $6, resolved_at = NOW()
+        WHERE target_type = $1 AND target_id = $2 AND status = 'pending'
+        "#,
+        target_type,
+        target_id,
+        status,
+        action.as_str(),
+        note,
+        moderator_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    let target_hidden = hidden_state(&mut tx, &target_type, target_id).await?.is_some();
+    tx.commit().await?;
+
+    Ok(Some(Resolution { action, target_type, target_id, reports_closed, target_hidden, suspended_user_id }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_hide_threshold() {
+        std::env::remove_var("REPORT_AUTO_HIDE_THRESHOLD");
+        assert_eq!(auto_hide_threshold(), DEFAULT_AUTO_HIDE_THRESHOLD);
+        std::env::set_var("REPORT_AUTO_HIDE_THRESHOLD", "0");
+        assert_eq!(auto_hide_threshold(), 1);
+        std::env::set_var("REPORT_AUTO_HIDE_THRESHOLD", "5");
+        assert_eq!(auto_hide_threshold(), 5);
+        std::env::remove_var("REPORT_AUTO_HIDE_THRESHOLD");
+    }
+
+    #[test]
+    fn test_action_names() {
+        for (action, name) in [(Action::Dismiss, "dismiss"), (Action::Hide, "hide"), (Action::Suspend, "suspend")] {
+            assert_eq!(action.as_str(), name);
+            assert_eq!(serde_json::to_value(action).unwrap(), name);
+        }
+    }
+}
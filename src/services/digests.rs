@@ -0,0 +1,308 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::email::{self, EmailTemplate};
+use crate::services::email_verification::new_token;
+use crate::utils::money::Stroops;
+
+pub const CADENCES: &[&str] = &["off", "daily", "weekly"];
+/// Unread notifications listed in a digest; the rest are only counted
+pub const MAX_DIGEST_NOTIFICATIONS: i64 = 10;
+/// Updates from followed projects listed in a digest
+pub const MAX_DIGEST_UPDATES: i64 = 10;
+
+/// A user's digest choice
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DigestSettings {
+    /// `off`, `daily` or `weekly`
+    pub cadence: String,
+    /// Hour of the day (UTC) the digest goes out
+    pub send_hour: i16,
+    /// Day weekly digests go out, 0 for Monday through 6 for Sunday
+    pub send_weekday: i16,
+    pub last_sent_at: Option<DateTime<Utc>>,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self { cadence: "off".to_string(), send_hour: 8, send_weekday: 0, last_sent_at: None }
+    }
+}
+
+/// How far back a digest on `cadence` looks; `None` when it's off
+pub fn period(cadence: &str) -> Option<Duration> {
+    match cadence {
+        "daily" => Some(Duration::days(1)),
+        "weekly" => Some(Duration::weeks(1)),
+        _ => None,
+    }
+}
+
+/// Whether a digest is due at `now`: its day and hour have come and it
+/// hasn't gone out today
+pub fn is_due(settings: &DigestSettings, now: DateTime<Utc>) -> bool {
+    if period(&settings.cadence).is_none() || (now.hour() as i16) < settings.send_hour {
+        return false;
+    }
+    if settings.cadence == "weekly" && now.weekday().num_days_from_monday() as i16 != settings.send_weekday {
+        return false;
+    }
+    settings.last_sent_at.map(|last| last.date_naive()) != Some(now.date_naive())
+}
+
+/// Where a digest sent at `now` starts: the last one, or one period back if
+/// that was longer ago or there wasn't one
+pub fn since(settings: &DigestSettings, now: DateTime<Utc>) -> DateTime<Utc> {
+    let start = now - period(&settings.cadence).unwrap_or_else(|| Duration::days(1));
+    settings.last_sent_at.map_or(start, |last| last.max(start))
+}
+
+/// What a user's digest covers
+#[derive(Debug, Default)]
+pub struct Digest {
+    pub unread: Vec<String>,
+    pub unread_count: i64,
+    pub donations: i64,
+    pub donated: Stroops,
+    pub updates: Vec<(String, String)>,
+}
+
+impl Digest {
+    pub fn is_empty(&self) -> bool {
+        self.unread_count == 0 && self.donations == 0 && self.updates.is_empty()
+    }
+}
+
+pub async fn settings(pool: &PgPool, user_id: Uuid) -> Result<DigestSettings> {
+    let settings = sqlx::query_as!(
+        DigestSettings,
+        "SELECT cadence, send_hour, send_weekday, last_sent_at FROM notification_digests WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(settings.unwrap_or_default())
+}
+
+/// Set a user's cadence and send time. Their unsubscribe token is issued
+/// with the first setting and kept after.
+pub async fn update_settings(
+    pool: &PgPool,
+    user_id: Uuid,
+    cadence: &str,
+    send_hour: i16,
+    send_weekday: i16,
+) -> Result<DigestSettings> {
+    let settings = sqlx::query_as!(
+        DigestSettings,
+        r#"
+        INSERT INTO notification_digests (user_id, cadence, send_hour, send_weekday, unsubscribe_token)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id) DO UPDATE
+        SET cadence = EXCLUDED.cadence, send_hour = EXCLUDED.send_hour,
+            send_weekday = EXCLUDED.send_weekday, updated_at = NOW()
+        RETURNING cadence, send_hour, send_weekday, last_sent_at
+        "#,
+        user_id,
+        cadence,
+        send_hour,
+        send_weekday,
+        new_token()
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(settings)
+}
+
+/// Turn off the digest an unsubscribe link was sent for, returning whose
+pub async fn unsubscribe(pool: &PgPool, token: &str) -> Result<Option<Uuid>> {
+    let user_id = sqlx::query_scalar!(
+        r#"
+        UPDATE notification_digests SET cadence = 'off', updated_at = NOW()
+        WHERE unsubscribe_token = $1
+        RETURNING user_id
+        "#,
+        token
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(user_id)
+}
+
+/// Unread notifications, donations to the user's projects and updates from
+/// what they follow, since `since`
+pub async fn gather(pool: &PgPool, user_id: Uuid, since: DateTime<Utc>) -> Result<Digest> {
+    let unread_count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM notifications WHERE user_id = $1 AND NOT is_read AND created_at >= $2"#,
+        user_id,
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+    let unread = sqlx::query_scalar!(
+        r#"
+        SELECT title FROM notifications
+        WHERE user_id = $1 AND NOT is_read AND created_at >= $2
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#,
+        user_id,
+        since,
+        MAX_DIGEST_NOTIFICATIONS
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let donations = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "count!", COALESCE(SUM(d.amount), 0) as "total!: Stroops"
+        FROM donations d
+        JOIN projects p ON p.id = d.project_id
+        JOIN students s ON s.id = p.student_id
+        WHERE s.user_id = $1 AND d.status = 'confirmed' AND COALESCE(d.confirmed_at, d.created_at) >= $2
+        "#,
+        user_id,
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let updates = sqlx::query!(
+        r#"
+        SELECT p.title as project_title, pu.title
+        FROM project_updates pu
+        JOIN projects p ON p.id = pu.project_id
+        WHERE pu.published_at >= $2 AND p.hidden_at IS NULL
+          AND EXISTS (
+              SELECT 1 FROM follows f
+              WHERE f.follower_id = $1 AND (f.project_id = p.id OR f.student_id = p.student_id)
+          )
+        ORDER BY pu.published_at DESC
+        LIMIT $3
+        "#,
+        user_id,
+        since,
+        MAX_DIGEST_UPDATES
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Digest {
+        unread,
+        unread_count,
+        donations: donations.count,
+        donated: donations.total,
+        updates: updates.into_iter().map(|u| (u.project_title, u.title)).collect(),
+    })
+}
+
+/// Queue the digests due at `now`, up to `limit` users. Users with nothing
+/// new are skipped until their next one.
+pub async fn queue_due(pool: &PgPool, now: DateTime<Utc>, limit: i64) -> Result<usize> {
+    let today = now.date_naive().and_hms_opt(0, 0, 0).expect("midnight is valid").and_utc();
+    let candidates = sqlx::query!(
+        r#"
+        SELECT d.user_id, d.cadence, d.send_hour, d.send_weekday, d.last_sent_at, d.unsubscribe_token,
+               u.username, u.email
+        FROM notification_digests d
+        JOIN users u ON u.id = d.user_id
+        WHERE d.cadence <> 'off' AND d.send_hour <= $1
+          AND (d.cadence = 'daily' OR d.send_weekday = $2)
+          AND (d.last_sent_at IS NULL OR d.last_sent_at < $3)
+        ORDER BY d.send_hour, d.user_id
+        LIMIT $4
+        "#,
+        now.hour() as i16,
+        now.weekday().num_days_from_monday() as i16,
+        today,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut queued = 0;
+    for candidate in candidates {
+        let settings = DigestSettings {
+            cadence: candidate.cadence,
+            send_hour: candidate.send_hour,
+            send_weekday: candidate.send_weekday,
+            last_sent_at: candidate.last_sent_at,
+        };
+        if !is_due(&settings, now) {
+            continue;
+        }
+
+        let digest = gather(pool, candidate.user_id, since(&settings, now)).await?;
+        if !digest.is_empty() {
+            let template = EmailTemplate::NotificationDigest {
+                username: candidate.username,
+                cadence: settings.cadence,
+                unread: digest.unread,
+                unread_count: digest.unread_count,
+                donations: digest.donations,
+                donated: digest.donated,
+                updates: digest.updates,
+                unsubscribe_url: email::public_url(&format!(
+                    "/api/notifications/digest/unsubscribe?token={}",
+                    candidate.unsubscribe_token
+                )),
+            };
+            let key = format!("notification_digest:{}:{}", candidate.user_id, now.date_naive());
+            if email::queue(pool, &candidate.email, &template, Some(&key)).await?.is_some() {
+                queued += 1;
+            }
+        }
+        sqlx::query!(
+            "UPDATE notification_digests SET last_sent_at = $2 WHERE user_id = $1",
+            candidate.user_id,
+            now
+        )
+        .execute(pool)
+        .await?;
+    }
+    Ok(queued)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn settings(cadence: &str, last_sent_at: Option<DateTime<Utc>>) -> DigestSettings {
+        DigestSettings { cadence: cadence.to_string(), send_hour: 8, send_weekday: 0, last_sent_at }
+    }
+
+    #[test]
+    fn test_is_due_daily() {
+        // A Monday
+        let early = Utc.with_ymd_and_hms(2025, 11, 3, 7, 59, 0).unwrap();
+        let on_time = Utc.with_ymd_and_hms(2025, 11, 3, 8, 0, 0).unwrap();
+        assert!(!is_due(&settings("daily", None), early));
+        assert!(is_due(&settings("daily", None), on_time));
+        assert!(!is_due(&settings("daily", Some(on_time)), on_time + Duration::hours(3)));
+        assert!(is_due(&settings("daily", Some(on_time)), on_time + Duration::days(1)));
+        assert!(!is_due(&settings("off", None), on_time));
+    }
+
+    #[test]
+    fn test_is_due_weekly() {
+        let monday = Utc.with_ymd_and_hms(2025, 11, 3, 9, 0, 0).unwrap();
+        assert!(is_due(&settings("weekly", None), monday));
+        assert!(!is_due(&settings("weekly", None), monday + Duration::days(1)));
+        assert!(is_due(&settings("weekly", Some(monday)), monday + Duration::weeks(1)));
+    }
+
+    #[test]
+    fn test_since() {
+        let now = Utc.with_ymd_and_hms(2025, 11, 3, 8, 0, 0).unwrap();
+        assert_eq!(since(&settings("daily", None), now), now - Duration::days(1));
+        let last = now - Duration::hours(20);
+        assert_eq!(since(&settings("daily", Some(last)), now), last);
+        // A digest switched from weekly to daily doesn't reach back a week
+        assert_eq!(since(&settings("daily", Some(now - Duration::weeks(1))), now), now - Duration::days(1));
+        assert_eq!(since(&settings("weekly", None), now), now - Duration::weeks(1));
+    }
+}
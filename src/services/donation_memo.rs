@@ -0,0 +1,148 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::EscrowMode;
+use crate::services::stellar::PaymentRecord;
+use crate::utils::money::Stroops;
+
+/// Stellar text memos are limited to 28 bytes
+pub const MAX_TEXT_MEMO_BYTES: usize = 28;
+/// Memo regenerations tried before giving up on a unique memo
+pub const MAX_MEMO_ATTEMPTS: u32 = 5;
+/// Hex characters of the hash kept in a memo (64 bits)
+const MEMO_HASH_CHARS: usize = 16;
+
+/// What a donation pays for; sets the memo prefix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemoKind {
+    Project,
+    Platform,
+}
+
+impl MemoKind {
+    fn prefix(&self) -> &'static str {
+        match self {
+            MemoKind::Project => "fh",
+            MemoKind::Platform => "fp",
+        }
+    }
+}
+
+/// Short memo derived from the donation id. `attempt` is bumped when the
+/// database reports the memo is already taken.
+pub fn generate(kind: MemoKind, donation_id: Uuid, attempt: u32) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(donation_id.as_bytes());
+    hasher.update(attempt.to_be_bytes());
+    let digest = hex::encode(hasher.finalize());
+    format!("{}-{}", kind.prefix(), &digest[..MEMO_HASH_CHARS])
+}
+
+/// Whether an insert failed because the memo is already in use
+pub fn is_memo_collision(err: &sqlx::Error) -> bool {
+    matches!(
+        err,
+        sqlx::Error::Database(db) if db.constraint() == Some("idx_donations_memo_unique")
+    )
+}
+
+/// A payment settles a donation only if it succeeded, went to the donation's
+/// destination, carries its memo, and is exactly its amount in native XLM.
+/// A muxed destination must have been paid through that same muxed address.
+pub fn payment_matches(payment: &PaymentRecord, destination: &str, memo: &str, expected: Stroops) -> bool {
+    let to_destination = if destination.starts_with('M') {
+        payment.to_muxed.as_deref() == Some(destination)
+    } else {
+        payment.to == destination
+    };
+
+    payment.successful
+        && to_destination
+        && payment.memo.as_deref() == Some(memo)
+        && payment.amount == Some(expected)
+}
+
+/// Wallet a Stellar donation to `project_id` should be paid into; platform
+/// donations (no project) go to the platform wallet
+pub async fn expected_destination(pool: &PgPool, project_id: Option<Uuid>, escrow_mode: EscrowMode) -> Result<String> {
+    let platform = std::env::var("PLATFORM_WALLET_PUBLIC_KEY").unwrap_or_default();
+    let Some(project_id) = project_id else {
+        return Ok(platform);
+    };
+
+    let row = sqlx::query!(
+        r#"
+        SELECT p.contract_address, w.public_key as "public_key?"
+        FROM projects p
+        LEFT JOIN wallets w ON w.student_id = p.student_id
+        WHERE p.id = $1
+        "#,
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(platform);
+    };
+    let wallet = row.public_key.filter(|k| !k.is_empty());
+
+    // Per-project escrow accounts receive donations directly
+    let destination = if escrow_mode == EscrowMode::PerProject {
+        row.contract_address.or(wallet)
+    } else {
+        wallet.or(row.contract_address)
+    };
+
+    Ok(destination.unwrap_or(platform))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_is_short_and_deterministic() {
+        let id = Uuid::new_v4();
+        let memo = generate(MemoKind::Project, id, 0);
+        assert!(memo.len() <= MAX_TEXT_MEMO_BYTES);
+        assert!(memo.starts_with("fh-"));
+        assert_eq!(memo, generate(MemoKind::Project, id, 0));
+        assert_ne!(memo, generate(MemoKind::Project, id, 1));
+        assert!(generate(MemoKind::Platform, id, 0).starts_with("fp-"));
+    }
+
+    #[test]
+    fn test_payment_matches_is_exact() {
+        let expected: Stroops = "25.5".parse().unwrap();
+        let payment = PaymentRecord {
+            paging_token: "1".to_string(),
+            tx_hash: "abc".to_string(),
+            to: "GWALLET".to_string(),
+            to_muxed: None,
+            amount: "25.5000000".parse().ok(),
+            memo: Some("fh-0123".to_string()),
+            successful: true,
+            created_at: chrono::Utc::now(),
+        };
+
+        assert!(payment_matches(&payment, "GWALLET", "fh-0123", expected));
+        assert!(!payment_matches(&payment, "GOTHER", "fh-0123", expected));
+        assert!(!payment_matches(&payment, "GWALLET", "fh-9999", expected));
+        // One stroop off is not a match
+        assert!(!payment_matches(&payment, "GWALLET", "fh-0123", "25.5000001".parse().unwrap()));
+
+        // A muxed destination only matches payments to that muxed address
+        assert!(!payment_matches(&payment, "MWALLET", "fh-0123", expected));
+        let muxed = PaymentRecord { to_muxed: Some("MWALLET".to_string()), ..payment.clone() };
+        assert!(payment_matches(&muxed, "MWALLET", "fh-0123", expected));
+        assert!(!payment_matches(&muxed, "MOTHER", "fh-0123", expected));
+
+        let failed = PaymentRecord { successful: false, ..payment.clone() };
+        assert!(!payment_matches(&failed, "GWALLET", "fh-0123", expected));
+        let non_native = PaymentRecord { amount: None, ..payment };
+        assert!(!payment_matches(&non_native, "GWALLET", "fh-0123", expected));
+    }
+}
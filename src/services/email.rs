@@ -0,0 +1,969 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::config::StellarNetwork;
+use crate::services::jobs::{self, Job};
+use crate::services::notification_preferences;
+use crate::utils::money::Stroops;
+
+/// Delivery attempts before a queued email is marked failed
+pub const MAX_SEND_ATTEMPTS: i32 = 6;
+
+/// A rendered message ready for a provider
+#[derive(Debug, Clone)]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+/// Something that delivers email. Errors are the provider's own message.
+#[async_trait]
+pub trait EmailProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), String>;
+}
+
+/// The provider named by `EMAIL_PROVIDER` (`smtp`, `sendgrid`, `ses` or
+/// `log`). `None` when unset: emails are queued but not sent.
+pub fn provider_from_env() -> Result<Option<Arc<dyn EmailProvider>>> {
+    let provider = std::env::var("EMAIL_PROVIDER").unwrap_or_default().trim().to_lowercase();
+    if provider.is_empty() {
+        return Ok(None);
+    }
+    if provider == "log" {
+        return Ok(Some(Arc::new(LogProvider)));
+    }
+
+    let from = required("EMAIL_FROM")?;
+    let provider: Arc<dyn EmailProvider> = match provider.as_str() {
+        "smtp" => Arc::new(SmtpProvider::from_env(from)?),
+        "sendgrid" => Arc::new(SendGridProvider::new(required("SENDGRID_API_KEY")?, from)),
+        "ses" => Arc::new(SesProvider::from_env(from)?),
+        other => return Err(anyhow!("Unknown EMAIL_PROVIDER '{}'", other)),
+    };
+    Ok(Some(provider))
+}
+
+fn required(name: &str) -> Result<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .ok_or_else(|| anyhow!("{} is required for the configured EMAIL_PROVIDER", name))
+}
+
+/// Splits `Name <address>` into its parts; a bare address has no name
+pub fn parse_mailbox(mailbox: &str) -> (Option<&str>, &str) {
+    match (mailbox.find('<'), mailbox.rfind('>')) {
+        (Some(open), Some(close)) if open < close => {
+            let name = mailbox[..open].trim().trim_matches('"').trim();
+            ((!name.is_empty()).then_some(name), mailbox[open + 1..close].trim())
+        }
+        _ => (None, mailbox.trim()),
+    }
+}
+
+/// Writes emails to the log instead of sending them, for development
+pub struct LogProvider;
+
+#[async_trait]
+impl EmailProvider for LogProvider {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), String> {
+        tracing::info!("Email to {}: {}\n{}", email.to, email.subject, email.text);
+        Ok(())
+    }
+}
+
+/// SMTP relay, with STARTTLS unless `SMTP_TLS` says otherwise
+pub struct SmtpProvider {
+    transport: lettre::AsyncSmtpTransport<lettre::Tokio1Executor>,
+    from: String,
+}
+
+impl SmtpProvider {
+    pub fn from_env(from: String) -> Result<Self> {
+        use lettre::transport::smtp::authentication::Credentials;
+        use lettre::{AsyncSmtpTransport, Tokio1Executor};
+
+        let host = required("SMTP_HOST")?;
+        let tls = std::env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string()).to_lowercase();
+        let mut builder = match tls.as_str() {
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?,
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            // Local catchers such as MailHog
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            other => return Err(anyhow!("SMTP_TLS must be starttls, tls or none, not '{}'", other)),
+        };
+        if let Some(port) = std::env::var("SMTP_PORT").ok().and_then(|p| p.parse().ok()) {
+            builder = builder.port(port);
+        }
+        if let (Ok(username), Ok(password)) = (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+            if !username.is_empty() {
+                builder = builder.credentials(Credentials::new(username, password));
+            }
+        }
+
+        Ok(Self { transport: builder.build(), from })
+    }
+}
+
+#[async_trait]
+impl EmailProvider for SmtpProvider {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), String> {
+        use lettre::message::{MultiPart, SinglePart};
+        use lettre::{AsyncTransport, Message};
+
+        let builder = Message::builder()
+            .from(self.from.parse().map_err(|e| format!("Invalid EMAIL_FROM: {}", e))?)
+            .to(email.to.parse().map_err(|e| format!("Invalid recipient: {}", e))?)
+            .subject(email.subject.clone());
+        let message = match &email.html {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(email.text.clone(), html.clone())),
+            None => builder.singlepart(SinglePart::plain(email.text.clone())),
+        }
+        .map_err(|e| e.to_string())?;
+
+        self.transport.send(message).await.map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+/// SendGrid v3 mail send API
+pub struct SendGridProvider {
+    client: reqwest::Client,
+    api_key: String,
+    from: String,
+}
+
+impl SendGridProvider {
+    pub fn new(api_key: String, from: String) -> Self {
+        Self { client: reqwest::Client::new(), api_key, from }
+    }
+}
+
+#[async_trait]
+impl EmailProvider for SendGridProvider {
+    fn name(&self) -> &'static str {
+        "sendgrid"
+    }
+
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), String> {
+        let (name, address) = parse_mailbox(&self.from);
+        let mut content = vec![serde_json::json!({"type": "text/plain", "value": email.text})];
+        if let Some(html) = &email.html {
+            content.push(serde_json::json!({"type": "text/html", "value": html}));
+        }
+        let body = serde_json::json!({
+            "personalizations": [{"to": [{"email": email.to}]}],
+            "from": {"email": address, "name": name},
+            "subject": email.subject,
+            "content": content,
+        });
+
+        let response = self
+            .client
+            .post("https://api.sendgrid.com/v3/mail/send")
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let detail = response.text().await.unwrap_or_default();
+            return Err(format!("SendGrid returned {}: {}", status, detail));
+        }
+        Ok(())
+    }
+}
+
+/// Amazon SES v2 `SendEmail`, signed with Signature Version 4
+pub struct SesProvider {
+    client: reqwest::Client,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    from: String,
+}
+
+impl SesProvider {
+    pub fn from_env(from: String) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::new(),
+            region: required("AWS_REGION")?,
+            access_key_id: required("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: required("AWS_SECRET_ACCESS_KEY")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok().filter(|t| !t.is_empty()),
+            from,
+        })
+    }
+
+    fn host(&self) -> String {
+        format!("email.{}.amazonaws.com", self.region)
+    }
+
+    /// The `Authorization` header for a JSON POST to `path`
+    fn authorization(&self, path: &str, body: &[u8], now: DateTime<Utc>) -> String {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let mut headers = vec![
+            ("content-type", "application/json".to_string()),
+            ("host", self.host()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v.trim())).collect();
+        let signed_headers = headers.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(";");
+
+        let canonical_request = format!(
+            "POST\n{}\n\n{}\n{}\n{}",
+            path,
+            canonical_headers,
+            signed_headers,
+            hex::encode(Sha256::digest(body))
+        );
+        let scope = format!("{}/{}/ses/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = sigv4_signing_key(&self.secret_access_key, &date, &self.region, "ses");
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id,
+            scope,
+            signed_headers,
+            hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()))
+        )
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// SigV4 key for one day, region and service
+fn sigv4_signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    hmac_sha256(&k_service, b"aws4_request")
+}
+
+#[async_trait]
+impl EmailProvider for SesProvider {
+    fn name(&self) -> &'static str {
+        "ses"
+    }
+
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), String> {
+        const PATH: &str = "/v2/email/outbound-emails";
+
+        let mut body = serde_json::json!({"Text": {"Data": email.text, "Charset": "UTF-8"}});
+        if let Some(html) = &email.html {
+            body["Html"] = serde_json::json!({"Data": html, "Charset": "UTF-8"});
+        }
+        let payload = serde_json::to_vec(&serde_json::json!({
+            "FromEmailAddress": self.from,
+            "Destination": {"ToAddresses": [email.to]},
+            "Content": {"Simple": {
+                "Subject": {"Data": email.subject, "Charset": "UTF-8"},
+                "Body": body,
+            }},
+        }))
+        .map_err(|e| e.to_string())?;
+
+        let now = Utc::now();
+        let mut request = self
+            .client
+            .post(format!("https://{}{}", self.host(), PATH))
+            .header("content-type", "application/json")
+            .header("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string())
+            .header("authorization", self.authorization(PATH, &payload, now));
+        if let Some(token) = &self.session_token {
+            request = request.header("x-amz-security-token", token);
+        }
+
+        let response = request.body(payload).send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let detail = response.text().await.unwrap_or_default();
+            return Err(format!("SES returned {}: {}", status, detail));
+        }
+        Ok(())
+    }
+}
+
+/// The emails the platform sends
+#[derive(Debug, Clone)]
+pub enum EmailTemplate {
+    Verification {
+        username: String,
+        link: String,
+    },
+    PasswordReset {
+        username: String,
+        link: String,
+    },
+    SchoolEmailCode {
+        username: String,
+        code: String,
+    },
+    DonationReceipt {
+        project_title: String,
+        amount: Stroops,
+        donated_at: DateTime<Utc>,
+        tx_url: Option<String>,
+        receipt_url: String,
+    },
+    VerificationDecision {
+        username: String,
+        approved: bool,
+        message: Option<String>,
+    },
+    MilestoneReleased {
+        project_title: String,
+        milestone_title: String,
+        amount: Stroops,
+        tx_url: Option<String>,
+    },
+    ProjectCompleted {
+        project_title: String,
+        raised: Stroops,
+        milestones_released: i64,
+        /// What was left after the last milestone
+        leftover: Stroops,
+        /// Whether the leftover goes back to donors rather than the student
+        leftover_refunded: bool,
+        project_url: String,
+    },
+    AnnouncementDigest {
+        username: String,
+        /// Title and message of each
+        announcements: Vec<(String, String)>,
+    },
+    NotificationDigest {
+        username: String,
+        /// `daily` or `weekly`
+        cadence: String,
+        /// Titles of the newest unread notifications, out of `unread_count`
+        unread: Vec<String>,
+        unread_count: i64,
+        /// Confirmed donations to the user's projects, and their total
+        donations: i64,
+        donated: Stroops,
+        /// Project and update titles from what the user follows
+        updates: Vec<(String, String)>,
+        unsubscribe_url: String,
+    },
+}
+
+/// Subject and bodies of a template
+#[derive(Debug, Clone)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+impl EmailTemplate {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::Verification { .. } => "verification",
+            EmailTemplate::PasswordReset { .. } => "password_reset",
+            EmailTemplate::SchoolEmailCode { .. } => "school_email_code",
+            EmailTemplate::DonationReceipt { .. } => "donation_receipt",
+            EmailTemplate::VerificationDecision { .. } => "verification_decision",
+            EmailTemplate::MilestoneReleased { .. } => "milestone_released",
+            EmailTemplate::ProjectCompleted { .. } => "project_completed",
+            EmailTemplate::AnnouncementDigest { .. } => "announcement_digest",
+            EmailTemplate::NotificationDigest { .. } => "notification_digest",
+        }
+    }
+
+    /// The notification event type whose email preference covers this
+    /// template; `None` for account emails, which always go out, and for
+    /// notification digests, which users sign up for on their own
+    pub fn event_type(&self) -> Option<&'static str> {
+        match self {
+            EmailTemplate::Verification { .. }
+            | EmailTemplate::PasswordReset { .. }
+            | EmailTemplate::SchoolEmailCode { .. }
+            | EmailTemplate::NotificationDigest { .. } => None,
+            EmailTemplate::DonationReceipt { .. } => Some("donation_confirmed"),
+            EmailTemplate::VerificationDecision { .. } => Some("verification_status"),
+            EmailTemplate::MilestoneReleased { .. } => Some("onchain_release"),
+            EmailTemplate::ProjectCompleted { .. } => Some("project_status"),
+            EmailTemplate::AnnouncementDigest { .. } => Some("announcement"),
+        }
+    }
+
+    pub fn render(&self) -> RenderedEmail {
+        let platform = platform_name();
+        let (subject, paragraphs, action) = match self {
+            EmailTemplate::Verification { username, link } => (
+                format!("Confirm your {} email address", platform),
+                vec![
+                    format!("Hi {},", username),
+                    "Confirm your email address to finish setting up your account. The link expires in 24 hours.".to_string(),
+                    "If you did not sign up, you can ignore this email.".to_string(),
+                ],
+                Some(("Confirm email", link.clone())),
+            ),
+            EmailTemplate::PasswordReset { username, link } => (
+                format!("Reset your {} password", platform),
+                vec![
+                    format!("Hi {},", username),
+                    "Someone asked to reset the password for your account. The link works once and expires in an hour.".to_string(),
+                    "If it wasn't you, ignore this email; your password has not changed.".to_string(),
+                ],
+                Some(("Reset password", link.clone())),
+            ),
+            EmailTemplate::SchoolEmailCode { username, code } => (
+                format!("Your {} school email code: {}", platform, code),
+                vec![
+                    format!("Hi {},", username),
+                    format!(
+                        "Enter {} to confirm this is your school email address. The code expires in {} minutes.",
+                        code,
+                        crate::services::school_email_otp::CODE_TTL_MINUTES
+                    ),
+                    "If you did not apply for student verification, you can ignore this email.".to_string(),
+                ],
+                None,
+            ),
+            EmailTemplate::DonationReceipt { project_title, amount, donated_at, tx_url, receipt_url } => {
+                let mut paragraphs = vec![
+                    format!("Thank you for your donation of {} XLM to {}.", amount, project_title),
+                    format!("It was confirmed on {}.", donated_at.format("%B %-d, %Y")),
+                ];
+                if let Some(tx_url) = tx_url {
+                    paragraphs.push(format!("View the transaction: {}", tx_url));
+                }
+                (
+                    format!("Your donation to {}", project_title),
+                    paragraphs,
+                    Some(("Download your receipt", receipt_url.clone())),
+                )
+            }
+            EmailTemplate::VerificationDecision { username, approved, message } => {
+                let mut paragraphs = vec![format!("Hi {},", username)];
+                paragraphs.push(if *approved {
+                    "Your student verification has been approved. You can now create projects and receive donations.".to_string()
+                } else {
+                    "Your student verification could not be approved.".to_string()
+                });
+                if let Some(message) = message.as_deref().filter(|m| !m.trim().is_empty()) {
+                    paragraphs.push(format!("Note from the reviewer: {}", message.trim()));
+                }
+                let subject = if *approved { "Your student verification was approved" } else { "Your student verification was not approved" };
+                (subject.to_string(), paragraphs, None)
+            }
+            EmailTemplate::MilestoneReleased { project_title, milestone_title, amount, tx_url } => {
+                let mut paragraphs = vec![format!(
+                    "The milestone \"{}\" of {} has been completed and {} XLM released.",
+                    milestone_title, project_title, amount
+                )];
+                if let Some(tx_url) = tx_url {
+                    paragraphs.push(format!("View the release: {}", tx_url));
+                }
+                (format!("Milestone released: {}", milestone_title), paragraphs, None)
+            }
+            EmailTemplate::ProjectCompleted {
+                project_title,
+                raised,
+                milestones_released,
+                leftover,
+                leftover_refunded,
+                project_url,
+            } => {
+                let mut paragraphs = vec![
+                    format!("{} is complete. Thank you for helping make it happen.", project_title),
+                    format!(
+                        "It raised {} XLM and delivered {} milestone{}.",
+                        raised,
+                        milestones_released,
+                        if *milestones_released == 1 { "" } else { "s" }
+                    ),
+                ];
+                if leftover.is_positive() {
+                    paragraphs.push(if *leftover_refunded {
+                        format!("The remaining {} XLM will be refunded to donors in proportion to what they gave.", leftover)
+                    } else {
+                        format!("The remaining {} XLM has been paid out to the student.", leftover)
+                    });
+                }
+                (
+                    format!("{} is complete", project_title),
+                    paragraphs,
+                    Some(("See the project", project_url.clone())),
+                )
+            }
+            EmailTemplate::AnnouncementDigest { username, announcements } => {
+                let mut paragraphs = vec![
+                    format!("Hi {},", username),
+                    format!("Here's what you may have missed on {}:", platform),
+                ];
+                paragraphs.extend(announcements.iter().map(|(title, message)| format!("{}\n{}", title, message)));
+                let subject = match announcements.as_slice() {
+                    [(title, _)] => title.clone(),
+                    _ => format!("{} announcements from {}", announcements.len(), platform),
+                };
+                (subject, paragraphs, Some(("Open your notifications", public_url("/notifications"))))
+            }
+            EmailTemplate::NotificationDigest {
+                username,
+                cadence,
+                unread,
+                unread_count,
+                donations,
+                donated,
+                updates,
+                unsubscribe_url,
+            } => {
+                let period = if cadence == "weekly" { "this week" } else { "today" };
+                let mut paragraphs = vec![format!("Hi {},", username), format!("Here's what happened on {} {}.", platform, period)];
+                if *unread_count > 0 {
+                    let mut lines = vec![format!("You have {} unread notification(s):", unread_count)];
+                    lines.extend(unread.iter().map(|title| format!("- {}", title)));
+                    if *unread_count > unread.len() as i64 {
+                        lines.push(format!("...and {} more", *unread_count - unread.len() as i64));
+                    }
+                    paragraphs.push(lines.join("\n"));
+                }
+                if *donations > 0 {
+                    paragraphs.push(format!("Your projects received {} donation(s), {} XLM in all.", donations, donated));
+                }
+                if !updates.is_empty() {
+                    let mut lines = vec!["New from projects you follow:".to_string()];
+                    lines.extend(updates.iter().map(|(project, update)| format!("- {}: {}", project, update)));
+                    paragraphs.push(lines.join("\n"));
+                }
+                paragraphs.push(format!(
+                    "You get this email {}. To stop, unsubscribe: {}",
+                    if cadence == "weekly" { "weekly" } else { "daily" },
+                    unsubscribe_url
+                ));
+                (
+                    format!("Your {} {} digest", cadence, platform),
+                    paragraphs,
+                    Some(("Open your notifications", public_url("/notifications"))),
+                )
+            }
+        };
+
+        let mut text = paragraphs.join("\n\n");
+        let mut html: String = paragraphs.iter().map(|p| format!("<p>{}</p>", escape_html(p))).collect();
+        if let Some((label, url)) = action {
+            text.push_str(&format!("\n\n{}: {}", label, url));
+            html.push_str(&format!("<p><a href=\"{}\">{}</a></p>", escape_html(&url), escape_html(label)));
+        }
+        text.push_str(&format!("\n\n— {}", platform));
+        html.push_str(&format!("<p>— {}</p>", escape_html(&platform)));
+
+        RenderedEmail { subject, text, html }
+    }
+}
+
+fn platform_name() -> String {
+    std::env::var("PLATFORM_LEGAL_NAME").unwrap_or_else(|_| "FundHub".to_string())
+}
+
+/// Where links in emails point, from `PUBLIC_BASE_URL`
+pub fn public_url(path: &str) -> String {
+    let base = std::env::var("PUBLIC_BASE_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+    format!("{}{}", base.trim_end_matches('/'), path)
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Wait before the next attempt after `attempts` failures: a minute,
+/// doubling, capped at six hours
+pub fn retry_delay(attempts: i32) -> Duration {
+    let minutes = 1u64 << attempts.clamp(1, 10).saturating_sub(1);
+    Duration::from_secs((minutes * 60).min(6 * 60 * 60))
+}
+
+/// Render and queue an email, with a job to send it. `dedupe_key` keeps an
+/// event from queuing the same email twice; `None` is returned when it
+/// already has, or when the recipient turned off email for the template's
+/// event type.
+pub async fn queue(pool: &PgPool, to: &str, template: &EmailTemplate, dedupe_key: Option<&str>) -> Result<Option<Uuid>> {
+    if let Some(event_type) = template.event_type() {
+        if !notification_preferences::wants_email(pool, to, event_type).await? {
+            tracing::debug!("Not queueing {} email: the recipient opted out", template.name());
+            return Ok(None);
+        }
+    }
+    let rendered = template.render();
+    let mut tx = pool.begin().await?;
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO email_outbox (recipient, template, subject, body_text, body_html, dedupe_key)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (dedupe_key) DO NOTHING
+        RETURNING id
+        "#,
+        to,
+        template.name(),
+        rendered.subject,
+        rendered.text,
+        rendered.html,
+        dedupe_key
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(email_id) = id {
+        let key = format!("send_email:{}", email_id);
+        jobs::enqueue(&mut *tx, &Job::SendEmail { email_id }, Utc::now(), Some(&key)).await?;
+    }
+    tx.commit().await?;
+
+    Ok(id)
+}
+
+/// Thank the donor of a confirmed donation, when they have an account
+pub async fn queue_donation_receipt(pool: &PgPool, network: StellarNetwork, donation_id: Uuid) -> Result<()> {
+    let Some(donation) = sqlx::query!(
+        r#"
+        SELECT u.email, p.title as "project_title?", d.amount as "amount: Stroops", d.tx_hash,
+               COALESCE(d.confirmed_at, d.created_at, NOW()) as "donated_at!"
+        FROM donations d
+        JOIN users u ON u.id = d.donor_id
+        LEFT JOIN projects p ON p.id = d.project_id
+        WHERE d.id = $1 AND d.status = 'confirmed'
+        "#,
+        donation_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let template = EmailTemplate::DonationReceipt {
+        project_title: donation.project_title.unwrap_or_else(platform_name),
+        amount: donation.amount,
+        donated_at: donation.donated_at,
+        tx_url: donation.tx_hash.map(|tx| network.transaction_url(&tx)),
+        receipt_url: public_url(&format!("/api/donations/{}/receipt", donation_id)),
+    };
+    queue(pool, &donation.email, &template, Some(&format!("donation_receipt:{}", donation_id))).await?;
+    Ok(())
+}
+
+/// Tell a student how their verification was decided
+pub async fn queue_verification_decision(
+    pool: &PgPool,
+    verification_id: Uuid,
+    user_id: Uuid,
+    approved: bool,
+    message: Option<String>,
+) -> Result<()> {
+    let user = sqlx::query!("SELECT username, email FROM users WHERE id = $1", user_id)
+        .fetch_one(pool)
+        .await?;
+
+    let template = EmailTemplate::VerificationDecision { username: user.username, approved, message };
+    let key = format!("verification_decision:{}:{}", verification_id, if approved { "approved" } else { "rejected" });
+    queue(pool, &user.email, &template, Some(&key)).await?;
+    Ok(())
+}
+
+/// Tell a project's owner and donors that a milestone's funds were released
+pub async fn queue_milestone_released(pool: &PgPool, milestone_id: Uuid, tx_url: Option<String>) -> Result<usize> {
+    let milestone = sqlx::query!(
+        r#"
+        SELECT m.title, m.project_id, m.target_amount as "amount: Stroops", p.title as project_title
+        FROM milestones m
+        JOIN projects p ON p.id = m.project_id
+        WHERE m.id = $1
+        "#,
+        milestone_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let recipients = sqlx::query!(
+        r#"
+        SELECT u.id, u.email
+        FROM users u
+        WHERE u.id IN (
+            SELECT s.user_id FROM projects p JOIN students s ON s.id = p.student_id WHERE p.id = $1
+            UNION
+            SELECT d.donor_id FROM donations d WHERE d.project_id = $1 AND d.status = 'confirmed' AND d.donor_id IS NOT NULL
+        )
+        "#,
+        milestone.project_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let template = EmailTemplate::MilestoneReleased {
+        project_title: milestone.project_title,
+        milestone_title: milestone.title,
+        amount: milestone.amount,
+        tx_url,
+    };
+    let mut queued = 0;
+    for recipient in recipients {
+        let key = format!("milestone_released:{}:{}", milestone_id, recipient.id);
+        if queue(pool, &recipient.email, &template, Some(&key)).await?.is_some() {
+            queued += 1;
+        }
+    }
+    Ok(queued)
+}
+
+/// Send a project's donors its completion report
+pub async fn queue_project_completed(pool: &PgPool, project_id: Uuid, leftover: Stroops, leftover_refunded: bool) -> Result<usize> {
+    let project = sqlx::query!(
+        r#"
+        SELECT p.title,
+               COALESCE((SELECT SUM(d.amount) FROM donations d
+                         WHERE d.project_id = p.id AND d.status = 'confirmed'), 0) as "raised!: Stroops",
+               (SELECT COUNT(*) FROM milestones m WHERE m.project_id = p.id AND m.released) as "milestones_released!"
+        FROM projects p
+        WHERE p.id = $1
+        "#,
+        project_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let recipients = sqlx::query!(
+        r#"
+        SELECT DISTINCT u.id, u.email
+        FROM donations d
+        JOIN users u ON u.id = d.donor_id
+        WHERE d.project_id = $1 AND d.status = 'confirmed'
+        "#,
+        project_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let template = EmailTemplate::ProjectCompleted {
+        project_title: project.title,
+        raised: project.raised,
+        milestones_released: project.milestones_released,
+        leftover,
+        leftover_refunded,
+        project_url: public_url(&format!("/projects/{}", project_id)),
+    };
+    let mut queued = 0;
+    for recipient in recipients {
+        let key = format!("project_completed:{}:{}", project_id, recipient.id);
+        if queue(pool, &recipient.email, &template, Some(&key)).await?.is_some() {
+            queued += 1;
+        }
+    }
+    Ok(queued)
+}
+
+/// Deliver an outboxed email, for its `send_email` job. Emails already sent
+/// are skipped. A failure is recorded on the email, which is marked failed
+/// on its job's last attempt.
+pub async fn send_queued(pool: &PgPool, provider: &dyn EmailProvider, id: Uuid, last_attempt: bool) -> Result<()> {
+    let Some(row) = sqlx::query!(
+        r#"
+        UPDATE email_outbox SET attempts = attempts + 1
+        WHERE id = $1 AND status <> 'sent'
+        RETURNING recipient, subject, body_text, body_html
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let email = OutgoingEmail { to: row.recipient, subject: row.subject, text: row.body_text, html: row.body_html };
+    match provider.send(&email).await {
+        Ok(()) => mark_sent(pool, id, provider.name()).await,
+        Err(e) => {
+            mark_failed(pool, id, &e, last_attempt).await?;
+            Err(anyhow!("Sending email {} to {} failed: {}", id, email.to, e))
+        }
+    }
+}
+
+pub async fn mark_sent(pool: &PgPool, id: Uuid, provider: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET status = 'sent', provider = $2, sent_at = NOW(), last_error = NULL
+        WHERE id = $1
+        "#,
+        id,
+        provider
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a failed attempt, and that the email was given up on after the last
+pub async fn mark_failed(pool: &PgPool, id: Uuid, error: &str, gave_up: bool) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET status = CASE WHEN $3 THEN 'failed' ELSE 'pending' END, last_error = $2
+        WHERE id = $1
+        "#,
+        id,
+        error,
+        gave_up
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mailbox() {
+        assert_eq!(parse_mailbox("FundHub <no-reply@fundhub.io>"), (Some("FundHub"), "no-reply@fundhub.io"));
+        assert_eq!(parse_mailbox("\"Fund Hub\" <a@b.c>"), (Some("Fund Hub"), "a@b.c"));
+        assert_eq!(parse_mailbox(" a@b.c "), (None, "a@b.c"));
+        assert_eq!(parse_mailbox("<a@b.c>"), (None, "a@b.c"));
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(escape_html("<b>\"Tom & Jerry's\"</b>"), "&lt;b&gt;&quot;Tom &amp; Jerry&#39;s&quot;&lt;/b&gt;");
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), Duration::from_secs(60));
+        assert_eq!(retry_delay(2), Duration::from_secs(120));
+        assert_eq!(retry_delay(5), Duration::from_secs(16 * 60));
+        assert_eq!(retry_delay(20), Duration::from_secs(6 * 60 * 60));
+    }
+
+    #[test]
+    fn test_sigv4_signing_key() {
+        // Example from the AWS Signature Version 4 documentation
+        let key = sigv4_signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex::encode(key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn test_render_escapes_user_text() {
+        let rendered = EmailTemplate::VerificationDecision {
+            username: "<script>".to_string(),
+            approved: false,
+            message: Some("Upload a clearer ID & try again".to_string()),
+        }
+        .render();
+
+        assert_eq!(rendered.subject, "Your student verification was not approved");
+        assert!(rendered.text.contains("Hi <script>,"));
+        assert!(rendered.text.contains("Upload a clearer ID & try again"));
+        assert!(rendered.html.contains("Hi &lt;script&gt;,"));
+        assert!(!rendered.html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_render_project_completed() {
+        let template = |leftover_refunded| EmailTemplate::ProjectCompleted {
+            project_title: "Solar Kiosk".to_string(),
+            raised: Stroops::from_xlm(500).unwrap(),
+            milestones_released: 1,
+            leftover: Stroops::from_xlm(20).unwrap(),
+            leftover_refunded,
+            project_url: "https://fundhub.io/projects/1".to_string(),
+        };
+
+        let paid_out = template(false).render();
+        assert_eq!(paid_out.subject, "Solar Kiosk is complete");
+        assert!(paid_out.text.contains("delivered 1 milestone."));
+        assert!(paid_out.text.contains("paid out to the student"));
+        assert!(template(true).render().text.contains("refunded to donors"));
+    }
+
+    #[test]
+    fn test_render_action_link() {
+        let rendered = EmailTemplate::Verification {
+            username: "ada".to_string(),
+            link: "https://fundhub.io/verify?token=abc&x=1".to_string(),
+        }
+        .render();
+
+        assert!(rendered.text.contains("Confirm email: https://fundhub.io/verify?token=abc&x=1"));
+        assert!(rendered.html.contains("<a href=\"https://fundhub.io/verify?token=abc&amp;x=1\">Confirm email</a>"));
+    }
+
+    #[test]
+    fn test_event_type_is_emailed() {
+        let reset = EmailTemplate::PasswordReset { username: "ada".to_string(), link: "https://fundhub.io".to_string() };
+        assert_eq!(reset.event_type(), None);
+
+        let digest = EmailTemplate::AnnouncementDigest { username: "ada".to_string(), announcements: Vec::new() };
+        let event_type = digest.event_type().unwrap();
+        assert!(notification_preferences::offers(event_type, notification_preferences::Medium::Email));
+    }
+
+    #[test]
+    fn test_render_notification_digest() {
+        let rendered = EmailTemplate::NotificationDigest {
+            username: "ada".to_string(),
+            cadence: "weekly".to_string(),
+            unread: vec!["New comment on your project".to_string()],
+            unread_count: 3,
+            donations: 0,
+            donated: Stroops::ZERO,
+            updates: vec![("Solar Kiosk".to_string(), "Panels installed".to_string())],
+            unsubscribe_url: "https://fundhub.io/api/notifications/digest/unsubscribe?token=abc".to_string(),
+        }
+        .render();
+
+        assert!(rendered.subject.starts_with("Your weekly "));
+        assert!(rendered.text.contains("You have 3 unread notification(s):\n- New comment on your project\n...and 2 more"));
+        assert!(!rendered.text.contains("donation(s)"));
+        assert!(rendered.text.contains("- Solar Kiosk: Panels installed"));
+        assert!(rendered.text.contains("unsubscribe: https://fundhub.io/api/notifications/digest/unsubscribe?token=abc"));
+    }
+}
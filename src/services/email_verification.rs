@@ -0,0 +1,100 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::email::{self, EmailTemplate};
+
+/// How long a verification link works
+pub const TOKEN_TTL_HOURS: i64 = 24;
+/// Shortest gap between two verification emails to one account
+pub const RESEND_COOLDOWN_SECS: i64 = 60;
+/// Verification emails one account may be sent per hour
+pub const MAX_SENDS_PER_HOUR: i64 = 3;
+
+/// Emailed tokens are stored hashed so a leaked table can't redeem them
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// A random token for an emailed link
+pub fn new_token() -> String {
+    let mut rng = rand::thread_rng();
+    (0..32).map(|_| format!("{:02x}", rng.gen::<u8>())).collect()
+}
+
+/// Seconds until another verification email may be sent, given how many
+/// went out in the last hour and when the latest did
+pub fn resend_wait(sent_last_hour: i64, last_sent: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<i64> {
+    let last_sent = last_sent?;
+    let cooldown = RESEND_COOLDOWN_SECS - (now - last_sent).num_seconds();
+    if sent_last_hour >= MAX_SENDS_PER_HOUR {
+        // Wait for the hour that began with the latest send to run out
+        return Some((3600 - (now - last_sent).num_seconds()).max(cooldown).max(1));
+    }
+    (cooldown > 0).then_some(cooldown)
+}
+
+/// Create a verification token for the user and queue the email with its link
+pub async fn send(pool: &PgPool, user_id: Uuid, username: &str, email_address: &str) -> Result<()> {
+    let token = new_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO email_verification_tokens (user_id, token, expires_at)
+        VALUES ($1, $2, $3)
+        "#,
+        user_id,
+        hash_token(&token),
+        Utc::now() + Duration::hours(TOKEN_TTL_HOURS)
+    )
+    .execute(pool)
+    .await?;
+
+    let template = EmailTemplate::Verification {
+        username: username.to_string(),
+        link: email::public_url(&format!("/api/auth/verify-email?token={}", token)),
+    };
+    email::queue(pool, email_address, &template, None).await?;
+    Ok(())
+}
+
+/// Seconds the user must wait before another verification email, if any
+pub async fn resend_wait_for(pool: &PgPool, user_id: Uuid) -> Result<Option<i64>> {
+    let sent = sqlx::query!(
+        r#"
+        SELECT COUNT(*) FILTER (WHERE created_at > NOW() - INTERVAL '1 hour') as "last_hour!",
+               MAX(created_at) as last_sent
+        FROM email_verification_tokens
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(resend_wait(sent.last_hour, sent.last_sent, Utc::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_token_is_stable_hex() {
+        let hashed = hash_token("abc");
+        assert_eq!(hashed, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_ne!(hash_token("abd"), hashed);
+    }
+
+    #[test]
+    fn test_resend_wait() {
+        let now = Utc::now();
+        assert_eq!(resend_wait(0, None, now), None);
+        assert_eq!(resend_wait(1, Some(now - Duration::seconds(20)), now), Some(40));
+        assert_eq!(resend_wait(1, Some(now - Duration::seconds(90)), now), None);
+        assert_eq!(resend_wait(3, Some(now - Duration::minutes(10)), now), Some(3000));
+        assert_eq!(resend_wait(3, Some(now - Duration::seconds(10)), now), Some(3590));
+    }
+}
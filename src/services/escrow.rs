@@ -0,0 +1,196 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use rand::RngCore;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::NewStellarService;
+use crate::utils::money::Stroops;
+
+/// XLM kept in an escrow account to cover the base reserve and fees (1.5 XLM)
+pub const ESCROW_MIN_RESERVE: Stroops = Stroops::from_stroops(15_000_000);
+
+/// Marks an escrow secret sealed by `SecretSealer`
+const SEALED_PREFIX: &str = "v1:";
+
+/// Encrypts escrow account secrets at rest with `ESCROW_ENCRYPTION_KEY`
+/// (32 bytes, hex encoded). Sealed secrets read `v1:` followed by the
+/// base64 of nonce || ciphertext.
+pub struct SecretSealer {
+    cipher: Aes256Gcm,
+}
+
+impl SecretSealer {
+    pub fn new(key_hex: &str) -> Result<Self> {
+        let key = hex::decode(key_hex.trim())
+            .ok()
+            .filter(|key| key.len() == 32)
+            .ok_or_else(|| anyhow!("ESCROW_ENCRYPTION_KEY must be 32 bytes, hex encoded"))?;
+        let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| anyhow!("Invalid escrow encryption key"))?;
+        Ok(Self { cipher })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let key = std::env::var("ESCROW_ENCRYPTION_KEY")
+            .map_err(|_| anyhow!("ESCROW_ENCRYPTION_KEY must be set to store escrow secrets"))?;
+        Self::new(&key)
+    }
+
+    pub fn seal(&self, secret: &str) -> Result<String> {
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(&Nonce::from(nonce), secret.as_bytes())
+            .map_err(|_| anyhow!("Failed to seal escrow secret"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", SEALED_PREFIX, base64::engine::general_purpose::STANDARD.encode(sealed)))
+    }
+
+    pub fn open(&self, sealed: &str) -> Result<String> {
+        let encoded = sealed
+            .strip_prefix(SEALED_PREFIX)
+            .ok_or_else(|| anyhow!("Escrow secret is not sealed"))?;
+        let bytes = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+        if bytes.len() < 12 {
+            return Err(anyhow!("Sealed escrow secret is truncated"));
+        }
+        let (nonce, ciphertext) = bytes.split_at(12);
+        let nonce: [u8; 12] = nonce.try_into()?;
+        let secret = self
+            .cipher
+            .decrypt(&Nonce::from(nonce), ciphertext)
+            .map_err(|_| anyhow!("Failed to open escrow secret; wrong ESCROW_ENCRYPTION_KEY?"))?;
+        Ok(String::from_utf8(secret)?)
+    }
+}
+
+/// Seal escrow secrets stored before they were encrypted, returning how
+/// many were sealed
+pub async fn seal_plaintext_secrets(pool: &PgPool, sealer: &SecretSealer) -> Result<usize> {
+    let accounts = sqlx::query!(
+        "SELECT id, secret_key FROM project_escrow_accounts WHERE secret_key NOT LIKE 'v1:%'"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for account in &accounts {
+        sqlx::query!(
+            "UPDATE project_escrow_accounts SET secret_key = $1 WHERE id = $2 AND secret_key = $3",
+            sealer.seal(&account.secret_key)?,
+            account.id,
+            account.secret_key
+        )
+        .execute(pool)
+        .await?;
+    }
+    Ok(accounts.len())
+}
+
+/// Manages dedicated per-project escrow accounts
+#[derive(Clone)]
+pub struct EscrowService {
+    pool: PgPool,
+    stellar: NewStellarService,
+}
+
+impl EscrowService {
+    pub fn new(pool: PgPool, stellar: NewStellarService) -> Self {
+        Self { pool, stellar }
+    }
+
+    /// Create and fund a dedicated escrow account for a project and store its
+    /// address as the project's `contract_address`. Returns the existing
+    /// address if the project already has one.
+    pub async fn provision(&self, project_id: Uuid) -> Result<String> {
+        if let Some(existing) = self.escrow_address(project_id).await? {
+            return Ok(existing);
+        }
+
+        let wallet = self.stellar.generate_wallet();
+        if !self.stellar.validate_address(&wallet.public_key) {
+            return Err(anyhow!("Generated escrow address is invalid"));
+        }
+        // Fail before funding the account if its secret can't be stored
+        let sealed_secret = SecretSealer::from_env()?.seal(&wallet.secret_key)?;
+
+        // Create the account on-chain by funding it from the platform wallet
+        let starting_balance: Stroops = std::env::var("ESCROW_ACCOUNT_STARTING_BALANCE")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse()?;
+        let memo = format!("escrow:{}", &project_id.simple().to_string()[..20]);
+        let funding_tx_hash = self
+            .stellar
+            .send_from_platform(&wallet.public_key, &starting_balance.to_string(), Some(&memo))
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO project_escrow_accounts (project_id, public_key, secret_key, funding_tx_hash)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            project_id,
+            wallet.public_key,
+            sealed_secret,
+            funding_tx_hash
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE projects SET contract_address = $1 WHERE id = $2",
+            wallet.public_key,
+            project_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        tracing::info!("Provisioned escrow account {} for project {}", wallet.public_key, project_id);
+        Ok(wallet.public_key)
+    }
+
+    /// Get the escrow account address for a project, if one was provisioned
+    pub async fn escrow_address(&self, project_id: Uuid) -> Result<Option<String>> {
+        let address = sqlx::query_scalar!(
+            "SELECT public_key FROM project_escrow_accounts WHERE project_id = $1",
+            project_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn test_seal_round_trip() {
+        let sealer = SecretSealer::new(KEY).unwrap();
+        let secret = "SBQWY3DNPFWGSZTFNV4WQZLBOJ2GQYLTMJSWK3TTMVZXEZLDOJSXIZLT";
+        let sealed = sealer.seal(secret).unwrap();
+        assert!(sealed.starts_with(SEALED_PREFIX));
+        assert!(!sealed.contains(secret));
+        assert_ne!(sealer.seal(secret).unwrap(), sealed);
+        assert_eq!(sealer.open(&sealed).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_open_rejects_plaintext_and_wrong_key() {
+        let sealer = SecretSealer::new(KEY).unwrap();
+        let sealed = sealer.seal("SECRET").unwrap();
+        assert!(sealer.open("SECRET").is_err());
+
+        let other = SecretSealer::new(&"ff".repeat(32)).unwrap();
+        assert!(other.open(&sealed).is_err());
+        assert!(SecretSealer::new("abcd").is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::money::Stroops;
+
+/// Longest a single featuring slot may run
+pub const MAX_FEATURE_DAYS: i64 = 30;
+
+/// A scheduled window during which a project is eligible for the spotlight
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureSlot {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub impressions: i64,
+    pub last_shown_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeaturedProject {
+    pub slot_id: Uuid,
+    pub project_id: Uuid,
+    pub title: String,
+    pub short_description: Option<String>,
+    pub media_url: Option<String>,
+    pub funding_goal: Stroops,
+    pub current_funding: Stroops,
+    pub featured_until: DateTime<Utc>,
+}
+
+/// Donations a project received during a slot compared with the equally
+/// long window just before it
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureUplift {
+    pub slot_id: Uuid,
+    pub project_id: Uuid,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub impressions: i64,
+    pub baseline_funding: Stroops,
+    pub featured_funding: Stroops,
+    /// `None` when there was no baseline funding to compare against
+    pub uplift_pct: Option<f64>,
+}
+
+/// Check a requested slot window before it is stored
+pub fn validate_window(starts_at: DateTime<Utc>, ends_at: DateTime<Utc>) -> Result<()> {
+    if ends_at <= starts_at {
+        return Err(anyhow::anyhow!("Feature slot must end after it starts"));
+    }
+    if ends_at - starts_at > Duration::days(MAX_FEATURE_DAYS) {
+        return Err(anyhow::anyhow!("Feature slot cannot exceed {} days", MAX_FEATURE_DAYS));
+    }
+    if ends_at <= Utc::now() {
+        return Err(anyhow::anyhow!("Feature slot is already over"));
+    }
+    Ok(())
+}
+
+/// Percentage change from the baseline window to the featured window
+pub fn uplift_pct(baseline: Stroops, featured: Stroops) -> Option<f64> {
+    if !baseline.is_positive() {
+        return None;
+    }
+    Some((featured.to_f64() - baseline.to_f64()) / baseline.to_f64() * 100.0)
+}
+
+/// Schedule a project for featuring. The project must be public and active,
+/// and may not already have an overlapping slot.
+pub async fn schedule(
+    pool: &PgPool,
+    project_id: Uuid,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    created_by: Option<Uuid>,
+) -> Result<FeatureSlot> {
+    validate_window(starts_at, ends_at)?;
+
+    let eligible = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1 AND status = 'active' AND visibility = 'public') as "eligible!""#,
+        project_id
+    )
+    .fetch_one(pool)
+    .await?;
+    if !eligible {
+        return Err(anyhow::anyhow!("Only public, active projects can be featured"));
+    }
+
+    let overlapping = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM project_features
+            WHERE project_id = $1 AND starts_at < $3 AND ends_at > $2
+        ) as "overlapping!"
+        "#,
+        project_id,
+        starts_at,
+        ends_at
+    )
+    .fetch_one(pool)
+    .await?;
+    if overlapping {
+        return Err(anyhow::anyhow!("Project already has a feature slot in that window"));
+    }
+
+    let slot = sqlx::query_as!(
+        FeatureSlot,
+        r#"
+        INSERT INTO project_features (project_id, starts_at, ends_at, created_by)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, project_id, starts_at, ends_at, created_by, impressions, last_shown_at, created_at
+        "#,
+        project_id,
+        starts_at,
+        ends_at,
+        created_by
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(slot)
+}
+
+/// Current and upcoming slots, plus past ones when `include_past` is set
+pub async fn list(pool: &PgPool, include_past: bool) -> Result<Vec<FeatureSlot>> {
+    let slots = sqlx::query_as!(
+        FeatureSlot,
+        r#"
+        SELECT id, project_id, starts_at, ends_at, created_by, impressions, last_shown_at, created_at
+        FROM project_features
+        WHERE $1 OR ends_at > NOW()
+        ORDER BY starts_at
+        "#,
+        include_past
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(slots)
+}
+
+/// Remove a slot; returns false if it did not exist
+pub async fn cancel(pool: &PgPool, id: Uuid) -> Result<bool> {
+    let result = sqlx::query!("DELETE FROM project_features WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Pick up to `limit` projects from the slots live right now, least recently
+/// shown first so every eligible project gets a fair share of the spotlight,
+/// and record the impression
+pub async fn rotate(pool: &PgPool, limit: i64) -> Result<Vec<FeaturedProject>> {
+    let mut tx = pool.begin().await?;
+
+    let slot_ids = sqlx::query_scalar!(
+        r#"
+        SELECT f.id
+        FROM project_features f
+        JOIN projects p ON p.id = f.project_id
+        WHERE f.starts_at <= NOW() AND f.ends_at > NOW()
+          AND p.status = 'active' AND p.visibility = 'public'
+        ORDER BY f.last_shown_at NULLS FIRST, f.impressions, f.starts_at
+        LIMIT $1
+        FOR UPDATE OF f SKIP LOCKED
+        "#,
+        limit
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE project_features
+        SET impressions = impressions + 1, last_shown_at = NOW()
+        WHERE id = ANY($1)
+        "#,
+        &slot_ids
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let projects = sqlx::query_as!(
+        FeaturedProject,
+        r#"
+        SELECT
+            f.id as slot_id,
+            p.id as project_id,
+            p.title,
+            LEFT(p.description, 200) as short_description,
+            p.media_url,
+            p.funding_goal as "funding_goal!: Stroops",
+            COALESCE((
+                SELECT SUM(d.amount) FROM donations d
+                WHERE d.project_id = p.id AND d.status = 'confirmed'
+            ), 0) as "current_funding!: Stroops",
+            f.ends_at as featured_until
+        FROM project_features f
+        JOIN projects p ON p.id = f.project_id
+        WHERE f.id = ANY($1)
+        ORDER BY array_position($1, f.id)
+        "#,
+        &slot_ids
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(projects)
+}
+
+/// Funding uplift for slots that started since `since`
+pub async fn uplift(pool: &PgPool, since: DateTime<Utc>) -> Result<Vec<FeatureUplift>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            f.id, f.project_id, f.starts_at, f.ends_at, f.impressions,
+            COALESCE((
+                SELECT SUM(d.amount) FROM donations d
+                WHERE d.project_id = f.project_id AND d.status = 'confirmed'
+                  AND d.confirmed_at >= f.starts_at - (LEAST(f.ends_at, NOW()) - f.starts_at)
+                  AND d.confirmed_at < f.starts_at
+            ), 0) as "baseline!: Stroops",
+            COALESCE((
+                SELECT SUM(d.amount) FROM donations d
+                WHERE d.project_id = f.project_id AND d.status = 'confirmed'
+                  AND d.confirmed_at >= f.starts_at
+                  AND d.confirmed_at < LEAST(f.ends_at, NOW())
+            ), 0) as "featured!: Stroops"
+        FROM project_features f
+        WHERE f.starts_at >= $1 AND f.starts_at <= NOW()
+        ORDER BY f.starts_at DESC
+        "#,
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| FeatureUplift {
+            slot_id: r.id,
+            project_id: r.project_id,
+            starts_at: r.starts_at,
+            ends_at: r.ends_at,
+            impressions: r.impressions,
+            uplift_pct: uplift_pct(r.baseline, r.featured),
+            baseline_funding: r.baseline,
+            featured_funding: r.featured,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_window() {
+        let now = Utc::now();
+        assert!(validate_window(now, now + Duration::days(7)).is_ok());
+        assert!(validate_window(now + Duration::days(1), now).is_err());
+        assert!(validate_window(now, now + Duration::days(MAX_FEATURE_DAYS + 1)).is_err());
+        assert!(validate_window(now - Duration::days(3), now - Duration::days(1)).is_err());
+    }
+
+    #[test]
+    fn test_uplift_pct() {
+        let xlm = |n| Stroops::from_xlm(n).unwrap();
+        assert_eq!(uplift_pct(xlm(100), xlm(150)), Some(50.0));
+        assert_eq!(uplift_pct(xlm(100), xlm(50)), Some(-50.0));
+        assert_eq!(uplift_pct(Stroops::ZERO, xlm(50)), None);
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::ledger;
+use crate::utils::money::Stroops;
+
+/// Fee charged on a confirmed donation: a percentage plus a fixed amount
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeRule {
+    pub bps: u32,
+    pub fixed: Stroops,
+}
+
+impl FeeRule {
+    /// The rule for a payment method. `PLATFORM_FEE_BPS` and
+    /// `PLATFORM_FEE_FIXED_XLM` apply to every method unless overridden by
+    /// `PLATFORM_FEE_<METHOD>_BPS` / `PLATFORM_FEE_<METHOD>_FIXED_XLM`.
+    pub fn for_method(payment_method: &str) -> Self {
+        let method = payment_method.to_uppercase();
+        let setting = |suffix: &str| {
+            std::env::var(format!("PLATFORM_FEE_{}_{}", method, suffix))
+                .or_else(|_| std::env::var(format!("PLATFORM_FEE_{}", suffix)))
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+        };
+
+        Self {
+            bps: setting("BPS").and_then(|v| v.trim().parse().ok()).unwrap_or(0).min(10_000),
+            fixed: setting("FIXED_XLM")
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|fixed: &Stroops| !fixed.as_stroops().is_negative())
+                .unwrap_or(Stroops::ZERO),
+        }
+    }
+
+    /// Fee on `gross`, never more than the donation itself
+    pub fn fee_on(&self, gross: Stroops) -> Stroops {
+        let fee = gross
+            .mul_bps(self.bps)
+            .checked_add(self.fixed)
+            .unwrap_or(gross);
+        fee.min(gross)
+    }
+}
+
+/// Take the platform fee on a confirmed project donation, recording it
+/// against the donation and in the ledger. Platform donations carry no fee.
+/// Returns the fee taken, or `None` if the donation already had one.
+pub async fn apply(
+    pool: &PgPool,
+    donation_id: Uuid,
+    project_id: Option<Uuid>,
+    payment_method: &str,
+    gross: Stroops,
+) -> Result<Option<Stroops>> {
+    let Some(project_id) = project_id else {
+        return Ok(None);
+    };
+    let rule = FeeRule::for_method(payment_method);
+    let fee = rule.fee_on(gross);
+    let net = gross.checked_sub(fee).unwrap_or(Stroops::ZERO);
+
+    let mut tx = pool.begin().await?;
+    let recorded = sqlx::query!(
+        r#"
+        INSERT INTO donation_fees
+        (donation_id, project_id, payment_method, gross_amount, fee_amount, net_amount, fee_bps, fixed_fee)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (donation_id) DO NOTHING
+        "#,
+        donation_id,
+        project_id,
+        payment_method,
+        gross.to_decimal(),
+        fee.to_decimal(),
+        net.to_decimal(),
+        rule.bps as i32,
+        rule.fixed.to_decimal()
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    if recorded == 0 {
+        return Ok(None);
+    }
+    // Post in the same transaction so a fee is never recorded without its ledger entry
+    if fee.is_positive() {
+        ledger::post_in(&mut tx, &ledger::fee_entry(donation_id, project_id, fee)).await?;
+    }
+    tx.commit().await?;
+    Ok(Some(fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_is_percentage_plus_fixed() {
+        let rule = FeeRule { bps: 250, fixed: Stroops::from_stroops(1_000_000) };
+        let gross = Stroops::from_xlm(100).unwrap();
+        assert_eq!(rule.fee_on(gross), Stroops::from_stroops(25_000_000 + 1_000_000));
+    }
+
+    #[test]
+    fn test_fee_never_exceeds_the_donation() {
+        let rule = FeeRule { bps: 100, fixed: Stroops::from_xlm(5).unwrap() };
+        let gross = Stroops::from_xlm(2).unwrap();
+        assert_eq!(rule.fee_on(gross), gross);
+        assert_eq!(FeeRule { bps: 0, fixed: Stroops::ZERO }.fee_on(gross), Stroops::ZERO);
+    }
+}
@@ -0,0 +1,283 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Monthly recurring gifts
+pub const RECURRING_DONATIONS: &str = "recurring_donations";
+/// Donating without an account
+pub const GUEST_CHECKOUT: &str = "guest_checkout";
+
+/// A flag handlers can be gated on with `RequireFlag`
+pub trait FeatureFlag {
+    const NAME: &'static str;
+}
+
+pub struct RecurringDonations;
+
+impl FeatureFlag for RecurringDonations {
+    const NAME: &'static str = RECURRING_DONATIONS;
+}
+
+pub struct GuestCheckout;
+
+impl FeatureFlag for GuestCheckout {
+    const NAME: &'static str = GUEST_CHECKOUT;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Flag {
+    pub name: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    /// `APP_ENV` values the flag is on in; empty for every environment
+    pub environments: Vec<String>,
+    /// Share of signed-in callers the flag is on for
+    pub rollout_percent: i32,
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A flag's settings as entered by an admin
+#[derive(Debug, Clone)]
+pub struct FlagChange<'a> {
+    pub description: Option<&'a str>,
+    pub enabled: bool,
+    pub environments: &'a [String],
+    pub rollout_percent: i32,
+}
+
+/// Lowercase letters, digits and underscores, starting with a letter
+pub fn is_valid_name(name: &str) -> bool {
+    (1..=64).contains(&name.len())
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Where a caller falls in a flag's rollout, 0 to 99. Hashing the flag name
+/// in spreads each flag's rollout over different users.
+pub fn rollout_bucket(name: &str, subject: Uuid) -> u8 {
+    let digest = Sha256::new().chain_update(name).chain_update(subject.as_bytes()).finalize();
+    let head = u64::from_be_bytes(digest[..8].try_into().expect("a SHA-256 digest is 32 bytes"));
+    (head % 100) as u8
+}
+
+impl Flag {
+    /// Whether the flag is on in `environment` for `subject`, the caller's
+    /// user id if they're signed in
+    pub fn evaluate(&self, environment: &str, subject: Option<Uuid>) -> bool {
+        if !self.enabled || !(self.environments.is_empty() || self.environments.iter().any(|e| e == environment)) {
+            return false;
+        }
+        match (self.rollout_percent, subject) {
+            (percent, _) if percent >= 100 => true,
+            (percent, _) if percent <= 0 => false,
+            (percent, Some(subject)) => i32::from(rollout_bucket(&self.name, subject)) < percent,
+            (_, None) => false,
+        }
+    }
+}
+
+struct Loaded {
+    at: Instant,
+    flags: HashMap<String, Flag>,
+}
+
+/// Feature flags held in memory, loaded at startup and reread when an admin
+/// changes one here or once `FEATURE_FLAG_CACHE_TTL_MS` has passed, which is
+/// how changes made through other instances arrive. Clones share the flags.
+#[derive(Clone)]
+pub struct FlagRegistry {
+    pool: PgPool,
+    environment: String,
+    ttl: Duration,
+    loaded: Arc<RwLock<Loaded>>,
+}
+
+impl FlagRegistry {
+    pub async fn load(pool: PgPool) -> Result<Self> {
+        let registry = Self {
+            pool,
+            environment: crate::config::app_environment(),
+            ttl: crate::config::feature_flag_cache_ttl(),
+            loaded: Arc::new(RwLock::new(Loaded { at: Instant::now(), flags: HashMap::new() })),
+        };
+        registry.reload().await?;
+        tracing::info!("Feature flags loaded for the {} environment", registry.environment);
+        Ok(registry)
+    }
+
+    pub fn environment(&self) -> &str {
+        &self.environment
+    }
+
+    /// Reread every flag
+    pub async fn reload(&self) -> Result<()> {
+        let flags = list(&self.pool).await?;
+        *self.loaded.write().unwrap() = Loaded {
+            at: Instant::now(),
+            flags: flags.into_iter().map(|flag| (flag.name.clone(), flag)).collect(),
+        };
+        Ok(())
+    }
+
+    /// Whether a flag is on for `subject`; unknown flags are off. If the
+    /// flags can't be reread, the last ones loaded keep being used.
+    pub async fn enabled(&self, name: &str, subject: Option<Uuid>) -> bool {
+        let stale = self.loaded.read().unwrap().at.elapsed() >= self.ttl;
+        if stale {
+            if let Err(e) = self.reload().await {
+                tracing::warn!("Failed to reload feature flags: {}", e);
+            }
+        }
+        self.loaded
+            .read()
+            .unwrap()
+            .flags
+            .get(name)
+            .is_some_and(|flag| flag.evaluate(&self.environment, subject))
+    }
+}
+
+pub async fn list(pool: &PgPool) -> Result<Vec<Flag>> {
+    let flags = sqlx::query_as!(
+        Flag,
+        r#"
+        SELECT name, description, enabled, environments, rollout_percent, updated_by, created_at, updated_at
+        FROM feature_flags
+        ORDER BY name
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(flags)
+}
+
+/// Add a flag; `None` if one by that name exists
+pub async fn create(pool: &PgPool, name: &str, change: &FlagChange<'_>, admin_id: Uuid) -> Result<Option<Flag>> {
+    let flag = sqlx::query_as!(
+        Flag,
+        r#"
+        INSERT INTO feature_flags (name, description, enabled, environments, rollout_percent, updated_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (name) DO NOTHING
+        RETURNING name, description, enabled, environments, rollout_percent, updated_by, created_at, updated_at
+        "#,
+        name,
+        change.description,
+        change.enabled,
+        change.environments,
+        change.rollout_percent,
+        admin_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(flag)
+}
+
+/// Replace a flag's settings; `None` if there's no such flag
+pub async fn update(pool: &PgPool, name: &str, change: &FlagChange<'_>, admin_id: Uuid) -> Result<Option<Flag>> {
+    let flag = sqlx::query_as!(
+        Flag,
+        r#"
+        UPDATE feature_flags
+        SET description = $2, enabled = $3, environments = $4, rollout_percent = $5,
+            updated_by = $6, updated_at = NOW()
+        WHERE name = $1
+        RETURNING name, description, enabled, environments, rollout_percent, updated_by, created_at, updated_at
+        "#,
+        name,
+        change.description,
+        change.enabled,
+        change.environments,
+        change.rollout_percent,
+        admin_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(flag)
+}
+
+/// Remove a flag, which turns it off everywhere
+pub async fn delete(pool: &PgPool, name: &str) -> Result<Option<Flag>> {
+    let flag = sqlx::query_as!(
+        Flag,
+        r#"
+        DELETE FROM feature_flags
+        WHERE name = $1
+        RETURNING name, description, enabled, environments, rollout_percent, updated_by, created_at, updated_at
+        "#,
+        name
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(flag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag(enabled: bool, environments: &[&str], rollout_percent: i32) -> Flag {
+        Flag {
+            name: "new_checkout".to_string(),
+            description: None,
+            enabled,
+            environments: environments.iter().map(|e| e.to_string()).collect(),
+            rollout_percent,
+            updated_by: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("recurring_donations"));
+        assert!(is_valid_name("v2_search"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("2fa_everywhere"));
+        assert!(!is_valid_name("Guest-Checkout"));
+        assert!(!is_valid_name(&"a".repeat(65)));
+    }
+
+    #[test]
+    fn test_evaluate_environments() {
+        let user = Some(Uuid::new_v4());
+        assert!(flag(true, &[], 100).evaluate("production", user));
+        assert!(!flag(false, &[], 100).evaluate("production", user));
+        assert!(flag(true, &["staging"], 100).evaluate("staging", None));
+        assert!(!flag(true, &["staging"], 100).evaluate("production", user));
+    }
+
+    #[test]
+    fn test_evaluate_rollout() {
+        assert!(!flag(true, &[], 0).evaluate("production", Some(Uuid::new_v4())));
+        assert!(!flag(true, &[], 50).evaluate("production", None));
+
+        let half = flag(true, &[], 50);
+        let on = (0..1000).filter(|_| half.evaluate("production", Some(Uuid::new_v4()))).count();
+        assert!((350..650).contains(&on), "{} of 1000 callers in a 50% rollout", on);
+    }
+
+    #[test]
+    fn test_rollout_bucket_is_stable() {
+        let user = Uuid::new_v4();
+        assert_eq!(rollout_bucket("a", user), rollout_bucket("a", user));
+        assert!(rollout_bucket("a", user) < 100);
+
+        // Anyone in a rollout stays in it as it grows
+        let user = (0..1000)
+            .map(|_| Uuid::new_v4())
+            .find(|u| rollout_bucket("new_checkout", *u) < 10)
+            .unwrap();
+        assert!(flag(true, &[], 10).evaluate("production", Some(user)));
+        assert!(flag(true, &[], 60).evaluate("production", Some(user)));
+    }
+}
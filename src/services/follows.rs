@@ -0,0 +1,252 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::notifications::NotificationEvent;
+use crate::utils::pagination::{Page, PageRequest};
+
+/// Shares of the funding goal, in percent, that followers are told about
+pub const FUNDING_THRESHOLDS: [i32; 4] = [25, 50, 75, 100];
+
+/// Something a user follows: a project or a student
+#[derive(Debug, Clone, Copy)]
+pub enum FollowTarget {
+    Project(Uuid),
+    Student(Uuid),
+}
+
+impl FollowTarget {
+    fn ids(self) -> (Option<Uuid>, Option<Uuid>) {
+        match self {
+            FollowTarget::Project(id) => (Some(id), None),
+            FollowTarget::Student(id) => (None, Some(id)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FollowStatus {
+    pub following: bool,
+    pub followers: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Followed {
+    pub id: Uuid,
+    pub project_id: Option<Uuid>,
+    pub student_id: Option<Uuid>,
+    /// The project's title or the student's username
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Thresholds at or below `percent` funded
+pub fn reached_thresholds(percent: i64) -> Vec<i32> {
+    FUNDING_THRESHOLDS.iter().copied().filter(|t| i64::from(*t) <= percent).collect()
+}
+
+/// Whether the target exists
+pub async fn target_exists(pool: &PgPool, target: FollowTarget) -> Result<bool> {
+    let (project_id, student_id) = target.ids();
+    let exists = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM projects WHERE id = $1)
+            OR EXISTS(SELECT 1 FROM students WHERE id = $2) as "exists!"
+        "#,
+        project_id,
+        student_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(exists)
+}
+
+async fn status(pool: &PgPool, follower_id: Uuid, target: FollowTarget) -> Result<FollowStatus> {
+    let (project_id, student_id) = target.ids();
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "followers!",
+               COUNT(*) FILTER (WHERE follower_id = $3) > 0 as "following!"
+        FROM follows
+        WHERE project_id = $1 OR student_id = $2
+        "#,
+        project_id,
+        student_id,
+        follower_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(FollowStatus { following: row.following, followers: row.followers })
+}
+
+/// Follow a project or student; following twice is a no-op
+pub async fn follow(pool: &PgPool, follower_id: Uuid, target: FollowTarget) -> Result<FollowStatus> {
+    match target {
+        FollowTarget::Project(project_id) => {
+            sqlx::query!(
+                r#"
+                INSERT INTO follows (follower_id, project_id) VALUES ($1, $2)
+                ON CONFLICT (project_id, follower_id) WHERE project_id IS NOT NULL DO NOTHING
+                "#,
+                follower_id,
+                project_id
+            )
+            .execute(pool)
+            .await?;
+        }
+        FollowTarget::Student(student_id) => {
+            sqlx::query!(
+                r#"
+                INSERT INTO follows (follower_id, student_id) VALUES ($1, $2)
+                ON CONFLICT (student_id, follower_id) WHERE student_id IS NOT NULL DO NOTHING
+                "#,
+                follower_id,
+                student_id
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+    status(pool, follower_id, target).await
+}
+
+pub async fn unfollow(pool: &PgPool, follower_id: Uuid, target: FollowTarget) -> Result<FollowStatus> {
+    let (project_id, student_id) = target.ids();
+    sqlx::query!(
+        "DELETE FROM follows WHERE follower_id = $1 AND (project_id = $2 OR student_id = $3)",
+        follower_id,
+        project_id,
+        student_id
+    )
+    .execute(pool)
+    .await?;
+    status(pool, follower_id, target).await
+}
+
+/// What a user follows, most recently followed first
+pub async fn following(pool: &PgPool, follower_id: Uuid, page: &PageRequest) -> Result<Page<Followed>> {
+    let rows = sqlx::query_as!(
+        Followed,
+        r#"
+        SELECT f.id, f.project_id, f.student_id,
+               COALESCE(p.title, u.username) as "name!",
+               f.created_at
+        FROM follows f
+        LEFT JOIN projects p ON p.id = f.project_id
+        LEFT JOIN students s ON s.id = f.student_id
+        LEFT JOIN users u ON u.id = s.user_id
+        WHERE f.follower_id = $1
+          AND ($2::timestamptz IS NULL OR (f.created_at, f.id) < ($2, $3::uuid))
+        ORDER BY f.created_at DESC, f.id DESC
+        LIMIT $4
+        "#,
+        follower_id,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(Page::new(rows, page, |f| (Some(f.created_at), f.id)))
+}
+
+/// Notify, in-app, everyone following the project or its student. Returns
+/// how many were notified.
+pub async fn notify_followers(
+    pool: &PgPool,
+    project_id: Uuid,
+    title: &str,
+    message: &str,
+    metadata: serde_json::Value,
+    event: &NotificationEvent,
+) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+        SELECT DISTINCT f.follower_id, 'project', $2, $3, $4::jsonb, $5::jsonb
+        FROM follows f
+        JOIN projects p ON p.id = $1
+        WHERE f.project_id = p.id OR f.student_id = p.student_id
+        "#,
+        project_id,
+        title,
+        message,
+        metadata,
+        event.to_json()
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// After a donation is confirmed, record any funding thresholds its project
+/// has newly crossed and tell followers about the highest. Returns the
+/// project and that threshold, if one was crossed.
+pub async fn check_funding_thresholds(pool: &PgPool, donation_id: Uuid) -> Result<Option<(Uuid, i32)>> {
+    let progress = sqlx::query!(
+        r#"
+        SELECT p.id, p.title, p.funding_goal,
+               COALESCE(FLOOR(SUM(d.amount) * 100 / NULLIF(p.funding_goal, 0)), 0)::bigint as "percent!"
+        FROM donations dn
+        JOIN projects p ON p.id = dn.project_id
+        JOIN donations d ON d.project_id = p.id AND d.status = 'confirmed'
+        WHERE dn.id = $1
+        GROUP BY p.id
+        "#,
+        donation_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    let Some(progress) = progress else { return Ok(None) };
+
+    let reached = reached_thresholds(progress.percent);
+    if reached.is_empty() {
+        return Ok(None);
+    }
+
+    let crossed = sqlx::query_scalar!(
+        r#"
+        INSERT INTO funding_thresholds_reached (project_id, percent)
+        SELECT $1, unnest($2::int[])
+        ON CONFLICT (project_id, percent) DO NOTHING
+        RETURNING percent
+        "#,
+        progress.id,
+        &reached
+    )
+    .fetch_all(pool)
+    .await?;
+    let Some(percent) = crossed.into_iter().max() else { return Ok(None) };
+
+    let message = if percent >= 100 {
+        format!("{} is fully funded", progress.title)
+    } else {
+        format!("{} has reached {}% of its funding goal", progress.title, percent)
+    };
+    notify_followers(
+        pool,
+        progress.id,
+        "Funding milestone reached",
+        &message,
+        serde_json::json!({"project_id": progress.id, "percent": percent, "funding_goal": progress.funding_goal}),
+        &NotificationEvent::FundingThreshold { project_id: progress.id, percent },
+    )
+    .await?;
+    Ok(Some((progress.id, percent)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reached_thresholds() {
+        assert!(reached_thresholds(0).is_empty());
+        assert!(reached_thresholds(24).is_empty());
+        assert_eq!(reached_thresholds(25), [25]);
+        assert_eq!(reached_thresholds(80), [25, 50, 75]);
+        assert_eq!(reached_thresholds(250), FUNDING_THRESHOLDS);
+    }
+}
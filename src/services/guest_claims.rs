@@ -0,0 +1,234 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::utils::money::Stroops;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClaimError {
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Verify your email address before claiming guest donations")]
+    EmailNotVerified,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for ClaimError {
+    fn from(e: sqlx::Error) -> Self {
+        ClaimError::Internal(e.into())
+    }
+}
+
+/// A guest donation now credited to an account
+#[derive(Debug, Clone, Serialize)]
+pub struct ClaimedDonation {
+    pub guest_donation_id: Uuid,
+    pub project_id: Uuid,
+    pub amount: Stroops,
+    pub tx_hash: Option<String>,
+    pub verified: bool,
+    /// The donation it became in the donor's history; unverified gifts get
+    /// one once their transaction is verified
+    pub donation_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClaimSummary {
+    pub claimed: usize,
+    /// How many are now part of the donor's donation history
+    pub merged: usize,
+    pub total_amount: Stroops,
+    pub donations: Vec<ClaimedDonation>,
+}
+
+/// The form guest emails are matched in
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Unclaimed guest donations made with this email
+pub async fn claimable_count(pool: &PgPool, email: &str) -> anyhow::Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM guest_donations
+        WHERE LOWER(TRIM(guest_email)) = $1 AND claimed_by IS NULL
+        "#,
+        normalize_email(email)
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Credit every unclaimed guest donation made with the user's (verified)
+/// email to their account. Verified gifts join the donor's donation history:
+/// the donation already recorded for the transaction is linked when there is
+/// one, otherwise a confirmed donation is added for it.
+pub async fn claim(pool: &PgPool, user_id: Uuid) -> Result<ClaimSummary, ClaimError> {
+    let user = sqlx::query!("SELECT email, status FROM users WHERE id = $1", user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(ClaimError::UserNotFound)?;
+    if user.status != "active" {
+        return Err(ClaimError::EmailNotVerified);
+    }
+
+    let mut tx = pool.begin().await?;
+    let rows = sqlx::query!(
+        r#"
+        UPDATE guest_donations
+        SET claimed_by = $1, claimed_at = NOW()
+        WHERE id IN (
+            SELECT id FROM guest_donations
+            WHERE LOWER(TRIM(guest_email)) = $2 AND claimed_by IS NULL
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, project_id, tx_hash, amount as "amount: Stroops", verified as "verified!: bool",
+                  created_at as "created_at!: DateTime<Utc>"
+        "#,
+        user_id,
+        normalize_email(&user.email)
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut donations = Vec::with_capacity(rows.len());
+    for row in rows {
+        let mut claimed = ClaimedDonation {
+            guest_donation_id: row.id,
+            project_id: row.project_id,
+            amount: row.amount,
+            tx_hash: row.tx_hash,
+            verified: row.verified,
+            donation_id: None,
+            created_at: row.created_at,
+        };
+        if claimed.verified {
+            claimed.donation_id = merge_into_history(&mut tx, &claimed, user_id).await?;
+        }
+        donations.push(claimed);
+    }
+    tx.commit().await?;
+
+    let total_amount = donations
+        .iter()
+        .try_fold(Stroops::ZERO, |total, d| total.checked_add(d.amount))
+        .ok_or_else(|| anyhow::anyhow!("Claimed total overflows"))?;
+
+    Ok(ClaimSummary {
+        claimed: donations.len(),
+        merged: donations.iter().filter(|d| d.donation_id.is_some()).count(),
+        total_amount,
+        donations,
+    })
+}
+
+/// Bring a claimed guest donation into its donor's history once its
+/// transaction is verified
+pub async fn merge_verified(pool: &PgPool, tx_hash: &str) -> anyhow::Result<Option<Uuid>> {
+    let mut tx = pool.begin().await?;
+    let Some(row) = sqlx::query!(
+        r#"
+        SELECT id, project_id, tx_hash, amount as "amount: Stroops", claimed_by as "claimed_by!",
+               created_at as "created_at!: DateTime<Utc>"
+        FROM guest_donations
+        WHERE tx_hash = $1 AND verified = TRUE AND claimed_by IS NOT NULL AND donation_id IS NULL
+        FOR UPDATE
+        "#,
+        tx_hash
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let claimed = ClaimedDonation {
+        guest_donation_id: row.id,
+        project_id: row.project_id,
+        amount: row.amount,
+        tx_hash: row.tx_hash,
+        verified: true,
+        donation_id: None,
+        created_at: row.created_at,
+    };
+    let donation_id = merge_into_history(&mut tx, &claimed, row.claimed_by).await?;
+    tx.commit().await?;
+
+    Ok(donation_id)
+}
+
+/// Link or record the donation behind a verified guest gift. A transaction
+/// already credited to another donor is left alone.
+async fn merge_into_history(
+    conn: &mut PgConnection,
+    claimed: &ClaimedDonation,
+    donor_id: Uuid,
+) -> anyhow::Result<Option<Uuid>> {
+    let existing = match &claimed.tx_hash {
+        Some(tx_hash) => {
+            sqlx::query!("SELECT id, donor_id FROM donations WHERE tx_hash = $1", tx_hash)
+                .fetch_optional(&mut *conn)
+                .await?
+        }
+        None => None,
+    };
+
+    let donation_id = match existing {
+        Some(donation) if donation.donor_id.is_some_and(|id| id != donor_id) => {
+            tracing::warn!(
+                "Guest donation {} matches donation {} of another donor, not merging",
+                claimed.guest_donation_id, donation.id
+            );
+            return Ok(None);
+        }
+        Some(donation) => {
+            sqlx::query!("UPDATE donations SET donor_id = $1 WHERE id = $2", donor_id, donation.id)
+                .execute(&mut *conn)
+                .await?;
+            donation.id
+        }
+        None => {
+            sqlx::query_scalar!(
+                r#"
+                INSERT INTO donations (donor_id, project_id, amount, tx_hash, status, payment_method, confirmed_at, created_at)
+                VALUES ($1, $2, $3, $4, 'confirmed', 'stellar', $5, $5)
+                RETURNING id
+                "#,
+                donor_id,
+                claimed.project_id,
+                claimed.amount.to_decimal(),
+                claimed.tx_hash,
+                claimed.created_at
+            )
+            .fetch_one(&mut *conn)
+            .await?
+        }
+    };
+
+    sqlx::query!(
+        "UPDATE guest_donations SET donation_id = $1 WHERE id = $2",
+        donation_id,
+        claimed.guest_donation_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Some(donation_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_email() {
+        assert_eq!(normalize_email("  Ada@Example.COM "), "ada@example.com");
+        assert_eq!(normalize_email("ada@example.com"), "ada@example.com");
+    }
+}
@@ -0,0 +1,280 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use reqwest::{header::RETRY_AFTER, Client, Response, StatusCode};
+use serde::Serialize;
+
+use crate::config;
+
+/// Longest single backoff, whatever the attempt count or Retry-After says
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How hard the client tries before giving up on Horizon
+#[derive(Debug, Clone, Copy)]
+pub struct HorizonPolicy {
+    /// Per-attempt timeout, covering connect through the full body
+    pub timeout: Duration,
+    /// Retries after the first attempt for 429s, 5xx responses, and transport errors
+    pub max_retries: u32,
+    /// Delay before the first retry; doubles on each one after
+    pub base_backoff: Duration,
+    /// Consecutive failed requests that open the circuit
+    pub breaker_threshold: u32,
+    /// How long an open circuit fails fast before letting a probe through
+    pub breaker_cooldown: Duration,
+}
+
+impl HorizonPolicy {
+    pub fn from_env() -> Self {
+        Self {
+            timeout: config::env_millis("HORIZON_TIMEOUT_MS", 10_000),
+            max_retries: config::env_u32("HORIZON_MAX_RETRIES", 3),
+            base_backoff: config::env_millis("HORIZON_BACKOFF_MS", 250),
+            breaker_threshold: config::env_u32("HORIZON_BREAKER_THRESHOLD", 5).max(1),
+            breaker_cooldown: config::env_millis("HORIZON_BREAKER_COOLDOWN_MS", 30_000),
+        }
+    }
+
+    /// Delay before retry number `attempt` (0-based), preferring the server's Retry-After
+    fn backoff(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let exponential = self.base_backoff.saturating_mul(1u32 << attempt.min(16));
+        retry_after.unwrap_or(exponential).min(MAX_BACKOFF)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Requests flow normally
+    Closed,
+    /// Horizon is considered down; requests fail without being sent
+    Open,
+    /// Cooldown elapsed; the next request is a probe
+    HalfOpen,
+}
+
+/// Horizon availability as reported by the health endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct HorizonHealth {
+    pub state: BreakerState,
+    pub degraded: bool,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    last_error: Option<String>,
+    last_failure_at: Option<DateTime<Utc>>,
+}
+
+impl Breaker {
+    fn state(&self, policy: &HorizonPolicy) -> BreakerState {
+        match self.opened_at {
+            None => BreakerState::Closed,
+            Some(at) if at.elapsed() >= policy.breaker_cooldown => BreakerState::HalfOpen,
+            Some(_) => BreakerState::Open,
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+    }
+
+    fn record_failure(&mut self, policy: &HorizonPolicy, error: String) {
+        self.consecutive_failures += 1;
+        self.last_error = Some(error);
+        self.last_failure_at = Some(Utc::now());
+        // A failed probe re-opens the circuit for another full cooldown
+        if self.consecutive_failures >= policy.breaker_threshold || self.opened_at.is_some() {
+            self.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// HTTP client for Horizon with per-request timeouts, 429-aware exponential
+/// backoff, and a circuit breaker shared by every clone, so an outage makes
+/// workers fail fast instead of piling up hung requests
+#[derive(Clone)]
+pub struct HorizonClient {
+    http: Client,
+    policy: HorizonPolicy,
+    breaker: Arc<Mutex<Breaker>>,
+}
+
+impl HorizonClient {
+    pub fn new(policy: HorizonPolicy) -> Result<Self> {
+        let http = Client::builder().connect_timeout(policy.timeout).build()?;
+        Ok(Self {
+            http,
+            policy,
+            breaker: Arc::new(Mutex::new(Breaker::default())),
+        })
+    }
+
+    /// GET `url`, retrying rate limits, server errors, and transport failures.
+    /// Other client errors (404 and friends) are returned as responses since
+    /// they are answers, not outages.
+    pub async fn get(&self, url: &str) -> Result<Response> {
+        self.check_breaker()?;
+
+        let mut attempt = 0;
+        loop {
+            let outcome = self.http.get(url).timeout(self.policy.timeout).send().await;
+            let (error, retry_after) = match outcome {
+                Ok(resp) if !is_retryable(resp.status()) => {
+                    self.breaker.lock().unwrap().record_success();
+                    return Ok(resp);
+                }
+                Ok(resp) => (format!("Horizon returned {}", resp.status()), retry_after(&resp)),
+                Err(e) => (format!("Horizon request failed: {}", e), None),
+            };
+
+            if attempt >= self.policy.max_retries {
+                self.record_failure(error.clone());
+                return Err(anyhow!(error));
+            }
+
+            let delay = self.policy.backoff(attempt, retry_after);
+            tracing::debug!("{} for {}; retrying in {:?}", error, url, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Open a long-lived SSE stream. No overall timeout or retries (callers
+    /// reconnect on their own schedule), but an open circuit still fails fast.
+    pub async fn stream(&self, url: &str) -> Result<Response> {
+        self.check_breaker()?;
+
+        match self.http.get(url).header("Accept", "text/event-stream").send().await {
+            Ok(resp) if !is_retryable(resp.status()) => {
+                self.breaker.lock().unwrap().record_success();
+                Ok(resp)
+            }
+            Ok(resp) => {
+                let error = format!("Horizon returned {}", resp.status());
+                self.record_failure(error.clone());
+                Err(anyhow!(error))
+            }
+            Err(e) => {
+                let error = format!("Horizon request failed: {}", e);
+                self.record_failure(error.clone());
+                Err(anyhow!(error))
+            }
+        }
+    }
+
+    pub fn health(&self) -> HorizonHealth {
+        let breaker = self.breaker.lock().unwrap();
+        let state = breaker.state(&self.policy);
+        HorizonHealth {
+            state,
+            degraded: state != BreakerState::Closed,
+            consecutive_failures: breaker.consecutive_failures,
+            last_error: breaker.last_error.clone(),
+            last_failure_at: breaker.last_failure_at,
+        }
+    }
+
+    /// Whether requests are currently being refused without reaching Horizon
+    pub fn is_unavailable(&self) -> bool {
+        self.breaker.lock().unwrap().state(&self.policy) == BreakerState::Open
+    }
+
+    fn check_breaker(&self) -> Result<()> {
+        if self.is_unavailable() {
+            return Err(anyhow!("Horizon is unavailable; circuit open after repeated failures"));
+        }
+        Ok(())
+    }
+
+    fn record_failure(&self, error: String) {
+        let mut breaker = self.breaker.lock().unwrap();
+        let was_open = breaker.opened_at.is_some();
+        breaker.record_failure(&self.policy, error);
+        if !was_open && breaker.opened_at.is_some() {
+            tracing::warn!(
+                "Horizon circuit opened after {} consecutive failures: {}",
+                breaker.consecutive_failures,
+                breaker.last_error.as_deref().unwrap_or_default()
+            );
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Retry-After in seconds; Horizon doesn't send the HTTP-date form
+fn retry_after(resp: &Response) -> Option<Duration> {
+    resp.headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> HorizonPolicy {
+        HorizonPolicy {
+            timeout: Duration::from_secs(1),
+            max_retries: 3,
+            base_backoff: Duration::from_millis(250),
+            breaker_threshold: 2,
+            breaker_cooldown: Duration::from_millis(50),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_honours_retry_after() {
+        let policy = policy();
+        assert_eq!(policy.backoff(0, None), Duration::from_millis(250));
+        assert_eq!(policy.backoff(2, None), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(0, Some(Duration::from_secs(3))), Duration::from_secs(3));
+        assert_eq!(policy.backoff(0, Some(Duration::from_secs(600))), MAX_BACKOFF);
+        assert_eq!(policy.backoff(20, None), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_breaker_opens_and_half_opens() {
+        let policy = policy();
+        let mut breaker = Breaker::default();
+
+        breaker.record_failure(&policy, "503".into());
+        assert_eq!(breaker.state(&policy), BreakerState::Closed);
+        breaker.record_failure(&policy, "503".into());
+        assert_eq!(breaker.state(&policy), BreakerState::Open);
+
+        std::thread::sleep(policy.breaker_cooldown);
+        assert_eq!(breaker.state(&policy), BreakerState::HalfOpen);
+
+        // A failed probe re-opens immediately; a successful one closes
+        breaker.record_failure(&policy, "timeout".into());
+        assert_eq!(breaker.state(&policy), BreakerState::Open);
+        breaker.record_success();
+        assert_eq!(breaker.state(&policy), BreakerState::Closed);
+        assert_eq!(breaker.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+        assert!(!is_retryable(StatusCode::OK));
+    }
+}
@@ -0,0 +1,288 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::services::email::{self, retry_delay};
+
+/// How long a claimed job is left to its runner before another may retry it
+const RUN_LEASE_SECS: i64 = 15 * 60;
+
+/// Work the job runner does, stored as the `payload` of a `jobs` row
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Job {
+    /// Deliver an email from the outbox
+    SendEmail { email_id: Uuid },
+    /// Pay out the reward pools of active and ended campaigns, or of the one
+    /// campaign whose end date queued it
+    DistributeCampaignFunds {
+        requested_by: Option<Uuid>,
+        #[serde(default)]
+        campaign_id: Option<Uuid>,
+    },
+    /// Convert confirmed fiat payments into XLM sent to their projects
+    SettlePayments,
+    /// Ask M-Pesa about STK pushes that never got a callback
+    QueryStuckPayments,
+    /// Check escrow totals against the funding contract
+    ReconcileEscrow,
+}
+
+impl Job {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Job::SendEmail { .. } => "send_email",
+            Job::DistributeCampaignFunds { .. } => "distribute_campaign_funds",
+            Job::SettlePayments => "settle_payments",
+            Job::QueryStuckPayments => "query_stuck_payments",
+            Job::ReconcileEscrow => "reconcile_escrow",
+        }
+    }
+
+    /// Attempts before the job is dead-lettered. Campaign payouts move money
+    /// recipient by recipient, so a failed run waits for an operator rather
+    /// than being repeated; recurring jobs have their next run to fall back on.
+    pub fn max_attempts(&self) -> i32 {
+        match self {
+            Job::SendEmail { .. } => email::MAX_SEND_ATTEMPTS,
+            Job::DistributeCampaignFunds { .. } => 1,
+            Job::SettlePayments | Job::QueryStuckPayments | Job::ReconcileEscrow => 3,
+        }
+    }
+
+    /// The worker switch that pauses jobs of this kind
+    pub fn worker(&self) -> &'static str {
+        match self {
+            Job::SendEmail { .. } => "email_sender",
+            Job::DistributeCampaignFunds { .. } => "campaign_distribution",
+            Job::SettlePayments | Job::QueryStuckPayments => "payment_reconciler",
+            Job::ReconcileEscrow => "escrow_reconciler",
+        }
+    }
+}
+
+/// A job as stored
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRecord {
+    pub id: Uuid,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A job claimed for running. `job` is an error for payloads this build
+/// doesn't understand.
+#[derive(Debug)]
+pub struct ClaimedJob {
+    pub id: Uuid,
+    pub kind: String,
+    pub job: Result<Job, String>,
+    pub attempts: i32,
+    pub max_attempts: i32,
+}
+
+impl ClaimedJob {
+    pub fn is_last_attempt(&self) -> bool {
+        self.attempts >= self.max_attempts
+    }
+}
+
+/// The dedupe key of a recurring job's run covering `now`, so each period
+/// is queued once however many runners there are
+pub fn recurring_key(job: &Job, every: Duration, now: DateTime<Utc>) -> String {
+    let period = every.as_secs().max(1) as i64;
+    format!("{}:{}", job.kind(), now.timestamp().div_euclid(period))
+}
+
+/// Queue a job to run from `run_at`. With a `dedupe_key` it is only queued
+/// once; `None` is returned when it already was.
+pub async fn enqueue<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    job: &Job,
+    run_at: DateTime<Utc>,
+    dedupe_key: Option<&str>,
+) -> Result<Option<Uuid>> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO jobs (kind, payload, max_attempts, run_at, dedupe_key)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (dedupe_key) DO NOTHING
+        RETURNING id
+        "#,
+        job.kind(),
+        serde_json::to_value(job)?,
+        job.max_attempts(),
+        run_at,
+        dedupe_key
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(id)
+}
+
+/// Claim the oldest due job of the given kinds. It is leased so a crashed
+/// runner's job is retried without two runners doing the same one; jobs are
+/// claimed one at a time so the lease runs from when the job starts.
+pub async fn claim_next(pool: &PgPool, kinds: &[&str]) -> Result<Option<ClaimedJob>> {
+    let kinds: Vec<String> = kinds.iter().map(|k| k.to_string()).collect();
+    let row = sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET attempts = attempts + 1, run_at = NOW() + make_interval(secs => $2)
+        WHERE id IN (
+            SELECT id FROM jobs
+            WHERE status = 'queued' AND run_at <= NOW() AND kind = ANY($1)
+            ORDER BY run_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, kind, payload, attempts, max_attempts
+        "#,
+        &kinds,
+        RUN_LEASE_SECS as f64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| ClaimedJob {
+        id: row.id,
+        job: serde_json::from_value(row.payload).map_err(|e| e.to_string()),
+        kind: row.kind,
+        attempts: row.attempts,
+        max_attempts: row.max_attempts,
+    }))
+}
+
+pub async fn mark_succeeded(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query!(
+        "UPDATE jobs SET status = 'succeeded', last_error = NULL, finished_at = NOW() WHERE id = $1",
+        id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a failed attempt, retrying with backoff; returns true once the
+/// job is dead-lettered
+pub async fn mark_failed(pool: &PgPool, job: &ClaimedJob, error: &str) -> Result<bool> {
+    let dead = job.is_last_attempt();
+    let retry_secs = retry_delay(job.attempts).as_secs() as f64;
+    sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET status = CASE WHEN $3 THEN 'dead' ELSE 'queued' END,
+            run_at = CASE WHEN $3 THEN run_at ELSE NOW() + make_interval(secs => $4) END,
+            finished_at = CASE WHEN $3 THEN NOW() END,
+            last_error = $2
+        WHERE id = $1
+        "#,
+        job.id,
+        error,
+        dead,
+        retry_secs
+    )
+    .execute(pool)
+    .await?;
+    Ok(dead)
+}
+
+/// Jobs newest first, optionally only those with `status` (e.g. `dead`)
+pub async fn list(pool: &PgPool, status: Option<&str>, limit: i64) -> Result<Vec<JobRecord>> {
+    let jobs = sqlx::query_as!(
+        JobRecord,
+        r#"
+        SELECT id, kind, payload, status, attempts, max_attempts, run_at, last_error, created_at, finished_at
+        FROM jobs
+        WHERE ($1::text IS NULL OR status = $1)
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+        status,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(jobs)
+}
+
+/// Put a dead-lettered job back in the queue with a fresh set of attempts;
+/// `None` unless it's dead
+pub async fn retry(pool: &PgPool, id: Uuid) -> Result<Option<JobRecord>> {
+    let job = sqlx::query_as!(
+        JobRecord,
+        r#"
+        UPDATE jobs
+        SET status = 'queued', attempts = 0, run_at = NOW(), last_error = NULL, finished_at = NULL
+        WHERE id = $1 AND status = 'dead'
+        RETURNING id, kind, payload, status, attempts, max_attempts, run_at, last_error, created_at, finished_at
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(job)
+}
+
+/// Forget jobs that succeeded more than `days` ago
+pub async fn prune_succeeded(pool: &PgPool, days: i32) -> Result<u64> {
+    let result = sqlx::query!(
+        "DELETE FROM jobs WHERE status = 'succeeded' AND finished_at < NOW() - make_interval(days => $1)",
+        days
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_job_payload_round_trip() {
+        let job = Job::SendEmail { email_id: Uuid::new_v4() };
+        let value = serde_json::to_value(&job).unwrap();
+        assert_eq!(value["kind"], job.kind());
+        assert_eq!(serde_json::from_value::<Job>(value).unwrap(), job);
+
+        // Jobs without fields are just their kind
+        let value = serde_json::to_value(Job::ReconcileEscrow).unwrap();
+        assert_eq!(value, serde_json::json!({"kind": "reconcile_escrow"}));
+        assert!(serde_json::from_value::<Job>(serde_json::json!({"kind": "unknown"})).is_err());
+    }
+
+    #[test]
+    fn test_recurring_key() {
+        let every = Duration::from_secs(300);
+        let start = Utc.with_ymd_and_hms(2025, 11, 3, 8, 0, 0).unwrap();
+        let key = recurring_key(&Job::SettlePayments, every, start);
+        assert!(key.starts_with("settle_payments:"));
+        assert_eq!(recurring_key(&Job::SettlePayments, every, start + chrono::Duration::seconds(299)), key);
+        assert_ne!(recurring_key(&Job::SettlePayments, every, start + chrono::Duration::seconds(300)), key);
+        assert_ne!(recurring_key(&Job::QueryStuckPayments, every, start), key);
+    }
+
+    #[test]
+    fn test_money_moving_jobs_are_not_repeated() {
+        assert_eq!(Job::DistributeCampaignFunds { requested_by: None, campaign_id: None }.max_attempts(), 1);
+        let claimed = ClaimedJob {
+            id: Uuid::new_v4(),
+            kind: "distribute_campaign_funds".to_string(),
+            job: Ok(Job::DistributeCampaignFunds { requested_by: None, campaign_id: None }),
+            attempts: 1,
+            max_attempts: 1,
+        };
+        assert!(claimed.is_last_attempt());
+    }
+}
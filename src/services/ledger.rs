@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::utils::money::{Cents, Stroops};
+
+/// Ledger transaction kinds
+pub const DONATION: &str = "donation";
+pub const FIAT_RECEIPT: &str = "fiat_receipt";
+pub const SETTLEMENT: &str = "settlement";
+pub const PAYOUT: &str = "payout";
+pub const REFUND: &str = "refund";
+pub const FEE: &str = "fee";
+
+/// Account names. Asset accounts are debited when value arrives; the
+/// accounts they are balanced against say whose value it is.
+pub mod accounts {
+    use uuid::Uuid;
+
+    /// XLM held by a Stellar account: the platform wallet, an escrow, or a student wallet
+    pub fn stellar(address: &str) -> String {
+        format!("stellar:{}", address)
+    }
+
+    /// Donations received for a project and not yet paid out
+    pub fn project_donations(project_id: Uuid) -> String {
+        format!("project:{}:donations", project_id)
+    }
+
+    /// Donations made to the platform itself
+    pub fn platform_donations() -> String {
+        "platform:donations".to_string()
+    }
+
+    /// Fiat confirmed for a project but not yet converted to XLM
+    pub fn project_fiat_pending(project_id: Uuid) -> String {
+        format!("project:{}:fiat_pending", project_id)
+    }
+
+    /// Fiat held by a payment provider on the platform's behalf
+    pub fn provider_clearing(provider: &str) -> String {
+        format!("provider:{}:clearing", provider)
+    }
+
+    /// Fiat returned to donors through a payment provider
+    pub fn provider_refunds(provider: &str) -> String {
+        format!("provider:{}:refunds", provider)
+    }
+
+    /// Everything paid out to a student
+    pub fn student_payouts(student_id: Uuid) -> String {
+        format!("student:{}:payouts", student_id)
+    }
+
+    /// Platform fees taken from donations
+    pub fn platform_fee_income() -> String {
+        "platform:fee_income".to_string()
+    }
+
+    /// XLM still held for projects whose donors were refunded in fiat, which
+    /// the platform is owed back
+    pub fn platform_refund_recoveries() -> String {
+        "platform:refund_recoveries".to_string()
+    }
+}
+
+/// Amounts are stored in each currency's smallest unit: stroops for XLM,
+/// cents for fiat
+pub fn format_amount(currency: &str, minor: i64) -> String {
+    match currency {
+        "XLM" => Stroops::from_stroops(minor).to_string(),
+        _ => Cents::from_cents(minor).to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Posting {
+    pub account: String,
+    pub currency: String,
+    pub debit: i64,
+    pub credit: i64,
+}
+
+/// A set of postings that must balance, recorded once per reference
+#[derive(Debug, Clone)]
+pub struct LedgerTransaction {
+    pub kind: String,
+    pub reference_type: String,
+    pub reference_id: String,
+    pub description: Option<String>,
+    pub postings: Vec<Posting>,
+}
+
+impl LedgerTransaction {
+    pub fn new(kind: &str, reference_type: &str, reference_id: impl ToString) -> Self {
+        Self {
+            kind: kind.to_string(),
+            reference_type: reference_type.to_string(),
+            reference_id: reference_id.to_string(),
+            description: None,
+            postings: Vec::new(),
+        }
+    }
+
+    pub fn describe(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn debit(mut self, account: String, currency: &str, amount: i64) -> Self {
+        self.postings.push(Posting { account, currency: currency.to_string(), debit: amount, credit: 0 });
+        self
+    }
+
+    pub fn credit(mut self, account: String, currency: &str, amount: i64) -> Self {
+        self.postings.push(Posting { account, currency: currency.to_string(), debit: 0, credit: amount });
+        self
+    }
+
+    /// Move `amount` from `from` (credited) to `to` (debited)
+    pub fn transfer(self, from: String, to: String, currency: &str, amount: i64) -> Self {
+        self.debit(to, currency, amount).credit(from, currency, amount)
+    }
+
+    /// Every posting is a positive debit or credit, and debits equal credits
+    /// in each currency
+    pub fn check_balanced(&self) -> Result<()> {
+        if self.postings.len() < 2 {
+            return Err(anyhow!("A ledger transaction needs at least two postings"));
+        }
+
+        let mut totals: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+        for posting in &self.postings {
+            let one_sided = (posting.debit > 0 && posting.credit == 0) || (posting.credit > 0 && posting.debit == 0);
+            if !one_sided || posting.debit < 0 || posting.credit < 0 {
+                return Err(anyhow!("Posting to {} must be a positive debit or credit", posting.account));
+            }
+            let total = totals.entry(posting.currency.as_str()).or_default();
+            total.0 = total.0.checked_add(posting.debit).ok_or_else(|| anyhow!("Ledger amount overflow"))?;
+            total.1 = total.1.checked_add(posting.credit).ok_or_else(|| anyhow!("Ledger amount overflow"))?;
+        }
+
+        for (currency, (debits, credits)) in totals {
+            if debits != credits {
+                return Err(anyhow!(
+                    "Unbalanced {} postings: debits {} != credits {}",
+                    currency,
+                    format_amount(currency, debits),
+                    format_amount(currency, credits)
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Post a transaction in its own database transaction. Returns false when
+/// the reference was already posted.
+pub async fn post(pool: &PgPool, entry: &LedgerTransaction) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let posted = post_in(&mut tx, entry).await?;
+    tx.commit().await?;
+    Ok(posted)
+}
+
+/// Post a transaction as part of a caller's database transaction
+pub async fn post_in(conn: &mut PgConnection, entry: &LedgerTransaction) -> Result<bool> {
+    entry.check_balanced()?;
+
+    let transaction_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO ledger_transactions (kind, reference_type, reference_id, description)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (kind, reference_type, reference_id) DO NOTHING
+        RETURNING id
+        "#,
+        entry.kind,
+        entry.reference_type,
+        entry.reference_id,
+        entry.description
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(transaction_id) = transaction_id else {
+        return Ok(false);
+    };
+
+    for posting in &entry.postings {
+        sqlx::query!(
+            r#"
+            INSERT INTO ledger_entries (transaction_id, account, currency, debit, credit)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            transaction_id,
+            posting.account,
+            posting.currency,
+            posting.debit,
+            posting.credit
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(true)
+}
+
+/// A confirmed XLM donation that arrived at `destination`
+pub fn donation_entry(donation_id: Uuid, project_id: Option<Uuid>, destination: &str, amount: Stroops) -> LedgerTransaction {
+    let owner = match project_id {
+        Some(project_id) => accounts::project_donations(project_id),
+        None => accounts::platform_donations(),
+    };
+    LedgerTransaction::new(DONATION, "donation", donation_id)
+        .transfer(owner, accounts::stellar(destination), "XLM", amount.as_stroops())
+}
+
+/// XLM paid to a student from `source`
+pub fn payout_entry(reference_type: &str, reference_id: &str, student_id: Uuid, source: &str, amount: Stroops) -> LedgerTransaction {
+    LedgerTransaction::new(PAYOUT, reference_type, reference_id)
+        .transfer(accounts::stellar(source), accounts::student_payouts(student_id), "XLM", amount.as_stroops())
+}
+
+/// Fiat a provider confirmed for a project, awaiting conversion
+pub fn fiat_receipt_entry(settlement_id: Uuid, provider: &str, project_id: Uuid, currency: &str, amount: Cents) -> LedgerTransaction {
+    LedgerTransaction::new(FIAT_RECEIPT, "fiat_settlement", settlement_id).transfer(
+        accounts::project_fiat_pending(project_id),
+        accounts::provider_clearing(provider),
+        currency,
+        amount.as_cents(),
+    )
+}
+
+/// Fiat converted and sent on-chain: the fiat leaves the provider and the
+/// XLM it bought arrives at `destination` for the project
+pub fn settlement_entry(
+    settlement_id: Uuid,
+    provider: &str,
+    project_id: Uuid,
+    currency: &str,
+    fiat_amount: Cents,
+    destination: &str,
+    xlm_amount: Stroops,
+) -> LedgerTransaction {
+    LedgerTransaction::new(SETTLEMENT, "fiat_settlement", settlement_id)
+        .transfer(
+            accounts::provider_clearing(provider),
+            accounts::project_fiat_pending(project_id),
+            currency,
+            fiat_amount.as_cents(),
+        )
+        .transfer(
+            accounts::project_donations(project_id),
+            accounts::stellar(destination),
+            "XLM",
+            xlm_amount.as_stroops(),
+        )
+}
+
+/// The platform's fee on a donation, taken out of what the project is owed
+pub fn fee_entry(donation_id: Uuid, project_id: Uuid, fee: Stroops) -> LedgerTransaction {
+    LedgerTransaction::new(FEE, "donation", donation_id)
+        .transfer(accounts::platform_fee_income(), accounts::project_donations(project_id), "XLM", fee.as_stroops())
+}
+
+/// Fiat returned to a donor through `provider`. Fiat not yet converted is
+/// taken back from the project it was waiting for (`unsettled_project`);
+/// once converted it is a cost to the platform, recovered by `clawback_entry`.
+pub fn refund_entry(
+    refund_id: Uuid,
+    provider: &str,
+    currency: &str,
+    amount: Cents,
+    unsettled_project: Option<Uuid>,
+) -> LedgerTransaction {
+    let to = match unsettled_project {
+        Some(project_id) => accounts::project_fiat_pending(project_id),
+        None => accounts::provider_refunds(provider),
+    };
+    LedgerTransaction::new(REFUND, "refund", refund_id)
+        .transfer(accounts::provider_clearing(provider), to, currency, amount.as_cents())
+}
+
+/// The XLM a refunded fiat donation bought, no longer owed to the project
+pub fn clawback_entry(refund_id: Uuid, project_id: Uuid, amount: Stroops) -> LedgerTransaction {
+    LedgerTransaction::new(REFUND, "refund_clawback", refund_id).transfer(
+        accounts::platform_refund_recoveries(),
+        accounts::project_donations(project_id),
+        "XLM",
+        amount.as_stroops(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balanced_transaction_per_currency() {
+        let project = Uuid::new_v4();
+        let entry = settlement_entry(
+            Uuid::new_v4(),
+            "mpesa",
+            project,
+            "KES",
+            Cents::from_cents(130_000),
+            "GESCROW",
+            Stroops::from_stroops(100_000_000),
+        );
+        assert!(entry.check_balanced().is_ok());
+        assert_eq!(entry.postings.len(), 4);
+
+        // Balanced in total but not per currency
+        let mixed = LedgerTransaction::new(FEE, "test", "1")
+            .debit(accounts::platform_fee_income(), "XLM", 100)
+            .credit(accounts::stellar("GPLATFORM"), "KES", 100);
+        assert!(mixed.check_balanced().is_err());
+    }
+
+    #[test]
+    fn test_postings_must_be_one_sided_and_positive() {
+        let zero = LedgerTransaction::new(FEE, "test", "1")
+            .debit(accounts::platform_fee_income(), "XLM", 0)
+            .credit(accounts::stellar("GPLATFORM"), "XLM", 0);
+        assert!(zero.check_balanced().is_err());
+
+        let negative = LedgerTransaction::new(FEE, "test", "1")
+            .debit(accounts::platform_fee_income(), "XLM", -5)
+            .credit(accounts::stellar("GPLATFORM"), "XLM", -5);
+        assert!(negative.check_balanced().is_err());
+
+        let single = LedgerTransaction::new(FEE, "test", "1").debit(accounts::platform_fee_income(), "XLM", 5);
+        assert!(single.check_balanced().is_err());
+    }
+
+    #[test]
+    fn test_refund_before_settlement_reverses_the_receipt() {
+        let (settlement, refund, project) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let receipt = fiat_receipt_entry(settlement, "mpesa", project, "KES", Cents::from_cents(50_000));
+        let refund = refund_entry(refund, "mpesa", "KES", Cents::from_cents(50_000), Some(project));
+        assert!(refund.check_balanced().is_ok());
+
+        // Every account the receipt touched nets to zero
+        let mut net: BTreeMap<String, i64> = BTreeMap::new();
+        for posting in receipt.postings.iter().chain(&refund.postings) {
+            *net.entry(posting.account.clone()).or_default() += posting.debit - posting.credit;
+        }
+        assert!(net.values().all(|balance| *balance == 0));
+    }
+
+    #[test]
+    fn test_format_amount_by_currency() {
+        assert_eq!(format_amount("XLM", 25_000_000), Stroops::from_stroops(25_000_000).to_string());
+        assert_eq!(format_amount("KES", 150_050), Cents::from_cents(150_050).to_string());
+    }
+}
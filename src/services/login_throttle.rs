@@ -0,0 +1,257 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::env_u32;
+
+/// Limits on failed logins, from the environment
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottlePolicy {
+    /// Consecutive failures on an account that lock it
+    pub max_failures: i64,
+    pub lockout: Duration,
+    /// Failures, per account or per IP, before each attempt must wait
+    pub account_free_failures: i64,
+    pub ip_free_failures: i64,
+    pub max_backoff_secs: i64,
+    /// Failures older than this are forgotten
+    pub window: Duration,
+    /// Account failures after which a CAPTCHA is asked for, when configured
+    pub captcha_after: i64,
+}
+
+impl ThrottlePolicy {
+    pub fn from_env() -> Self {
+        Self {
+            max_failures: env_u32("LOGIN_MAX_FAILURES", 5) as i64,
+            lockout: Duration::minutes(env_u32("LOGIN_LOCKOUT_MINUTES", 15) as i64),
+            account_free_failures: env_u32("LOGIN_BACKOFF_AFTER", 3) as i64,
+            ip_free_failures: env_u32("LOGIN_IP_BACKOFF_AFTER", 10) as i64,
+            max_backoff_secs: env_u32("LOGIN_MAX_BACKOFF_SECS", 300) as i64,
+            window: Duration::minutes(env_u32("LOGIN_FAILURE_WINDOW_MINUTES", 15) as i64),
+            captcha_after: env_u32("LOGIN_CAPTCHA_AFTER", 3) as i64,
+        }
+    }
+}
+
+/// Recent failures for an account or an IP
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FailureStats {
+    pub failures: i64,
+    pub last_failure: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginGate {
+    Allowed { captcha_required: bool },
+    /// Refused without checking the password
+    Throttled { retry_after_secs: i64, locked: bool },
+}
+
+/// Wait required after `failures`, doubling from one second once the free
+/// attempts are used up
+pub fn backoff_secs(failures: i64, free_failures: i64, max_backoff_secs: i64) -> i64 {
+    if failures < free_failures {
+        return 0;
+    }
+    let exponent = (failures - free_failures).min(30) as u32;
+    2i64.pow(exponent).min(max_backoff_secs)
+}
+
+fn remaining_wait(stats: &FailureStats, free_failures: i64, policy: &ThrottlePolicy, now: DateTime<Utc>) -> i64 {
+    let Some(last_failure) = stats.last_failure else {
+        return 0;
+    };
+    let wait = backoff_secs(stats.failures, free_failures, policy.max_backoff_secs);
+    (wait - (now - last_failure).num_seconds()).max(0)
+}
+
+/// Whether a login may be tried now
+pub fn evaluate(
+    policy: &ThrottlePolicy,
+    account: &FailureStats,
+    ip: &FailureStats,
+    locked_until: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> LoginGate {
+    if let Some(until) = locked_until.filter(|until| *until > now) {
+        return LoginGate::Throttled { retry_after_secs: (until - now).num_seconds().max(1), locked: true };
+    }
+
+    let wait = remaining_wait(account, policy.account_free_failures, policy, now)
+        .max(remaining_wait(ip, policy.ip_free_failures, policy, now));
+    if wait > 0 {
+        return LoginGate::Throttled { retry_after_secs: wait, locked: false };
+    }
+
+    LoginGate::Allowed { captcha_required: account.failures >= policy.captcha_after }
+}
+
+/// Failures for the email since its last successful login
+async fn account_failures(pool: &PgPool, email: &str, since: DateTime<Utc>) -> Result<FailureStats> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "failures!", MAX(created_at) as last_failure
+        FROM login_attempts
+        WHERE email = $1
+          AND NOT succeeded
+          AND created_at > GREATEST($2, COALESCE(
+              (SELECT MAX(created_at) FROM login_attempts WHERE email = $1 AND succeeded), $2))
+        "#,
+        email,
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(FailureStats { failures: row.failures, last_failure: row.last_failure })
+}
+
+/// Failures from the IP across all accounts; a success doesn't clear them
+async fn ip_failures(pool: &PgPool, ip: &str, since: DateTime<Utc>) -> Result<FailureStats> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "failures!", MAX(created_at) as last_failure
+        FROM login_attempts
+        WHERE ip_address = $1 AND NOT succeeded AND created_at > $2
+        "#,
+        ip,
+        since
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(FailureStats { failures: row.failures, last_failure: row.last_failure })
+}
+
+/// Check the account and IP before the password is looked at
+pub async fn check(pool: &PgPool, policy: &ThrottlePolicy, email: &str, ip: Option<&str>) -> Result<LoginGate> {
+    let now = Utc::now();
+    let since = now - policy.window;
+    let account = account_failures(pool, email, since).await?;
+    let ip = match ip {
+        Some(ip) => ip_failures(pool, ip, since).await?,
+        None => FailureStats::default(),
+    };
+    let locked_until = sqlx::query_scalar!("SELECT locked_until FROM users WHERE LOWER(email) = $1", email)
+        .fetch_optional(pool)
+        .await?
+        .flatten();
+
+    Ok(evaluate(policy, &account, &ip, locked_until, now))
+}
+
+/// A login attempt to record
+#[derive(Debug, Clone, Copy)]
+pub struct Attempt<'a> {
+    pub email: &'a str,
+    pub ip: Option<&'a str>,
+    pub user_id: Option<Uuid>,
+    pub succeeded: bool,
+}
+
+/// Record an attempt. A failure that reaches the limit locks the account
+/// and is written to the activity log; returns when the lock ends.
+pub async fn record(pool: &PgPool, policy: &ThrottlePolicy, attempt: Attempt<'_>) -> Result<Option<DateTime<Utc>>> {
+    sqlx::query!(
+        r#"
+        INSERT INTO login_attempts (email, ip_address, user_id, succeeded)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        attempt.email,
+        attempt.ip,
+        attempt.user_id,
+        attempt.succeeded
+    )
+    .execute(pool)
+    .await?;
+
+    let Some(user_id) = attempt.user_id.filter(|_| !attempt.succeeded) else {
+        return Ok(None);
+    };
+    let account = account_failures(pool, attempt.email, Utc::now() - policy.window).await?;
+    if account.failures < policy.max_failures {
+        return Ok(None);
+    }
+
+    let locked_until = Utc::now() + policy.lockout;
+    sqlx::query!("UPDATE users SET locked_until = $1 WHERE id = $2", locked_until, user_id)
+        .execute(pool)
+        .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (user_id, action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        "account_locked",
+        user_id,
+        "user",
+        serde_json::json!({
+            "reason": "failed_logins",
+            "failures": account.failures,
+            "ip_address": attempt.ip,
+            "locked_until": locked_until
+        })
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(Some(locked_until))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ThrottlePolicy {
+        ThrottlePolicy {
+            max_failures: 5,
+            lockout: Duration::minutes(15),
+            account_free_failures: 3,
+            ip_free_failures: 10,
+            max_backoff_secs: 300,
+            window: Duration::minutes(15),
+            captcha_after: 3,
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        assert_eq!(backoff_secs(2, 3, 300), 0);
+        assert_eq!(backoff_secs(3, 3, 300), 1);
+        assert_eq!(backoff_secs(4, 3, 300), 2);
+        assert_eq!(backoff_secs(6, 3, 300), 8);
+        assert_eq!(backoff_secs(40, 3, 300), 300);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let now = Utc::now();
+        let clean = FailureStats::default();
+        assert_eq!(evaluate(&policy(), &clean, &clean, None, now), LoginGate::Allowed { captcha_required: false });
+
+        let recent = FailureStats { failures: 4, last_failure: Some(now - Duration::seconds(1)) };
+        assert_eq!(
+            evaluate(&policy(), &recent, &clean, None, now),
+            LoginGate::Throttled { retry_after_secs: 1, locked: false }
+        );
+
+        let waited = FailureStats { failures: 4, last_failure: Some(now - Duration::seconds(5)) };
+        assert_eq!(evaluate(&policy(), &waited, &clean, None, now), LoginGate::Allowed { captcha_required: true });
+
+        let busy_ip = FailureStats { failures: 12, last_failure: Some(now) };
+        assert_eq!(
+            evaluate(&policy(), &clean, &busy_ip, None, now),
+            LoginGate::Throttled { retry_after_secs: 4, locked: false }
+        );
+
+        assert_eq!(
+            evaluate(&policy(), &clean, &clean, Some(now + Duration::minutes(10)), now),
+            LoginGate::Throttled { retry_after_secs: 600, locked: true }
+        );
+        assert!(matches!(
+            evaluate(&policy(), &clean, &clean, Some(now - Duration::minutes(1)), now),
+            LoginGate::Allowed { .. }
+        ));
+    }
+}
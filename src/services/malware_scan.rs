@@ -0,0 +1,324 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use uuid::Uuid;
+
+use crate::services::notifications::NotificationEvent;
+use crate::services::project_media;
+use crate::services::storage::ObjectStorage;
+use crate::utils::pagination::{Page, PageRequest};
+
+/// Scanner errors tolerated before a file is given up on as `failed`
+pub const MAX_SCAN_ATTEMPTS: i32 = 3;
+/// Longest a single scan may take
+const SCAN_TIMEOUT: Duration = Duration::from_secs(120);
+/// Statuses an upload moves through
+pub const SCAN_STATUSES: &[&str] = &["pending", "clean", "quarantined", "failed"];
+
+/// What a scanner made of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Clean,
+    /// Carries the signature that matched
+    Infected(String),
+}
+
+/// Scans uploads for malware, with a ClamAV daemon or an HTTP scanning API
+#[derive(Clone)]
+pub enum MalwareScanner {
+    /// clamd's INSTREAM command over TCP
+    Clamd { addr: String },
+    /// POSTs the file and reads back `{"infected": bool, "signature": "..."}`
+    Http { client: reqwest::Client, url: String, api_key: Option<String> },
+}
+
+#[derive(Deserialize)]
+struct HttpVerdict {
+    infected: bool,
+    signature: Option<String>,
+}
+
+impl MalwareScanner {
+    /// `MALWARE_SCANNER` is `clamd` (at `CLAMD_ADDR`) or `http` (at
+    /// `MALWARE_SCAN_URL`, with an optional `MALWARE_SCAN_API_KEY`); `None`
+    /// leaves uploads unscanned, and so not downloadable
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(kind) = std::env::var("MALWARE_SCANNER").ok().filter(|k| !k.trim().is_empty()) else {
+            return Ok(None);
+        };
+        match kind.trim().to_lowercase().as_str() {
+            "clamd" | "clamav" => Ok(Some(MalwareScanner::Clamd {
+                addr: std::env::var("CLAMD_ADDR").unwrap_or_else(|_| "127.0.0.1:3310".to_string()),
+            })),
+            "http" => Ok(Some(MalwareScanner::Http {
+                client: reqwest::Client::new(),
+                url: std::env::var("MALWARE_SCAN_URL").map_err(|_| "MALWARE_SCAN_URL must be set".to_string())?,
+                api_key: std::env::var("MALWARE_SCAN_API_KEY").ok().filter(|k| !k.trim().is_empty()),
+            })),
+            other => Err(format!("Unknown MALWARE_SCANNER {}", other)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MalwareScanner::Clamd { .. } => "clamd",
+            MalwareScanner::Http { .. } => "http",
+        }
+    }
+
+    /// Scan a file as it's read from storage
+    pub async fn scan(&self, object: reqwest::Response) -> Result<Verdict> {
+        let scan = async {
+            match self {
+                MalwareScanner::Clamd { addr } => scan_clamd(addr, object).await,
+                MalwareScanner::Http { client, url, api_key } => {
+                    let mut request = client
+                        .post(url)
+                        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                        .body(reqwest::Body::wrap_stream(object.bytes_stream()));
+                    if let Some(key) = api_key {
+                        request = request.bearer_auth(key);
+                    }
+                    let response = request.send().await.context("Scanner request failed")?;
+                    if !response.status().is_success() {
+                        return Err(anyhow!("Scanner returned {}", response.status()));
+                    }
+                    let verdict: HttpVerdict = response.json().await.context("Scanner returned an invalid response")?;
+                    Ok(match verdict.infected {
+                        true => Verdict::Infected(verdict.signature.unwrap_or_else(|| "unknown".to_string())),
+                        false => Verdict::Clean,
+                    })
+                }
+            }
+        };
+        tokio::time::timeout(SCAN_TIMEOUT, scan)
+            .await
+            .map_err(|_| anyhow!("Scan timed out after {}s", SCAN_TIMEOUT.as_secs()))?
+    }
+}
+
+/// Stream the file to clamd in length-prefixed chunks, ended by an empty one
+async fn scan_clamd(addr: &str, mut object: reqwest::Response) -> Result<Verdict> {
+    let mut socket = TcpStream::connect(addr).await.with_context(|| format!("Failed to reach clamd at {}", addr))?;
+    socket.write_all(b"zINSTREAM\0").await?;
+    while let Some(chunk) = object.chunk().await.context("Storage download failed")? {
+        socket.write_all(&(chunk.len() as u32).to_be_bytes()).await?;
+        socket.write_all(&chunk).await?;
+    }
+    socket.write_all(&0u32.to_be_bytes()).await?;
+
+    let mut reply = Vec::new();
+    socket.read_to_end(&mut reply).await?;
+    parse_clamd_reply(&String::from_utf8_lossy(&reply))
+}
+
+/// clamd answers `stream: OK`, `stream: <signature> FOUND` or `... ERROR`
+pub fn parse_clamd_reply(reply: &str) -> Result<Verdict> {
+    let reply = reply.trim_end_matches('\0').trim();
+    let result = reply.strip_prefix("stream:").map(str::trim).unwrap_or(reply);
+    if result == "OK" {
+        return Ok(Verdict::Clean);
+    }
+    if let Some(signature) = result.strip_suffix("FOUND") {
+        return Ok(Verdict::Infected(signature.trim().to_string()));
+    }
+    Err(anyhow!("clamd: {}", reply))
+}
+
+/// A stored file and how its scan went
+#[derive(Debug, Clone, Serialize)]
+pub struct FileScan {
+    pub id: Uuid,
+    pub owner_id: Option<Uuid>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<Uuid>,
+    pub filename: String,
+    pub mime_type: Option<String>,
+    pub size_bytes: Option<i64>,
+    /// `pending`, `clean`, `quarantined` or `failed`
+    pub scan_status: String,
+    /// The signature that matched, or the last scanner error
+    pub scan_result: Option<String>,
+    pub scan_attempts: i32,
+    pub scanned_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Stored files waiting to be scanned, oldest first, as (id, object key)
+pub async fn pending(pool: &PgPool, limit: i64) -> Result<Vec<(Uuid, String)>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, path FROM files
+        WHERE storage = 'object' AND scan_status = 'pending'
+        ORDER BY created_at
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| (r.id, r.path)).collect())
+}
+
+/// Scans, newest first, optionally only those in one status (admin)
+pub async fn list(pool: &PgPool, status: Option<&str>, page: &PageRequest) -> Result<Page<FileScan>> {
+    let scans = sqlx::query_as!(
+        FileScan,
+        r#"
+        SELECT id, owner_id, entity_type, entity_id, filename, mime_type, size_bytes,
+               scan_status, scan_result, scan_attempts, scanned_at, created_at
+        FROM files
+        WHERE storage = 'object'
+          AND ($1::text IS NULL OR scan_status = $1)
+          AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3::uuid))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
+        "#,
+        status,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(Page::new(scans, page, |s| (s.created_at, s.id)))
+}
+
+/// Queue a stored file to be scanned again; `None` if there's no such file
+pub async fn rescan(pool: &PgPool, file_id: Uuid) -> Result<Option<FileScan>> {
+    let scan = sqlx::query_as!(
+        FileScan,
+        r#"
+        UPDATE files
+        SET scan_status = 'pending', scan_result = NULL, scan_attempts = 0, scanned_at = NULL
+        WHERE id = $1 AND storage = 'object'
+        RETURNING id, owner_id, entity_type, entity_id, filename, mime_type, size_bytes,
+                  scan_status, scan_result, scan_attempts, scanned_at, created_at
+        "#,
+        file_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(scan)
+}
+
+/// Record a scanner error, giving up on the file after `MAX_SCAN_ATTEMPTS`
+async fn record_failure(pool: &PgPool, file_id: Uuid, error: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE files
+        SET scan_attempts = scan_attempts + 1,
+            scan_result = $2,
+            scan_status = CASE WHEN scan_attempts + 1 >= $3 THEN 'failed' ELSE scan_status END,
+            scanned_at = NOW()
+        WHERE id = $1
+        "#,
+        file_id,
+        error,
+        MAX_SCAN_ATTEMPTS
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Take an infected image out of its project's public gallery, deleting the
+/// object that was being served
+async fn withdraw_media(pool: &PgPool, storage: &ObjectStorage, file_id: Uuid, key: &str) -> Result<()> {
+    let projects = sqlx::query_scalar!("DELETE FROM project_media WHERE file_id = $1 RETURNING project_id", file_id)
+        .fetch_all(pool)
+        .await?;
+    if projects.is_empty() {
+        return Ok(());
+    }
+    for project_id in projects {
+        project_media::sync_cover(pool, project_id).await?;
+    }
+    storage.delete(key).await
+}
+
+async fn notify_admins(pool: &PgPool, file_id: Uuid, signature: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+        SELECT id, 'system', 'Upload quarantined', 'An uploaded file matched ' || $1 || ' and was quarantined',
+               $2::jsonb, $3::jsonb
+        FROM users
+        WHERE role = 'admin'
+        "#,
+        signature,
+        serde_json::json!({"file_id": file_id, "signature": signature}),
+        NotificationEvent::FileQuarantined { file_id }.to_json()
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Scan one stored file and record the verdict. Infected files are
+/// quarantined, which keeps them from being downloaded; infected gallery
+/// images are also taken down, since they're served publicly.
+pub async fn scan_file(
+    pool: &PgPool,
+    storage: &ObjectStorage,
+    scanner: &MalwareScanner,
+    file_id: Uuid,
+    key: &str,
+) -> Result<Option<Verdict>> {
+    let verdict = match storage.get(key).await {
+        Ok(Some(object)) => scanner.scan(object).await,
+        Ok(None) => Err(anyhow!("Object {} is missing from storage", key)),
+        Err(e) => Err(e),
+    };
+    let verdict = match verdict {
+        Ok(verdict) => verdict,
+        Err(e) => {
+            tracing::warn!("Failed to scan file {}: {}", file_id, e);
+            record_failure(pool, file_id, &e.to_string()).await?;
+            return Ok(None);
+        }
+    };
+
+    let (status, signature) = match &verdict {
+        Verdict::Clean => ("clean", None),
+        Verdict::Infected(signature) => ("quarantined", Some(signature.as_str())),
+    };
+    sqlx::query!(
+        r#"
+        UPDATE files
+        SET scan_status = $2, scan_result = $3, scan_attempts = scan_attempts + 1, scanned_at = NOW()
+        WHERE id = $1
+        "#,
+        file_id,
+        status,
+        signature
+    )
+    .execute(pool)
+    .await?;
+
+    if let Some(signature) = signature {
+        tracing::warn!("Quarantined file {}: {}", file_id, signature);
+        withdraw_media(pool, storage, file_id, key).await?;
+        notify_admins(pool, file_id, signature).await?;
+    }
+    Ok(Some(verdict))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clamd_reply() {
+        assert_eq!(parse_clamd_reply("stream: OK\0").unwrap(), Verdict::Clean);
+        assert_eq!(
+            parse_clamd_reply("stream: Eicar-Test-Signature FOUND\0").unwrap(),
+            Verdict::Infected("Eicar-Test-Signature".to_string())
+        );
+        assert!(parse_clamd_reply("INSTREAM size limit exceeded. ERROR\0").is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::payments::mpesa::{B2cResult, B2cSendError, MpesaProvider};
+use crate::services::{email, outgoing_webhooks};
+use crate::services::ledger::{self, LedgerTransaction};
+use crate::utils::money::{Cents, Stroops};
+
+/// A mobile money payout of a released milestone
+#[derive(Debug, Clone, Serialize)]
+pub struct MobilePayout {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub milestone_id: Uuid,
+    pub student_id: Uuid,
+    pub phone_number: String,
+    pub amount_xlm: Stroops,
+    pub amount_kes: Cents,
+    pub status: String,
+    pub conversation_id: Option<String>,
+    pub receipt: Option<String>,
+    pub result_description: Option<String>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// KES for `amount` at `kes_per_xlm`, rounded down to whole shillings since
+/// that is all B2C can send
+pub fn kes_amount(amount: Stroops, kes_per_xlm: f64) -> Result<Cents> {
+    let cents = Cents::from_f64(amount.to_f64() * kes_per_xlm)?;
+    Ok(Cents::from_cents(cents.as_cents() / 100 * 100))
+}
+
+/// Queue a milestone payout to `phone_number` for admin approval
+pub async fn request_payout(
+    pool: &PgPool,
+    project_id: Uuid,
+    milestone_id: Uuid,
+    phone_number: &str,
+    amount: Stroops,
+    kes_per_xlm: f64,
+) -> Result<MobilePayout> {
+    let amount_kes = kes_amount(amount, kes_per_xlm)?;
+    if !amount_kes.is_positive() {
+        return Err(anyhow!("Milestone amount is too small to pay out by mobile money"));
+    }
+
+    let payout = sqlx::query_as!(
+        MobilePayout,
+        r#"
+        INSERT INTO mobile_payouts (project_id, milestone_id, student_id, phone_number, amount_xlm, amount_kes)
+        SELECT $1, $2, student_id, $3, $4, $5 FROM projects WHERE id = $1
+        RETURNING id, project_id, milestone_id, student_id, phone_number,
+                  amount_xlm as "amount_xlm: Stroops", amount_kes as "amount_kes: Cents", status,
+                  conversation_id, receipt, result_description, created_at
+        "#,
+        project_id,
+        milestone_id,
+        phone_number,
+        amount.to_decimal(),
+        amount_kes.to_decimal()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(payout)
+}
+
+/// Approve a pending payout and send it. The payout is claimed before the
+/// request goes out so two approvals can't both pay; a rejected send marks it
+/// failed. A send whose outcome is unknown leaves it submitted for the
+/// result callback to settle, since M-Pesa may still pay it.
+pub async fn approve_payout(pool: &PgPool, mpesa: &MpesaProvider, payout_id: Uuid, admin_id: Option<Uuid>) -> Result<MobilePayout> {
+    let payout = sqlx::query_as!(
+        MobilePayout,
+        r#"
+        UPDATE mobile_payouts
+        SET status = 'submitted', approved_by = $2, approved_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND status = 'pending_approval'
+        RETURNING id, project_id, milestone_id, student_id, phone_number,
+                  amount_xlm as "amount_xlm: Stroops", amount_kes as "amount_kes: Cents", status,
+                  conversation_id, receipt, result_description, created_at
+        "#,
+        payout_id,
+        admin_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| anyhow!("Payout is not awaiting approval"))?;
+
+    let remarks = format!("Milestone {}", &payout.milestone_id.simple().to_string()[..8]);
+    match mpesa
+        .send_b2c_payment(&payout.id.to_string(), &payout.phone_number, payout.amount_kes, &remarks)
+        .await
+    {
+        Ok(accepted) => {
+            sqlx::query!(
+                "UPDATE mobile_payouts SET conversation_id = $2, result_description = $3, updated_at = NOW() WHERE id = $1",
+                payout.id,
+                accepted.conversation_id,
+                accepted.description
+            )
+            .execute(pool)
+            .await?;
+            Ok(MobilePayout {
+                conversation_id: Some(accepted.conversation_id),
+                result_description: Some(accepted.description),
+                ..payout
+            })
+        }
+        Err(B2cSendError::Unknown(e)) => {
+            tracing::warn!("Outcome of mobile payout {} unknown, awaiting its result: {}", payout.id, e);
+            sqlx::query!(
+                "UPDATE mobile_payouts SET result_description = $2, updated_at = NOW() WHERE id = $1",
+                payout.id,
+                e
+            )
+            .execute(pool)
+            .await?;
+            Ok(MobilePayout {
+                result_description: Some(e),
+                ..payout
+            })
+        }
+        Err(B2cSendError::Rejected(e)) => {
+            sqlx::query!(
+                "UPDATE mobile_payouts SET status = 'failed', result_description = $2, updated_at = NOW() WHERE id = $1",
+                payout.id,
+                e
+            )
+            .execute(pool)
+            .await?;
+            Err(anyhow!(e))
+        }
+    }
+}
+
+pub async fn reject_payout(pool: &PgPool, payout_id: Uuid, admin_id: Option<Uuid>, reason: Option<&str>) -> Result<bool> {
+    let rejected = sqlx::query!(
+        r#"
+        UPDATE mobile_payouts
+        SET status = 'rejected', approved_by = $2, result_description = $3, updated_at = NOW()
+        WHERE id = $1 AND status = 'pending_approval'
+        "#,
+        payout_id,
+        admin_id,
+        reason
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(rejected > 0)
+}
+
+/// Settle a submitted payout from its B2C result. A successful payout
+/// releases its milestone. Returns the payout, or `None` for results that
+/// match no submitted payout (unknown or already settled).
+pub async fn apply_result(pool: &PgPool, result: &B2cResult) -> Result<Option<MobilePayout>> {
+    let Ok(payout_id) = Uuid::parse_str(&result.originator_conversation_id) else {
+        return Ok(None);
+    };
+    let status = if result.successful { "completed" } else { "failed" };
+
+    let mut tx = pool.begin().await?;
+    let payout = sqlx::query_as!(
+        MobilePayout,
+        r#"
+        UPDATE mobile_payouts
+        SET status = $2, receipt = $3, result_description = $4,
+            conversation_id = COALESCE(conversation_id, $5), updated_at = NOW()
+        WHERE id = $1 AND status = 'submitted'
+        RETURNING id, project_id, milestone_id, student_id, phone_number,
+                  amount_xlm as "amount_xlm: Stroops", amount_kes as "amount_kes: Cents", status,
+                  conversation_id, receipt, result_description, created_at
+        "#,
+        payout_id,
+        status,
+        result.receipt,
+        result.description,
+        result.conversation_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(payout) = payout else {
+        return Ok(None);
+    };
+
+    if result.successful {
+        sqlx::query!(
+            "UPDATE milestones SET released = true, released_at = CURRENT_TIMESTAMP WHERE id = $1",
+            payout.milestone_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // B2C pays out of the M-Pesa float, in shillings
+        let entry = LedgerTransaction::new(ledger::PAYOUT, "mobile_payout", payout.id).transfer(
+            ledger::accounts::provider_clearing("mpesa"),
+            ledger::accounts::student_payouts(payout.student_id),
+            "KES",
+            payout.amount_kes.as_cents(),
+        );
+        ledger::post_in(&mut tx, &entry).await?;
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        if result.successful { "milestone_released" } else { "milestone_payout_failed" },
+        payout.milestone_id,
+        "milestone",
+        serde_json::json!({
+            "project_id": payout.project_id,
+            "payout_id": payout.id,
+            "payout_method": "mpesa",
+            "receipt": result.receipt,
+            "amount": payout.amount_xlm,
+            "amount_kes": payout.amount_kes,
+            "result": result.description
+        })
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    if result.successful {
+        if let Err(e) = email::queue_milestone_released(pool, payout.milestone_id, None).await {
+            tracing::error!("Failed to queue milestone release emails for {}: {}", payout.milestone_id, e);
+        }
+        if let Err(e) = outgoing_webhooks::queue_milestone_released(pool, payout.milestone_id, None).await {
+            tracing::error!("Failed to queue milestone release webhooks for {}: {}", payout.milestone_id, e);
+        }
+    }
+    Ok(Some(payout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kes_amount_rounds_down_to_shillings() {
+        let amount: Stroops = "10.5".parse().unwrap();
+        assert_eq!(kes_amount(amount, 15.37).unwrap(), Cents::from_cents(16_100));
+        assert_eq!(kes_amount("0.01".parse().unwrap(), 15.0).unwrap(), Cents::ZERO);
+    }
+
+    #[test]
+    fn test_b2c_result_from_callback() {
+        let body = serde_json::json!({
+            "Result": {
+                "ResultType": 0,
+                "ResultCode": 0,
+                "ResultDesc": "The service request is processed successfully.",
+                "OriginatorConversationID": "7f1b0b8e-3c55-4c5e-8a57-0d9a4a0b1c2d",
+                "ConversationID": "AG_20251021_00004e4c3d2b1a09f8e7",
+                "TransactionID": "NLJ41HAY6Q"
+            }
+        });
+        let result = B2cResult::from_callback(&body).unwrap();
+        assert!(result.successful);
+        assert_eq!(result.receipt.as_deref(), Some("NLJ41HAY6Q"));
+
+        let failed = serde_json::json!({
+            "Result": {
+                "ResultCode": 2001,
+                "ResultDesc": "The initiator information is invalid.",
+                "OriginatorConversationID": "x",
+                "ConversationID": "y",
+                "TransactionID": ""
+            }
+        });
+        let result = B2cResult::from_callback(&failed).unwrap();
+        assert!(!result.successful);
+        assert_eq!(result.receipt, None);
+    }
+}
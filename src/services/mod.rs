@@ -1,8 +1,66 @@
 pub mod stellar;
+pub mod horizon;
+pub mod stellar_api;
 pub mod stellar_service;
+pub mod stellar_tx;
 pub mod notifications;
 pub mod contract_client;
+pub mod donation_memo;
+pub mod sep10;
+pub mod sep7;
+pub mod soroban_rpc;
 pub mod payment_service;
+pub mod escrow;
+pub mod payouts;
+pub mod mobile_payouts;
+pub mod webhook_deliveries;
+pub mod featuring;
+pub mod rates;
+pub mod ledger;
+pub mod fees;
+pub mod refunds;
+pub mod subscriptions;
+pub mod receipts;
+pub mod guest_claims;
+pub mod email;
+pub mod email_verification;
+pub mod flags;
+pub mod password_reset;
+pub mod sessions;
+pub mod login_throttle;
+pub mod two_factor;
+pub mod captcha;
+pub mod rbac;
+pub mod approvals;
+pub mod api_keys;
+pub mod outgoing_webhooks;
+pub mod categories;
+pub mod comments;
+pub mod content_reports;
+pub mod project_updates;
+pub mod follows;
+pub mod completion;
+pub mod analytics_events;
+pub mod announcements;
+pub mod project_refunds;
+pub mod project_members;
+pub mod project_revisions;
+pub mod project_schedule;
+pub mod campaign_schedule;
+pub mod campaign_rules;
+pub mod malware_scan;
+pub mod verification_documents;
+pub mod university_domains;
+pub mod school_email_otp;
+pub mod verification_decisions;
+pub mod preapproved_admissions;
+pub mod project_media;
+pub mod storage;
+pub mod web_push;
+pub mod notification_preferences;
+pub mod digests;
+pub mod jobs;
+pub mod worker_heartbeats;
 
 pub use self::stellar::StellarService;
 pub use self::stellar_service::{StellarService as NewStellarService, WalletInfo, BalanceInfo, TransactionInfo};
\ No newline at end of file
@@ -0,0 +1,270 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Every notification event name, in the order preferences are listed
+pub const EVENT_TYPES: &[&str] = &[
+    "donation_confirmed",
+    "donation_refunded",
+    "refund_status",
+    "funding_threshold",
+    "onchain_deposit",
+    "onchain_release",
+    "milestone_created",
+    "subscription_charged",
+    "subscription_due",
+    "subscription_past_due",
+    "project_status",
+    "project_invite",
+    "project_update",
+    "project_revision",
+    "project_comment",
+    "verification_status",
+    "approval_status",
+    "escrow_drift",
+    "file_quarantined",
+    "content_hidden",
+    "announcement",
+];
+
+/// Event types that also go out by email: donation receipts, verification
+/// decisions, milestone releases, completion reports and announcement digests
+pub const EMAILED: &[&str] = &[
+    "donation_confirmed",
+    "verification_status",
+    "onchain_release",
+    "project_status",
+    "announcement",
+];
+
+/// Event types that can be pushed to browsers (see `web_push::push_text`)
+pub const PUSHED: &[&str] = &["donation_confirmed", "verification_status", "onchain_release"];
+
+/// Staff roles, which publish announcements rather than read them by email
+const STAFF_ROLES: &[&str] = &["admin", "moderator", "finance"];
+
+/// How an event reaches a user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Medium {
+    InApp,
+    Email,
+    Push,
+}
+
+/// A user's own choices for one event type; `None` follows their role's default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Overrides {
+    pub in_app: Option<bool>,
+    pub email: Option<bool>,
+    pub push: Option<bool>,
+}
+
+/// How one event type reaches a user. `email` and `push` are `None` for
+/// event types never sent that way.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Preference {
+    pub event_type: &'static str,
+    pub in_app: bool,
+    pub email: Option<bool>,
+    pub push: Option<bool>,
+}
+
+impl Preference {
+    pub fn allows(&self, medium: Medium) -> bool {
+        match medium {
+            Medium::InApp => self.in_app,
+            Medium::Email => self.email.unwrap_or(false),
+            Medium::Push => self.push.unwrap_or(false),
+        }
+    }
+}
+
+/// Whether `event_type` is ever sent by `medium`
+pub fn offers(event_type: &str, medium: Medium) -> bool {
+    match medium {
+        Medium::InApp => EVENT_TYPES.contains(&event_type),
+        Medium::Email => EMAILED.contains(&event_type),
+        Medium::Push => PUSHED.contains(&event_type),
+    }
+}
+
+/// What a role gets before choosing: everything in-app and by push, and
+/// every email except announcement digests for staff
+pub fn default_for(role: &str, event_type: &'static str) -> Preference {
+    let staff = STAFF_ROLES.contains(&role);
+    Preference {
+        event_type,
+        in_app: true,
+        email: offers(event_type, Medium::Email).then_some(!(staff && event_type == "announcement")),
+        push: offers(event_type, Medium::Push).then_some(true),
+    }
+}
+
+/// A role's default with the user's overrides applied
+pub fn resolve(role: &str, event_type: &'static str, overrides: Overrides) -> Preference {
+    let default = default_for(role, event_type);
+    Preference {
+        event_type,
+        in_app: overrides.in_app.unwrap_or(default.in_app),
+        email: default.email.map(|on| overrides.email.unwrap_or(on)),
+        push: default.push.map(|on| overrides.push.unwrap_or(on)),
+    }
+}
+
+/// Every event type's preference for a user
+pub async fn for_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<Preference>> {
+    let role = sqlx::query_scalar!(r#"SELECT role::text as "role!" FROM users WHERE id = $1"#, user_id)
+        .fetch_one(pool)
+        .await?;
+    let rows = sqlx::query!(
+        "SELECT event_type, in_app, email, push FROM notification_preferences WHERE user_id = $1",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(EVENT_TYPES
+        .iter()
+        .map(|&event_type| {
+            let overrides = rows
+                .iter()
+                .find(|r| r.event_type == event_type)
+                .map(|r| Overrides { in_app: r.in_app, email: r.email, push: r.push })
+                .unwrap_or_default();
+            resolve(&role, event_type, overrides)
+        })
+        .collect())
+}
+
+/// Store a user's choices for an event type; mediums left `None` keep
+/// what they were
+pub async fn update(pool: &PgPool, user_id: Uuid, event_type: &str, overrides: Overrides) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO notification_preferences (user_id, event_type, in_app, email, push)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id, event_type) DO UPDATE
+        SET in_app = COALESCE(EXCLUDED.in_app, notification_preferences.in_app),
+            email = COALESCE(EXCLUDED.email, notification_preferences.email),
+            push = COALESCE(EXCLUDED.push, notification_preferences.push),
+            updated_at = NOW()
+        "#,
+        user_id,
+        event_type,
+        overrides.in_app,
+        overrides.email,
+        overrides.push
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Which of `user_ids` want `event_type` by `medium`
+pub async fn wanting(pool: &PgPool, user_ids: &[Uuid], event_type: &'static str, medium: Medium) -> Result<Vec<Uuid>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT u.id, u.role::text as "role!", np.in_app as "in_app?", np.email as "email?", np.push as "push?"
+        FROM users u
+        LEFT JOIN notification_preferences np ON np.user_id = u.id AND np.event_type = $2
+        WHERE u.id = ANY($1)
+        "#,
+        user_ids,
+        event_type
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|r| {
+            let overrides = Overrides { in_app: r.in_app, email: r.email, push: r.push };
+            resolve(&r.role, event_type, overrides).allows(medium)
+        })
+        .map(|r| r.id)
+        .collect())
+}
+
+/// Whether the account at `email_address` wants `event_type` by email.
+/// Addresses without an account, such as guest donors', always do.
+pub async fn wants_email(pool: &PgPool, email_address: &str, event_type: &'static str) -> Result<bool> {
+    let row = sqlx::query!(
+        r#"
+        SELECT u.role::text as "role!", np.email as "email?"
+        FROM users u
+        LEFT JOIN notification_preferences np ON np.user_id = u.id AND np.event_type = $2
+        WHERE LOWER(u.email) = LOWER($1)
+        LIMIT 1
+        "#,
+        email_address,
+        event_type
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(match row {
+        Some(r) => {
+            let overrides = Overrides { email: r.email, ..Overrides::default() };
+            resolve(&r.role, event_type, overrides).allows(Medium::Email)
+        }
+        None => true,
+    })
+}
+
+/// Event types a user has turned off in-app, which their live streams skip
+pub async fn muted_in_app(pool: &PgPool, user_id: Uuid) -> Result<Vec<String>> {
+    let muted = sqlx::query_scalar!(
+        "SELECT event_type FROM notification_preferences WHERE user_id = $1 AND in_app = FALSE",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(muted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offered_types_are_event_types() {
+        for event_type in EMAILED.iter().chain(PUSHED) {
+            assert!(EVENT_TYPES.contains(event_type), "{} is not an event type", event_type);
+        }
+    }
+
+    #[test]
+    fn test_default_for_role() {
+        let student = default_for("student", "announcement");
+        assert!(student.in_app);
+        assert_eq!(student.email, Some(true));
+        assert_eq!(student.push, None);
+
+        let admin = default_for("admin", "announcement");
+        assert!(admin.in_app);
+        assert_eq!(admin.email, Some(false));
+        assert_eq!(default_for("admin", "donation_confirmed").email, Some(true));
+
+        let comment = default_for("user", "project_comment");
+        assert_eq!((comment.email, comment.push), (None, None));
+    }
+
+    #[test]
+    fn test_resolve_applies_overrides() {
+        let overrides = Overrides { in_app: Some(false), email: Some(false), push: None };
+        let preference = resolve("student", "onchain_release", overrides);
+        assert!(!preference.allows(Medium::InApp));
+        assert!(!preference.allows(Medium::Email));
+        assert!(preference.allows(Medium::Push));
+
+        // Staff can opt back in to digests
+        let digest = resolve("moderator", "announcement", Overrides { email: Some(true), ..Overrides::default() });
+        assert!(digest.allows(Medium::Email));
+
+        // A medium the event never uses stays off whatever is stored
+        let comment = resolve("student", "project_comment", Overrides { email: Some(true), ..Overrides::default() });
+        assert_eq!(comment.email, None);
+        assert!(!comment.allows(Medium::Email));
+    }
+}
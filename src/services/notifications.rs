@@ -1,29 +1,314 @@
-use chrono::{DateTime, Utc};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
 
-#[derive(Clone)]
-pub struct NotificationsService;
+use crate::utils::money::Stroops;
 
-#[derive(Debug, Clone)]
-pub enum NotificationType { Realtime, Email }
+/// Most project topics one SSE connection listens on
+pub const MAX_PROJECT_TOPICS: i64 = 200;
 
-#[derive(Debug, Clone)]
-pub struct NotificationMessage {
-    pub user_id: Option<uuid::Uuid>,
-    pub message: String,
-    pub ntype: NotificationType,
-    pub created_at: DateTime<Utc>,
+/// Who an event goes to over SSE
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// One user's own events
+    User(Uuid),
+    /// Review queues and operational alerts, for admins
+    Admins,
+    /// A project's public activity, for its followers, donors and team
+    Project(Uuid),
+    /// A project's status and review outcomes, for its owner and members
+    ProjectTeam(Uuid),
 }
 
-impl NotificationsService {
-    pub fn new() -> Self { Self }
+/// Something users are told about, in-app and over SSE. Stored with each
+/// notification in `notifications.event` and sent to SSE clients as an
+/// `EventPayload`, so both carry the same fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum NotificationEvent {
+    DonationConfirmed { donation_id: Uuid, project_id: Option<Uuid> },
+    /// A donation paid back, through its provider or on-chain
+    DonationRefunded { refund_id: Uuid, project_id: Option<Uuid> },
+    /// A refund moved through review or processing
+    RefundStatus { refund_id: Uuid, status: String },
+    FundingThreshold { project_id: Uuid, percent: i32 },
+    OnchainDeposit { project_id: Uuid, amount: Stroops },
+    OnchainRelease { project_id: Uuid, milestone_id: String },
+    MilestoneCreated { project_id: Uuid, milestone_id: Uuid },
+    /// A recurring gift was charged or its donor reminded for the month
+    SubscriptionCharged { subscription_id: Uuid, period: chrono::NaiveDate },
+    SubscriptionDue { subscription_id: Uuid, donation_id: Uuid },
+    SubscriptionPastDue { subscription_id: Uuid },
+    /// `scheduled`, `active`, `rejected`, `cancelled`, `completed`, `funded` or `unfunded`
+    ProjectStatus { project_id: Uuid, status: String },
+    ProjectInvite { project_id: Uuid, user_id: Option<Uuid> },
+    ProjectUpdate { project_id: Uuid, update_id: Uuid },
+    /// `pending`, `approved` or `rejected`
+    ProjectRevision { project_id: Uuid, revision_id: Uuid, status: String },
+    ProjectComment { project_id: Uuid, comment_id: Uuid },
+    VerificationStatus { user_id: Uuid, status: String, reason: Option<String> },
+    ApprovalStatus { approval_id: Uuid, status: String },
+    EscrowDrift { project_id: Uuid, contract_drift: Stroops },
+    FileQuarantined { file_id: Uuid },
+    ContentHidden { target_type: String, target_id: Uuid },
+    Announcement { announcement_id: Uuid },
+}
+
+/// The record an event is about
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntityRef {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    pub id: Uuid,
+}
+
+impl EntityRef {
+    fn new(entity_type: &str, id: Uuid) -> Self {
+        Self { entity_type: entity_type.to_string(), id }
+    }
+}
+
+/// What clients get for an event over REST and SSE alike: its fields under
+/// `event`, what it's about and where the app shows it
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPayload {
+    #[serde(flatten)]
+    pub event: NotificationEvent,
+    pub entity: EntityRef,
+    /// Path in the web app, relative to `PUBLIC_BASE_URL`
+    pub link: String,
+}
+
+impl From<NotificationEvent> for EventPayload {
+    fn from(event: NotificationEvent) -> Self {
+        Self { entity: event.entity(), link: event.link(), event }
+    }
+}
+
+impl NotificationEvent {
+    /// The `event` tag, also used as the SSE event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotificationEvent::DonationConfirmed { .. } => "donation_confirmed",
+            NotificationEvent::DonationRefunded { .. } => "donation_refunded",
+            NotificationEvent::RefundStatus { .. } => "refund_status",
+            NotificationEvent::FundingThreshold { .. } => "funding_threshold",
+            NotificationEvent::OnchainDeposit { .. } => "onchain_deposit",
+            NotificationEvent::OnchainRelease { .. } => "onchain_release",
+            NotificationEvent::MilestoneCreated { .. } => "milestone_created",
+            NotificationEvent::SubscriptionCharged { .. } => "subscription_charged",
+            NotificationEvent::SubscriptionDue { .. } => "subscription_due",
+            NotificationEvent::SubscriptionPastDue { .. } => "subscription_past_due",
+            NotificationEvent::ProjectStatus { .. } => "project_status",
+            NotificationEvent::ProjectInvite { .. } => "project_invite",
+            NotificationEvent::ProjectUpdate { .. } => "project_update",
+            NotificationEvent::ProjectRevision { .. } => "project_revision",
+            NotificationEvent::ProjectComment { .. } => "project_comment",
+            NotificationEvent::VerificationStatus { .. } => "verification_status",
+            NotificationEvent::ApprovalStatus { .. } => "approval_status",
+            NotificationEvent::EscrowDrift { .. } => "escrow_drift",
+            NotificationEvent::FileQuarantined { .. } => "file_quarantined",
+            NotificationEvent::ContentHidden { .. } => "content_hidden",
+            NotificationEvent::Announcement { .. } => "announcement",
+        }
+    }
+
+    pub fn entity(&self) -> EntityRef {
+        match self {
+            NotificationEvent::DonationConfirmed { donation_id, .. } => EntityRef::new("donation", *donation_id),
+            NotificationEvent::DonationRefunded { refund_id, .. } | NotificationEvent::RefundStatus { refund_id, .. } => {
+                EntityRef::new("refund", *refund_id)
+            }
+            NotificationEvent::FundingThreshold { project_id, .. }
+            | NotificationEvent::OnchainDeposit { project_id, .. }
+            | NotificationEvent::OnchainRelease { project_id, .. }
+            | NotificationEvent::ProjectStatus { project_id, .. }
+            | NotificationEvent::ProjectInvite { project_id, .. }
+            | NotificationEvent::EscrowDrift { project_id, .. } => EntityRef::new("project", *project_id),
+            NotificationEvent::MilestoneCreated { milestone_id, .. } => EntityRef::new("milestone", *milestone_id),
+            NotificationEvent::SubscriptionCharged { subscription_id, .. }
+            | NotificationEvent::SubscriptionDue { subscription_id, .. }
+            | NotificationEvent::SubscriptionPastDue { subscription_id } => {
+                EntityRef::new("subscription", *subscription_id)
+            }
+            NotificationEvent::ProjectUpdate { update_id, .. } => EntityRef::new("project_update", *update_id),
+            NotificationEvent::ProjectRevision { revision_id, .. } => EntityRef::new("project_revision", *revision_id),
+            NotificationEvent::ProjectComment { comment_id, .. } => EntityRef::new("comment", *comment_id),
+            NotificationEvent::VerificationStatus { user_id, .. } => EntityRef::new("user", *user_id),
+            NotificationEvent::ApprovalStatus { approval_id, .. } => EntityRef::new("approval", *approval_id),
+            NotificationEvent::FileQuarantined { file_id } => EntityRef::new("file", *file_id),
+            NotificationEvent::ContentHidden { target_type, target_id } => EntityRef::new(target_type, *target_id),
+            NotificationEvent::Announcement { announcement_id } => EntityRef::new("announcement", *announcement_id),
+        }
+    }
+
+    /// Where the web app shows what the event is about
+    pub fn link(&self) -> String {
+        match self {
+            NotificationEvent::DonationConfirmed { donation_id, .. } => format!("/donations/{}", donation_id),
+            NotificationEvent::DonationRefunded { .. } => "/donations".to_string(),
+            NotificationEvent::RefundStatus { refund_id, .. } => format!("/admin/refunds/{}", refund_id),
+            NotificationEvent::FundingThreshold { project_id, .. }
+            | NotificationEvent::OnchainDeposit { project_id, .. }
+            | NotificationEvent::ProjectStatus { project_id, .. } => format!("/projects/{}", project_id),
+            NotificationEvent::OnchainRelease { project_id, .. } | NotificationEvent::MilestoneCreated { project_id, .. } => {
+                format!("/projects/{}/milestones", project_id)
+            }
+            NotificationEvent::SubscriptionCharged { subscription_id, .. }
+            | NotificationEvent::SubscriptionDue { subscription_id, .. }
+            | NotificationEvent::SubscriptionPastDue { subscription_id } => format!("/subscriptions/{}", subscription_id),
+            NotificationEvent::ProjectInvite { project_id, .. } => format!("/projects/{}/team", project_id),
+            NotificationEvent::ProjectUpdate { project_id, update_id } => {
+                format!("/projects/{}/updates/{}", project_id, update_id)
+            }
+            NotificationEvent::ProjectRevision { project_id, revision_id, .. } => {
+                format!("/projects/{}/revisions/{}", project_id, revision_id)
+            }
+            NotificationEvent::ProjectComment { project_id, comment_id } => {
+                format!("/projects/{}/comments/{}", project_id, comment_id)
+            }
+            NotificationEvent::VerificationStatus { .. } => "/students/verification".to_string(),
+            NotificationEvent::ApprovalStatus { approval_id, .. } => format!("/admin/approvals/{}", approval_id),
+            NotificationEvent::EscrowDrift { project_id, .. } => format!("/admin/projects/{}/escrow", project_id),
+            NotificationEvent::FileQuarantined { file_id } => format!("/admin/files/{}", file_id),
+            NotificationEvent::ContentHidden { target_type, .. } => format!("/admin/reports?target_type={}", target_type),
+            NotificationEvent::Announcement { announcement_id } => format!("/announcements/{}", announcement_id),
+        }
+    }
 
-    pub async fn send_realtime(&self, _msg: &NotificationMessage) {
-        // TODO: integrate SSE/WebSocket
+    /// For the `notifications.event` column
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("notification events always serialize")
     }
+}
+
+/// The channels a user's SSE connection listens on: their own, the admin
+/// one for admins, team topics for projects they own or have joined and
+/// project topics for those plus the projects they follow or gave to.
+/// Topics are fixed when the connection opens.
+pub async fn channels_for(pool: &PgPool, user_id: Uuid, is_admin: bool) -> Result<Vec<Channel>> {
+    let team = sqlx::query_scalar!(
+        r#"
+        SELECT p.id as "id!" FROM projects p JOIN students s ON s.id = p.student_id WHERE s.user_id = $1
+        UNION
+        SELECT pm.project_id FROM project_members pm JOIN students s ON s.id = pm.student_id
+        WHERE s.user_id = $1 AND pm.status = 'active'
+        LIMIT $2
+        "#,
+        user_id,
+        MAX_PROJECT_TOPICS
+    )
+    .fetch_all(pool)
+    .await?;
+    let watched = sqlx::query_scalar!(
+        r#"
+        SELECT f.project_id as "id!" FROM follows f WHERE f.follower_id = $1 AND f.project_id IS NOT NULL
+        UNION
+        SELECT p.id FROM follows f JOIN projects p ON p.student_id = f.student_id WHERE f.follower_id = $1
+        UNION
+        SELECT d.project_id FROM donations d
+        WHERE d.donor_id = $1 AND d.status = 'confirmed' AND d.project_id IS NOT NULL
+        LIMIT $2
+        "#,
+        user_id,
+        MAX_PROJECT_TOPICS
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(channels(user_id, is_admin, &team, &watched))
+}
+
+/// Whether anyone may follow a project's public activity
+pub async fn is_watchable(pool: &PgPool, project_id: Uuid) -> Result<bool> {
+    let watchable = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM projects WHERE id = $1 AND hidden_at IS NULL) as "exists!""#,
+        project_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(watchable)
+}
 
-    pub async fn send_email(&self, _msg: &NotificationMessage) {
-        // TODO: integrate email provider
+/// Mark one of a user's notifications read; false if they have no such
+/// notification
+pub async fn mark_read(pool: &PgPool, user_id: Uuid, notification_id: Uuid) -> Result<bool> {
+    let result = sqlx::query!(
+        "UPDATE notifications SET is_read = true, updated_at = NOW() WHERE id = $1 AND user_id = $2",
+        notification_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+fn channels(user_id: Uuid, is_admin: bool, team: &[Uuid], watched: &[Uuid]) -> Vec<Channel> {
+    let mut projects: Vec<Uuid> = team.iter().chain(watched).copied().collect();
+    projects.sort();
+    projects.dedup();
+    projects.truncate(MAX_PROJECT_TOPICS as usize);
+
+    let mut channels = vec![Channel::User(user_id)];
+    if is_admin {
+        channels.push(Channel::Admins);
     }
+    channels.extend(team.iter().map(|id| Channel::ProjectTeam(*id)));
+    channels.extend(projects.into_iter().map(Channel::Project));
+    channels
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
 
+    #[test]
+    fn test_serializes_tagged() {
+        let project_id = Uuid::new_v4();
+        let update_id = Uuid::new_v4();
+        let event = NotificationEvent::ProjectUpdate { project_id, update_id };
+        assert_eq!(
+            event.to_json(),
+            json!({"event": "project_update", "project_id": project_id, "update_id": update_id})
+        );
+        assert_eq!(serde_json::from_value::<NotificationEvent>(event.to_json()).unwrap(), event);
+        assert_eq!(event.to_json()["event"], event.name());
+    }
+
+    #[test]
+    fn test_payload() {
+        let project_id = Uuid::new_v4();
+        let comment_id = Uuid::new_v4();
+        let payload = EventPayload::from(NotificationEvent::ProjectComment { project_id, comment_id });
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            json!({
+                "event": "project_comment",
+                "project_id": project_id,
+                "comment_id": comment_id,
+                "entity": {"type": "comment", "id": comment_id},
+                "link": format!("/projects/{}/comments/{}", project_id, comment_id),
+            })
+        );
+    }
+
+    #[test]
+    fn test_channels() {
+        let user = Uuid::new_v4();
+        let (owned, followed) = (Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(channels(user, false, &[], &[]), vec![Channel::User(user)]);
+
+        let all = channels(user, true, &[owned], &[followed, owned]);
+        assert_eq!(all[..3], [Channel::User(user), Channel::Admins, Channel::ProjectTeam(owned)]);
+        assert_eq!(all.iter().filter(|c| **c == Channel::Project(owned)).count(), 1);
+        assert!(all.contains(&Channel::Project(followed)));
+        assert!(!all.contains(&Channel::ProjectTeam(followed)));
+    }
+
+    #[test]
+    fn test_amounts_round_trip() {
+        let event = NotificationEvent::OnchainDeposit { project_id: Uuid::new_v4(), amount: Stroops::from_xlm(25).unwrap() };
+        assert_eq!(serde_json::from_value::<NotificationEvent>(event.to_json()).unwrap(), event);
+    }
+}
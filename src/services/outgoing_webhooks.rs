@@ -0,0 +1,480 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::email::retry_delay;
+use crate::services::email_verification::new_token;
+use crate::utils::money::Stroops;
+
+pub const DONATION_CONFIRMED: &str = "donation.confirmed";
+pub const MILESTONE_RELEASED: &str = "milestone.released";
+pub const VERIFICATION_APPROVED: &str = "verification.approved";
+
+pub const ALL_EVENTS: [&str; 3] = [DONATION_CONFIRMED, MILESTONE_RELEASED, VERIFICATION_APPROVED];
+
+/// `t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`
+pub const SIGNATURE_HEADER: &str = "x-fundhub-signature";
+pub const EVENT_HEADER: &str = "x-fundhub-event";
+/// The `webhook_outbox` row, the same across retries of one delivery
+pub const DELIVERY_HEADER: &str = "x-fundhub-delivery";
+
+/// Attempts before an event is dead-lettered; with `retry_delay` that's
+/// about two hours of retries
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 8;
+
+/// How long a claimed event is left to its dispatcher before another may retry it
+const DELIVERY_LEASE_SECS: i64 = 120;
+
+const SECRET_PREFIX: &str = "whsec_";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEndpoint {
+    pub id: Uuid,
+    /// `None` for partner endpoints
+    pub owner_user_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub name: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub disabled_at: Option<DateTime<Utc>>,
+}
+
+/// A newly registered endpoint; the secret is only returned this once
+#[derive(Debug, Serialize)]
+pub struct CreatedEndpoint {
+    #[serde(flatten)]
+    pub endpoint: WebhookEndpoint,
+    pub secret: String,
+}
+
+pub struct NewEndpoint<'a> {
+    pub owner_user_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub name: &'a str,
+    pub url: &'a str,
+    pub events: &'a [String],
+    pub created_by: Uuid,
+}
+
+/// One endpoint's copy of an event, as shown to admins
+#[derive(Debug, Serialize)]
+pub struct WebhookEvent {
+    pub id: Uuid,
+    pub endpoint_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub last_response_status: Option<i32>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A platform event to fan out to subscribed endpoints. Owner endpoints
+/// only hear about events for their own user and, if narrowed, project.
+pub struct Event<'a> {
+    pub event_type: &'a str,
+    /// Keeps retries of whatever raised the event from sending it twice
+    pub dedupe_key: String,
+    pub owner_user_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub data: serde_json::Value,
+}
+
+/// Events that aren't known, if any
+pub fn unknown_events(events: &[String]) -> Vec<&str> {
+    events
+        .iter()
+        .map(String::as_str)
+        .filter(|e| !ALL_EVENTS.contains(e))
+        .collect()
+}
+
+fn hmac_hex(key: &[u8], message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(message.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Signature header value for `body` sent at `timestamp`. The timestamp is
+/// signed too so receivers can reject replays of old deliveries.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    format!("t={},v1={}", timestamp, hmac_hex(secret.as_bytes(), &format!("{}.{}", timestamp, body)))
+}
+
+/// The JSON body every endpoint receives for an event
+pub fn envelope(event_id: Uuid, event_type: &str, created_at: DateTime<Utc>, data: &serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "id": event_id,
+        "type": event_type,
+        "created_at": created_at,
+        "data": data,
+    })
+}
+
+pub async fn create_endpoint(pool: &PgPool, new: &NewEndpoint<'_>) -> Result<CreatedEndpoint> {
+    let secret = format!("{}{}", SECRET_PREFIX, new_token());
+    let endpoint = sqlx::query_as!(
+        WebhookEndpoint,
+        r#"
+        INSERT INTO webhook_endpoints (owner_user_id, project_id, name, url, secret, events, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, owner_user_id, project_id, name, url, events, active, created_by, created_at, disabled_at
+        "#,
+        new.owner_user_id,
+        new.project_id,
+        new.name,
+        new.url,
+        secret,
+        new.events,
+        new.created_by
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(CreatedEndpoint { endpoint, secret })
+}
+
+/// Endpoints newest first: one owner's, or every endpoint when `owner` is `None`
+pub async fn list_endpoints(pool: &PgPool, owner: Option<Uuid>) -> Result<Vec<WebhookEndpoint>> {
+    let endpoints = sqlx::query_as!(
+        WebhookEndpoint,
+        r#"
+        SELECT id, owner_user_id, project_id, name, url, events, active, created_by, created_at, disabled_at
+        FROM webhook_endpoints
+        WHERE $1::uuid IS NULL OR owner_user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        owner
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(endpoints)
+}
+
+/// Disable an endpoint and dead-letter what it still had pending. `owner`
+/// limits this to that user's endpoints; `None` if there was nothing to disable.
+pub async fn disable_endpoint(pool: &PgPool, id: Uuid, owner: Option<Uuid>) -> Result<Option<WebhookEndpoint>> {
+    let mut tx = pool.begin().await?;
+    let endpoint = sqlx::query_as!(
+        WebhookEndpoint,
+        r#"
+        UPDATE webhook_endpoints
+        SET active = FALSE, disabled_at = NOW()
+        WHERE id = $1 AND active AND ($2::uuid IS NULL OR owner_user_id = $2)
+        RETURNING id, owner_user_id, project_id, name, url, events, active, created_by, created_at, disabled_at
+        "#,
+        id,
+        owner
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if endpoint.is_some() {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_outbox
+            SET status = 'dead', last_error = 'Endpoint disabled'
+            WHERE endpoint_id = $1 AND status = 'pending'
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(endpoint)
+}
+
+/// Queue `event` for every active endpoint subscribed to it; returns how many
+pub async fn enqueue(pool: &PgPool, event: &Event<'_>) -> Result<u64> {
+    let event_id = Uuid::new_v4();
+    let payload = envelope(event_id, event.event_type, Utc::now(), &event.data);
+    let queued = sqlx::query!(
+        r#"
+        INSERT INTO webhook_outbox (endpoint_id, event_id, event_type, payload, dedupe_key)
+        SELECT id, $1, $2::varchar, $3, $4
+        FROM webhook_endpoints
+        WHERE active AND $2::text = ANY(events)
+        AND (owner_user_id IS NULL OR (owner_user_id = $5 AND (project_id IS NULL OR project_id = $6)))
+        ON CONFLICT (endpoint_id, dedupe_key) DO NOTHING
+        "#,
+        event_id,
+        event.event_type,
+        payload,
+        event.dedupe_key,
+        event.owner_user_id,
+        event.project_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(queued)
+}
+
+/// Announce a confirmed donation to its project's owner and to partners
+pub async fn queue_donation_confirmed(pool: &PgPool, donation_id: Uuid) -> Result<u64> {
+    let Some(donation) = sqlx::query!(
+        r#"
+        SELECT d.project_id, d.amount as "amount: Stroops", d.tx_hash, d.confirmed_at, s.user_id as "owner_user_id?"
+        FROM donations d
+        LEFT JOIN projects p ON p.id = d.project_id
+        LEFT JOIN students s ON s.id = p.student_id
+        WHERE d.id = $1 AND d.status = 'confirmed'
+        "#,
+        donation_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(0);
+    };
+
+    // Donor identity stays out of the payload, as it does for any non-admin caller
+    enqueue(pool, &Event {
+        event_type: DONATION_CONFIRMED,
+        dedupe_key: format!("{}:{}", DONATION_CONFIRMED, donation_id),
+        owner_user_id: donation.owner_user_id,
+        project_id: donation.project_id,
+        data: serde_json::json!({
+            "donation_id": donation_id,
+            "project_id": donation.project_id,
+            "amount_xlm": donation.amount,
+            "tx_hash": donation.tx_hash,
+            "confirmed_at": donation.confirmed_at,
+        }),
+    })
+    .await
+}
+
+pub async fn queue_milestone_released(pool: &PgPool, milestone_id: Uuid, tx_hash: Option<&str>) -> Result<u64> {
+    let milestone = sqlx::query!(
+        r#"
+        SELECT m.title, m.project_id, m.target_amount as "amount: Stroops", s.user_id
+        FROM milestones m
+        JOIN projects p ON p.id = m.project_id
+        JOIN students s ON s.id = p.student_id
+        WHERE m.id = $1
+        "#,
+        milestone_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    enqueue(pool, &Event {
+        event_type: MILESTONE_RELEASED,
+        dedupe_key: format!("{}:{}", MILESTONE_RELEASED, milestone_id),
+        owner_user_id: Some(milestone.user_id),
+        project_id: Some(milestone.project_id),
+        data: serde_json::json!({
+            "milestone_id": milestone_id,
+            "project_id": milestone.project_id,
+            "title": milestone.title,
+            "amount_xlm": milestone.amount,
+            "tx_hash": tx_hash,
+        }),
+    })
+    .await
+}
+
+pub async fn queue_verification_approved(pool: &PgPool, verification_id: Uuid, user_id: Uuid) -> Result<u64> {
+    enqueue(pool, &Event {
+        event_type: VERIFICATION_APPROVED,
+        dedupe_key: format!("{}:{}", VERIFICATION_APPROVED, verification_id),
+        owner_user_id: Some(user_id),
+        project_id: None,
+        data: serde_json::json!({
+            "verification_id": verification_id,
+            "user_id": user_id,
+        }),
+    })
+    .await
+}
+
+/// An event claimed for delivery, with what's needed to sign and send it
+#[derive(Debug)]
+pub struct QueuedEvent {
+    pub id: Uuid,
+    pub event_type: String,
+    pub attempts: i32,
+    pub body: String,
+    pub endpoint_name: String,
+    pub url: String,
+    pub secret: String,
+}
+
+/// Claim due events of active endpoints. Each is leased for a couple of
+/// minutes so a crashed dispatcher's events are retried without two
+/// dispatchers delivering the same one.
+pub async fn claim_due(pool: &PgPool, limit: i64) -> Result<Vec<QueuedEvent>> {
+    let rows = sqlx::query!(
+        r#"
+        UPDATE webhook_outbox e
+        SET attempts = e.attempts + 1, next_attempt_at = NOW() + make_interval(secs => $2)
+        FROM webhook_endpoints w
+        WHERE w.id = e.endpoint_id
+        AND e.id IN (
+            SELECT ev.id FROM webhook_outbox ev
+            JOIN webhook_endpoints ep ON ep.id = ev.endpoint_id
+            WHERE ev.status = 'pending' AND ev.next_attempt_at <= NOW() AND ep.active
+            ORDER BY ev.next_attempt_at
+            LIMIT $1
+            FOR UPDATE OF ev SKIP LOCKED
+        )
+        RETURNING e.id, e.event_type, e.attempts, e.payload, w.name, w.url, w.secret
+        "#,
+        limit,
+        DELIVERY_LEASE_SECS as f64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| QueuedEvent {
+            id: row.id,
+            event_type: row.event_type,
+            attempts: row.attempts,
+            body: row.payload.to_string(),
+            endpoint_name: row.name,
+            url: row.url,
+            secret: row.secret,
+        })
+        .collect())
+}
+
+pub async fn mark_delivered(pool: &PgPool, id: Uuid, response_status: i32) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE webhook_outbox
+        SET status = 'delivered', delivered_at = NOW(), last_response_status = $2, last_error = NULL
+        WHERE id = $1
+        "#,
+        id,
+        response_status
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a failed attempt; returns true once the event is dead-lettered
+pub async fn mark_failed(pool: &PgPool, event: &QueuedEvent, response_status: Option<i32>, error: &str) -> Result<bool> {
+    let dead = event.attempts >= MAX_DELIVERY_ATTEMPTS;
+    let retry_secs = retry_delay(event.attempts).as_secs() as f64;
+    sqlx::query!(
+        r#"
+        UPDATE webhook_outbox
+        SET status = CASE WHEN $3 THEN 'dead' ELSE 'pending' END,
+            next_attempt_at = NOW() + make_interval(secs => $4),
+            last_error = $2,
+            last_response_status = $5
+        WHERE id = $1
+        "#,
+        event.id,
+        error,
+        dead,
+        retry_secs,
+        response_status
+    )
+    .execute(pool)
+    .await?;
+    Ok(dead)
+}
+
+/// Events newest first, optionally by status (e.g. `dead`) and endpoint
+pub async fn list_events(
+    pool: &PgPool,
+    status: Option<&str>,
+    endpoint_id: Option<Uuid>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<WebhookEvent>> {
+    let events = sqlx::query_as!(
+        WebhookEvent,
+        r#"
+        SELECT id, endpoint_id, event_id, event_type, payload, status, attempts, next_attempt_at,
+               last_error, last_response_status, delivered_at, created_at
+        FROM webhook_outbox
+        WHERE ($1::text IS NULL OR status = $1)
+        AND ($2::uuid IS NULL OR endpoint_id = $2)
+        ORDER BY created_at DESC
+        LIMIT $3 OFFSET $4
+        "#,
+        status,
+        endpoint_id,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(events)
+}
+
+/// Put a dead-lettered event back in the queue with a fresh set of
+/// attempts; `None` unless it's dead and its endpoint is still active
+pub async fn redeliver(pool: &PgPool, id: Uuid) -> Result<Option<WebhookEvent>> {
+    let event = sqlx::query_as!(
+        WebhookEvent,
+        r#"
+        UPDATE webhook_outbox e
+        SET status = 'pending', attempts = 0, next_attempt_at = NOW(), last_error = NULL
+        FROM webhook_endpoints w
+        WHERE e.id = $1 AND e.status = 'dead' AND w.id = e.endpoint_id AND w.active
+        RETURNING e.id, e.endpoint_id, e.event_id, e.event_type, e.payload, e.status, e.attempts,
+                  e.next_attempt_at, e.last_error, e.last_response_status, e.delivered_at, e.created_at
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_hex() {
+        // A widely published HMAC-SHA256 test vector
+        assert_eq!(
+            hmac_hex(b"key", "The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn test_sign_covers_timestamp_and_body() {
+        let signature = sign("whsec_test", 1_700_000_000, r#"{"type":"donation.confirmed"}"#);
+        let expected = hmac_hex(b"whsec_test", r#"1700000000.{"type":"donation.confirmed"}"#);
+        assert_eq!(signature, format!("t=1700000000,v1={}", expected));
+        assert_ne!(signature, sign("whsec_test", 1_700_000_001, r#"{"type":"donation.confirmed"}"#));
+        assert_ne!(signature, sign("whsec_other", 1_700_000_000, r#"{"type":"donation.confirmed"}"#));
+    }
+
+    #[test]
+    fn test_unknown_events() {
+        let requested = vec![DONATION_CONFIRMED.to_string(), "donation.refunded".to_string()];
+        assert_eq!(unknown_events(&requested), ["donation.refunded"]);
+        assert!(unknown_events(&ALL_EVENTS.map(String::from)).is_empty());
+    }
+
+    #[test]
+    fn test_envelope() {
+        let id = Uuid::new_v4();
+        let body = envelope(id, MILESTONE_RELEASED, Utc::now(), &serde_json::json!({"milestone_id": "m"}));
+        assert_eq!(body["id"], id.to_string());
+        assert_eq!(body["type"], MILESTONE_RELEASED);
+        assert_eq!(body["data"]["milestone_id"], "m");
+    }
+}
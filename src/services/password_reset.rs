@@ -0,0 +1,140 @@
+use anyhow::Result;
+use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+use argon2::Argon2;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::email::{self, EmailTemplate};
+use crate::services::email_verification::{hash_token, new_token};
+use crate::services::sessions;
+
+/// How long a reset link works
+pub const TOKEN_TTL_MINUTES: i64 = 60;
+/// Reset emails one account may be sent per hour
+pub const MAX_REQUESTS_PER_HOUR: i64 = 3;
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResetError {
+    #[error("Reset link is invalid or has expired")]
+    InvalidToken,
+    #[error("Password must be at least 8 characters")]
+    WeakPassword,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for ResetError {
+    fn from(e: sqlx::Error) -> Self {
+        ResetError::Internal(e.into())
+    }
+}
+
+pub fn validate_password(password: &str) -> Result<(), ResetError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(ResetError::WeakPassword);
+    }
+    Ok(())
+}
+
+/// Email a reset link to the account with this address. Returns the user it
+/// went to, or `None` for unknown, suspended or rate-limited accounts so
+/// callers can answer the same either way.
+pub async fn request(pool: &PgPool, email_address: &str) -> Result<Option<Uuid>> {
+    let Some(user) = sqlx::query!(
+        r#"
+        SELECT u.id, u.username, u.email,
+               (SELECT COUNT(*) FROM password_reset_tokens t
+                WHERE t.user_id = u.id AND t.created_at > NOW() - INTERVAL '1 hour') as "recent!"
+        FROM users u
+        WHERE LOWER(u.email) = LOWER($1) AND u.status <> 'suspended'
+        "#,
+        email_address.trim()
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    if user.recent >= MAX_REQUESTS_PER_HOUR {
+        tracing::warn!("Password reset requests for user {} are rate limited", user.id);
+        return Ok(None);
+    }
+
+    let token = new_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+        VALUES ($1, $2, $3)
+        "#,
+        user.id,
+        hash_token(&token),
+        Utc::now() + Duration::minutes(TOKEN_TTL_MINUTES)
+    )
+    .execute(pool)
+    .await?;
+
+    let template = EmailTemplate::PasswordReset {
+        username: user.username,
+        link: email::public_url(&format!("/reset-password?token={}", token)),
+    };
+    email::queue(pool, &user.email, &template, None).await?;
+
+    Ok(Some(user.id))
+}
+
+/// Set a new password with a reset token. The token and any others
+/// outstanding for the user stop working, and every refresh token is revoked
+/// so existing sessions must sign in again.
+pub async fn reset(pool: &PgPool, token: &str, new_password: &str) -> Result<Uuid, ResetError> {
+    validate_password(new_password)?;
+
+    let mut tx = pool.begin().await?;
+    let user_id = sqlx::query_scalar!(
+        r#"
+        SELECT user_id
+        FROM password_reset_tokens
+        WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+        FOR UPDATE
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ResetError::InvalidToken)?;
+
+    let password_hash = Argon2::default()
+        .hash_password(new_password.as_bytes(), &SaltString::generate(&mut OsRng))
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?
+        .to_string();
+
+    // A new password also lifts a lockout from failed logins
+    sqlx::query!("UPDATE users SET password_hash = $1, locked_until = NULL WHERE id = $2", password_hash, user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sessions::revoke_all_in(&mut tx, user_id, None, "password_reset").await?;
+    tx.commit().await?;
+
+    Ok(user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_password() {
+        assert!(matches!(validate_password("short"), Err(ResetError::WeakPassword)));
+        assert!(validate_password("long enough").is_ok());
+        // Counted in characters, not bytes
+        assert!(matches!(validate_password("ééééééé"), Err(ResetError::WeakPassword)));
+    }
+}
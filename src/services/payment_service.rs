@@ -1,14 +1,334 @@
 use crate::routes::payments::provider::*;
-use crate::routes::payments::provider::{MpesaConfig, StripeConfig};
+use crate::routes::payments::provider::{AirtelConfig, FlutterwaveConfig, MpesaB2cConfig, MpesaConfig, PaypalConfig, StripeConfig};
+use crate::services::ledger;
+use crate::utils::money::Cents;
 use anyhow::Result;
-use bigdecimal::BigDecimal;
+use serde::Serialize;
 use sqlx::PgPool;
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
+/// Every provider the registry knows how to build
+pub const PROVIDER_NAMES: [&str; 5] = ["mpesa", "stripe", "flutterwave", "paypal", "airtel"];
+
+/// Where a provider's settings come from: lowercase keys in its
+/// `payment_providers.config` row first, then `<PREFIX>_<KEY>` environment
+/// variables, so a row only needs the values it changes
+pub struct ProviderSettings<'a> {
+    prefix: &'static str,
+    config: Option<&'a serde_json::Value>,
+}
+
+impl<'a> ProviderSettings<'a> {
+    pub fn env(prefix: &'static str) -> Self {
+        Self { prefix, config: None }
+    }
+
+    pub fn with_config(prefix: &'static str, config: &'a serde_json::Value) -> Self {
+        Self { prefix, config: Some(config) }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let from_config = self.config.and_then(|config| match &config[key.to_lowercase()] {
+            serde_json::Value::String(value) => Some(value.clone()),
+            serde_json::Value::Number(value) => Some(value.to_string()),
+            serde_json::Value::Bool(value) => Some(value.to_string()),
+            _ => None,
+        });
+        from_config
+            .filter(|value| !value.is_empty())
+            .or_else(|| std::env::var(format!("{}_{}", self.prefix, key)).ok())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn get_or(&self, key: &str, default: &str) -> String {
+        self.get(key).unwrap_or_else(|| default.to_string())
+    }
+
+    fn tolerance_secs(&self) -> i64 {
+        self.get("WEBHOOK_TOLERANCE_SECS")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(300)
+    }
+}
+
+/// Env var prefix for a provider's settings
+fn settings_prefix(name: &str) -> Option<&'static str> {
+    match name {
+        "mpesa" => Some("MPESA"),
+        "stripe" => Some("STRIPE"),
+        "flutterwave" => Some("FLUTTERWAVE"),
+        "paypal" => Some("PAYPAL"),
+        "airtel" => Some("AIRTEL"),
+        _ => None,
+    }
+}
+
+/// M-Pesa settings; `None` unless the Daraja credentials are set
+pub fn mpesa_config(settings: &ProviderSettings) -> Option<MpesaConfig> {
+    let business_short_code = settings.get("BUSINESS_SHORT_CODE")?;
+
+    let b2c = match (
+        settings.get("B2C_INITIATOR_NAME"),
+        settings.get("B2C_SECURITY_CREDENTIAL"),
+        settings.get("B2C_CALLBACK_TOKEN"),
+    ) {
+        (Some(initiator_name), Some(security_credential), Some(callback_token)) => Some(MpesaB2cConfig {
+            initiator_name,
+            security_credential,
+            short_code: settings.get_or("B2C_SHORT_CODE", &business_short_code),
+            result_url: settings.get_or("B2C_RESULT_URL", "https://your-domain.com/api/payments/mpesa/b2c/result"),
+            timeout_url: settings.get_or("B2C_TIMEOUT_URL", "https://your-domain.com/api/payments/mpesa/b2c/timeout"),
+            reversal_result_url: settings.get_or("REVERSAL_RESULT_URL", "https://your-domain.com/api/payments/mpesa/reversal/result"),
+            reversal_timeout_url: settings.get_or("REVERSAL_TIMEOUT_URL", "https://your-domain.com/api/payments/mpesa/reversal/timeout"),
+            callback_token,
+        }),
+        _ => None,
+    };
+
+    Some(MpesaConfig {
+        consumer_key: settings.get("CONSUMER_KEY")?,
+        consumer_secret: settings.get("CONSUMER_SECRET")?,
+        business_short_code,
+        passkey: settings.get("PASSKEY")?,
+        callback_url: settings.get_or("CALLBACK_URL", "https://your-domain.com/api/payments/mpesa/webhook"),
+        environment: settings.get_or("ENVIRONMENT", "sandbox"),
+        b2c,
+    })
+}
+
+/// M-Pesa settings from the environment alone
+pub fn mpesa_config_from_env() -> Option<MpesaConfig> {
+    mpesa_config(&ProviderSettings::env("MPESA"))
+}
+
+fn stripe_config(settings: &ProviderSettings) -> Option<StripeConfig> {
+    Some(StripeConfig {
+        secret_key: settings.get("SECRET_KEY")?,
+        publishable_key: settings.get("PUBLISHABLE_KEY")?,
+        webhook_secret: settings.get("WEBHOOK_SECRET")?,
+        webhook_tolerance_secs: settings.tolerance_secs(),
+        success_url: settings.get_or("SUCCESS_URL", "https://your-domain.com/success"),
+        cancel_url: settings.get_or("CANCEL_URL", "https://your-domain.com/cancel"),
+        payment_method_types: settings
+            .get_or("PAYMENT_METHOD_TYPES", "card")
+            .split(',')
+            .map(|method| method.trim().to_string())
+            .filter(|method| !method.is_empty())
+            .collect(),
+        payment_method_configuration: settings.get("PAYMENT_METHOD_CONFIGURATION"),
+    })
+}
+
+fn flutterwave_config(settings: &ProviderSettings) -> Option<FlutterwaveConfig> {
+    Some(FlutterwaveConfig {
+        secret_key: settings.get("SECRET_KEY")?,
+        secret_hash: settings.get("SECRET_HASH")?,
+        redirect_url: settings.get_or("REDIRECT_URL", "https://your-domain.com/success"),
+    })
+}
+
+fn paypal_config(settings: &ProviderSettings) -> Option<PaypalConfig> {
+    Some(PaypalConfig {
+        client_id: settings.get("CLIENT_ID")?,
+        client_secret: settings.get("CLIENT_SECRET")?,
+        webhook_id: settings.get("WEBHOOK_ID")?,
+        webhook_tolerance_secs: settings.tolerance_secs(),
+        environment: settings.get_or("ENVIRONMENT", "sandbox"),
+        return_url: settings.get_or("RETURN_URL", "https://your-domain.com/success"),
+        cancel_url: settings.get_or("CANCEL_URL", "https://your-domain.com/cancel"),
+    })
+}
+
+fn airtel_config(settings: &ProviderSettings) -> Option<AirtelConfig> {
+    Some(AirtelConfig {
+        client_id: settings.get("CLIENT_ID")?,
+        client_secret: settings.get("CLIENT_SECRET")?,
+        environment: settings.get_or("ENVIRONMENT", "sandbox"),
+    })
+}
+
+/// Build a provider by name; `None` for unknown names or missing credentials
+pub fn build_provider(name: &str, settings: &ProviderSettings) -> Option<Box<dyn PaymentProvider>> {
+    match name {
+        "mpesa" => mpesa_config(settings).map(PaymentProviderFactory::create_mpesa_provider),
+        "stripe" => stripe_config(settings).map(PaymentProviderFactory::create_stripe_provider),
+        "flutterwave" => flutterwave_config(settings).map(PaymentProviderFactory::create_flutterwave_provider),
+        "paypal" => paypal_config(settings).map(PaymentProviderFactory::create_paypal_provider),
+        "airtel" => airtel_config(settings).map(PaymentProviderFactory::create_airtel_provider),
+        _ => None,
+    }
+}
+
+/// Default provider for a currency among those configured: local mobile
+/// money or cards where we have them, PayPal for everyone else (mostly
+/// international alumni), and Stripe as the fallback
+pub fn choose_provider<'a>(available: &'a [String], currency: &str, has_phone: bool) -> Option<&'a str> {
+    let preferences: &[&str] = match currency.to_uppercase().as_str() {
+        "KES" if has_phone => &["mpesa", "airtel", "stripe", "paypal"],
+        "UGX" if has_phone => &["airtel", "stripe", "paypal"],
+        "NGN" | "GHS" => &["flutterwave", "stripe", "paypal"],
+        _ => &["paypal", "stripe"],
+    };
+    preferences
+        .iter()
+        .find_map(|preferred| available.iter().find(|p| p == preferred))
+        .map(|p| p.as_str())
+}
+
+/// A provider the registry has loaded
+#[derive(Clone)]
+struct LoadedProvider {
+    provider: Arc<dyn PaymentProvider>,
+    /// Upper-case ISO codes; empty means any
+    currencies: Vec<String>,
+    countries: Vec<String>,
+    /// "database" when a `payment_providers` row configured it, else "environment"
+    source: &'static str,
+}
+
+/// What `GET /api/payments/providers` and the admin listing show about a loaded provider
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderSummary {
+    pub name: String,
+    pub currencies: Vec<String>,
+    pub countries: Vec<String>,
+    pub source: String,
+}
+
+#[derive(Default)]
+struct RegistryState {
+    providers: HashMap<String, LoadedProvider>,
+    mpesa: Option<MpesaConfig>,
+    stripe: Option<StripeConfig>,
+}
+
+/// Payment providers built from `payment_providers` rows, falling back to
+/// environment variables for providers without a row. Loaded at startup and
+/// rebuilt whenever an admin changes a row, so providers can be switched on
+/// and off without a redeploy. Clones share the same providers.
+#[derive(Clone)]
+pub struct ProviderRegistry {
+    pool: PgPool,
+    state: Arc<RwLock<RegistryState>>,
+}
+
+impl ProviderRegistry {
+    pub async fn load(pool: PgPool) -> Result<Self> {
+        let registry = Self {
+            pool,
+            state: Arc::new(RwLock::new(RegistryState::default())),
+        };
+        registry.reload().await?;
+        Ok(registry)
+    }
+
+    /// Rebuild every provider from the current rows and environment
+    pub async fn reload(&self) -> Result<()> {
+        let rows = sqlx::query!(
+            "SELECT name, enabled, config, currencies, countries FROM payment_providers"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut rows: HashMap<String, _> = rows.into_iter().map(|row| (row.name.clone(), row)).collect();
+
+        let mut state = RegistryState::default();
+        for name in PROVIDER_NAMES {
+            let prefix = settings_prefix(name).expect("every provider has a settings prefix");
+            let row = rows.remove(name);
+            if row.as_ref().is_some_and(|row| !row.enabled) {
+                continue;
+            }
+            let (settings, currencies, countries, source) = match &row {
+                Some(row) => (
+                    ProviderSettings::with_config(prefix, &row.config),
+                    row.currencies.iter().map(|c| c.to_uppercase()).collect(),
+                    row.countries.iter().map(|c| c.to_uppercase()).collect(),
+                    "database",
+                ),
+                None => (ProviderSettings::env(prefix), Vec::new(), Vec::new(), "environment"),
+            };
+
+            let Some(provider) = build_provider(name, &settings) else {
+                if row.is_some() {
+                    tracing::warn!("Payment provider {} is enabled but missing credentials", name);
+                }
+                continue;
+            };
+            match name {
+                "mpesa" => state.mpesa = mpesa_config(&settings),
+                "stripe" => state.stripe = stripe_config(&settings),
+                _ => {}
+            }
+            state.providers.insert(name.to_string(), LoadedProvider {
+                provider: Arc::from(provider),
+                currencies,
+                countries,
+                source,
+            });
+        }
+
+        for name in rows.keys() {
+            tracing::warn!("Ignoring unknown payment provider '{}' in payment_providers", name);
+        }
+
+        let loaded: Vec<&String> = state.providers.keys().collect();
+        tracing::info!("Payment providers loaded: {:?}", loaded);
+        *self.state.write().unwrap() = state;
+        Ok(())
+    }
+
+    /// A service over the providers loaded right now
+    pub fn service(&self) -> PaymentService {
+        let state = self.state.read().unwrap();
+        PaymentService {
+            pool: self.pool.clone(),
+            providers: state
+                .providers
+                .iter()
+                .map(|(name, loaded)| (name.clone(), loaded.provider.clone()))
+                .collect(),
+            currencies: state
+                .providers
+                .iter()
+                .map(|(name, loaded)| (name.clone(), loaded.currencies.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn summaries(&self) -> Vec<ProviderSummary> {
+        let state = self.state.read().unwrap();
+        let mut summaries: Vec<ProviderSummary> = state
+            .providers
+            .iter()
+            .map(|(name, loaded)| ProviderSummary {
+                name: name.clone(),
+                currencies: loaded.currencies.clone(),
+                countries: loaded.countries.clone(),
+                source: loaded.source.to_string(),
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.name.cmp(&b.name));
+        summaries
+    }
+
+    /// Settings of the loaded M-Pesa provider, for B2C payouts
+    pub fn mpesa_config(&self) -> Option<MpesaConfig> {
+        self.state.read().unwrap().mpesa.clone()
+    }
+
+    /// Settings of the loaded Stripe provider, for charging saved cards
+    pub fn stripe_config(&self) -> Option<StripeConfig> {
+        self.state.read().unwrap().stripe.clone()
+    }
+}
+
 pub struct PaymentService {
     pool: PgPool,
-    providers: HashMap<String, Box<dyn PaymentProvider>>,
+    providers: HashMap<String, Arc<dyn PaymentProvider>>,
+    /// Currencies each provider is limited to; empty or missing means any
+    currencies: HashMap<String, Vec<String>>,
 }
 
 impl PaymentService {
@@ -16,56 +336,29 @@ impl PaymentService {
         Self {
             pool,
             providers: HashMap::new(),
+            currencies: HashMap::new(),
         }
     }
 
-    /// Initialize payment providers from environment variables
+    /// Initialize payment providers from environment variables alone
     pub fn initialize_providers(&mut self) -> Result<()> {
-        // Initialize M-Pesa provider if configured
-        if let (Ok(consumer_key), Ok(consumer_secret), Ok(business_short_code), Ok(passkey)) = (
-            std::env::var("MPESA_CONSUMER_KEY"),
-            std::env::var("MPESA_CONSUMER_SECRET"),
-            std::env::var("MPESA_BUSINESS_SHORT_CODE"),
-            std::env::var("MPESA_PASSKEY"),
-        ) {
-            let mpesa_config = MpesaConfig {
-                consumer_key,
-                consumer_secret,
-                business_short_code,
-                passkey,
-                callback_url: std::env::var("MPESA_CALLBACK_URL")
-                    .unwrap_or_else(|_| "https://your-domain.com/api/payments/mpesa/webhook".to_string()),
-                environment: std::env::var("MPESA_ENVIRONMENT")
-                    .unwrap_or_else(|_| "sandbox".to_string()),
-            };
-            
-            let mpesa_provider = PaymentProviderFactory::create_mpesa_provider(mpesa_config);
-            self.providers.insert("mpesa".to_string(), mpesa_provider);
-        }
-
-        // Initialize Stripe provider if configured
-        if let (Ok(secret_key), Ok(publishable_key), Ok(webhook_secret)) = (
-            std::env::var("STRIPE_SECRET_KEY"),
-            std::env::var("STRIPE_PUBLISHABLE_KEY"),
-            std::env::var("STRIPE_WEBHOOK_SECRET"),
-        ) {
-            let stripe_config = StripeConfig {
-                secret_key,
-                publishable_key,
-                webhook_secret,
-                success_url: std::env::var("STRIPE_SUCCESS_URL")
-                    .unwrap_or_else(|_| "https://your-domain.com/success".to_string()),
-                cancel_url: std::env::var("STRIPE_CANCEL_URL")
-                    .unwrap_or_else(|_| "https://your-domain.com/cancel".to_string()),
-            };
-            
-            let stripe_provider = PaymentProviderFactory::create_stripe_provider(stripe_config);
-            self.providers.insert("stripe".to_string(), stripe_provider);
+        for name in PROVIDER_NAMES {
+            let prefix = settings_prefix(name).expect("every provider has a settings prefix");
+            if let Some(provider) = build_provider(name, &ProviderSettings::env(prefix)) {
+                self.providers.insert(name.to_string(), Arc::from(provider));
+            }
         }
 
         Ok(())
     }
 
+    /// Whether `provider_name` takes payments in `currency`
+    pub fn supports_currency(&self, provider_name: &str, currency: &str) -> bool {
+        self.currencies
+            .get(provider_name)
+            .is_none_or(|currencies| currencies.is_empty() || currencies.iter().any(|c| c.eq_ignore_ascii_case(currency)))
+    }
+
     /// Initiate payment with specified provider
     pub async fn initiate_payment(
         &self,
@@ -74,57 +367,105 @@ impl PaymentService {
     ) -> Result<PaymentInstruction, String> {
         let provider = self.providers.get(provider_name)
             .ok_or_else(|| format!("Payment provider '{}' not found", provider_name))?;
+        if !self.supports_currency(provider_name, &request.currency) {
+            return Err(format!("Payment provider '{}' does not accept {}", provider_name, request.currency));
+        }
 
+        let (project_id, amount, currency) = (request.project_id, request.amount, request.currency.to_uppercase());
+        let donor_email = request.donor_email.clone();
         let instruction = provider.initiate_payment(request).await?;
         
         // Store payment instruction in database
-        self.store_payment_instruction(&instruction).await
+        self.store_payment_instruction(&instruction, project_id, amount, &currency, &donor_email).await
             .map_err(|e| e.to_string())?;
         
         Ok(instruction)
     }
 
-    /// Process webhook from payment provider
+    /// Process webhook from payment provider. `payload` is the body exactly as
+    /// received, which is what providers sign. Returns `None` for an event id
+    /// that has already been processed.
     pub async fn process_webhook(
         &self,
         provider_name: &str,
         webhook: ProviderWebhook,
-    ) -> Result<VerificationResult, String> {
+        payload: &str,
+        received_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Option<VerificationResult>, String> {
         let provider = self.providers.get(provider_name)
             .ok_or_else(|| format!("Payment provider '{}' not found", provider_name))?;
 
         // Validate webhook signature
         let signature = webhook.signature.clone().unwrap_or_default();
-        if !provider.validate_webhook(&serde_json::to_string(&webhook.raw_data).unwrap_or_default(), 
-                                   &signature) {
+        if !provider.validate_webhook(payload, &signature, received_at).await {
             return Err("Invalid webhook signature".to_string());
         }
 
-        let verification = provider.verify_payment(webhook).await?;
-        
-        // Update donation status in database
-        self.update_donation_status(&verification).await
-            .map_err(|e| e.to_string())?;
-        
-        Ok(verification)
+        // Claim the event before applying it so concurrent retries can't both
+        // process it; the claim is released if processing fails
+        let event_id = webhook.event_id.clone();
+        if let Some(event_id) = &event_id {
+            if !self.claim_event(provider_name, event_id, &webhook.event_type).await.map_err(|e| e.to_string())? {
+                return Ok(None);
+            }
+        }
+
+        let result = async {
+            let verification = provider.verify_payment(webhook).await?;
+
+            // Update donation status in database
+            self.update_donation_status(&verification).await
+                .map_err(|e| e.to_string())?;
+
+            Ok(verification)
+        }
+        .await;
+
+        if let (Err(_), Some(event_id)) = (&result, &event_id) {
+            if let Err(e) = self.release_event(provider_name, event_id).await {
+                eprintln!("Failed to release webhook event {}: {}", event_id, e);
+            }
+        }
+
+        result.map(Some)
     }
 
-    /// Process refund
+    /// Send a refund to the provider. Validating and recording it is up to
+    /// `services::refunds`.
     pub async fn process_refund(
         &self,
         provider_name: &str,
         request: RefundRequest,
-    ) -> Result<String, String> {
+    ) -> Result<RefundResult, String> {
         let provider = self.providers.get(provider_name)
             .ok_or_else(|| format!("Payment provider '{}' not found", provider_name))?;
 
-        let refund_id = provider.refund(request.clone()).await?;
-        
-        // Update refund status in database
-        self.record_refund(&refund_id, &request).await
+        provider.refund(request).await
+    }
+
+    /// Ask the provider for the current status of a payment whose callback
+    /// hasn't arrived, and settle it if the provider has a final answer
+    pub async fn refresh_payment_status(&self, provider_name: &str, payment_id: &str) -> Result<PaymentStatus, String> {
+        let provider = self.providers.get(provider_name)
+            .ok_or_else(|| format!("Payment provider '{}' not found", provider_name))?;
+
+        let status = provider.get_payment_status(payment_id).await?;
+        if matches!(status, PaymentStatus::Pending | PaymentStatus::Processing) {
+            return Ok(status);
+        }
+
+        let verification = VerificationResult {
+            payment_id: payment_id.to_string(),
+            status: status.clone(),
+            amount: Cents::ZERO,
+            currency: String::new(),
+            transaction_id: None,
+            provider_response: serde_json::json!({ "source": "status_query", "status": status }),
+        };
+        self.update_donation_status(&verification).await
             .map_err(|e| e.to_string())?;
-        
-        Ok(refund_id)
+
+        Ok(status)
     }
 
     /// Get available payment providers
@@ -132,18 +473,42 @@ impl PaymentService {
         self.providers.keys().cloned().collect()
     }
 
+    /// Pick a provider for a donor who didn't name one
+    pub fn select_provider(&self, currency: &str, donor_phone: Option<&str>) -> Option<String> {
+        let available: Vec<String> = self
+            .get_available_providers()
+            .into_iter()
+            .filter(|provider| self.supports_currency(provider, currency))
+            .collect();
+        choose_provider(&available, currency, donor_phone.is_some())
+            .map(|provider| provider.to_string())
+    }
+
     /// Store payment instruction in database
-    async fn store_payment_instruction(&self, instruction: &PaymentInstruction) -> Result<()> {
+    /// Store a payment instruction with what was charged, for which project
+    /// and by whom, which its settlement and any refund need later
+    async fn store_payment_instruction(
+        &self,
+        instruction: &PaymentInstruction,
+        project_id: Uuid,
+        amount: Cents,
+        currency: &str,
+        donor_email: &str,
+    ) -> Result<()> {
         sqlx::query!(
             r#"
             INSERT INTO payment_instructions 
-            (payment_id, payment_method, instructions, expires_at, created_at)
-            VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+            (payment_id, payment_method, instructions, expires_at, project_id, amount, currency, donor_email, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, CURRENT_TIMESTAMP)
             "#,
             instruction.payment_id,
             instruction.payment_method,
             serde_json::to_value(&instruction.instructions)?,
-            instruction.expires_at
+            instruction.expires_at,
+            project_id,
+            amount.to_decimal(),
+            currency,
+            donor_email
         )
         .execute(&self.pool)
         .await?;
@@ -155,7 +520,7 @@ impl PaymentService {
     async fn update_donation_status(&self, verification: &VerificationResult) -> Result<()> {
         let status = match verification.status {
             PaymentStatus::Completed => "confirmed",
-            PaymentStatus::Failed | PaymentStatus::Cancelled => "failed",
+            PaymentStatus::Failed | PaymentStatus::Cancelled | PaymentStatus::Expired => "failed",
             PaymentStatus::Processing => "processing",
             _ => "pending",
         };
@@ -174,21 +539,94 @@ impl PaymentService {
         .execute(&self.pool)
         .await?;
 
+        let instruction_status = match verification.status {
+            PaymentStatus::Completed => "completed",
+            PaymentStatus::Failed => "failed",
+            PaymentStatus::Cancelled => "cancelled",
+            PaymentStatus::Expired => "expired",
+            PaymentStatus::Pending | PaymentStatus::Processing => return Ok(()),
+        };
+        sqlx::query!(
+            r#"
+            UPDATE payment_instructions
+            SET status = $1, resolved_at = NOW(), provider_reference = COALESCE($3, provider_reference)
+            WHERE payment_id = $2 AND status = 'pending'
+            "#,
+            instruction_status,
+            verification.payment_id,
+            verification.transaction_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        if matches!(verification.status, PaymentStatus::Completed) {
+            self.queue_settlement(verification).await?;
+        }
+
         Ok(())
     }
 
-    /// Record refund in database
-    async fn record_refund(&self, refund_id: &str, request: &RefundRequest) -> Result<()> {
-        sqlx::query!(
+    /// Queue a completed payment for conversion to XLM by the payment
+    /// reconciler. The provider's confirmed amount wins over what was asked
+    /// for; payments started before instructions recorded a project are skipped.
+    async fn queue_settlement(&self, verification: &VerificationResult) -> Result<()> {
+        let confirmed_amount = verification.amount.is_positive().then(|| verification.amount.to_decimal());
+        let queued = sqlx::query!(
+            r#"
+            INSERT INTO fiat_settlements (payment_id, provider, fiat_amount, fiat_currency, project_id)
+            SELECT payment_id, payment_method, COALESCE($2, amount), COALESCE(currency, UPPER($3)), project_id
+            FROM payment_instructions
+            WHERE payment_id = $1 AND project_id IS NOT NULL AND COALESCE($2, amount) IS NOT NULL
+            ON CONFLICT (provider, payment_id) DO NOTHING
+            RETURNING id, provider, project_id, fiat_amount, fiat_currency
+            "#,
+            verification.payment_id,
+            confirmed_amount,
+            verification.currency
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if let Some(settlement) = queued {
+            tracing::info!("Queued settlement for payment {}", verification.payment_id);
+            if let Some(project_id) = settlement.project_id {
+                let entry = ledger::fiat_receipt_entry(
+                    settlement.id,
+                    &settlement.provider,
+                    project_id,
+                    &settlement.fiat_currency,
+                    Cents::from_decimal(&settlement.fiat_amount)?,
+                );
+                ledger::post(&self.pool, &entry).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record an event as processed; false if it already was
+    async fn claim_event(&self, provider_name: &str, event_id: &str, event_type: &str) -> Result<bool> {
+        let claimed = sqlx::query!(
             r#"
-            INSERT INTO refunds 
-            (refund_id, payment_id, amount, reason, created_at)
-            VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+            INSERT INTO webhook_events (provider, event_id, event_type)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (provider, event_id) DO NOTHING
             "#,
-            refund_id,
-            request.payment_id,
-            request.amount.map(|amt| BigDecimal::from(amt as i64) / BigDecimal::from(100)),
-            request.reason
+            provider_name,
+            event_id,
+            event_type
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(claimed > 0)
+    }
+
+    async fn release_event(&self, provider_name: &str, event_id: &str) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM webhook_events WHERE provider = $1 AND event_id = $2",
+            provider_name,
+            event_id
         )
         .execute(&self.pool)
         .await?;
@@ -211,4 +649,38 @@ mod tests {
         let result = service.initialize_providers();
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_provider_settings_prefer_config_over_env() {
+        std::env::set_var("FLUTTERWAVE_TEST_SECRET_KEY", "from-env");
+        std::env::set_var("FLUTTERWAVE_TEST_REDIRECT_URL", "https://env.example/done");
+        let config = serde_json::json!({ "secret_key": "from-config", "timeout": 30, "redirect_url": "" });
+        let settings = ProviderSettings::with_config("FLUTTERWAVE_TEST", &config);
+
+        assert_eq!(settings.get("SECRET_KEY").as_deref(), Some("from-config"));
+        assert_eq!(settings.get("TIMEOUT").as_deref(), Some("30"));
+        // Empty config values fall through to the environment
+        assert_eq!(settings.get("REDIRECT_URL").as_deref(), Some("https://env.example/done"));
+        assert_eq!(settings.get("SECRET_HASH"), None);
+        assert!(build_provider("flutterwave", &settings).is_none());
+        assert!(build_provider("unknown", &settings).is_none());
+    }
+
+    #[test]
+    fn test_choose_provider() {
+        let available: Vec<String> = ["stripe", "mpesa", "flutterwave", "paypal"].iter().map(|p| p.to_string()).collect();
+        assert_eq!(choose_provider(&available, "KES", true), Some("mpesa"));
+        assert_eq!(choose_provider(&available, "KES", false), Some("paypal"));
+        assert_eq!(choose_provider(&available, "ngn", false), Some("flutterwave"));
+        assert_eq!(choose_provider(&available, "USD", false), Some("paypal"));
+
+        let airtel: Vec<String> = ["airtel", "stripe"].iter().map(|p| p.to_string()).collect();
+        assert_eq!(choose_provider(&airtel, "KES", true), Some("airtel"));
+        assert_eq!(choose_provider(&airtel, "UGX", true), Some("airtel"));
+        assert_eq!(choose_provider(&airtel, "UGX", false), Some("stripe"));
+
+        let stripe_only = vec!["stripe".to_string()];
+        assert_eq!(choose_provider(&stripe_only, "GHS", true), Some("stripe"));
+        assert_eq!(choose_provider(&[], "USD", false), None);
+    }
 }
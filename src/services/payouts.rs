@@ -0,0 +1,103 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::{ledger, stellar, stellar_tx::TxSubmitter};
+use crate::utils::money::Stroops;
+
+/// How a student was paid
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payout {
+    /// Sent straight to a connected, funded wallet
+    Paid { destination: String, tx_hash: String },
+    /// Locked in a claimable balance addressed to the student's key
+    Claimable { claimant: String, tx_hash: String, balance_id: String },
+}
+
+impl Payout {
+    pub fn tx_hash(&self) -> &str {
+        match self {
+            Payout::Paid { tx_hash, .. } | Payout::Claimable { tx_hash, .. } => tx_hash,
+        }
+    }
+}
+
+/// The key a student's payouts go to. Wallets linked through the student
+/// record or the student's user account both count; a connected one wins.
+pub async fn student_wallet(pool: &PgPool, student_id: Uuid) -> Result<Option<(String, bool)>> {
+    let wallet = sqlx::query!(
+        r#"
+        SELECT w.public_key, w.status
+        FROM wallets w
+        JOIN students s ON s.id = $1
+        WHERE w.student_id = s.id OR w.user_id = s.user_id
+        ORDER BY (w.status = 'connected') DESC, w.last_synced_at DESC NULLS LAST
+        LIMIT 1
+        "#,
+        student_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(wallet.map(|w| (w.public_key, w.status == "connected")))
+}
+
+/// Pay a student from the platform account. Connected wallets whose account
+/// exists are paid directly; otherwise the funds go into a claimable balance
+/// for the student's key, recorded against `source_type`/`source_id`.
+/// Returns `None` when the student has no wallet key at all.
+pub async fn pay_student(
+    pool: &PgPool,
+    payments: &TxSubmitter,
+    student_id: Uuid,
+    amount: Stroops,
+    memo: Option<&str>,
+    source_type: &str,
+    source_id: Option<Uuid>,
+) -> Result<Option<Payout>> {
+    let Some((public_key, connected)) = student_wallet(pool, student_id).await? else {
+        return Ok(None);
+    };
+
+    if connected && payments.account_exists(&stellar::base_account(&public_key)).await? {
+        let tx_hash = payments.pay(&public_key, amount, memo).await?;
+        post_payout(pool, payments, source_type, &tx_hash, student_id, amount).await;
+        return Ok(Some(Payout::Paid { destination: public_key, tx_hash }));
+    }
+
+    let claimant = stellar::base_account(&public_key);
+    let balance = payments.create_claimable_balance(&claimant, amount, memo).await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO claimable_balances (balance_id, student_id, claimant, amount, source_type, source_id, tx_hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        balance.balance_id,
+        student_id,
+        claimant,
+        amount.to_decimal(),
+        source_type,
+        source_id,
+        balance.tx_hash
+    )
+    .execute(pool)
+    .await?;
+
+    post_payout(pool, payments, source_type, &balance.tx_hash, student_id, amount).await;
+
+    Ok(Some(Payout::Claimable {
+        claimant,
+        tx_hash: balance.tx_hash,
+        balance_id: balance.balance_id,
+    }))
+}
+
+/// Post a sent payout to the ledger. The XLM has already left, so a failure
+/// here is logged rather than failing the payout.
+async fn post_payout(pool: &PgPool, payments: &TxSubmitter, source_type: &str, tx_hash: &str, student_id: Uuid, amount: Stroops) {
+    let entry = ledger::payout_entry(source_type, tx_hash, student_id, &payments.public_key(), amount);
+    if let Err(e) = ledger::post(pool, &entry).await {
+        tracing::error!("Failed to post payout {} to the ledger: {}", tx_hash, e);
+    }
+}
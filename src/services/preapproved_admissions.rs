@@ -0,0 +1,285 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::university_domains;
+use crate::services::verification_decisions;
+use crate::utils::pagination::{Page, PageRequest};
+
+/// Rows one import may hold
+pub const MAX_IMPORT_ROWS: usize = 5000;
+/// Recorded on verifications approved by a match
+pub const MATCH_MESSAGE: &str = "Verified automatically from the school's pre-approved admission list";
+
+/// A student the school has vouched for, by admission number
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdmissionRow {
+    pub admission_number: String,
+    /// The exact domain of the school's email addresses
+    pub school_domain: String,
+    pub full_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Line {line}: {message}")]
+pub struct CsvError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreapprovedAdmission {
+    pub id: Uuid,
+    pub admission_number: String,
+    pub school_domain: String,
+    pub full_name: Option<String>,
+    pub imported_by: Option<Uuid>,
+    pub matched_verification_id: Option<Uuid>,
+    pub matched_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A verification approved by matching a row
+#[derive(Debug, Clone, Serialize)]
+pub struct Match {
+    pub verification_id: Uuid,
+    pub user_id: Uuid,
+}
+
+/// What an import did
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportSummary {
+    pub rows: usize,
+    pub imported: u64,
+    /// Rows already on the list
+    pub duplicates: u64,
+    /// Pending verifications the list now matches, which were approved
+    pub matched: Vec<Match>,
+}
+
+/// Admission numbers compare without case or surrounding space
+pub fn normalize_admission(raw: &str) -> String {
+    raw.trim().to_uppercase()
+}
+
+/// Split one CSV line into fields, honouring double quotes
+fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("Unterminated quote".to_string());
+    }
+    fields.push(field);
+    Ok(fields.into_iter().map(|f| f.trim().to_string()).collect())
+}
+
+/// Parse a CSV with a header naming `admission_number` and `school_domain`
+/// columns, and optionally `full_name`, in any order
+pub fn parse_csv(text: &str) -> Result<Vec<AdmissionRow>, CsvError> {
+    let mut lines = text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+    let error = |line: usize, message: &str| CsvError { line: line + 1, message: message.to_string() };
+
+    let (header_line, header) = lines.next().ok_or_else(|| error(0, "The file is empty"))?;
+    let header = split_line(header.trim_start_matches('\u{feff}')).map_err(|e| error(header_line, &e))?;
+    let column = |name: &str| header.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let admission = column("admission_number").ok_or_else(|| error(header_line, "Missing an admission_number column"))?;
+    let domain = column("school_domain").ok_or_else(|| error(header_line, "Missing a school_domain column"))?;
+    let name = column("full_name");
+
+    let mut rows = Vec::new();
+    for (line, text) in lines {
+        let fields = split_line(text).map_err(|e| error(line, &e))?;
+        let field = |i: usize| fields.get(i).map(String::as_str).unwrap_or_default();
+
+        let admission_number = normalize_admission(field(admission));
+        if admission_number.is_empty() || admission_number.len() > 50 {
+            return Err(error(line, "Admission number must be 1 to 50 characters"));
+        }
+        let school_domain = university_domains::normalize_pattern(field(domain))
+            .filter(|d| !d.starts_with("*."))
+            .ok_or_else(|| error(line, "School domain must be a domain like uonbi.ac.ke"))?;
+        let full_name = name.map(field).filter(|n| !n.is_empty()).map(str::to_string);
+        rows.push(AdmissionRow { admission_number, school_domain, full_name });
+        if rows.len() > MAX_IMPORT_ROWS {
+            return Err(error(line, &format!("Import at most {} rows at a time", MAX_IMPORT_ROWS)));
+        }
+    }
+    Ok(rows)
+}
+
+/// Add rows to the list, skipping ones already on it, then approve pending
+/// verifications they match
+pub async fn import(pool: &PgPool, rows: &[AdmissionRow], admin_id: Uuid) -> Result<ImportSummary> {
+    let mut imported = 0;
+    for row in rows {
+        imported += sqlx::query!(
+            r#"
+            INSERT INTO preapproved_admissions (admission_number, school_domain, full_name, imported_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (school_domain, admission_number) DO NOTHING
+            "#,
+            row.admission_number,
+            row.school_domain,
+            row.full_name,
+            admin_id
+        )
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+    let matched = match_pending(pool).await?;
+    Ok(ImportSummary { rows: rows.len(), imported, duplicates: rows.len() as u64 - imported, matched })
+}
+
+/// Imported rows, newest first
+pub async fn list(pool: &PgPool, page: &PageRequest) -> Result<Page<PreapprovedAdmission>> {
+    let rows = sqlx::query_as!(
+        PreapprovedAdmission,
+        r#"
+        SELECT id, admission_number, school_domain, full_name, imported_by,
+               matched_verification_id, matched_at, created_at
+        FROM preapproved_admissions
+        WHERE ($1::timestamptz IS NULL OR (created_at, id) < ($1, $2::uuid))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $3
+        "#,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(Page::new(rows, page, |r| (Some(r.created_at), r.id)))
+}
+
+/// Approve a verification against the row it matches, claiming the row, and
+/// return its applicant; `None` if either was taken in the meantime
+async fn approve_match(pool: &PgPool, verification_id: Uuid, admission_id: Uuid) -> Result<Option<Uuid>> {
+    let mut tx = pool.begin().await?;
+    let claimed = sqlx::query!(
+        r#"
+        UPDATE preapproved_admissions
+        SET matched_verification_id = $2, matched_at = NOW()
+        WHERE id = $1 AND matched_verification_id IS NULL
+        "#,
+        admission_id,
+        verification_id
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+    if claimed == 0 {
+        return Ok(None);
+    }
+    let Some(user_id) = verification_decisions::approve_in(&mut tx, verification_id, None, Some(MATCH_MESSAGE)).await? else {
+        return Ok(None);
+    };
+    tx.commit().await?;
+    Ok(Some(user_id))
+}
+
+/// Pending, email-confirmed verifications with an unclaimed matching row:
+/// same admission number, and a school email at the row's domain
+async fn matches(pool: &PgPool, verification_id: Option<Uuid>) -> Result<Vec<(Uuid, Uuid)>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT DISTINCT ON (sv.id) sv.id as verification_id, pa.id as admission_id
+        FROM student_verifications sv
+        JOIN preapproved_admissions pa
+          ON pa.admission_number = UPPER(TRIM(sv.admission_number))
+         AND pa.school_domain = LOWER(SPLIT_PART(sv.school_email, '@', 2))
+        WHERE sv.status = 'pending'
+          AND sv.school_email_confirmed_at IS NOT NULL
+          AND pa.matched_verification_id IS NULL
+          AND ($1::uuid IS NULL OR sv.id = $1)
+        ORDER BY sv.id, pa.created_at
+        "#,
+        verification_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| (r.verification_id, r.admission_id)).collect())
+}
+
+/// Approve every pending verification a row matches
+pub async fn match_pending(pool: &PgPool) -> Result<Vec<Match>> {
+    let mut approved = Vec::new();
+    for (verification_id, admission_id) in matches(pool, None).await? {
+        if let Some(user_id) = approve_match(pool, verification_id, admission_id).await? {
+            approved.push(Match { verification_id, user_id });
+        }
+    }
+    Ok(approved)
+}
+
+/// Approve one verification if a row matches it, returning its applicant
+pub async fn match_verification(pool: &PgPool, verification_id: Uuid) -> Result<Option<Uuid>> {
+    for (verification_id, admission_id) in matches(pool, Some(verification_id)).await? {
+        if let Some(user_id) = approve_match(pool, verification_id, admission_id).await? {
+            return Ok(Some(user_id));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_line() {
+        assert_eq!(split_line("a, b ,c").unwrap(), vec!["a", "b", "c"]);
+        assert_eq!(split_line(r#""Otieno, Jane","say ""hi""",x"#).unwrap(), vec!["Otieno, Jane", r#"say "hi""#, "x"]);
+        assert_eq!(split_line("a,,").unwrap(), vec!["a", "", ""]);
+        assert!(split_line(r#""open"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = "\u{feff}full_name,School_Domain,admission_number\n\"Otieno, Jane\",UoNBI.ac.ke, p15/1234/2021 \n\n,uonbi.ac.ke,P15/9/2022\n";
+        let rows = parse_csv(csv).unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                AdmissionRow {
+                    admission_number: "P15/1234/2021".to_string(),
+                    school_domain: "uonbi.ac.ke".to_string(),
+                    full_name: Some("Otieno, Jane".to_string()),
+                },
+                AdmissionRow {
+                    admission_number: "P15/9/2022".to_string(),
+                    school_domain: "uonbi.ac.ke".to_string(),
+                    full_name: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_errors() {
+        assert_eq!(parse_csv("").unwrap_err().line, 1);
+        assert!(parse_csv("admission_number\nA1").unwrap_err().message.contains("school_domain"));
+        let err = parse_csv("admission_number,school_domain\nA1,uonbi.ac.ke\n,uonbi.ac.ke").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert_eq!(parse_csv("admission_number,school_domain\nA1,*.ac.ke").unwrap_err().line, 2);
+    }
+}
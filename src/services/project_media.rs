@@ -0,0 +1,422 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Method;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::storage::{self, NewFile, ObjectStorage, PresignedUrl};
+
+pub const KIND_IMAGE: &str = "image";
+pub const KIND_VIDEO: &str = "video";
+/// Most items one project's gallery can hold
+pub const MAX_MEDIA_PER_PROJECT: i64 = 20;
+/// Largest image that can be uploaded
+pub const MAX_IMAGE_BYTES: i64 = 10 * 1024 * 1024;
+/// How long a presigned upload URL stays usable
+pub const UPLOAD_URL_TTL_MINUTES: i64 = 15;
+const IMAGE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/webp", "image/gif"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum MediaError {
+    #[error("A project's gallery holds at most {MAX_MEDIA_PER_PROJECT} items")]
+    GalleryFull,
+    #[error("Images must be JPEG, PNG, WebP or GIF, not {0}")]
+    UnsupportedType(String),
+    #[error("Images must be at most {} MB", MAX_IMAGE_BYTES / (1024 * 1024))]
+    TooLarge,
+    #[error("The upload hasn't reached storage yet")]
+    NotUploaded,
+    #[error("The uploaded file doesn't match its checksum")]
+    ChecksumMismatch,
+    #[error("The order must list each of the project's media exactly once")]
+    InvalidOrder,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for MediaError {
+    fn from(e: sqlx::Error) -> Self {
+        MediaError::Internal(e.into())
+    }
+}
+
+/// An image or video link in a project's gallery
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectMedia {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    /// `image` or `video`
+    pub kind: String,
+    pub url: String,
+    pub caption: Option<String>,
+    pub position: i32,
+    /// `pending` until a presigned upload is confirmed, then `ready`
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A file to be uploaded straight to storage
+#[derive(Debug, Clone)]
+pub struct NewUpload {
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// Hex SHA-256 the upload is checked against once it's in storage
+    pub checksum: Option<String>,
+    pub caption: Option<String>,
+}
+
+/// A gallery item waiting for its file, and where to send it
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingUpload {
+    pub media: ProjectMedia,
+    pub upload: PresignedUrl,
+    /// Send with the upload
+    pub content_type: String,
+}
+
+/// Images the gallery takes
+pub fn check_image(content_type: &str, size_bytes: i64) -> Result<(), MediaError> {
+    let content_type = content_type.trim().to_lowercase();
+    if !IMAGE_TYPES.contains(&content_type.as_str()) {
+        return Err(MediaError::UnsupportedType(content_type));
+    }
+    if size_bytes > MAX_IMAGE_BYTES {
+        return Err(MediaError::TooLarge);
+    }
+    Ok(())
+}
+
+/// Whether `order` lists exactly the ids in `current`, each once
+pub fn is_permutation(current: &[Uuid], order: &[Uuid]) -> bool {
+    let mut current = current.to_vec();
+    let mut order = order.to_vec();
+    current.sort();
+    order.sort();
+    current == order
+}
+
+/// A project's gallery in order; pending uploads only when asked for
+pub async fn list(pool: &PgPool, project_id: Uuid, include_pending: bool) -> Result<Vec<ProjectMedia>> {
+    let media = sqlx::query_as!(
+        ProjectMedia,
+        r#"
+        SELECT id, project_id, kind, url, caption, position, status, created_at
+        FROM project_media
+        WHERE project_id = $1 AND ($2 OR status = 'ready')
+        ORDER BY position, created_at
+        "#,
+        project_id,
+        include_pending
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(media)
+}
+
+/// The next position at the end of the gallery, refusing once it's full
+async fn next_position(pool: &PgPool, project_id: Uuid) -> Result<i32, MediaError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "count!", COALESCE(MAX(position) + 1, 0) as "next!"
+        FROM project_media
+        WHERE project_id = $1
+        "#,
+        project_id
+    )
+    .fetch_one(pool)
+    .await?;
+    if row.count >= MAX_MEDIA_PER_PROJECT {
+        return Err(MediaError::GalleryFull);
+    }
+    Ok(row.next)
+}
+
+/// Keep `projects.media_url` pointing at the first ready image
+pub async fn sync_cover(pool: &PgPool, project_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE projects
+        SET media_url = (
+            SELECT url FROM project_media
+            WHERE project_id = $1 AND kind = 'image' AND status = 'ready'
+            ORDER BY position, created_at
+            LIMIT 1
+        )
+        WHERE id = $1
+        "#,
+        project_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// A gallery row about to be added
+struct NewMedia<'a> {
+    kind: &'a str,
+    file_id: Option<Uuid>,
+    url: &'a str,
+    caption: Option<&'a str>,
+    status: &'a str,
+}
+
+async fn insert(pool: &PgPool, project_id: Uuid, media: NewMedia<'_>, created_by: Uuid) -> Result<ProjectMedia, MediaError> {
+    let position = next_position(pool, project_id).await?;
+    let media = sqlx::query_as!(
+        ProjectMedia,
+        r#"
+        INSERT INTO project_media (project_id, kind, file_id, url, caption, position, status, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, project_id, kind, url, caption, position, status, created_at
+        "#,
+        project_id,
+        media.kind,
+        media.file_id,
+        media.url,
+        media.caption,
+        position,
+        media.status,
+        created_by
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(media)
+}
+
+async fn insert_file(pool: &PgPool, owner_id: Uuid, project_id: Uuid, key: &str, upload: &NewUpload) -> Result<Uuid> {
+    let file = NewFile {
+        owner_id,
+        entity_type: "project_media",
+        entity_id: project_id,
+        key,
+        filename: &upload.filename,
+        mime_type: &upload.content_type,
+        size_bytes: upload.size_bytes,
+        checksum: upload.checksum.as_deref(),
+    };
+    storage::record_file(pool, &file).await
+}
+
+/// Add a link to an image or video hosted elsewhere
+pub async fn add_link(
+    pool: &PgPool,
+    project_id: Uuid,
+    kind: &str,
+    url: &str,
+    caption: Option<&str>,
+    created_by: Uuid,
+) -> Result<ProjectMedia, MediaError> {
+    let media = insert(pool, project_id, NewMedia { kind, file_id: None, url, caption, status: "ready" }, created_by).await?;
+    sync_cover(pool, project_id).await?;
+    Ok(media)
+}
+
+/// Store an image sent through the API and add it to the gallery
+pub async fn store_image(
+    pool: &PgPool,
+    storage: &ObjectStorage,
+    project_id: Uuid,
+    mut upload: NewUpload,
+    body: Vec<u8>,
+    created_by: Uuid,
+) -> Result<ProjectMedia, MediaError> {
+    check_image(&upload.content_type, body.len() as i64)?;
+    next_position(pool, project_id).await?;
+
+    let key = storage::object_key(&format!("projects/{}/media", project_id), Uuid::new_v4(), &upload.filename);
+    let stored = storage.put(&key, &upload.content_type, body).await?;
+    upload.checksum = Some(stored.checksum);
+    let file_id = insert_file(pool, created_by, project_id, &key, &upload).await?;
+    let url = storage.public_url(&key);
+    let media = NewMedia {
+        kind: KIND_IMAGE,
+        file_id: Some(file_id),
+        url: &url,
+        caption: upload.caption.as_deref(),
+        status: "ready",
+    };
+    let media = insert(pool, project_id, media, created_by).await?;
+    sync_cover(pool, project_id).await?;
+    Ok(media)
+}
+
+/// Add a pending image and a presigned URL for the client to upload it to
+pub async fn begin_upload(
+    pool: &PgPool,
+    storage: &ObjectStorage,
+    project_id: Uuid,
+    upload: NewUpload,
+    created_by: Uuid,
+) -> Result<PendingUpload, MediaError> {
+    check_image(&upload.content_type, upload.size_bytes)?;
+    next_position(pool, project_id).await?;
+
+    let key = storage::object_key(&format!("projects/{}/media", project_id), Uuid::new_v4(), &upload.filename);
+    let file_id = insert_file(pool, created_by, project_id, &key, &upload).await?;
+    let url = storage.public_url(&key);
+    let media = NewMedia {
+        kind: KIND_IMAGE,
+        file_id: Some(file_id),
+        url: &url,
+        caption: upload.caption.as_deref(),
+        status: "pending",
+    };
+    let media = insert(pool, project_id, media, created_by).await?;
+    let upload_url = storage.presign(&Method::PUT, &key, Duration::minutes(UPLOAD_URL_TTL_MINUTES));
+    Ok(PendingUpload { media, upload: upload_url, content_type: upload.content_type })
+}
+
+/// Mark a presigned upload ready once its file is in storage and matches
+/// the checksum it was started with; `None` if there's no such pending upload
+pub async fn complete_upload(
+    pool: &PgPool,
+    storage: &ObjectStorage,
+    project_id: Uuid,
+    media_id: Uuid,
+) -> Result<Option<ProjectMedia>, MediaError> {
+    let Some(file) = sqlx::query!(
+        r#"
+        SELECT f.id, f.path, f.checksum
+        FROM project_media m
+        JOIN files f ON f.id = m.file_id
+        WHERE m.id = $1 AND m.project_id = $2 AND m.status = 'pending'
+        "#,
+        media_id,
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let Some(stored) = storage.checksum(&file.path).await? else {
+        return Err(MediaError::NotUploaded);
+    };
+    if stored.size_bytes > MAX_IMAGE_BYTES {
+        storage.delete(&file.path).await?;
+        return Err(MediaError::TooLarge);
+    }
+    if file.checksum.is_some_and(|expected| !expected.eq_ignore_ascii_case(&stored.checksum)) {
+        storage.delete(&file.path).await?;
+        return Err(MediaError::ChecksumMismatch);
+    }
+
+    sqlx::query!(
+        "UPDATE files SET size_bytes = $2, checksum = $3 WHERE id = $1",
+        file.id,
+        stored.size_bytes,
+        stored.checksum
+    )
+    .execute(pool)
+    .await?;
+    let media = sqlx::query_as!(
+        ProjectMedia,
+        r#"
+        UPDATE project_media SET status = 'ready'
+        WHERE id = $1
+        RETURNING id, project_id, kind, url, caption, position, status, created_at
+        "#,
+        media_id
+    )
+    .fetch_one(pool)
+    .await?;
+    sync_cover(pool, project_id).await?;
+    Ok(Some(media))
+}
+
+/// Change an item's caption; `None` if it isn't in the gallery
+pub async fn set_caption(pool: &PgPool, project_id: Uuid, media_id: Uuid, caption: Option<&str>) -> Result<Option<ProjectMedia>> {
+    let media = sqlx::query_as!(
+        ProjectMedia,
+        r#"
+        UPDATE project_media SET caption = $3
+        WHERE id = $1 AND project_id = $2
+        RETURNING id, project_id, kind, url, caption, position, status, created_at
+        "#,
+        media_id,
+        project_id,
+        caption
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(media)
+}
+
+/// Put the gallery in the given order, which must list every item once
+pub async fn reorder(pool: &PgPool, project_id: Uuid, order: &[Uuid]) -> Result<Vec<ProjectMedia>, MediaError> {
+    let current: Vec<Uuid> = list(pool, project_id, true).await?.into_iter().map(|m| m.id).collect();
+    if !is_permutation(&current, order) {
+        return Err(MediaError::InvalidOrder);
+    }
+
+    let mut tx = pool.begin().await?;
+    for (position, media_id) in order.iter().enumerate() {
+        sqlx::query!(
+            "UPDATE project_media SET position = $3 WHERE id = $1 AND project_id = $2",
+            media_id,
+            project_id,
+            position as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    sync_cover(pool, project_id).await?;
+    Ok(list(pool, project_id, true).await?)
+}
+
+/// Take an item out of the gallery, deleting its file; `false` if it wasn't there
+pub async fn remove(pool: &PgPool, storage: Option<&ObjectStorage>, project_id: Uuid, media_id: Uuid) -> Result<bool> {
+    let Some(removed) = sqlx::query!(
+        r#"
+        DELETE FROM project_media
+        WHERE id = $1 AND project_id = $2
+        RETURNING file_id
+        "#,
+        media_id,
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    if let Some(file_id) = removed.file_id {
+        let path = sqlx::query_scalar!("DELETE FROM files WHERE id = $1 RETURNING path", file_id)
+            .fetch_optional(pool)
+            .await?;
+        if let (Some(storage), Some(path)) = (storage, path) {
+            if let Err(e) = storage.delete(&path).await {
+                tracing::warn!("Failed to delete stored media {}: {}", path, e);
+            }
+        }
+    }
+    sync_cover(pool, project_id).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_image() {
+        assert!(check_image("image/PNG", 1024).is_ok());
+        assert!(matches!(check_image("image/svg+xml", 1024), Err(MediaError::UnsupportedType(t)) if t == "image/svg+xml"));
+        assert!(matches!(check_image("image/jpeg", MAX_IMAGE_BYTES + 1), Err(MediaError::TooLarge)));
+    }
+
+    #[test]
+    fn test_is_permutation() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        assert!(is_permutation(&[a, b, c], &[c, a, b]));
+        assert!(!is_permutation(&[a, b, c], &[a, b]));
+        assert!(!is_permutation(&[a, b], &[a, a]));
+        assert!(is_permutation(&[], &[]));
+    }
+}
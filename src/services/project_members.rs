@@ -0,0 +1,348 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::contract_client::TOTAL_SHARE_BPS;
+use crate::services::notifications::NotificationEvent;
+use crate::utils::money::Stroops;
+
+pub const ROLE_OWNER: &str = "owner";
+pub const ROLE_MEMBER: &str = "member";
+
+#[derive(Debug, thiserror::Error)]
+pub enum MemberError {
+    #[error("Student not found")]
+    StudentNotFound,
+    #[error("Only verified students can join projects")]
+    NotVerified,
+    #[error("The student is already on this project")]
+    AlreadyMember,
+    #[error("The project's owner can't be removed or given a share")]
+    Owner,
+    #[error("Members' shares would come to {0} bps, more than the whole payout")]
+    SharesExceeded(i64),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for MemberError {
+    fn from(e: sqlx::Error) -> Self {
+        MemberError::Internal(e.into())
+    }
+}
+
+/// A student on a project, or invited to it
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectMember {
+    pub project_id: Uuid,
+    pub student_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    /// `owner` or `member`
+    pub role: String,
+    /// `invited` or `active`
+    pub status: String,
+    /// Share of each milestone payout; the owner's is whatever the members
+    /// don't take
+    pub share_bps: i32,
+    pub created_at: DateTime<Utc>,
+    pub joined_at: Option<DateTime<Utc>>,
+}
+
+/// The caller's place on a project
+#[derive(Debug, Clone)]
+pub struct Membership {
+    pub student_id: Uuid,
+    pub role: String,
+    pub status: String,
+}
+
+impl Membership {
+    pub fn is_owner(&self) -> bool {
+        self.role == ROLE_OWNER
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.status == "active"
+    }
+}
+
+/// Split a payout by shares that add up to `TOTAL_SHARE_BPS`, rounding each
+/// down; the first share (the owner's) takes the remainder
+pub fn split_by_shares(amount: Stroops, shares_bps: &[u32]) -> Vec<Stroops> {
+    if shares_bps.is_empty() {
+        return Vec::new();
+    }
+    let mut parts: Vec<i64> = shares_bps
+        .iter()
+        .map(|bps| (amount.as_stroops() as i128 * *bps as i128 / TOTAL_SHARE_BPS as i128) as i64)
+        .collect();
+    let remainder = amount.as_stroops() - parts.iter().sum::<i64>();
+    parts[0] += remainder;
+    parts.into_iter().map(Stroops::from_stroops).collect()
+}
+
+pub async fn membership(pool: &PgPool, project_id: Uuid, user_id: Uuid) -> Result<Option<Membership>> {
+    let membership = sqlx::query_as!(
+        Membership,
+        r#"
+        SELECT m.student_id, m.role, m.status
+        FROM project_members m
+        JOIN students s ON s.id = m.student_id
+        WHERE m.project_id = $1 AND s.user_id = $2
+        "#,
+        project_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(membership)
+}
+
+/// A project's team, owner first; invitations only when asked for. The
+/// owner's share is filled in from the members'.
+pub async fn list(pool: &PgPool, project_id: Uuid, include_invited: bool) -> Result<Vec<ProjectMember>> {
+    let mut members = sqlx::query_as!(
+        ProjectMember,
+        r#"
+        SELECT m.project_id, m.student_id, u.id as user_id, u.username, m.role, m.status,
+               m.share_bps, m.created_at, m.joined_at
+        FROM project_members m
+        JOIN students s ON s.id = m.student_id
+        JOIN users u ON u.id = s.user_id
+        WHERE m.project_id = $1 AND ($2 OR m.status = 'active')
+        ORDER BY m.role = 'owner' DESC, m.created_at
+        "#,
+        project_id,
+        include_invited
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let members_share: i32 = members
+        .iter()
+        .filter(|m| m.role == ROLE_MEMBER && m.status == "active")
+        .map(|m| m.share_bps)
+        .sum();
+    for member in members.iter_mut().filter(|m| m.role == ROLE_OWNER) {
+        member.share_bps = (TOTAL_SHARE_BPS as i32 - members_share).max(0);
+    }
+    Ok(members)
+}
+
+async fn find(pool: &PgPool, project_id: Uuid, student_id: Uuid) -> Result<Option<ProjectMember>> {
+    let members = list(pool, project_id, true).await?;
+    Ok(members.into_iter().find(|m| m.student_id == student_id))
+}
+
+/// Make a project's student its owner, when the project is created
+pub async fn add_owner(pool: &PgPool, project_id: Uuid, student_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO project_members (project_id, student_id, role, status, joined_at)
+        VALUES ($1, $2, 'owner', 'active', NOW())
+        ON CONFLICT (project_id, student_id) DO NOTHING
+        "#,
+        project_id,
+        student_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Shares held by the project's members and invitees, leaving one student out
+async fn shares_excluding(pool: &PgPool, project_id: Uuid, student_id: Uuid) -> Result<i64> {
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(share_bps), 0) as "total!"
+        FROM project_members
+        WHERE project_id = $1 AND role = 'member' AND student_id <> $2
+        "#,
+        project_id,
+        student_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(total)
+}
+
+/// Invite a verified student onto a project with a share of its payouts.
+/// Shares offered to members and invitees can't add up to more than the
+/// whole payout.
+pub async fn invite(
+    pool: &PgPool,
+    project_id: Uuid,
+    student_id: Uuid,
+    share_bps: u32,
+    invited_by: Uuid,
+) -> Result<ProjectMember, MemberError> {
+    let verification = sqlx::query_scalar!("SELECT verification_status FROM students WHERE id = $1", student_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(MemberError::StudentNotFound)?;
+    if verification != "verified" {
+        return Err(MemberError::NotVerified);
+    }
+
+    let total = shares_excluding(pool, project_id, student_id).await? + share_bps as i64;
+    if total > TOTAL_SHARE_BPS as i64 {
+        return Err(MemberError::SharesExceeded(total));
+    }
+
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO project_members (project_id, student_id, role, status, share_bps, invited_by)
+        VALUES ($1, $2, 'member', 'invited', $3, $4)
+        ON CONFLICT (project_id, student_id) DO NOTHING
+        "#,
+        project_id,
+        student_id,
+        share_bps as i32,
+        invited_by
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    if inserted == 0 {
+        return Err(MemberError::AlreadyMember);
+    }
+
+    find(pool, project_id, student_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Invitation for student {} vanished", student_id).into())
+}
+
+/// Accept an invitation; `None` if there's none outstanding
+pub async fn accept(pool: &PgPool, project_id: Uuid, student_id: Uuid) -> Result<Option<ProjectMember>> {
+    let accepted = sqlx::query!(
+        r#"
+        UPDATE project_members
+        SET status = 'active', joined_at = NOW()
+        WHERE project_id = $1 AND student_id = $2 AND status = 'invited'
+        "#,
+        project_id,
+        student_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    if accepted == 0 {
+        return Ok(None);
+    }
+    find(pool, project_id, student_id).await
+}
+
+/// Change a member's share of the payouts; `None` if they aren't on the project
+pub async fn set_share(pool: &PgPool, project_id: Uuid, student_id: Uuid, share_bps: u32) -> Result<Option<ProjectMember>, MemberError> {
+    let Some(member) = find(pool, project_id, student_id).await? else {
+        return Ok(None);
+    };
+    if member.role == ROLE_OWNER {
+        return Err(MemberError::Owner);
+    }
+
+    let total = shares_excluding(pool, project_id, student_id).await? + share_bps as i64;
+    if total > TOTAL_SHARE_BPS as i64 {
+        return Err(MemberError::SharesExceeded(total));
+    }
+
+    sqlx::query!(
+        "UPDATE project_members SET share_bps = $3 WHERE project_id = $1 AND student_id = $2",
+        project_id,
+        student_id,
+        share_bps as i32
+    )
+    .execute(pool)
+    .await?;
+    Ok(find(pool, project_id, student_id).await?)
+}
+
+/// Take a member or invitation off a project; `false` if there was none.
+/// The owner stays.
+pub async fn remove(pool: &PgPool, project_id: Uuid, student_id: Uuid) -> Result<bool, MemberError> {
+    let role = sqlx::query_scalar!(
+        "SELECT role FROM project_members WHERE project_id = $1 AND student_id = $2",
+        project_id,
+        student_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    match role.as_deref() {
+        None => Ok(false),
+        Some(ROLE_OWNER) => Err(MemberError::Owner),
+        Some(_) => {
+            sqlx::query!(
+                "DELETE FROM project_members WHERE project_id = $1 AND student_id = $2",
+                project_id,
+                student_id
+            )
+            .execute(pool)
+            .await?;
+            Ok(true)
+        }
+    }
+}
+
+/// Who gets what of a milestone payout: the owner first, then each active
+/// member by their share
+pub async fn payout_split(pool: &PgPool, project_id: Uuid, amount: Stroops) -> Result<Vec<(Uuid, Stroops)>> {
+    let members: Vec<ProjectMember> = list(pool, project_id, false)
+        .await?
+        .into_iter()
+        .filter(|m| m.role == ROLE_OWNER || m.share_bps > 0)
+        .collect();
+    if members.first().is_none_or(|m| m.role != ROLE_OWNER) {
+        return Err(anyhow::anyhow!("Project {} has no owner", project_id));
+    }
+
+    let shares: Vec<u32> = members.iter().map(|m| m.share_bps.max(0) as u32).collect();
+    Ok(members
+        .iter()
+        .zip(split_by_shares(amount, &shares))
+        .filter(|(_, part)| part.is_positive())
+        .map(|(m, part)| (m.student_id, part))
+        .collect())
+}
+
+/// Tell an invited student about their invitation
+pub async fn notify_invited(pool: &PgPool, project_id: Uuid, student_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+        SELECT s.user_id, 'project', 'Project invitation', 'You have been invited to join ' || p.title, $3,
+               jsonb_set($4::jsonb, '{user_id}', to_jsonb(s.user_id))
+        FROM students s, projects p
+        WHERE s.id = $2 AND p.id = $1
+        "#,
+        project_id,
+        student_id,
+        serde_json::json!({"project_id": project_id}),
+        NotificationEvent::ProjectInvite { project_id, user_id: None }.to_json()
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_by_shares() {
+        let amount = Stroops::from_xlm(100).unwrap();
+        let parts = split_by_shares(amount, &[5_000, 3_000, 2_000]);
+        assert_eq!(parts, vec![Stroops::from_xlm(50).unwrap(), Stroops::from_xlm(30).unwrap(), Stroops::from_xlm(20).unwrap()]);
+    }
+
+    #[test]
+    fn test_split_remainder_goes_to_owner() {
+        let parts = split_by_shares(Stroops::from_stroops(10), &[3_334, 3_333, 3_333]);
+        assert_eq!(parts, vec![Stroops::from_stroops(4), Stroops::from_stroops(3), Stroops::from_stroops(3)]);
+        assert_eq!(split_by_shares(Stroops::from_stroops(10), &[10_000]), vec![Stroops::from_stroops(10)]);
+        assert!(split_by_shares(Stroops::from_stroops(10), &[]).is_empty());
+    }
+}
@@ -0,0 +1,488 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::types::BigDecimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::StellarNetwork;
+use crate::models::Project;
+use crate::services::contract_client::ContractClient;
+use crate::services::email;
+use crate::services::notifications::NotificationEvent;
+use crate::services::payment_service::PaymentService;
+use crate::services::refunds::{self, RefundError};
+use crate::utils::money::Stroops;
+
+/// Failed attempts before a refund is left for an admin to look at
+pub const MAX_ATTEMPTS: i32 = 5;
+/// How long a claimed refund is held before another run may pick it up
+const CLAIM_LEASE_SECS: f64 = 600.0;
+/// Reason recorded on provider refunds for cancelled projects
+const REFUND_REASON: &str = "Project cancelled";
+
+/// One donor's money going back after a project was cancelled
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectRefund {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    /// `escrow` or `fiat`
+    pub source: String,
+    pub deposit_id: Option<Uuid>,
+    pub payment_id: Option<String>,
+    pub donor_address: Option<String>,
+    /// XLM for escrow refunds, `currency` for fiat ones
+    pub amount: BigDecimal,
+    pub currency: String,
+    /// `pending`, `submitted`, `succeeded` or `failed`
+    pub status: String,
+    pub attempts: i32,
+    pub tx_hash: Option<String>,
+    pub refund_id: Option<Uuid>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub processed_at: Option<DateTime<Utc>>,
+}
+
+/// Refunds still owed or returned in one currency
+#[derive(Debug, Clone, Serialize)]
+pub struct CurrencyProgress {
+    pub currency: String,
+    pub refunded: BigDecimal,
+    pub outstanding: BigDecimal,
+}
+
+/// How far a cancelled project's refunds have got
+#[derive(Debug, Clone, Serialize)]
+pub struct RefundProgress {
+    pub project_id: Uuid,
+    pub total: i64,
+    pub pending: i64,
+    pub submitted: i64,
+    pub succeeded: i64,
+    pub failed: i64,
+    pub by_currency: Vec<CurrencyProgress>,
+    /// Every refund has succeeded or been given up on
+    pub finished: bool,
+}
+
+/// A cancelled project and what cancelling it set in motion
+#[derive(Debug, Serialize)]
+pub struct ProjectCancellation {
+    pub project: Project,
+    /// Donor refunds queued by the cancellation
+    pub refunds_planned: usize,
+    pub subscriptions_cancelled: u64,
+}
+
+/// What each deposit gets back when the escrow holds `available`: the full
+/// deposit if there's enough, otherwise a pro-rata share rounded down
+pub fn refund_shares(deposits: &[i64], available: i64) -> Vec<i64> {
+    let total: i128 = deposits.iter().map(|d| *d as i128).sum();
+    if total <= available as i128 {
+        return deposits.to_vec();
+    }
+    let available = available.max(0) as i128;
+    deposits.iter().map(|d| (*d as i128 * available / total) as i64).collect()
+}
+
+/// Close the books on a project the registry has already cancelled: mark it
+/// cancelled, stop its recurring donations and plan its donors' refunds.
+/// `cancelled_by` is `None` when the platform cancelled it.
+pub async fn cancel_project(
+    pool: &PgPool,
+    contracts: &ContractClient,
+    project_id: Uuid,
+    cancelled_by: Option<Uuid>,
+    reason: Option<&str>,
+) -> Result<ProjectCancellation> {
+    // Keep the workflow column in step with the registry
+    let project = sqlx::query_as!(
+        Project,
+        r#"
+        UPDATE projects
+        SET status = 'cancelled', cancelled_at = NOW(), cancelled_by = $2, cancellation_reason = $3
+        WHERE id = $1
+        RETURNING id, student_id, title, description, repo_url,
+                  media_url, tags, funding_goal, status,
+                  contract_address, created_at
+        "#,
+        project_id,
+        cancelled_by,
+        reason
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let subscriptions_cancelled = sqlx::query!(
+        r#"
+        UPDATE donation_subscriptions
+        SET status = 'cancelled', cancelled_at = NOW(), updated_at = NOW()
+        WHERE project_id = $1 AND status <> 'cancelled'
+        "#,
+        project_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    let refunds_planned = plan(pool, contracts, project_id).await?;
+    Ok(ProjectCancellation { project, refunds_planned, subscriptions_cancelled })
+}
+
+/// Record the refunds a cancelled project owes: one per escrow deposit, sized
+/// to what's left in the escrow, and one per completed fiat payment. Deposits
+/// made by converting a fiat payment are refunded through the provider
+/// instead. Safe to run again; refunds already planned are kept.
+pub async fn plan(pool: &PgPool, contracts: &ContractClient, project_id: Uuid) -> Result<usize> {
+    let deposits = sqlx::query!(
+        r#"
+        SELECT d.id, d.donor_address, d.amount_stroops,
+               EXISTS(SELECT 1 FROM fiat_settlements s WHERE s.tx_hash = d.tx_hash) as "from_fiat!"
+        FROM contract_deposits d
+        WHERE d.project_id = $1
+        ORDER BY d.created_at, d.id
+        "#,
+        project_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut planned = 0;
+    if !deposits.is_empty() {
+        let available = contracts.get_project_balance(project_id).await?;
+        let amounts: Vec<i64> = deposits.iter().map(|d| d.amount_stroops).collect();
+        for (deposit, share) in deposits.iter().zip(refund_shares(&amounts, available)) {
+            if deposit.from_fiat || share <= 0 {
+                continue;
+            }
+            planned += sqlx::query!(
+                r#"
+                INSERT INTO project_refunds (project_id, source, deposit_id, donor_address, amount, currency)
+                VALUES ($1, 'escrow', $2, $3, $4, 'XLM')
+                ON CONFLICT DO NOTHING
+                "#,
+                project_id,
+                deposit.id,
+                deposit.donor_address,
+                Stroops::from_stroops(share).to_decimal()
+            )
+            .execute(pool)
+            .await?
+            .rows_affected() as usize;
+        }
+    }
+
+    // Whatever each payment has left after earlier refunds
+    planned += sqlx::query!(
+        r#"
+        INSERT INTO project_refunds (project_id, source, payment_id, amount, currency)
+        SELECT p.project_id, 'fiat', p.payment_id,
+               p.amount - COALESCE((SELECT SUM(COALESCE(r.amount, p.amount)) FROM refunds r
+                                    WHERE r.payment_id = p.payment_id AND r.status <> 'failed'), 0),
+               p.currency
+        FROM payment_instructions p
+        WHERE p.project_id = $1 AND p.status = 'completed'
+          AND p.amount IS NOT NULL AND p.currency IS NOT NULL
+          AND p.amount > COALESCE((SELECT SUM(COALESCE(r.amount, p.amount)) FROM refunds r
+                                   WHERE r.payment_id = p.payment_id AND r.status <> 'failed'), 0)
+        ON CONFLICT DO NOTHING
+        "#,
+        project_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected() as usize;
+
+    Ok(planned)
+}
+
+/// Claim refunds that are due, for one project or all of them. Claimed
+/// refunds are leased so a crashed run's are picked up again later.
+pub async fn claim_due(pool: &PgPool, project_id: Option<Uuid>, limit: i64) -> Result<Vec<ProjectRefund>> {
+    let refunds = sqlx::query_as!(
+        ProjectRefund,
+        r#"
+        UPDATE project_refunds
+        SET attempts = attempts + 1, next_attempt_at = NOW() + make_interval(secs => $3), updated_at = NOW()
+        WHERE id IN (
+            SELECT id FROM project_refunds
+            WHERE status = 'pending' AND next_attempt_at <= NOW()
+              AND ($1::uuid IS NULL OR project_id = $1)
+            ORDER BY next_attempt_at
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, project_id, source, deposit_id, payment_id, donor_address, amount, currency,
+                  status, attempts, tx_hash, refund_id, error, created_at, processed_at
+        "#,
+        project_id,
+        limit,
+        CLAIM_LEASE_SECS
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(refunds)
+}
+
+/// Make one claimed refund: on-chain from the escrow, or through the payment
+/// provider. Failures are retried with backoff until `MAX_ATTEMPTS`.
+pub async fn process(pool: &PgPool, contracts: &ContractClient, payments: &PaymentService, refund: &ProjectRefund) -> Result<()> {
+    match refund.source.as_str() {
+        "escrow" => process_escrow(pool, contracts, refund).await,
+        _ => process_fiat(pool, payments, refund).await,
+    }
+}
+
+async fn process_escrow(pool: &PgPool, contracts: &ContractClient, refund: &ProjectRefund) -> Result<()> {
+    let (Some(deposit_id), Some(donor_address)) = (refund.deposit_id, refund.donor_address.as_deref()) else {
+        return give_up(pool, refund.id, "Escrow refund has no deposit to return").await;
+    };
+    let amount = Stroops::from_decimal(&refund.amount)?;
+
+    match contracts.refund_deposit(refund.project_id, donor_address, amount.as_stroops()).await {
+        Ok((tx_hash, refunded)) => {
+            let refunded = Stroops::from_stroops(refunded);
+            sqlx::query!(
+                r#"
+                UPDATE project_refunds
+                SET status = 'succeeded', tx_hash = $2, amount = $3, error = NULL,
+                    processed_at = NOW(), updated_at = NOW()
+                WHERE id = $1
+                "#,
+                refund.id,
+                tx_hash,
+                refunded.to_decimal()
+            )
+            .execute(pool)
+            .await?;
+
+            // The donation the deposit paid for, and its donor if they have an account
+            let donor = sqlx::query_scalar!(
+                r#"
+                UPDATE donations d
+                SET status = 'refunded'
+                FROM contract_deposits c
+                WHERE c.id = $1 AND d.tx_hash = c.tx_hash AND d.project_id = c.project_id
+                RETURNING d.donor_id
+                "#,
+                deposit_id
+            )
+            .fetch_optional(pool)
+            .await?
+            .flatten();
+            if let Some(donor_id) = donor {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+                    VALUES ($1, 'donation', $2, $3, $4, $5)
+                    "#,
+                    donor_id,
+                    "Donation refunded",
+                    format!("{} XLM has been returned to your wallet because the project was cancelled", refunded),
+                    serde_json::json!({
+                        "project_id": refund.project_id,
+                        "project_refund_id": refund.id,
+                        "tx_hash": tx_hash
+                    }),
+                    NotificationEvent::DonationRefunded { refund_id: refund.id, project_id: Some(refund.project_id) }
+                        .to_json()
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(())
+        }
+        Err(e) => retry_later(pool, refund, &e.to_string()).await,
+    }
+}
+
+async fn process_fiat(pool: &PgPool, payments: &PaymentService, refund: &ProjectRefund) -> Result<()> {
+    let Some(payment_id) = refund.payment_id.as_deref() else {
+        return give_up(pool, refund.id, "Fiat refund has no payment to refund").await;
+    };
+
+    match refunds::request_refund(pool, payments, payment_id, None, REFUND_REASON, None).await {
+        Ok(provider_refund) => {
+            let status = match provider_refund.status.as_str() {
+                "succeeded" => "succeeded",
+                "failed" => return retry_later(pool, refund, provider_refund.failure_reason.as_deref().unwrap_or("Refund failed")).await,
+                _ => "submitted",
+            };
+            sqlx::query!(
+                r#"
+                UPDATE project_refunds
+                SET status = $2::varchar, refund_id = $3, error = NULL, updated_at = NOW(),
+                    processed_at = CASE WHEN $2::varchar = 'succeeded' THEN NOW() END
+                WHERE id = $1
+                "#,
+                refund.id,
+                status,
+                provider_refund.id
+            )
+            .execute(pool)
+            .await?;
+            Ok(())
+        }
+        // Nothing left to refund, or nothing to refund it with: retrying won't help
+        Err(e @ (RefundError::NotFound | RefundError::NotRefundable(_))) => give_up(pool, refund.id, &e.to_string()).await,
+        Err(e) => retry_later(pool, refund, &e.to_string()).await,
+    }
+}
+
+async fn retry_later(pool: &PgPool, refund: &ProjectRefund, error: &str) -> Result<()> {
+    if refund.attempts >= MAX_ATTEMPTS {
+        return give_up(pool, refund.id, error).await;
+    }
+    tracing::warn!("Refund {} failed (attempt {}): {}", refund.id, refund.attempts, error);
+    sqlx::query!(
+        r#"
+        UPDATE project_refunds
+        SET error = $2, next_attempt_at = NOW() + make_interval(secs => $3), updated_at = NOW()
+        WHERE id = $1
+        "#,
+        refund.id,
+        error,
+        email::retry_delay(refund.attempts).as_secs_f64()
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn give_up(pool: &PgPool, id: Uuid, error: &str) -> Result<()> {
+    tracing::error!("Refund {} failed for good: {}", id, error);
+    sqlx::query!(
+        "UPDATE project_refunds SET status = 'failed', error = $2, processed_at = NOW(), updated_at = NOW() WHERE id = $1",
+        id,
+        error
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Carry provider results over to submitted fiat refunds
+pub async fn sync_submitted(pool: &PgPool) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE project_refunds pr
+        SET status = r.status, error = r.failure_reason, processed_at = r.processed_at, updated_at = NOW()
+        FROM refunds r
+        WHERE pr.refund_id = r.id AND pr.status = 'submitted' AND r.status IN ('succeeded', 'failed')
+        "#
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Claim and make due refunds, for one project or all of them. Returns how
+/// many were attempted.
+pub async fn run_due(
+    pool: &PgPool,
+    network: StellarNetwork,
+    payments: &PaymentService,
+    project_id: Option<Uuid>,
+    limit: i64,
+) -> Result<usize> {
+    sync_submitted(pool).await?;
+
+    let due = claim_due(pool, project_id, limit).await?;
+    if due.is_empty() {
+        return Ok(0);
+    }
+    let mut contracts = ContractClient::new(pool.clone(), network);
+    contracts.load_contracts().await?;
+
+    for refund in &due {
+        if let Err(e) = process(pool, &contracts, payments, refund).await {
+            tracing::error!("Error processing refund {}: {}", refund.id, e);
+        }
+    }
+    Ok(due.len())
+}
+
+pub async fn progress(pool: &PgPool, project_id: Uuid) -> Result<RefundProgress> {
+    let counts = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "total!",
+               COUNT(*) FILTER (WHERE status = 'pending') as "pending!",
+               COUNT(*) FILTER (WHERE status = 'submitted') as "submitted!",
+               COUNT(*) FILTER (WHERE status = 'succeeded') as "succeeded!",
+               COUNT(*) FILTER (WHERE status = 'failed') as "failed!"
+        FROM project_refunds
+        WHERE project_id = $1
+        "#,
+        project_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let by_currency = sqlx::query_as!(
+        CurrencyProgress,
+        r#"
+        SELECT currency,
+               COALESCE(SUM(amount) FILTER (WHERE status = 'succeeded'), 0) as "refunded!",
+               COALESCE(SUM(amount) FILTER (WHERE status IN ('pending', 'submitted')), 0) as "outstanding!"
+        FROM project_refunds
+        WHERE project_id = $1
+        GROUP BY currency
+        ORDER BY currency
+        "#,
+        project_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(RefundProgress {
+        project_id,
+        total: counts.total,
+        pending: counts.pending,
+        submitted: counts.submitted,
+        succeeded: counts.succeeded,
+        failed: counts.failed,
+        by_currency,
+        finished: counts.pending == 0 && counts.submitted == 0,
+    })
+}
+
+/// Refunds that gave up, for an admin to follow up by hand
+pub async fn failed(pool: &PgPool, project_id: Uuid) -> Result<Vec<ProjectRefund>> {
+    let refunds = sqlx::query_as!(
+        ProjectRefund,
+        r#"
+        SELECT id, project_id, source, deposit_id, payment_id, donor_address, amount, currency,
+               status, attempts, tx_hash, refund_id, error, created_at, processed_at
+        FROM project_refunds
+        WHERE project_id = $1 AND status = 'failed'
+        ORDER BY created_at
+        "#,
+        project_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(refunds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refund_shares_in_full_when_covered() {
+        assert_eq!(refund_shares(&[100, 250], 350), vec![100, 250]);
+        assert_eq!(refund_shares(&[100, 250], 1000), vec![100, 250]);
+    }
+
+    #[test]
+    fn test_refund_shares_pro_rata_when_short() {
+        // 60% of the escrow is left after releases
+        assert_eq!(refund_shares(&[100, 250, 50], 240), vec![60, 150, 30]);
+        // Rounded down, never more than what's there
+        let shares = refund_shares(&[1, 1, 1], 2);
+        assert!(shares.iter().sum::<i64>() <= 2);
+        assert_eq!(refund_shares(&[100, 200], 0), vec![0, 0]);
+        assert_eq!(refund_shares(&[100, 200], -5), vec![0, 0]);
+    }
+}
@@ -0,0 +1,385 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::types::{BigDecimal, Json};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::Project;
+use crate::services::notifications::NotificationEvent;
+use crate::utils::money::Stroops;
+use crate::utils::pagination::{Page, PageRequest};
+
+/// Fields donors backed a published project on; changing them needs an
+/// admin's approval
+pub const MATERIAL_FIELDS: &[&str] = &["funding_goal", "milestones"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum RevisionError {
+    #[error("The project already has a revision awaiting review")]
+    AlreadyPending,
+    #[error("Revision not found or already reviewed")]
+    NotPending,
+    #[error("The project changed since this revision was submitted: {0}")]
+    Stale(String),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for RevisionError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::Database(db) if db.constraint() == Some("idx_project_revisions_pending") => {
+                RevisionError::AlreadyPending
+            }
+            _ => RevisionError::Internal(e.into()),
+        }
+    }
+}
+
+/// One field's value before and after an edit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl FieldChange {
+    pub fn is_material(&self) -> bool {
+        MATERIAL_FIELDS.contains(&self.field.as_str())
+    }
+}
+
+/// A milestone of the project's plan that hasn't started yet. Milestones
+/// under way or done are settled and can't be revised.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedMilestone {
+    pub title: String,
+    pub description: Option<String>,
+    pub amount: Stroops,
+    pub proof_type: Option<String>,
+    pub position: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectRevision {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub author_id: Option<Uuid>,
+    pub changes: Json<Vec<FieldChange>>,
+    pub requires_review: bool,
+    /// `pending`, `approved`, `rejected` or `applied`
+    pub status: String,
+    pub review_note: Option<String>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The editable fields of a project, keyed as in revisions
+pub fn snapshot(project: &Project) -> Value {
+    serde_json::json!({
+        "title": project.title,
+        "description": project.description,
+        "repo_url": project.repo_url,
+        "media_url": project.media_url,
+        "tags": project.tags,
+        "funding_goal": project.funding_goal.normalized().to_string(),
+    })
+}
+
+/// The fields of `after` whose values differ from `before`, in `after`'s
+/// key order
+pub fn diff(before: &Value, after: &Value) -> Vec<FieldChange> {
+    let Some(after) = after.as_object() else {
+        return Vec::new();
+    };
+    after
+        .iter()
+        .filter_map(|(field, value)| {
+            let old = before.get(field).cloned().unwrap_or(Value::Null);
+            (old != *value).then(|| FieldChange { field: field.clone(), before: old, after: value.clone() })
+        })
+        .collect()
+}
+
+/// Split changes into those that go live now and those needing review
+pub fn partition(changes: Vec<FieldChange>) -> (Vec<FieldChange>, Vec<FieldChange>) {
+    changes.into_iter().partition(|c| !c.is_material())
+}
+
+/// The milestones of a project's plan that haven't started, in order
+pub async fn milestone_plan(conn: &mut PgConnection, project_id: Uuid) -> Result<Vec<PlannedMilestone>> {
+    let milestones = sqlx::query_as!(
+        PlannedMilestone,
+        r#"
+        SELECT title, description, amount_stroops as "amount: Stroops", proof_type, position
+        FROM project_milestones
+        WHERE project_id = $1 AND COALESCE(status, 'pending') = 'pending'
+        ORDER BY position, created_at
+        "#,
+        project_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(milestones)
+}
+
+/// Swap the not-yet-started milestones for a new plan
+pub async fn replace_milestones(conn: &mut PgConnection, project_id: Uuid, plan: &[PlannedMilestone]) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM project_milestones WHERE project_id = $1 AND COALESCE(status, 'pending') = 'pending'",
+        project_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    for milestone in plan {
+        sqlx::query!(
+            r#"
+            INSERT INTO project_milestones (project_id, title, description, amount_stroops, proof_type, position, status)
+            VALUES ($1, $2, $3, $4, $5, $6, 'pending')
+            "#,
+            project_id,
+            milestone.title,
+            milestone.description,
+            milestone.amount.as_stroops(),
+            milestone.proof_type,
+            milestone.position
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Keep an edit. Edits needing review wait as `pending`, at most one per
+/// project; the rest are recorded as `applied`.
+pub async fn record(
+    pool: &PgPool,
+    project_id: Uuid,
+    author_id: Uuid,
+    changes: &[FieldChange],
+    requires_review: bool,
+) -> Result<ProjectRevision, RevisionError> {
+    let status = if requires_review { "pending" } else { "applied" };
+    let revision = sqlx::query_as!(
+        ProjectRevision,
+        r#"
+        INSERT INTO project_revisions (project_id, author_id, changes, requires_review, status)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, project_id, author_id, changes as "changes: Json<Vec<FieldChange>>", requires_review,
+                  status, review_note, reviewed_by, reviewed_at, created_at
+        "#,
+        project_id,
+        author_id,
+        Json(changes) as _,
+        requires_review,
+        status
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(revision)
+}
+
+/// The revision awaiting review, if any
+pub async fn pending(pool: &PgPool, project_id: Uuid) -> Result<Option<ProjectRevision>> {
+    let revision = sqlx::query_as!(
+        ProjectRevision,
+        r#"
+        SELECT id, project_id, author_id, changes as "changes: Json<Vec<FieldChange>>", requires_review,
+               status, review_note, reviewed_by, reviewed_at, created_at
+        FROM project_revisions
+        WHERE project_id = $1 AND status = 'pending'
+        "#,
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(revision)
+}
+
+/// A page of a project's revisions, newest first
+pub async fn list(pool: &PgPool, project_id: Uuid, page: &PageRequest) -> Result<Page<ProjectRevision>> {
+    let revisions = sqlx::query_as!(
+        ProjectRevision,
+        r#"
+        SELECT id, project_id, author_id, changes as "changes: Json<Vec<FieldChange>>", requires_review,
+               status, review_note, reviewed_by, reviewed_at, created_at
+        FROM project_revisions
+        WHERE project_id = $1
+          AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3::uuid))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
+        "#,
+        project_id,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(Page::new(revisions, page, |r| (Some(r.created_at), r.id)))
+}
+
+/// Close a pending revision as `approved` or `rejected`
+async fn close(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    revision_id: Uuid,
+    status: &str,
+    reviewer: Uuid,
+    note: Option<&str>,
+) -> Result<ProjectRevision, RevisionError> {
+    sqlx::query_as!(
+        ProjectRevision,
+        r#"
+        UPDATE project_revisions
+        SET status = $3, reviewed_by = $4, review_note = $5, reviewed_at = NOW()
+        WHERE id = $1 AND project_id = $2 AND status = 'pending'
+        RETURNING id, project_id, author_id, changes as "changes: Json<Vec<FieldChange>>", requires_review,
+                  status, review_note, reviewed_by, reviewed_at, created_at
+        "#,
+        revision_id,
+        project_id,
+        status,
+        reviewer,
+        note
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(RevisionError::NotPending)
+}
+
+/// Approve a pending revision and apply its changes. Refused if a field no
+/// longer holds the value the revision was made against.
+pub async fn approve(
+    pool: &PgPool,
+    project_id: Uuid,
+    revision_id: Uuid,
+    reviewer: Uuid,
+    note: Option<&str>,
+) -> Result<ProjectRevision, RevisionError> {
+    let mut tx = pool.begin().await?;
+    let project = sqlx::query_as!(
+        Project,
+        r#"
+        SELECT id, student_id, title, description, repo_url,
+               media_url, tags, funding_goal, status,
+               contract_address, created_at
+        FROM projects
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        project_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(RevisionError::NotPending)?;
+    let revision = close(&mut tx, project_id, revision_id, "approved", reviewer, note).await?;
+
+    let mut current = snapshot(&project);
+    current["milestones"] = serde_json::to_value(milestone_plan(&mut tx, project_id).await?).map_err(anyhow::Error::from)?;
+
+    for change in revision.changes.iter() {
+        if current.get(&change.field).cloned().unwrap_or(Value::Null) != change.before {
+            return Err(RevisionError::Stale(change.field.clone()));
+        }
+        match change.field.as_str() {
+            "funding_goal" => {
+                let goal: BigDecimal = change
+                    .after
+                    .as_str()
+                    .and_then(|g| g.parse().ok())
+                    .ok_or_else(|| anyhow::anyhow!("Revision {} has an invalid funding goal", revision.id))?;
+                sqlx::query!("UPDATE projects SET funding_goal = $2 WHERE id = $1", project_id, goal)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            "milestones" => {
+                let plan: Vec<PlannedMilestone> = serde_json::from_value(change.after.clone()).map_err(anyhow::Error::from)?;
+                replace_milestones(&mut tx, project_id, &plan).await?;
+            }
+            other => return Err(anyhow::anyhow!("Revision {} changes unknown field {}", revision.id, other).into()),
+        }
+    }
+
+    tx.commit().await?;
+    Ok(revision)
+}
+
+/// Reject a pending revision; the project stays as it is
+pub async fn reject(
+    pool: &PgPool,
+    project_id: Uuid,
+    revision_id: Uuid,
+    reviewer: Uuid,
+    note: Option<&str>,
+) -> Result<ProjectRevision, RevisionError> {
+    let mut conn = pool.acquire().await?;
+    close(&mut conn, project_id, revision_id, "rejected", reviewer, note).await
+}
+
+/// Tell the project's owner how their revision was reviewed
+pub async fn notify_reviewed(pool: &PgPool, revision: &ProjectRevision) -> Result<()> {
+    let (title, verb) = if revision.status == "approved" {
+        ("Project changes approved", "were approved")
+    } else {
+        ("Project changes rejected", "were rejected")
+    };
+    sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+        SELECT s.user_id, 'project', $2, 'Your changes to ' || p.title || ' ' || $3, $4, $5
+        FROM projects p
+        JOIN students s ON s.id = p.student_id
+        WHERE p.id = $1
+        "#,
+        revision.project_id,
+        title,
+        verb,
+        serde_json::json!({"project_id": revision.project_id, "revision_id": revision.id, "note": revision.review_note}),
+        NotificationEvent::ProjectRevision {
+            project_id: revision.project_id,
+            revision_id: revision.id,
+            status: revision.status.clone(),
+        }
+        .to_json()
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_only_changed_fields() {
+        let before = json!({"title": "Solar kit", "tags": ["energy"], "funding_goal": "500"});
+        let after = json!({"title": "Solar kit", "tags": ["energy", "school"], "funding_goal": "750"});
+        let changes = diff(&before, &after);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].field, "funding_goal");
+        assert_eq!(changes[0].before, json!("500"));
+        assert_eq!(changes[1].after, json!(["energy", "school"]));
+    }
+
+    #[test]
+    fn test_diff_missing_before_is_null() {
+        let changes = diff(&json!({}), &json!({"description": "New"}));
+        assert_eq!(changes, vec![FieldChange { field: "description".into(), before: Value::Null, after: json!("New") }]);
+        assert!(diff(&json!({"title": "A"}), &json!({"title": "A"})).is_empty());
+    }
+
+    #[test]
+    fn test_partition_material() {
+        let change = |field: &str| FieldChange { field: field.into(), before: Value::Null, after: Value::Null };
+        let (live, review) = partition(vec![change("title"), change("funding_goal"), change("milestones"), change("tags")]);
+        assert_eq!(live.iter().map(|c| c.field.as_str()).collect::<Vec<_>>(), vec!["title", "tags"]);
+        assert_eq!(review.iter().map(|c| c.field.as_str()).collect::<Vec<_>>(), vec!["funding_goal", "milestones"]);
+    }
+}
@@ -0,0 +1,344 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::types::BigDecimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::Project;
+use crate::services::completion;
+use crate::services::contract_client::{ContractClient, OnchainProjectStatus};
+use crate::services::escrow::EscrowService;
+use crate::services::notifications::NotificationEvent;
+use crate::services::project_refunds::{self, ProjectCancellation};
+use crate::utils::money::Stroops;
+
+/// Recorded on projects cancelled for missing their goal
+pub const UNFUNDED_REASON: &str = "The funding deadline passed before the goal was reached";
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("The funding deadline has already passed")]
+    DeadlinePassed,
+    #[error("The funding deadline must fall after the project is published")]
+    DeadlineBeforePublish,
+}
+
+/// How a project's funding ended at its deadline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FundingOutcome {
+    /// Reached its goal; carries on without new donations
+    Funded,
+    /// Missed its goal; cancelled and its donors refunded
+    Unfunded,
+}
+
+impl FundingOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FundingOutcome::Funded => "funded",
+            FundingOutcome::Unfunded => "unfunded",
+        }
+    }
+
+    pub fn of(raised: Stroops, goal: Stroops) -> Self {
+        if raised >= goal {
+            FundingOutcome::Funded
+        } else {
+            FundingOutcome::Unfunded
+        }
+    }
+}
+
+/// When a project goes live and stops taking donations
+#[derive(Debug, Clone, Serialize)]
+pub struct Countdown {
+    pub publish_at: Option<DateTime<Utc>>,
+    pub funding_deadline: Option<DateTime<Utc>>,
+    /// Until the project goes live; `None` once it has or if unscheduled
+    pub seconds_until_publish: Option<i64>,
+    /// Until donations close; `0` once the deadline has passed
+    pub seconds_until_deadline: Option<i64>,
+    pub funding_open: bool,
+}
+
+impl Countdown {
+    /// `closed` when the project isn't live or its funding has closed
+    pub fn at(now: DateTime<Utc>, publish_at: Option<DateTime<Utc>>, funding_deadline: Option<DateTime<Utc>>, closed: bool) -> Self {
+        let seconds_until = |at: DateTime<Utc>| (at - now).num_seconds().max(0);
+        let seconds_until_publish = publish_at.filter(|at| *at > now).map(seconds_until);
+        let seconds_until_deadline = funding_deadline.map(seconds_until);
+        Self {
+            publish_at,
+            funding_deadline,
+            seconds_until_publish,
+            seconds_until_deadline,
+            funding_open: !closed && seconds_until_publish.is_none() && seconds_until_deadline != Some(0),
+        }
+    }
+}
+
+/// A deadline has to fall after now and after the project goes live
+pub fn check_schedule(
+    now: DateTime<Utc>,
+    publish_at: Option<DateTime<Utc>>,
+    funding_deadline: Option<DateTime<Utc>>,
+) -> Result<(), ScheduleError> {
+    let Some(deadline) = funding_deadline else {
+        return Ok(());
+    };
+    if deadline <= now {
+        return Err(ScheduleError::DeadlinePassed);
+    }
+    if publish_at.is_some_and(|at| deadline <= at) {
+        return Err(ScheduleError::DeadlineBeforePublish);
+    }
+    Ok(())
+}
+
+/// A project's schedule as of now
+pub async fn countdown(pool: &PgPool, project_id: Uuid) -> Result<Countdown> {
+    let row = sqlx::query!(
+        "SELECT status, publish_at, funding_deadline, funding_closed_at FROM projects WHERE id = $1",
+        project_id
+    )
+    .fetch_one(pool)
+    .await?;
+    let closed = row.status != "active" || row.funding_closed_at.is_some();
+    Ok(Countdown::at(Utc::now(), row.publish_at, row.funding_deadline, closed))
+}
+
+/// Whether a project takes donations: `None` if there's no such project.
+/// Only live projects do, until their deadline.
+pub async fn accepting_donations(pool: &PgPool, project_id: Uuid) -> Result<Option<bool>> {
+    let accepting = sqlx::query_scalar!(
+        r#"
+        SELECT (status = 'active' AND funding_closed_at IS NULL
+                AND (funding_deadline IS NULL OR funding_deadline > NOW())) as "accepting!"
+        FROM projects
+        WHERE id = $1
+        "#,
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(accepting)
+}
+
+/// Scheduled projects whose publish time has come, earliest first
+pub async fn due_publications(pool: &PgPool, limit: i64) -> Result<Vec<Uuid>> {
+    let ids = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM projects
+        WHERE status = 'scheduled' AND publish_at <= NOW()
+        ORDER BY publish_at
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ids)
+}
+
+/// Live projects whose funding deadline has passed, earliest first
+pub async fn due_deadlines(pool: &PgPool, limit: i64) -> Result<Vec<Uuid>> {
+    let ids = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM projects
+        WHERE status = 'active' AND funding_closed_at IS NULL AND funding_deadline <= NOW()
+        ORDER BY funding_deadline
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ids)
+}
+
+/// Move the project in the registry unless it's already there
+async fn transition(contracts: &ContractClient, project_id: Uuid, to: OnchainProjectStatus) -> Result<()> {
+    let current = contracts
+        .get_project_status(project_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Project {} is not in the registry", project_id))?;
+    if current != to {
+        contracts.set_project_status(project_id, to, true, false).await?;
+    }
+    Ok(())
+}
+
+/// Tell the project's owner, and its donors if `include_donors`, that it's
+/// now `status`
+async fn notify(
+    pool: &PgPool,
+    project_id: Uuid,
+    status: &str,
+    title: &str,
+    message: &str,
+    include_donors: bool,
+) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+        SELECT audience.user_id, 'project', $2, p.title || ' ' || $3, $5::jsonb, $6::jsonb
+        FROM projects p
+        JOIN (
+            SELECT s.user_id FROM students s JOIN projects sp ON sp.student_id = s.id WHERE sp.id = $1
+            UNION
+            SELECT d.donor_id FROM donations d
+            WHERE $4 AND d.project_id = $1 AND d.status = 'confirmed' AND d.donor_id IS NOT NULL
+        ) audience ON TRUE
+        WHERE p.id = $1
+        "#,
+        project_id,
+        title,
+        message,
+        include_donors,
+        serde_json::json!({"project_id": project_id}),
+        NotificationEvent::ProjectStatus { project_id, status: status.to_string() }.to_json()
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Publish a scheduled project: give it an escrow account when projects hold
+/// their own, then activate it in the registry and here. `None` if it was
+/// no longer scheduled.
+pub async fn activate(
+    pool: &PgPool,
+    contracts: &ContractClient,
+    escrow: Option<&EscrowService>,
+    project_id: Uuid,
+) -> Result<Option<Project>> {
+    let Some(contract_address) = sqlx::query_scalar!(
+        "SELECT contract_address FROM projects WHERE id = $1 AND status = 'scheduled'",
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+    if let (Some(escrow), None) = (escrow, contract_address) {
+        escrow.provision(project_id).await?;
+    }
+
+    transition(contracts, project_id, OnchainProjectStatus::Active).await?;
+
+    let Some(project) = sqlx::query_as!(
+        Project,
+        r#"
+        UPDATE projects
+        SET status = 'active'
+        WHERE id = $1 AND status = 'scheduled'
+        RETURNING id, student_id, title, description, repo_url,
+                  media_url, tags, funding_goal, status,
+                  contract_address, created_at
+        "#,
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    notify(pool, project_id, "active", "Project published", "is now live", false).await?;
+    Ok(Some(project))
+}
+
+/// Close a project's funding at its deadline. A project that reached its goal
+/// keeps going without new donations; one that didn't is cancelled in the
+/// registry and here, and its donors are refunded by the refund processor.
+pub async fn close_funding(pool: &PgPool, contracts: &ContractClient, project_id: Uuid) -> Result<FundingOutcome> {
+    let goal: BigDecimal = sqlx::query_scalar!("SELECT funding_goal FROM projects WHERE id = $1", project_id)
+        .fetch_one(pool)
+        .await?;
+    let raised = completion::funds(pool, project_id).await?.raised;
+    let outcome = FundingOutcome::of(raised, Stroops::from_decimal(&goal)?);
+
+    match outcome {
+        FundingOutcome::Funded => {
+            sqlx::query!(
+                r#"
+                UPDATE donation_subscriptions
+                SET status = 'cancelled', cancelled_at = NOW(), updated_at = NOW()
+                WHERE project_id = $1 AND status <> 'cancelled'
+                "#,
+                project_id
+            )
+            .execute(pool)
+            .await?;
+        }
+        FundingOutcome::Unfunded => {
+            transition(contracts, project_id, OnchainProjectStatus::Cancelled).await?;
+            let ProjectCancellation { refunds_planned, .. } =
+                project_refunds::cancel_project(pool, contracts, project_id, None, Some(UNFUNDED_REASON)).await?;
+            tracing::info!("Project {} missed its goal; {} refunds planned", project_id, refunds_planned);
+        }
+    }
+
+    sqlx::query!(
+        "UPDATE projects SET funding_closed_at = NOW(), funding_outcome = $2 WHERE id = $1",
+        project_id,
+        outcome.as_str()
+    )
+    .execute(pool)
+    .await?;
+
+    let (title, message) = match outcome {
+        FundingOutcome::Funded => ("Funding goal reached", "reached its goal and has closed to donations"),
+        FundingOutcome::Unfunded => ("Funding goal missed", "missed its goal; donations will be refunded"),
+    };
+    notify(pool, project_id, outcome.as_str(), title, message, true).await?;
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_outcome() {
+        let xlm = |n| Stroops::from_xlm(n).unwrap();
+        assert_eq!(FundingOutcome::of(xlm(100), xlm(100)), FundingOutcome::Funded);
+        assert_eq!(FundingOutcome::of(xlm(150), xlm(100)), FundingOutcome::Funded);
+        assert_eq!(FundingOutcome::of(xlm(99), xlm(100)), FundingOutcome::Unfunded);
+    }
+
+    #[test]
+    fn test_check_schedule() {
+        let now = Utc::now();
+        assert_eq!(check_schedule(now, None, None), Ok(()));
+        assert_eq!(check_schedule(now, None, Some(now + Duration::days(7))), Ok(()));
+        assert_eq!(check_schedule(now, None, Some(now - Duration::hours(1))), Err(ScheduleError::DeadlinePassed));
+        assert_eq!(
+            check_schedule(now, Some(now + Duration::days(7)), Some(now + Duration::days(3))),
+            Err(ScheduleError::DeadlineBeforePublish)
+        );
+    }
+
+    #[test]
+    fn test_countdown() {
+        let now = Utc::now();
+        let scheduled = Countdown::at(now, Some(now + Duration::hours(2)), Some(now + Duration::days(1)), false);
+        assert_eq!(scheduled.seconds_until_publish, Some(7_200));
+        assert_eq!(scheduled.seconds_until_deadline, Some(86_400));
+        assert!(!scheduled.funding_open);
+
+        let live = Countdown::at(now, Some(now - Duration::hours(2)), Some(now + Duration::minutes(5)), false);
+        assert_eq!(live.seconds_until_publish, None);
+        assert!(live.funding_open);
+
+        let passed = Countdown::at(now, None, Some(now - Duration::minutes(5)), false);
+        assert_eq!(passed.seconds_until_deadline, Some(0));
+        assert!(!passed.funding_open);
+
+        assert!(!Countdown::at(now, None, None, true).funding_open);
+        assert!(Countdown::at(now, None, None, false).funding_open);
+    }
+}
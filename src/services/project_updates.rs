@@ -0,0 +1,278 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::utils::pagination::{Page, PageRequest};
+
+/// Published updates shown with a project
+pub const RECENT_UPDATES: i64 = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpdateError {
+    #[error("The milestone isn't part of this project")]
+    MilestoneNotFound,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for UpdateError {
+    fn from(e: sqlx::Error) -> Self {
+        UpdateError::Internal(e.into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectUpdate {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub author_id: Uuid,
+    pub milestone_id: Option<Uuid>,
+    pub title: String,
+    pub body: String,
+    pub media_urls: Vec<String>,
+    /// `None` while a draft
+    pub published_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The content of an update, as written or rewritten by its owner
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateDraft {
+    pub title: String,
+    pub body: String,
+    pub media_urls: Vec<String>,
+    pub milestone_id: Option<Uuid>,
+}
+
+impl UpdateDraft {
+    /// Trims the text and drops blank or repeated media URLs
+    pub fn new(title: &str, body: &str, media_urls: &[String], milestone_id: Option<Uuid>) -> Self {
+        let mut urls: Vec<String> = Vec::new();
+        for url in media_urls.iter().map(|u| u.trim()).filter(|u| !u.is_empty()) {
+            if !urls.iter().any(|u| u == url) {
+                urls.push(url.to_string());
+            }
+        }
+        Self { title: title.trim().to_string(), body: body.trim().to_string(), media_urls: urls, milestone_id }
+    }
+}
+
+async fn check_milestone(pool: &PgPool, project_id: Uuid, milestone_id: Option<Uuid>) -> Result<(), UpdateError> {
+    let Some(milestone_id) = milestone_id else { return Ok(()) };
+    let found = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM project_milestones WHERE id = $1 AND project_id = $2) as "found!""#,
+        milestone_id,
+        project_id
+    )
+    .fetch_one(pool)
+    .await?;
+    if !found {
+        return Err(UpdateError::MilestoneNotFound);
+    }
+    Ok(())
+}
+
+/// A page of a project's updates, newest first; drafts only when asked for
+pub async fn list(pool: &PgPool, project_id: Uuid, include_drafts: bool, page: &PageRequest) -> Result<Page<ProjectUpdate>> {
+    let updates = sqlx::query_as!(
+        ProjectUpdate,
+        r#"
+        SELECT id, project_id, author_id, milestone_id, title, body, media_urls,
+               published_at, created_at, updated_at
+        FROM project_updates
+        WHERE project_id = $1
+          AND ($2 OR published_at IS NOT NULL)
+          AND ($3::timestamptz IS NULL OR (created_at, id) < ($3, $4::uuid))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $5
+        "#,
+        project_id,
+        include_drafts,
+        page.after_created_at(),
+        page.after_id(),
+        page.fetch_limit()
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(Page::new(updates, page, |u| (Some(u.created_at), u.id)))
+}
+
+/// The latest published updates, for the project page
+pub async fn recent(pool: &PgPool, project_id: Uuid) -> Result<Vec<ProjectUpdate>> {
+    let updates = sqlx::query_as!(
+        ProjectUpdate,
+        r#"
+        SELECT id, project_id, author_id, milestone_id, title, body, media_urls,
+               published_at, created_at, updated_at
+        FROM project_updates
+        WHERE project_id = $1 AND published_at IS NOT NULL
+        ORDER BY published_at DESC, id DESC
+        LIMIT $2
+        "#,
+        project_id,
+        RECENT_UPDATES
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(updates)
+}
+
+pub async fn find(pool: &PgPool, project_id: Uuid, update_id: Uuid) -> Result<Option<ProjectUpdate>> {
+    let update = sqlx::query_as!(
+        ProjectUpdate,
+        r#"
+        SELECT id, project_id, author_id, milestone_id, title, body, media_urls,
+               published_at, created_at, updated_at
+        FROM project_updates
+        WHERE id = $1 AND project_id = $2
+        "#,
+        update_id,
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(update)
+}
+
+/// Save a new update, published straight away unless `draft`
+pub async fn create(
+    pool: &PgPool,
+    project_id: Uuid,
+    author_id: Uuid,
+    draft: &UpdateDraft,
+    publish: bool,
+) -> Result<ProjectUpdate, UpdateError> {
+    check_milestone(pool, project_id, draft.milestone_id).await?;
+
+    let update = sqlx::query_as!(
+        ProjectUpdate,
+        r#"
+        INSERT INTO project_updates (project_id, author_id, milestone_id, title, body, media_urls, published_at)
+        VALUES ($1, $2, $3, $4, $5, $6, CASE WHEN $7 THEN NOW() END)
+        RETURNING id, project_id, author_id, milestone_id, title, body, media_urls,
+                  published_at, created_at, updated_at
+        "#,
+        project_id,
+        author_id,
+        draft.milestone_id,
+        draft.title,
+        draft.body,
+        &draft.media_urls[..],
+        publish
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(update)
+}
+
+/// Replace an update's content; its published state is kept
+pub async fn replace(
+    pool: &PgPool,
+    project_id: Uuid,
+    update_id: Uuid,
+    draft: &UpdateDraft,
+) -> Result<Option<ProjectUpdate>, UpdateError> {
+    check_milestone(pool, project_id, draft.milestone_id).await?;
+
+    let update = sqlx::query_as!(
+        ProjectUpdate,
+        r#"
+        UPDATE project_updates
+        SET title = $3, body = $4, media_urls = $5, milestone_id = $6, updated_at = NOW()
+        WHERE id = $1 AND project_id = $2
+        RETURNING id, project_id, author_id, milestone_id, title, body, media_urls,
+                  published_at, created_at, updated_at
+        "#,
+        update_id,
+        project_id,
+        draft.title,
+        draft.body,
+        &draft.media_urls[..],
+        draft.milestone_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(update)
+}
+
+/// Publish a draft; `None` if there's no such draft
+pub async fn publish(pool: &PgPool, project_id: Uuid, update_id: Uuid) -> Result<Option<ProjectUpdate>> {
+    let update = sqlx::query_as!(
+        ProjectUpdate,
+        r#"
+        UPDATE project_updates
+        SET published_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND project_id = $2 AND published_at IS NULL
+        RETURNING id, project_id, author_id, milestone_id, title, body, media_urls,
+                  published_at, created_at, updated_at
+        "#,
+        update_id,
+        project_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(update)
+}
+
+pub async fn delete(pool: &PgPool, project_id: Uuid, update_id: Uuid) -> Result<bool> {
+    let result = sqlx::query!(
+        "DELETE FROM project_updates WHERE id = $1 AND project_id = $2",
+        update_id,
+        project_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Tell the project's donors and followers about a newly published update,
+/// in-app. Returns how many were notified.
+pub async fn notify_published(pool: &PgPool, update: &ProjectUpdate) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata)
+        SELECT audience.user_id, 'project', 'New project update', p.title || ': ' || $3, $4::jsonb
+        FROM projects p
+        JOIN (
+            SELECT d.donor_id as user_id FROM donations d
+            WHERE d.project_id = $1 AND d.status = 'confirmed' AND d.donor_id IS NOT NULL
+            UNION
+            SELECT f.follower_id FROM follows f JOIN projects fp ON fp.id = $1
+            WHERE f.project_id = fp.id OR f.student_id = fp.student_id
+        ) audience ON audience.user_id <> $2
+        WHERE p.id = $1
+        "#,
+        update.project_id,
+        update.author_id,
+        update.title,
+        serde_json::json!({
+            "project_id": update.project_id,
+            "update_id": update.id,
+            "milestone_id": update.milestone_id
+        })
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draft_trims_and_dedupes_media() {
+        let urls: Vec<String> = ["https://cdn.example.com/a.png", " ", "https://cdn.example.com/a.png ", "https://cdn.example.com/b.png"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let draft = UpdateDraft::new("  Prototype done ", "\nWe shipped it.\n", &urls, None);
+
+        assert_eq!(draft.title, "Prototype done");
+        assert_eq!(draft.body, "We shipped it.");
+        assert_eq!(draft.media_urls, ["https://cdn.example.com/a.png", "https://cdn.example.com/b.png"]);
+    }
+}
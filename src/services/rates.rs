@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::config;
+
+const DEFAULT_RATE_API_URL: &str = "https://api.coingecko.com/api/v3/simple/price";
+
+/// Price API ids for the on-chain assets we quote
+const CRYPTO_IDS: [(&str, &str); 2] = [("XLM", "stellar"), ("USDC", "usd-coin")];
+
+/// Fiat currencies fetched alongside; every fiat a provider accepts belongs here
+pub const FIAT_CURRENCIES: [&str; 7] = ["USD", "KES", "NGN", "GHS", "UGX", "EUR", "GBP"];
+
+/// A conversion rate between two currencies
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RateQuote {
+    pub from: String,
+    pub to: String,
+    /// Units of `to` bought by one unit of `from`
+    pub rate: f64,
+    pub source: String,
+    pub fetched_at: DateTime<Utc>,
+    /// When the cached rate behind this quote is refreshed
+    pub expires_at: DateTime<Utc>,
+}
+
+/// USD value of one unit of each supported currency, from a single fetch
+#[derive(Debug, Clone)]
+struct RateTable {
+    usd_values: HashMap<String, f64>,
+    source: String,
+    fetched_at: DateTime<Utc>,
+    fetched: Instant,
+}
+
+impl RateTable {
+    /// Build from a `simple/price` response, which prices each crypto asset in
+    /// every fiat currency. Fiat values are derived through XLM's USD price,
+    /// except KES when a fixed CBK rate is configured.
+    fn from_prices(body: &serde_json::Value, kes_per_usd: Option<f64>, source: &str) -> Result<Self> {
+        let price = |id: &str, fiat: &str| {
+            body[id][fiat.to_lowercase()]
+                .as_f64()
+                .filter(|price| price.is_finite() && *price > 0.0)
+        };
+        let xlm_usd = price("stellar", "USD").ok_or_else(|| anyhow!("No XLM/USD price quoted"))?;
+
+        let mut usd_values = HashMap::new();
+        for (symbol, id) in CRYPTO_IDS {
+            if let Some(usd) = price(id, "USD") {
+                usd_values.insert(symbol.to_string(), usd);
+            }
+        }
+        for fiat in FIAT_CURRENCIES {
+            if let Some(xlm_fiat) = price("stellar", fiat) {
+                usd_values.insert(fiat.to_string(), xlm_usd / xlm_fiat);
+            }
+        }
+        usd_values.insert("USD".to_string(), 1.0);
+        if let Some(kes_per_usd) = kes_per_usd {
+            usd_values.insert("KES".to_string(), 1.0 / kes_per_usd);
+        }
+
+        Ok(Self {
+            usd_values,
+            source: source.to_string(),
+            fetched_at: Utc::now(),
+            fetched: Instant::now(),
+        })
+    }
+
+    /// Units of `to` per unit of `from`
+    fn rate(&self, from: &str, to: &str) -> Result<f64> {
+        let value = |currency: &str| {
+            self.usd_values
+                .get(currency)
+                .copied()
+                .ok_or_else(|| anyhow!("Unsupported currency {}", currency))
+        };
+        Ok(value(from)? / value(to)?)
+    }
+}
+
+/// Exchange rates for showing donors conversions and settling fiat payments.
+/// Rates are fetched for every supported currency at once and cached for
+/// `RATES_CACHE_TTL_SECS`; clones share the cache.
+#[derive(Clone)]
+pub struct Rates {
+    http: Client,
+    api_url: String,
+    ttl: Duration,
+    /// Kenyan shillings per US dollar from the CBK mean rate, overriding the market rate
+    kes_per_usd: Option<f64>,
+    cache: Arc<Mutex<Option<RateTable>>>,
+}
+
+/// Whether `currency` can be quoted
+pub fn is_supported(currency: &str) -> bool {
+    CRYPTO_IDS.iter().any(|(symbol, _)| *symbol == currency) || FIAT_CURRENCIES.contains(&currency)
+}
+
+impl Rates {
+    pub fn from_env() -> Self {
+        Self {
+            http: Client::new(),
+            api_url: std::env::var("EXCHANGE_RATE_API_URL")
+                .ok()
+                .filter(|url| !url.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_RATE_API_URL.to_string()),
+            ttl: Duration::from_secs(config::env_u32("RATES_CACHE_TTL_SECS", 60) as u64),
+            kes_per_usd: std::env::var("CBK_KES_PER_USD")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|rate: &f64| rate.is_finite() && *rate > 0.0),
+            cache: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Current rate from `from` to `to`, e.g. `quote("KES", "XLM")`
+    pub async fn quote(&self, from: &str, to: &str) -> Result<RateQuote> {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        for currency in [&from, &to] {
+            if !is_supported(currency) {
+                return Err(anyhow!("Unsupported currency {}", currency));
+            }
+        }
+
+        let table = self.table().await?;
+        let expires_at = table.fetched_at + chrono::Duration::from_std(self.ttl)?;
+        Ok(RateQuote {
+            rate: table.rate(&from, &to)?,
+            from,
+            to,
+            source: table.source,
+            fetched_at: table.fetched_at,
+            expires_at,
+        })
+    }
+
+    /// The cached table, refetched once it is older than the TTL
+    async fn table(&self) -> Result<RateTable> {
+        if let Some(table) = self.cache.lock().unwrap().as_ref() {
+            if table.fetched.elapsed() < self.ttl {
+                return Ok(table.clone());
+            }
+        }
+
+        let ids: Vec<&str> = CRYPTO_IDS.iter().map(|(_, id)| *id).collect();
+        let response = self
+            .http
+            .get(&self.api_url)
+            .query(&[
+                ("ids", ids.join(",")),
+                ("vs_currencies", FIAT_CURRENCIES.join(",").to_lowercase()),
+            ])
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Exchange rate lookup failed: {}", response.status()));
+        }
+
+        let body: serde_json::Value = response.json().await?;
+        let table = RateTable::from_prices(&body, self.kes_per_usd, &self.api_url)?;
+        *self.cache.lock().unwrap() = Some(table.clone());
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices() -> serde_json::Value {
+        serde_json::json!({
+            "stellar": {"usd": 0.1, "kes": 13.0, "ngn": 150.0, "eur": 0.0},
+            "usd-coin": {"usd": 1.0, "kes": 130.0}
+        })
+    }
+
+    #[test]
+    fn test_cross_rates_go_through_usd() {
+        let table = RateTable::from_prices(&prices(), None, "test").unwrap();
+        assert!((table.rate("KES", "XLM").unwrap() - 1.0 / 13.0).abs() < 1e-12);
+        assert!((table.rate("USD", "XLM").unwrap() - 10.0).abs() < 1e-12);
+        assert!((table.rate("USDC", "KES").unwrap() - 130.0).abs() < 1e-9);
+        assert!((table.rate("XLM", "NGN").unwrap() - 150.0).abs() < 1e-9);
+        // A zero price is treated as missing rather than dividing by it
+        assert!(table.rate("EUR", "XLM").is_err());
+    }
+
+    #[test]
+    fn test_cbk_rate_overrides_kes() {
+        let table = RateTable::from_prices(&prices(), Some(125.0), "test").unwrap();
+        assert!((table.rate("USD", "KES").unwrap() - 125.0).abs() < 1e-9);
+        assert!((table.rate("KES", "XLM").unwrap() - 0.08).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_missing_xlm_price_is_an_error() {
+        let body = serde_json::json!({"usd-coin": {"usd": 1.0}});
+        assert!(RateTable::from_prices(&body, None, "test").is_err());
+        assert!(is_supported("USDC"));
+        assert!(!is_supported("BTC"));
+    }
+}
@@ -0,0 +1,147 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub const PROJECTS_PUBLISH: &str = "projects.publish";
+pub const CAMPAIGNS_EXECUTE: &str = "campaigns.execute";
+pub const PAYMENTS_REFUND: &str = "payments.refund";
+pub const ROLES_MANAGE: &str = "roles.manage";
+
+#[derive(Debug, thiserror::Error)]
+pub enum RbacError {
+    #[error("Unknown role")]
+    UnknownRole,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("The admin role is a primary role and can't be assigned here")]
+    AdminNotAssignable,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for RbacError {
+    fn from(e: sqlx::Error) -> Self {
+        RbacError::Internal(e.into())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleInfo {
+    pub name: String,
+    pub description: String,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserRoles {
+    pub user_id: Uuid,
+    /// `users.role`
+    pub primary_role: String,
+    pub assigned_roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+/// Permissions from the user's primary role and assigned roles
+pub async fn effective_permissions(pool: &PgPool, user_id: Uuid) -> Result<Vec<String>> {
+    let permissions = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT rp.permission
+        FROM role_permissions rp
+        WHERE rp.role IN (
+            SELECT role FROM users WHERE id = $1
+            UNION
+            SELECT role FROM user_role_assignments WHERE user_id = $1
+        )
+        ORDER BY rp.permission
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(permissions)
+}
+
+pub async fn list_roles(pool: &PgPool) -> Result<Vec<RoleInfo>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT r.name, r.description,
+               COALESCE(ARRAY_AGG(rp.permission ORDER BY rp.permission)
+                        FILTER (WHERE rp.permission IS NOT NULL), '{}') as "permissions!"
+        FROM roles r
+        LEFT JOIN role_permissions rp ON rp.role = r.name
+        GROUP BY r.name, r.description
+        ORDER BY r.name
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| RoleInfo { name: r.name, description: r.description, permissions: r.permissions })
+        .collect())
+}
+
+pub async fn user_roles(pool: &PgPool, user_id: Uuid) -> Result<UserRoles, RbacError> {
+    let primary_role = sqlx::query_scalar!("SELECT role FROM users WHERE id = $1", user_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(RbacError::UserNotFound)?;
+    let assigned_roles = sqlx::query_scalar!(
+        "SELECT role FROM user_role_assignments WHERE user_id = $1 ORDER BY role",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    let permissions = effective_permissions(pool, user_id).await?;
+
+    Ok(UserRoles { user_id, primary_role, assigned_roles, permissions })
+}
+
+/// Give the user a role; false if they already had it
+pub async fn assign(pool: &PgPool, user_id: Uuid, role: &str, granted_by: Uuid) -> Result<bool, RbacError> {
+    if role == "admin" {
+        return Err(RbacError::AdminNotAssignable);
+    }
+    let role_exists = sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM roles WHERE name = $1) as "exists!""#, role)
+        .fetch_one(pool)
+        .await?;
+    if !role_exists {
+        return Err(RbacError::UnknownRole);
+    }
+    let user_exists = sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM users WHERE id = $1) as "exists!""#, user_id)
+        .fetch_one(pool)
+        .await?;
+    if !user_exists {
+        return Err(RbacError::UserNotFound);
+    }
+
+    let inserted = sqlx::query!(
+        r#"
+        INSERT INTO user_role_assignments (user_id, role, granted_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, role) DO NOTHING
+        "#,
+        user_id,
+        role,
+        granted_by
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(inserted > 0)
+}
+
+/// Take an assigned role away; false if the user didn't have it
+pub async fn revoke(pool: &PgPool, user_id: Uuid, role: &str) -> Result<bool> {
+    let removed = sqlx::query!(
+        "DELETE FROM user_role_assignments WHERE user_id = $1 AND role = $2",
+        user_id,
+        role
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(removed > 0)
+}
@@ -0,0 +1,348 @@
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Datelike, Utc};
+use ed25519_dalek::{Signer, SigningKey};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::utils::money::Stroops;
+
+/// Signs receipts with the platform's Stellar key, so anyone holding a
+/// receipt can check it against the platform's public account
+pub struct ReceiptSigner {
+    signing_key: SigningKey,
+}
+
+impl ReceiptSigner {
+    pub fn new(platform_secret: &str) -> Result<Self> {
+        let seed = stellar_strkey::ed25519::PrivateKey::from_string(platform_secret)
+            .map_err(|_| anyhow!("PLATFORM_WALLET_SECRET_KEY is not a valid Stellar secret key"))?;
+        Ok(Self { signing_key: SigningKey::from_bytes(&seed.0) })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        let secret = std::env::var("PLATFORM_WALLET_SECRET_KEY")
+            .map_err(|_| anyhow!("PLATFORM_WALLET_SECRET_KEY must be set to sign receipts"))?;
+        Self::new(&secret)
+    }
+
+    pub fn public_key(&self) -> String {
+        stellar_strkey::ed25519::PublicKey(self.signing_key.verifying_key().to_bytes()).to_string()
+    }
+
+    /// Append a signature block over `lines`: the SHA-256 of the lines joined
+    /// by newlines, and the platform key's ed25519 signature of that digest
+    pub fn sign_lines(&self, mut lines: Vec<String>) -> Vec<String> {
+        let digest = content_digest(&lines);
+        let signature = self.signing_key.sign(&digest);
+        lines.push(String::new());
+        lines.push(format!("Content SHA-256: {}", hex::encode(digest)));
+        lines.push(format!("Signed by: {}", self.public_key()));
+        lines.push(format!(
+            "Signature: {}",
+            base64::engine::general_purpose::STANDARD.encode(signature.to_bytes())
+        ));
+        lines
+    }
+}
+
+fn content_digest(lines: &[String]) -> [u8; 32] {
+    Sha256::digest(lines.join("\n").as_bytes()).into()
+}
+
+/// A rendered receipt, ready to download
+pub struct Receipt {
+    pub filename: String,
+    pub pdf: Vec<u8>,
+}
+
+struct Platform {
+    name: String,
+    tax_id: Option<String>,
+}
+
+impl Platform {
+    fn from_env() -> Self {
+        Self {
+            name: std::env::var("PLATFORM_LEGAL_NAME").unwrap_or_else(|_| "FundHub".to_string()),
+            tax_id: std::env::var("PLATFORM_TAX_ID").ok().filter(|id| !id.is_empty()),
+        }
+    }
+}
+
+/// What a single-donation receipt states
+pub struct DonationReceipt {
+    pub donation_id: Uuid,
+    pub donor: String,
+    pub project_title: Option<String>,
+    pub amount: Stroops,
+    pub payment_method: String,
+    pub tx_hash: Option<String>,
+    pub confirmed_at: DateTime<Utc>,
+    /// What the donor paid when the donation came in through a card or mobile money
+    pub fiat: Option<(BigDecimal, String)>,
+}
+
+/// Receipt numbers are stable per donation: the year it was confirmed and
+/// the start of its id
+pub fn receipt_number(donation_id: Uuid, confirmed_at: DateTime<Utc>) -> String {
+    format!(
+        "FH-{}-{}",
+        confirmed_at.year(),
+        donation_id.simple().to_string()[..8].to_uppercase()
+    )
+}
+
+fn donation_lines(receipt: &DonationReceipt, platform: &Platform, issued_at: DateTime<Utc>) -> Vec<String> {
+    let mut lines = vec![
+        format!("Receipt number: {}", receipt_number(receipt.donation_id, receipt.confirmed_at)),
+        format!("Issued: {}", issued_at.format("%Y-%m-%d %H:%M UTC")),
+        format!("Recipient platform: {}", platform.name),
+        format!("Platform tax ID: {}", platform.tax_id.as_deref().unwrap_or("n/a")),
+        String::new(),
+        format!("Donor: {}", receipt.donor),
+        format!("Donation ID: {}", receipt.donation_id),
+        format!("Project: {}", receipt.project_title.as_deref().unwrap_or("Platform")),
+        format!("Amount: {} XLM", receipt.amount),
+    ];
+    if let Some((amount, currency)) = &receipt.fiat {
+        lines.push(format!("Paid: {} {}", amount.with_scale(2), currency));
+    }
+    lines.push(format!("Payment method: {}", receipt.payment_method));
+    lines.push(format!("Confirmed: {}", receipt.confirmed_at.format("%Y-%m-%d %H:%M UTC")));
+    if let Some(tx_hash) = &receipt.tx_hash {
+        lines.push(format!("Stellar transaction: {}", tx_hash));
+    }
+    lines.push(String::new());
+    lines.push("No goods or services were provided in exchange for this donation.".to_string());
+    lines
+}
+
+/// One line of an annual summary
+pub struct AnnualEntry {
+    pub donation_id: Uuid,
+    pub project_title: Option<String>,
+    pub amount: Stroops,
+    pub confirmed_at: DateTime<Utc>,
+}
+
+fn annual_lines(donor: &str, year: i32, entries: &[AnnualEntry], platform: &Platform, issued_at: DateTime<Utc>) -> Vec<String> {
+    let mut lines = vec![
+        format!("Recipient platform: {}", platform.name),
+        format!("Platform tax ID: {}", platform.tax_id.as_deref().unwrap_or("n/a")),
+        format!("Donor: {}", donor),
+        format!("Issued: {}", issued_at.format("%Y-%m-%d %H:%M UTC")),
+        String::new(),
+    ];
+    let mut total = Stroops::ZERO;
+    for entry in entries {
+        total = total.checked_add(entry.amount).unwrap_or(total);
+        lines.push(format!(
+            "{}  {}  {} XLM  {}",
+            entry.confirmed_at.format("%Y-%m-%d"),
+            receipt_number(entry.donation_id, entry.confirmed_at),
+            entry.amount,
+            entry.project_title.as_deref().unwrap_or("Platform"),
+        ));
+    }
+    lines.push(String::new());
+    lines.push(format!("Total donated in {}: {} XLM ({} donations)", year, total, entries.len()));
+    lines.push("No goods or services were provided in exchange for these donations.".to_string());
+    lines
+}
+
+/// Where generated receipts are kept; `RECEIPTS_DIR`, default `uploads/receipts`
+fn receipts_dir() -> PathBuf {
+    PathBuf::from(std::env::var("RECEIPTS_DIR").unwrap_or_else(|_| "uploads/receipts".to_string()))
+}
+
+/// A receipt already stored for this owner and file name, if its file is still there
+async fn stored(pool: &PgPool, owner_id: Uuid, entity_type: &str, filename: &str) -> Result<Option<Vec<u8>>> {
+    let path = sqlx::query_scalar!(
+        r#"
+        SELECT path FROM files
+        WHERE owner_id = $1 AND entity_type = $2 AND filename = $3
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        owner_id,
+        entity_type,
+        filename
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    match path {
+        Some(path) => Ok(tokio::fs::read(&path).await.ok()),
+        None => Ok(None),
+    }
+}
+
+/// Write a receipt to disk and record it in `files`
+async fn store(
+    pool: &PgPool,
+    owner_id: Uuid,
+    entity_type: &str,
+    entity_id: Uuid,
+    receipt: &Receipt,
+) -> Result<()> {
+    let dir = receipts_dir().join(owner_id.to_string());
+    tokio::fs::create_dir_all(&dir).await?;
+    let path = dir.join(&receipt.filename);
+    tokio::fs::write(&path, &receipt.pdf).await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO files (owner_id, entity_type, entity_id, path, filename, mime_type, size_bytes, checksum)
+        VALUES ($1, $2, $3, $4, $5, 'application/pdf', $6, $7)
+        "#,
+        owner_id,
+        entity_type,
+        entity_id,
+        path.to_string_lossy().to_string(),
+        receipt.filename,
+        receipt.pdf.len() as i64,
+        hex::encode(Sha256::digest(&receipt.pdf))
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// The signed receipt for one of a donor's confirmed donations, generated
+/// and stored the first time it is asked for. `None` if the donation isn't
+/// theirs or isn't confirmed.
+pub async fn donation_receipt(pool: &PgPool, donor_id: Uuid, donation_id: Uuid) -> Result<Option<Receipt>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT d.id, u.username, u.email, p.title as "project_title?", d.amount as "amount: Stroops",
+               d.payment_method, d.tx_hash, COALESCE(d.confirmed_at, d.created_at) as "confirmed_at!",
+               s.fiat_amount as "fiat_amount?", s.fiat_currency as "fiat_currency?"
+        FROM donations d
+        JOIN users u ON u.id = d.donor_id
+        LEFT JOIN projects p ON p.id = d.project_id
+        LEFT JOIN fiat_settlements s ON s.donation_id = d.id
+        WHERE d.id = $1 AND d.donor_id = $2 AND d.status = 'confirmed'
+        "#,
+        donation_id,
+        donor_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let filename = format!("{}.pdf", receipt_number(row.id, row.confirmed_at));
+    if let Some(pdf) = stored(pool, donor_id, "donation_receipt", &filename).await? {
+        return Ok(Some(Receipt { filename, pdf }));
+    }
+
+    let receipt = DonationReceipt {
+        donation_id: row.id,
+        donor: format!("{} <{}>", row.username, row.email),
+        project_title: row.project_title,
+        amount: row.amount,
+        payment_method: row.payment_method,
+        tx_hash: row.tx_hash,
+        confirmed_at: row.confirmed_at,
+        fiat: row.fiat_amount.zip(row.fiat_currency),
+    };
+    let platform = Platform::from_env();
+    let lines = ReceiptSigner::from_env()?.sign_lines(donation_lines(&receipt, &platform, Utc::now()));
+    let receipt = Receipt {
+        pdf: crate::utils::pdf::text_document(&format!("{} - Donation Receipt", platform.name), &lines),
+        filename,
+    };
+    store(pool, donor_id, "donation_receipt", donation_id, &receipt).await?;
+
+    Ok(Some(receipt))
+}
+
+/// A donor's signed summary of everything they gave in `year`. Past years are
+/// generated once and stored; the current year is rebuilt on every request.
+pub async fn annual_summary(pool: &PgPool, donor_id: Uuid, year: i32) -> Result<Option<Receipt>> {
+    let donor = sqlx::query!("SELECT username, email FROM users WHERE id = $1", donor_id)
+        .fetch_optional(pool)
+        .await?;
+    let Some(donor) = donor else {
+        return Ok(None);
+    };
+
+    let filename = format!("fundhub-donations-{}.pdf", year);
+    let closed = year < Utc::now().year();
+    if closed {
+        if let Some(pdf) = stored(pool, donor_id, "annual_receipt", &filename).await? {
+            return Ok(Some(Receipt { filename, pdf }));
+        }
+    }
+
+    let entries = sqlx::query_as!(
+        AnnualEntry,
+        r#"
+        SELECT d.id as donation_id, p.title as "project_title?", d.amount as "amount: Stroops",
+               COALESCE(d.confirmed_at, d.created_at) as "confirmed_at!"
+        FROM donations d
+        LEFT JOIN projects p ON p.id = d.project_id
+        WHERE d.donor_id = $1 AND d.status = 'confirmed'
+        AND EXTRACT(YEAR FROM COALESCE(d.confirmed_at, d.created_at))::INTEGER = $2
+        ORDER BY COALESCE(d.confirmed_at, d.created_at)
+        "#,
+        donor_id,
+        year
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let platform = Platform::from_env();
+    let donor = format!("{} <{}>", donor.username, donor.email);
+    let lines = ReceiptSigner::from_env()?.sign_lines(annual_lines(&donor, year, &entries, &platform, Utc::now()));
+    let receipt = Receipt {
+        pdf: crate::utils::pdf::text_document(&format!("{} - Donations {}", platform.name, year), &lines),
+        filename,
+    };
+    if closed {
+        store(pool, donor_id, "annual_receipt", donor_id, &receipt).await?;
+    }
+
+    Ok(Some(receipt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier};
+
+    fn signer() -> ReceiptSigner {
+        let secret = stellar_strkey::ed25519::PrivateKey([1; 32]).to_string();
+        ReceiptSigner::new(&secret).unwrap()
+    }
+
+    #[test]
+    fn test_receipt_number() {
+        let id = Uuid::parse_str("5f1c2a9e-0000-4000-8000-000000000000").unwrap();
+        let confirmed_at = DateTime::parse_from_rfc3339("2025-03-04T10:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(receipt_number(id, confirmed_at), "FH-2025-5F1C2A9E");
+    }
+
+    #[test]
+    fn test_signed_lines_verify_against_platform_key() {
+        let signer = signer();
+        let lines = vec!["Donor: ada".to_string(), "Amount: 10 XLM".to_string()];
+        let signed = signer.sign_lines(lines.clone());
+        assert_eq!(&signed[..lines.len()], &lines[..]);
+
+        let signature = signed.last().unwrap().strip_prefix("Signature: ").unwrap();
+        let signature = base64::engine::general_purpose::STANDARD.decode(signature).unwrap();
+        let signature = Signature::from_slice(&signature).unwrap();
+        let verifying_key = signer.signing_key.verifying_key();
+        assert!(verifying_key.verify(&content_digest(&lines), &signature).is_ok());
+
+        // Any change to the content breaks the signature
+        let tampered = vec!["Donor: ada".to_string(), "Amount: 100 XLM".to_string()];
+        assert!(verifying_key.verify(&content_digest(&tampered), &signature).is_err());
+    }
+}
@@ -0,0 +1,447 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::payments::mpesa::B2cResult;
+use crate::routes::payments::provider::{RefundRequest, RefundStatus};
+use crate::services::ledger;
+use crate::services::notifications::NotificationEvent;
+use crate::services::payment_service::PaymentService;
+use crate::utils::money::{Cents, Stroops};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RefundError {
+    #[error("Payment not found")]
+    NotFound,
+    #[error("{0}")]
+    NotRefundable(String),
+    #[error("{0}")]
+    Provider(String),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for RefundError {
+    fn from(e: sqlx::Error) -> Self {
+        RefundError::Internal(e.into())
+    }
+}
+
+/// A refund of a fiat donation through the provider that took it
+#[derive(Debug, Clone, Serialize)]
+pub struct Refund {
+    pub id: Uuid,
+    pub payment_id: String,
+    pub provider: Option<String>,
+    /// The provider's id for the refund, once submitted
+    pub refund_id: Option<String>,
+    pub amount: Option<Cents>,
+    pub currency: Option<String>,
+    pub reason: String,
+    /// `pending`, `submitted`, `succeeded` or `failed`
+    pub status: String,
+    pub project_id: Option<Uuid>,
+    pub donation_id: Option<Uuid>,
+    /// XLM taken off the project's donation for refunds after settlement
+    pub xlm_amount: Option<Stroops>,
+    pub failure_reason: Option<String>,
+    pub requested_by: Option<Uuid>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub processed_at: Option<DateTime<Utc>>,
+}
+
+/// What a refund may return of a payment: the amount asked for, or all that
+/// is left when none is given
+pub fn refund_amount(paid: Cents, already_refunded: Cents, requested: Option<Cents>) -> Result<Cents, String> {
+    let remaining = paid
+        .checked_sub(already_refunded)
+        .filter(|remaining| remaining.is_positive())
+        .ok_or("Payment has already been refunded in full")?;
+    let amount = requested.unwrap_or(remaining);
+    if !amount.is_positive() {
+        return Err("Refund amount must be positive".to_string());
+    }
+    if amount > remaining {
+        return Err(format!("Only {} of this payment is left to refund", remaining));
+    }
+    Ok(amount)
+}
+
+/// The share of `total` that `part` of `whole` accounts for, rounded down
+pub fn pro_rata(total: Stroops, part: Cents, whole: Cents) -> Stroops {
+    if !whole.is_positive() {
+        return Stroops::ZERO;
+    }
+    let part = part.as_cents().clamp(0, whole.as_cents());
+    Stroops::from_stroops((total.as_stroops() as i128 * part as i128 / whole.as_cents() as i128) as i64)
+}
+
+pub async fn get_refund(pool: &PgPool, id: Uuid) -> anyhow::Result<Option<Refund>> {
+    let refund = sqlx::query_as!(
+        Refund,
+        r#"
+        SELECT id, payment_id, provider, refund_id, amount as "amount: Cents", currency, reason, status,
+               project_id, donation_id, xlm_amount as "xlm_amount: Stroops", failure_reason, requested_by,
+               created_at, submitted_at, processed_at
+        FROM refunds
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(refund)
+}
+
+/// Refund a completed payment through the provider that took it. The refund
+/// is recorded before the provider is called, so concurrent requests can't
+/// return more than was paid between them; a provider that rejects it marks
+/// it failed. Refunds the provider settles at once are applied straight away,
+/// the rest when their result arrives.
+pub async fn request_refund(
+    pool: &PgPool,
+    payments: &PaymentService,
+    payment_id: &str,
+    amount: Option<Cents>,
+    reason: &str,
+    requested_by: Option<Uuid>,
+) -> Result<Refund, RefundError> {
+    let mut tx = pool.begin().await?;
+    let payment = sqlx::query!(
+        r#"
+        SELECT payment_method, status, amount, currency, project_id, provider_reference
+        FROM payment_instructions
+        WHERE payment_id = $1
+        FOR UPDATE
+        "#,
+        payment_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(RefundError::NotFound)?;
+
+    if payment.status != "completed" {
+        return Err(RefundError::NotRefundable(format!("Payment is {}, not completed", payment.status)));
+    }
+    let (Some(paid), Some(currency)) = (payment.amount, payment.currency) else {
+        return Err(RefundError::NotRefundable("Payment has no recorded amount".to_string()));
+    };
+    let paid = Cents::from_decimal(&paid).map_err(anyhow::Error::from)?;
+
+    let settling = sqlx::query_scalar!(
+        "SELECT status FROM fiat_settlements WHERE provider = $1 AND payment_id = $2",
+        payment.payment_method,
+        payment_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .flatten();
+    if settling.as_deref() == Some("submitting") {
+        return Err(RefundError::NotRefundable("Payment is being settled on-chain; try again shortly".to_string()));
+    }
+
+    // Refunds recorded without an amount returned the whole payment
+    let already_refunded = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(COALESCE(amount, $2)), 0) as "total!: Cents"
+        FROM refunds
+        WHERE payment_id = $1 AND status <> 'failed'
+        "#,
+        payment_id,
+        paid.to_decimal()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let amount = refund_amount(paid, already_refunded, amount).map_err(RefundError::NotRefundable)?;
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO refunds (payment_id, provider, amount, currency, reason, project_id, requested_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+        payment_id,
+        payment.payment_method,
+        amount.to_decimal(),
+        currency,
+        reason,
+        payment.project_id,
+        requested_by
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let request = RefundRequest {
+        payment_id: payment_id.to_string(),
+        amount: Some(amount),
+        reason: reason.to_string(),
+        provider_reference: payment.provider_reference,
+    };
+    match payments.process_refund(&payment.payment_method, request).await {
+        Ok(result) => {
+            sqlx::query!(
+                r#"
+                UPDATE refunds
+                SET refund_id = $2, status = 'submitted', submitted_at = NOW(), updated_at = NOW()
+                WHERE id = $1 AND status = 'pending'
+                "#,
+                id,
+                result.refund_id
+            )
+            .execute(pool)
+            .await?;
+            match result.status {
+                RefundStatus::Succeeded => {
+                    settle_refund(pool, id, true, None).await?;
+                }
+                RefundStatus::Failed => {
+                    settle_refund(pool, id, false, Some("Rejected by the provider")).await?;
+                }
+                RefundStatus::Pending => {}
+            }
+        }
+        Err(e) => {
+            settle_refund(pool, id, false, Some(&e)).await?;
+            return Err(RefundError::Provider(e));
+        }
+    }
+
+    get_refund(pool, id)
+        .await?
+        .ok_or_else(|| RefundError::Internal(anyhow::anyhow!("Refund {} disappeared", id)))
+}
+
+/// Settle a submitted M-Pesa refund from its reversal result. Returns `None`
+/// for results that match no outstanding refund.
+pub async fn apply_reversal_result(pool: &PgPool, result: &B2cResult) -> anyhow::Result<Option<Refund>> {
+    let id = sqlx::query_scalar!(
+        "SELECT id FROM refunds WHERE provider = 'mpesa' AND refund_id = $1 AND status = 'submitted'",
+        result.conversation_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    match id {
+        Some(id) => {
+            let failure = (!result.successful).then_some(result.description.as_str());
+            settle_refund(pool, id, result.successful, failure).await
+        }
+        None => Ok(None),
+    }
+}
+
+/// Move an outstanding refund to `succeeded` or `failed`. A successful refund
+/// comes off what the project raised: from the fiat still waiting to be
+/// converted, or from the donation it was converted into. The platform fee
+/// on that donation is kept. Returns `None` if the refund was already settled.
+pub async fn settle_refund(pool: &PgPool, id: Uuid, successful: bool, failure: Option<&str>) -> anyhow::Result<Option<Refund>> {
+    let mut tx = pool.begin().await?;
+    let refund = sqlx::query!(
+        r#"
+        UPDATE refunds
+        SET status = $2, failure_reason = $3, processed_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND status IN ('pending', 'submitted')
+        RETURNING payment_id, provider, refund_id, amount as "amount: Cents", currency
+        "#,
+        id,
+        if successful { "succeeded" } else { "failed" },
+        failure
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(refund) = refund else {
+        return Ok(None);
+    };
+
+    if successful {
+        let provider = refund.provider.unwrap_or_default();
+        let currency = refund.currency.unwrap_or_default();
+        let amount = refund.amount.unwrap_or(Cents::ZERO);
+
+        let settlement = sqlx::query!(
+            r#"
+            SELECT s.id, s.status, s.project_id, s.fiat_amount, s.xlm_amount, s.donation_id,
+                   d.amount as "donation_amount?", f.net_amount as "net_amount?"
+            FROM fiat_settlements s
+            LEFT JOIN donations d ON d.id = s.donation_id
+            LEFT JOIN donation_fees f ON f.donation_id = s.donation_id
+            WHERE s.provider = $1 AND s.payment_id = $2
+            FOR UPDATE OF s
+            "#,
+            provider,
+            refund.payment_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let mut unsettled_project = None;
+        if let Some(settlement) = settlement {
+            let fiat_amount = Cents::from_decimal(&settlement.fiat_amount)?;
+            match (settlement.donation_id, settlement.xlm_amount, settlement.project_id) {
+                (Some(donation_id), Some(xlm_amount), Some(project_id)) if settlement.status.as_deref() == Some("completed") => {
+                    let xlm_amount = Stroops::from_decimal(&xlm_amount)?;
+                    let donation_amount = match &settlement.donation_amount {
+                        Some(amount) => Stroops::from_decimal(amount)?,
+                        None => xlm_amount,
+                    };
+                    let net = match &settlement.net_amount {
+                        Some(net) => Stroops::from_decimal(net)?,
+                        None => xlm_amount,
+                    };
+
+                    // Refunds since conversion, this one included; the one
+                    // that completes them takes whatever the others left
+                    let refunded = sqlx::query_scalar!(
+                        r#"
+                        SELECT COALESCE(SUM(amount), 0) as "total!: Cents"
+                        FROM refunds
+                        WHERE status = 'succeeded' AND (donation_id = $1 OR id = $2)
+                        "#,
+                        donation_id,
+                        id
+                    )
+                    .fetch_one(&mut *tx)
+                    .await?;
+                    let in_full = refunded >= fiat_amount;
+                    let taken = if in_full { donation_amount } else { pro_rata(xlm_amount, amount, fiat_amount).min(donation_amount) };
+
+                    sqlx::query!(
+                        r#"
+                        UPDATE donations
+                        SET amount = CASE WHEN $3 THEN amount ELSE amount - $2 END,
+                            status = CASE WHEN $3 THEN 'refunded' ELSE status END
+                        WHERE id = $1
+                        "#,
+                        donation_id,
+                        taken.to_decimal(),
+                        in_full
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    sqlx::query!(
+                        "UPDATE refunds SET project_id = $2, donation_id = $3, xlm_amount = $4 WHERE id = $1",
+                        id,
+                        project_id,
+                        donation_id,
+                        taken.to_decimal()
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+
+                    let clawback = pro_rata(net, amount, fiat_amount);
+                    if clawback.is_positive() {
+                        ledger::post_in(&mut tx, &ledger::clawback_entry(id, project_id, clawback)).await?;
+                    }
+                }
+                (_, _, project_id) => {
+                    // Not converted yet: the reconciler settles only what is left
+                    let reduced = sqlx::query!(
+                        r#"
+                        UPDATE fiat_settlements
+                        SET fiat_amount = GREATEST(fiat_amount - $2, 0),
+                            status = CASE WHEN fiat_amount - $2 <= 0 THEN 'cancelled' ELSE status END
+                        WHERE id = $1 AND status IN ('pending', 'failed')
+                        "#,
+                        settlement.id,
+                        amount.to_decimal()
+                    )
+                    .execute(&mut *tx)
+                    .await?
+                    .rows_affected();
+                    if reduced > 0 {
+                        unsettled_project = project_id;
+                    } else {
+                        tracing::warn!(
+                            "Refund {} settled while payment {} was being converted; the project keeps its XLM",
+                            id,
+                            refund.payment_id
+                        );
+                    }
+                }
+            }
+        }
+
+        if amount.is_positive() {
+            let entry = ledger::refund_entry(id, &provider, &currency, amount, unsettled_project);
+            ledger::post_in(&mut tx, &entry).await?;
+        }
+
+        // Donors with an account hear about it in-app
+        sqlx::query!(
+            r#"
+            INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+            SELECT u.id, 'donation', $2, $3, $4, $5
+            FROM payment_instructions p
+            JOIN users u ON LOWER(u.email) = LOWER(p.donor_email)
+            WHERE p.payment_id = $1
+            "#,
+            refund.payment_id,
+            "Donation refunded",
+            format!("{} {} of your donation has been refunded", amount, currency),
+            serde_json::json!({
+                "refund_id": id,
+                "payment_id": refund.payment_id,
+                "provider": provider,
+                "amount": amount,
+                "currency": currency
+            }),
+            NotificationEvent::DonationRefunded { refund_id: id, project_id: None }.to_json()
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO activity_logs (action, target_id, target_type, metadata)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        if successful { "refund_succeeded" } else { "refund_failed" },
+        id,
+        "refund",
+        serde_json::json!({
+            "payment_id": refund.payment_id,
+            "provider_refund_id": refund.refund_id,
+            "amount": refund.amount,
+            "failure": failure
+        })
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    get_refund(pool, id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refund_amount_stays_within_what_was_paid() {
+        let paid = Cents::from_cents(10_000);
+        assert_eq!(refund_amount(paid, Cents::ZERO, None).unwrap(), paid);
+        assert_eq!(refund_amount(paid, Cents::from_cents(2_500), None).unwrap(), Cents::from_cents(7_500));
+        assert_eq!(
+            refund_amount(paid, Cents::from_cents(2_500), Some(Cents::from_cents(7_500))).unwrap(),
+            Cents::from_cents(7_500)
+        );
+        assert!(refund_amount(paid, Cents::from_cents(2_500), Some(Cents::from_cents(7_501))).is_err());
+        assert!(refund_amount(paid, paid, None).is_err());
+        assert!(refund_amount(paid, Cents::ZERO, Some(Cents::ZERO)).is_err());
+    }
+
+    #[test]
+    fn test_pro_rata_rounds_down() {
+        let xlm = Stroops::from_stroops(1_000_000_001);
+        assert_eq!(pro_rata(xlm, Cents::from_cents(1), Cents::from_cents(3)), Stroops::from_stroops(333_333_333));
+        assert_eq!(pro_rata(xlm, Cents::from_cents(3), Cents::from_cents(3)), xlm);
+        // Never more than the whole, and nothing of nothing
+        assert_eq!(pro_rata(xlm, Cents::from_cents(5), Cents::from_cents(3)), xlm);
+        assert_eq!(pro_rata(xlm, Cents::from_cents(1), Cents::ZERO), Stroops::ZERO);
+    }
+}
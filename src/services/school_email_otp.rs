@@ -0,0 +1,231 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::email::{self, EmailTemplate};
+use crate::services::email_verification::hash_token;
+
+/// How long an emailed code works
+pub const CODE_TTL_MINUTES: i64 = 15;
+/// Wrong guesses allowed per code
+pub const MAX_ATTEMPTS: i32 = 5;
+/// Shortest gap between two codes for one application
+pub const RESEND_COOLDOWN_SECS: i64 = 60;
+
+/// Why a code can't be redeemed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CodeError {
+    #[error("The school email is already confirmed")]
+    AlreadyConfirmed,
+    #[error("Request a new code")]
+    NoCode,
+    #[error("The code has expired; request a new one")]
+    Expired,
+    #[error("Too many wrong codes; request a new one")]
+    TooManyAttempts,
+    #[error("Incorrect code, {0} attempts left")]
+    Incorrect(i32),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OtpError {
+    #[error("Verification not found")]
+    NotFound,
+    #[error("Wait {0} seconds before requesting another code")]
+    CoolingDown(i64),
+    #[error(transparent)]
+    Code(#[from] CodeError),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for OtpError {
+    fn from(e: sqlx::Error) -> Self {
+        OtpError::Internal(e.into())
+    }
+}
+
+/// A random six-digit code
+pub fn new_code() -> String {
+    format!("{:06}", rand::thread_rng().gen_range(0..1_000_000))
+}
+
+/// Codes are short, so they're hashed with the application they belong to
+pub fn hash_code(verification_id: Uuid, code: &str) -> String {
+    hash_token(&format!("{}:{}", verification_id, code.trim()))
+}
+
+/// Where an application's current code stands
+#[derive(Debug, Clone)]
+pub struct CodeState {
+    pub confirmed: bool,
+    pub code_hash: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub attempts: i32,
+}
+
+/// Whether `code_hash` redeems the application's code as of `now`
+pub fn check(state: &CodeState, code_hash: &str, now: DateTime<Utc>) -> Result<(), CodeError> {
+    if state.confirmed {
+        return Err(CodeError::AlreadyConfirmed);
+    }
+    let (Some(expected), Some(expires_at)) = (&state.code_hash, state.expires_at) else {
+        return Err(CodeError::NoCode);
+    };
+    if state.attempts >= MAX_ATTEMPTS {
+        return Err(CodeError::TooManyAttempts);
+    }
+    if expires_at <= now {
+        return Err(CodeError::Expired);
+    }
+    if expected != code_hash {
+        return Err(CodeError::Incorrect(MAX_ATTEMPTS - state.attempts - 1));
+    }
+    Ok(())
+}
+
+/// Email a fresh code to the application's school address, replacing any
+/// earlier one
+pub async fn send(pool: &PgPool, verification_id: Uuid) -> Result<(), OtpError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT sv.school_email, sv.school_email_confirmed_at, sv.school_email_code_sent_at, u.username
+        FROM student_verifications sv
+        JOIN users u ON u.id = sv.user_id
+        WHERE sv.id = $1
+        "#,
+        verification_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(OtpError::NotFound)?;
+    if row.school_email_confirmed_at.is_some() {
+        return Err(CodeError::AlreadyConfirmed.into());
+    }
+    if let Some(sent_at) = row.school_email_code_sent_at {
+        let wait = RESEND_COOLDOWN_SECS - (Utc::now() - sent_at).num_seconds();
+        if wait > 0 {
+            return Err(OtpError::CoolingDown(wait));
+        }
+    }
+
+    let code = new_code();
+    sqlx::query!(
+        r#"
+        UPDATE student_verifications
+        SET school_email_code_hash = $2, school_email_code_expires_at = $3,
+            school_email_code_sent_at = NOW(), school_email_code_attempts = 0
+        WHERE id = $1
+        "#,
+        verification_id,
+        hash_code(verification_id, &code),
+        Utc::now() + Duration::minutes(CODE_TTL_MINUTES)
+    )
+    .execute(pool)
+    .await?;
+
+    let template = EmailTemplate::SchoolEmailCode { username: row.username, code };
+    email::queue(pool, &row.school_email, &template, None).await?;
+    Ok(())
+}
+
+/// Redeem a code, confirming the school address. A wrong code uses up one
+/// of the code's attempts.
+pub async fn confirm(pool: &PgPool, verification_id: Uuid, code: &str) -> Result<(), OtpError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT school_email_confirmed_at, school_email_code_hash, school_email_code_expires_at,
+               school_email_code_attempts
+        FROM student_verifications
+        WHERE id = $1
+        "#,
+        verification_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or(OtpError::NotFound)?;
+    let state = CodeState {
+        confirmed: row.school_email_confirmed_at.is_some(),
+        code_hash: row.school_email_code_hash,
+        expires_at: row.school_email_code_expires_at,
+        attempts: row.school_email_code_attempts,
+    };
+
+    if let Err(e) = check(&state, &hash_code(verification_id, code), Utc::now()) {
+        if matches!(e, CodeError::Incorrect(_)) {
+            sqlx::query!(
+                "UPDATE student_verifications SET school_email_code_attempts = school_email_code_attempts + 1 WHERE id = $1",
+                verification_id
+            )
+            .execute(pool)
+            .await?;
+        }
+        return Err(e.into());
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE student_verifications
+        SET school_email_confirmed_at = NOW(), school_email_code_hash = NULL, school_email_code_expires_at = NULL
+        WHERE id = $1
+        "#,
+        verification_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Whether the user has confirmed the school email on any of their applications
+pub async fn confirmed(pool: &PgPool, user_id: Uuid) -> Result<bool> {
+    let confirmed = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM student_verifications WHERE user_id = $1 AND school_email_confirmed_at IS NOT NULL
+        ) as "confirmed!"
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(confirmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_code_is_six_digits() {
+        let code = new_code();
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_check() {
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+        let state = CodeState {
+            confirmed: false,
+            code_hash: Some(hash_code(id, "123456")),
+            expires_at: Some(now + Duration::minutes(5)),
+            attempts: 0,
+        };
+        assert_eq!(check(&state, &hash_code(id, " 123456 "), now), Ok(()));
+        assert_eq!(check(&state, &hash_code(id, "654321"), now), Err(CodeError::Incorrect(4)));
+        assert_eq!(check(&state, &hash_code(Uuid::new_v4(), "123456"), now), Err(CodeError::Incorrect(4)));
+        assert_eq!(
+            check(&state, &hash_code(id, "123456"), now + Duration::minutes(6)),
+            Err(CodeError::Expired)
+        );
+        let exhausted = CodeState { attempts: MAX_ATTEMPTS, ..state.clone() };
+        assert_eq!(check(&exhausted, &hash_code(id, "123456"), now), Err(CodeError::TooManyAttempts));
+        let confirmed = CodeState { confirmed: true, ..state.clone() };
+        assert_eq!(check(&confirmed, &hash_code(id, "123456"), now), Err(CodeError::AlreadyConfirmed));
+        let unsent = CodeState { code_hash: None, expires_at: None, ..state };
+        assert_eq!(check(&unsent, &hash_code(id, "123456"), now), Err(CodeError::NoCode));
+    }
+}
@@ -0,0 +1,292 @@
+use anyhow::{anyhow, bail, Result};
+use base64::Engine;
+use chrono::{DateTime, TimeZone, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::RngCore;
+use stellar_xdr::curr::{self as xdr, Limits, ReadXdr, WriteXdr};
+
+use crate::services::soroban_rpc;
+
+/// How long a challenge may be signed and returned
+pub const CHALLENGE_TTL_SECS: i64 = 300;
+/// Random bytes per nonce; base64 encoded they fill the 64-byte data value
+const NONCE_BYTES: usize = 48;
+const BASE_FEE: u32 = 100;
+
+/// A challenge transaction handed to a wallet for signing
+#[derive(Debug, Clone)]
+pub struct Challenge {
+    /// Base64 XDR envelope, already signed by the server
+    pub transaction: String,
+    pub nonce: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// SEP-10 web authentication: issues challenge transactions signed by the
+/// platform account and checks that wallets have co-signed them, proving
+/// the caller controls the wallet's secret key
+#[derive(Clone)]
+pub struct WebAuth {
+    signing_key: SigningKey,
+    network_passphrase: String,
+    home_domain: String,
+}
+
+impl WebAuth {
+    pub fn new(server_secret: &str, network_passphrase: &str, home_domain: &str) -> Result<Self> {
+        let seed = stellar_strkey::ed25519::PrivateKey::from_string(server_secret)
+            .map_err(|_| anyhow!("PLATFORM_WALLET_SECRET_KEY is not a valid Stellar secret key"))?;
+
+        Ok(Self {
+            signing_key: SigningKey::from_bytes(&seed.0),
+            network_passphrase: network_passphrase.to_string(),
+            home_domain: home_domain.to_string(),
+        })
+    }
+
+    pub fn network_passphrase(&self) -> &str {
+        &self.network_passphrase
+    }
+
+    /// Build a challenge for `client_account`, valid for `CHALLENGE_TTL_SECS`
+    pub fn challenge(&self, client_account: &str) -> Result<Challenge> {
+        let client = parse_account(client_account)?;
+
+        let mut random = [0u8; NONCE_BYTES];
+        rand::thread_rng().fill_bytes(&mut random);
+        let nonce = base64::engine::general_purpose::STANDARD.encode(random);
+
+        let now = Utc::now().timestamp();
+        let expires_at = now + CHALLENGE_TTL_SECS;
+
+        let operations = vec![
+            manage_data(Some(client), &self.auth_key(), nonce.as_bytes())?,
+            manage_data(Some(self.server_key()), "web_auth_domain", self.home_domain.as_bytes())?,
+        ];
+
+        let tx = xdr::Transaction {
+            source_account: xdr::MuxedAccount::Ed25519(xdr::Uint256(self.server_key())),
+            fee: BASE_FEE * operations.len() as u32,
+            seq_num: xdr::SequenceNumber(0),
+            cond: xdr::Preconditions::Time(xdr::TimeBounds {
+                min_time: xdr::TimePoint(now as u64),
+                max_time: xdr::TimePoint(expires_at as u64),
+            }),
+            memo: xdr::Memo::None,
+            operations: operations.try_into()?,
+            ext: xdr::TransactionExt::V0,
+        };
+
+        let hash = soroban_rpc::transaction_hash(&tx, &self.network_passphrase)?;
+        let signature = decorated_signature(&self.signing_key, &hash)?;
+        let envelope = xdr::TransactionEnvelope::Tx(xdr::TransactionV1Envelope {
+            tx,
+            signatures: vec![signature].try_into()?,
+        });
+
+        Ok(Challenge {
+            transaction: envelope.to_xdr_base64(Limits::none())?,
+            nonce,
+            expires_at: Utc.timestamp_opt(expires_at, 0).single().unwrap_or_else(Utc::now),
+        })
+    }
+
+    /// Check a signed challenge returned by `client_account` and return its
+    /// nonce. The caller must still confirm the nonce was issued and unused.
+    pub fn verify(&self, transaction: &str, client_account: &str) -> Result<String> {
+        let client = parse_account(client_account)?;
+
+        let envelope = xdr::TransactionEnvelope::from_xdr_base64(transaction, Limits::none())
+            .map_err(|_| anyhow!("Challenge is not a valid transaction envelope"))?;
+        let xdr::TransactionEnvelope::Tx(envelope) = envelope else {
+            bail!("Challenge must be a v1 transaction");
+        };
+        let tx = &envelope.tx;
+
+        if tx.source_account != xdr::MuxedAccount::Ed25519(xdr::Uint256(self.server_key())) {
+            bail!("Challenge was not issued by this server");
+        }
+        if tx.seq_num.0 != 0 {
+            bail!("Challenge sequence number must be zero");
+        }
+
+        let xdr::Preconditions::Time(bounds) = &tx.cond else {
+            bail!("Challenge has no time bounds");
+        };
+        let now = Utc::now().timestamp() as u64;
+        if now < bounds.min_time.0 || now > bounds.max_time.0 {
+            bail!("Challenge has expired");
+        }
+
+        let mut operations = tx.operations.iter();
+        let nonce = match operations.next().map(|op| (&op.source_account, &op.body)) {
+            Some((Some(source), xdr::OperationBody::ManageData(data)))
+                if *source == xdr::MuxedAccount::Ed25519(xdr::Uint256(client)) =>
+            {
+                if data.data_name.0.as_slice() != self.auth_key().as_bytes() {
+                    bail!("Challenge is for a different home domain");
+                }
+                let value = data.data_value.as_ref().ok_or_else(|| anyhow!("Challenge nonce is missing"))?;
+                String::from_utf8(value.0.to_vec()).map_err(|_| anyhow!("Challenge nonce is malformed"))?
+            }
+            _ => bail!("Challenge is not for this account"),
+        };
+
+        // Any further operations must be the server's own
+        for op in operations {
+            let server_op = matches!(
+                (&op.source_account, &op.body),
+                (Some(source), xdr::OperationBody::ManageData(_))
+                    if *source == xdr::MuxedAccount::Ed25519(xdr::Uint256(self.server_key()))
+            );
+            if !server_op {
+                bail!("Challenge contains an unexpected operation");
+            }
+        }
+
+        let hash = soroban_rpc::transaction_hash(tx, &self.network_passphrase)?;
+        if !is_signed_by(&envelope.signatures, &self.server_key(), &hash) {
+            bail!("Challenge is missing the server signature");
+        }
+        if !is_signed_by(&envelope.signatures, &client, &hash) {
+            bail!("Challenge is not signed by {}", client_account);
+        }
+
+        Ok(nonce)
+    }
+
+    fn server_key(&self) -> [u8; 32] {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    fn auth_key(&self) -> String {
+        format!("{} auth", self.home_domain)
+    }
+}
+
+/// Domain that challenges are issued for, from `SEP10_HOME_DOMAIN`
+pub fn configured_home_domain() -> String {
+    std::env::var("SEP10_HOME_DOMAIN")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+fn parse_account(account: &str) -> Result<[u8; 32]> {
+    stellar_strkey::ed25519::PublicKey::from_string(account)
+        .map(|key| key.0)
+        .map_err(|_| anyhow!("Invalid Stellar account {}", account))
+}
+
+fn manage_data(source: Option<[u8; 32]>, name: &str, value: &[u8]) -> Result<xdr::Operation> {
+    Ok(xdr::Operation {
+        source_account: source.map(|key| xdr::MuxedAccount::Ed25519(xdr::Uint256(key))),
+        body: xdr::OperationBody::ManageData(xdr::ManageDataOp {
+            data_name: xdr::String64(name.as_bytes().to_vec().try_into()?),
+            data_value: Some(xdr::DataValue(value.to_vec().try_into()?)),
+        }),
+    })
+}
+
+fn decorated_signature(key: &SigningKey, hash: &[u8; 32]) -> Result<xdr::DecoratedSignature> {
+    let public_key = key.verifying_key().to_bytes();
+    Ok(xdr::DecoratedSignature {
+        hint: xdr::SignatureHint(public_key[28..].try_into()?),
+        signature: xdr::Signature(key.sign(hash).to_bytes().to_vec().try_into()?),
+    })
+}
+
+fn is_signed_by(signatures: &[xdr::DecoratedSignature], public_key: &[u8; 32], hash: &[u8; 32]) -> bool {
+    let Ok(verifying_key) = VerifyingKey::from_bytes(public_key) else {
+        return false;
+    };
+
+    signatures
+        .iter()
+        .filter(|s| s.hint.0 == public_key[28..])
+        .filter_map(|s| Signature::from_slice(s.signature.0.as_slice()).ok())
+        .any(|signature| verifying_key.verify(hash, &signature).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &str = "Test SDF Network ; September 2015";
+
+    fn keypair(seed: u8) -> (SigningKey, String) {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        let account = stellar_strkey::ed25519::PublicKey(key.verifying_key().to_bytes()).to_string();
+        (key, account)
+    }
+
+    fn server() -> WebAuth {
+        let secret = stellar_strkey::ed25519::PrivateKey([1; 32]).to_string();
+        WebAuth::new(&secret, PASSPHRASE, "fundhub.test").unwrap()
+    }
+
+    /// Add the client's signature, as a wallet would
+    fn co_sign(transaction: &str, key: &SigningKey) -> String {
+        let xdr::TransactionEnvelope::Tx(mut envelope) =
+            xdr::TransactionEnvelope::from_xdr_base64(transaction, Limits::none()).unwrap()
+        else {
+            panic!("expected a v1 envelope");
+        };
+        let hash = soroban_rpc::transaction_hash(&envelope.tx, PASSPHRASE).unwrap();
+        let mut signatures = envelope.signatures.to_vec();
+        signatures.push(decorated_signature(key, &hash).unwrap());
+        envelope.signatures = signatures.try_into().unwrap();
+        xdr::TransactionEnvelope::Tx(envelope).to_xdr_base64(Limits::none()).unwrap()
+    }
+
+    #[test]
+    fn test_signed_challenge_verifies() {
+        let auth = server();
+        let (client_key, client) = keypair(2);
+
+        let challenge = auth.challenge(&client).unwrap();
+        let signed = co_sign(&challenge.transaction, &client_key);
+
+        assert_eq!(auth.verify(&signed, &client).unwrap(), challenge.nonce);
+        assert_eq!(challenge.nonce.len(), 64);
+    }
+
+    #[test]
+    fn test_unsigned_challenge_is_rejected() {
+        let auth = server();
+        let (_, client) = keypair(2);
+
+        let challenge = auth.challenge(&client).unwrap();
+        assert!(auth.verify(&challenge.transaction, &client).is_err());
+    }
+
+    #[test]
+    fn test_signature_from_another_wallet_is_rejected() {
+        let auth = server();
+        let (_, client) = keypair(2);
+        let (other_key, other) = keypair(3);
+
+        let challenge = auth.challenge(&client).unwrap();
+        let signed = co_sign(&challenge.transaction, &other_key);
+
+        assert!(auth.verify(&signed, &client).is_err());
+        assert!(auth.verify(&signed, &other).is_err());
+    }
+
+    #[test]
+    fn test_challenge_from_another_server_is_rejected() {
+        let auth = server();
+        let other_server = WebAuth::new(
+            &stellar_strkey::ed25519::PrivateKey([9; 32]).to_string(),
+            PASSPHRASE,
+            "fundhub.test",
+        )
+        .unwrap();
+        let (client_key, client) = keypair(2);
+
+        let challenge = other_server.challenge(&client).unwrap();
+        let signed = co_sign(&challenge.transaction, &client_key);
+
+        assert!(auth.verify(&signed, &client).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use std::io::Cursor;
+
+use anyhow::Result;
+use base64::Engine;
+use image::{ImageOutputFormat, Luma};
+use qrcode::QrCode;
+
+use crate::utils::money::Stroops;
+
+/// Passphrase wallets assume when a URI doesn't name a network
+const PUBLIC_NETWORK_PASSPHRASE: &str = "Public Global Stellar Network ; September 2015";
+/// Smallest rendered QR code edge, in pixels
+const QR_MIN_SIZE: u32 = 256;
+
+/// A SEP-7 `web+stellar:pay` request
+#[derive(Debug, Clone)]
+pub struct PayRequest<'a> {
+    pub destination: &'a str,
+    pub amount: Stroops,
+    pub memo: Option<&'a str>,
+    /// `None` for native XLM, otherwise `(code, issuer)`
+    pub asset: Option<(&'a str, &'a str)>,
+    pub network_passphrase: &'a str,
+}
+
+impl PayRequest<'_> {
+    /// Render as a `web+stellar:pay?...` URI
+    pub fn to_uri(&self) -> String {
+        let mut params = vec![
+            ("destination", self.destination.to_string()),
+            ("amount", self.amount.to_string()),
+        ];
+        if let Some((code, issuer)) = self.asset {
+            params.push(("asset_code", code.to_string()));
+            params.push(("asset_issuer", issuer.to_string()));
+        }
+        if let Some(memo) = self.memo {
+            params.push(("memo", memo.to_string()));
+            params.push(("memo_type", "MEMO_TEXT".to_string()));
+        }
+        // Wallets default to the public network, so only other networks are named
+        if self.network_passphrase != PUBLIC_NETWORK_PASSPHRASE {
+            params.push(("network_passphrase", self.network_passphrase.to_string()));
+        }
+
+        let query = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, percent_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+        format!("web+stellar:pay?{}", query)
+    }
+}
+
+/// Render `data` as a QR code and return the PNG, base64 encoded
+pub fn qr_png_base64(data: &str) -> Result<String> {
+    let code = QrCode::new(data.as_bytes())?;
+    let image = code
+        .render::<Luma<u8>>()
+        .min_dimensions(QR_MIN_SIZE, QR_MIN_SIZE)
+        .build();
+
+    let mut png = Cursor::new(Vec::new());
+    image::DynamicImage::ImageLuma8(image).write_to(&mut png, ImageOutputFormat::Png)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(png.into_inner()))
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESTINATION: &str = "GAUZUPTHOMSZEV65VNSRMUDAAE4VBMSRYYAX3UOWYU3BQUZ6OK65NOWM";
+
+    #[test]
+    fn test_native_pay_uri() {
+        let request = PayRequest {
+            destination: DESTINATION,
+            amount: "12.5".parse().unwrap(),
+            memo: Some("fh-0123456789abcdef"),
+            asset: None,
+            network_passphrase: "Test SDF Network ; September 2015",
+        };
+
+        assert_eq!(
+            request.to_uri(),
+            format!(
+                "web+stellar:pay?destination={}&amount=12.5000000&memo=fh-0123456789abcdef&memo_type=MEMO_TEXT\
+                 &network_passphrase=Test%20SDF%20Network%20%3B%20September%202015",
+                DESTINATION
+            )
+        );
+    }
+
+    #[test]
+    fn test_public_network_and_asset() {
+        let request = PayRequest {
+            destination: DESTINATION,
+            amount: "1".parse().unwrap(),
+            memo: None,
+            asset: Some(("USDC", "GISSUER")),
+            network_passphrase: PUBLIC_NETWORK_PASSPHRASE,
+        };
+
+        let uri = request.to_uri();
+        assert!(uri.contains("&asset_code=USDC&asset_issuer=GISSUER"));
+        assert!(!uri.contains("network_passphrase"));
+        assert!(!uri.contains("memo"));
+    }
+
+    #[test]
+    fn test_qr_png_base64() {
+        let encoded = qr_png_base64("web+stellar:pay?destination=G").unwrap();
+        let png = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+}
@@ -0,0 +1,304 @@
+use anyhow::{anyhow, Result};
+use axum::http::HeaderMap;
+use std::net::SocketAddr;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::services::email_verification::new_token;
+use crate::utils::client_ip::{client_ip, TrustedProxies};
+
+/// How long a refresh token may be exchanged; each use issues a new one
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("Invalid or expired refresh token")]
+    InvalidToken,
+    #[error("Refresh token was already used; the session has been revoked")]
+    TokenReused { user_id: Uuid, session_id: Uuid },
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for SessionError {
+    fn from(e: sqlx::Error) -> Self {
+        SessionError::Internal(e.into())
+    }
+}
+
+/// The device a session was started or last refreshed from
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl ClientInfo {
+    /// `peer` is the connecting socket; forwarded headers are only believed
+    /// when it's one of `TRUSTED_PROXIES`
+    pub fn from_request(headers: &HeaderMap, peer: Option<SocketAddr>) -> Self {
+        Self::with_proxies(headers, peer, TrustedProxies::from_env())
+    }
+
+    fn with_proxies(headers: &HeaderMap, peer: Option<SocketAddr>, trusted: &TrustedProxies) -> Self {
+        let user_agent = headers
+            .get("user-agent")
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|ua| truncate(ua, 255));
+        let ip_address = client_ip(headers, peer, trusted).map(|ip| ip.to_string());
+
+        Self { user_agent, ip_address }
+    }
+}
+
+fn truncate(value: &str, max_chars: usize) -> String {
+    value.chars().take(max_chars).collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    /// Whether this is the session the request was made from
+    pub current: bool,
+}
+
+/// A refresh token and the session it belongs to
+#[derive(Debug)]
+pub struct IssuedRefresh {
+    pub user_id: Uuid,
+    pub session_id: Uuid,
+    pub token: String,
+}
+
+/// Refresh tokens are stored as HMAC-SHA256 under a server-side pepper:
+/// deterministic so they can be looked up, useless without the pepper
+pub fn hash_refresh_token(token: &str) -> Result<String> {
+    let pepper = std::env::var("REFRESH_TOKEN_PEPPER")
+        .or_else(|_| std::env::var("JWT_SECRET"))
+        .ok()
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| anyhow!("REFRESH_TOKEN_PEPPER is not set"))?;
+    Ok(hmac_hex(pepper.as_bytes(), token))
+}
+
+fn hmac_hex(key: &[u8], token: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(token.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Start a session for a fresh sign-in
+pub async fn start(pool: &PgPool, user_id: Uuid, client: &ClientInfo) -> Result<IssuedRefresh> {
+    let mut tx = pool.begin().await?;
+    let session_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO auth_sessions (user_id, user_agent, ip_address)
+        VALUES ($1, $2, $3)
+        RETURNING id
+        "#,
+        user_id,
+        client.user_agent,
+        client.ip_address
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let token = insert_token(&mut tx, user_id, session_id).await?;
+    tx.commit().await?;
+    Ok(IssuedRefresh { user_id, session_id, token })
+}
+
+/// Trade a refresh token for the next one in its session. A token that
+/// was already rotated means it leaked (or was replayed), so the whole
+/// session is revoked.
+pub async fn rotate(pool: &PgPool, token: &str, client: &ClientInfo) -> Result<IssuedRefresh, SessionError> {
+    let mut tx = pool.begin().await?;
+    let record = sqlx::query!(
+        r#"
+        SELECT rt.id, rt.user_id, rt.session_id, rt.used_at, rt.expires_at,
+               s.revoked_at as "revoked_at?"
+        FROM refresh_tokens rt
+        JOIN auth_sessions s ON s.id = rt.session_id
+        WHERE rt.token_hash = $1
+        FOR UPDATE OF rt, s
+        "#,
+        hash_refresh_token(token)?
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(SessionError::InvalidToken)?;
+
+    if record.revoked_at.is_some() {
+        return Err(SessionError::InvalidToken);
+    }
+    if record.used_at.is_some() {
+        revoke_in(&mut tx, record.user_id, record.session_id, "token_reuse").await?;
+        tx.commit().await?;
+        return Err(SessionError::TokenReused { user_id: record.user_id, session_id: record.session_id });
+    }
+    if record.expires_at <= Utc::now() {
+        return Err(SessionError::InvalidToken);
+    }
+
+    sqlx::query!("UPDATE refresh_tokens SET used_at = NOW() WHERE id = $1", record.id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        r#"
+        UPDATE auth_sessions
+        SET last_used_at = NOW(),
+            user_agent = COALESCE($2, user_agent),
+            ip_address = COALESCE($3, ip_address)
+        WHERE id = $1
+        "#,
+        record.session_id,
+        client.user_agent,
+        client.ip_address
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let token = insert_token(&mut tx, record.user_id, record.session_id).await?;
+    tx.commit().await?;
+    Ok(IssuedRefresh { user_id: record.user_id, session_id: record.session_id, token })
+}
+
+async fn insert_token(conn: &mut PgConnection, user_id: Uuid, session_id: Uuid) -> Result<String> {
+    let token = new_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (user_id, session_id, token_hash, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        session_id,
+        hash_refresh_token(&token)?,
+        Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS)
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(token)
+}
+
+/// The user's sessions that can still be refreshed, most recent first
+pub async fn list(pool: &PgPool, user_id: Uuid, current: Option<Uuid>) -> Result<Vec<SessionInfo>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT s.id, s.user_agent, s.ip_address, s.created_at, s.last_used_at
+        FROM auth_sessions s
+        WHERE s.user_id = $1
+          AND s.revoked_at IS NULL
+          AND EXISTS (
+              SELECT 1 FROM refresh_tokens rt
+              WHERE rt.session_id = s.id AND rt.used_at IS NULL AND rt.expires_at > NOW()
+          )
+        ORDER BY s.last_used_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| SessionInfo {
+            current: current == Some(r.id),
+            id: r.id,
+            user_agent: r.user_agent,
+            ip_address: r.ip_address,
+            created_at: r.created_at,
+            last_used_at: r.last_used_at,
+        })
+        .collect())
+}
+
+/// Revoke one of the user's sessions; false if it isn't theirs or is already revoked
+pub async fn revoke(pool: &PgPool, user_id: Uuid, session_id: Uuid, reason: &str) -> Result<bool> {
+    let mut conn = pool.acquire().await?;
+    revoke_in(&mut conn, user_id, session_id, reason).await
+}
+
+async fn revoke_in(conn: &mut PgConnection, user_id: Uuid, session_id: Uuid, reason: &str) -> Result<bool> {
+    let revoked = sqlx::query!(
+        r#"
+        UPDATE auth_sessions
+        SET revoked_at = NOW(), revoked_reason = $3
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+        "#,
+        session_id,
+        user_id,
+        reason
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    Ok(revoked > 0)
+}
+
+/// Revoke every session of the user's, optionally keeping one
+pub async fn revoke_all(pool: &PgPool, user_id: Uuid, except: Option<Uuid>, reason: &str) -> Result<u64> {
+    let mut conn = pool.acquire().await?;
+    revoke_all_in(&mut conn, user_id, except, reason).await
+}
+
+/// `revoke_all` as part of a caller's transaction
+pub async fn revoke_all_in(conn: &mut PgConnection, user_id: Uuid, except: Option<Uuid>, reason: &str) -> Result<u64> {
+    let revoked = sqlx::query!(
+        r#"
+        UPDATE auth_sessions
+        SET revoked_at = NOW(), revoked_reason = $3
+        WHERE user_id = $1 AND revoked_at IS NULL AND ($2::uuid IS NULL OR id <> $2)
+        "#,
+        user_id,
+        except,
+        reason
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    Ok(revoked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_hex_rfc4231_vector() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hmac_hex(b"Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_client_info_from_request() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.1".parse().unwrap());
+        headers.insert("user-agent", "FundHub/1.0 (iPhone)".parse().unwrap());
+        let proxy: SocketAddr = "10.0.0.2:443".parse().unwrap();
+
+        let trusted = TrustedProxies::parse("10.0.0.0/8");
+        let client = ClientInfo::with_proxies(&headers, Some(proxy), &trusted);
+        assert_eq!(client.ip_address.as_deref(), Some("203.0.113.7"));
+        assert_eq!(client.user_agent.as_deref(), Some("FundHub/1.0 (iPhone)"));
+
+        // Without a trusted proxy in front, the header is the caller's word
+        let client = ClientInfo::with_proxies(&headers, Some(proxy), &TrustedProxies::default());
+        assert_eq!(client.ip_address.as_deref(), Some("10.0.0.2"));
+
+        let client = ClientInfo::with_proxies(&HeaderMap::new(), None, &trusted);
+        assert!(client.ip_address.is_none() && client.user_agent.is_none());
+    }
+}
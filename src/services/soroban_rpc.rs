@@ -0,0 +1,573 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{de::DeserializeOwned, Deserialize};
+use sha2::{Digest, Sha256};
+use stellar_xdr::curr::{
+    self as xdr, Limits, ReadXdr, ScVal, WriteXdr,
+};
+
+use crate::config::StellarNetwork;
+
+/// Inclusion fee offered on top of the simulated resource fee
+const BASE_FEE: u32 = 100;
+/// How long a submitted transaction stays valid
+const TX_TIMEOUT_SECS: u64 = 300;
+/// Polling for a submitted transaction's outcome
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_POLLS: u32 = 30;
+
+/// Result of a submitted contract call
+#[derive(Debug, Clone)]
+pub struct Invocation {
+    pub tx_hash: String,
+    pub return_value: ScVal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxStatus {
+    Success,
+    Failed,
+    NotFound,
+}
+
+/// A contract event as returned by `getEvents`; topics and value are base64 ScVal XDR
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcEvent {
+    pub id: String,
+    pub ledger: i64,
+    pub ledger_closed_at: String,
+    pub contract_id: String,
+    #[serde(default)]
+    pub tx_hash: Option<String>,
+    #[serde(default)]
+    pub in_successful_contract_call: bool,
+    pub topic: Vec<String>,
+    pub value: String,
+}
+
+/// One page of `getEvents`; pass `cursor` back in to continue
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventsPage {
+    #[serde(default)]
+    pub events: Vec<RpcEvent>,
+    pub latest_ledger: i64,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LatestLedgerResponse {
+    sequence: i64,
+}
+
+#[derive(Deserialize)]
+struct LedgerEntriesResponse {
+    entries: Option<Vec<LedgerEntryResult>>,
+}
+
+#[derive(Deserialize)]
+struct LedgerEntryResult {
+    xdr: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SimulateResponse {
+    error: Option<String>,
+    transaction_data: Option<String>,
+    min_resource_fee: Option<String>,
+    #[serde(default)]
+    results: Vec<SimulateResult>,
+}
+
+#[derive(Deserialize)]
+struct SimulateResult {
+    #[serde(default)]
+    auth: Vec<String>,
+    xdr: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendResponse {
+    status: String,
+    hash: String,
+    error_result_xdr: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetTransactionResponse {
+    status: String,
+    result_meta_xdr: Option<String>,
+}
+
+/// Minimal soroban-rpc client that builds, simulates, signs with the platform
+/// key, and submits contract invocations
+pub struct SorobanRpc {
+    http: reqwest::Client,
+    rpc_url: String,
+    network: StellarNetwork,
+    network_passphrase: String,
+    signing_key: SigningKey,
+}
+
+impl SorobanRpc {
+    /// Build for `network` from its RPC settings and `PLATFORM_WALLET_SECRET_KEY`.
+    /// Returns `None` when no RPC URL is configured.
+    pub fn from_env(network: StellarNetwork) -> Result<Option<Self>> {
+        let Some(rpc_url) = network.soroban_rpc_url() else {
+            return Ok(None);
+        };
+        let secret = std::env::var("PLATFORM_WALLET_SECRET_KEY")
+            .map_err(|_| anyhow!("PLATFORM_WALLET_SECRET_KEY must be set to sign Soroban transactions"))?;
+        let seed = stellar_strkey::ed25519::PrivateKey::from_string(&secret)
+            .map_err(|_| anyhow!("PLATFORM_WALLET_SECRET_KEY is not a valid Stellar secret key"))?;
+
+        Ok(Some(Self {
+            http: reqwest::Client::new(),
+            rpc_url,
+            network,
+            network_passphrase: network.network_passphrase(),
+            signing_key: SigningKey::from_bytes(&seed.0),
+        }))
+    }
+
+    pub fn network(&self) -> StellarNetwork {
+        self.network
+    }
+
+    /// Strkey (G...) of the platform account that signs and pays for invocations
+    pub fn source_address(&self) -> String {
+        stellar_strkey::ed25519::PublicKey(self.signing_key.verifying_key().to_bytes()).to_string()
+    }
+
+    /// Sign a contract attestation payload with the platform key; contracts
+    /// that verify attestations are initialized with its public key
+    pub fn attest(&self, payload: &[u8]) -> [u8; 64] {
+        self.signing_key.sign(payload).to_bytes()
+    }
+
+    /// Simulate a read-only call and return its result without submitting
+    pub async fn simulate(&self, contract_id: &str, function: &str, args: Vec<ScVal>) -> Result<ScVal> {
+        let sequence = self.sequence_number().await?;
+        let tx = self.build_transaction(sequence + 1, contract_id, function, args)?;
+        let simulation = self.simulate_transaction(&tx).await?;
+
+        let result = simulation
+            .results
+            .first()
+            .ok_or_else(|| anyhow!("Simulation of {} returned no result", function))?;
+        Ok(ScVal::from_xdr_base64(&result.xdr, Limits::none())?)
+    }
+
+    /// Simulate, sign, submit, and wait for a state-changing call
+    pub async fn invoke(&self, contract_id: &str, function: &str, args: Vec<ScVal>) -> Result<Invocation> {
+        let sequence = self.sequence_number().await?;
+        let mut tx = self.build_transaction(sequence + 1, contract_id, function, args)?;
+        let simulation = self.simulate_transaction(&tx).await?;
+
+        // Attach the footprint, resource fee, and auth entries from simulation
+        let soroban_data = simulation
+            .transaction_data
+            .as_deref()
+            .ok_or_else(|| anyhow!("Simulation of {} returned no transaction data", function))?;
+        tx.ext = xdr::TransactionExt::V1(xdr::SorobanTransactionData::from_xdr_base64(
+            soroban_data,
+            Limits::none(),
+        )?);
+        let resource_fee: u32 = simulation
+            .min_resource_fee
+            .as_deref()
+            .unwrap_or("0")
+            .parse()
+            .map_err(|_| anyhow!("Invalid resource fee from simulation"))?;
+        tx.fee = BASE_FEE.saturating_add(resource_fee);
+
+        let auth = simulation
+            .results
+            .first()
+            .map(|r| {
+                r.auth
+                    .iter()
+                    .map(|a| xdr::SorobanAuthorizationEntry::from_xdr_base64(a, Limits::none()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let mut operations: Vec<xdr::Operation> = tx.operations.to_vec();
+        if let Some(xdr::Operation { body: xdr::OperationBody::InvokeHostFunction(op), .. }) = operations.first_mut() {
+            op.auth = auth.try_into()?;
+        }
+        tx.operations = operations.try_into()?;
+
+        let (envelope, hash) = self.sign(tx)?;
+        let tx_hash = hex::encode(hash);
+
+        let sent: SendResponse = self
+            .call("sendTransaction", serde_json::json!({ "transaction": envelope }))
+            .await?;
+        if sent.status == "ERROR" || sent.status == "TRY_AGAIN_LATER" {
+            return Err(anyhow!(
+                "{} was rejected ({}): {}",
+                function,
+                sent.status,
+                sent.error_result_xdr.unwrap_or_default()
+            ));
+        }
+        if sent.hash != tx_hash {
+            tracing::warn!("soroban-rpc reported hash {} for transaction {}", sent.hash, tx_hash);
+        }
+
+        for _ in 0..MAX_POLLS {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let result: GetTransactionResponse = self
+                .call("getTransaction", serde_json::json!({ "hash": tx_hash }))
+                .await?;
+            match result.status.as_str() {
+                "SUCCESS" => {
+                    let return_value = result
+                        .result_meta_xdr
+                        .as_deref()
+                        .map(return_value_from_meta)
+                        .transpose()?
+                        .unwrap_or(ScVal::Void);
+                    return Ok(Invocation { tx_hash, return_value });
+                }
+                "FAILED" => return Err(anyhow!("{} failed on-chain (tx {})", function, tx_hash)),
+                _ => continue,
+            }
+        }
+
+        Err(anyhow!("Timed out waiting for {} (tx {})", function, tx_hash))
+    }
+
+    /// Look up the outcome of a transaction by hash
+    pub async fn transaction_status(&self, tx_hash: &str) -> Result<TxStatus> {
+        let result: GetTransactionResponse = self
+            .call("getTransaction", serde_json::json!({ "hash": tx_hash }))
+            .await?;
+        Ok(match result.status.as_str() {
+            "SUCCESS" => TxStatus::Success,
+            "FAILED" => TxStatus::Failed,
+            _ => TxStatus::NotFound,
+        })
+    }
+
+    /// Sequence of the most recent ledger the RPC node has ingested
+    pub async fn latest_ledger(&self) -> Result<i64> {
+        let result: LatestLedgerResponse = self.call("getLatestLedger", serde_json::json!({})).await?;
+        Ok(result.sequence)
+    }
+
+    /// Contract events for `contract_ids`, either from `start_ledger` or
+    /// continuing after `cursor`
+    pub async fn get_events(
+        &self,
+        contract_ids: &[String],
+        start_ledger: Option<i64>,
+        cursor: Option<&str>,
+        limit: u32,
+    ) -> Result<EventsPage> {
+        let mut params = serde_json::json!({
+            "filters": [{ "type": "contract", "contractIds": contract_ids }],
+            "pagination": { "limit": limit }
+        });
+        // The RPC rejects requests that set both a start ledger and a cursor
+        match (cursor, start_ledger) {
+            (Some(cursor), _) => params["pagination"]["cursor"] = serde_json::json!(cursor),
+            (None, Some(ledger)) => params["startLedger"] = serde_json::json!(ledger),
+            (None, None) => return Err(anyhow!("getEvents needs a start ledger or a cursor")),
+        }
+
+        self.call("getEvents", params).await
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> Result<T> {
+        let response: serde_json::Value = self
+            .http
+            .post(&self.rpc_url)
+            .json(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("soroban-rpc {} failed: {}", method, error));
+        }
+        Ok(serde_json::from_value(response["result"].clone())?)
+    }
+
+    async fn sequence_number(&self) -> Result<i64> {
+        let key = xdr::LedgerKey::Account(xdr::LedgerKeyAccount {
+            account_id: account_id(self.signing_key.verifying_key().to_bytes()),
+        });
+        let result: LedgerEntriesResponse = self
+            .call(
+                "getLedgerEntries",
+                serde_json::json!({ "keys": [key.to_xdr_base64(Limits::none())?] }),
+            )
+            .await?;
+
+        let entry = result
+            .entries
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Platform account {} not found on network", self.source_address()))?;
+        match xdr::LedgerEntryData::from_xdr_base64(&entry.xdr, Limits::none())? {
+            xdr::LedgerEntryData::Account(account) => Ok(account.seq_num.0),
+            _ => Err(anyhow!("Unexpected ledger entry for platform account")),
+        }
+    }
+
+    async fn simulate_transaction(&self, tx: &xdr::Transaction) -> Result<SimulateResponse> {
+        let envelope = xdr::TransactionEnvelope::Tx(xdr::TransactionV1Envelope {
+            tx: tx.clone(),
+            signatures: xdr::VecM::default(),
+        });
+        let simulation: SimulateResponse = self
+            .call(
+                "simulateTransaction",
+                serde_json::json!({ "transaction": envelope.to_xdr_base64(Limits::none())? }),
+            )
+            .await?;
+
+        if let Some(error) = simulation.error {
+            return Err(anyhow!("Simulation failed: {}", error));
+        }
+        Ok(simulation)
+    }
+
+    fn build_transaction(
+        &self,
+        sequence: i64,
+        contract_id: &str,
+        function: &str,
+        args: Vec<ScVal>,
+    ) -> Result<xdr::Transaction> {
+        let contract = stellar_strkey::Contract::from_string(contract_id)
+            .map_err(|_| anyhow!("Invalid contract id {}", contract_id))?;
+        let max_time = chrono::Utc::now().timestamp() as u64 + TX_TIMEOUT_SECS;
+
+        let operation = xdr::Operation {
+            source_account: None,
+            body: xdr::OperationBody::InvokeHostFunction(xdr::InvokeHostFunctionOp {
+                host_function: xdr::HostFunction::InvokeContract(xdr::InvokeContractArgs {
+                    contract_address: xdr::ScAddress::Contract(xdr::Hash(contract.0)),
+                    function_name: xdr::ScSymbol(function.try_into()?),
+                    args: args.try_into()?,
+                }),
+                auth: xdr::VecM::default(),
+            }),
+        };
+
+        Ok(xdr::Transaction {
+            source_account: xdr::MuxedAccount::Ed25519(xdr::Uint256(
+                self.signing_key.verifying_key().to_bytes(),
+            )),
+            fee: BASE_FEE,
+            seq_num: xdr::SequenceNumber(sequence),
+            cond: xdr::Preconditions::Time(xdr::TimeBounds {
+                min_time: xdr::TimePoint(0),
+                max_time: xdr::TimePoint(max_time),
+            }),
+            memo: xdr::Memo::None,
+            operations: vec![operation].try_into()?,
+            ext: xdr::TransactionExt::V0,
+        })
+    }
+
+    /// Sign with the platform key; returns the base64 envelope and the tx hash
+    fn sign(&self, tx: xdr::Transaction) -> Result<(String, [u8; 32])> {
+        let hash = transaction_hash(&tx, &self.network_passphrase)?;
+        let signature = self.signing_key.sign(&hash);
+        let public_key = self.signing_key.verifying_key().to_bytes();
+
+        let decorated = xdr::DecoratedSignature {
+            hint: xdr::SignatureHint(public_key[28..].try_into()?),
+            signature: xdr::Signature(signature.to_bytes().to_vec().try_into()?),
+        };
+        let envelope = xdr::TransactionEnvelope::Tx(xdr::TransactionV1Envelope {
+            tx,
+            signatures: vec![decorated].try_into()?,
+        });
+
+        Ok((envelope.to_xdr_base64(Limits::none())?, hash))
+    }
+}
+
+/// Hash that is signed and used as the transaction id
+pub fn transaction_hash(tx: &xdr::Transaction, network_passphrase: &str) -> Result<[u8; 32]> {
+    let network_id: [u8; 32] = Sha256::digest(network_passphrase.as_bytes()).into();
+    let payload = xdr::TransactionSignaturePayload {
+        network_id: xdr::Hash(network_id),
+        tagged_transaction: xdr::TransactionSignaturePayloadTaggedTransaction::Tx(tx.clone()),
+    };
+    Ok(Sha256::digest(payload.to_xdr(Limits::none())?).into())
+}
+
+fn return_value_from_meta(meta_xdr: &str) -> Result<ScVal> {
+    match xdr::TransactionMeta::from_xdr_base64(meta_xdr, Limits::none())? {
+        xdr::TransactionMeta::V3(meta) => Ok(meta.soroban_meta.map(|m| m.return_value).unwrap_or(ScVal::Void)),
+        _ => Ok(ScVal::Void),
+    }
+}
+
+fn account_id(public_key: [u8; 32]) -> xdr::AccountId {
+    xdr::AccountId(xdr::PublicKey::PublicKeyTypeEd25519(xdr::Uint256(public_key)))
+}
+
+// Conversions between Rust values and contract arguments
+
+pub fn bytes_val(bytes: &[u8]) -> Result<ScVal> {
+    Ok(ScVal::Bytes(xdr::ScBytes(bytes.to_vec().try_into()?)))
+}
+
+pub fn i128_val(value: i128) -> ScVal {
+    ScVal::I128(xdr::Int128Parts {
+        hi: (value >> 64) as i64,
+        lo: value as u64,
+    })
+}
+
+pub fn string_val(value: &str) -> Result<ScVal> {
+    Ok(ScVal::String(xdr::ScString(value.try_into()?)))
+}
+
+/// `G...` accounts and `C...` contracts
+pub fn address_val(address: &str) -> Result<ScVal> {
+    let address = if let Ok(key) = stellar_strkey::ed25519::PublicKey::from_string(address) {
+        xdr::ScAddress::Account(account_id(key.0))
+    } else if let Ok(contract) = stellar_strkey::Contract::from_string(address) {
+        xdr::ScAddress::Contract(xdr::Hash(contract.0))
+    } else {
+        return Err(anyhow!("Invalid Stellar address {}", address));
+    };
+    Ok(ScVal::Address(address))
+}
+
+pub fn vec_val(items: Vec<ScVal>) -> Result<ScVal> {
+    Ok(ScVal::Vec(Some(xdr::ScVec(items.try_into()?))))
+}
+
+/// A `#[contracttype]` struct: a map keyed by field name, sorted as the host expects
+pub fn struct_val(mut fields: Vec<(&str, ScVal)>) -> Result<ScVal> {
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+    let entries = fields
+        .into_iter()
+        .map(|(name, val)| {
+            Ok(xdr::ScMapEntry {
+                key: ScVal::Symbol(xdr::ScSymbol(name.try_into()?)),
+                val,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ScVal::Map(Some(xdr::ScMap(entries.try_into()?))))
+}
+
+/// A named field of a `#[contracttype]` struct value
+pub fn struct_field<'a>(value: &'a ScVal, name: &str) -> Result<&'a ScVal> {
+    match value {
+        ScVal::Map(Some(map)) => map
+            .iter()
+            .find(|entry| matches!(&entry.key, ScVal::Symbol(key) if key.as_slice() == name.as_bytes()))
+            .map(|entry| &entry.val)
+            .ok_or_else(|| anyhow!("Missing struct field {}", name)),
+        other => Err(anyhow!("Expected struct contract value, got {:?}", other)),
+    }
+}
+
+/// A unit variant of a `#[contracttype]` enum: a vec holding the variant name
+pub fn unit_variant_val(name: &str) -> Result<ScVal> {
+    vec_val(vec![ScVal::Symbol(xdr::ScSymbol(name.try_into()?))])
+}
+
+/// Name of a unit enum variant value
+pub fn unit_variant_from_val(value: &ScVal) -> Result<String> {
+    match value {
+        ScVal::Vec(Some(items)) if items.len() == 1 => match &items[0] {
+            ScVal::Symbol(name) => Ok(name.to_utf8_string()?),
+            other => Err(anyhow!("Expected enum variant symbol, got {:?}", other)),
+        },
+        other => Err(anyhow!("Expected enum variant contract value, got {:?}", other)),
+    }
+}
+
+/// Strkey of an address value
+pub fn address_from_val(value: &ScVal) -> Result<String> {
+    match value {
+        ScVal::Address(xdr::ScAddress::Account(xdr::AccountId(xdr::PublicKey::PublicKeyTypeEd25519(key)))) => {
+            Ok(stellar_strkey::ed25519::PublicKey(key.0).to_string())
+        }
+        ScVal::Address(xdr::ScAddress::Contract(hash)) => Ok(stellar_strkey::Contract(hash.0).to_string()),
+        other => Err(anyhow!("Expected address contract value, got {:?}", other)),
+    }
+}
+
+pub fn i128_from_val(value: &ScVal) -> Result<i128> {
+    match value {
+        ScVal::I128(parts) => Ok(((parts.hi as i128) << 64) | parts.lo as i128),
+        other => Err(anyhow!("Expected i128 contract value, got {:?}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_i128_round_trip() {
+        for value in [0i128, 1, -1, 15_000_000, i64::MAX as i128 + 1, i128::MIN, i128::MAX] {
+            assert_eq!(i128_from_val(&i128_val(value)).unwrap(), value);
+        }
+        assert!(i128_from_val(&ScVal::Void).is_err());
+    }
+
+    #[test]
+    fn test_address_round_trip() {
+        let account = "GBRPYHIL2CI3FNQ4BXLFMNDLFJUNPU2HY3ZMFSHONUCEOASW7QC7OX2H";
+        assert_eq!(address_from_val(&address_val(account).unwrap()).unwrap(), account);
+        assert!(address_from_val(&ScVal::Void).is_err());
+    }
+
+    #[test]
+    fn test_struct_val_sorts_fields() {
+        let val = struct_val(vec![("share_bps", ScVal::U32(10_000)), ("address", ScVal::Bool(true))]).unwrap();
+        let ScVal::Map(Some(map)) = val else { panic!("expected map") };
+        let keys: Vec<_> = map.iter().map(|e| e.key.clone()).collect();
+        assert_eq!(keys[0], ScVal::Symbol(xdr::ScSymbol("address".try_into().unwrap())));
+    }
+
+    #[test]
+    fn test_struct_field_lookup() {
+        let val = struct_val(vec![("owner", ScVal::U32(7)), ("status", ScVal::Bool(true))]).unwrap();
+        assert_eq!(struct_field(&val, "owner").unwrap(), &ScVal::U32(7));
+        assert!(struct_field(&val, "missing").is_err());
+        assert!(struct_field(&ScVal::Void, "owner").is_err());
+    }
+
+    #[test]
+    fn test_unit_variant_round_trip() {
+        assert_eq!(unit_variant_from_val(&unit_variant_val("Active").unwrap()).unwrap(), "Active");
+        assert!(unit_variant_from_val(&ScVal::Void).is_err());
+    }
+
+    #[test]
+    fn test_address_val_rejects_garbage() {
+        assert!(address_val("GBRPYHIL2CI3FNQ4BXLFMNDLFJUNPU2HY3ZMFSHONUCEOASW7QC7OX2H").is_ok());
+        assert!(address_val("not-an-address").is_err());
+    }
+}
@@ -4,39 +4,67 @@ use stellar_sdk::{
     utils::{Direction, Endpoint},
     CallBuilder,
 };
-use crate::config::Config;
+use crate::config::{Config, StellarNetwork};
+use crate::services::horizon::{HorizonClient, HorizonHealth, HorizonPolicy};
+use crate::utils::money::Stroops;
+use crate::utils::sse::SseBuffer;
+use crate::utils::ttl_cache::TtlCache;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt, TryStreamExt};
 use serde::Deserialize;
 use reqwest::Client;
 
+/// How long a resolved federation address is reused
+const FEDERATION_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+const FEDERATION_CACHE_SIZE: usize = 1024;
+
 #[derive(Clone)]
 pub struct StellarService {
     server: Server,
+    network: StellarNetwork,
     horizon_url: String,
     platform_public_key: String,
+    /// Horizon requests, with retries and a circuit breaker
+    horizon: HorizonClient,
+    /// Everything else (stellar.toml and federation servers)
     http: Client,
+    /// Federation lookups keyed by the lowercased `name*domain` address
+    federation_cache: TtlCache<ResolvedAddress>,
 }
 
 impl StellarService {
     pub fn new(config: &Config) -> Result<Self> {
-        let horizon_url = match config.stellar_network.as_str() {
-            "testnet" => "https://horizon-testnet.stellar.org".to_string(),
-            "public" => "https://horizon.stellar.org".to_string(),
-            _ => return Err(anyhow::anyhow!("Invalid network specified")),
-        };
+        let horizon_url = config.stellar_horizon_url.trim_end_matches('/').to_string();
 
         Ok(Self {
             server: Server::new(horizon_url.clone(), None)?,
+            network: config.stellar_network,
             horizon_url,
             platform_public_key: config.platform_wallet_public_key.clone(),
+            horizon: HorizonClient::new(HorizonPolicy::from_env())?,
             http: Client::new(),
+            federation_cache: TtlCache::new(FEDERATION_CACHE_TTL, FEDERATION_CACHE_SIZE),
         })
     }
 
+    /// Network this service's Horizon belongs to
+    pub fn network(&self) -> StellarNetwork {
+        self.network
+    }
+
+    pub fn horizon_health(&self) -> HorizonHealth {
+        self.horizon.health()
+    }
+
+    /// Whether Horizon calls are currently failing fast
+    pub fn horizon_unavailable(&self) -> bool {
+        self.horizon.is_unavailable()
+    }
+
     pub async fn verify_transaction(&self, tx_hash: &str) -> Result<bool> {
         let url = format!("{}/transactions/{}", self.horizon_url, tx_hash);
-        let resp = self.http.get(url).send().await?;
+        let resp = self.horizon.get(&url).await?;
         if !resp.status().is_success() {
             return Ok(false);
         }
@@ -47,22 +75,22 @@ impl StellarService {
 
     pub async fn validate_wallet(&self, public_key: &str) -> Result<bool> {
         let url = format!("{}/accounts/{}", self.horizon_url, public_key);
-        let resp = self.http.get(url).send().await?;
+        let resp = self.horizon.get(&url).await?;
         Ok(resp.status().is_success())
     }
 
     pub async fn fetch_wallet_balance(&self, public_key: &str) -> Result<WalletBalance> {
         let url = format!("{}/accounts/{}", self.horizon_url, public_key);
-        let resp = self.http.get(url).send().await?;
+        let resp = self.horizon.get(&url).await?;
         if !resp.status().is_success() { return Err(anyhow::anyhow!("account not found")); }
         let acc = resp.json::<AccountResponse>().await?;
-        let mut xlm: f64 = 0.0;
-        let mut usdc: f64 = 0.0;
+        let mut xlm = Stroops::ZERO;
+        let mut usdc = Stroops::ZERO;
         for b in acc.balances.into_iter() {
             if b.asset_type == "native" {
-                xlm = b.balance.parse().unwrap_or(0.0);
+                xlm = b.balance.parse().unwrap_or_default();
             } else if b.asset_code.as_deref() == Some("USDC") {
-                usdc += b.balance.parse().unwrap_or(0.0);
+                usdc += b.balance.parse().unwrap_or_default();
             }
         }
         Ok(WalletBalance { xlm, usdc })
@@ -70,7 +98,7 @@ impl StellarService {
 
     pub async fn fetch_wallet_transactions(&self, public_key: &str) -> Result<Vec<TransactionRecord>> {
         let url = format!("{}/accounts/{}/payments?limit=20&order=desc", self.horizon_url, public_key);
-        let resp = self.http.get(url).send().await?;
+        let resp = self.horizon.get(&url).await?;
         if !resp.status().is_success() { return Ok(vec![]); }
         let list = resp.json::<RecordsEnvelope<PaymentOp>>().await?;
         let mut out = Vec::new();
@@ -79,7 +107,7 @@ impl StellarService {
                 "native" => "XLM".to_string(),
                 _ => rec.asset_code.clone().unwrap_or_else(|| "UNKNOWN".into()),
             };
-            let amount = rec.amount.parse().unwrap_or(0.0);
+            let amount = rec.amount.parse().unwrap_or_default();
             let timestamp: DateTime<Utc> = rec.created_at.parse().unwrap_or_else(|_| Utc::now());
             out.push(TransactionRecord {
                 hash: rec.transaction_hash,
@@ -93,9 +121,167 @@ impl StellarService {
         Ok(out)
     }
 
+    /// Most recent payments to an account, newest first, with their transaction memos
+    pub async fn fetch_recent_payments(&self, public_key: &str, limit: u32) -> Result<Vec<PaymentRecord>> {
+        let url = format!(
+            "{}/accounts/{}/payments?limit={}&order=desc&join=transactions",
+            self.horizon_url, public_key, limit
+        );
+        let resp = self.horizon.get(&url).await?;
+        if !resp.status().is_success() { return Ok(vec![]); }
+        let list = resp.json::<RecordsEnvelope<JoinedPaymentOp>>().await?;
+        Ok(list._embedded.records.into_iter().filter_map(JoinedPaymentOp::into_record).collect())
+    }
+
+    /// Payments made by a transaction, with its memo
+    pub async fn fetch_transaction_payments(&self, tx_hash: &str) -> Result<Vec<PaymentRecord>> {
+        let url = format!("{}/transactions/{}/payments?join=transactions&limit=100", self.horizon_url, tx_hash);
+        let resp = self.horizon.get(&url).await?;
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!("Transaction not found"));
+        }
+        let list = resp.json::<RecordsEnvelope<JoinedPaymentOp>>().await?;
+        Ok(list._embedded.records.into_iter().filter_map(JoinedPaymentOp::into_record).collect())
+    }
+
+    /// One page of an account's operations, oldest first, starting after
+    /// `cursor` (a paging token). Failed transactions are included. Accounts
+    /// Horizon doesn't know yet have no history.
+    pub async fn fetch_operations(&self, public_key: &str, cursor: Option<&str>, limit: u32) -> Result<Vec<OperationRecord>> {
+        let mut url = format!(
+            "{}/accounts/{}/operations?order=asc&limit={}&include_failed=true&join=transactions",
+            self.horizon_url, public_key, limit
+        );
+        if let Some(cursor) = cursor {
+            url.push_str(&format!("&cursor={}", cursor));
+        }
+        let resp = self.horizon.get(&url).await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(vec![]);
+        }
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!("Horizon returned {} for operations of {}", resp.status(), public_key));
+        }
+        let list = resp.json::<RecordsEnvelope<OperationOp>>().await?;
+        Ok(list._embedded.records.into_iter().filter_map(OperationOp::into_record).collect())
+    }
+
+    /// Stream payments to an account from Horizon as they land, starting
+    /// after `cursor` (a paging token, or "now")
+    pub async fn stream_payments(
+        &self,
+        public_key: &str,
+        cursor: &str,
+    ) -> Result<impl Stream<Item = Result<PaymentRecord>>> {
+        let url = format!(
+            "{}/accounts/{}/payments?cursor={}&join=transactions",
+            self.horizon_url, public_key, cursor
+        );
+        let resp = self.horizon.stream(&url).await?;
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!("Payment stream for {} failed: {}", public_key, resp.status()));
+        }
+
+        let mut sse = SseBuffer::new();
+        let records = resp
+            .bytes_stream()
+            .map(move |chunk| -> Result<Vec<PaymentRecord>> {
+                let chunk = chunk?;
+                // Horizon opens each stream with a "hello" event that isn't a record
+                Ok(sse
+                    .push(&chunk)
+                    .iter()
+                    .filter_map(|data| serde_json::from_str::<JoinedPaymentOp>(data).ok())
+                    .filter_map(JoinedPaymentOp::into_record)
+                    .collect())
+            })
+            .map_ok(|batch| futures::stream::iter(batch.into_iter().map(Ok)))
+            .try_flatten();
+
+        Ok(records)
+    }
+
+    /// Resolve a `G...` account, `M...` muxed address, or `name*domain.com`
+    /// federation address (SEP-2) to the account to pay. Federation answers
+    /// that require an id memo come back as muxed destinations; other memo
+    /// types are refused since donation memos are already taken.
+    pub async fn resolve_address(&self, address: &str) -> Result<ResolvedAddress> {
+        let address = address.trim();
+        let Some((_, domain)) = split_federation_address(address) else {
+            return parse_address(address);
+        };
+
+        let cache_key = address.to_lowercase();
+        if let Some(resolved) = self.federation_cache.get(&cache_key) {
+            return Ok(resolved);
+        }
+
+        let toml = self
+            .http
+            .get(format!("https://{}/.well-known/stellar.toml", domain))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let server = federation_server_from_toml(&toml)
+            .ok_or_else(|| anyhow::anyhow!("{} does not publish a federation server", domain))?;
+
+        let answer = self
+            .http
+            .get(&server)
+            .query(&[("q", address), ("type", "name")])
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(|_| anyhow::anyhow!("Federation server for {} does not know {}", domain, address))?
+            .json::<FederationResponse>()
+            .await?;
+
+        let account = parse_address(&answer.account_id)?;
+        let resolved = match (answer.memo_type.as_deref(), answer.memo) {
+            (None, _) | (_, None) => account,
+            (Some("id"), Some(memo)) if account.muxed_id.is_none() => ResolvedAddress {
+                muxed_id: Some(memo.parse().map_err(|_| anyhow::anyhow!("Federation returned a bad id memo"))?),
+                ..account
+            },
+            (Some(memo_type), Some(_)) => {
+                return Err(anyhow::anyhow!(
+                    "{} requires a {} memo, which is not supported",
+                    address,
+                    memo_type
+                ))
+            }
+        };
+
+        self.federation_cache.insert(cache_key, resolved.clone());
+        Ok(resolved)
+    }
+
+    /// Unclaimed claimable balances that `claimant` can claim
+    pub async fn fetch_claimable_balances(&self, claimant: &str) -> Result<Vec<ClaimableBalanceRecord>> {
+        let url = format!("{}/claimable_balances?claimant={}&limit=200", self.horizon_url, claimant);
+        let resp = self.horizon.get(&url).await?;
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!("Horizon returned {} for claimable balances", resp.status()));
+        }
+        let list = resp.json::<RecordsEnvelope<ClaimableBalanceOp>>().await?;
+        Ok(list
+            ._embedded
+            .records
+            .into_iter()
+            .map(|b| ClaimableBalanceRecord {
+                amount: (b.asset == "native").then(|| b.amount.parse().ok()).flatten(),
+                id: b.id,
+                asset: b.asset,
+                sponsor: b.sponsor,
+            })
+            .collect())
+    }
+
     pub async fn fetch_transaction_details(&self, tx_hash: &str) -> Result<TransactionDetails> {
         let url = format!("{}/transactions/{}", self.horizon_url, tx_hash);
-        let resp = self.http.get(url).send().await?;
+        let resp = self.horizon.get(&url).await?;
         if !resp.status().is_success() {
             return Err(anyhow::anyhow!("Transaction not found"));
         }
@@ -113,22 +299,58 @@ impl StellarService {
     }
 }
 
+/// Horizon reports every asset with 7 decimal places, so USDC is held in stroop units too
 #[derive(Debug, Clone)]
 pub struct WalletBalance {
-    pub xlm: f64,
-    pub usdc: f64,
+    pub xlm: Stroops,
+    pub usdc: Stroops,
 }
 
 #[derive(Debug, Clone)]
 pub struct TransactionRecord {
     pub hash: String,
-    pub amount: f64,
+    pub amount: Stroops,
     pub asset: String,
     pub from: String,
     pub to: String,
     pub timestamp: DateTime<Utc>,
 }
 
+/// A payment into a watched account along with its transaction's text memo
+#[derive(Debug, Clone)]
+pub struct PaymentRecord {
+    pub paging_token: String,
+    pub tx_hash: String,
+    pub to: String,
+    /// The `M...` address paid, when the payment went to a muxed destination
+    pub to_muxed: Option<String>,
+    /// Set only for native XLM payments
+    pub amount: Option<Stroops>,
+    pub memo: Option<String>,
+    pub successful: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An operation touching an indexed account, flattened to who paid whom
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationRecord {
+    /// Horizon operation id, which is also its paging token
+    pub id: i64,
+    pub tx_hash: String,
+    pub operation_type: String,
+    /// The account funds or authority came from
+    pub source_account: String,
+    pub destination_account: Option<String>,
+    pub amount: Option<Stroops>,
+    /// `native` or `CODE:ISSUER`, when the operation moves an asset
+    pub asset: Option<String>,
+    pub memo: Option<String>,
+    pub memo_type: Option<String>,
+    pub ledger: Option<i32>,
+    pub successful: bool,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone)]
 pub struct TransactionDetails {
     pub hash: String,
@@ -141,6 +363,81 @@ pub struct TransactionDetails {
     pub source_account: String,
 }
 
+/// A claimable balance as reported by Horizon
+#[derive(Debug, Clone)]
+pub struct ClaimableBalanceRecord {
+    pub id: String,
+    /// `native` or `CODE:ISSUER`
+    pub asset: String,
+    /// Native balances only
+    pub amount: Option<Stroops>,
+    pub sponsor: Option<String>,
+}
+
+/// A payment destination after federation and muxed address resolution
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedAddress {
+    /// The underlying `G...` account
+    pub account_id: String,
+    /// Sub-account id when the destination is muxed
+    pub muxed_id: Option<u64>,
+}
+
+impl ResolvedAddress {
+    /// The address to pay: `M...` for muxed destinations, otherwise `G...`
+    pub fn address(&self) -> String {
+        match self.muxed_id {
+            Some(id) => match stellar_strkey::ed25519::PublicKey::from_string(&self.account_id) {
+                Ok(key) => stellar_strkey::ed25519::MuxedAccount { ed25519: key.0, id }.to_string(),
+                Err(_) => self.account_id.clone(),
+            },
+            None => self.account_id.clone(),
+        }
+    }
+}
+
+/// Decode a `G...` or `M...` address without any network lookups
+pub fn parse_address(address: &str) -> Result<ResolvedAddress> {
+    if let Ok(key) = stellar_strkey::ed25519::PublicKey::from_string(address) {
+        return Ok(ResolvedAddress { account_id: key.to_string(), muxed_id: None });
+    }
+    if let Ok(muxed) = stellar_strkey::ed25519::MuxedAccount::from_string(address) {
+        return Ok(ResolvedAddress {
+            account_id: stellar_strkey::ed25519::PublicKey(muxed.ed25519).to_string(),
+            muxed_id: Some(muxed.id),
+        });
+    }
+    Err(anyhow::anyhow!("{} is not a Stellar account or muxed address", address))
+}
+
+/// The `G...` account behind an address; anything unparseable is returned as is
+pub fn base_account(address: &str) -> String {
+    parse_address(address)
+        .map(|resolved| resolved.account_id)
+        .unwrap_or_else(|_| address.to_string())
+}
+
+/// `FEDERATION_SERVER` from a stellar.toml
+fn federation_server_from_toml(toml: &str) -> Option<String> {
+    toml.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if key.trim() != "FEDERATION_SERVER" {
+            return None;
+        }
+        let value = value.split('#').next()?.trim().trim_matches('"').trim_matches('\'');
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Split `name*domain.com` into its name and domain
+fn split_federation_address(address: &str) -> Option<(&str, &str)> {
+    let (name, domain) = address.rsplit_once('*')?;
+    let valid_domain = !domain.is_empty()
+        && domain.contains('.')
+        && domain.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    (!name.is_empty() && valid_domain).then_some((name, domain))
+}
+
 // Horizon response types (partial)
 #[derive(Deserialize)]
 struct AccountResponse {
@@ -174,6 +471,21 @@ struct PaymentOp {
     transaction_hash: String,
 }
 
+#[derive(Deserialize)]
+struct ClaimableBalanceOp {
+    id: String,
+    asset: String,
+    amount: String,
+    sponsor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FederationResponse {
+    account_id: String,
+    memo_type: Option<String>,
+    memo: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct TransactionResponse {
     hash: String,
@@ -184,4 +496,207 @@ struct TransactionResponse {
     operation_count: i32,
     memo: Option<String>,
     source_account: String,
-}
\ No newline at end of file
+}
+
+#[derive(Deserialize)]
+struct JoinedPaymentOp {
+    paging_token: String,
+    transaction_hash: String,
+    #[serde(default)]
+    transaction_successful: bool,
+    to: Option<String>,
+    to_muxed: Option<String>,
+    amount: Option<String>,
+    asset_type: Option<String>,
+    created_at: String,
+    transaction: Option<JoinedTransaction>,
+}
+
+#[derive(Deserialize)]
+struct JoinedTransaction {
+    memo_type: String,
+    memo: Option<String>,
+    #[serde(default)]
+    ledger: Option<i32>,
+}
+
+/// Any Horizon operation; only the fields the indexer reads, most of them type specific
+#[derive(Deserialize)]
+struct OperationOp {
+    id: String,
+    #[serde(rename = "type")]
+    operation_type: String,
+    source_account: String,
+    transaction_hash: String,
+    #[serde(default)]
+    transaction_successful: bool,
+    created_at: String,
+    // payment, path payments
+    from: Option<String>,
+    to: Option<String>,
+    amount: Option<String>,
+    asset_type: Option<String>,
+    asset_code: Option<String>,
+    asset_issuer: Option<String>,
+    // create_account, account_merge
+    funder: Option<String>,
+    account: Option<String>,
+    starting_balance: Option<String>,
+    into: Option<String>,
+    // create_claimable_balance
+    asset: Option<String>,
+    transaction: Option<JoinedTransaction>,
+}
+
+impl OperationOp {
+    fn into_record(self) -> Option<OperationRecord> {
+        let id = self.id.parse().ok()?;
+
+        let (source_account, destination_account, amount, asset) = match self.operation_type.as_str() {
+            "create_account" => (
+                self.funder.unwrap_or(self.source_account),
+                self.account,
+                self.starting_balance,
+                Some("native".to_string()),
+            ),
+            "account_merge" => (self.account.unwrap_or(self.source_account), self.into, None, None),
+            "create_claimable_balance" => (self.source_account, None, self.amount, self.asset),
+            _ => {
+                let asset = self.asset_type.map(|asset_type| match (asset_type.as_str(), self.asset_code, self.asset_issuer) {
+                    ("native", _, _) => "native".to_string(),
+                    (_, Some(code), Some(issuer)) => format!("{}:{}", code, issuer),
+                    _ => asset_type,
+                });
+                (self.from.unwrap_or(self.source_account), self.to, self.amount, asset)
+            }
+        };
+
+        let (memo, memo_type, ledger) = match self.transaction {
+            Some(tx) => (tx.memo, (tx.memo_type != "none").then_some(tx.memo_type), tx.ledger),
+            None => (None, None, None),
+        };
+
+        Some(OperationRecord {
+            id,
+            tx_hash: self.transaction_hash,
+            operation_type: self.operation_type,
+            source_account,
+            destination_account,
+            amount: amount.and_then(|a| a.parse().ok()),
+            asset,
+            memo,
+            memo_type,
+            ledger,
+            successful: self.transaction_successful,
+            created_at: self.created_at.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+}
+
+impl JoinedPaymentOp {
+    /// Operations without a destination and amount (e.g. account merges) are skipped
+    fn into_record(self) -> Option<PaymentRecord> {
+        let to = self.to?;
+        let amount = self.amount?;
+        Some(PaymentRecord {
+            paging_token: self.paging_token,
+            tx_hash: self.transaction_hash,
+            to,
+            to_muxed: self.to_muxed,
+            amount: match self.asset_type.as_deref() {
+                Some("native") => amount.parse().ok(),
+                _ => None,
+            },
+            memo: self
+                .transaction
+                .filter(|tx| tx.memo_type == "text")
+                .and_then(|tx| tx.memo),
+            successful: self.transaction_successful,
+            created_at: self.created_at.parse().unwrap_or_else(|_| Utc::now()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT: &str = "GAUZUPTHOMSZEV65VNSRMUDAAE4VBMSRYYAX3UOWYU3BQUZ6OK65NOWM";
+
+    #[test]
+    fn test_parse_muxed_address_round_trip() {
+        let muxed = ResolvedAddress { account_id: ACCOUNT.to_string(), muxed_id: Some(420) }.address();
+        assert!(muxed.starts_with('M'));
+
+        let parsed = parse_address(&muxed).unwrap();
+        assert_eq!(parsed.account_id, ACCOUNT);
+        assert_eq!(parsed.muxed_id, Some(420));
+        assert_eq!(base_account(&muxed), ACCOUNT);
+        assert_eq!(parse_address(ACCOUNT).unwrap().address(), ACCOUNT);
+        assert!(parse_address("bob*example.com").is_err());
+    }
+
+    #[test]
+    fn test_federation_server_from_toml() {
+        let toml = "NETWORK_PASSPHRASE=\"Test SDF Network ; September 2015\"\n\
+                    FEDERATION_SERVER = \"https://example.com/federation\" # SEP-2\n";
+        assert_eq!(federation_server_from_toml(toml).as_deref(), Some("https://example.com/federation"));
+        assert_eq!(federation_server_from_toml("TRANSFER_SERVER=\"https://x\""), None);
+    }
+
+    #[test]
+    fn test_operation_records() {
+        let payment: OperationOp = serde_json::from_value(serde_json::json!({
+            "id": "12884905985",
+            "type": "payment",
+            "source_account": ACCOUNT,
+            "transaction_hash": "abc",
+            "transaction_successful": true,
+            "created_at": "2025-10-21T12:00:00Z",
+            "from": ACCOUNT,
+            "to": "GDEST",
+            "amount": "12.5000000",
+            "asset_type": "credit_alphanum4",
+            "asset_code": "USDC",
+            "asset_issuer": "GISSUER",
+            "transaction": {"memo_type": "text", "memo": "fh-1", "ledger": 3}
+        }))
+        .unwrap();
+        let record = payment.into_record().unwrap();
+        assert_eq!(record.id, 12884905985);
+        assert_eq!(record.destination_account.as_deref(), Some("GDEST"));
+        assert_eq!(record.amount, Some("12.5".parse().unwrap()));
+        assert_eq!(record.asset.as_deref(), Some("USDC:GISSUER"));
+        assert_eq!(record.memo.as_deref(), Some("fh-1"));
+        assert_eq!(record.ledger, Some(3));
+
+        let create: OperationOp = serde_json::from_value(serde_json::json!({
+            "id": "12884905986",
+            "type": "create_account",
+            "source_account": ACCOUNT,
+            "transaction_hash": "def",
+            "created_at": "2025-10-21T12:00:00Z",
+            "funder": ACCOUNT,
+            "account": "GNEW",
+            "starting_balance": "2.0000000",
+            "transaction": {"memo_type": "none", "ledger": 3}
+        }))
+        .unwrap();
+        let record = create.into_record().unwrap();
+        assert_eq!(record.source_account, ACCOUNT);
+        assert_eq!(record.destination_account.as_deref(), Some("GNEW"));
+        assert_eq!(record.asset.as_deref(), Some("native"));
+        assert_eq!(record.memo_type, None);
+        assert!(!record.successful);
+    }
+
+    #[test]
+    fn test_split_federation_address() {
+        assert_eq!(split_federation_address("bob*example.com"), Some(("bob", "example.com")));
+        assert_eq!(split_federation_address("bob@mail.com*example.com"), Some(("bob@mail.com", "example.com")));
+        assert_eq!(split_federation_address("bob*"), None);
+        assert_eq!(split_federation_address("*example.com"), None);
+        assert_eq!(split_federation_address("bob*example.com/evil"), None);
+        assert_eq!(split_federation_address(ACCOUNT), None);
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use crate::services::stellar::{StellarService, TransactionRecord, WalletBalance};
+use crate::services::stellar_tx::TxSubmitter;
+use crate::utils::money::Stroops;
+
+/// The Stellar operations handlers depend on, so they can run against
+/// Horizon in production and `MockStellar` in tests
+#[async_trait]
+pub trait StellarApi: Send + Sync {
+    /// Whether the account exists on the network
+    async fn validate_wallet(&self, public_key: &str) -> Result<bool>;
+
+    async fn fetch_balance(&self, public_key: &str) -> Result<WalletBalance>;
+
+    /// Most recent payments involving the account, newest first
+    async fn fetch_transactions(&self, public_key: &str) -> Result<Vec<TransactionRecord>>;
+
+    /// Whether `submit_payment` can succeed at all
+    fn can_submit_payments(&self) -> bool;
+
+    /// Pay `amount` XLM from the platform account; returns the transaction hash
+    async fn submit_payment(&self, destination: &str, amount: Stroops, memo: Option<&str>) -> Result<String>;
+}
+
+/// `StellarApi` backed by Horizon, submitting payments with the platform key
+pub struct HorizonStellar {
+    service: StellarService,
+    payments: Option<TxSubmitter>,
+}
+
+impl HorizonStellar {
+    pub fn new(service: StellarService, payments: Option<TxSubmitter>) -> Self {
+        Self { service, payments }
+    }
+}
+
+#[async_trait]
+impl StellarApi for HorizonStellar {
+    async fn validate_wallet(&self, public_key: &str) -> Result<bool> {
+        self.service.validate_wallet(public_key).await
+    }
+
+    async fn fetch_balance(&self, public_key: &str) -> Result<WalletBalance> {
+        self.service.fetch_wallet_balance(public_key).await
+    }
+
+    async fn fetch_transactions(&self, public_key: &str) -> Result<Vec<TransactionRecord>> {
+        self.service.fetch_wallet_transactions(public_key).await
+    }
+
+    fn can_submit_payments(&self) -> bool {
+        self.payments.is_some()
+    }
+
+    async fn submit_payment(&self, destination: &str, amount: Stroops, memo: Option<&str>) -> Result<String> {
+        let payments = self
+            .payments
+            .as_ref()
+            .ok_or_else(|| anyhow!("Platform payments are not configured"))?;
+        payments.pay(destination, amount, memo).await
+    }
+}
+
+/// A payment recorded by `MockStellar`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockPayment {
+    pub destination: String,
+    pub amount: Stroops,
+    pub memo: Option<String>,
+}
+
+/// In-memory `StellarApi` for tests. Only accounts added with `with_account`
+/// exist; payments are recorded and credited to the destination's balance.
+#[derive(Default)]
+pub struct MockStellar {
+    accounts: Mutex<HashMap<String, WalletBalance>>,
+    transactions: Mutex<HashMap<String, Vec<TransactionRecord>>>,
+    payments: Mutex<Vec<MockPayment>>,
+    /// When set, every submission fails with this message
+    fail_payments: Option<String>,
+}
+
+impl MockStellar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_account(self, public_key: &str, xlm: Stroops) -> Self {
+        self.accounts
+            .lock()
+            .unwrap()
+            .insert(public_key.to_string(), WalletBalance { xlm, usdc: Stroops::ZERO });
+        self
+    }
+
+    pub fn with_transactions(self, public_key: &str, transactions: Vec<TransactionRecord>) -> Self {
+        self.transactions.lock().unwrap().insert(public_key.to_string(), transactions);
+        self
+    }
+
+    pub fn failing_payments(mut self, reason: &str) -> Self {
+        self.fail_payments = Some(reason.to_string());
+        self
+    }
+
+    /// Payments submitted so far, oldest first
+    pub fn payments(&self) -> Vec<MockPayment> {
+        self.payments.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl StellarApi for MockStellar {
+    async fn validate_wallet(&self, public_key: &str) -> Result<bool> {
+        Ok(self.accounts.lock().unwrap().contains_key(public_key))
+    }
+
+    async fn fetch_balance(&self, public_key: &str) -> Result<WalletBalance> {
+        self.accounts
+            .lock()
+            .unwrap()
+            .get(public_key)
+            .cloned()
+            .ok_or_else(|| anyhow!("account not found"))
+    }
+
+    async fn fetch_transactions(&self, public_key: &str) -> Result<Vec<TransactionRecord>> {
+        Ok(self.transactions.lock().unwrap().get(public_key).cloned().unwrap_or_default())
+    }
+
+    fn can_submit_payments(&self) -> bool {
+        true
+    }
+
+    async fn submit_payment(&self, destination: &str, amount: Stroops, memo: Option<&str>) -> Result<String> {
+        if let Some(reason) = &self.fail_payments {
+            return Err(anyhow!("{}", reason));
+        }
+        if !amount.is_positive() {
+            return Err(anyhow!("Payment amount must be positive"));
+        }
+
+        let mut payments = self.payments.lock().unwrap();
+        payments.push(MockPayment {
+            destination: destination.to_string(),
+            amount,
+            memo: memo.map(str::to_string),
+        });
+        self.accounts
+            .lock()
+            .unwrap()
+            .entry(destination.to_string())
+            .or_insert(WalletBalance { xlm: Stroops::ZERO, usdc: Stroops::ZERO })
+            .xlm += amount;
+
+        Ok(format!("{:064x}", payments.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_payments_credit_destination() {
+        let mock = MockStellar::new().with_account("GSTUDENT", Stroops::from_xlm(1).unwrap());
+
+        assert!(mock.validate_wallet("GSTUDENT").await.unwrap());
+        assert!(!mock.validate_wallet("GUNKNOWN").await.unwrap());
+
+        let hash = mock.submit_payment("GSTUDENT", Stroops::from_xlm(5).unwrap(), Some("hi")).await.unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(mock.fetch_balance("GSTUDENT").await.unwrap().xlm, Stroops::from_xlm(6).unwrap());
+        assert_eq!(
+            mock.payments(),
+            vec![MockPayment {
+                destination: "GSTUDENT".to_string(),
+                amount: Stroops::from_xlm(5).unwrap(),
+                memo: Some("hi".to_string()),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_failing_payments() {
+        let mock = MockStellar::new().failing_payments("horizon down");
+        let api: &dyn StellarApi = &mock;
+
+        assert!(api.submit_payment("GSTUDENT", Stroops::from_xlm(1).unwrap(), None).await.is_err());
+        assert!(mock.payments().is_empty());
+        assert!(api.fetch_balance("GSTUDENT").await.is_err());
+        assert!(api.fetch_transactions("GSTUDENT").await.unwrap().is_empty());
+    }
+}
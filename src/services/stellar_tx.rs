@@ -0,0 +1,459 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use ed25519_dalek::{Signer, SigningKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use stellar_xdr::curr::{self as xdr, Limits, WriteXdr};
+use tokio::sync::Mutex;
+
+use crate::services::{soroban_rpc, stellar};
+use crate::utils::money::Stroops;
+
+/// Per-operation fee offered on the first submission
+const BASE_FEE: u32 = 100;
+/// Fee bumps multiply the per-operation fee by this much...
+const FEE_BUMP_MULTIPLIER: u32 = 10;
+/// ...but never beyond this per operation unless `STELLAR_MAX_FEE_STROOPS` says otherwise
+const DEFAULT_MAX_FEE: u32 = 10_000;
+/// How long a submitted transaction stays valid
+const TX_TIMEOUT_SECS: u64 = 120;
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+/// How long a claimant has before the platform may reclaim an unclaimed balance
+const CLAIM_WINDOW_SECS: i64 = 90 * 24 * 60 * 60;
+
+/// Why Horizon refused a submission
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubmitError {
+    /// Our cached sequence number is stale
+    BadSequence,
+    InsufficientFee,
+    /// The time bounds expired before the transaction was applied
+    TooLate,
+    /// Horizon timed out; the transaction may still be applied
+    Timeout,
+    Other(String),
+}
+
+impl SubmitError {
+    /// Classify a Horizon error response by its result codes
+    pub fn from_response(status: u16, body: &serde_json::Value) -> Self {
+        if status == 504 {
+            return SubmitError::Timeout;
+        }
+        let codes = &body["extras"]["result_codes"];
+        match codes["transaction"].as_str() {
+            Some("tx_bad_seq") => SubmitError::BadSequence,
+            Some("tx_insufficient_fee") => SubmitError::InsufficientFee,
+            Some("tx_too_late") => SubmitError::TooLate,
+            Some(code) => {
+                let ops = codes["operations"]
+                    .as_array()
+                    .map(|ops| ops.iter().filter_map(|o| o.as_str()).collect::<Vec<_>>().join(","))
+                    .unwrap_or_default();
+                SubmitError::Other(if ops.is_empty() { code.to_string() } else { format!("{} ({})", code, ops) })
+            }
+            None => SubmitError::Other(format!("Horizon returned {}", status)),
+        }
+    }
+}
+
+/// Total fee for a fee bump wrapping a transaction with `op_count` operations
+/// that was submitted at `base_fee` per operation
+pub fn fee_bump_fee(base_fee: u32, op_count: u32, max_fee: u32) -> i64 {
+    let bumped = base_fee.saturating_mul(FEE_BUMP_MULTIPLIER).min(max_fee).max(base_fee);
+    // A fee bump pays for the inner operations plus itself
+    bumped as i64 * (op_count as i64 + 1)
+}
+
+/// A claimable balance created by the platform
+#[derive(Debug, Clone)]
+pub struct ClaimableBalance {
+    pub tx_hash: String,
+    /// Hex id as Horizon reports it
+    pub balance_id: String,
+}
+
+#[derive(Deserialize)]
+struct AccountResponse {
+    sequence: String,
+}
+
+#[derive(Deserialize)]
+struct SubmitResponse {
+    hash: String,
+}
+
+/// Builds, signs with the platform secret, and submits payments from the
+/// platform account. Sequence numbers are cached and handed out one at a
+/// time so concurrent payments don't collide; a stale sequence is reloaded
+/// and the payment retried.
+#[derive(Clone)]
+pub struct TxSubmitter {
+    http: reqwest::Client,
+    horizon_url: String,
+    network_passphrase: String,
+    signing_key: SigningKey,
+    max_fee: u32,
+    /// Last sequence number used; `None` until loaded from Horizon
+    sequence: Arc<Mutex<Option<i64>>>,
+}
+
+impl TxSubmitter {
+    pub fn new(horizon_url: &str, platform_secret: &str, network_passphrase: &str) -> Result<Self> {
+        let seed = stellar_strkey::ed25519::PrivateKey::from_string(platform_secret)
+            .map_err(|_| anyhow!("PLATFORM_WALLET_SECRET_KEY is not a valid Stellar secret key"))?;
+        let max_fee = std::env::var("STELLAR_MAX_FEE_STROOPS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_FEE);
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            horizon_url: horizon_url.trim_end_matches('/').to_string(),
+            network_passphrase: network_passphrase.to_string(),
+            signing_key: SigningKey::from_bytes(&seed.0),
+            max_fee,
+            sequence: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    pub fn public_key(&self) -> String {
+        stellar_strkey::ed25519::PublicKey(self.signing_key.verifying_key().to_bytes()).to_string()
+    }
+
+    /// Pay `amount` XLM from the platform account, creating the destination
+    /// account if it doesn't exist yet. `destination` may be a muxed `M...`
+    /// address, whose account must already exist. Returns the transaction hash.
+    pub async fn pay(&self, destination: &str, amount: Stroops, memo: Option<&str>) -> Result<String> {
+        if !amount.is_positive() {
+            return Err(anyhow!("Payment amount must be positive"));
+        }
+        let resolved = stellar::parse_address(destination)
+            .map_err(|_| anyhow!("Invalid destination address {}", destination))?;
+        let destination_key = stellar_strkey::ed25519::PublicKey::from_string(&resolved.account_id)?;
+        let memo = text_memo(memo)?;
+
+        let body = if self.account_exists(&resolved.account_id).await? {
+            xdr::OperationBody::Payment(xdr::PaymentOp {
+                destination: match resolved.muxed_id {
+                    Some(id) => xdr::MuxedAccount::MuxedEd25519(xdr::MuxedAccountMed25519 {
+                        id,
+                        ed25519: xdr::Uint256(destination_key.0),
+                    }),
+                    None => xdr::MuxedAccount::Ed25519(xdr::Uint256(destination_key.0)),
+                },
+                asset: xdr::Asset::Native,
+                amount: amount.as_stroops(),
+            })
+        } else if resolved.muxed_id.is_some() {
+            return Err(anyhow!("Account behind muxed address {} does not exist", destination));
+        } else {
+            xdr::OperationBody::CreateAccount(xdr::CreateAccountOp {
+                destination: account_id(destination_key.0),
+                starting_balance: amount.as_stroops(),
+            })
+        };
+        let operation = xdr::Operation { source_account: None, body };
+
+        let (hash, _) = self.submit_operation(&format!("Payment to {}", destination), memo, operation).await?;
+        Ok(hash)
+    }
+
+    /// Lock `amount` XLM in a claimable balance that `claimant` can claim at
+    /// any time, e.g. once their account is funded. The platform may reclaim
+    /// it after `CLAIM_WINDOW_SECS` if it is never claimed.
+    pub async fn create_claimable_balance(
+        &self,
+        claimant: &str,
+        amount: Stroops,
+        memo: Option<&str>,
+    ) -> Result<ClaimableBalance> {
+        if !amount.is_positive() {
+            return Err(anyhow!("Claimable balance amount must be positive"));
+        }
+        // Claimants are plain accounts; a muxed address claims through its account
+        let claimant_key = stellar::parse_address(claimant)
+            .and_then(|resolved| Ok(stellar_strkey::ed25519::PublicKey::from_string(&resolved.account_id)?))
+            .map_err(|_| anyhow!("Invalid claimant address {}", claimant))?;
+        let memo = text_memo(memo)?;
+        let platform = self.signing_key.verifying_key().to_bytes();
+
+        let operation = xdr::Operation {
+            source_account: None,
+            body: xdr::OperationBody::CreateClaimableBalance(xdr::CreateClaimableBalanceOp {
+                asset: xdr::Asset::Native,
+                amount: amount.as_stroops(),
+                claimants: vec![
+                    xdr::Claimant::ClaimantTypeV0(xdr::ClaimantV0 {
+                        destination: account_id(claimant_key.0),
+                        predicate: xdr::ClaimPredicate::Unconditional,
+                    }),
+                    xdr::Claimant::ClaimantTypeV0(xdr::ClaimantV0 {
+                        destination: account_id(platform),
+                        predicate: xdr::ClaimPredicate::Not(Some(Box::new(
+                            xdr::ClaimPredicate::BeforeRelativeTime(CLAIM_WINDOW_SECS),
+                        ))),
+                    }),
+                ]
+                .try_into()?,
+            }),
+        };
+
+        let (tx_hash, sequence) = self
+            .submit_operation(&format!("Claimable balance for {}", claimant), memo, operation)
+            .await?;
+
+        Ok(ClaimableBalance {
+            tx_hash,
+            balance_id: claimable_balance_id(platform, sequence, 0)?,
+        })
+    }
+
+    /// Build, sign, and submit a single-operation transaction, retrying stale
+    /// sequences. Returns the transaction hash and the sequence it used.
+    async fn submit_operation(
+        &self,
+        label: &str,
+        memo: xdr::Memo,
+        operation: xdr::Operation,
+    ) -> Result<(String, i64)> {
+        // Hold the sequence lock for the whole submission so transactions
+        // reach Horizon in sequence order
+        let mut sequence = self.sequence.lock().await;
+        let mut last_error = SubmitError::Other("not submitted".to_string());
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let next = match *sequence {
+                Some(seq) => seq + 1,
+                None => self.load_sequence().await? + 1,
+            };
+            let tx = self.build(next, memo.clone(), operation.clone())?;
+            let signed = self.sign(&tx)?;
+            let mut envelope = xdr::TransactionEnvelope::Tx(signed.clone());
+
+            let mut result = self.submit(&envelope).await?;
+            if result == Err(SubmitError::InsufficientFee) {
+                tracing::warn!("{} underpriced, submitting fee bump", label);
+                envelope = self.fee_bump(signed)?;
+                result = self.submit(&envelope).await?;
+            }
+            if result == Err(SubmitError::Timeout) {
+                // Resubmitting the identical envelope is idempotent
+                result = self.submit(&envelope).await?;
+            }
+
+            match result {
+                Ok(hash) => {
+                    *sequence = Some(next);
+                    return Ok((hash, next));
+                }
+                Err(e) => {
+                    // Anything but success may leave our sequence out of step
+                    *sequence = None;
+                    match &e {
+                        SubmitError::Other(_) => {
+                            return Err(anyhow!("{} failed: {:?}", label, e));
+                        }
+                        // Never retry under a new sequence: the payment may still land
+                        SubmitError::Timeout => {
+                            let hash = soroban_rpc::transaction_hash(&tx, &self.network_passphrase)?;
+                            return Err(anyhow!(
+                                "{} timed out; transaction {} may still be applied",
+                                label,
+                                hex::encode(hash)
+                            ));
+                        }
+                        _ => {}
+                    }
+                    tracing::warn!("{} attempt {} failed: {:?}", label, attempt, e);
+                    last_error = e;
+                }
+            }
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+
+        Err(anyhow!("{} failed after {} attempts: {:?}", label, MAX_ATTEMPTS, last_error))
+    }
+
+    pub async fn account_exists(&self, public_key: &str) -> Result<bool> {
+        let resp = self
+            .http
+            .get(format!("{}/accounts/{}", self.horizon_url, public_key))
+            .send()
+            .await?;
+        match resp.status().as_u16() {
+            200 => Ok(true),
+            404 => Ok(false),
+            status => Err(anyhow!("Horizon returned {} for account {}", status, public_key)),
+        }
+    }
+
+    async fn load_sequence(&self) -> Result<i64> {
+        let account = self
+            .http
+            .get(format!("{}/accounts/{}", self.horizon_url, self.public_key()))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<AccountResponse>()
+            .await?;
+        Ok(account.sequence.parse()?)
+    }
+
+    fn build(&self, sequence: i64, memo: xdr::Memo, operation: xdr::Operation) -> Result<xdr::Transaction> {
+        let max_time = chrono::Utc::now().timestamp() as u64 + TX_TIMEOUT_SECS;
+        Ok(xdr::Transaction {
+            source_account: xdr::MuxedAccount::Ed25519(xdr::Uint256(self.signing_key.verifying_key().to_bytes())),
+            fee: BASE_FEE,
+            seq_num: xdr::SequenceNumber(sequence),
+            cond: xdr::Preconditions::Time(xdr::TimeBounds {
+                min_time: xdr::TimePoint(0),
+                max_time: xdr::TimePoint(max_time),
+            }),
+            memo,
+            operations: vec![operation].try_into()?,
+            ext: xdr::TransactionExt::V0,
+        })
+    }
+
+    fn signature(&self, payload: xdr::TransactionSignaturePayloadTaggedTransaction) -> Result<xdr::DecoratedSignature> {
+        let network_id: [u8; 32] = Sha256::digest(self.network_passphrase.as_bytes()).into();
+        let payload = xdr::TransactionSignaturePayload {
+            network_id: xdr::Hash(network_id),
+            tagged_transaction: payload,
+        };
+        let hash: [u8; 32] = Sha256::digest(payload.to_xdr(Limits::none())?).into();
+        let public_key = self.signing_key.verifying_key().to_bytes();
+
+        Ok(xdr::DecoratedSignature {
+            hint: xdr::SignatureHint(public_key[28..].try_into()?),
+            signature: xdr::Signature(self.signing_key.sign(&hash).to_bytes().to_vec().try_into()?),
+        })
+    }
+
+    fn sign(&self, tx: &xdr::Transaction) -> Result<xdr::TransactionV1Envelope> {
+        let signature = self.signature(xdr::TransactionSignaturePayloadTaggedTransaction::Tx(tx.clone()))?;
+        Ok(xdr::TransactionV1Envelope {
+            tx: tx.clone(),
+            signatures: vec![signature].try_into()?,
+        })
+    }
+
+    /// Wrap a signed transaction in a fee bump paid by the platform account
+    fn fee_bump(&self, inner: xdr::TransactionV1Envelope) -> Result<xdr::TransactionEnvelope> {
+        let op_count = inner.tx.operations.len() as u32;
+        let tx = xdr::FeeBumpTransaction {
+            fee_source: xdr::MuxedAccount::Ed25519(xdr::Uint256(self.signing_key.verifying_key().to_bytes())),
+            fee: fee_bump_fee(BASE_FEE, op_count, self.max_fee),
+            inner_tx: xdr::FeeBumpTransactionInnerTx::Tx(inner),
+            ext: xdr::FeeBumpTransactionExt::V0,
+        };
+        let signature = self.signature(xdr::TransactionSignaturePayloadTaggedTransaction::TxFeeBump(tx.clone()))?;
+        Ok(xdr::TransactionEnvelope::TxFeeBump(xdr::FeeBumpTransactionEnvelope {
+            tx,
+            signatures: vec![signature].try_into()?,
+        }))
+    }
+
+    /// Post an envelope to Horizon. The outer error is for transport
+    /// failures; the inner one is Horizon refusing the transaction.
+    async fn submit(&self, envelope: &xdr::TransactionEnvelope) -> Result<std::result::Result<String, SubmitError>> {
+        let resp = self
+            .http
+            .post(format!("{}/transactions", self.horizon_url))
+            .form(&[("tx", envelope.to_xdr_base64(Limits::none())?)])
+            .send()
+            .await?;
+
+        let status = resp.status().as_u16();
+        if resp.status().is_success() {
+            return Ok(Ok(resp.json::<SubmitResponse>().await?.hash));
+        }
+        let body = resp.json::<serde_json::Value>().await.unwrap_or_default();
+        Ok(Err(SubmitError::from_response(status, &body)))
+    }
+}
+
+fn text_memo(memo: Option<&str>) -> Result<xdr::Memo> {
+    match memo {
+        Some(text) => Ok(xdr::Memo::Text(
+            text.try_into()
+                .map_err(|_| anyhow!("Memo must be at most 28 bytes"))?,
+        )),
+        None => Ok(xdr::Memo::None),
+    }
+}
+
+fn account_id(public_key: [u8; 32]) -> xdr::AccountId {
+    xdr::AccountId(xdr::PublicKey::PublicKeyTypeEd25519(xdr::Uint256(public_key)))
+}
+
+/// Id of the claimable balance created by operation `op_index` of the
+/// transaction `source` submitted at `sequence`
+pub fn claimable_balance_id(source: [u8; 32], sequence: i64, op_index: u32) -> Result<String> {
+    let preimage = xdr::HashIdPreimage::OpId(xdr::HashIdPreimageOperationId {
+        source_account: account_id(source),
+        seq_num: xdr::SequenceNumber(sequence),
+        op_num: op_index,
+    });
+    let hash: [u8; 32] = Sha256::digest(preimage.to_xdr(Limits::none())?).into();
+    let id = xdr::ClaimableBalanceId::ClaimableBalanceIdTypeV0(xdr::Hash(hash));
+    Ok(hex::encode(id.to_xdr(Limits::none())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_submit_error_classification() {
+        let body = |code: &str| serde_json::json!({"extras": {"result_codes": {"transaction": code}}});
+        assert_eq!(SubmitError::from_response(400, &body("tx_bad_seq")), SubmitError::BadSequence);
+        assert_eq!(SubmitError::from_response(400, &body("tx_insufficient_fee")), SubmitError::InsufficientFee);
+        assert_eq!(SubmitError::from_response(400, &body("tx_too_late")), SubmitError::TooLate);
+        assert_eq!(SubmitError::from_response(504, &serde_json::Value::Null), SubmitError::Timeout);
+
+        let failed = serde_json::json!({"extras": {"result_codes": {
+            "transaction": "tx_failed", "operations": ["op_underfunded"]
+        }}});
+        assert_eq!(
+            SubmitError::from_response(400, &failed),
+            SubmitError::Other("tx_failed (op_underfunded)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fee_bump_fee() {
+        // One inner operation plus the fee bump itself
+        assert_eq!(fee_bump_fee(100, 1, 10_000), 2_000);
+        assert_eq!(fee_bump_fee(5_000, 1, 10_000), 20_000);
+        // The cap never drops the fee below what was already offered
+        assert_eq!(fee_bump_fee(100, 1, 50), 200);
+    }
+
+    #[tokio::test]
+    async fn test_pay_rejects_bad_input_before_submitting() {
+        let secret = stellar_strkey::ed25519::PrivateKey([7u8; 32]).to_string();
+        let submitter = TxSubmitter::new("http://127.0.0.1:1", &secret, "Test SDF Network ; September 2015").unwrap();
+        let dest = "GBRPYHIL2CI3FNQ4BXLFMNDLFJUNPU2HY3ZMFSHONUCEOASW7QC7OX2H";
+
+        assert!(submitter.pay(dest, Stroops::ZERO, None).await.is_err());
+        assert!(submitter.pay("not-an-address", Stroops::from_xlm(1).unwrap(), None).await.is_err());
+        assert!(submitter.pay(dest, Stroops::from_xlm(1).unwrap(), Some(&"x".repeat(29))).await.is_err());
+        assert!(submitter.create_claimable_balance(dest, Stroops::ZERO, None).await.is_err());
+        assert!(submitter.create_claimable_balance("not-an-address", Stroops::from_xlm(1).unwrap(), None).await.is_err());
+    }
+
+    #[test]
+    fn test_claimable_balance_id() {
+        let id = claimable_balance_id([7u8; 32], 42, 0).unwrap();
+        // Horizon ids are the XDR of a v0 balance id: a zero type tag and a 32-byte hash
+        assert_eq!(id.len(), 72);
+        assert!(id.starts_with("00000000"));
+        assert_ne!(id, claimable_balance_id([7u8; 32], 43, 0).unwrap());
+        assert_ne!(id, claimable_balance_id([7u8; 32], 42, 1).unwrap());
+    }
+}
@@ -0,0 +1,745 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Datelike, Months, NaiveDate, TimeZone, Utc};
+use num_traits::Zero;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::config::{EscrowMode, StellarNetwork};
+use crate::routes::payments::provider::PaymentStatus;
+use crate::routes::payments::stripe::{SavedCard, SetupOutcome, StripeProvider};
+use crate::services::donation_memo::{self, MemoKind};
+use crate::services::notifications::NotificationEvent;
+use crate::services::payment_service::ProviderRegistry;
+use crate::services::project_schedule;
+use crate::services::sep7;
+use crate::utils::money::{Cents, Stroops};
+
+/// Charge attempts for one billing period before a subscription goes past due
+pub const MAX_CHARGE_ATTEMPTS: i32 = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SubscriptionError {
+    #[error("Subscription not found")]
+    NotFound,
+    #[error("{0}")]
+    Invalid(String),
+    #[error("{0}")]
+    Provider(String),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for SubscriptionError {
+    fn from(e: sqlx::Error) -> Self {
+        SubscriptionError::Internal(e.into())
+    }
+}
+
+/// A donor's monthly recurring gift to a project
+#[derive(Debug, Clone, Serialize)]
+pub struct Subscription {
+    pub id: Uuid,
+    pub donor_id: Uuid,
+    pub project_id: Uuid,
+    /// Fiat amount in `currency` for card subscriptions, XLM for Stellar
+    pub amount: BigDecimal,
+    pub currency: String,
+    /// `stripe` or `stellar`
+    pub payment_method: String,
+    /// `pending_setup`, `active`, `past_due` or `cancelled`
+    pub status: String,
+    pub billing_day: i32,
+    pub next_charge_at: Option<DateTime<Utc>>,
+    pub last_charged_at: Option<DateTime<Utc>>,
+    pub failed_attempts: i32,
+    pub created_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+}
+
+pub struct NewSubscription {
+    pub project_id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub payment_method: String,
+    /// Receipt address for card subscriptions; defaults to the account's email
+    pub donor_email: Option<String>,
+}
+
+/// A new subscription and, for cards, the Checkout page that saves the card
+#[derive(Debug, Serialize)]
+pub struct CreatedSubscription {
+    pub subscription: Subscription,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub setup_url: Option<String>,
+}
+
+/// How one subscription to a project has performed
+#[derive(Debug, Serialize)]
+pub struct SubscriptionStats {
+    pub id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub payment_method: String,
+    pub status: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub next_charge_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    /// Months the gift arrived: card charges taken or reminders paid
+    pub paid_periods: i64,
+    pub failed_periods: i64,
+    pub total_given: BigDecimal,
+}
+
+/// Recurring support for a project, for its owner
+#[derive(Debug, Serialize)]
+pub struct ProjectSubscriptionAnalytics {
+    pub project_id: Uuid,
+    pub active: usize,
+    pub past_due: usize,
+    pub cancelled: usize,
+    /// What active subscriptions bring in each month, by currency
+    pub monthly_recurring: BTreeMap<String, BigDecimal>,
+    /// Given so far across all subscriptions, by currency
+    pub total_given: BTreeMap<String, BigDecimal>,
+    pub subscriptions: Vec<SubscriptionStats>,
+}
+
+/// The `billing_day` of `year`-`month`, or the month's last day if it is shorter
+fn billing_date(year: i32, month: u32, billing_day: u32) -> NaiveDate {
+    let first = NaiveDate::from_ymd_opt(year, month, 1).expect("valid month");
+    let days_in_month = (first + Months::new(1) - first).num_days() as u32;
+    first
+        .with_day(billing_day.clamp(1, days_in_month))
+        .expect("day within month")
+}
+
+/// The billing period after `period`
+pub fn next_period(period: NaiveDate, billing_day: u32) -> NaiveDate {
+    let next_month = period.with_day(1).expect("first of month") + Months::new(1);
+    billing_date(next_month.year(), next_month.month(), billing_day)
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight"))
+}
+
+pub async fn get_subscription(pool: &PgPool, id: Uuid) -> anyhow::Result<Option<Subscription>> {
+    let subscription = sqlx::query_as!(
+        Subscription,
+        r#"
+        SELECT id, donor_id, project_id, amount, currency, payment_method, status, billing_day,
+               next_charge_at, last_charged_at, failed_attempts, created_at, cancelled_at
+        FROM donation_subscriptions
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(subscription)
+}
+
+/// A donor's subscriptions, newest first
+pub async fn list_for_donor(pool: &PgPool, donor_id: Uuid) -> anyhow::Result<Vec<Subscription>> {
+    let subscriptions = sqlx::query_as!(
+        Subscription,
+        r#"
+        SELECT id, donor_id, project_id, amount, currency, payment_method, status, billing_day,
+               next_charge_at, last_charged_at, failed_attempts, created_at, cancelled_at
+        FROM donation_subscriptions
+        WHERE donor_id = $1
+        ORDER BY created_at DESC
+        "#,
+        donor_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subscriptions)
+}
+
+/// Start a monthly gift. Stellar subscriptions are active straight away and
+/// get their first reminder on the scheduler's next run; card subscriptions
+/// wait until the donor has saved a card through the returned setup page.
+pub async fn create(
+    pool: &PgPool,
+    providers: &ProviderRegistry,
+    donor_id: Uuid,
+    request: NewSubscription,
+) -> Result<CreatedSubscription, SubscriptionError> {
+    let accepting = project_schedule::accepting_donations(pool, request.project_id)
+        .await?
+        .ok_or_else(|| SubscriptionError::Invalid("Project not found".to_string()))?;
+    if !accepting {
+        return Err(SubscriptionError::Invalid("Project is not accepting donations".to_string()));
+    }
+
+    let currency = request.currency.trim().to_uppercase();
+    match request.payment_method.as_str() {
+        "stellar" => {
+            if currency != "XLM" {
+                return Err(SubscriptionError::Invalid("Stellar subscriptions are in XLM".to_string()));
+            }
+            let amount = Stroops::from_decimal(&request.amount).map_err(|e| SubscriptionError::Invalid(e.to_string()))?;
+            if !amount.is_positive() {
+                return Err(SubscriptionError::Invalid("Amount must be positive".to_string()));
+            }
+        }
+        "stripe" => {
+            let amount = Cents::from_decimal(&request.amount).map_err(|e| SubscriptionError::Invalid(e.to_string()))?;
+            if !amount.is_positive() {
+                return Err(SubscriptionError::Invalid("Amount must be positive".to_string()));
+            }
+            if providers.stripe_config().is_none() {
+                return Err(SubscriptionError::Invalid("Card subscriptions are not available".to_string()));
+            }
+            if !providers.service().supports_currency("stripe", &currency) {
+                return Err(SubscriptionError::Invalid(format!("Card subscriptions are not available in {}", currency)));
+            }
+        }
+        other => {
+            return Err(SubscriptionError::Invalid(format!("Unsupported payment method '{}'", other)));
+        }
+    }
+
+    let donor_email = match request.donor_email.filter(|email| !email.trim().is_empty()) {
+        Some(email) => email.trim().to_string(),
+        None => sqlx::query_scalar!("SELECT email FROM users WHERE id = $1", donor_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or(SubscriptionError::NotFound)?,
+    };
+
+    let now = Utc::now();
+    let (status, current_period, next_charge_at) = match request.payment_method.as_str() {
+        "stellar" => ("active", Some(now.date_naive()), Some(now)),
+        _ => ("pending_setup", None, None),
+    };
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO donation_subscriptions
+            (donor_id, project_id, amount, currency, payment_method, status, billing_day,
+             current_period, next_charge_at, donor_email)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING id
+        "#,
+        donor_id,
+        request.project_id,
+        request.amount,
+        currency,
+        request.payment_method,
+        status,
+        now.day() as i32,
+        current_period,
+        next_charge_at,
+        donor_email
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let mut setup_url = None;
+    if let Some(config) = providers.stripe_config().filter(|_| request.payment_method == "stripe") {
+        let metadata = [("subscription_id", id.to_string()), ("project_id", request.project_id.to_string())];
+        let session = StripeProvider::new(config)
+            .create_setup_session(&donor_email, &currency, &metadata)
+            .await;
+        let (session_id, url) = match session {
+            Ok(session) => session,
+            Err(e) => {
+                sqlx::query!(
+                    "UPDATE donation_subscriptions SET status = 'cancelled', cancelled_at = NOW(), updated_at = NOW() WHERE id = $1",
+                    id
+                )
+                .execute(pool)
+                .await?;
+                return Err(SubscriptionError::Provider(e));
+            }
+        };
+        sqlx::query!(
+            "UPDATE donation_subscriptions SET setup_session_id = $2, updated_at = NOW() WHERE id = $1",
+            id,
+            session_id
+        )
+        .execute(pool)
+        .await?;
+        setup_url = Some(url);
+    }
+
+    let subscription = get_subscription(pool, id).await?.ok_or(SubscriptionError::NotFound)?;
+    Ok(CreatedSubscription { subscription, setup_url })
+}
+
+/// Stop a donor's subscription. Charges already taken are left alone.
+pub async fn cancel(pool: &PgPool, donor_id: Uuid, id: Uuid) -> Result<Subscription, SubscriptionError> {
+    let subscription = get_subscription(pool, id)
+        .await?
+        .filter(|subscription| subscription.donor_id == donor_id)
+        .ok_or(SubscriptionError::NotFound)?;
+    if subscription.status == "cancelled" {
+        return Err(SubscriptionError::Invalid("Subscription is already cancelled".to_string()));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE donation_subscriptions
+        SET status = 'cancelled', cancelled_at = NOW(), next_charge_at = NULL, updated_at = NOW()
+        WHERE id = $1 AND status <> 'cancelled'
+        "#,
+        id
+    )
+    .execute(pool)
+    .await?;
+
+    get_subscription(pool, id).await?.ok_or(SubscriptionError::NotFound)
+}
+
+/// Per-subscription totals for a project and what they add up to each month
+pub async fn project_analytics(pool: &PgPool, project_id: Uuid) -> anyhow::Result<ProjectSubscriptionAnalytics> {
+    let subscriptions = sqlx::query_as!(
+        SubscriptionStats,
+        r#"
+        SELECT s.id, s.amount, s.currency, s.payment_method, s.status, s.created_at, s.next_charge_at, s.cancelled_at,
+               COUNT(c.id) FILTER (WHERE c.status = 'charged' OR d.status = 'confirmed') as "paid_periods!",
+               COUNT(c.id) FILTER (WHERE c.status = 'failed') as "failed_periods!",
+               COALESCE(SUM(c.amount) FILTER (WHERE c.status = 'charged' OR d.status = 'confirmed'), 0) as "total_given!"
+        FROM donation_subscriptions s
+        LEFT JOIN subscription_charges c ON c.subscription_id = s.id
+        LEFT JOIN donations d ON d.id = c.donation_id
+        WHERE s.project_id = $1 AND s.status <> 'pending_setup'
+        GROUP BY s.id
+        ORDER BY s.created_at DESC
+        "#,
+        project_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let count = |status: &str| subscriptions.iter().filter(|s| s.status == status).count();
+    let mut monthly_recurring: BTreeMap<String, BigDecimal> = BTreeMap::new();
+    let mut total_given: BTreeMap<String, BigDecimal> = BTreeMap::new();
+    for subscription in &subscriptions {
+        if subscription.status == "active" {
+            let sum = monthly_recurring.entry(subscription.currency.clone()).or_insert_with(BigDecimal::zero);
+            *sum = &*sum + &subscription.amount;
+        }
+        let sum = total_given.entry(subscription.currency.clone()).or_insert_with(BigDecimal::zero);
+        *sum = &*sum + &subscription.total_given;
+    }
+
+    Ok(ProjectSubscriptionAnalytics {
+        project_id,
+        active: count("active"),
+        past_due: count("past_due"),
+        cancelled: count("cancelled"),
+        monthly_recurring,
+        total_given,
+        subscriptions,
+    })
+}
+
+/// Activate card subscriptions whose donor has finished the setup page, and
+/// cancel those whose setup page expired unused. Returns how many activated.
+pub async fn activate_pending_setups(pool: &PgPool, providers: &ProviderRegistry) -> anyhow::Result<usize> {
+    let Some(config) = providers.stripe_config() else {
+        return Ok(0);
+    };
+    let stripe = StripeProvider::new(config);
+
+    let pending = sqlx::query!(
+        r#"
+        SELECT id, setup_session_id as "setup_session_id!"
+        FROM donation_subscriptions
+        WHERE status = 'pending_setup' AND setup_session_id IS NOT NULL
+        ORDER BY created_at
+        LIMIT 50
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut activated = 0;
+    for subscription in pending {
+        match stripe.setup_outcome(&subscription.setup_session_id).await {
+            Ok(SetupOutcome::Saved(card)) => {
+                // Billing starts the day the card is saved
+                let now = Utc::now();
+                sqlx::query!(
+                    r#"
+                    UPDATE donation_subscriptions
+                    SET status = 'active', stripe_customer_id = $2, stripe_payment_method_id = $3,
+                        billing_day = $4, current_period = $5, next_charge_at = $6, updated_at = NOW()
+                    WHERE id = $1 AND status = 'pending_setup'
+                    "#,
+                    subscription.id,
+                    card.customer,
+                    card.payment_method,
+                    now.day() as i32,
+                    now.date_naive(),
+                    now
+                )
+                .execute(pool)
+                .await?;
+                activated += 1;
+            }
+            Ok(SetupOutcome::Expired) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE donation_subscriptions
+                    SET status = 'cancelled', cancelled_at = NOW(), updated_at = NOW()
+                    WHERE id = $1 AND status = 'pending_setup'
+                    "#,
+                    subscription.id
+                )
+                .execute(pool)
+                .await?;
+            }
+            Ok(SetupOutcome::Open) => {}
+            Err(e) => tracing::warn!("Failed to check setup of subscription {}: {}", subscription.id, e),
+        }
+    }
+
+    Ok(activated)
+}
+
+/// A subscription due for its monthly gift
+pub struct DueSubscription {
+    pub id: Uuid,
+    pub donor_id: Uuid,
+    pub project_id: Uuid,
+    pub amount: BigDecimal,
+    pub currency: String,
+    pub payment_method: String,
+    pub billing_day: i32,
+    /// The billing period this gift is for
+    pub period: NaiveDate,
+    pub donor_email: Option<String>,
+    pub stripe_customer_id: Option<String>,
+    pub stripe_payment_method_id: Option<String>,
+}
+
+pub async fn due_subscriptions(pool: &PgPool, limit: i64) -> anyhow::Result<Vec<DueSubscription>> {
+    let due = sqlx::query_as!(
+        DueSubscription,
+        r#"
+        SELECT id, donor_id, project_id, amount, currency, payment_method, billing_day,
+               current_period as "period!", donor_email, stripe_customer_id, stripe_payment_method_id
+        FROM donation_subscriptions
+        WHERE status IN ('active', 'past_due') AND next_charge_at <= NOW() AND current_period IS NOT NULL
+        ORDER BY next_charge_at
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(due)
+}
+
+/// Claim this period's charge for `subscription`, returning the charge id and
+/// attempt number. A failed charge is claimed again for a new attempt; one
+/// left `charging` by a stopped worker is retried under the same attempt, so
+/// the provider's idempotency key stops it being taken twice. `None` means
+/// the period is already handled or in progress.
+pub async fn claim_charge(pool: &PgPool, subscription: &DueSubscription) -> anyhow::Result<Option<(Uuid, i32)>> {
+    let claimed = sqlx::query!(
+        r#"
+        INSERT INTO subscription_charges (subscription_id, period, amount, currency, attempts)
+        VALUES ($1, $2, $3, $4, 1)
+        ON CONFLICT (subscription_id, period) DO UPDATE
+        SET status = 'charging',
+            attempts = subscription_charges.attempts + CASE WHEN subscription_charges.status = 'failed' THEN 1 ELSE 0 END,
+            updated_at = NOW()
+        WHERE subscription_charges.status = 'failed'
+           OR (subscription_charges.status = 'charging' AND subscription_charges.updated_at < NOW() - INTERVAL '1 hour')
+        RETURNING id, attempts
+        "#,
+        subscription.id,
+        subscription.period,
+        subscription.amount,
+        subscription.currency
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(claimed.map(|charge| (charge.id, charge.attempts)))
+}
+
+/// Charge a card subscription's saved card for this period. The charge is
+/// recorded as a payment instruction, so its webhook settles it to the
+/// project like any other card payment.
+pub async fn charge_card(
+    pool: &PgPool,
+    providers: &ProviderRegistry,
+    subscription: &DueSubscription,
+    charge_id: Uuid,
+    attempt: i32,
+) -> Result<String, String> {
+    let config = providers.stripe_config().ok_or("Stripe is not configured")?;
+    let card = match (&subscription.stripe_customer_id, &subscription.stripe_payment_method_id) {
+        (Some(customer), Some(payment_method)) => SavedCard {
+            customer: customer.clone(),
+            payment_method: payment_method.clone(),
+        },
+        _ => return Err("Subscription has no saved card".to_string()),
+    };
+    let amount = Cents::from_decimal(&subscription.amount).map_err(|e| e.to_string())?;
+    let period = subscription.period;
+
+    let metadata = [
+        ("subscription_id", subscription.id.to_string()),
+        ("project_id", subscription.project_id.to_string()),
+        ("period", period.to_string()),
+    ];
+    let idempotency_key = format!("subscription-{}-{}-{}", subscription.id, period, attempt);
+    let (payment_id, status) = StripeProvider::new(config)
+        .charge_saved_card(&card, amount, &subscription.currency, &metadata, &idempotency_key)
+        .await?;
+    if !matches!(status, PaymentStatus::Completed | PaymentStatus::Processing) {
+        return Err(format!("Charge {} ended {:?}", payment_id, status));
+    }
+
+    let recorded = async {
+        sqlx::query!(
+            r#"
+            INSERT INTO payment_instructions
+            (payment_id, payment_method, instructions, expires_at, project_id, amount, currency, donor_email, created_at)
+            VALUES ($1, 'stripe', $2, NOW() + INTERVAL '1 day', $3, $4, $5, $6, CURRENT_TIMESTAMP)
+            ON CONFLICT (payment_id) DO NOTHING
+            "#,
+            payment_id,
+            serde_json::json!({ "subscription_id": subscription.id, "period": period }),
+            subscription.project_id,
+            amount.to_decimal(),
+            subscription.currency,
+            subscription.donor_email
+        )
+        .execute(pool)
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE subscription_charges
+            SET status = 'charged', payment_id = $2, last_error = NULL, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            charge_id,
+            payment_id
+        )
+        .execute(pool)
+        .await?;
+        advance(pool, subscription).await
+    }
+    .await;
+    recorded.map_err(|e: anyhow::Error| format!("Charged {} but failed to record it: {}", payment_id, e))?;
+
+    // The webhook may have arrived before the instruction existed
+    if matches!(status, PaymentStatus::Completed) {
+        if let Err(e) = providers.service().refresh_payment_status("stripe", &payment_id).await {
+            tracing::warn!("Failed to settle subscription charge {}: {}", payment_id, e);
+        }
+    }
+
+    Ok(payment_id)
+}
+
+/// Send a Stellar subscriber this period's payment link: a pending donation
+/// under a fresh memo, and an in-app notification with its SEP-7 URI
+pub async fn send_stellar_reminder(
+    pool: &PgPool,
+    subscription: &DueSubscription,
+    charge_id: Uuid,
+    escrow_mode: EscrowMode,
+    network: StellarNetwork,
+) -> anyhow::Result<Uuid> {
+    let amount = Stroops::from_decimal(&subscription.amount)?;
+    let destination = donation_memo::expected_destination(pool, Some(subscription.project_id), escrow_mode).await?;
+
+    let donation_id = Uuid::new_v4();
+    let mut attempt = 0;
+    let memo = loop {
+        let memo = donation_memo::generate(MemoKind::Project, donation_id, attempt);
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO donations (id, donor_id, project_id, amount, payment_method, memo, status)
+            VALUES ($1, $2, $3, $4, 'stellar', $5, 'pending')
+            "#,
+            donation_id,
+            subscription.donor_id,
+            subscription.project_id,
+            amount.to_decimal(),
+            memo
+        )
+        .execute(pool)
+        .await;
+
+        match inserted {
+            Ok(_) => break memo,
+            Err(e) if donation_memo::is_memo_collision(&e) && attempt + 1 < donation_memo::MAX_MEMO_ATTEMPTS => {
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    let pay_uri = sep7::PayRequest {
+        destination: &destination,
+        amount,
+        memo: Some(&memo),
+        asset: None,
+        network_passphrase: &network.network_passphrase(),
+    }
+    .to_uri();
+    let project_title = sqlx::query_scalar!("SELECT title FROM projects WHERE id = $1", subscription.project_id)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or_else(|| "your project".to_string());
+
+    sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+        VALUES ($1, 'donation', $2, $3, $4, $5)
+        "#,
+        subscription.donor_id,
+        "Your monthly gift is due",
+        format!("Send {} XLM to {} with memo {} to continue supporting {}.", amount, destination, memo, project_title),
+        serde_json::json!({
+            "subscription_id": subscription.id,
+            "donation_id": donation_id,
+            "destination": destination,
+            "amount_xlm": amount,
+            "memo": memo,
+            "sep7_uri": pay_uri
+        }),
+        NotificationEvent::SubscriptionDue { subscription_id: subscription.id, donation_id }.to_json()
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE subscription_charges
+        SET status = 'reminded', donation_id = $2, last_error = NULL, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        charge_id,
+        donation_id
+    )
+    .execute(pool)
+    .await?;
+    advance(pool, subscription).await?;
+
+    Ok(donation_id)
+}
+
+/// Move a subscription on to its next period after this one's gift
+async fn advance(pool: &PgPool, subscription: &DueSubscription) -> anyhow::Result<()> {
+    let next = next_period(subscription.period, subscription.billing_day as u32);
+    sqlx::query!(
+        r#"
+        UPDATE donation_subscriptions
+        SET status = 'active', last_charged_at = NOW(), current_period = $2, next_charge_at = $3,
+            failed_attempts = 0, updated_at = NOW()
+        WHERE id = $1 AND status IN ('active', 'past_due')
+        "#,
+        subscription.id,
+        next,
+        start_of_day(next)
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a failed attempt at this period's charge. Attempts are retried a
+/// day apart; once they run out the subscription goes past due, the donor is
+/// told, and billing moves on to the next period. Returns true once past due.
+pub async fn record_failure(
+    pool: &PgPool,
+    subscription: &DueSubscription,
+    charge_id: Uuid,
+    attempt: i32,
+    error: &str,
+) -> anyhow::Result<bool> {
+    sqlx::query!(
+        "UPDATE subscription_charges SET status = 'failed', last_error = $2, updated_at = NOW() WHERE id = $1",
+        charge_id,
+        error
+    )
+    .execute(pool)
+    .await?;
+
+    if attempt < MAX_CHARGE_ATTEMPTS {
+        sqlx::query!(
+            r#"
+            UPDATE donation_subscriptions
+            SET failed_attempts = $2, next_charge_at = NOW() + INTERVAL '1 day', updated_at = NOW()
+            WHERE id = $1 AND status IN ('active', 'past_due')
+            "#,
+            subscription.id,
+            attempt
+        )
+        .execute(pool)
+        .await?;
+        return Ok(false);
+    }
+
+    let next = next_period(subscription.period, subscription.billing_day as u32);
+    sqlx::query!(
+        r#"
+        UPDATE donation_subscriptions
+        SET status = 'past_due', failed_attempts = $2, current_period = $3, next_charge_at = $4, updated_at = NOW()
+        WHERE id = $1 AND status IN ('active', 'past_due')
+        "#,
+        subscription.id,
+        attempt,
+        next,
+        start_of_day(next)
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+        VALUES ($1, 'donation', $2, $3, $4, $5)
+        "#,
+        subscription.donor_id,
+        "Your monthly gift could not be charged",
+        format!(
+            "We could not charge your card for this month's gift after {} attempts. We'll try again on {}.",
+            attempt, next
+        ),
+        serde_json::json!({ "subscription_id": subscription.id, "error": error }),
+        NotificationEvent::SubscriptionPastDue { subscription_id: subscription.id }.to_json()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_next_period_keeps_billing_day() {
+        assert_eq!(next_period(date(2025, 3, 15), 15), date(2025, 4, 15));
+        assert_eq!(next_period(date(2025, 12, 15), 15), date(2026, 1, 15));
+    }
+
+    #[test]
+    fn test_next_period_clamps_to_short_months() {
+        assert_eq!(next_period(date(2025, 1, 31), 31), date(2025, 2, 28));
+        assert_eq!(next_period(date(2024, 1, 31), 31), date(2024, 2, 29));
+        // Back to the billing day once the month is long enough
+        assert_eq!(next_period(date(2025, 2, 28), 31), date(2025, 3, 31));
+        assert_eq!(next_period(date(2025, 3, 31), 31), date(2025, 4, 30));
+    }
+}
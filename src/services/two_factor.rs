@@ -0,0 +1,344 @@
+use chrono::{Duration, Utc};
+use rand::Rng;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::services::email_verification::{hash_token, new_token};
+use crate::services::sep7;
+use crate::utils::totp;
+
+/// How long a pre-auth token from login may be exchanged for a session
+pub const CHALLENGE_TTL_MINUTES: i64 = 5;
+/// Wrong codes a pre-auth token survives before login must start over
+pub const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+pub const BACKUP_CODE_COUNT: usize = 10;
+
+/// Backup code characters, without look-alikes such as 0/o and 1/l
+const BACKUP_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TwoFactorError {
+    #[error("Two-factor authentication is available to staff and student accounts")]
+    NotAllowed,
+    #[error("Two-factor authentication is already enabled")]
+    AlreadyEnabled,
+    #[error("Start two-factor setup first")]
+    NotEnrolled,
+    #[error("Invalid authentication code")]
+    InvalidCode,
+    #[error("Sign-in has expired; log in again")]
+    InvalidChallenge,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for TwoFactorError {
+    fn from(e: sqlx::Error) -> Self {
+        TwoFactorError::Internal(e.into())
+    }
+}
+
+/// What an authenticator app needs to enroll
+#[derive(Debug, Serialize)]
+pub struct TwoFactorSetup {
+    /// Base32 secret, for manual entry
+    pub secret: String,
+    pub otpauth_uri: String,
+    /// PNG of the URI as a QR code, base64 encoded
+    pub qr_code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    /// Whether the account's role requires it (staff)
+    pub required: bool,
+    pub backup_codes_remaining: i64,
+}
+
+/// Roles that must enroll before using admin routes
+pub const STAFF_ROLES: [&str; 3] = ["admin", "moderator", "finance"];
+
+pub fn may_enroll(role: &str, base_role: &str) -> bool {
+    STAFF_ROLES.contains(&role) || role == "student" || base_role == "student"
+}
+
+/// Backup codes are compared without case, dashes or spaces
+pub fn normalize_backup_code(code: &str) -> String {
+    code.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+fn generate_backup_code() -> String {
+    let mut rng = rand::thread_rng();
+    let chars: String = (0..10)
+        .map(|_| BACKUP_CODE_ALPHABET[rng.gen_range(0..BACKUP_CODE_ALPHABET.len())] as char)
+        .collect();
+    format!("{}-{}", &chars[..5], &chars[5..])
+}
+
+/// Start enrollment with a new secret. Until a code confirms it with
+/// `enable`, sign-in is unchanged.
+pub async fn setup(pool: &PgPool, user_id: Uuid) -> Result<TwoFactorSetup, TwoFactorError> {
+    let user = sqlx::query!(
+        r#"
+        SELECT email, role, COALESCE(base_role, 'base_user') as "base_role!", totp_enabled
+        FROM users
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+    if !may_enroll(&user.role, &user.base_role) {
+        return Err(TwoFactorError::NotAllowed);
+    }
+    if user.totp_enabled {
+        return Err(TwoFactorError::AlreadyEnabled);
+    }
+
+    let secret = totp::generate_secret();
+    let encoded = totp::base32_encode(&secret);
+    sqlx::query!("UPDATE users SET totp_secret = $1 WHERE id = $2", encoded, user_id)
+        .execute(pool)
+        .await?;
+
+    let issuer = std::env::var("PLATFORM_LEGAL_NAME").unwrap_or_else(|_| "FundHub".to_string());
+    let otpauth_uri = totp::otpauth_uri(&issuer, &user.email, &secret);
+    let qr_code = sep7::qr_png_base64(&otpauth_uri)?;
+
+    Ok(TwoFactorSetup { secret: encoded, otpauth_uri, qr_code })
+}
+
+/// Confirm setup with a code from the app and turn 2FA on. Returns the
+/// backup codes, which are only ever shown this once.
+pub async fn enable(pool: &PgPool, user_id: Uuid, code: &str) -> Result<Vec<String>, TwoFactorError> {
+    let mut tx = pool.begin().await?;
+    let user = sqlx::query!(
+        "SELECT totp_secret, totp_enabled FROM users WHERE id = $1 FOR UPDATE",
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if user.totp_enabled {
+        return Err(TwoFactorError::AlreadyEnabled);
+    }
+    let secret = user
+        .totp_secret
+        .as_deref()
+        .and_then(totp::base32_decode)
+        .ok_or(TwoFactorError::NotEnrolled)?;
+    let step = totp::verify(&secret, code, Utc::now().timestamp()).ok_or(TwoFactorError::InvalidCode)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_enabled = TRUE, totp_enabled_at = NOW(), totp_last_step = $2
+        WHERE id = $1
+        "#,
+        user_id,
+        step
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let codes = replace_backup_codes(&mut tx, user_id).await?;
+    tx.commit().await?;
+    Ok(codes)
+}
+
+/// Turn 2FA off; needs a current code or a backup code
+pub async fn disable(pool: &PgPool, user_id: Uuid, code: &str) -> Result<(), TwoFactorError> {
+    let mut tx = pool.begin().await?;
+    if !check_second_factor(&mut tx, user_id, code).await? {
+        return Err(TwoFactorError::InvalidCode);
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_enabled = FALSE, totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!("DELETE FROM two_factor_backup_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn status(pool: &PgPool, user_id: Uuid) -> Result<TwoFactorStatus, TwoFactorError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT u.totp_enabled, u.role = ANY($2) as "required!",
+               (SELECT COUNT(*) FROM two_factor_backup_codes c
+                WHERE c.user_id = u.id AND c.used_at IS NULL) as "remaining!"
+        FROM users u
+        WHERE u.id = $1
+        "#,
+        user_id,
+        &STAFF_ROLES.map(String::from)[..]
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(TwoFactorStatus { enabled: row.totp_enabled, required: row.required, backup_codes_remaining: row.remaining })
+}
+
+/// Whether the user must enroll before using admin routes
+pub async fn enrollment_required(pool: &PgPool, user_id: Uuid) -> anyhow::Result<bool> {
+    let required = sqlx::query_scalar!(
+        r#"SELECT role = ANY($2) AND NOT totp_enabled as "required!" FROM users WHERE id = $1"#,
+        user_id,
+        &STAFF_ROLES.map(String::from)[..]
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(required.unwrap_or(false))
+}
+
+pub async fn is_enabled(pool: &PgPool, user_id: Uuid) -> anyhow::Result<bool> {
+    let enabled = sqlx::query_scalar!("SELECT totp_enabled FROM users WHERE id = $1", user_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(enabled)
+}
+
+/// Pre-auth token for a login that still owes its second factor
+pub async fn create_challenge(pool: &PgPool, user_id: Uuid) -> anyhow::Result<String> {
+    let token = new_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO login_challenges (user_id, token_hash, expires_at)
+        VALUES ($1, $2, $3)
+        "#,
+        user_id,
+        hash_token(&token),
+        Utc::now() + Duration::minutes(CHALLENGE_TTL_MINUTES)
+    )
+    .execute(pool)
+    .await?;
+    Ok(token)
+}
+
+/// Finish a login with its pre-auth token and a code; returns who signed in
+pub async fn complete_challenge(pool: &PgPool, token: &str, code: &str) -> Result<Uuid, TwoFactorError> {
+    let mut tx = pool.begin().await?;
+    let challenge = sqlx::query!(
+        r#"
+        SELECT id, user_id, attempts
+        FROM login_challenges
+        WHERE token_hash = $1 AND completed_at IS NULL AND expires_at > NOW()
+        FOR UPDATE
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .filter(|c| c.attempts < MAX_CHALLENGE_ATTEMPTS)
+    .ok_or(TwoFactorError::InvalidChallenge)?;
+
+    if !check_second_factor(&mut tx, challenge.user_id, code).await? {
+        sqlx::query!("UPDATE login_challenges SET attempts = attempts + 1 WHERE id = $1", challenge.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        return Err(TwoFactorError::InvalidCode);
+    }
+
+    sqlx::query!("UPDATE login_challenges SET completed_at = NOW() WHERE id = $1", challenge.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(challenge.user_id)
+}
+
+/// Accept a TOTP code (each at most once) or an unused backup code
+async fn check_second_factor(conn: &mut PgConnection, user_id: Uuid, code: &str) -> Result<bool, TwoFactorError> {
+    let user = sqlx::query!(
+        "SELECT totp_secret, totp_last_step FROM users WHERE id = $1 AND totp_enabled FOR UPDATE",
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(TwoFactorError::NotEnrolled)?;
+
+    let secret = user
+        .totp_secret
+        .as_deref()
+        .and_then(totp::base32_decode)
+        .ok_or(TwoFactorError::NotEnrolled)?;
+    if let Some(step) = totp::verify(&secret, code, Utc::now().timestamp()) {
+        if user.totp_last_step.is_some_and(|last| step <= last) {
+            return Ok(false);
+        }
+        sqlx::query!("UPDATE users SET totp_last_step = $1 WHERE id = $2", step, user_id)
+            .execute(&mut *conn)
+            .await?;
+        return Ok(true);
+    }
+
+    let used = sqlx::query!(
+        r#"
+        UPDATE two_factor_backup_codes
+        SET used_at = NOW()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+        user_id,
+        hash_token(&normalize_backup_code(code))
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    Ok(used > 0)
+}
+
+async fn replace_backup_codes(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<String>, TwoFactorError> {
+    sqlx::query!("DELETE FROM two_factor_backup_codes WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let codes: Vec<String> = (0..BACKUP_CODE_COUNT).map(|_| generate_backup_code()).collect();
+    for code in &codes {
+        sqlx::query!(
+            "INSERT INTO two_factor_backup_codes (user_id, code_hash) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            user_id,
+            hash_token(&normalize_backup_code(code))
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(codes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_code_format() {
+        let code = generate_backup_code();
+        assert_eq!(code.len(), 11);
+        assert_eq!(code.as_bytes()[5], b'-');
+        assert!(normalize_backup_code(&code).bytes().all(|b| BACKUP_CODE_ALPHABET.contains(&b)));
+    }
+
+    #[test]
+    fn test_normalize_backup_code() {
+        assert_eq!(normalize_backup_code(" AbCde-fGH23 "), "abcdefgh23");
+        assert_eq!(normalize_backup_code("abcde fgh23"), "abcdefgh23");
+    }
+
+    #[test]
+    fn test_may_enroll() {
+        assert!(may_enroll("admin", "base_user"));
+        assert!(may_enroll("finance", "base_user"));
+        assert!(may_enroll("student", "student"));
+        assert!(may_enroll("user", "student"));
+        assert!(!may_enroll("user", "base_user"));
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::verification_decisions;
+
+/// What happens to a verification application from a domain
+pub const POLICIES: &[&str] = &[POLICY_MANUAL, POLICY_AUTO_VERIFY, POLICY_BLOCKED];
+/// Queued for an admin
+pub const POLICY_MANUAL: &str = "manual";
+/// Approved on submission
+pub const POLICY_AUTO_VERIFY: &str = "auto_verify";
+/// Refused
+pub const POLICY_BLOCKED: &str = "blocked";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UniversityDomain {
+    pub id: Uuid,
+    /// Exact, like `uonbi.ac.ke`, or a wildcard over subdomains, like `*.ac.ke`
+    pub domain: String,
+    pub university_name: Option<String>,
+    pub policy: String,
+    pub is_active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A domain as entered by an admin
+#[derive(Debug, Clone)]
+pub struct DomainChange<'a> {
+    pub domain: &'a str,
+    pub university_name: Option<&'a str>,
+    pub policy: &'a str,
+    pub is_active: bool,
+}
+
+fn is_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= 63
+        && label.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+}
+
+/// Lowercase and check a domain pattern: dot-separated labels, optionally
+/// behind `*.`. A bare `*` or `*.tld`-less pattern isn't allowed.
+pub fn normalize_pattern(raw: &str) -> Option<String> {
+    let pattern = raw.trim().trim_end_matches('.').to_lowercase();
+    let host = pattern.strip_prefix("*.").unwrap_or(&pattern);
+    (pattern.len() <= 255 && host.split('.').all(is_label)).then_some(pattern)
+}
+
+/// The domain of an email address, lowercased
+pub fn email_domain(email: &str) -> Option<String> {
+    let (_, domain) = email.trim().rsplit_once('@')?;
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    domain.split('.').all(is_label).then_some(domain)
+}
+
+/// Every pattern that would match a domain, most specific first: the domain
+/// itself, then wildcards over each of its parents
+pub fn candidates(domain: &str) -> Vec<String> {
+    let mut candidates = vec![domain.to_string()];
+    let mut rest = domain;
+    while let Some((_, parent)) = rest.split_once('.') {
+        candidates.push(format!("*.{}", parent));
+        rest = parent;
+    }
+    candidates
+}
+
+/// The active entry governing a school email, if its domain is listed
+pub async fn lookup(pool: &PgPool, email: &str) -> Result<Option<UniversityDomain>> {
+    let Some(domain) = email_domain(email) else {
+        return Ok(None);
+    };
+    let candidates = candidates(&domain);
+    let mut matches = sqlx::query_as!(
+        UniversityDomain,
+        r#"
+        SELECT id, domain, university_name, policy, is_active, created_by, created_at, updated_at
+        FROM university_domains
+        WHERE is_active AND domain = ANY($1)
+        "#,
+        &candidates
+    )
+    .fetch_all(pool)
+    .await?;
+    matches.sort_by_key(|d| candidates.iter().position(|c| *c == d.domain));
+    Ok(matches.into_iter().next())
+}
+
+pub async fn list(pool: &PgPool) -> Result<Vec<UniversityDomain>> {
+    let domains = sqlx::query_as!(
+        UniversityDomain,
+        r#"
+        SELECT id, domain, university_name, policy, is_active, created_by, created_at, updated_at
+        FROM university_domains
+        ORDER BY domain
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(domains)
+}
+
+/// `None` if the domain is already listed
+pub async fn create(pool: &PgPool, change: &DomainChange<'_>, admin_id: Uuid) -> Result<Option<UniversityDomain>> {
+    let domain = sqlx::query_as!(
+        UniversityDomain,
+        r#"
+        INSERT INTO university_domains (domain, university_name, policy, is_active, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (domain) DO NOTHING
+        RETURNING id, domain, university_name, policy, is_active, created_by, created_at, updated_at
+        "#,
+        change.domain,
+        change.university_name,
+        change.policy,
+        change.is_active,
+        admin_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(domain)
+}
+
+/// `Err` on a clash with another entry's domain; `None` if there's no such entry
+pub async fn update(pool: &PgPool, id: Uuid, change: &DomainChange<'_>) -> Result<Option<UniversityDomain>, sqlx::Error> {
+    sqlx::query_as!(
+        UniversityDomain,
+        r#"
+        UPDATE university_domains
+        SET domain = $2, university_name = $3, policy = $4, is_active = $5, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, domain, university_name, policy, is_active, created_by, created_at, updated_at
+        "#,
+        id,
+        change.domain,
+        change.university_name,
+        change.policy,
+        change.is_active
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn delete(pool: &PgPool, id: Uuid) -> Result<Option<UniversityDomain>> {
+    let domain = sqlx::query_as!(
+        UniversityDomain,
+        r#"
+        DELETE FROM university_domains
+        WHERE id = $1
+        RETURNING id, domain, university_name, policy, is_active, created_by, created_at, updated_at
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(domain)
+}
+
+/// Approve a verification whose school domain is trusted, as an admin would
+pub async fn auto_verify(pool: &PgPool, verification_id: Uuid, message: &str) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let approved = verification_decisions::approve_in(&mut tx, verification_id, None, Some(message)).await?;
+    tx.commit().await?;
+    Ok(approved.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_pattern() {
+        assert_eq!(normalize_pattern(" UoNBI.ac.ke. ").as_deref(), Some("uonbi.ac.ke"));
+        assert_eq!(normalize_pattern("*.edu").as_deref(), Some("*.edu"));
+        assert_eq!(normalize_pattern("*"), None);
+        assert_eq!(normalize_pattern("*.*.edu"), None);
+        assert_eq!(normalize_pattern("uni..edu"), None);
+        assert_eq!(normalize_pattern("-uni.edu"), None);
+        assert_eq!(normalize_pattern("jane@uni.edu"), None);
+    }
+
+    #[test]
+    fn test_email_domain() {
+        assert_eq!(email_domain("Jane@Students.UoNBI.ac.ke").as_deref(), Some("students.uonbi.ac.ke"));
+        assert_eq!(email_domain("jane"), None);
+        assert_eq!(email_domain("jane@"), None);
+    }
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(
+            candidates("students.uonbi.ac.ke"),
+            vec!["students.uonbi.ac.ke", "*.uonbi.ac.ke", "*.ac.ke", "*.ke"]
+        );
+        assert_eq!(candidates("localhost"), vec!["localhost"]);
+    }
+}
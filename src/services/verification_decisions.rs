@@ -0,0 +1,257 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::{Acquire, PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::services::{email, outgoing_webhooks, verification_documents};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Approve,
+    Reject,
+}
+
+impl Decision {
+    /// The status a verification ends up in
+    pub fn status(self) -> &'static str {
+        match self {
+            Decision::Approve => "verified",
+            Decision::Reject => "rejected",
+        }
+    }
+}
+
+/// How one verification in a bulk request went
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkResult {
+    pub verification_id: Uuid,
+    pub ok: bool,
+    /// The verification's new status, when decided
+    pub status: Option<&'static str>,
+    /// Why it was left alone: `not_found`, `not_pending`,
+    /// `school_email_unconfirmed`, `documents_not_accepted`, `failed`, or
+    /// `rolled_back` when an all-or-nothing request had another item fail
+    pub error: Option<&'static str>,
+    #[serde(skip)]
+    pub user_id: Option<Uuid>,
+}
+
+impl BulkResult {
+    fn failed(verification_id: Uuid, user_id: Option<Uuid>, error: &'static str) -> Self {
+        Self { verification_id, ok: false, status: None, error: Some(error), user_id }
+    }
+}
+
+/// Approve a pending verification, making its applicant a student; `None`
+/// if it isn't pending
+pub async fn approve_in(
+    conn: &mut PgConnection,
+    verification_id: Uuid,
+    admin_id: Option<Uuid>,
+    message: Option<&str>,
+) -> Result<Option<Uuid>> {
+    let Some(verification) = sqlx::query!(
+        r#"
+        UPDATE student_verifications
+        SET status = 'verified', admin_message = $2, approved_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND status = 'pending'
+        RETURNING user_id, school_email
+        "#,
+        verification_id,
+        message
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET role = 'student', base_role = 'student', is_verified = true, verification_status = 'verified',
+            verification_approved_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+        verification.user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO students (user_id, school_email, verification_status, verified_at, verified_by)
+        VALUES ($1, $2, 'verified', CURRENT_TIMESTAMP, $3)
+        ON CONFLICT (school_email)
+        DO UPDATE SET user_id = $1, verification_status = 'verified', verified_at = CURRENT_TIMESTAMP, verified_by = $3
+        "#,
+        verification.user_id,
+        verification.school_email,
+        admin_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO verification_history (user_id, verification_id, status, admin_message, admin_id)
+        VALUES ($1, $2, 'verified', $3, $4)
+        "#,
+        verification.user_id,
+        verification_id,
+        message,
+        admin_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(Some(verification.user_id))
+}
+
+/// Reject a pending verification; `None` if it isn't pending
+pub async fn reject_in(conn: &mut PgConnection, verification_id: Uuid, admin_id: Option<Uuid>, reason: &str) -> Result<Option<Uuid>> {
+    let Some(user_id) = sqlx::query_scalar!(
+        r#"
+        UPDATE student_verifications
+        SET status = 'rejected', admin_message = $2
+        WHERE id = $1 AND status = 'pending'
+        RETURNING user_id
+        "#,
+        verification_id,
+        reason
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    sqlx::query!("UPDATE users SET verification_status = 'rejected' WHERE id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO verification_history (user_id, verification_id, status, admin_message, admin_id)
+        VALUES ($1, $2, 'rejected', $3, $4)
+        "#,
+        user_id,
+        verification_id,
+        reason,
+        admin_id
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(Some(user_id))
+}
+
+/// Email the applicant the decision and, for approvals, tell partner webhooks
+pub async fn announce(pool: &PgPool, verification_id: Uuid, user_id: Uuid, approved: bool, message: Option<String>) {
+    if let Err(e) = email::queue_verification_decision(pool, verification_id, user_id, approved, message).await {
+        tracing::error!("Failed to queue the verification decision email for {}: {}", verification_id, e);
+    }
+    if approved {
+        if let Err(e) = outgoing_webhooks::queue_verification_approved(pool, verification_id, user_id).await {
+            tracing::error!("Failed to queue verification webhooks for {}: {}", verification_id, e);
+        }
+    }
+}
+
+/// Each id once, in the order given
+pub fn dedupe(ids: &[Uuid]) -> Vec<Uuid> {
+    let mut seen = std::collections::HashSet::new();
+    ids.iter().copied().filter(|id| seen.insert(*id)).collect()
+}
+
+/// Decide many verifications in one transaction, each under its own
+/// savepoint so one failing leaves the rest decided. With `atomic`, any
+/// failure rolls every decision back.
+pub async fn bulk(
+    pool: &PgPool,
+    ids: &[Uuid],
+    decision: Decision,
+    admin_id: Uuid,
+    message: Option<&str>,
+    atomic: bool,
+) -> Result<Vec<BulkResult>> {
+    let mut tx = pool.begin().await?;
+    let mut results = Vec::with_capacity(ids.len());
+
+    for verification_id in dedupe(ids) {
+        let current = sqlx::query!(
+            r#"
+            SELECT user_id, status, school_email_confirmed_at
+            FROM student_verifications
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            verification_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(current) = current else {
+            results.push(BulkResult::failed(verification_id, None, "not_found"));
+            continue;
+        };
+        let user_id = Some(current.user_id);
+        if current.status.as_deref() != Some("pending") {
+            results.push(BulkResult::failed(verification_id, user_id, "not_pending"));
+            continue;
+        }
+        if decision == Decision::Approve {
+            if current.school_email_confirmed_at.is_none() {
+                results.push(BulkResult::failed(verification_id, user_id, "school_email_unconfirmed"));
+                continue;
+            }
+            if !verification_documents::has_accepted(pool, current.user_id).await? {
+                results.push(BulkResult::failed(verification_id, user_id, "documents_not_accepted"));
+                continue;
+            }
+        }
+
+        let mut savepoint = (&mut *tx).begin().await?;
+        let decided = match decision {
+            Decision::Approve => approve_in(&mut savepoint, verification_id, Some(admin_id), message).await,
+            Decision::Reject => reject_in(&mut savepoint, verification_id, Some(admin_id), message.unwrap_or_default()).await,
+        };
+        match decided {
+            Ok(Some(_)) => {
+                savepoint.commit().await?;
+                results.push(BulkResult { verification_id, ok: true, status: Some(decision.status()), error: None, user_id });
+            }
+            Ok(None) => {
+                savepoint.rollback().await?;
+                results.push(BulkResult::failed(verification_id, user_id, "not_pending"));
+            }
+            Err(e) => {
+                tracing::error!("Failed to {:?} verification {}: {}", decision, verification_id, e);
+                savepoint.rollback().await?;
+                results.push(BulkResult::failed(verification_id, user_id, "failed"));
+            }
+        }
+    }
+
+    if atomic && results.iter().any(|r| !r.ok) {
+        tx.rollback().await?;
+        for result in results.iter_mut().filter(|r| r.ok) {
+            *result = BulkResult::failed(result.verification_id, result.user_id, "rolled_back");
+        }
+        return Ok(results);
+    }
+    tx.commit().await?;
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedupe_keeps_first_order() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(dedupe(&[a, b, a, c, b]), vec![a, b, c]);
+    }
+
+    #[test]
+    fn test_decision_status() {
+        assert_eq!(Decision::Approve.status(), "verified");
+        assert_eq!(Decision::Reject.status(), "rejected");
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// An admin's call on one document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Accepted,
+    Rejected,
+}
+
+impl Decision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Decision::Accepted => "accepted",
+            Decision::Rejected => "rejected",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReviewError {
+    #[error("Only documents scanned clean of malware can be accepted")]
+    NotClean,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<sqlx::Error> for ReviewError {
+    fn from(e: sqlx::Error) -> Self {
+        ReviewError::Internal(e.into())
+    }
+}
+
+/// A file a student submitted to get verified, with its review
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationDocument {
+    pub id: Uuid,
+    pub document_type: Option<String>,
+    pub filename: String,
+    pub mime_type: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub checksum: Option<String>,
+    /// `object` when uploaded to storage, `local` for older references
+    pub storage: String,
+    pub scan_status: String,
+    /// `accepted` or `rejected`; `None` until reviewed
+    pub review_status: Option<String>,
+    pub review_note: Option<String>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub key: String,
+}
+
+/// Whose documents a verification covers; `None` if there's no such verification
+pub async fn applicant(pool: &PgPool, verification_id: Uuid) -> Result<Option<Uuid>> {
+    let user_id = sqlx::query_scalar!("SELECT user_id FROM student_verifications WHERE id = $1", verification_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(user_id)
+}
+
+/// Everything a user submitted for verification, oldest first: files they
+/// own that are attached to one of their verifications or student records
+pub async fn documents(pool: &PgPool, user_id: Uuid) -> Result<Vec<VerificationDocument>> {
+    let documents = sqlx::query_as!(
+        VerificationDocument,
+        r#"
+        SELECT f.id, f.entity_type as document_type, f.filename, f.mime_type, f.size_bytes, f.checksum,
+               f.storage, f.scan_status, f.review_status, f.review_note, f.reviewed_by, f.reviewed_at,
+               f.created_at, f.path as key
+        FROM files f
+        WHERE f.owner_id = $1
+          AND (f.entity_id IN (SELECT id FROM student_verifications WHERE user_id = $1)
+               OR f.entity_id IN (SELECT id FROM students WHERE user_id = $1))
+        ORDER BY f.created_at, f.id
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(documents)
+}
+
+/// Accept or reject one of a user's documents; `None` if it isn't theirs
+pub async fn review(
+    pool: &PgPool,
+    user_id: Uuid,
+    file_id: Uuid,
+    decision: Decision,
+    note: Option<&str>,
+    reviewer: Uuid,
+) -> Result<Option<VerificationDocument>, ReviewError> {
+    let Some(document) = documents(pool, user_id).await?.into_iter().find(|d| d.id == file_id) else {
+        return Ok(None);
+    };
+    // Documents that never went through storage predate scanning
+    let scanned_clean = document.scan_status == "clean" || document.storage != "object";
+    if decision == Decision::Accepted && !scanned_clean {
+        return Err(ReviewError::NotClean);
+    }
+
+    let document = sqlx::query_as!(
+        VerificationDocument,
+        r#"
+        UPDATE files
+        SET review_status = $2, review_note = $3, reviewed_by = $4, reviewed_at = NOW()
+        WHERE id = $1
+        RETURNING id, entity_type as document_type, filename, mime_type, size_bytes, checksum,
+                  storage, scan_status, review_status, review_note, reviewed_by, reviewed_at,
+                  created_at, path as key
+        "#,
+        file_id,
+        decision.as_str(),
+        note,
+        reviewer
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(Some(document))
+}
+
+/// Whether at least one of a user's documents has been accepted
+pub async fn has_accepted(pool: &PgPool, user_id: Uuid) -> Result<bool> {
+    Ok(documents(pool, user_id)
+        .await?
+        .iter()
+        .any(|d| d.review_status.as_deref() == Some(Decision::Accepted.as_str())))
+}
@@ -0,0 +1,342 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use sqlx::PgPool;
+use tokio_util::task::TaskTracker;
+use uuid::Uuid;
+use web_push::{ContentEncoding, SubscriptionInfo, VapidSignatureBuilder, WebPushMessage, WebPushMessageBuilder};
+
+use crate::services::notification_preferences::{self, Medium};
+use crate::services::notifications::{EventPayload, NotificationEvent};
+
+/// How long push services hold a message for an offline browser
+const PUSH_TTL_SECS: u32 = 24 * 60 * 60;
+
+/// A browser's push subscription, as stored
+#[derive(Debug, Clone, Serialize)]
+pub struct PushSubscription {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub endpoint: String,
+    #[serde(skip_serializing)]
+    pub p256dh: String,
+    #[serde(skip_serializing)]
+    pub auth: String,
+    pub user_agent: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// What the service worker gets to show
+#[derive(Debug, Serialize)]
+pub struct PushMessage {
+    pub title: &'static str,
+    pub body: &'static str,
+    #[serde(flatten)]
+    pub payload: EventPayload,
+}
+
+/// Title and body for the events worth a push: verification approved,
+/// milestone released and donation received. Everything else stays in-app.
+pub fn push_text(event: &NotificationEvent) -> Option<(&'static str, &'static str)> {
+    match event {
+        NotificationEvent::VerificationStatus { status, .. } if status == "verified" => {
+            Some(("Verification approved", "Your student verification was approved"))
+        }
+        NotificationEvent::OnchainRelease { .. } => {
+            Some(("Milestone released", "Funds for a milestone were released from escrow"))
+        }
+        NotificationEvent::DonationConfirmed { project_id: Some(_), .. } => {
+            Some(("Donation received", "Your project received a new donation"))
+        }
+        _ => None,
+    }
+}
+
+/// Push services answer 404 or 410 for subscriptions that are gone for good
+pub fn is_expired(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::GONE
+}
+
+/// Sends high-priority events to users' browsers over Web Push, signed with
+/// the server's VAPID key
+#[derive(Clone)]
+pub struct WebPush {
+    pool: PgPool,
+    client: reqwest::Client,
+    /// Base64url P-256 private key
+    private_key: String,
+    /// Base64url public key, which browsers subscribe with
+    public_key: String,
+    /// `mailto:` or `https:` contact push services can reach
+    subject: String,
+    /// Deliveries in flight, which shutdown waits for
+    deliveries: TaskTracker,
+}
+
+impl WebPush {
+    /// `VAPID_PRIVATE_KEY` and `VAPID_PUBLIC_KEY` turn Web Push on, with
+    /// `VAPID_SUBJECT` as the contact; `None` leaves it off
+    pub fn from_env(pool: PgPool) -> Result<Option<Self>, String> {
+        let Some(private_key) = std::env::var("VAPID_PRIVATE_KEY").ok().filter(|k| !k.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let public_key = std::env::var("VAPID_PUBLIC_KEY")
+            .ok()
+            .filter(|k| !k.trim().is_empty())
+            .ok_or_else(|| "VAPID_PUBLIC_KEY must be set".to_string())?;
+        let subject = std::env::var("VAPID_SUBJECT").map_err(|_| "VAPID_SUBJECT must be set".to_string())?;
+        if !subject.starts_with("mailto:") && !subject.starts_with("https:") {
+            return Err("VAPID_SUBJECT must be a mailto: or https: URL".to_string());
+        }
+        Ok(Some(Self {
+            pool,
+            client: reqwest::Client::new(),
+            private_key: private_key.trim().to_string(),
+            public_key: public_key.trim().to_string(),
+            subject,
+            deliveries: TaskTracker::new(),
+        }))
+    }
+
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// Push an event to its recipients' browsers in the background, if it's
+    /// one worth pushing
+    pub fn deliver(&self, event: &NotificationEvent) {
+        let Some((title, body)) = push_text(event) else { return };
+        let push = self.clone();
+        let event = event.clone();
+        self.deliveries.spawn(async move {
+            let message = PushMessage { title, body, payload: event.clone().into() };
+            if let Err(e) = push.deliver_now(&event, &message).await {
+                tracing::warn!("Failed to push {} event: {}", event.name(), e);
+            }
+        });
+    }
+
+    /// Wait for the pushes already being delivered
+    pub async fn flush(&self) {
+        self.deliveries.close();
+        self.deliveries.wait().await;
+    }
+
+    async fn deliver_now(&self, event: &NotificationEvent, message: &PushMessage) -> Result<()> {
+        let recipients = recipients(&self.pool, event).await?;
+        let recipients = notification_preferences::wanting(&self.pool, &recipients, event.name(), Medium::Push).await?;
+        if recipients.is_empty() {
+            return Ok(());
+        }
+        let body = serde_json::to_vec(message)?;
+        for subscription in for_users(&self.pool, &recipients).await? {
+            match self.send(&subscription, &body).await {
+                Ok(()) => {
+                    sqlx::query!("UPDATE push_subscriptions SET last_used_at = NOW() WHERE id = $1", subscription.id)
+                        .execute(&self.pool)
+                        .await?;
+                }
+                Err(PushFailure::Expired) => {
+                    tracing::debug!("Removing expired push subscription {}", subscription.id);
+                    sqlx::query!("DELETE FROM push_subscriptions WHERE id = $1", subscription.id)
+                        .execute(&self.pool)
+                        .await?;
+                }
+                Err(PushFailure::Other(e)) => {
+                    tracing::warn!("Failed to push to subscription {}: {}", subscription.id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn send(&self, subscription: &PushSubscription, body: &[u8]) -> Result<(), PushFailure> {
+        let message = build_message(&self.private_key, &self.subject, subscription, body).map_err(PushFailure::Other)?;
+        // Everything pushed is high priority, so devices wake for it
+        let mut request = self
+            .client
+            .post(message.endpoint.to_string())
+            .header("TTL", message.ttl.to_string())
+            .header("Urgency", "high");
+        if let Some(payload) = message.payload {
+            request = request
+                .header(reqwest::header::CONTENT_ENCODING, payload.content_encoding.to_str())
+                .header(reqwest::header::CONTENT_TYPE, "application/octet-stream");
+            for (name, value) in payload.crypto_headers {
+                request = request.header(name, value);
+            }
+            request = request.body(payload.content);
+        }
+
+        let response = request
+            .send()
+            .await
+            .context("Push service request failed")
+            .map_err(PushFailure::Other)?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status if is_expired(status) => Err(PushFailure::Expired),
+            status => Err(PushFailure::Other(anyhow!("Push service returned {}", status))),
+        }
+    }
+}
+
+/// Encrypt `body` for a subscription and sign it with the VAPID key
+fn build_message(private_key: &str, subject: &str, subscription: &PushSubscription, body: &[u8]) -> Result<WebPushMessage> {
+    let info = SubscriptionInfo::new(&subscription.endpoint, &subscription.p256dh, &subscription.auth);
+    let mut signature = VapidSignatureBuilder::from_base64(private_key, web_push::URL_SAFE_NO_PAD, &info)
+        .map_err(|e| anyhow!("Invalid VAPID_PRIVATE_KEY: {}", e))?;
+    signature.add_claim("sub", subject);
+
+    let mut builder = WebPushMessageBuilder::new(&info);
+    builder.set_payload(ContentEncoding::Aes128Gcm, body);
+    builder.set_ttl(PUSH_TTL_SECS);
+    builder.set_vapid_signature(signature.build().map_err(|e| anyhow!("Failed to sign VAPID claims: {}", e))?);
+    builder.build().map_err(|e| anyhow!("Failed to encrypt push message: {}", e))
+}
+
+enum PushFailure {
+    /// The browser unsubscribed or the subscription lapsed
+    Expired,
+    Other(anyhow::Error),
+}
+
+/// Who an event is pushed to: the applicant for verifications and the
+/// project's owner and members for donations and releases
+async fn recipients(pool: &PgPool, event: &NotificationEvent) -> Result<Vec<Uuid>> {
+    let project_id = match event {
+        NotificationEvent::VerificationStatus { user_id, .. } => return Ok(vec![*user_id]),
+        NotificationEvent::OnchainRelease { project_id, .. } => *project_id,
+        NotificationEvent::DonationConfirmed { project_id: Some(project_id), .. } => *project_id,
+        _ => return Ok(Vec::new()),
+    };
+    let users = sqlx::query_scalar!(
+        r#"
+        SELECT s.user_id as "user_id!" FROM projects p JOIN students s ON s.id = p.student_id WHERE p.id = $1
+        UNION
+        SELECT s.user_id FROM project_members pm JOIN students s ON s.id = pm.student_id
+        WHERE pm.project_id = $1 AND pm.status = 'active'
+        "#,
+        project_id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(users)
+}
+
+async fn for_users(pool: &PgPool, user_ids: &[Uuid]) -> Result<Vec<PushSubscription>> {
+    let subscriptions = sqlx::query_as!(
+        PushSubscription,
+        r#"
+        SELECT id, user_id, endpoint, p256dh, auth, user_agent, created_at, last_used_at
+        FROM push_subscriptions
+        WHERE user_id = ANY($1)
+        "#,
+        user_ids
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(subscriptions)
+}
+
+/// Store a browser's subscription for a user. A browser re-subscribing with
+/// the same endpoint, even after switching accounts, replaces its old one.
+pub async fn subscribe(
+    pool: &PgPool,
+    user_id: Uuid,
+    endpoint: &str,
+    p256dh: &str,
+    auth: &str,
+    user_agent: Option<&str>,
+) -> Result<PushSubscription> {
+    let subscription = sqlx::query_as!(
+        PushSubscription,
+        r#"
+        INSERT INTO push_subscriptions (user_id, endpoint, p256dh, auth, user_agent)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (endpoint) DO UPDATE
+        SET user_id = EXCLUDED.user_id, p256dh = EXCLUDED.p256dh, auth = EXCLUDED.auth,
+            user_agent = EXCLUDED.user_agent, created_at = NOW(), last_used_at = NULL
+        RETURNING id, user_id, endpoint, p256dh, auth, user_agent, created_at, last_used_at
+        "#,
+        user_id,
+        endpoint,
+        p256dh,
+        auth,
+        user_agent
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(subscription)
+}
+
+/// Forget one of a user's subscriptions; false if they have none at `endpoint`
+pub async fn unsubscribe(pool: &PgPool, user_id: Uuid, endpoint: &str) -> Result<bool> {
+    let result = sqlx::query!(
+        "DELETE FROM push_subscriptions WHERE user_id = $1 AND endpoint = $2",
+        user_id,
+        endpoint
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_text() {
+        let user_id = Uuid::new_v4();
+        let project_id = Uuid::new_v4();
+        let verified = NotificationEvent::VerificationStatus { user_id, status: "verified".to_string(), reason: None };
+        let rejected = NotificationEvent::VerificationStatus {
+            user_id,
+            status: "rejected".to_string(),
+            reason: Some("Blurry ID".to_string()),
+        };
+        assert_eq!(push_text(&verified).map(|(title, _)| title), Some("Verification approved"));
+        assert!(push_text(&rejected).is_none());
+
+        let release = NotificationEvent::OnchainRelease { project_id, milestone_id: "1".to_string() };
+        assert!(push_text(&release).is_some());
+        let donation = NotificationEvent::DonationConfirmed { donation_id: Uuid::new_v4(), project_id: Some(project_id) };
+        assert!(push_text(&donation).is_some());
+        // Platform donations have no project to tell
+        let platform = NotificationEvent::DonationConfirmed { donation_id: Uuid::new_v4(), project_id: None };
+        assert!(push_text(&platform).is_none());
+        assert!(push_text(&NotificationEvent::ProjectUpdate { project_id, update_id: Uuid::new_v4() }).is_none());
+    }
+
+    #[test]
+    fn test_build_message() {
+        let subscription = PushSubscription {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            endpoint: "https://push.example.com/send/abc123".to_string(),
+            p256dh: "BCYO_UTmViccuL1TCpNo9UL_tiysTp5kxuoUV7uBTMHlvFxQOHTf4nv4w8ml9SZX3MSHgwFKMQTzmAmFxPSYa00".to_string(),
+            auth: "_RNYldKOjnRdEHFGJr5ZFg".to_string(),
+            user_agent: None,
+            created_at: chrono::Utc::now(),
+            last_used_at: None,
+        };
+        let private_key = "d08Cz1t1-hBd6svklOamCCmhff5IDHaLR6VDUjGfg18";
+
+        let message = build_message(private_key, "mailto:admin@fundhub.example", &subscription, b"{\"title\":\"hi\"}").unwrap();
+        assert_eq!(message.endpoint.to_string(), subscription.endpoint);
+        assert_eq!(message.ttl, PUSH_TTL_SECS);
+        let payload = message.payload.unwrap();
+        assert_eq!(payload.content_encoding.to_str(), "aes128gcm");
+        assert!(!payload.content.is_empty());
+
+        assert!(build_message("not a key", "mailto:admin@fundhub.example", &subscription, b"{}").is_err());
+    }
+
+    #[test]
+    fn test_is_expired() {
+        assert!(is_expired(reqwest::StatusCode::GONE));
+        assert!(is_expired(reqwest::StatusCode::NOT_FOUND));
+        assert!(!is_expired(reqwest::StatusCode::TOO_MANY_REQUESTS));
+    }
+}
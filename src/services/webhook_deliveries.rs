@@ -0,0 +1,118 @@
+use anyhow::Result;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A delivery to be recorded in `webhook_deliveries`
+pub struct NewDelivery<'a> {
+    pub direction: &'a str,
+    pub provider: &'a str,
+    pub event_type: Option<&'a str>,
+    pub target_url: Option<&'a str>,
+    pub request_headers: Option<serde_json::Value>,
+    pub request_body: &'a str,
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+    pub replay_of: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub direction: String,
+    pub provider: String,
+    pub event_type: Option<String>,
+    pub target_url: Option<String>,
+    pub request_headers: Option<serde_json::Value>,
+    pub request_body: String,
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+    pub replay_of: Option<Uuid>,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Record a delivery. Failures are logged rather than returned so that
+/// logging never breaks webhook processing.
+pub async fn record(pool: &PgPool, delivery: NewDelivery<'_>) -> Option<Uuid> {
+    let status = if delivery.error.is_none() { "succeeded" } else { "failed" };
+
+    let result = sqlx::query_scalar!(
+        r#"
+        INSERT INTO webhook_deliveries
+        (direction, provider, event_type, target_url, request_headers, request_body,
+         response_status, response_body, status, error, replay_of)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING id
+        "#,
+        delivery.direction,
+        delivery.provider,
+        delivery.event_type,
+        delivery.target_url,
+        delivery.request_headers,
+        delivery.request_body,
+        delivery.response_status,
+        delivery.response_body,
+        status,
+        delivery.error,
+        delivery.replay_of
+    )
+    .fetch_one(pool)
+    .await;
+
+    match result {
+        Ok(id) => Some(id),
+        Err(e) => {
+            tracing::error!("Failed to record webhook delivery from {}: {}", delivery.provider, e);
+            None
+        }
+    }
+}
+
+pub async fn get(pool: &PgPool, id: Uuid) -> Result<Option<WebhookDelivery>> {
+    let delivery = sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+        SELECT id, direction, provider, event_type, target_url, request_headers, request_body,
+               response_status, response_body, status, error, replay_of, created_at
+        FROM webhook_deliveries
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(delivery)
+}
+
+pub async fn list(
+    pool: &PgPool,
+    status: Option<&str>,
+    provider: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<WebhookDelivery>> {
+    let deliveries = sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+        SELECT id, direction, provider, event_type, target_url, request_headers, request_body,
+               response_status, response_body, status, error, replay_of, created_at
+        FROM webhook_deliveries
+        WHERE ($1::TEXT IS NULL OR status = $1)
+        AND ($2::TEXT IS NULL OR provider = $2)
+        ORDER BY created_at DESC
+        LIMIT $3 OFFSET $4
+        "#,
+        status,
+        provider,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(deliveries)
+}
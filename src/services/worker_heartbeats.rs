@@ -0,0 +1,245 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+use crate::workers::control::{WorkerControl, WORKER_NAMES};
+
+/// Longest error message kept on a heartbeat
+const MAX_ERROR_LEN: usize = 1000;
+
+/// A worker's latest heartbeat, as stored
+#[derive(Debug, Clone, Default)]
+pub struct Heartbeat {
+    pub name: String,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub runs: i64,
+    pub items_processed: i64,
+}
+
+/// What the admin workers endpoint reports for each worker
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerStatus {
+    pub name: String,
+    pub paused: bool,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub runs: i64,
+    pub items_processed: i64,
+    /// When the oldest item waiting on the worker became due, for workers
+    /// with a queue
+    pub oldest_pending_at: Option<DateTime<Utc>>,
+    /// How long that item has waited
+    pub lag_secs: Option<i64>,
+}
+
+/// Record one run of `worker`: how many items it handled, or why it failed.
+/// A heartbeat that can't be written is logged rather than failing the run.
+pub async fn record(pool: &PgPool, worker: &str, outcome: &Result<usize>) {
+    let (items, error) = match outcome {
+        Ok(items) => (*items as i64, None),
+        Err(e) => (0, Some(truncate(&e.to_string()))),
+    };
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO worker_heartbeats (name, last_run_at, last_success_at, last_error_at, last_error, runs, items_processed)
+        VALUES ($1, NOW(), CASE WHEN $2::text IS NULL THEN NOW() END, CASE WHEN $2::text IS NOT NULL THEN NOW() END, $2, 1, $3)
+        ON CONFLICT (name) DO UPDATE
+        SET last_run_at = NOW(),
+            last_success_at = CASE WHEN $2::text IS NULL THEN NOW() ELSE worker_heartbeats.last_success_at END,
+            last_error_at = CASE WHEN $2::text IS NOT NULL THEN NOW() ELSE worker_heartbeats.last_error_at END,
+            last_error = COALESCE($2, worker_heartbeats.last_error),
+            runs = worker_heartbeats.runs + 1,
+            items_processed = worker_heartbeats.items_processed + $3
+        "#,
+        worker,
+        error,
+        items
+    )
+    .execute(pool)
+    .await;
+    if let Err(e) = result {
+        tracing::warn!("Failed to record heartbeat for worker {}: {}", worker, e);
+    }
+}
+
+fn truncate(error: &str) -> String {
+    match error.char_indices().nth(MAX_ERROR_LEN) {
+        Some((end, _)) => format!("{}…", &error[..end]),
+        None => error.to_string(),
+    }
+}
+
+/// Seconds between `oldest` and `now`, never negative
+pub fn lag_secs(oldest: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<i64> {
+    oldest.map(|oldest| (now - oldest).num_seconds().max(0))
+}
+
+pub async fn heartbeats(pool: &PgPool) -> Result<Vec<Heartbeat>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT name, last_run_at, last_success_at, last_error_at, last_error, runs, items_processed
+        FROM worker_heartbeats
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| Heartbeat {
+            name: row.name,
+            last_run_at: Some(row.last_run_at),
+            last_success_at: row.last_success_at,
+            last_error_at: row.last_error_at,
+            last_error: row.last_error,
+            runs: row.runs,
+            items_processed: row.items_processed,
+        })
+        .collect())
+}
+
+/// When the oldest due item of each queue-backed worker became due
+pub async fn backlog(pool: &PgPool) -> Result<HashMap<&'static str, DateTime<Utc>>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT MIN(created_at) FROM donations
+             WHERE status = 'pending' AND payment_method = 'stellar') as donation_verification,
+            (SELECT MIN(run_at) FROM jobs
+             WHERE status = 'queued' AND run_at <= NOW()) as job_runner,
+            (SELECT MIN(run_at) FROM jobs
+             WHERE status = 'queued' AND run_at <= NOW() AND kind = 'send_email') as email_sender,
+            (SELECT MIN(run_at) FROM jobs
+             WHERE status = 'queued' AND run_at <= NOW() AND kind = 'distribute_campaign_funds') as campaign_distribution,
+            (SELECT MIN(created_at) FROM fiat_settlements
+             WHERE status = 'pending' AND project_id IS NOT NULL) as payment_reconciler,
+            (SELECT MIN(next_attempt_at) FROM webhook_outbox
+             WHERE status = 'pending' AND next_attempt_at <= NOW()) as webhook_dispatcher,
+            (SELECT MIN(next_attempt_at) FROM project_refunds
+             WHERE status = 'pending' AND next_attempt_at <= NOW()) as refund_processor,
+            (SELECT MIN(next_charge_at) FROM donation_subscriptions
+             WHERE status IN ('active', 'past_due') AND next_charge_at <= NOW()) as subscription_scheduler,
+            (SELECT MIN(created_at) FROM files
+             WHERE storage = 'object' AND scan_status = 'pending') as file_scanner,
+            (SELECT MIN(publish_at) FROM announcements
+             WHERE status = 'scheduled' AND publish_at <= NOW()) as announcement_dispatcher,
+            (SELECT MIN(due) FROM (
+                SELECT publish_at as due FROM projects WHERE status = 'scheduled' AND publish_at <= NOW()
+                UNION ALL
+                SELECT funding_deadline FROM projects
+                WHERE status = 'active' AND funding_closed_at IS NULL AND funding_deadline <= NOW()
+             ) due) as project_scheduler,
+            (SELECT MIN(due) FROM (
+                SELECT start_date as due FROM campaigns WHERE status = 'scheduled' AND start_date <= NOW()
+                UNION ALL
+                SELECT end_date FROM campaigns WHERE status = 'active' AND end_date <= NOW()
+             ) due) as campaign_scheduler
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok([
+        ("donation_verification", row.donation_verification),
+        ("job_runner", row.job_runner),
+        ("email_sender", row.email_sender),
+        ("campaign_distribution", row.campaign_distribution),
+        ("payment_reconciler", row.payment_reconciler),
+        ("webhook_dispatcher", row.webhook_dispatcher),
+        ("refund_processor", row.refund_processor),
+        ("subscription_scheduler", row.subscription_scheduler),
+        ("file_scanner", row.file_scanner),
+        ("announcement_dispatcher", row.announcement_dispatcher),
+        ("project_scheduler", row.project_scheduler),
+        ("campaign_scheduler", row.campaign_scheduler),
+    ]
+    .into_iter()
+    .filter_map(|(name, oldest)| oldest.map(|oldest| (name, oldest)))
+    .collect())
+}
+
+/// Every known worker, with its heartbeat if it has run and its lag if it
+/// has a queue
+pub fn assemble(
+    heartbeats: Vec<Heartbeat>,
+    backlog: &HashMap<&'static str, DateTime<Utc>>,
+    control: &WorkerControl,
+    now: DateTime<Utc>,
+) -> Vec<WorkerStatus> {
+    let mut heartbeats: HashMap<String, Heartbeat> = heartbeats.into_iter().map(|h| (h.name.clone(), h)).collect();
+    WORKER_NAMES
+        .iter()
+        .map(|&name| {
+            let heartbeat = heartbeats.remove(name).unwrap_or_default();
+            let oldest_pending_at = backlog.get(name).copied();
+            WorkerStatus {
+                name: name.to_string(),
+                paused: control.is_paused(name),
+                last_run_at: heartbeat.last_run_at,
+                last_success_at: heartbeat.last_success_at,
+                last_error_at: heartbeat.last_error_at,
+                last_error: heartbeat.last_error,
+                runs: heartbeat.runs,
+                items_processed: heartbeat.items_processed,
+                oldest_pending_at,
+                lag_secs: lag_secs(oldest_pending_at, now),
+            }
+        })
+        .collect()
+}
+
+pub async fn status(pool: &PgPool, control: &WorkerControl) -> Result<Vec<WorkerStatus>> {
+    let heartbeats = heartbeats(pool).await?;
+    let backlog = backlog(pool).await?;
+    Ok(assemble(heartbeats, &backlog, control, Utc::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_lag_secs() {
+        let now = Utc.with_ymd_and_hms(2025, 11, 3, 8, 0, 0).unwrap();
+        assert_eq!(lag_secs(None, now), None);
+        assert_eq!(lag_secs(Some(now - Duration::minutes(5)), now), Some(300));
+        // Clock skew between the database and the app doesn't go negative
+        assert_eq!(lag_secs(Some(now + Duration::seconds(3)), now), Some(0));
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short"), "short");
+        let long = "é".repeat(MAX_ERROR_LEN + 10);
+        assert_eq!(truncate(&long).chars().count(), MAX_ERROR_LEN + 1);
+    }
+
+    #[test]
+    fn test_assemble_lists_every_worker() {
+        let now = Utc.with_ymd_and_hms(2025, 11, 3, 8, 0, 0).unwrap();
+        let control = WorkerControl::new();
+        control.pause("file_scanner");
+        let heartbeats = vec![Heartbeat {
+            name: "webhook_dispatcher".to_string(),
+            last_run_at: Some(now),
+            runs: 4,
+            items_processed: 12,
+            ..Default::default()
+        }];
+        let backlog = HashMap::from([("webhook_dispatcher", now - Duration::seconds(90))]);
+
+        let statuses = assemble(heartbeats, &backlog, &control, now);
+        assert_eq!(statuses.len(), WORKER_NAMES.len());
+        let webhooks = statuses.iter().find(|s| s.name == "webhook_dispatcher").unwrap();
+        assert_eq!((webhooks.runs, webhooks.items_processed, webhooks.lag_secs), (4, 12, Some(90)));
+        let scanner = statuses.iter().find(|s| s.name == "file_scanner").unwrap();
+        assert!(scanner.paused && scanner.last_run_at.is_none() && scanner.lag_secs.is_none());
+    }
+}
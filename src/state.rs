@@ -1,14 +1,169 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use dashmap::DashMap;
 use sqlx::PgPool;
 use tokio::sync::broadcast;
 
-use crate::services::{stellar::StellarService, NewStellarService};
+use crate::config::{EscrowMode, StellarNetwork};
+use crate::services::notifications::{Channel, EventPayload, NotificationEvent};
+use crate::services::web_push::WebPush;
+use crate::services::{captcha::CaptchaVerifier, flags::FlagRegistry, payment_service::ProviderRegistry, rates::Rates, sep10::WebAuth, stellar::StellarService, stellar_api::StellarApi, stellar_tx::TxSubmitter, storage::ObjectStorage, NewStellarService};
+use crate::models::ProjectComparison;
+use crate::utils::latency::LatencyTracker;
+use crate::utils::ttl_cache::TtlCache;
+use crate::utils::usage::UsageRecorder;
+use crate::workers::control::WorkerControl;
+
+/// Capacity of each SSE channel
+const NOTIFIER_CAPACITY: usize = 100;
+/// Events each SSE channel keeps for clients that reconnect
+const REPLAY_BUFFER: usize = 100;
+/// How long a channel nobody listens on is kept, with its events
+const REPLAY_WINDOW: Duration = Duration::from_secs(300);
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
     pub stellar: StellarService,
     pub stellar_service: NewStellarService,
-    pub notifier: broadcast::Sender<String>,
+    /// Wallet lookups and platform payments; Horizon in production, `MockStellar` in tests
+    pub stellar_api: Arc<dyn StellarApi>,
+    pub notifier: Notifier,
+    pub worker_dry_run: bool,
+    pub escrow_mode: EscrowMode,
+    pub network: StellarNetwork,
+    pub worker_control: WorkerControl,
+    pub latency: LatencyTracker,
+    /// Cached `/api/projects/compare` results keyed by the sorted project ids
+    pub compare_cache: TtlCache<Vec<ProjectComparison>>,
+    pub usage: UsageRecorder,
+    /// Signs and submits platform payments; `None` when the platform secret is unusable
+    pub payments: Option<TxSubmitter>,
+    /// Issues and checks SEP-10 wallet ownership challenges; `None` without a platform secret
+    pub web_auth: Option<WebAuth>,
+    /// Fiat payment providers, rebuilt when an admin changes their settings
+    pub payment_providers: ProviderRegistry,
+    /// Cached exchange rates for donor-facing conversion quotes
+    pub rates: Rates,
+    /// Checks CAPTCHA tokens on logins after repeated failures; `None` turns the challenge off
+    pub captcha: Option<CaptchaVerifier>,
+    /// Object storage for uploads; `None` turns uploads off
+    pub storage: Option<ObjectStorage>,
+    /// Feature flags, cached and reread when changed
+    pub flags: FlagRegistry,
+    /// Web Push to browsers; `None` when VAPID keys aren't configured
+    pub push: Option<WebPush>,
 }
 
+/// A notification event as streamed, with the id clients resume from
+#[derive(Debug, Clone)]
+pub struct StreamedEvent {
+    pub id: u64,
+    pub payload: EventPayload,
+}
+
+struct ChannelState {
+    sender: broadcast::Sender<StreamedEvent>,
+    /// The last `REPLAY_BUFFER` events, for clients reconnecting
+    recent: VecDeque<StreamedEvent>,
+    /// Last subscribed to or sent on
+    touched: Instant,
+}
 
+impl ChannelState {
+    fn new() -> Self {
+        Self {
+            sender: broadcast::channel(NOTIFIER_CAPACITY).0,
+            recent: VecDeque::with_capacity(REPLAY_BUFFER),
+            touched: Instant::now(),
+        }
+    }
+
+    fn idle(&self) -> bool {
+        self.sender.receiver_count() == 0 && self.touched.elapsed() >= REPLAY_WINDOW
+    }
+}
+
+/// SSE channels of notification events, one per user or topic, created when
+/// someone subscribes. Each keeps its recent events so a client reconnecting
+/// with `Last-Event-ID` gets what it missed, and is dropped once nobody has
+/// listened for `REPLAY_WINDOW`. Clones share the channels.
+#[derive(Clone)]
+pub struct Notifier {
+    channels: Arc<DashMap<Channel, ChannelState>>,
+    next_id: Arc<AtomicU64>,
+    /// Also pushes high-priority events to browsers, when configured
+    push: Option<WebPush>,
+}
+
+impl Notifier {
+    pub fn new(push: Option<WebPush>) -> Self {
+        // Ids start from the clock so they keep increasing across restarts
+        let start = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_micros() as u64).unwrap_or(0);
+        Self { channels: Arc::new(DashMap::new()), next_id: Arc::new(AtomicU64::new(start)), push }
+    }
+
+    /// Send an event to everyone listening on `channel`, returning how many were
+    pub fn send(&self, channel: Channel, event: NotificationEvent) -> usize {
+        self.send_all([channel], event)
+    }
+
+    /// Send an event on each of `channels`, under one id so clients on
+    /// several of them can tell it's the same event
+    pub fn send_all(&self, channels: impl IntoIterator<Item = Channel>, event: NotificationEvent) -> usize {
+        if let Some(push) = &self.push {
+            push.deliver(&event);
+        }
+        let event = StreamedEvent { id: self.next_id.fetch_add(1, Ordering::Relaxed), payload: event.into() };
+        let mut sent = 0;
+        for channel in channels {
+            let Some(mut state) = self.channels.get_mut(&channel) else { continue };
+            if state.recent.len() == REPLAY_BUFFER {
+                state.recent.pop_front();
+            }
+            state.recent.push_back(event.clone());
+            state.touched = Instant::now();
+            sent += state.sender.send(event.clone()).unwrap_or(0);
+        }
+        sent
+    }
+
+    /// Listen on `channel`, along with the buffered events after
+    /// `last_event_id`, oldest first
+    pub fn subscribe(
+        &self,
+        channel: Channel,
+        last_event_id: Option<u64>,
+    ) -> (broadcast::Receiver<StreamedEvent>, Vec<StreamedEvent>) {
+        self.channels.retain(|_, state| !state.idle());
+        let mut state = self.channels.entry(channel).or_insert_with(ChannelState::new);
+        state.touched = Instant::now();
+        let missed = match last_event_id {
+            Some(after) => state.recent.iter().filter(|e| e.id > after).cloned().collect(),
+            None => Vec::new(),
+        };
+        (state.sender.subscribe(), missed)
+    }
+
+    /// Replace every channel's sender, which ends every open stream so
+    /// clients reconnect, and return how many subscriptions were
+    /// disconnected. Buffered events are kept for them to resume from.
+    pub fn rotate(&self) -> usize {
+        let mut disconnected = 0;
+        for mut state in self.channels.iter_mut() {
+            disconnected += state.sender.receiver_count();
+            state.sender = broadcast::channel(NOTIFIER_CAPACITY).0;
+        }
+        disconnected
+    }
+
+    /// Wait for the push notifications already on their way, for shutdown
+    pub async fn flush(&self) {
+        if let Some(push) = &self.push {
+            push.flush().await;
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use axum::{
+    extract::State,
+    http::{HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::PgPool;
+
+use crate::routes::error::AppError;
+use crate::services::api_keys;
+use crate::utils::rate_limit::{Decision, RateLimiter};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// What the `X-Api-Key` middleware needs: the key table and per-key counters
+#[derive(Clone)]
+pub struct ApiKeyAuth {
+    pub pool: PgPool,
+    pub limiter: RateLimiter,
+}
+
+/// Authenticate `X-Api-Key` requests for routes readable with `scope`.
+/// Requests without the header pass through to the usual JWT checks. Keys
+/// are read-only, so they only open GET requests.
+pub async fn api_key_mw(
+    State(auth): State<ApiKeyAuth>,
+    scope: &'static str,
+    mut req: Request<axum::body::Body>,
+    next: Next,
+) -> Response {
+    let Some(raw) = req.headers().get(API_KEY_HEADER) else {
+        return next.run(req).await;
+    };
+    let Some(raw) = raw.to_str().ok().map(str::trim) else {
+        return invalid_key().into_response();
+    };
+
+    let api_key = match api_keys::authenticate(&auth.pool, raw).await {
+        Ok(Some(api_key)) => api_key,
+        Ok(None) => return invalid_key().into_response(),
+        Err(e) => return AppError::Internal(e.context("Failed to check API key")).into_response(),
+    };
+
+    if !api_key.has_scope(scope) || !matches!(*req.method(), Method::GET | Method::HEAD) {
+        return AppError::Coded {
+            status: StatusCode::FORBIDDEN,
+            code: "insufficient_scope",
+            message: format!("This API key can't {} here; it needs the {} scope", req.method(), scope),
+            details: Some(serde_json::json!({"required_scope": scope, "scopes": api_key.scopes})),
+        }
+        .into_response();
+    }
+
+    let limit = api_key.rate_limit_per_minute.max(1) as u32;
+    let remaining = match auth.limiter.check_limit("api_key", &api_key.id.to_string(), limit).await {
+        Decision::Allowed { remaining } => remaining,
+        Decision::Limited { retry_after_secs } => {
+            return AppError::RateLimited {
+                message: "This API key is over its rate limit".to_string(),
+                retry_after_secs,
+            }
+            .into_response();
+        }
+    };
+
+    req.extensions_mut().insert(api_key);
+    let mut response = next.run(req).await;
+    // The key's own limit, which is what integrations need to pace against
+    let headers = response.headers_mut();
+    headers.insert("x-ratelimit-limit", HeaderValue::from(limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(remaining));
+    response
+}
+
+fn invalid_key() -> AppError {
+    AppError::Coded {
+        status: StatusCode::UNAUTHORIZED,
+        code: "invalid_api_key",
+        message: "API key is invalid, expired or revoked".to_string(),
+        details: None,
+    }
+}
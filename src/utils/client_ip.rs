@@ -0,0 +1,112 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
+
+use axum::http::HeaderMap;
+use ipnet::IpNet;
+
+/// Proxies allowed to say who the client is through `X-Forwarded-For`,
+/// read from `TRUSTED_PROXIES` as comma-separated addresses or CIDR ranges
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    pub fn parse(value: &str) -> Self {
+        let nets = value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let net = entry.parse::<IpNet>().ok().or_else(|| entry.parse::<IpAddr>().ok().map(IpNet::from));
+                if net.is_none() {
+                    tracing::warn!("Ignoring invalid TRUSTED_PROXIES entry {}", entry);
+                }
+                net
+            })
+            .collect();
+        Self(nets)
+    }
+
+    pub fn from_env() -> &'static Self {
+        static TRUSTED: OnceLock<TrustedProxies> = OnceLock::new();
+        TRUSTED.get_or_init(|| Self::parse(&std::env::var("TRUSTED_PROXIES").unwrap_or_default()))
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|net| net.contains(&ip))
+    }
+}
+
+/// The address a request came from: the connecting peer, or when the peer
+/// is a trusted proxy, the nearest forwarded hop that isn't one. Forwarded
+/// headers from anyone else are ignored, since clients can set them freely.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>, trusted: &TrustedProxies) -> Option<IpAddr> {
+    let peer = peer?.ip();
+    if !trusted.contains(peer) {
+        return Some(peer);
+    }
+
+    let hops: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect();
+    if hops.is_empty() {
+        return headers
+            .get("x-real-ip")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok())
+            .or(Some(peer));
+    }
+
+    let mut client = peer;
+    for hop in hops.iter().rev() {
+        match hop.parse::<IpAddr>() {
+            Ok(ip) if trusted.contains(ip) => client = ip,
+            Ok(ip) => return Some(ip),
+            // Whatever's left of a garbled hop can't be trusted
+            Err(_) => break,
+        }
+    }
+    Some(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers
+    }
+
+    fn peer(ip: &str) -> Option<SocketAddr> {
+        Some(SocketAddr::new(ip.parse().unwrap(), 443))
+    }
+
+    #[test]
+    fn test_untrusted_peer_ignores_forwarded_for() {
+        let trusted = TrustedProxies::parse("10.0.0.0/8");
+        let ip = client_ip(&forwarded("1.2.3.4"), peer("203.0.113.7"), &trusted);
+        assert_eq!(ip, Some("203.0.113.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_trusted_proxy_chain_yields_nearest_untrusted_hop() {
+        let trusted = TrustedProxies::parse("10.0.0.0/8, 192.168.1.5");
+        // A client-supplied first hop is skipped over
+        let headers = forwarded("1.2.3.4, 198.51.100.9, 192.168.1.5");
+        assert_eq!(client_ip(&headers, peer("10.0.0.2"), &trusted), Some("198.51.100.9".parse().unwrap()));
+
+        assert_eq!(client_ip(&HeaderMap::new(), peer("10.0.0.2"), &trusted), Some("10.0.0.2".parse().unwrap()));
+        assert_eq!(client_ip(&forwarded("garbage"), peer("10.0.0.2"), &trusted), Some("10.0.0.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_no_peer_no_address() {
+        let trusted = TrustedProxies::parse("");
+        assert_eq!(client_ip(&forwarded("1.2.3.4"), None, &trusted), None);
+    }
+}
@@ -0,0 +1,30 @@
+use std::marker::PhantomData;
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts, http::StatusCode};
+
+use crate::routes::error::AppError;
+use crate::services::flags::FeatureFlag;
+use crate::state::AppState;
+use crate::utils::jwt;
+
+/// Refuses the request with a 404 unless flag `F` is on for the caller, e.g.
+/// a `_: RequireFlag<RecurringDonations>` handler argument
+pub struct RequireFlag<F>(PhantomData<fn() -> F>);
+
+#[async_trait]
+impl<F: FeatureFlag> FromRequestParts<AppState> for RequireFlag<F> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let subject = jwt::extract_user_id_from_headers(&parts.headers).ok();
+        if !state.flags.enabled(F::NAME, subject).await {
+            return Err(AppError::Coded {
+                status: StatusCode::NOT_FOUND,
+                code: "feature_disabled",
+                message: "This feature isn't available".to_string(),
+                details: Some(serde_json::json!({"feature": F::NAME})),
+            });
+        }
+        Ok(RequireFlag(PhantomData))
+    }
+}
@@ -1,48 +1,302 @@
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use axum::http::HeaderMap;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+/// Lifetime of an access token; matches the `expires_in` sent to clients
+pub const ACCESS_TOKEN_TTL_SECS: i64 = 3600;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: Uuid,
     pub exp: usize,
+    #[serde(default)]
+    pub iat: usize,
+    #[serde(default)]
+    pub iss: String,
+    /// `users.role` when the token was issued
+    #[serde(default)]
+    pub role: String,
+    #[serde(default)]
+    pub base_role: String,
+    /// Student verification status, for accounts with a student record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification_status: Option<String>,
+    /// Permissions from the user's roles; admins hold all of them implicitly
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<String>,
+    /// Device session the token was issued for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
+}
+
+impl Claims {
+    pub fn is_admin(&self) -> bool {
+        self.role == "admin"
+    }
+
+    /// Reviews verifications and projects; admins included
+    pub fn can_moderate(&self) -> bool {
+        self.is_admin() || self.role == "moderator"
+    }
+
+    /// Moves funds: payouts, refunds, ledger; admins included
+    pub fn can_manage_funds(&self) -> bool {
+        self.is_admin() || self.role == "finance"
+    }
+
+    /// Any admin-area role
+    pub fn is_staff(&self) -> bool {
+        self.can_moderate() || self.can_manage_funds()
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.is_admin() || self.permissions.iter().any(|p| p == permission)
+    }
+
+    pub fn is_verified_student(&self) -> bool {
+        self.verification_status
+            .as_deref()
+            .is_some_and(|status| status.eq_ignore_ascii_case("verified"))
+    }
+}
+
+/// Role data embedded in access tokens so middleware can authorize without
+/// a database lookup. Changes show up when the token is next refreshed.
+#[derive(Debug, Clone)]
+pub struct TokenRoles {
+    pub role: String,
+    pub base_role: String,
+    pub verification_status: Option<String>,
+    pub permissions: Vec<String>,
+}
+
+/// Public half of an RS256 signing key, as published at the JWKS endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct Jwk {
+    pub kty: &'static str,
+    #[serde(rename = "use")]
+    pub key_use: &'static str,
+    pub alg: &'static str,
+    pub kid: String,
+    pub n: String,
+    pub e: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+struct VerifyingKey {
+    jwk: Jwk,
+    key: DecodingKey,
+}
+
+enum Signer {
+    Rsa { kid: String, key: EncodingKey },
+    /// Shared-secret signing for development setups without a keypair
+    Hmac(EncodingKey),
+}
+
+/// Keys tokens are signed and checked with. New tokens are signed with the
+/// current key; retired keys stay accepted (and published) until the tokens
+/// they signed have expired.
+pub struct KeySet {
+    issuer: String,
+    signer: Signer,
+    rsa_keys: Vec<VerifyingKey>,
+    hmac: Option<DecodingKey>,
+}
+
+impl KeySet {
+    /// `JWT_SIGNING_KEY_FILE` is the current RSA private key and
+    /// `JWT_VERIFY_KEY_FILES` a comma-separated list of retired public keys.
+    /// Without a signing key, tokens fall back to HS256 with `JWT_SECRET`.
+    pub fn from_env() -> Result<Self> {
+        let issuer = std::env::var("JWT_ISSUER").unwrap_or_else(|_| "fundhub".to_string());
+        let secret = std::env::var("JWT_SECRET").ok().filter(|s| !s.is_empty());
+
+        let Some(signing_path) = std::env::var("JWT_SIGNING_KEY_FILE").ok().filter(|p| !p.trim().is_empty()) else {
+            let secret = secret.ok_or_else(|| anyhow!("Set JWT_SIGNING_KEY_FILE or JWT_SECRET"))?;
+            return Ok(Self::hmac(&issuer, &secret));
+        };
+
+        let signing_pem = std::fs::read_to_string(signing_path.trim())
+            .with_context(|| format!("Failed to read JWT signing key {}", signing_path))?;
+        let mut retired = Vec::new();
+        for path in std::env::var("JWT_VERIFY_KEY_FILES").unwrap_or_default().split(',') {
+            let path = path.trim();
+            if path.is_empty() {
+                continue;
+            }
+            retired.push(
+                std::fs::read_to_string(path).with_context(|| format!("Failed to read JWT verify key {}", path))?,
+            );
+        }
+
+        let mut keys = Self::rsa(&issuer, &signing_pem, &retired)?;
+        // Lets tokens issued before the switch to RS256 run out their lifetime
+        let accept_hs256 = std::env::var("JWT_ACCEPT_HS256")
+            .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        if accept_hs256 {
+            keys.hmac = secret.map(|s| DecodingKey::from_secret(s.as_bytes()));
+        }
+        Ok(keys)
+    }
+
+    pub fn hmac(issuer: &str, secret: &str) -> Self {
+        Self {
+            issuer: issuer.to_string(),
+            signer: Signer::Hmac(EncodingKey::from_secret(secret.as_bytes())),
+            rsa_keys: Vec::new(),
+            hmac: Some(DecodingKey::from_secret(secret.as_bytes())),
+        }
+    }
+
+    /// Sign with `signing_pem` (PKCS#1 or PKCS#8) and also accept tokens
+    /// from each of the `retired` keys, public or private
+    pub fn rsa(issuer: &str, signing_pem: &str, retired: &[String]) -> Result<Self> {
+        let signing = verifying_key(signing_pem)?;
+        let signer = Signer::Rsa {
+            kid: signing.jwk.kid.clone(),
+            key: EncodingKey::from_rsa_pem(signing_pem.as_bytes())?,
+        };
+
+        let mut rsa_keys = vec![signing];
+        for pem in retired {
+            let key = verifying_key(pem)?;
+            if rsa_keys.iter().all(|k| k.jwk.kid != key.jwk.kid) {
+                rsa_keys.push(key);
+            }
+        }
+
+        Ok(Self { issuer: issuer.to_string(), signer, rsa_keys, hmac: None })
+    }
+
+    pub fn sign(&self, claims: &Claims) -> Result<String> {
+        let token = match &self.signer {
+            Signer::Rsa { kid, key } => {
+                let mut header = Header::new(Algorithm::RS256);
+                header.kid = Some(kid.clone());
+                encode(&header, claims, key)?
+            }
+            Signer::Hmac(key) => encode(&Header::default(), claims, key)?,
+        };
+        Ok(token)
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims> {
+        let header = decode_header(token)?;
+        // The key is picked by algorithm and key id, and validation allows
+        // only that algorithm, so an RSA public key is never used as an
+        // HMAC secret
+        let key = match header.alg {
+            Algorithm::RS256 => {
+                let kid = header.kid.ok_or_else(|| anyhow!("Token has no key id"))?;
+                &self
+                    .rsa_keys
+                    .iter()
+                    .find(|k| k.jwk.kid == kid)
+                    .ok_or_else(|| anyhow!("Unknown signing key {}", kid))?
+                    .key
+            }
+            Algorithm::HS256 => self.hmac.as_ref().ok_or_else(|| anyhow!("HS256 tokens are not accepted"))?,
+            other => return Err(anyhow!("Unsupported token algorithm {:?}", other)),
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        let token_data = decode::<Claims>(token, key, &validation)?;
+        Ok(token_data.claims)
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet { keys: self.rsa_keys.iter().map(|k| k.jwk.clone()).collect() }
+    }
+}
+
+fn verifying_key(pem: &str) -> Result<VerifyingKey> {
+    let public = public_key_from_pem(pem)?;
+    let n = URL_SAFE_NO_PAD.encode(public.n().to_bytes_be());
+    let e = URL_SAFE_NO_PAD.encode(public.e().to_bytes_be());
+    let key = DecodingKey::from_rsa_components(&n, &e)?;
+    let jwk = Jwk { kty: "RSA", key_use: "sig", alg: "RS256", kid: thumbprint(&n, &e), n, e };
+    Ok(VerifyingKey { jwk, key })
+}
+
+fn public_key_from_pem(pem: &str) -> Result<RsaPublicKey> {
+    if let Ok(key) = RsaPrivateKey::from_pkcs8_pem(pem).or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem)) {
+        return Ok(key.to_public_key());
+    }
+    RsaPublicKey::from_public_key_pem(pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+        .map_err(|_| anyhow!("Not an RSA key in PEM form"))
+}
+
+/// RFC 7638 thumbprint of an RSA key, used as its `kid` so a key's id
+/// follows from the key itself
+pub fn thumbprint(n: &str, e: &str) -> String {
+    let canonical = format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n);
+    URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+}
+
+static KEYS: OnceLock<KeySet> = OnceLock::new();
+
+/// Load the keys at startup so a bad key file stops the server early.
+/// Keys are read once; rotating them takes a restart.
+pub fn init_keys() -> Result<()> {
+    let keys = KeySet::from_env()?;
+    let _ = KEYS.set(keys);
+    Ok(())
+}
+
+fn keys() -> &'static KeySet {
+    KEYS.get_or_init(|| KeySet::from_env().expect("JWT keys must be configured"))
 }
 
-pub fn create_token(user_id: &Uuid) -> Result<String> {
-    let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::hours(24))
+pub fn create_token(user_id: &Uuid, roles: &TokenRoles, session_id: Option<Uuid>) -> Result<String> {
+    let now = chrono::Utc::now();
+    let expiration = now
+        .checked_add_signed(chrono::Duration::seconds(ACCESS_TOKEN_TTL_SECS))
         .expect("valid timestamp")
         .timestamp() as usize;
 
+    let keys = keys();
     let claims = Claims {
         sub: *user_id,
         exp: expiration,
+        iat: now.timestamp() as usize,
+        iss: keys.issuer.clone(),
+        role: roles.role.clone(),
+        base_role: roles.base_role.clone(),
+        verification_status: roles.verification_status.clone(),
+        permissions: roles.permissions.clone(),
+        sid: session_id,
     };
 
-    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )?;
-
-    Ok(token)
+    keys.sign(&claims)
 }
 
 pub fn verify_token(token: &str) -> Result<Claims> {
-    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
-    )?;
+    keys().verify(token)
+}
 
-    Ok(token_data.claims)
+/// Public keys for other services validating our tokens
+pub fn jwks() -> JwkSet {
+    keys().jwks()
 }
 
-pub fn extract_user_id_from_headers(headers: &HeaderMap) -> Result<Uuid> {
+pub fn extract_claims_from_headers(headers: &HeaderMap) -> Result<Claims> {
     let auth_header = headers
         .get("authorization")
         .ok_or_else(|| anyhow::anyhow!("Missing authorization header"))?
@@ -53,6 +307,89 @@ pub fn extract_user_id_from_headers(headers: &HeaderMap) -> Result<Uuid> {
         .strip_prefix("Bearer ")
         .ok_or_else(|| anyhow::anyhow!("Invalid authorization format"))?;
 
-    let claims = verify_token(token)?;
-    Ok(claims.sub)
-}
\ No newline at end of file
+    verify_token(token)
+}
+
+pub fn extract_user_id_from_headers(headers: &HeaderMap) -> Result<Uuid> {
+    Ok(extract_claims_from_headers(headers)?.sub)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_KEY: &str = include_str!("../../tests/fixtures/jwt_test_key.pem");
+    const TEST_PUBLIC_KEY: &str = include_str!("../../tests/fixtures/jwt_test_key.pub.pem");
+    const TEST_KID: &str = "vRW8xt3VoWuOBBmVCS5vb30tbZF73v_LKbcAQZh_GWA";
+
+    fn claims(issuer: &str) -> Claims {
+        Claims {
+            sub: Uuid::new_v4(),
+            exp: (chrono::Utc::now().timestamp() + 60) as usize,
+            iat: chrono::Utc::now().timestamp() as usize,
+            iss: issuer.to_string(),
+            role: "student".to_string(),
+            base_role: "student".to_string(),
+            verification_status: Some("verified".to_string()),
+            permissions: vec!["projects.publish".to_string()],
+            sid: None,
+        }
+    }
+
+    #[test]
+    fn test_thumbprint_rfc7638_vector() {
+        let n = "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw";
+        assert_eq!(thumbprint(n, "AQAB"), "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs");
+    }
+
+    #[test]
+    fn test_rs256_round_trip_with_kid() {
+        let keys = KeySet::rsa("fundhub", TEST_KEY, &[]).unwrap();
+        let original = claims("fundhub");
+        let token = keys.sign(&original).unwrap();
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::RS256);
+        assert_eq!(header.kid.as_deref(), Some(TEST_KID));
+
+        let verified = keys.verify(&token).unwrap();
+        assert_eq!(verified.sub, original.sub);
+        assert!(verified.is_verified_student());
+        assert!(!verified.is_admin());
+        assert!(verified.has_permission("projects.publish"));
+        assert!(!verified.has_permission("payments.refund"));
+    }
+
+    #[test]
+    fn test_staff_role_scopes() {
+        let with_role = |role: &str| Claims { role: role.to_string(), ..claims("fundhub") };
+        assert!(with_role("admin").can_moderate() && with_role("admin").can_manage_funds());
+        assert!(with_role("moderator").can_moderate() && !with_role("moderator").can_manage_funds());
+        assert!(with_role("finance").can_manage_funds() && !with_role("finance").can_moderate());
+        assert!(with_role("finance").is_staff());
+        assert!(!with_role("student").is_staff());
+    }
+
+    #[test]
+    fn test_jwks_publishes_public_key() {
+        let keys = KeySet::rsa("fundhub", TEST_KEY, &[TEST_PUBLIC_KEY.to_string()]).unwrap();
+        let jwks = keys.jwks();
+        // The retired copy of the same key is not listed twice
+        assert_eq!(jwks.keys.len(), 1);
+        assert_eq!(jwks.keys[0].kid, TEST_KID);
+        assert_eq!(jwks.keys[0].e, "AQAB");
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys_and_issuers() {
+        let rsa = KeySet::rsa("fundhub", TEST_KEY, &[]).unwrap();
+        let hmac = KeySet::hmac("fundhub", "secret");
+
+        // RS256 tokens need the key; HS256 ones aren't accepted unless enabled
+        assert!(hmac.verify(&rsa.sign(&claims("fundhub")).unwrap()).is_err());
+        assert!(rsa.verify(&hmac.sign(&claims("fundhub")).unwrap()).is_err());
+
+        assert!(hmac.verify(&hmac.sign(&claims("fundhub")).unwrap()).is_ok());
+        assert!(hmac.verify(&hmac.sign(&claims("elsewhere")).unwrap()).is_err());
+    }
+}
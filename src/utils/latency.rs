@@ -0,0 +1,347 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{MatchedPath, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::field::{Field, Visit};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Samples kept per route for percentile calculation
+const MAX_ROUTE_SAMPLES: usize = 1000;
+/// Distinct query fingerprints tracked before new ones are ignored
+const MAX_QUERY_FINGERPRINTS: usize = 500;
+
+#[derive(Default)]
+struct RouteSamples {
+    samples: VecDeque<u64>, // microseconds
+    count: u64,
+    over_budget: u64,
+    max_us: u64,
+}
+
+struct QueryStats {
+    count: u64,
+    total_us: u64,
+    max_us: u64,
+    last_seen: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct Inner {
+    routes: HashMap<String, RouteSamples>,
+    queries: HashMap<String, QueryStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteLatency {
+    pub route: String,
+    pub count: u64,
+    pub over_budget: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlowQuery {
+    pub fingerprint: String,
+    pub count: u64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Collects per-route request latency and slow SQL statements
+#[derive(Clone)]
+pub struct LatencyTracker {
+    inner: Arc<Mutex<Inner>>,
+    budget: Duration,
+}
+
+impl LatencyTracker {
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner::default())),
+            budget,
+        }
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    pub fn record_route(&self, route: &str, elapsed: Duration) {
+        let micros = elapsed.as_micros() as u64;
+        let mut inner = self.inner.lock().unwrap();
+        let stats = inner.routes.entry(route.to_string()).or_default();
+        if stats.samples.len() == MAX_ROUTE_SAMPLES {
+            stats.samples.pop_front();
+        }
+        stats.samples.push_back(micros);
+        stats.count += 1;
+        stats.max_us = stats.max_us.max(micros);
+        if elapsed > self.budget {
+            stats.over_budget += 1;
+        }
+    }
+
+    pub fn record_query(&self, sql: &str, elapsed: Duration) {
+        let micros = elapsed.as_micros() as u64;
+        let key = fingerprint(sql);
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.queries.contains_key(&key) && inner.queries.len() >= MAX_QUERY_FINGERPRINTS {
+            return;
+        }
+        let stats = inner.queries.entry(key).or_insert(QueryStats {
+            count: 0,
+            total_us: 0,
+            max_us: 0,
+            last_seen: Utc::now(),
+        });
+        stats.count += 1;
+        stats.total_us += micros;
+        stats.max_us = stats.max_us.max(micros);
+        stats.last_seen = Utc::now();
+    }
+
+    /// Routes ordered by p95 latency, slowest first
+    pub fn slowest_routes(&self, limit: usize) -> Vec<RouteLatency> {
+        let inner = self.inner.lock().unwrap();
+        let mut routes: Vec<RouteLatency> = inner
+            .routes
+            .iter()
+            .map(|(route, stats)| {
+                let mut sorted: Vec<u64> = stats.samples.iter().copied().collect();
+                sorted.sort_unstable();
+                RouteLatency {
+                    route: route.clone(),
+                    count: stats.count,
+                    over_budget: stats.over_budget,
+                    p50_ms: percentile(&sorted, 0.50),
+                    p95_ms: percentile(&sorted, 0.95),
+                    p99_ms: percentile(&sorted, 0.99),
+                    max_ms: stats.max_us as f64 / 1000.0,
+                }
+            })
+            .collect();
+        routes.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms));
+        routes.truncate(limit);
+        routes
+    }
+
+    /// Slow query fingerprints ordered by worst observed duration
+    pub fn slowest_queries(&self, limit: usize) -> Vec<SlowQuery> {
+        let inner = self.inner.lock().unwrap();
+        let mut queries: Vec<SlowQuery> = inner
+            .queries
+            .iter()
+            .map(|(fingerprint, stats)| SlowQuery {
+                fingerprint: fingerprint.clone(),
+                count: stats.count,
+                avg_ms: stats.total_us as f64 / stats.count.max(1) as f64 / 1000.0,
+                max_ms: stats.max_us as f64 / 1000.0,
+                last_seen: stats.last_seen,
+            })
+            .collect();
+        queries.sort_by(|a, b| b.max_ms.total_cmp(&a.max_ms));
+        queries.truncate(limit);
+        queries
+    }
+}
+
+fn percentile(sorted: &[u64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let index = ((sorted.len() as f64 - 1.0) * q).round() as usize;
+    sorted[index] as f64 / 1000.0
+}
+
+/// Normalize a SQL statement so queries differing only in literals group together
+pub fn fingerprint(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut last_was_space = true;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // Skip string literal, including escaped '' quotes
+                while let Some(n) = chars.next() {
+                    if n == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                out.push('?');
+                last_was_space = false;
+            }
+            c if c.is_ascii_digit() && !out.ends_with(|p: char| p.is_alphanumeric() || p == '_' || p == '$') => {
+                while chars.peek().is_some_and(|n| n.is_ascii_digit() || *n == '.') {
+                    chars.next();
+                }
+                out.push('?');
+                last_was_space = false;
+            }
+            c if c.is_whitespace() => {
+                if !last_was_space {
+                    out.push(' ');
+                    last_was_space = true;
+                }
+            }
+            c => {
+                out.extend(c.to_lowercase());
+                last_was_space = false;
+            }
+        }
+    }
+
+    out.trim().to_string()
+}
+
+/// Record request latency per matched route and warn when a request exceeds the budget
+pub async fn latency_mw(
+    State(tracker): State<LatencyTracker>,
+    matched: Option<MatchedPath>,
+    req: Request<axum::body::Body>,
+    next: Next,
+) -> Response {
+    let route = matched
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = req.method().clone();
+    let started = Instant::now();
+
+    let response = next.run(req).await;
+
+    let elapsed = started.elapsed();
+    let key = format!("{} {}", method, route);
+    if elapsed > tracker.budget() {
+        tracing::warn!("{} took {:?}, over the {:?} latency budget", key, elapsed, tracker.budget());
+    }
+    tracker.record_route(&key, elapsed);
+
+    response
+}
+
+/// Tracing layer that captures sqlx slow-statement events into the tracker
+pub struct SlowQueryLayer {
+    tracker: LatencyTracker,
+}
+
+impl SlowQueryLayer {
+    pub fn new(tracker: LatencyTracker) -> Self {
+        Self { tracker }
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for SlowQueryLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        if !event.metadata().target().starts_with("sqlx::query") {
+            return;
+        }
+
+        let mut visitor = QueryEventVisitor::default();
+        event.record(&mut visitor);
+
+        if let (Some(sql), Some(elapsed)) = (visitor.statement.or(visitor.summary), visitor.elapsed) {
+            self.tracker.record_query(&sql, elapsed);
+        }
+    }
+}
+
+#[derive(Default)]
+struct QueryEventVisitor {
+    statement: Option<String>,
+    summary: Option<String>,
+    elapsed: Option<Duration>,
+}
+
+impl Visit for QueryEventVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "db.statement" if !value.trim().is_empty() => self.statement = Some(value.to_string()),
+            "summary" => self.summary = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" {
+            self.elapsed = Some(Duration::from_secs_f64(value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "elapsed" if self.elapsed.is_none() => self.elapsed = parse_duration(&format!("{:?}", value)),
+            "db.statement" | "summary" => self.record_str(field, format!("{:?}", value).trim_matches('"')),
+            _ => {}
+        }
+    }
+}
+
+/// Parse a `Duration` Debug string such as `1.5s`, `12.3ms`, `7µs`, or `100ns`
+fn parse_duration(text: &str) -> Option<Duration> {
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+    let (number, unit) = text.split_at(split);
+    let value: f64 = number.parse().ok()?;
+    let secs = match unit {
+        "s" => value,
+        "ms" => value / 1_000.0,
+        "µs" | "us" => value / 1_000_000.0,
+        "ns" => value / 1_000_000_000.0,
+        _ => return None,
+    };
+    Some(Duration::from_secs_f64(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_normalizes_literals() {
+        assert_eq!(
+            fingerprint("SELECT *  FROM donations\n WHERE amount > 10.5 AND memo = 'it''s'"),
+            "select * from donations where amount > ? and memo = ?"
+        );
+        assert_eq!(
+            fingerprint("SELECT id FROM projects WHERE id = $1 LIMIT 20"),
+            "select id from projects where id = $1 limit ?"
+        );
+    }
+
+    #[test]
+    fn test_route_percentiles() {
+        let tracker = LatencyTracker::new(Duration::from_millis(50));
+        for ms in 1..=100 {
+            tracker.record_route("GET /api/projects", Duration::from_millis(ms));
+        }
+
+        let routes = tracker.slowest_routes(10);
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].count, 100);
+        assert_eq!(routes[0].over_budget, 50);
+        assert_eq!(routes[0].p50_ms, 51.0);
+        assert_eq!(routes[0].max_ms, 100.0);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("bogus"), None);
+    }
+}
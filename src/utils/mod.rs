@@ -1,2 +1,15 @@
+pub mod api_key;
+pub mod client_ip;
+pub mod feature_gate;
 pub mod jwt;
-pub mod roles;
\ No newline at end of file
+pub mod latency;
+pub mod roles;
+pub mod pdf;
+pub mod rate_limit;
+pub mod request_id;
+pub mod money;
+pub mod pagination;
+pub mod sse;
+pub mod totp;
+pub mod ttl_cache;
+pub mod usage;
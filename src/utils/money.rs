@@ -0,0 +1,362 @@
+//! Fixed-point money types.
+//!
+//! XLM amounts are held as [`Stroops`] (1 XLM = 10^7 stroops) and fiat amounts as
+//! [`Cents`] (minor units, two decimal places). Converting from decimal text, `f64`,
+//! or `BigDecimal` rounds half away from zero at the type's precision; converting
+//! back to `f64` is only meant for display and metrics.
+//!
+//! Both types serialize to a decimal string (`"12.5000000"`, `"12.50"`) so JSON
+//! clients never see a float, and deserialize from either a string or a number.
+//! They map to Postgres `NUMERIC`, so query results can be read with a type
+//! override such as `amount as "amount: Stroops"`; bind parameters with
+//! `to_decimal()` since `query!` checks argument types against `BigDecimal`.
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef};
+use sqlx::types::BigDecimal;
+use sqlx::{Decode, Encode, Postgres, Type};
+
+pub const STROOPS_PER_XLM: i64 = 10_000_000;
+pub const XLM_DECIMALS: u32 = 7;
+pub const FIAT_DECIMALS: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MoneyError {
+    #[error("invalid amount: {0}")]
+    Invalid(String),
+    #[error("amount out of range")]
+    Overflow,
+}
+
+/// Parse decimal text into an integer number of minor units at `decimals` places
+fn parse_fixed(text: &str, decimals: u32) -> Result<i64, MoneyError> {
+    let invalid = || MoneyError::Invalid(text.to_string());
+    let trimmed = text.trim();
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let (whole, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    if (whole.is_empty() && frac.is_empty())
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !frac.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let scale = 10i128.pow(decimals);
+    let whole: i128 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| MoneyError::Overflow)? };
+    let mut value = whole.checked_mul(scale).ok_or(MoneyError::Overflow)?;
+
+    let frac_bytes = frac.as_bytes();
+    let mut place = scale / 10;
+    for digit in frac_bytes.iter().take(decimals as usize) {
+        value += (digit - b'0') as i128 * place;
+        place /= 10;
+    }
+    // Round half away from zero on the first dropped digit
+    if frac_bytes.get(decimals as usize).is_some_and(|d| *d >= b'5') {
+        value += 1;
+    }
+
+    let value = if negative { -value } else { value };
+    i64::try_from(value).map_err(|_| MoneyError::Overflow)
+}
+
+fn format_fixed(value: i64, decimals: u32, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let scale = 10u64.pow(decimals);
+    let sign = if value < 0 { "-" } else { "" };
+    let abs = value.unsigned_abs();
+    write!(f, "{}{}.{:0width$}", sign, abs / scale, abs % scale, width = decimals as usize)
+}
+
+fn from_f64_fixed(value: f64, decimals: u32) -> Result<i64, MoneyError> {
+    if !value.is_finite() {
+        return Err(MoneyError::Invalid(value.to_string()));
+    }
+    let scaled = (value * 10f64.powi(decimals as i32)).round();
+    if scaled.abs() >= i64::MAX as f64 {
+        return Err(MoneyError::Overflow);
+    }
+    Ok(scaled as i64)
+}
+
+macro_rules! fixed_point_money {
+    ($name:ident, $decimals:expr, $unit:literal) => {
+        impl $name {
+            pub const ZERO: $name = $name(0);
+
+            /// Amount in whole units, rounded half away from zero at the type's precision
+            pub fn from_f64(value: f64) -> Result<Self, MoneyError> {
+                from_f64_fixed(value, $decimals).map($name)
+            }
+
+            pub fn from_decimal(value: &BigDecimal) -> Result<Self, MoneyError> {
+                parse_fixed(&value.to_string(), $decimals).map($name)
+            }
+
+            pub fn to_decimal(self) -> BigDecimal {
+                BigDecimal::from_str(&self.to_string()).expect("formatted amount is a valid decimal")
+            }
+
+            /// Lossy conversion for display, metrics, and analytics
+            pub fn to_f64(self) -> f64 {
+                self.0 as f64 / 10f64.powi($decimals as i32)
+            }
+
+            pub fn checked_add(self, other: Self) -> Option<Self> {
+                self.0.checked_add(other.0).map($name)
+            }
+
+            pub fn checked_sub(self, other: Self) -> Option<Self> {
+                self.0.checked_sub(other.0).map($name)
+            }
+
+            pub fn is_positive(self) -> bool {
+                self.0 > 0
+            }
+
+            /// Portion of this amount for a share in basis points, rounded down
+            pub fn mul_bps(self, bps: u32) -> Self {
+                $name((self.0 as i128 * bps as i128 / 10_000) as i64)
+            }
+
+            /// Split into `parts` amounts that differ by at most one minor unit and sum exactly
+            pub fn split_even(self, parts: usize) -> Vec<Self> {
+                if parts == 0 {
+                    return Vec::new();
+                }
+                let base = self.0 / parts as i64;
+                let remainder = (self.0 % parts as i64) as usize;
+                (0..parts)
+                    .map(|i| $name(base + if i < remainder { 1 } else { 0 }))
+                    .collect()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                format_fixed(self.0, $decimals, f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = MoneyError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                parse_fixed(s, $decimals).map($name)
+            }
+        }
+
+        impl Add for $name {
+            type Output = $name;
+
+            fn add(self, other: Self) -> Self {
+                self.checked_add(other).expect(concat!($unit, " overflow"))
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: Self) {
+                *self = *self + other;
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, other: Self) -> Self {
+                self.checked_sub(other).expect(concat!($unit, " overflow"))
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, other: Self) {
+                *self = *self - other;
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> Self {
+                $name(-self.0)
+            }
+        }
+
+        impl Sum for $name {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                iter.fold($name::ZERO, |acc, x| acc + x)
+            }
+        }
+
+        impl Type<Postgres> for $name {
+            fn type_info() -> PgTypeInfo {
+                <BigDecimal as Type<Postgres>>::type_info()
+            }
+
+            fn compatible(ty: &PgTypeInfo) -> bool {
+                <BigDecimal as Type<Postgres>>::compatible(ty)
+            }
+        }
+
+        impl<'r> Decode<'r, Postgres> for $name {
+            fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+                let decimal = <BigDecimal as Decode<Postgres>>::decode(value)?;
+                Ok($name::from_decimal(&decimal)?)
+            }
+        }
+
+        impl Encode<'_, Postgres> for $name {
+            fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+                <BigDecimal as Encode<Postgres>>::encode(self.to_decimal(), buf)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct AmountVisitor;
+
+                impl<'de> de::Visitor<'de> for AmountVisitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        write!(f, "a decimal {} amount as a string or number", $unit)
+                    }
+
+                    fn visit_str<E: de::Error>(self, v: &str) -> Result<$name, E> {
+                        v.parse().map_err(E::custom)
+                    }
+
+                    fn visit_i64<E: de::Error>(self, v: i64) -> Result<$name, E> {
+                        v.to_string().parse().map_err(E::custom)
+                    }
+
+                    fn visit_u64<E: de::Error>(self, v: u64) -> Result<$name, E> {
+                        v.to_string().parse().map_err(E::custom)
+                    }
+
+                    fn visit_f64<E: de::Error>(self, v: f64) -> Result<$name, E> {
+                        // Shortest round-trip text keeps 0.1 as 0.1 rather than 0.1000000000000000055
+                        v.to_string().parse().map_err(E::custom)
+                    }
+                }
+
+                deserializer.deserialize_any(AmountVisitor)
+            }
+        }
+    };
+}
+
+/// XLM amount in stroops
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Stroops(i64);
+
+impl Stroops {
+    pub const fn from_stroops(stroops: i64) -> Self {
+        Stroops(stroops)
+    }
+
+    pub const fn as_stroops(self) -> i64 {
+        self.0
+    }
+
+    pub fn from_xlm(xlm: i64) -> Result<Self, MoneyError> {
+        xlm.checked_mul(STROOPS_PER_XLM).map(Stroops).ok_or(MoneyError::Overflow)
+    }
+}
+
+fixed_point_money!(Stroops, XLM_DECIMALS, "XLM");
+
+/// Fiat amount in minor units (cents)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cents(i64);
+
+impl Cents {
+    pub const fn from_cents(cents: i64) -> Self {
+        Cents(cents)
+    }
+
+    pub const fn as_cents(self) -> i64 {
+        self.0
+    }
+
+    /// Minor units as the `u32` that card and mobile money APIs expect
+    pub fn as_u32(self) -> Result<u32, MoneyError> {
+        u32::try_from(self.0).map_err(|_| MoneyError::Overflow)
+    }
+
+    /// Convert to XLM at `rate` XLM per unit of this currency
+    pub fn to_stroops(self, rate: f64) -> Result<Stroops, MoneyError> {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(MoneyError::Invalid(rate.to_string()));
+        }
+        Stroops::from_f64(self.to_f64() * rate)
+    }
+}
+
+fixed_point_money!(Cents, FIAT_DECIMALS, "fiat");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_round() {
+        assert_eq!("12.5".parse::<Stroops>().unwrap().as_stroops(), 125_000_000);
+        assert_eq!("0.00000005".parse::<Stroops>().unwrap().as_stroops(), 1);
+        assert_eq!("-0.00000005".parse::<Stroops>().unwrap().as_stroops(), -1);
+        assert_eq!("19.995".parse::<Cents>().unwrap().as_cents(), 2000);
+        assert!("1e5".parse::<Stroops>().is_err());
+        assert!(".".parse::<Cents>().is_err());
+        assert!("99999999999999".parse::<Stroops>().is_err());
+    }
+
+    #[test]
+    fn test_display_and_decimal_round_trip() {
+        let amount = Stroops::from_stroops(-5);
+        assert_eq!(amount.to_string(), "-0.0000005");
+        assert_eq!(Stroops::from_decimal(&amount.to_decimal()).unwrap(), amount);
+        assert_eq!(Cents::from_cents(1050).to_string(), "10.50");
+    }
+
+    #[test]
+    fn test_f64_conversion() {
+        assert_eq!(Cents::from_f64(0.1 + 0.2).unwrap().as_cents(), 30);
+        assert_eq!(Stroops::from_f64(1.1).unwrap().as_stroops(), 11_000_000);
+        assert!(Stroops::from_f64(f64::NAN).is_err());
+        assert_eq!(Cents::from_cents(10_000).to_stroops(0.5).unwrap(), Stroops::from_xlm(50).unwrap());
+    }
+
+    #[test]
+    fn test_split_even_sums_exactly() {
+        let pool = Stroops::from_stroops(100);
+        let parts = pool.split_even(3);
+        assert_eq!(parts, vec![Stroops::from_stroops(34), Stroops::from_stroops(33), Stroops::from_stroops(33)]);
+        assert_eq!(parts.into_iter().sum::<Stroops>(), pool);
+        assert_eq!(pool.mul_bps(2_500), Stroops::from_stroops(25));
+    }
+
+    #[test]
+    fn test_serde() {
+        let amount: Stroops = serde_json::from_str("1.5").unwrap();
+        assert_eq!(amount.as_stroops(), 15_000_000);
+        let amount: Stroops = serde_json::from_str("\"0.0000001\"").unwrap();
+        assert_eq!(serde_json::to_string(&amount).unwrap(), "\"0.0000001\"");
+        let amount: Cents = serde_json::from_str("20").unwrap();
+        assert_eq!(amount.as_cents(), 2000);
+    }
+}
@@ -0,0 +1,147 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::routes::error::{AppError, AppResult};
+
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+/// Position after the last row of a page, for lists ordered by
+/// `created_at DESC, id DESC`. The id breaks ties between rows created in
+/// the same microsecond.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    /// Opaque to clients so the ordering can change without breaking them
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}|{}", self.created_at.timestamp_micros(), self.id))
+    }
+
+    pub fn decode(value: &str) -> Option<Self> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(value).ok()?).ok()?;
+        let (micros, id) = raw.split_once('|')?;
+        Some(Self {
+            created_at: DateTime::from_timestamp_micros(micros.parse().ok()?)?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+/// `?cursor=&limit=` for lists with no other filters
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// A page asked for with `?cursor=&limit=`
+#[derive(Debug, Clone, Copy)]
+pub struct PageRequest {
+    pub after: Option<Cursor>,
+    pub limit: i64,
+}
+
+impl PageRequest {
+    pub fn new(cursor: Option<&str>, limit: Option<i64>) -> AppResult<Self> {
+        let after = match cursor.filter(|c| !c.is_empty()) {
+            Some(cursor) => {
+                Some(Cursor::decode(cursor).ok_or_else(|| AppError::invalid("cursor", "Cursor is not valid; start again without it"))?)
+            }
+            None => None,
+        };
+        Ok(Self { after, limit: limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) })
+    }
+
+    /// Rows to fetch: one more than the page, to tell whether another follows
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+
+    /// Bind as `$n::timestamptz`; queries filter on
+    /// `($n::timestamptz IS NULL OR (created_at, id) < ($n, $m::uuid))`
+    pub fn after_created_at(&self) -> Option<DateTime<Utc>> {
+        self.after.map(|c| c.created_at)
+    }
+
+    pub fn after_id(&self) -> Option<Uuid> {
+        self.after.map(|c| c.id)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass back as `?cursor=` for the next page; `None` on the last one
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    /// Trim rows fetched with `fetch_limit` to a page. `key` gives a row's
+    /// `created_at` and `id`, the columns the query orders by.
+    pub fn new(mut rows: Vec<T>, request: &PageRequest, key: impl Fn(&T) -> (Option<DateTime<Utc>>, Uuid)) -> Self {
+        let has_more = rows.len() as i64 > request.limit;
+        rows.truncate(request.limit as usize);
+        let next_cursor = if has_more {
+            rows.last().map(|row| {
+                let (created_at, id) = key(row);
+                Cursor { created_at: created_at.unwrap_or_default(), id }.encode()
+            })
+        } else {
+            None
+        };
+        Self { items: rows, next_cursor, has_more }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page { items: self.items.into_iter().map(f).collect(), next_cursor: self.next_cursor, has_more: self.has_more }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor {
+            created_at: DateTime::from_timestamp_micros(1_729_600_000_123_456).unwrap(),
+            id: Uuid::new_v4(),
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(Cursor::decode("not a cursor"), None);
+        assert_eq!(Cursor::decode(&URL_SAFE_NO_PAD.encode("12|not-a-uuid")), None);
+    }
+
+    #[test]
+    fn test_page_request_limits() {
+        assert_eq!(PageRequest::new(None, None).unwrap().limit, DEFAULT_PAGE_SIZE);
+        assert_eq!(PageRequest::new(None, Some(1000)).unwrap().limit, MAX_PAGE_SIZE);
+        assert_eq!(PageRequest::new(None, Some(0)).unwrap().limit, 1);
+        assert!(PageRequest::new(Some(""), None).unwrap().after.is_none());
+        assert!(PageRequest::new(Some("garbage!"), None).is_err());
+    }
+
+    #[test]
+    fn test_page_trims_extra_row() {
+        let request = PageRequest::new(None, Some(2)).unwrap();
+        let rows: Vec<(Option<DateTime<Utc>>, Uuid)> = (0..3).map(|_| (Some(Utc::now()), Uuid::new_v4())).collect();
+        let last_kept = rows[1];
+
+        let page = Page::new(rows.clone(), &request, |row| *row);
+        assert!(page.has_more);
+        assert_eq!(page.items.len(), 2);
+        let next = Cursor::decode(page.next_cursor.as_deref().unwrap()).unwrap();
+        assert_eq!(next.id, last_kept.1);
+
+        let last = Page::new(rows[..2].to_vec(), &request, |row| *row);
+        assert!(!last.has_more);
+        assert_eq!(last.next_cursor, None);
+    }
+}
@@ -0,0 +1,87 @@
+//! Minimal plain-text PDF writer for generated reports (receipts, statements).
+
+const LINES_PER_PAGE: usize = 48;
+
+/// Render lines of text into a simple A4 PDF using the built-in Helvetica font.
+/// Long documents are split across pages.
+pub fn text_document(title: &str, lines: &[String]) -> Vec<u8> {
+    let mut pages: Vec<&[String]> = lines.chunks(LINES_PER_PAGE).collect();
+    if pages.is_empty() {
+        pages.push(&[]);
+    }
+
+    // Object layout: 1 catalog, 2 page tree, 3 font, then (page, content) pairs
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 4 + i * 2).collect();
+    let mut objects: Vec<String> = Vec::new();
+    objects.push("<< /Type /Catalog /Pages 2 0 R >>".to_string());
+    objects.push(format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<_>>().join(" "),
+        pages.len()
+    ));
+    objects.push("<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string());
+
+    for (index, page_lines) in pages.iter().enumerate() {
+        let mut stream = String::from("BT\n/F1 10 Tf\n14 TL\n50 800 Td\n");
+        if index == 0 {
+            stream.push_str(&format!("/F1 14 Tf\n({}) Tj\nT*\nT*\n/F1 10 Tf\n", escape(title)));
+        }
+        for line in page_lines.iter() {
+            stream.push_str(&format!("({}) Tj\nT*\n", escape(line)));
+        }
+        stream.push_str("ET");
+
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            page_ids[index] + 1
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", stream.len(), stream));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, body) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, body));
+    }
+
+    let xref_offset = out.len();
+    out.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        out.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    out.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref_offset
+    ));
+
+    out.into_bytes()
+}
+
+/// Escape PDF string delimiters and drop characters Helvetica can't encode
+fn escape(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_ascii() && !c.is_ascii_control())
+        .flat_map(|c| match c {
+            '(' | ')' | '\\' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_document_structure() {
+        let lines: Vec<String> = (0..100).map(|i| format!("Line (#{})", i)).collect();
+        let pdf = String::from_utf8(text_document("Report", &lines)).unwrap();
+
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 3"));
+        assert!(pdf.contains("(Line \\(#0\\)) Tj"));
+    }
+}
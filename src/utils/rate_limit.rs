@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{HeaderValue, Method, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::config::env_u32;
+use crate::routes::error::AppError;
+use crate::utils::client_ip::{client_ip, TrustedProxies};
+
+/// Every bucket counts requests over a fixed one-minute window
+const WINDOW: Duration = Duration::from_secs(60);
+/// Memory store size at which expired windows are swept out
+const MAX_MEMORY_KEYS: usize = 50_000;
+
+/// Which limit a request counts against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    /// `/api/auth`, always keyed by IP since most callers aren't signed in yet
+    Auth,
+    Admin,
+    Analytics,
+    General,
+}
+
+impl Bucket {
+    /// `None` for routes that aren't limited: health checks, metrics and the SSE stream
+    pub fn for_path(path: &str) -> Option<Self> {
+        if !path.starts_with("/api/") || path == "/api/notifications/sse" {
+            return None;
+        }
+        Some(if path.starts_with("/api/auth") {
+            Bucket::Auth
+        } else if path.starts_with("/api/admin") {
+            Bucket::Admin
+        } else if path.starts_with("/api/analytics") {
+            Bucket::Analytics
+        } else {
+            Bucket::General
+        })
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Bucket::Auth => "auth",
+            Bucket::Admin => "admin",
+            Bucket::Analytics => "analytics",
+            Bucket::General => "general",
+        }
+    }
+}
+
+/// Requests allowed per minute in each bucket, from `RATE_LIMIT_*_PER_MINUTE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    pub general: u32,
+    pub auth: u32,
+    pub analytics: u32,
+    pub admin: u32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self { general: 100, auth: 10, analytics: 50, admin: 20 }
+    }
+}
+
+impl RateLimits {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            general: env_u32("RATE_LIMIT_GENERAL_PER_MINUTE", defaults.general),
+            auth: env_u32("RATE_LIMIT_AUTH_PER_MINUTE", defaults.auth),
+            analytics: env_u32("RATE_LIMIT_ANALYTICS_PER_MINUTE", defaults.analytics),
+            admin: env_u32("RATE_LIMIT_ADMIN_PER_MINUTE", defaults.admin),
+        }
+    }
+
+    pub fn limit(&self, bucket: Bucket) -> u32 {
+        match bucket {
+            Bucket::Auth => self.auth,
+            Bucket::Admin => self.admin,
+            Bucket::Analytics => self.analytics,
+            Bucket::General => self.general,
+        }
+    }
+}
+
+/// Outcome of counting one request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Allowed { remaining: u32 },
+    Limited { retry_after_secs: i64 },
+}
+
+fn decide(count: u32, limit: u32, resets_in_secs: i64) -> Decision {
+    if count > limit {
+        Decision::Limited { retry_after_secs: resets_in_secs.max(1) }
+    } else {
+        Decision::Allowed { remaining: limit - count }
+    }
+}
+
+/// Fixed-window counters held in this process
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    windows: Arc<Mutex<HashMap<String, (Instant, u32)>>>,
+}
+
+impl MemoryStore {
+    /// Count a hit and return the window's count and seconds until it resets
+    fn hit(&self, key: &str, now: Instant) -> (u32, i64) {
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() >= MAX_MEMORY_KEYS {
+            windows.retain(|_, (started, _)| now.duration_since(*started) < WINDOW);
+        }
+        let entry = windows.entry(key.to_string()).or_insert((now, 0));
+        if now.duration_since(entry.0) >= WINDOW {
+            *entry = (now, 0);
+        }
+        entry.1 += 1;
+        let resets_in = WINDOW.saturating_sub(now.duration_since(entry.0));
+        (entry.1, resets_in.as_secs_f64().ceil() as i64)
+    }
+}
+
+/// Counters shared by every instance through Redis
+#[derive(Clone)]
+pub struct RedisStore {
+    conn: redis::aio::MultiplexedConnection,
+}
+
+impl RedisStore {
+    pub async fn connect(url: &str) -> redis::RedisResult<Self> {
+        let conn = redis::Client::open(url)?.get_multiplexed_tokio_connection().await?;
+        Ok(Self { conn })
+    }
+
+    async fn hit(&self, key: &str) -> redis::RedisResult<(u32, i64)> {
+        let mut conn = self.conn.clone();
+        // Start the window only if there isn't one, so hits never extend it
+        let (count, ttl): (u32, i64) = redis::pipe()
+            .atomic()
+            .cmd("SET").arg(key).arg(0).arg("EX").arg(WINDOW.as_secs()).arg("NX").ignore()
+            .incr(key, 1)
+            .ttl(key)
+            .query_async(&mut conn)
+            .await?;
+        Ok((count, ttl))
+    }
+}
+
+#[derive(Clone)]
+pub enum RateLimitStore {
+    Memory(MemoryStore),
+    Redis(RedisStore),
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: RateLimitStore,
+    limits: RateLimits,
+    enabled: bool,
+}
+
+impl RateLimiter {
+    pub fn new(store: RateLimitStore, limits: RateLimits) -> Self {
+        Self { store, limits, enabled: true }
+    }
+
+    /// Reads `RATE_LIMIT_ENABLED` and `RATE_LIMIT_STORE`. With `redis` the
+    /// counters live at `redis_url` and are shared across instances; if Redis
+    /// can't be reached the limiter falls back to memory.
+    pub async fn from_env(redis_url: &str) -> Self {
+        let enabled = std::env::var("RATE_LIMIT_ENABLED")
+            .map(|v| !matches!(v.to_lowercase().as_str(), "0" | "false" | "no"))
+            .unwrap_or(true);
+        let store = match std::env::var("RATE_LIMIT_STORE").unwrap_or_default().to_lowercase().as_str() {
+            "redis" => match RedisStore::connect(redis_url).await {
+                Ok(store) => RateLimitStore::Redis(store),
+                Err(e) => {
+                    tracing::warn!("Rate limiting falls back to memory; Redis is unavailable: {}", e);
+                    RateLimitStore::Memory(MemoryStore::default())
+                }
+            },
+            _ => RateLimitStore::Memory(MemoryStore::default()),
+        };
+        Self { enabled, ..Self::new(store, RateLimits::from_env()) }
+    }
+
+    pub async fn check(&self, bucket: Bucket, client: &str) -> Decision {
+        self.check_limit(bucket.as_str(), client, self.limits.limit(bucket)).await
+    }
+
+    /// Count a request against a named limit of its own, e.g. an API key's
+    pub async fn check_limit(&self, name: &str, client: &str, limit: u32) -> Decision {
+        let key = format!("ratelimit:{}:{}", name, client);
+        let (count, resets_in) = match &self.store {
+            RateLimitStore::Memory(store) => store.hit(&key, Instant::now()),
+            RateLimitStore::Redis(store) => match store.hit(&key).await {
+                Ok(hit) => hit,
+                Err(e) => {
+                    // Fail open rather than take the API down with Redis
+                    tracing::warn!("Rate limit check failed for {}: {}", key, e);
+                    return Decision::Allowed { remaining: limit };
+                }
+            },
+        };
+        decide(count, limit, resets_in)
+    }
+}
+
+/// Signed-in callers are limited per user, everyone else per IP. The IP is
+/// the socket's unless it's a trusted proxy's, so a forged
+/// `X-Forwarded-For` can't pick a fresh bucket.
+fn client_key(bucket: Bucket, headers: &axum::http::HeaderMap, peer: Option<SocketAddr>) -> String {
+    if bucket != Bucket::Auth {
+        if let Ok(user_id) = crate::utils::jwt::extract_user_id_from_headers(headers) {
+            return format!("user:{}", user_id);
+        }
+    }
+    let ip = client_ip(headers, peer, TrustedProxies::from_env())
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    format!("ip:{}", ip)
+}
+
+/// Answer 429 with `Retry-After` once a client uses up its bucket, and tell
+/// allowed requests their limit and what's left of it
+pub async fn rate_limit_mw(
+    State(limiter): State<RateLimiter>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    req: Request<axum::body::Body>,
+    next: Next,
+) -> Response {
+    let bucket = match Bucket::for_path(req.uri().path()) {
+        Some(bucket) if limiter.enabled && req.method() != Method::OPTIONS => bucket,
+        _ => return next.run(req).await,
+    };
+
+    let client = client_key(bucket, req.headers(), peer.map(|ConnectInfo(addr)| addr));
+    match limiter.check(bucket, &client).await {
+        Decision::Limited { retry_after_secs } => {
+            tracing::warn!("Rate limited {} on the {} bucket", client, bucket.as_str());
+            AppError::RateLimited {
+                message: "Too many requests; slow down and try again shortly".to_string(),
+                retry_after_secs,
+            }
+            .into_response()
+        }
+        Decision::Allowed { remaining } => {
+            let mut response = next.run(req).await;
+            // Inner limits, such as an API key's, report their own numbers
+            let headers = response.headers_mut();
+            if !headers.contains_key("x-ratelimit-limit") {
+                headers.insert("x-ratelimit-limit", HeaderValue::from(limiter.limits.limit(bucket)));
+                headers.insert("x-ratelimit-remaining", HeaderValue::from(remaining));
+            }
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_for_path() {
+        assert_eq!(Bucket::for_path("/api/auth/login"), Some(Bucket::Auth));
+        assert_eq!(Bucket::for_path("/api/admin/overview"), Some(Bucket::Admin));
+        assert_eq!(Bucket::for_path("/api/analytics/platform"), Some(Bucket::Analytics));
+        assert_eq!(Bucket::for_path("/api/projects"), Some(Bucket::General));
+        assert_eq!(Bucket::for_path("/health"), None);
+        assert_eq!(Bucket::for_path("/api/notifications/sse"), None);
+    }
+
+    #[test]
+    fn test_memory_window_resets() {
+        let store = MemoryStore::default();
+        let start = Instant::now();
+        assert_eq!(store.hit("k", start), (1, 60));
+        assert_eq!(store.hit("k", start + Duration::from_secs(20)).0, 2);
+        assert_eq!(store.hit("k", start + Duration::from_secs(20)).1, 40);
+        assert_eq!(store.hit("k", start + WINDOW), (1, 60));
+        assert_eq!(store.hit("other", start).0, 1);
+    }
+
+    #[test]
+    fn test_decide() {
+        assert_eq!(decide(10, 10, 30), Decision::Allowed { remaining: 0 });
+        assert_eq!(decide(11, 10, 30), Decision::Limited { retry_after_secs: 30 });
+        assert_eq!(decide(11, 10, 0), Decision::Limited { retry_after_secs: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_limiter_counts_per_client() {
+        let limits = RateLimits { auth: 2, ..RateLimits::default() };
+        let limiter = RateLimiter::new(RateLimitStore::Memory(MemoryStore::default()), limits);
+        assert_eq!(limiter.check(Bucket::Auth, "ip:1.2.3.4").await, Decision::Allowed { remaining: 1 });
+        assert_eq!(limiter.check(Bucket::Auth, "ip:1.2.3.4").await, Decision::Allowed { remaining: 0 });
+        assert!(matches!(limiter.check(Bucket::Auth, "ip:1.2.3.4").await, Decision::Limited { .. }));
+        assert_eq!(limiter.check(Bucket::Auth, "ip:5.6.7.8").await, Decision::Allowed { remaining: 1 });
+        assert_eq!(limiter.check(Bucket::General, "ip:1.2.3.4").await, Decision::Allowed { remaining: 99 });
+    }
+}
@@ -0,0 +1,67 @@
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id of the request being handled, for error bodies and logs
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Keep an id from the proxy only if it's short and plain; anything else is
+/// replaced so it can't inject into logs or headers
+fn accept_incoming(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 128
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Give every request an id (the caller's `X-Request-Id` when usable) and
+/// echo it on the response
+pub async fn request_id_mw(req: Request<axum::body::Body>, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| accept_incoming(v))
+        .map(String::from)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!("request", request_id = %id);
+    let mut response = REQUEST_ID
+        .scope(id.clone(), tracing::Instrument::instrument(next.run(req), span))
+        .await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_incoming() {
+        assert!(accept_incoming("3f1c9a7e-52b4-4c1e-9d7a-0c2b8f5e6a11"));
+        assert!(accept_incoming("edge.req_42"));
+        assert!(!accept_incoming(""));
+        assert!(!accept_incoming("id\r\nx-admin: 1"));
+        assert!(!accept_incoming(&"a".repeat(129)));
+    }
+
+    #[tokio::test]
+    async fn test_current_inside_scope() {
+        assert_eq!(current(), None);
+        let id = REQUEST_ID.scope("req-1".to_string(), async { current() }).await;
+        assert_eq!(id.as_deref(), Some("req-1"));
+    }
+}
@@ -1,7 +1,7 @@
 use axum::{http::{StatusCode, Request, Response}, middleware::Next, extract::FromRequestParts};
 use crate::utils::jwt;
 
-fn bearer_from_auth(header: Option<&str>) -> Option<&str> {
+pub(crate) fn bearer_from_auth(header: Option<&str>) -> Option<&str> {
     header.and_then(|h| h.strip_prefix("Bearer "))
 }
 
@@ -13,43 +13,61 @@ pub async fn require_admin_mw(
     let token = bearer_from_auth(auth).ok_or(StatusCode::UNAUTHORIZED)?;
     let claims = jwt::verify_token(token).map_err(|_| StatusCode::UNAUTHORIZED)?;
     
-    // For now, just check if the user ID is the admin user ID
-    // This is a temporary fix to test the endpoints
-    if claims.sub.to_string() != "00000000-0000-0000-0000-000000000001" {
+    if !claims.is_admin() {
         tracing::error!("User {} is not admin", claims.sub);
         return Err(StatusCode::FORBIDDEN); 
     }
     
+    req.extensions_mut().insert(claims);
+    Ok(next.run(req).await)
+}
+
+/// Admin routes moderators may use as well
+pub async fn require_moderator_mw(
+    mut req: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response<axum::body::Body>, StatusCode> {
+    let auth = req.headers().get("authorization").and_then(|v| v.to_str().ok());
+    let token = bearer_from_auth(auth).ok_or(StatusCode::UNAUTHORIZED)?;
+    let claims = jwt::verify_token(token).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    if !claims.can_moderate() {
+        tracing::error!("User {} is not a moderator", claims.sub);
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    req.extensions_mut().insert(claims);
+    Ok(next.run(req).await)
+}
+
+/// Admin routes finance staff may use as well
+pub async fn require_finance_mw(
+    mut req: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response<axum::body::Body>, StatusCode> {
+    let auth = req.headers().get("authorization").and_then(|v| v.to_str().ok());
+    let token = bearer_from_auth(auth).ok_or(StatusCode::UNAUTHORIZED)?;
+    let claims = jwt::verify_token(token).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    if !claims.can_manage_funds() {
+        tracing::error!("User {} is not finance staff", claims.sub);
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    req.extensions_mut().insert(claims);
     Ok(next.run(req).await)
 }
 
+/// Verification status comes from the token, so a newly verified student
+/// gets through once their session is refreshed
 pub async fn require_verified_student_mw(mut req: Request<axum::body::Body>, next: Next) -> Result<Response<axum::body::Body>, StatusCode> {
     let auth = req.headers().get("authorization").and_then(|v| v.to_str().ok());
     let token = bearer_from_auth(auth).ok_or(StatusCode::UNAUTHORIZED)?;
     let claims = jwt::verify_token(token).map_err(|_| StatusCode::UNAUTHORIZED)?;
     
-    // Get database URL from environment
-    let database_url = std::env::var("DATABASE_URL")
-        .map_err(|_| {
-            tracing::error!("DATABASE_URL not set");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    
-    // Create a temporary connection for this check
-    let pool = sqlx::PgPool::connect(&database_url).await
-        .map_err(|e| {
-            tracing::error!("Failed to connect to database: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-    
-    let rec = sqlx::query!("SELECT verification_status FROM students WHERE user_id = $1", claims.sub)
-        .fetch_optional(&pool).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::FORBIDDEN)?;
-    if rec.verification_status.to_lowercase() != "verified" { return Err(StatusCode::FORBIDDEN); }
-    
-    // Close the temporary connection
-    pool.close().await;
+    if !claims.is_verified_student() { return Err(StatusCode::FORBIDDEN); }
     
+    req.extensions_mut().insert(claims);
     Ok(next.run(req).await)
 }
 
@@ -67,4 +85,68 @@ pub async fn require_auth_mw(
     Ok(next.run(req).await)
 }
 
+/// Require a permission from the caller's token, e.g.
+/// `middleware::from_fn(|req, next| require_permission_mw(rbac::PROJECTS_PUBLISH, req, next))`
+pub async fn require_permission_mw(
+    permission: &'static str,
+    mut req: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response<axum::body::Body>, StatusCode> {
+    let auth = req.headers().get("authorization").and_then(|v| v.to_str().ok());
+    let token = bearer_from_auth(auth).ok_or(StatusCode::UNAUTHORIZED)?;
+    let claims = jwt::verify_token(token).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    if !claims.has_permission(permission) {
+        tracing::warn!("User {} lacks permission {}", claims.sub, permission);
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    req.extensions_mut().insert(claims);
+    Ok(next.run(req).await)
+}
+
+
+/// Admins, moderators and finance staff must have two-factor authentication
+/// before using admin routes. Other callers pass through to the role checks.
+pub async fn require_admin_two_factor_mw(
+    axum::extract::State(pool): axum::extract::State<sqlx::PgPool>,
+    req: Request<axum::body::Body>,
+    next: Next,
+) -> Result<Response<axum::body::Body>, (StatusCode, axum::Json<serde_json::Value>)> {
+    // Only staff can owe enrollment, so other callers skip the lookup
+    let staff = jwt::extract_claims_from_headers(req.headers()).ok().filter(|c| c.is_staff());
+    if let Some(user_id) = staff.map(|c| c.sub) {
+        let required = crate::services::two_factor::enrollment_required(&pool, user_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to check two-factor enrollment for {}: {}", user_id, e);
+                (StatusCode::INTERNAL_SERVER_ERROR, axum::Json(serde_json::json!({"error": "Internal server error"})))
+            })?;
+        if required {
+            return Err((
+                StatusCode::FORBIDDEN,
+                axum::Json(serde_json::json!({
+                    "error": "Staff accounts must enable two-factor authentication at /api/auth/2fa/setup",
+                    "two_factor_required": true
+                })),
+            ));
+        }
+    }
 
+    Ok(next.run(req).await)
+}
+
+
+/// Whether the request carries a valid token for an admin. For handlers that
+/// show admins more than other callers rather than refusing everyone else.
+pub async fn caller_is_admin(pool: &sqlx::PgPool, headers: &axum::http::HeaderMap) -> bool {
+    let Ok(user_id) = jwt::extract_user_id_from_headers(headers) else {
+        return false;
+    };
+    sqlx::query_scalar!("SELECT role FROM users WHERE id = $1", user_id)
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .is_some_and(|role| role == "admin")
+}
@@ -0,0 +1,52 @@
+/// Accumulates bytes from a server-sent event stream and yields the data of
+/// each complete event. Events may arrive split across chunks.
+#[derive(Debug, Default)]
+pub struct SseBuffer {
+    buf: Vec<u8>,
+}
+
+impl SseBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a chunk and return the data payloads of any events it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buf.windows(2).position(|w| w == b"\n\n") {
+            let raw: Vec<u8> = self.buf.drain(..pos + 2).collect();
+            let text = String::from_utf8_lossy(&raw);
+            let data: Vec<&str> = text
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|d| d.strip_prefix(' ').unwrap_or(d))
+                .collect();
+            if !data.is_empty() {
+                events.push(data.join("\n"));
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut sse = SseBuffer::new();
+        assert!(sse.push(b"retry: 1000\nevent: open\ndata: \"hel").is_empty());
+        assert_eq!(sse.push(b"lo\"\n\nid: 1\ndata: {\"a\":1}\n\n"), vec!["\"hello\"", "{\"a\":1}"]);
+        assert!(sse.push(b"id: 2\ndata: {\"a\"").is_empty());
+        assert_eq!(sse.push(b":2}\n\n"), vec!["{\"a\":2}"]);
+    }
+
+    #[test]
+    fn test_multiline_data_and_comments() {
+        let mut sse = SseBuffer::new();
+        assert_eq!(sse.push(b"data: one\ndata: two\n\n: keepalive\n\n"), vec!["one\ntwo"]);
+    }
+}
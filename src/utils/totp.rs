@@ -0,0 +1,147 @@
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+/// Seconds each code is valid for
+pub const STEP_SECS: i64 = 30;
+pub const DIGITS: u32 = 6;
+/// Steps either side of now that are still accepted, for clock drift
+const DRIFT_STEPS: i64 = 1;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// A fresh 160-bit shared secret
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; 20];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+/// RFC 4648 base32 without padding, as authenticator apps expect
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// Decode base32, ignoring case, spaces and padding
+pub fn base32_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in text.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = BASE32_ALPHABET.iter().position(|&a| a as char == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// The code for a time step (RFC 6238 with HMAC-SHA1)
+pub fn code_at_step(secret: &[u8], step: i64, digits: u32) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([hash[offset] & 0x7f, hash[offset + 1], hash[offset + 2], hash[offset + 3]]);
+    binary % 10u32.pow(digits)
+}
+
+pub fn step_at(unix_time: i64) -> i64 {
+    unix_time.div_euclid(STEP_SECS)
+}
+
+/// The step a submitted code belongs to, if it is valid around `unix_time`
+pub fn verify(secret: &[u8], code: &str, unix_time: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let submitted: u32 = code.parse().ok()?;
+    let now = step_at(unix_time);
+    (now - DRIFT_STEPS..=now + DRIFT_STEPS).find(|&step| code_at_step(secret, step, DIGITS) == submitted)
+}
+
+/// `otpauth://` URI for enrolling the secret in an authenticator app
+pub fn otpauth_uri(issuer: &str, account: &str, secret: &[u8]) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        uri_encode(issuer),
+        uri_encode(account),
+        base32_encode(secret),
+        uri_encode(issuer),
+        DIGITS,
+        STEP_SECS
+    )
+}
+
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'@' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        assert_eq!(code_at_step(RFC_SECRET, step_at(59), 8), 94287082);
+        assert_eq!(code_at_step(RFC_SECRET, step_at(1111111109), 8), 7081804);
+        assert_eq!(code_at_step(RFC_SECRET, step_at(1234567890), 8), 89005924);
+        assert_eq!(code_at_step(RFC_SECRET, step_at(20000000000), 8), 65353130);
+    }
+
+    #[test]
+    fn test_verify_accepts_drift_only() {
+        let now = 1111111109;
+        let code = format!("{:06}", code_at_step(RFC_SECRET, step_at(now), DIGITS));
+        assert_eq!(verify(RFC_SECRET, &code, now), Some(step_at(now)));
+        assert_eq!(verify(RFC_SECRET, &code, now + STEP_SECS), Some(step_at(now)));
+        assert_eq!(verify(RFC_SECRET, &code, now + 3 * STEP_SECS), None);
+        assert_eq!(verify(RFC_SECRET, "12345", now), None);
+        assert_eq!(verify(RFC_SECRET, "abcdef", now), None);
+    }
+
+    #[test]
+    fn test_base32_round_trip() {
+        assert_eq!(base32_encode(RFC_SECRET), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("mzxw 6ytb oi==").unwrap(), b"foobar");
+        assert_eq!(base32_decode(&base32_encode(RFC_SECRET)).unwrap(), RFC_SECRET);
+        assert!(base32_decode("MZ1").is_none());
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        assert_eq!(
+            otpauth_uri("Fund Hub", "ada@example.com", b"foobar"),
+            "otpauth://totp/Fund%20Hub:ada@example.com?secret=MZXW6YTBOI&issuer=Fund%20Hub&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
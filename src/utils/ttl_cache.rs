@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Small in-process cache whose entries expire after a fixed TTL. Meant for
+/// read-heavy public endpoints where slightly stale data is acceptable.
+#[derive(Clone)]
+pub struct TtlCache<V: Clone> {
+    entries: Arc<Mutex<HashMap<String, (Instant, V)>>>,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl<V: Clone> TtlCache<V> {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+            max_entries,
+        }
+    }
+
+    /// Return the cached value if it has not expired
+    pub fn get(&self, key: &str) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|(inserted, _)| inserted.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub fn insert(&self, key: String, value: V) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            // Drop expired entries first; if still full, evict the oldest
+            let ttl = self.ttl;
+            entries.retain(|_, (inserted, _)| inserted.elapsed() < ttl);
+            if entries.len() >= self.max_entries {
+                if let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, (inserted, _))| *inserted)
+                    .map(|(k, _)| k.clone())
+                {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returns_values_until_expired() {
+        let cache = TtlCache::new(Duration::from_millis(50), 10);
+        cache.insert("a".to_string(), 1);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("b"), None);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.get("a"), None);
+    }
+
+    #[test]
+    fn evicts_oldest_when_full() {
+        let cache = TtlCache::new(Duration::from_secs(60), 2);
+        cache.insert("a".to_string(), 1);
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert("b".to_string(), 2);
+        cache.insert("c".to_string(), 3);
+
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(2));
+        assert_eq!(cache.get("c"), Some(3));
+    }
+
+    #[test]
+    fn zero_ttl_disables_caching() {
+        let cache = TtlCache::new(Duration::ZERO, 10);
+        cache.insert("a".to_string(), 1);
+        assert_eq!(cache.get("a"), None);
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Counters for one user and endpoint since the last flush
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageCounts {
+    pub requests: i64,
+    pub errors: i64,
+    pub total_latency_ms: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageBucket {
+    pub user_id: Uuid,
+    pub endpoint: String,
+    pub counts: UsageCounts,
+}
+
+/// Buffers authenticated API usage in memory; the analytics worker drains it
+/// into `api_usage_daily` so requests never wait on a database write
+#[derive(Clone, Default)]
+pub struct UsageRecorder {
+    buckets: Arc<Mutex<HashMap<(Uuid, String), UsageCounts>>>,
+}
+
+impl UsageRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, user_id: Uuid, endpoint: &str, is_error: bool, latency_ms: i64) {
+        let mut buckets = self.buckets.lock().unwrap();
+        let counts = buckets.entry((user_id, endpoint.to_string())).or_default();
+        counts.requests += 1;
+        if is_error {
+            counts.errors += 1;
+        }
+        counts.total_latency_ms += latency_ms;
+    }
+
+    /// Take everything recorded so far
+    pub fn drain(&self) -> Vec<UsageBucket> {
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets
+            .drain()
+            .map(|((user_id, endpoint), counts)| UsageBucket { user_id, endpoint, counts })
+            .collect()
+    }
+
+    /// Put a bucket back after a failed flush so its counts are not lost
+    pub fn restore(&self, bucket: UsageBucket) {
+        let mut buckets = self.buckets.lock().unwrap();
+        let counts = buckets.entry((bucket.user_id, bucket.endpoint)).or_default();
+        counts.requests += bucket.counts.requests;
+        counts.errors += bucket.counts.errors;
+        counts.total_latency_ms += bucket.counts.total_latency_ms;
+    }
+}
+
+/// Suggested rate-limit tier derived from recent usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateTier {
+    Standard,
+    Elevated,
+    Heavy,
+    /// Mostly failing requests; likely a misbehaving client
+    Restricted,
+}
+
+/// Average daily requests above which a user moves up a tier
+const ELEVATED_DAILY_REQUESTS: f64 = 1_000.0;
+const HEAVY_DAILY_REQUESTS: f64 = 10_000.0;
+/// Error rate that marks a busy client as restricted
+const RESTRICTED_ERROR_RATE: f64 = 0.5;
+/// Below this many requests the error rate is too noisy to act on
+const MIN_REQUESTS_FOR_ERROR_RATE: i64 = 100;
+
+impl RateTier {
+    pub fn suggest(requests: i64, errors: i64, days: i64) -> Self {
+        if requests >= MIN_REQUESTS_FOR_ERROR_RATE
+            && errors as f64 / requests as f64 >= RESTRICTED_ERROR_RATE
+        {
+            return RateTier::Restricted;
+        }
+
+        let daily = requests as f64 / days.max(1) as f64;
+        if daily >= HEAVY_DAILY_REQUESTS {
+            RateTier::Heavy
+        } else if daily >= ELEVATED_DAILY_REQUESTS {
+            RateTier::Elevated
+        } else {
+            RateTier::Standard
+        }
+    }
+}
+
+/// Record per-user request counts, error counts, and latency for
+/// authenticated requests (route_layer so the matched path is known)
+pub async fn usage_mw(
+    State(recorder): State<UsageRecorder>,
+    matched: Option<MatchedPath>,
+    req: Request<axum::body::Body>,
+    next: Next,
+) -> Response {
+    let user_id = crate::utils::jwt::extract_user_id_from_headers(req.headers()).ok();
+    let endpoint = format!(
+        "{} {}",
+        req.method(),
+        matched.as_ref().map(|m| m.as_str()).unwrap_or("unmatched")
+    );
+    let started = Instant::now();
+
+    let response = next.run(req).await;
+
+    if let Some(user_id) = user_id {
+        let is_error = response.status().is_client_error() || response.status().is_server_error();
+        recorder.record(user_id, &endpoint, is_error, started.elapsed().as_millis() as i64);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_drain_and_restore() {
+        let recorder = UsageRecorder::new();
+        let user = Uuid::new_v4();
+
+        recorder.record(user, "GET /api/projects", false, 10);
+        recorder.record(user, "GET /api/projects", true, 30);
+        recorder.record(user, "POST /api/donations", false, 5);
+
+        let mut buckets = recorder.drain();
+        buckets.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].counts, UsageCounts { requests: 2, errors: 1, total_latency_ms: 40 });
+        assert!(recorder.drain().is_empty());
+
+        recorder.record(user, "GET /api/projects", false, 10);
+        recorder.restore(buckets.remove(0));
+        let restored = recorder.drain();
+        assert_eq!(restored[0].counts, UsageCounts { requests: 3, errors: 1, total_latency_ms: 50 });
+    }
+
+    #[test]
+    fn test_suggest_tier() {
+        assert_eq!(RateTier::suggest(500, 0, 1), RateTier::Standard);
+        assert_eq!(RateTier::suggest(7_000, 0, 7), RateTier::Elevated);
+        assert_eq!(RateTier::suggest(70_000, 0, 7), RateTier::Heavy);
+        assert_eq!(RateTier::suggest(200, 150, 7), RateTier::Restricted);
+        // Too few requests to judge the error rate
+        assert_eq!(RateTier::suggest(10, 10, 1), RateTier::Standard);
+    }
+}
@@ -2,17 +2,28 @@ use std::time::Duration;
 use anyhow::Result;
 use sqlx::PgPool;
 use tracing::{info, error, warn};
-use chrono::{Utc, Duration as ChronoDuration};
+use chrono::{DateTime, Utc, Duration as ChronoDuration};
 use sqlx::types::BigDecimal;
 use num_traits::cast::ToPrimitive;
 
+use super::control::WorkerControl;
+use crate::utils::usage::UsageRecorder;
+
+/// `analytics_watermarks` row tracking the latest confirmed donation counted
+const DONATIONS_WATERMARK: &str = "donations";
+/// Donations confirming around a run can commit with a `confirmed_at` just
+/// before the watermark it records, so each run looks back this far past it
+const WATERMARK_OVERLAP_SECS: i64 = 300;
+
 pub struct AnalyticsWorker {
     pool: PgPool,
+    control: WorkerControl,
+    usage: UsageRecorder,
 }
 
 impl AnalyticsWorker {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, control: WorkerControl, usage: UsageRecorder) -> Self {
+        Self { pool, control, usage }
     }
 
     pub async fn start(self) -> Result<()> {
@@ -20,34 +31,75 @@ impl AnalyticsWorker {
         
         // Real-time analytics collection (every 5 minutes)
         let pool_clone = self.pool.clone();
-        tokio::spawn(async move {
+        let control = self.control.clone();
+        self.control.spawn(async move {
             loop {
-                if let Err(e) = Self::collect_realtime_analytics(&pool_clone).await {
+                if control.is_paused("analytics") {
+                    info!("Analytics worker paused, skipping real-time collection");
+                } else if !control.leads("analytics").await {
+                    tracing::debug!("Another instance leads analytics, skipping real-time collection");
+                } else if let Err(e) = Self::collect_realtime_analytics(&pool_clone).await {
                     error!("Error collecting real-time analytics: {}", e);
                 }
-                tokio::time::sleep(Duration::from_secs(300)).await;
+                if !control.idle(Duration::from_secs(300)).await {
+                    break;
+                }
             }
         });
 
         // Daily analytics aggregation (every hour)
         let pool_clone2 = self.pool.clone();
-        tokio::spawn(async move {
+        let control = self.control.clone();
+        self.control.spawn(async move {
             loop {
-                if let Err(e) = Self::aggregate_daily_analytics(&pool_clone2).await {
+                if control.is_paused("analytics") {
+                    info!("Analytics worker paused, skipping daily aggregation");
+                } else if !control.leads("analytics").await {
+                    tracing::debug!("Another instance leads analytics, skipping daily aggregation");
+                } else if let Err(e) = Self::aggregate_daily_analytics(&pool_clone2).await {
                     error!("Error aggregating daily analytics: {}", e);
                 }
-                tokio::time::sleep(Duration::from_secs(3600)).await;
+                if !control.idle(Duration::from_secs(3600)).await {
+                    break;
+                }
+            }
+        });
+
+        // API usage flush (every minute). Usage is counted in memory, so every
+        // instance flushes its own whether or not it leads analytics
+        let pool_clone4 = self.pool.clone();
+        let control = self.control.clone();
+        let usage = self.usage.clone();
+        self.control.spawn(async move {
+            loop {
+                if control.is_paused("analytics") {
+                    info!("Analytics worker paused, skipping API usage flush");
+                } else {
+                    Self::flush_api_usage(&pool_clone4, &usage).await;
+                }
+                if !control.idle(Duration::from_secs(60)).await {
+                    // Counts recorded since the last flush would be lost
+                    Self::flush_api_usage(&pool_clone4, &usage).await;
+                    break;
+                }
             }
         });
 
         // Weekly analytics summary (every 6 hours)
         let pool_clone3 = self.pool.clone();
-        tokio::spawn(async move {
+        let control = self.control.clone();
+        self.control.spawn(async move {
             loop {
-                if let Err(e) = Self::generate_weekly_summary(&pool_clone3).await {
+                if control.is_paused("analytics") {
+                    info!("Analytics worker paused, skipping weekly summary");
+                } else if !control.leads("analytics").await {
+                    tracing::debug!("Another instance leads analytics, skipping weekly summary");
+                } else if let Err(e) = Self::generate_weekly_summary(&pool_clone3).await {
                     error!("Error generating weekly summary: {}", e);
                 }
-                tokio::time::sleep(Duration::from_secs(21600)).await;
+                if !control.idle(Duration::from_secs(21600)).await {
+                    break;
+                }
             }
         });
 
@@ -55,11 +107,21 @@ impl AnalyticsWorker {
     }
 
     async fn collect_realtime_analytics(pool: &PgPool) -> Result<()> {
+        // Only entities with donations confirmed since the last run are recomputed
+        let mark = sqlx::query_scalar!(
+            "SELECT high_water FROM analytics_watermarks WHERE name = $1",
+            DONATIONS_WATERMARK
+        ).fetch_optional(pool).await?;
+        let until = sqlx::query_scalar!(
+            r#"SELECT MAX(confirmed_at) FROM donations WHERE status = 'confirmed'"#
+        ).fetch_one(pool).await?;
+        let since = incremental_since(mark);
+
         // Update project analytics
-        Self::update_project_analytics(pool).await?;
+        Self::update_project_analytics(pool, since).await?;
         
         // Update student analytics
-        Self::update_student_analytics(pool).await?;
+        Self::update_student_analytics(pool, since).await?;
         
         // Update campaign analytics
         Self::update_campaign_analytics(pool).await?;
@@ -67,6 +129,87 @@ impl AnalyticsWorker {
         // Update platform metrics
         Self::update_platform_metrics(pool).await?;
 
+        if let Some(until) = until {
+            sqlx::query!(
+                r#"INSERT INTO analytics_watermarks (name, high_water, updated_at)
+                    VALUES ($1, $2, NOW())
+                    ON CONFLICT (name) DO UPDATE SET high_water = EXCLUDED.high_water, updated_at = NOW()"#,
+                DONATIONS_WATERMARK,
+                until
+            ).execute(pool).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fold buffered per-user API usage into today's `api_usage_daily` rows.
+    /// Buckets that fail to write go back into the recorder for the next run.
+    async fn flush_api_usage(pool: &PgPool, usage: &UsageRecorder) {
+        let today = Utc::now().date_naive();
+        let buckets = usage.drain();
+        let mut failed = 0;
+
+        for bucket in buckets {
+            let result = sqlx::query!(
+                r#"INSERT INTO api_usage_daily (user_id, day, endpoint, requests, errors, total_latency_ms, updated_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, NOW())
+                    ON CONFLICT (user_id, day, endpoint)
+                    DO UPDATE SET requests = api_usage_daily.requests + EXCLUDED.requests,
+                                  errors = api_usage_daily.errors + EXCLUDED.errors,
+                                  total_latency_ms = api_usage_daily.total_latency_ms + EXCLUDED.total_latency_ms,
+                                  updated_at = NOW()"#,
+                bucket.user_id,
+                today,
+                bucket.endpoint,
+                bucket.counts.requests,
+                bucket.counts.errors,
+                bucket.counts.total_latency_ms
+            ).execute(pool).await;
+
+            if let Err(e) = result {
+                warn!("Failed to flush API usage for user {}: {}", bucket.user_id, e);
+                usage.restore(bucket);
+                failed += 1;
+            }
+        }
+
+        if failed > 0 {
+            error!("{} API usage buckets kept for retry", failed);
+        }
+    }
+
+    async fn aggregate_feature_uplift(pool: &PgPool) -> Result<()> {
+        let since = Utc::now() - ChronoDuration::days(30);
+        let mut slot_ids = Vec::new();
+        let mut metrics = Vec::new();
+        let mut values = Vec::new();
+        for slot in crate::services::featuring::uplift(pool, since).await? {
+            let slot_metrics = [
+                ("feature_impressions", Some(slot.impressions as f64)),
+                ("feature_funding", Some(slot.featured_funding.to_f64())),
+                ("feature_uplift_pct", slot.uplift_pct),
+            ];
+            for (metric, value) in slot_metrics {
+                let Some(value) = value else { continue };
+                slot_ids.push(slot.slot_id);
+                metrics.push(metric.to_string());
+                values.push(value);
+            }
+        }
+        if slot_ids.is_empty() {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
+                SELECT 'feature_slot', slot.id, slot.metric, slot.value, NOW()
+                FROM UNNEST($1::uuid[], $2::text[], $3::float8[]) AS slot(id, metric, value)
+                ON CONFLICT (entity_type, entity_id, metric)
+                DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
+            &slot_ids,
+            &metrics,
+            &values
+        ).execute(pool).await?;
         Ok(())
     }
 
@@ -74,6 +217,11 @@ impl AnalyticsWorker {
         let today = Utc::now().date_naive();
         let yesterday = today - ChronoDuration::days(1);
 
+        // Rebuild every donation total, catching donations refunded or
+        // moved since the incremental runs counted them
+        Self::update_project_analytics(pool, None).await?;
+        Self::update_student_analytics(pool, None).await?;
+
         // Daily donation trends
         Self::aggregate_donation_trends(pool, yesterday).await?;
         
@@ -83,6 +231,9 @@ impl AnalyticsWorker {
         // Daily project performance
         Self::aggregate_project_performance(pool, yesterday).await?;
 
+        // Funding uplift of recent feature slots
+        Self::aggregate_feature_uplift(pool).await?;
+
         info!("Daily analytics aggregated for {}", yesterday);
         Ok(())
     }
@@ -103,144 +254,95 @@ impl AnalyticsWorker {
         Ok(())
     }
 
-    async fn update_project_analytics(pool: &PgPool) -> Result<()> {
-        // Total donations per project
-        let rows = sqlx::query!(
-            r#"SELECT project_id, SUM(amount) as total FROM donations WHERE status = 'confirmed' GROUP BY project_id"#
-        ).fetch_all(pool).await?;
-        
-        for r in rows {
-            let _ = sqlx::query!(
-                r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
-                    VALUES ('project', $1, 'total_donations', $2, NOW())
-                    ON CONFLICT (entity_type, entity_id, metric)
-                    DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
-                r.project_id,
-                r.total.unwrap_or_default().to_f64().unwrap_or(0.0)
-            ).execute(pool).await;
-        }
-
-        // Donation count per project
-        let count_rows = sqlx::query!(
-            r#"SELECT project_id, COUNT(*) as count FROM donations WHERE status = 'confirmed' GROUP BY project_id"#
-        ).fetch_all(pool).await?;
-        
-        for r in count_rows {
-            let _ = sqlx::query!(
-                r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
-                    VALUES ('project', $1, 'donation_count', $2, NOW())
-                    ON CONFLICT (entity_type, entity_id, metric)
-                    DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
-                r.project_id,
-                r.count.unwrap_or(0) as f64
-            ).execute(pool).await;
-        }
+    /// Donation totals and counts for projects with donations confirmed
+    /// after `since`, or for every project when `None`
+    async fn update_project_analytics(pool: &PgPool, since: Option<DateTime<Utc>>) -> Result<()> {
+        sqlx::query!(
+            r#"WITH touched AS (
+                    SELECT DISTINCT project_id FROM donations
+                    WHERE project_id IS NOT NULL AND ($1::timestamptz IS NULL OR confirmed_at > $1)
+                ), totals AS (
+                    SELECT t.project_id, COALESCE(SUM(d.amount), 0)::float8 as total, COUNT(d.id)::float8 as count
+                    FROM touched t
+                    LEFT JOIN donations d ON d.project_id = t.project_id AND d.status = 'confirmed'
+                    GROUP BY t.project_id
+                )
+                INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
+                SELECT 'project', totals.project_id, m.metric, m.value, NOW()
+                FROM totals
+                CROSS JOIN LATERAL (VALUES ('total_donations', totals.total), ('donation_count', totals.count)) AS m(metric, value)
+                ON CONFLICT (entity_type, entity_id, metric)
+                DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
+            since
+        ).execute(pool).await?;
 
         Ok(())
     }
 
-    async fn update_student_analytics(pool: &PgPool) -> Result<()> {
-        // Total donations per student (via projects)
-        let rows = sqlx::query!(
-            r#"SELECT p.student_id as sid, SUM(d.amount) as total
-                FROM donations d
-                JOIN projects p ON p.id = d.project_id
-                WHERE d.status = 'confirmed'
-                GROUP BY p.student_id"#
-        ).fetch_all(pool).await?;
-        
-        for r in rows {
-            let _ = sqlx::query!(
-                r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
-                    VALUES ('student', $1, 'total_donations', $2, NOW())
-                    ON CONFLICT (entity_type, entity_id, metric)
-                    DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
-                r.sid,
-                r.total.unwrap_or_default().to_f64().unwrap_or(0.0)
-            ).execute(pool).await;
-        }
+    /// Donation totals for students whose projects had donations confirmed
+    /// after `since` (every student when `None`), and every student's
+    /// project count
+    async fn update_student_analytics(pool: &PgPool, since: Option<DateTime<Utc>>) -> Result<()> {
+        sqlx::query!(
+            r#"WITH touched AS (
+                    SELECT DISTINCT p.student_id
+                    FROM donations d
+                    JOIN projects p ON p.id = d.project_id
+                    WHERE $1::timestamptz IS NULL OR d.confirmed_at > $1
+                )
+                INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
+                SELECT 'student', t.student_id, 'total_donations', COALESCE(SUM(d.amount), 0)::float8, NOW()
+                FROM touched t
+                JOIN projects p ON p.student_id = t.student_id
+                LEFT JOIN donations d ON d.project_id = p.id AND d.status = 'confirmed'
+                GROUP BY t.student_id
+                ON CONFLICT (entity_type, entity_id, metric)
+                DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
+            since
+        ).execute(pool).await?;
 
-        // Project count per student
-        let project_rows = sqlx::query!(
-            r#"SELECT student_id, COUNT(*) as count FROM projects GROUP BY student_id"#
-        ).fetch_all(pool).await?;
-        
-        for r in project_rows {
-            let _ = sqlx::query!(
-                r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
-                    VALUES ('student', $1, 'project_count', $2, NOW())
-                    ON CONFLICT (entity_type, entity_id, metric)
-                    DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
-                r.student_id,
-                r.count.unwrap_or(0) as f64
-            ).execute(pool).await;
-        }
+        sqlx::query!(
+            r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
+                SELECT 'student', student_id, 'project_count', COUNT(*)::float8, NOW()
+                FROM projects
+                GROUP BY student_id
+                ON CONFLICT (entity_type, entity_id, metric)
+                DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#
+        ).execute(pool).await?;
 
         Ok(())
     }
 
     async fn update_campaign_analytics(pool: &PgPool) -> Result<()> {
-        // Campaign distribution amounts
-        let rows = sqlx::query!(
-            r#"SELECT campaign_id, SUM(amount) as total FROM campaign_distributions GROUP BY campaign_id"#
-        ).fetch_all(pool).await?;
-        
-        for r in rows {
-            let _ = sqlx::query!(
-                r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
-                    VALUES ('campaign', $1, 'distributed_amount', $2, NOW())
-                    ON CONFLICT (entity_type, entity_id, metric)
-                    DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
-                r.campaign_id,
-                r.total.unwrap_or_default().to_f64().unwrap_or(0.0)
-            ).execute(pool).await;
-        }
-
-        // Campaign recipient count
-        let recipient_rows = sqlx::query!(
-            r#"SELECT campaign_id, COUNT(DISTINCT recipient_id) as count FROM campaign_distributions GROUP BY campaign_id"#
-        ).fetch_all(pool).await?;
-        
-        for r in recipient_rows {
-            let _ = sqlx::query!(
-                r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
-                    VALUES ('campaign', $1, 'recipient_count', $2, NOW())
-                    ON CONFLICT (entity_type, entity_id, metric)
-                    DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
-                r.campaign_id,
-                r.count.unwrap_or(0) as f64
-            ).execute(pool).await;
-        }
+        // Distributed amount and recipient count per campaign
+        sqlx::query!(
+            r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
+                SELECT 'campaign', totals.campaign_id, m.metric, m.value, NOW()
+                FROM (
+                    SELECT campaign_id, COALESCE(SUM(amount), 0)::float8 as total, COUNT(DISTINCT recipient_id)::float8 as recipients
+                    FROM campaign_distributions
+                    GROUP BY campaign_id
+                ) totals
+                CROSS JOIN LATERAL (VALUES ('distributed_amount', totals.total), ('recipient_count', totals.recipients)) AS m(metric, value)
+                ON CONFLICT (entity_type, entity_id, metric)
+                DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#
+        ).execute(pool).await?;
 
         Ok(())
     }
 
     async fn update_platform_metrics(pool: &PgPool) -> Result<()> {
-        // Total platform donations
-        let total_donations = sqlx::query!(
-            r#"SELECT COALESCE(SUM(amount), 0) as total FROM donations WHERE status = 'confirmed'"#
-        ).fetch_one(pool).await?;
-
-        let _ = sqlx::query!(
+        // Total platform donations and users
+        sqlx::query!(
             r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
-                VALUES ('platform', '00000000-0000-0000-0000-000000000000', 'total_donations', $1, NOW())
+                SELECT 'platform', '00000000-0000-0000-0000-000000000000', m.metric, m.value, NOW()
+                FROM (VALUES
+                    ('total_donations', (SELECT COALESCE(SUM(amount), 0)::float8 FROM donations WHERE status = 'confirmed')),
+                    ('total_users', (SELECT COUNT(*)::float8 FROM users))
+                ) AS m(metric, value)
                 ON CONFLICT (entity_type, entity_id, metric)
-                DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
-            total_donations.total.unwrap_or(BigDecimal::from(0)).to_f64().unwrap_or(0.0)
-        ).execute(pool).await;
-
-        // Total users
-        let total_users = sqlx::query!(
-            r#"SELECT COUNT(*) as count FROM users"#
-        ).fetch_one(pool).await?;
-
-        let _ = sqlx::query!(
-            r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
-                VALUES ('platform', '00000000-0000-0000-0000-000000000000', 'total_users', $1, NOW())
-                ON CONFLICT (entity_type, entity_id, metric)
-                DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#,
-            total_users.count.unwrap_or(0) as f64
-        ).execute(pool).await;
+                DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()"#
+        ).execute(pool).await?;
 
         Ok(())
     }
@@ -443,3 +545,26 @@ impl AnalyticsWorker {
         Ok(())
     }
 }
+
+/// Where an incremental run starts: a little before the watermark, or from
+/// scratch when there is none yet
+fn incremental_since(mark: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    mark.map(|mark| mark - ChronoDuration::seconds(WATERMARK_OVERLAP_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_incremental_since_overlaps_the_watermark() {
+        let mark = Utc.with_ymd_and_hms(2025, 10, 22, 12, 0, 0).unwrap();
+        assert_eq!(incremental_since(Some(mark)), Some(Utc.with_ymd_and_hms(2025, 10, 22, 11, 55, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_incremental_since_without_watermark_is_a_full_run() {
+        assert_eq!(incremental_since(None), None);
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::control::WorkerControl;
+use crate::services::{announcements, worker_heartbeats};
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::state::Notifier;
+
+/// Announcements sent per run
+const DELIVERY_BATCH: i64 = 20;
+
+/// Sends scheduled announcements when their time comes, and once a day
+/// emails each recipient a digest of the ones they haven't read
+pub struct AnnouncementDispatcher {
+    pool: PgPool,
+    notifier: Notifier,
+    dry_run: bool,
+    interval: Duration,
+    /// UTC hour the daily digest goes out
+    digest_hour: u32,
+    control: WorkerControl,
+}
+
+/// Whether today's digest is due: once the hour has come, if it hasn't gone
+/// out today. Recipients are only emailed an announcement once, so a digest
+/// run again after a restart only picks up what's new.
+pub fn digest_due(now: DateTime<Utc>, digest_hour: u32, last_digest: Option<NaiveDate>) -> bool {
+    now.hour() >= digest_hour && last_digest != Some(now.date_naive())
+}
+
+impl AnnouncementDispatcher {
+    pub fn new(pool: PgPool, notifier: Notifier, dry_run: bool, control: WorkerControl) -> Self {
+        let interval_secs = std::env::var("ANNOUNCEMENT_DISPATCHER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60);
+        let digest_hour = std::env::var("ANNOUNCEMENT_DIGEST_HOUR_UTC")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|hour| *hour < 24)
+            .unwrap_or(8);
+        Self {
+            pool,
+            notifier,
+            dry_run,
+            interval: Duration::from_secs(interval_secs),
+            digest_hour,
+            control,
+        }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        let mut last_digest = None;
+        loop {
+            if self.control.is_paused("announcement_dispatcher") {
+                tracing::info!("Announcement dispatcher paused, skipping run");
+            } else if !self.control.leads("announcement_dispatcher").await {
+                tracing::debug!("Another instance leads announcement_dispatcher, skipping run");
+            } else {
+                let outcome = self.deliver_due().await;
+                if let Err(e) = &outcome {
+                    eprintln!("Announcement dispatcher error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "announcement_dispatcher", &outcome).await;
+                let now = Utc::now();
+                if digest_due(now, self.digest_hour, last_digest) {
+                    match self.send_digests().await {
+                        Ok(()) => last_digest = Some(now.date_naive()),
+                        Err(e) => eprintln!("Announcement digest error: {}", e),
+                    }
+                }
+            }
+
+            if !self.control.idle(self.interval).await {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn deliver_due(&self) -> Result<usize> {
+        let due = announcements::due(&self.pool, DELIVERY_BATCH).await?;
+        if due.is_empty() {
+            return Ok(0);
+        }
+        if self.dry_run {
+            tracing::info!("[dry-run] Would send {} scheduled announcements", due.len());
+            return Ok(0);
+        }
+
+        let mut sent = 0;
+        for id in due {
+            match announcements::deliver(&self.pool, id).await {
+                Ok(Some(announcement)) => {
+                    tracing::info!("Sent announcement {} to {} users", id, announcement.recipients);
+                    sent += 1;
+                    match announcements::recipients(&self.pool, id).await {
+                        Ok(users) => {
+                            let event = NotificationEvent::Announcement { announcement_id: id };
+                            self.notifier.send_all(users.into_iter().map(Channel::User), event);
+                        }
+                        Err(e) => tracing::warn!("Failed to find the recipients of announcement {}: {}", id, e),
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to send announcement {}: {}", id, e),
+            }
+        }
+        Ok(sent)
+    }
+
+    async fn send_digests(&self) -> Result<()> {
+        if self.dry_run {
+            tracing::info!("[dry-run] Would queue announcement digests");
+            return Ok(());
+        }
+        let queued = announcements::queue_digests(&self.pool).await?;
+        if queued > 0 {
+            tracing::info!("Queued {} announcement digest emails", queued);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_digest_due() {
+        let morning = Utc.with_ymd_and_hms(2025, 11, 3, 7, 59, 0).unwrap();
+        let later = Utc.with_ymd_and_hms(2025, 11, 3, 8, 0, 0).unwrap();
+        assert!(!digest_due(morning, 8, None));
+        assert!(digest_due(later, 8, None));
+        assert!(!digest_due(later, 8, Some(later.date_naive())));
+        assert!(digest_due(later, 8, NaiveDate::from_ymd_opt(2025, 11, 2)));
+    }
+}
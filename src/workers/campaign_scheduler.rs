@@ -0,0 +1,84 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::control::WorkerControl;
+use crate::services::campaign_schedule;
+use crate::services::worker_heartbeats;
+
+/// Campaigns started or ended per run, of each kind
+const SCHEDULE_BATCH: i64 = 50;
+
+/// Activates scheduled campaigns at their start date and ends active ones at
+/// their end date, queueing their distribution when that's their trigger
+pub struct CampaignScheduler {
+    pool: PgPool,
+    dry_run: bool,
+    interval: Duration,
+    control: WorkerControl,
+}
+
+impl CampaignScheduler {
+    pub fn new(pool: PgPool, dry_run: bool, control: WorkerControl) -> Self {
+        let interval_secs = std::env::var("CAMPAIGN_SCHEDULER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60);
+        Self { pool, dry_run, interval: Duration::from_secs(interval_secs), control }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        loop {
+            if self.control.is_paused("campaign_scheduler") {
+                tracing::info!("Campaign scheduler paused, skipping run");
+            } else if !self.control.leads("campaign_scheduler").await {
+                tracing::debug!("Another instance leads campaign_scheduler, skipping run");
+            } else {
+                let outcome = self.run_once().await;
+                if let Err(e) = &outcome {
+                    tracing::error!("Campaign scheduler error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "campaign_scheduler", &outcome).await;
+            }
+
+            if !self.control.idle(self.interval).await {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn run_once(&self) -> Result<usize> {
+        let starts = campaign_schedule::due_starts(&self.pool, SCHEDULE_BATCH).await?;
+        let ends = campaign_schedule::due_ends(&self.pool, SCHEDULE_BATCH).await?;
+        if starts.is_empty() && ends.is_empty() {
+            return Ok(0);
+        }
+        if self.dry_run {
+            tracing::info!("[dry-run] Would activate {} campaigns and end {}", starts.len(), ends.len());
+            return Ok(0);
+        }
+
+        let mut handled = 0;
+        for campaign_id in starts {
+            match campaign_schedule::activate(&self.pool, campaign_id).await {
+                Ok(true) => {
+                    tracing::info!("Activated scheduled campaign {}", campaign_id);
+                    handled += 1;
+                }
+                Ok(false) => {}
+                Err(e) => tracing::error!("Failed to activate campaign {}: {}", campaign_id, e),
+            }
+        }
+        for (campaign_id, trigger) in ends {
+            match campaign_schedule::end(&self.pool, campaign_id, &trigger).await {
+                Ok(Some(status)) => {
+                    tracing::info!("Ended campaign {}, now {}", campaign_id, status);
+                    handled += 1;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to end campaign {}: {}", campaign_id, e),
+            }
+        }
+        Ok(handled)
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+use super::leader::Leadership;
+
+/// Names of the background loops that can be paused from the ops endpoints
+pub const WORKER_NAMES: &[&str] = &[
+    "donation_verification",
+    "wallet_sync",
+    "analytics",
+    "payment_reconciler",
+    "escrow_sweeper",
+    "campaign_matching",
+    "event_indexer",
+    "ledger_indexer",
+    "escrow_reconciler",
+    "subscription_scheduler",
+    "email_sender",
+    "webhook_dispatcher",
+    "refund_processor",
+    "project_scheduler",
+    "campaign_scheduler",
+    "file_scanner",
+    "announcement_dispatcher",
+    "digest_sender",
+    "campaign_distribution",
+    "job_runner",
+];
+
+/// Shared pause switches for background workers. Paused workers skip their
+/// iterations until resumed. On shutdown every worker finishes the iteration
+/// it's in and returns. With leader election, only the instance leading a
+/// worker runs it.
+#[derive(Clone, Default)]
+pub struct WorkerControl {
+    paused: Arc<RwLock<HashSet<String>>>,
+    shutdown: CancellationToken,
+    tasks: TaskTracker,
+    leadership: Option<Leadership>,
+}
+
+impl WorkerControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Elect one leader per worker across every instance sharing `pool`
+    pub fn with_leader_election(mut self, pool: PgPool) -> Self {
+        self.leadership = Some(Leadership::new(pool));
+        self
+    }
+
+    pub fn is_known(name: &str) -> bool {
+        WORKER_NAMES.contains(&name)
+    }
+
+    pub fn pause(&self, name: &str) {
+        self.paused.write().unwrap().insert(name.to_string());
+    }
+
+    pub fn resume(&self, name: &str) {
+        self.paused.write().unwrap().remove(name);
+    }
+
+    pub fn is_paused(&self, name: &str) -> bool {
+        self.paused.read().unwrap().contains(name)
+    }
+
+    pub fn paused(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.paused.read().unwrap().iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Whether this instance should run `name`: it leads it, or there's no
+    /// leader election
+    pub async fn leads(&self, name: &str) -> bool {
+        match &self.leadership {
+            Some(leadership) => leadership.lead(name).await,
+            None => true,
+        }
+    }
+
+    /// Workers this instance leads; `None` without leader election
+    pub async fn led(&self) -> Option<Vec<String>> {
+        match &self.leadership {
+            Some(leadership) => Some(leadership.held().await),
+            None => None,
+        }
+    }
+
+    /// Hand every worker this instance leads over to the other instances
+    pub async fn release_leadership(&self) {
+        if let Some(leadership) = &self.leadership {
+            leadership.release().await;
+        }
+    }
+
+    /// Run a worker on its own task, which shutdown waits for
+    pub fn spawn<F>(&self, worker: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(worker);
+    }
+
+    /// Wait out the interval between iterations. Returns false once
+    /// shutdown is requested, when the worker should return.
+    pub async fn idle(&self, interval: Duration) -> bool {
+        tokio::select! {
+            _ = self.shutdown.cancelled() => false,
+            _ = tokio::time::sleep(interval) => true,
+        }
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    /// Resolves once shutdown is requested, for workers waiting on streams
+    pub async fn stopping(&self) {
+        self.shutdown.cancelled().await
+    }
+
+    /// Tell every worker to stop after its current iteration
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+        self.tasks.close();
+    }
+
+    /// Wait up to `grace` for the workers to stop, returning how many were
+    /// still running
+    pub async fn wait(&self, grace: Duration) -> usize {
+        self.tasks.close();
+        let _ = tokio::time::timeout(grace, self.tasks.wait()).await;
+        self.tasks.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_and_resume() {
+        let control = WorkerControl::new();
+        let shared = control.clone();
+
+        control.pause("wallet_sync");
+        assert!(shared.is_paused("wallet_sync"));
+        assert!(!shared.is_paused("analytics"));
+        assert_eq!(shared.paused(), vec!["wallet_sync".to_string()]);
+
+        shared.resume("wallet_sync");
+        assert!(!control.is_paused("wallet_sync"));
+        assert!(WorkerControl::is_known("escrow_sweeper"));
+        assert!(!WorkerControl::is_known("unknown"));
+    }
+
+    #[tokio::test]
+    async fn test_leads_everything_without_election() {
+        let control = WorkerControl::new();
+        assert!(control.leads("campaign_matching").await);
+        assert_eq!(control.led().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_ends_idle_workers() {
+        let control = WorkerControl::new();
+        let worker = control.clone();
+        control.spawn(async move {
+            while worker.idle(Duration::from_secs(3600)).await {}
+        });
+        assert!(!control.is_shutting_down());
+
+        control.shutdown();
+        assert!(control.is_shutting_down());
+        assert_eq!(control.wait(Duration::from_secs(5)).await, 0);
+        assert!(!control.idle(Duration::from_secs(3600)).await);
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use chrono::Utc;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::control::WorkerControl;
+use crate::services::{digests, worker_heartbeats};
+
+/// Digests queued per run; the rest wait for the next
+const DIGEST_BATCH: i64 = 100;
+
+/// Emails users their daily or weekly notification digest once their send
+/// hour comes
+pub struct DigestSender {
+    pool: PgPool,
+    dry_run: bool,
+    interval: Duration,
+    control: WorkerControl,
+}
+
+impl DigestSender {
+    pub fn new(pool: PgPool, dry_run: bool, control: WorkerControl) -> Self {
+        let interval_secs = std::env::var("DIGEST_SENDER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(300);
+        Self { pool, dry_run, interval: Duration::from_secs(interval_secs), control }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        loop {
+            if self.control.is_paused("digest_sender") {
+                tracing::info!("Digest sender paused, skipping run");
+            } else if !self.control.leads("digest_sender").await {
+                tracing::debug!("Another instance leads digest_sender, skipping run");
+            } else {
+                let outcome = self.run_once().await;
+                if let Err(e) = &outcome {
+                    tracing::error!("Digest sender error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "digest_sender", &outcome).await;
+            }
+
+            if !self.control.idle(self.interval).await {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn run_once(&self) -> Result<usize> {
+        if self.dry_run {
+            tracing::info!("[dry-run] Would queue due notification digests");
+            return Ok(0);
+        }
+        let queued = digests::queue_due(&self.pool, Utc::now(), DIGEST_BATCH).await?;
+        if queued > 0 {
+            tracing::info!("Queued {} notification digest emails", queued);
+        }
+        Ok(queued)
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::config::{self, StellarNetwork};
+use crate::services::contract_client::ContractClient;
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::state::Notifier;
+use crate::utils::money::Stroops;
+
+/// Totals for one project from the database and the escrow contract
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EscrowTotals {
+    pub onchain_balance: Stroops,
+    pub deposits: Stroops,
+    pub releases: Stroops,
+    pub donations: Stroops,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drift {
+    /// On-chain balance minus what deposits and releases say it should be
+    pub contract: Stroops,
+    /// Confirmed donations that never became deposits (negative: deposits without donations)
+    pub donations: Stroops,
+    pub exceeds_threshold: bool,
+}
+
+impl EscrowTotals {
+    pub fn drift(&self, threshold: Stroops) -> Drift {
+        let contract = Stroops::from_stroops(
+            self.onchain_balance.as_stroops() - (self.deposits.as_stroops() - self.releases.as_stroops()),
+        );
+        let donations = Stroops::from_stroops(self.donations.as_stroops() - self.deposits.as_stroops());
+        let exceeds_threshold = contract.as_stroops().abs() > threshold.as_stroops()
+            || donations.as_stroops().abs() > threshold.as_stroops();
+        Drift { contract, donations, exceeds_threshold }
+    }
+}
+
+/// Compares each project's `contract_deposits`/`contract_releases` totals and
+/// confirmed donations against the funding escrow contract's live balance,
+/// records a `reconciliation_reports` row per project, and notifies admins
+/// when drift first crosses the threshold (or changes while above it). Runs
+/// as the job runner's hourly `reconcile_escrow` job.
+pub struct EscrowReconciler {
+    pool: PgPool,
+    network: StellarNetwork,
+    notifier: Notifier,
+    dry_run: bool,
+}
+
+impl EscrowReconciler {
+    pub fn new(pool: PgPool, network: StellarNetwork, notifier: Notifier, dry_run: bool) -> Self {
+        Self { pool, network, notifier, dry_run }
+    }
+
+    pub async fn reconcile(&self) -> Result<()> {
+        let mut client = ContractClient::new(self.pool.clone(), self.network);
+        client.load_contracts().await?;
+        let threshold = config::reconciliation_drift_threshold();
+
+        let projects = sqlx::query!(
+            r#"
+            SELECT p.id, p.title,
+                   COALESCE((SELECT SUM(amount_stroops) FROM contract_deposits WHERE project_id = p.id), 0)::BIGINT as "deposits!",
+                   COALESCE((SELECT SUM(amount_stroops) FROM contract_releases WHERE project_id = p.id), 0)::BIGINT as "releases!",
+                   COALESCE((SELECT SUM(amount) FROM donations WHERE project_id = p.id AND status = 'confirmed'), 0) as "donations!: Stroops"
+            FROM projects p
+            WHERE EXISTS (SELECT 1 FROM contract_deposits WHERE project_id = p.id)
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for project in projects {
+            let onchain_balance = match client.get_onchain_project_balance(project.id).await {
+                Ok(Some(balance)) => balance,
+                Ok(None) => {
+                    info!("Soroban RPC not configured, skipping escrow reconciliation");
+                    return Ok(());
+                }
+                Err(e) => {
+                    warn!("Failed to read escrow balance for project {}: {}", project.id, e);
+                    continue;
+                }
+            };
+
+            let totals = EscrowTotals {
+                onchain_balance: Stroops::from_stroops(onchain_balance),
+                deposits: Stroops::from_stroops(project.deposits),
+                releases: Stroops::from_stroops(project.releases),
+                donations: project.donations,
+            };
+            let drift = totals.drift(threshold);
+
+            if self.dry_run {
+                info!("[dry-run] Would record reconciliation for project {}: {:?}", project.id, drift);
+                continue;
+            }
+            if let Err(e) = self.record(project.id, &project.title, &totals, &drift).await {
+                error!("Failed to record reconciliation for project {}: {}", project.id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn record(&self, project_id: Uuid, title: &str, totals: &EscrowTotals, drift: &Drift) -> Result<()> {
+        let previous = sqlx::query!(
+            r#"
+            SELECT contract_drift_stroops, donation_drift_stroops, exceeds_threshold
+            FROM reconciliation_reports
+            WHERE project_id = $1
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+            project_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO reconciliation_reports (
+                project_id, onchain_balance_stroops, deposits_stroops, releases_stroops, donations_stroops,
+                contract_drift_stroops, donation_drift_stroops, exceeds_threshold
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            project_id,
+            totals.onchain_balance.as_stroops(),
+            totals.deposits.as_stroops(),
+            totals.releases.as_stroops(),
+            totals.donations.as_stroops(),
+            drift.contract.as_stroops(),
+            drift.donations.as_stroops(),
+            drift.exceeds_threshold
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Only alert on a new or changed discrepancy, not every run it persists
+        let already_reported = previous.is_some_and(|p| {
+            p.exceeds_threshold
+                && p.contract_drift_stroops == drift.contract.as_stroops()
+                && p.donation_drift_stroops == drift.donations.as_stroops()
+        });
+        if !drift.exceeds_threshold || already_reported {
+            return Ok(());
+        }
+
+        warn!(
+            "Escrow drift for project {}: contract {} XLM, donations {} XLM",
+            project_id, drift.contract, drift.donations
+        );
+
+        let metadata = serde_json::json!({
+            "project_id": project_id,
+            "onchain_balance": totals.onchain_balance,
+            "deposits": totals.deposits,
+            "releases": totals.releases,
+            "donations": totals.donations,
+            "contract_drift": drift.contract,
+            "donation_drift": drift.donations,
+        });
+        let event = NotificationEvent::EscrowDrift { project_id, contract_drift: drift.contract };
+        sqlx::query!(
+            r#"
+            INSERT INTO notifications (user_id, notification_type, title, message, metadata, event)
+            SELECT id, 'system', $1, $2, $3, $4
+            FROM users
+            WHERE role = 'admin'
+            "#,
+            "Escrow reconciliation drift",
+            format!(
+                "Escrow for \"{}\" is off by {} XLM against the contract and {} XLM against donations",
+                title, drift.contract, drift.donations
+            ),
+            metadata,
+            event.to_json()
+        )
+        .execute(&self.pool)
+        .await?;
+        self.notifier.send(Channel::Admins, event);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xlm(amount: &str) -> Stroops {
+        amount.parse().unwrap()
+    }
+
+    #[test]
+    fn test_drift_within_threshold() {
+        let totals = EscrowTotals {
+            onchain_balance: xlm("70"),
+            deposits: xlm("100"),
+            releases: xlm("30"),
+            donations: xlm("100.5"),
+        };
+        let drift = totals.drift(xlm("1"));
+        assert_eq!(drift.contract, Stroops::ZERO);
+        assert_eq!(drift.donations, xlm("0.5"));
+        assert!(!drift.exceeds_threshold);
+    }
+
+    #[test]
+    fn test_drift_exceeds_threshold_either_way() {
+        let short = EscrowTotals {
+            onchain_balance: xlm("60"),
+            deposits: xlm("100"),
+            releases: xlm("30"),
+            donations: xlm("100"),
+        };
+        let drift = short.drift(xlm("1"));
+        assert_eq!(drift.contract, xlm("-10"));
+        assert!(drift.exceeds_threshold);
+
+        let undeposited = EscrowTotals { donations: xlm("120"), onchain_balance: xlm("70"), ..short };
+        let drift = undeposited.drift(xlm("1"));
+        assert_eq!(drift.contract, Stroops::ZERO);
+        assert_eq!(drift.donations, xlm("20"));
+        assert!(drift.exceeds_threshold);
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use super::control::WorkerControl;
+use crate::services::escrow::{self, SecretSealer, ESCROW_MIN_RESERVE};
+use crate::services::{project_refunds, stellar::StellarService, worker_heartbeats, NewStellarService};
+use crate::utils::money::Stroops;
+use uuid::Uuid;
+
+/// Reconciles per-project escrow accounts against the ledger, sweeps
+/// leftover funds from completed projects back to the platform wallet and
+/// returns rejected projects' donations
+pub struct EscrowSweeper {
+    pool: PgPool,
+    stellar: StellarService,
+    stellar_service: NewStellarService,
+    dry_run: bool,
+    control: WorkerControl,
+}
+
+impl EscrowSweeper {
+    pub fn new(
+        pool: PgPool,
+        stellar: StellarService,
+        stellar_service: NewStellarService,
+        dry_run: bool,
+        control: WorkerControl,
+    ) -> Self {
+        Self { pool, stellar, stellar_service, dry_run, control }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        if !self.dry_run {
+            match SecretSealer::from_env() {
+                Ok(sealer) => match escrow::seal_plaintext_secrets(&self.pool, &sealer).await {
+                    Ok(0) => {}
+                    Ok(sealed) => info!("Sealed {} plaintext escrow secrets", sealed),
+                    Err(e) => error!("Failed to seal plaintext escrow secrets: {}", e),
+                },
+                Err(e) => error!("Escrow secrets can't be read: {}", e),
+            }
+        }
+
+        loop {
+            if self.control.is_paused("escrow_sweeper") {
+                info!("Escrow sweeper paused, skipping run");
+            } else if !self.control.leads("escrow_sweeper").await {
+                tracing::debug!("Another instance leads escrow_sweeper, skipping run");
+            } else {
+                let reconciled = self.reconcile_accounts().await;
+                if let Err(e) = &reconciled {
+                    error!("Escrow reconciliation error: {}", e);
+                }
+
+                let swept = self.sweep_closed_projects().await;
+                if let Err(e) = &swept {
+                    error!("Escrow sweep error: {}", e);
+                }
+
+                let outcome = match (reconciled, swept) {
+                    (Ok(reconciled), Ok(swept)) => Ok(reconciled + swept),
+                    (Err(e), _) | (_, Err(e)) => Err(e),
+                };
+                worker_heartbeats::record(&self.pool, "escrow_sweeper", &outcome).await;
+            }
+
+            // Run every 15 minutes
+            if !self.control.idle(Duration::from_secs(900)).await {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Compare each escrow account's on-chain balance with confirmed donations
+    /// minus released milestone funds, returning how many were recorded
+    async fn reconcile_accounts(&self) -> Result<usize> {
+        let accounts = sqlx::query!(
+            r#"
+            SELECT e.id, e.project_id, e.public_key,
+                   COALESCE((SELECT SUM(d.amount) FROM donations d
+                             WHERE d.project_id = e.project_id AND d.status = 'confirmed'), 0) as "donated!: Stroops",
+                   COALESCE((SELECT SUM(r.amount_stroops) FROM contract_releases r
+                             WHERE r.project_id = e.project_id), 0)::BIGINT as "released_stroops!"
+            FROM project_escrow_accounts e
+            WHERE e.swept_at IS NULL
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut reconciled = 0;
+        for account in accounts {
+            let balance = match self.stellar.fetch_wallet_balance(&account.public_key).await {
+                Ok(b) => b.xlm,
+                Err(e) => {
+                    warn!("Failed to fetch balance for escrow {}: {}", account.public_key, e);
+                    continue;
+                }
+            };
+
+            let expected = account.donated - Stroops::from_stroops(account.released_stroops);
+
+            // On-chain balance also holds the starting reserve, so only flag shortfalls
+            if balance < expected {
+                warn!(
+                    "Escrow {} for project {} is short: on-chain {} XLM, expected {} XLM",
+                    account.public_key, account.project_id, balance, expected
+                );
+            }
+
+            if self.dry_run {
+                info!("[dry-run] Would record escrow {} balance {} (expected {})", account.public_key, balance, expected);
+                continue;
+            }
+
+            sqlx::query!(
+                r#"
+                UPDATE project_escrow_accounts
+                SET last_balance = $1, expected_balance = $2, last_reconciled_at = NOW()
+                WHERE id = $3
+                "#,
+                balance.to_decimal(),
+                expected.to_decimal(),
+                account.id
+            )
+            .execute(&self.pool)
+            .await?;
+            reconciled += 1;
+        }
+
+        Ok(reconciled)
+    }
+
+    /// Settle escrows of completed or rejected projects, returning how many
+    /// were settled: a completed project's leftover funds are swept to the
+    /// platform wallet, a rejected project's donations go back to its donors
+    async fn sweep_closed_projects(&self) -> Result<usize> {
+        let accounts = sqlx::query!(
+            r#"
+            SELECT e.id, e.project_id, e.public_key, e.secret_key, p.status
+            FROM project_escrow_accounts e
+            JOIN projects p ON p.id = e.project_id
+            WHERE e.swept_at IS NULL
+            AND p.status IN ('completed', 'rejected')
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        if accounts.is_empty() {
+            return Ok(0);
+        }
+
+        let sealer = SecretSealer::from_env()?;
+        let platform_address = std::env::var("PLATFORM_WALLET_PUBLIC_KEY").ok().filter(|a| !a.is_empty());
+
+        let mut swept = 0;
+        for account in accounts {
+            let balance = match self.stellar.fetch_wallet_balance(&account.public_key).await {
+                Ok(b) => b.xlm,
+                Err(e) => {
+                    warn!("Failed to fetch balance for escrow {}: {}", account.public_key, e);
+                    continue;
+                }
+            };
+
+            let sweep_amount = balance - ESCROW_MIN_RESERVE;
+            if !sweep_amount.is_positive() {
+                continue;
+            }
+
+            let secret = match sealer.open(&account.secret_key) {
+                Ok(secret) => secret,
+                Err(e) => {
+                    error!("Can't use escrow {} (project {}): {}", account.public_key, account.project_id, e);
+                    continue;
+                }
+            };
+
+            let tx_hash = if account.status == "rejected" {
+                if !self.refund_donors(account.project_id, &secret, sweep_amount).await? {
+                    continue;
+                }
+                None
+            } else {
+                let Some(platform_address) = &platform_address else {
+                    warn!("PLATFORM_WALLET_PUBLIC_KEY is not set; leaving escrow {} unswept", account.public_key);
+                    continue;
+                };
+
+                if self.dry_run {
+                    info!(
+                        "[dry-run] Would sweep {} XLM from escrow {} (project {}) to platform wallet",
+                        sweep_amount, account.public_key, account.project_id
+                    );
+                    continue;
+                }
+
+                let memo = format!("sweep:{}", &account.project_id.simple().to_string()[..20]);
+                let tx_hash = self
+                    .stellar_service
+                    .send_payment(&secret, platform_address, &sweep_amount.to_string(), Some(&memo))
+                    .await?;
+                info!("Swept {} XLM from escrow {} (tx {})", sweep_amount, account.public_key, tx_hash);
+                Some(tx_hash)
+            };
+
+            sqlx::query!(
+                r#"
+                UPDATE project_escrow_accounts
+                SET swept_at = NOW(), sweep_tx_hash = $1
+                WHERE id = $2
+                "#,
+                tx_hash,
+                account.id
+            )
+            .execute(&self.pool)
+            .await?;
+            swept += 1;
+        }
+
+        Ok(swept)
+    }
+
+    /// Return a rejected project's confirmed donations to the accounts that
+    /// sent them, pro rata when the escrow holds less than was given.
+    /// Returns whether every donation has been returned.
+    async fn refund_donors(&self, project_id: Uuid, secret: &str, available: Stroops) -> Result<bool> {
+        let donations = sqlx::query!(
+            r#"
+            SELECT id, tx_hash as "tx_hash!", amount as "amount: Stroops"
+            FROM donations
+            WHERE project_id = $1 AND status = 'confirmed' AND tx_hash IS NOT NULL
+            ORDER BY created_at, id
+            "#,
+            project_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let amounts: Vec<i64> = donations.iter().map(|d| d.amount.as_stroops()).collect();
+        let mut all_returned = true;
+        for (donation, share) in donations.iter().zip(project_refunds::refund_shares(&amounts, available.as_stroops())) {
+            let share = Stroops::from_stroops(share);
+            if !share.is_positive() {
+                continue;
+            }
+
+            let donor = match self.stellar.fetch_transaction_details(&donation.tx_hash).await {
+                Ok(tx) => tx.source_account,
+                Err(e) => {
+                    warn!("Can't find the sender of donation {} ({}): {}", donation.id, donation.tx_hash, e);
+                    all_returned = false;
+                    continue;
+                }
+            };
+
+            if self.dry_run {
+                info!("[dry-run] Would return {} XLM of donation {} to {}", share, donation.id, donor);
+                all_returned = false;
+                continue;
+            }
+
+            let memo = format!("refund:{}", &donation.id.simple().to_string()[..20]);
+            match self.stellar_service.send_payment(secret, &donor, &share.to_string(), Some(&memo)).await {
+                Ok(tx_hash) => {
+                    sqlx::query!("UPDATE donations SET status = 'refunded' WHERE id = $1", donation.id)
+                        .execute(&self.pool)
+                        .await?;
+                    info!("Returned {} XLM of donation {} to {} (tx {})", share, donation.id, donor, tx_hash);
+                }
+                Err(e) => {
+                    warn!("Failed to return donation {} to {}: {}", donation.id, donor, e);
+                    all_returned = false;
+                }
+            }
+        }
+
+        Ok(all_returned)
+    }
+}
@@ -0,0 +1,383 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::time::Duration;
+use stellar_xdr::curr::{Limits, ReadXdr, ScVal};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use super::control::WorkerControl;
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::soroban_rpc::{self, RpcEvent, SorobanRpc};
+use crate::services::worker_heartbeats;
+use crate::state::Notifier;
+use crate::utils::money::Stroops;
+
+/// Contracts whose events are indexed
+const INDEXED_CONTRACTS: &[&str] = &["funding_escrow", "milestone_manager"];
+/// Row in `indexer_cursors` holding the last processed getEvents cursor
+const CURSOR_NAME: &str = "soroban_events";
+const PAGE_LIMIT: u32 = 100;
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Structured fields pulled out of a contract event's topics and value
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodedEvent {
+    pub event_type: String,
+    pub project_id: Option<Uuid>,
+    pub milestone_id: Option<String>,
+    pub address: Option<String>,
+    pub amount_stroops: Option<i64>,
+    pub memo: Option<String>,
+}
+
+/// Project uuid from a contract project key (uuid bytes, zero padded)
+fn project_from_key(value: &ScVal) -> Option<Uuid> {
+    match value {
+        ScVal::Bytes(bytes) if bytes.0.len() == 32 => Uuid::from_slice(&bytes.0.as_slice()[..16]).ok(),
+        _ => None,
+    }
+}
+
+/// Milestone id string from a contract milestone key (string bytes, zero padded)
+fn milestone_from_key(value: &ScVal) -> Option<String> {
+    match value {
+        ScVal::Bytes(bytes) => {
+            let bytes = bytes.0.as_slice();
+            let end = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+            Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+        }
+        _ => None,
+    }
+}
+
+/// Decode the `deposit` and `released` events; anything else keeps only its name
+pub fn decode_event(event: &RpcEvent) -> Result<DecodedEvent> {
+    let topics = event
+        .topic
+        .iter()
+        .map(|t| ScVal::from_xdr_base64(t, Limits::none()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let value = ScVal::from_xdr_base64(&event.value, Limits::none())?;
+
+    let event_type = match topics.first() {
+        Some(ScVal::Symbol(name)) => name.0.to_utf8_string_lossy(),
+        _ => "unknown".to_string(),
+    };
+    let mut decoded = DecodedEvent {
+        project_id: topics.get(1).and_then(project_from_key),
+        event_type,
+        ..Default::default()
+    };
+
+    match decoded.event_type.as_str() {
+        "deposit" => {
+            let ScVal::Vec(Some(fields)) = &value else {
+                return Err(anyhow!("Malformed deposit event {}", event.id));
+            };
+            if fields.len() < 2 {
+                return Err(anyhow!("Malformed deposit event {}", event.id));
+            }
+            decoded.address = Some(soroban_rpc::address_from_val(&fields[0])?);
+            decoded.amount_stroops = Some(i64::try_from(soroban_rpc::i128_from_val(&fields[1])?)?);
+            decoded.memo = match fields.get(2) {
+                Some(ScVal::String(memo)) => Some(memo.0.to_utf8_string_lossy()).filter(|m| !m.is_empty()),
+                _ => None,
+            };
+        }
+        "released" => {
+            decoded.milestone_id = topics.get(2).and_then(milestone_from_key);
+            decoded.amount_stroops = Some(i64::try_from(soroban_rpc::i128_from_val(&value)?)?);
+        }
+        _ => {}
+    }
+
+    Ok(decoded)
+}
+
+/// Polls soroban-rpc for escrow and milestone contract events, stores them in
+/// `onchain_events`, applies deposits and releases to the contract tables, and
+/// pushes them to SSE subscribers
+pub struct EventIndexer {
+    pool: PgPool,
+    rpc: SorobanRpc,
+    notifier: Notifier,
+    dry_run: bool,
+    control: WorkerControl,
+}
+
+impl EventIndexer {
+    pub fn new(pool: PgPool, rpc: SorobanRpc, notifier: Notifier, dry_run: bool, control: WorkerControl) -> Self {
+        Self { pool, rpc, notifier, dry_run, control }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        loop {
+            if self.control.is_paused("event_indexer") {
+                info!("Event indexer paused, skipping run");
+            } else if !self.control.leads("event_indexer").await {
+                tracing::debug!("Another instance leads event_indexer, skipping run");
+            } else {
+                let outcome = self.index_events().await;
+                if let Err(e) = &outcome {
+                    error!("Event indexing error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "event_indexer", &outcome).await;
+            }
+
+            if !self.control.idle(POLL_INTERVAL).await {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Index new contract events, returning how many were stored
+    async fn index_events(&self) -> Result<usize> {
+        let names: Vec<String> = INDEXED_CONTRACTS.iter().map(|n| n.to_string()).collect();
+        let contracts: HashMap<String, String> = sqlx::query!(
+            "SELECT name, address FROM contracts WHERE name = ANY($1) AND network = $2",
+            &names,
+            self.rpc.network().as_str()
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|c| (c.address, c.name))
+        .collect();
+
+        if contracts.is_empty() {
+            return Ok(0);
+        }
+        let addresses: Vec<String> = contracts.keys().cloned().collect();
+
+        let mut cursor = sqlx::query_scalar!("SELECT cursor FROM indexer_cursors WHERE name = $1", CURSOR_NAME)
+            .fetch_optional(&self.pool)
+            .await?;
+        // First run starts at the tip; earlier history is already in the contract tables
+        let mut start_ledger = match cursor {
+            Some(_) => None,
+            None => Some(self.rpc.latest_ledger().await?),
+        };
+
+        let mut indexed = 0;
+        loop {
+            let page = self
+                .rpc
+                .get_events(&addresses, start_ledger.take(), cursor.as_deref(), PAGE_LIMIT)
+                .await?;
+
+            for event in &page.events {
+                if !event.in_successful_contract_call {
+                    continue;
+                }
+                let contract_name = contracts.get(&event.contract_id).map(String::as_str).unwrap_or("unknown");
+                // Stop before moving the cursor so the page is retried next run
+                self.store_event(event, contract_name)
+                    .await
+                    .map_err(|e| anyhow!("Failed to index event {}: {}", event.id, e))?;
+                indexed += 1;
+            }
+
+            let next = page.cursor.clone().or_else(|| page.events.last().map(|e| e.id.clone()));
+            let Some(next) = next else { break };
+
+            if self.dry_run {
+                info!("[dry-run] Would advance event cursor to {}", next);
+                break;
+            }
+            sqlx::query!(
+                r#"
+                INSERT INTO indexer_cursors (name, cursor, updated_at)
+                VALUES ($1, $2, NOW())
+                ON CONFLICT (name) DO UPDATE SET cursor = EXCLUDED.cursor, updated_at = NOW()
+                "#,
+                CURSOR_NAME,
+                next
+            )
+            .execute(&self.pool)
+            .await?;
+            cursor = Some(next);
+
+            // The cursor is saved, so shutdown can stop between pages
+            if page.events.len() < PAGE_LIMIT as usize || self.control.is_shutting_down() {
+                break;
+            }
+        }
+
+        Ok(indexed)
+    }
+
+    async fn store_event(&self, event: &RpcEvent, contract_name: &str) -> Result<()> {
+        let decoded = decode_event(event).unwrap_or_else(|e| {
+            warn!("Storing event {} undecoded: {}", event.id, e);
+            DecodedEvent { event_type: "unknown".to_string(), ..Default::default() }
+        });
+        let closed_at = DateTime::parse_from_rfc3339(&event.ledger_closed_at)?.with_timezone(&Utc);
+
+        if self.dry_run {
+            info!("[dry-run] Would index {} event {} from {}", decoded.event_type, event.id, contract_name);
+            return Ok(());
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let inserted = sqlx::query!(
+            r#"
+            INSERT INTO onchain_events
+            (event_id, contract_name, contract_address, event_type, ledger, ledger_closed_at, tx_hash,
+             project_id, milestone_id, address, amount_stroops, topics, value)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, (SELECT id FROM projects WHERE id = $8), $9, $10, $11, $12, $13)
+            ON CONFLICT (event_id) DO NOTHING
+            "#,
+            event.id,
+            contract_name,
+            event.contract_id,
+            decoded.event_type,
+            event.ledger,
+            closed_at,
+            event.tx_hash,
+            decoded.project_id,
+            decoded.milestone_id,
+            decoded.address,
+            decoded.amount_stroops,
+            &event.topic,
+            event.value
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+
+        if !inserted {
+            return Ok(());
+        }
+
+        let notification = match (&decoded, decoded.event_type.as_str()) {
+            (
+                DecodedEvent { project_id: Some(project_id), address: Some(address), amount_stroops: Some(amount), .. },
+                "deposit",
+            ) => {
+                if let Some(tx_hash) = &event.tx_hash {
+                    sqlx::query!(
+                        r#"
+                        INSERT INTO contract_deposits (project_id, donor_address, amount_stroops, memo, tx_hash, block_number)
+                        SELECT $1, $2, $3, $4, $5, $6
+                        WHERE EXISTS (SELECT 1 FROM projects WHERE id = $1)
+                        ON CONFLICT (tx_hash) DO UPDATE SET block_number = EXCLUDED.block_number
+                        "#,
+                        project_id,
+                        address,
+                        amount,
+                        decoded.memo,
+                        tx_hash,
+                        event.ledger
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                Some((*project_id, NotificationEvent::OnchainDeposit { project_id: *project_id, amount: Stroops::from_stroops(*amount) }))
+            }
+            (
+                DecodedEvent { project_id: Some(project_id), milestone_id: Some(milestone_id), .. },
+                "released",
+            ) => {
+                sqlx::query!(
+                    r#"
+                    UPDATE contract_milestones
+                    SET released = true,
+                        released_at = COALESCE(released_at, $3),
+                        release_tx_hash = COALESCE(release_tx_hash, $4)
+                    WHERE project_id = $1 AND milestone_id = $2
+                    "#,
+                    project_id,
+                    milestone_id,
+                    closed_at,
+                    event.tx_hash
+                )
+                .execute(&mut *tx)
+                .await?;
+                Some((*project_id, NotificationEvent::OnchainRelease { project_id: *project_id, milestone_id: milestone_id.clone() }))
+            }
+            _ => None,
+        };
+
+        tx.commit().await?;
+
+        if let Some((project_id, event)) = notification {
+            self.notifier.send(Channel::Project(project_id), event);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stellar_xdr::curr::{ScSymbol, WriteXdr};
+
+    fn event(topics: Vec<ScVal>, value: ScVal) -> RpcEvent {
+        RpcEvent {
+            id: "0000000001-0000000001".to_string(),
+            ledger: 1,
+            ledger_closed_at: "2025-10-21T12:00:00Z".to_string(),
+            contract_id: "C".to_string(),
+            tx_hash: Some("ab".repeat(32)),
+            in_successful_contract_call: true,
+            topic: topics.iter().map(|t| t.to_xdr_base64(Limits::none()).unwrap()).collect(),
+            value: value.to_xdr_base64(Limits::none()).unwrap(),
+        }
+    }
+
+    fn symbol(name: &str) -> ScVal {
+        ScVal::Symbol(ScSymbol(name.try_into().unwrap()))
+    }
+
+    fn project_key(project_id: Uuid) -> ScVal {
+        let mut key = [0u8; 32];
+        key[..16].copy_from_slice(project_id.as_bytes());
+        soroban_rpc::bytes_val(&key).unwrap()
+    }
+
+    #[test]
+    fn test_decode_deposit() {
+        let project_id = Uuid::new_v4();
+        let donor = "GBRPYHIL2CI3FNQ4BXLFMNDLFJUNPU2HY3ZMFSHONUCEOASW7QC7OX2H";
+        let value = soroban_rpc::vec_val(vec![
+            soroban_rpc::address_val(donor).unwrap(),
+            soroban_rpc::i128_val(50_000_000),
+            soroban_rpc::string_val("thanks").unwrap(),
+        ])
+        .unwrap();
+
+        let decoded = decode_event(&event(vec![symbol("deposit"), project_key(project_id)], value)).unwrap();
+        assert_eq!(decoded.event_type, "deposit");
+        assert_eq!(decoded.project_id, Some(project_id));
+        assert_eq!(decoded.address.as_deref(), Some(donor));
+        assert_eq!(decoded.amount_stroops, Some(50_000_000));
+        assert_eq!(decoded.memo.as_deref(), Some("thanks"));
+    }
+
+    #[test]
+    fn test_decode_release() {
+        let project_id = Uuid::new_v4();
+        let mut milestone = [0u8; 32];
+        milestone[..4].copy_from_slice(b"ms-1");
+        let topics = vec![symbol("released"), project_key(project_id), soroban_rpc::bytes_val(&milestone).unwrap()];
+
+        let decoded = decode_event(&event(topics, soroban_rpc::i128_val(1_000))).unwrap();
+        assert_eq!(decoded.project_id, Some(project_id));
+        assert_eq!(decoded.milestone_id.as_deref(), Some("ms-1"));
+        assert_eq!(decoded.amount_stroops, Some(1_000));
+    }
+
+    #[test]
+    fn test_decode_other_and_malformed() {
+        let other = decode_event(&event(vec![symbol("claim")], ScVal::Void)).unwrap();
+        assert_eq!(other.event_type, "claim");
+        assert_eq!(other.amount_stroops, None);
+
+        assert!(decode_event(&event(vec![symbol("deposit")], ScVal::Void)).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::control::WorkerControl;
+use crate::services::malware_scan::{self, MalwareScanner, Verdict};
+use crate::services::storage::ObjectStorage;
+use crate::services::worker_heartbeats;
+
+/// Files scanned per run
+const SCAN_BATCH: i64 = 20;
+
+/// Scans new uploads for malware, clearing them for download or quarantining
+/// them
+pub struct FileScanner {
+    pool: PgPool,
+    storage: ObjectStorage,
+    scanner: MalwareScanner,
+    dry_run: bool,
+    interval: Duration,
+    control: WorkerControl,
+}
+
+impl FileScanner {
+    pub fn new(
+        pool: PgPool,
+        storage: ObjectStorage,
+        scanner: MalwareScanner,
+        dry_run: bool,
+        control: WorkerControl,
+    ) -> Self {
+        let interval_secs = std::env::var("FILE_SCANNER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(30);
+        Self {
+            pool,
+            storage,
+            scanner,
+            dry_run,
+            interval: Duration::from_secs(interval_secs),
+            control,
+        }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        loop {
+            if self.control.is_paused("file_scanner") {
+                tracing::info!("File scanner paused, skipping run");
+            } else if !self.control.leads("file_scanner").await {
+                tracing::debug!("Another instance leads file_scanner, skipping run");
+            } else {
+                let outcome = self.run_once().await;
+                if let Err(e) = &outcome {
+                    eprintln!("File scanner error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "file_scanner", &outcome).await;
+            }
+
+            if !self.control.idle(self.interval).await {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn run_once(&self) -> Result<usize> {
+        let pending = malware_scan::pending(&self.pool, SCAN_BATCH).await?;
+        if pending.is_empty() {
+            return Ok(0);
+        }
+        if self.dry_run {
+            tracing::info!("[dry-run] Would scan {} uploaded files with {}", pending.len(), self.scanner.name());
+            return Ok(0);
+        }
+
+        let mut scanned = 0;
+        for (file_id, key) in pending {
+            match malware_scan::scan_file(&self.pool, &self.storage, &self.scanner, file_id, &key).await {
+                Ok(Some(Verdict::Clean)) => {
+                    tracing::info!("File {} scanned clean", file_id);
+                    scanned += 1;
+                }
+                Ok(Some(Verdict::Infected(_))) => scanned += 1,
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to record scan of file {}: {}", file_id, e),
+            }
+        }
+        Ok(scanned)
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::control::WorkerControl;
+use super::escrow_reconciler::EscrowReconciler;
+use super::payment_reconciler::PaymentReconciler;
+use crate::services::email::{self, EmailProvider};
+use crate::services::jobs::{self, ClaimedJob, Job};
+use crate::services::stellar_tx::TxSubmitter;
+use crate::services::worker_heartbeats;
+
+/// Most jobs run per pass
+const RUN_BATCH: i64 = 20;
+/// Days succeeded jobs are kept for inspection
+const KEEP_SUCCEEDED_DAYS: i32 = 7;
+
+/// Jobs queued on a schedule, and how often
+const RECURRING: &[(Job, Duration)] = &[
+    (Job::QueryStuckPayments, Duration::from_secs(300)),
+    (Job::SettlePayments, Duration::from_secs(300)),
+    (Job::ReconcileEscrow, Duration::from_secs(60 * 60)),
+];
+
+/// Every kind of job, for claiming
+const KINDS: &[Job] = &[
+    Job::SendEmail { email_id: uuid::Uuid::nil() },
+    Job::DistributeCampaignFunds { requested_by: None, campaign_id: None },
+    Job::SettlePayments,
+    Job::QueryStuckPayments,
+    Job::ReconcileEscrow,
+];
+
+/// What the runner's jobs run with
+pub struct JobHandlers {
+    /// `None` leaves emails queued
+    pub email: Option<Arc<dyn EmailProvider>>,
+    pub payments: Option<TxSubmitter>,
+    pub payment_reconciler: PaymentReconciler,
+    pub escrow_reconciler: EscrowReconciler,
+}
+
+/// Runs the `jobs` queue: due jobs are claimed, run and retried with backoff
+/// until they succeed or are dead-lettered. Recurring jobs are queued once
+/// per period however many instances are running. Claims skip locked rows,
+/// so every instance runs jobs without electing a leader.
+pub struct JobRunner {
+    pool: PgPool,
+    handlers: JobHandlers,
+    dry_run: bool,
+    interval: Duration,
+    control: WorkerControl,
+}
+
+impl JobRunner {
+    pub fn new(pool: PgPool, handlers: JobHandlers, dry_run: bool, control: WorkerControl) -> Self {
+        let interval_secs = std::env::var("JOB_RUNNER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5);
+        Self { pool, handlers, dry_run, interval: Duration::from_secs(interval_secs), control }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        loop {
+            if self.control.is_paused("job_runner") {
+                tracing::info!("Job runner paused, skipping run");
+            } else {
+                if let Err(e) = self.schedule_recurring().await {
+                    tracing::error!("Failed to queue recurring jobs: {}", e);
+                }
+                let outcome = self.run_once().await;
+                if let Err(e) = &outcome {
+                    tracing::error!("Job runner error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "job_runner", &outcome).await;
+            }
+
+            if !self.control.idle(self.interval).await {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn schedule_recurring(&self) -> Result<()> {
+        let now = Utc::now();
+        for (job, every) in RECURRING {
+            let key = jobs::recurring_key(job, *every, now);
+            jobs::enqueue(&self.pool, job, now, Some(&key)).await?;
+        }
+        Ok(())
+    }
+
+    /// Kinds that can run now: not paused, and emails only with a provider
+    /// outside dry runs
+    fn runnable_kinds(&self) -> Vec<&'static str> {
+        KINDS
+            .iter()
+            .filter(|job| !self.control.is_paused(job.worker()))
+            .filter(|job| !matches!(job, Job::SendEmail { .. }) || (self.handlers.email.is_some() && !self.dry_run))
+            .map(|job| job.kind())
+            .collect()
+    }
+
+    /// Run the due jobs, returning how many succeeded. Each job also beats
+    /// the heartbeat of the worker it stands in for.
+    async fn run_once(&self) -> Result<usize> {
+        let kinds = self.runnable_kinds();
+        if kinds.is_empty() {
+            return Ok(0);
+        }
+
+        let mut succeeded = 0;
+        for _ in 0..RUN_BATCH {
+            let Some(claimed) = jobs::claim_next(&self.pool, &kinds).await? else {
+                break;
+            };
+            let outcome = match &claimed.job {
+                Ok(job) => {
+                    let outcome = self.run(job, &claimed).await.map(|()| 1);
+                    worker_heartbeats::record(&self.pool, job.worker(), &outcome).await;
+                    outcome
+                }
+                Err(e) => Err(anyhow!("Unreadable {} job: {}", claimed.kind, e)),
+            };
+            match outcome {
+                Ok(_) => {
+                    jobs::mark_succeeded(&self.pool, claimed.id).await?;
+                    succeeded += 1;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Job {} ({}) failed (attempt {} of {}): {}",
+                        claimed.id,
+                        claimed.kind,
+                        claimed.attempts,
+                        claimed.max_attempts,
+                        e
+                    );
+                    if jobs::mark_failed(&self.pool, &claimed, &e.to_string()).await? {
+                        tracing::error!("Job {} ({}) dead-lettered after {} attempts", claimed.id, claimed.kind, claimed.attempts);
+                    }
+                }
+            }
+        }
+
+        let pruned = jobs::prune_succeeded(&self.pool, KEEP_SUCCEEDED_DAYS).await?;
+        if pruned > 0 {
+            tracing::debug!("Pruned {} finished jobs", pruned);
+        }
+        Ok(succeeded)
+    }
+
+    async fn run(&self, job: &Job, claimed: &ClaimedJob) -> Result<()> {
+        match job {
+            Job::SendEmail { email_id } => {
+                let provider = self.handlers.email.as_ref().ok_or_else(|| anyhow!("No email provider is configured"))?;
+                email::send_queued(&self.pool, provider.as_ref(), *email_id, claimed.is_last_attempt()).await
+            }
+            Job::DistributeCampaignFunds { requested_by, campaign_id } => {
+                tracing::info!("Distributing campaign funds (requested by {:?})", requested_by);
+                super::distribute_campaign_funds(&self.pool, self.handlers.payments.as_ref(), *campaign_id, self.dry_run).await
+            }
+            Job::SettlePayments => self.handlers.payment_reconciler.reconcile_payments().await,
+            Job::QueryStuckPayments => self.handlers.payment_reconciler.query_stuck_mpesa_payments().await,
+            Job::ReconcileEscrow => self.handlers.escrow_reconciler.reconcile().await,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use sqlx::{Connection, PgConnection, PgPool};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// First half of every worker lock's key, keeping them apart from other
+/// advisory locks ("FUND")
+const LOCK_NAMESPACE: i32 = 0x4655_4e44;
+
+#[derive(Default)]
+struct Session {
+    conn: Option<PgConnection>,
+    held: HashSet<String>,
+}
+
+/// Leader election per worker over Postgres advisory locks, so replicas
+/// don't both run the same periodic work. The locks live on one connection
+/// kept outside the pool: when the leading instance dies or loses the
+/// database, Postgres drops its locks and another instance's next try
+/// takes over.
+#[derive(Clone)]
+pub struct Leadership {
+    pool: PgPool,
+    session: Arc<Mutex<Session>>,
+}
+
+impl Leadership {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, session: Arc::new(Mutex::new(Session::default())) }
+    }
+
+    /// Whether this instance leads `worker`, taking the lock when it's free.
+    /// Errors count as not leading, so a worker never runs unsure.
+    pub async fn lead(&self, worker: &str) -> bool {
+        let mut session = self.session.lock().await;
+        match self.try_lead(&mut session, worker).await {
+            Ok(leads) => leads,
+            Err(e) => {
+                tracing::warn!("Leader election for {} failed: {}", worker, e);
+                // Whatever locks there were went with the connection
+                session.conn = None;
+                session.held.clear();
+                false
+            }
+        }
+    }
+
+    async fn try_lead(&self, session: &mut Session, worker: &str) -> Result<bool> {
+        if session.conn.is_none() {
+            session.conn = Some(self.pool.acquire().await?.detach());
+            session.held.clear();
+        }
+        let conn = session.conn.as_mut().expect("connected above");
+
+        if session.held.contains(worker) {
+            // The lock lasts as long as the session, so check it's still there
+            conn.ping().await?;
+            return Ok(true);
+        }
+
+        let acquired = sqlx::query_scalar!(
+            r#"SELECT pg_try_advisory_lock($1, hashtext($2)) as "acquired!""#,
+            LOCK_NAMESPACE,
+            worker
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        if acquired {
+            tracing::info!("This instance now leads {}", worker);
+            session.held.insert(worker.to_string());
+        }
+        Ok(acquired)
+    }
+
+    /// Workers this instance leads
+    pub async fn held(&self) -> Vec<String> {
+        let mut held: Vec<String> = self.session.lock().await.held.iter().cloned().collect();
+        held.sort();
+        held
+    }
+
+    /// Give up every lock so another instance can take over straight away
+    pub async fn release(&self) {
+        let mut session = self.session.lock().await;
+        session.held.clear();
+        if let Some(conn) = session.conn.take() {
+            if let Err(e) = conn.close().await {
+                tracing::warn!("Failed to close the leader election connection: {}", e);
+            }
+        }
+    }
+}
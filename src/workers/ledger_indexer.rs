@@ -0,0 +1,182 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{error, info};
+
+use super::control::WorkerControl;
+use crate::services::stellar::{self, OperationRecord, StellarService};
+use crate::services::worker_heartbeats;
+
+const PAGE_LIMIT: u32 = 200;
+/// Pages read per account per run, so one long history can't starve the rest
+const MAX_PAGES_PER_RUN: usize = 10;
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn cursor_name(account: &str) -> String {
+    format!("horizon_operations:{}", account)
+}
+
+/// Pages Horizon operations for the platform wallet, per-project escrow
+/// accounts, and the wallets of students with projects into
+/// `onchain_transactions`, one row per operation. Each account's paging token
+/// is saved with the page it covers, so a run resumes exactly where the last
+/// one stopped.
+pub struct LedgerIndexer {
+    pool: PgPool,
+    stellar: StellarService,
+    dry_run: bool,
+    control: WorkerControl,
+}
+
+impl LedgerIndexer {
+    pub fn new(pool: PgPool, stellar: StellarService, dry_run: bool, control: WorkerControl) -> Self {
+        Self { pool, stellar, dry_run, control }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        loop {
+            if self.control.is_paused("ledger_indexer") {
+                info!("Ledger indexer paused, skipping run");
+            } else if !self.control.leads("ledger_indexer").await {
+                tracing::debug!("Another instance leads ledger_indexer, skipping run");
+            } else if self.stellar.horizon_unavailable() {
+                info!("Horizon unavailable, skipping ledger indexing run");
+            } else {
+                let outcome = self.index_all().await;
+                if let Err(e) = &outcome {
+                    error!("Ledger indexing error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "ledger_indexer", &outcome).await;
+            }
+
+            if !self.control.idle(POLL_INTERVAL).await {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Index each account's new operations, returning how many accounts were
+    /// brought up to date
+    async fn index_all(&self) -> Result<usize> {
+        let mut indexed = 0;
+        for account in self.indexed_accounts().await? {
+            match self.index_account(&account).await {
+                Ok(()) => indexed += 1,
+                Err(e) => error!("Failed to index operations for {}: {}", account, e),
+            }
+        }
+        Ok(indexed)
+    }
+
+    async fn indexed_accounts(&self) -> Result<Vec<String>> {
+        let platform = std::env::var("PLATFORM_WALLET_PUBLIC_KEY").unwrap_or_default();
+
+        let rows = sqlx::query_scalar!(
+            r#"
+            SELECT public_key as "public_key!" FROM project_escrow_accounts
+            UNION
+            SELECT w.public_key as "public_key!"
+            FROM wallets w
+            JOIN projects p ON p.student_id = w.student_id
+            WHERE w.status = 'connected'
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut accounts: Vec<String> = rows
+            .into_iter()
+            .chain(std::iter::once(platform))
+            .map(|account| stellar::base_account(&account))
+            .filter(|account| !account.is_empty())
+            .collect();
+        accounts.sort();
+        accounts.dedup();
+        Ok(accounts)
+    }
+
+    async fn index_account(&self, account: &str) -> Result<()> {
+        let mut cursor = sqlx::query_scalar!(
+            "SELECT cursor FROM indexer_cursors WHERE name = $1",
+            cursor_name(account)
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        for _ in 0..MAX_PAGES_PER_RUN {
+            let operations = self
+                .stellar
+                .fetch_operations(account, cursor.as_deref(), PAGE_LIMIT)
+                .await?;
+            let Some(last) = operations.last() else { break };
+            let next = last.id.to_string();
+
+            if self.dry_run {
+                info!("[dry-run] Would index {} operations for {} up to {}", operations.len(), account, next);
+                break;
+            }
+
+            // Rows and cursor move together so a failure retries the whole page
+            let mut tx = self.pool.begin().await?;
+            for operation in &operations {
+                store_operation(&mut tx, operation).await?;
+            }
+            sqlx::query!(
+                r#"
+                INSERT INTO indexer_cursors (name, cursor, updated_at)
+                VALUES ($1, $2, NOW())
+                ON CONFLICT (name) DO UPDATE SET cursor = EXCLUDED.cursor, updated_at = NOW()
+                "#,
+                cursor_name(account),
+                next
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            if operations.len() < PAGE_LIMIT as usize {
+                break;
+            }
+            cursor = Some(next);
+        }
+
+        Ok(())
+    }
+}
+
+async fn store_operation(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, operation: &OperationRecord) -> Result<()> {
+    // amount_xlm only holds native amounts; other assets keep stroop units in amount_stroops
+    let amount_xlm = operation
+        .amount
+        .filter(|_| operation.asset.as_deref() == Some("native"))
+        .map(|a| a.to_decimal());
+
+    sqlx::query!(
+        r#"
+        INSERT INTO onchain_transactions (
+            operation_id, tx_hash, source_account, destination_account,
+            amount_stroops, amount_xlm, asset, memo, memo_type, ledger,
+            operation_type, successful, created_at, indexed_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, NOW())
+        ON CONFLICT (operation_id) DO NOTHING
+        "#,
+        operation.id,
+        operation.tx_hash,
+        operation.source_account,
+        operation.destination_account,
+        operation.amount.map(|a| a.as_stroops()),
+        amount_xlm,
+        operation.asset,
+        operation.memo,
+        operation.memo_type,
+        operation.ledger,
+        operation.operation_type,
+        operation.successful,
+        operation.created_at,
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
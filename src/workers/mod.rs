@@ -1,178 +1,236 @@
 use std::time::Duration;
-use tokio::time;
 use anyhow::Result;
 use sqlx::PgPool;
 use crate::{
+    config::{EscrowMode, StellarNetwork},
     models::{Donation, DonationStatus, PaymentMethod},
-    services::stellar::StellarService,
+    services::{campaign_rules, campaign_schedule, contract_client::ContractClient, payouts, stellar::StellarService, stellar_tx::TxSubmitter, worker_heartbeats},
+    utils::money::Stroops,
 };
 use tracing::{info, error, warn};
-use sqlx::types::BigDecimal;
+use control::WorkerControl;
 use num_traits::cast::ToPrimitive;
-use num_traits::FromPrimitive;
 
 pub mod analytics;
+pub mod announcement_dispatcher;
+pub mod campaign_scheduler;
+pub mod control;
+pub mod digest_sender;
+pub mod escrow_reconciler;
+pub mod escrow_sweeper;
+pub mod event_indexer;
+pub mod file_scanner;
+pub mod job_runner;
+pub mod leader;
+pub mod ledger_indexer;
 pub mod payment_reconciler;
+pub mod payment_stream;
+pub mod project_scheduler;
+pub mod refund_processor;
+pub mod subscription_scheduler;
+pub mod webhook_dispatcher;
 
 #[derive(Clone)]
 pub struct Worker {
     pool: PgPool,
     stellar: StellarService,
+    dry_run: bool,
+    escrow_mode: EscrowMode,
+    control: WorkerControl,
 }
 
 impl Worker {
-    pub fn new(pool: PgPool, stellar: StellarService) -> Self {
-        Self { pool, stellar }
+    pub fn new(
+        pool: PgPool,
+        stellar: StellarService,
+        dry_run: bool,
+        escrow_mode: EscrowMode,
+        control: WorkerControl,
+    ) -> Self {
+        Self { pool, stellar, dry_run, escrow_mode, control }
     }
 
     pub async fn start(self) -> Result<()> {
         info!("Starting background workers...");
+        if self.dry_run {
+            warn!("Workers running in dry-run mode: no changes will be written or submitted");
+        }
         
+        // Donation verification: stream payments into watched wallets
+        let streamer = payment_stream::PaymentStreamer::new(
+            self.pool.clone(),
+            self.stellar.clone(),
+            self.dry_run,
+            self.escrow_mode,
+            self.control.clone(),
+        );
+        self.control.spawn(async move {
+            if let Err(e) = streamer.start().await {
+                error!("Payment streamer error: {}", e);
+            }
+        });
+
+        // Expire stale pending donations (every 10 minutes)
         let worker_clone = self.clone();
-        tokio::spawn(async move {
+        self.control.spawn(async move {
             loop {
-                if let Err(e) = worker_clone.verify_pending_donations().await {
-                    error!("Error verifying donations: {}", e);
+                if worker_clone.control.is_paused("donation_verification") {
+                    info!("Donation verification worker paused, skipping run");
+                } else if !worker_clone.control.leads("donation_verification").await {
+                    tracing::debug!("Another instance leads donation_verification, skipping run");
+                } else {
+                    let outcome = worker_clone.expire_pending_donations().await;
+                    if let Err(e) = &outcome {
+                        error!("Error expiring donations: {}", e);
+                    }
+                    worker_heartbeats::record(&worker_clone.pool, "donation_verification", &outcome).await;
+                }
+                if !worker_clone.control.idle(Duration::from_secs(600)).await {
+                    break;
                 }
-                time::sleep(Duration::from_secs(120)).await;
             }
         });
 
         // Wallet sync worker (every 5 minutes)
         let pool_clone = self.pool.clone();
         let stellar_clone = self.stellar.clone();
-        tokio::spawn(async move {
+        let control = self.control.clone();
+        self.control.spawn(async move {
             loop {
-                if let Err(e) = sync_wallets(&pool_clone, &stellar_clone).await {
-                    error!("Error syncing wallets: {}", e);
+                if control.is_paused("wallet_sync") {
+                    info!("Wallet sync worker paused, skipping run");
+                } else if !control.leads("wallet_sync").await {
+                    tracing::debug!("Another instance leads wallet_sync, skipping run");
+                } else {
+                    let outcome = sync_wallets(&pool_clone, &stellar_clone).await;
+                    if let Err(e) = &outcome {
+                        error!("Error syncing wallets: {}", e);
+                    }
+                    worker_heartbeats::record(&pool_clone, "wallet_sync", &outcome).await;
+                }
+                if !control.idle(Duration::from_secs(300)).await {
+                    break;
                 }
-                time::sleep(Duration::from_secs(300)).await;
             }
         });
 
         // Analytics collector (every 10 minutes)
         let pool_clone2 = self.pool.clone();
-        tokio::spawn(async move {
+        let control = self.control.clone();
+        self.control.spawn(async move {
+            loop {
+                if control.is_paused("analytics") {
+                    info!("Analytics collector paused, skipping run");
+                } else if !control.leads("analytics").await {
+                    tracing::debug!("Another instance leads analytics, skipping run");
+                } else {
+                    let outcome = collect_analytics(&pool_clone2).await;
+                    if let Err(e) = &outcome {
+                        error!("Error collecting analytics: {}", e);
+                    }
+                    worker_heartbeats::record(&pool_clone2, "analytics", &outcome).await;
+                }
+                if !control.idle(Duration::from_secs(600)).await {
+                    break;
+                }
+            }
+        });
+
+        // Campaign matching (every 5 minutes)
+        let pool_clone3 = self.pool.clone();
+        let control = self.control.clone();
+        let dry_run = self.dry_run;
+        let network = self.stellar.network();
+        self.control.spawn(async move {
             loop {
-                if let Err(e) = collect_analytics(&pool_clone2).await {
-                    error!("Error collecting analytics: {}", e);
+                if control.is_paused("campaign_matching") {
+                    info!("Campaign matching worker paused, skipping run");
+                } else if !control.leads("campaign_matching").await {
+                    tracing::debug!("Another instance leads campaign_matching, skipping run");
+                } else {
+                    let outcome = match_campaign_deposits(&pool_clone3, network, dry_run).await;
+                    if let Err(e) = &outcome {
+                        error!("Error matching campaign deposits: {}", e);
+                    }
+                    worker_heartbeats::record(&pool_clone3, "campaign_matching", &outcome).await;
+                }
+                if !control.idle(Duration::from_secs(300)).await {
+                    break;
                 }
-                time::sleep(Duration::from_secs(600)).await;
             }
         });
 
         Ok(())
     }
 
-    async fn verify_pending_donations(&self) -> Result<()> {
-        // Get pending stellar donations with memo
-        let pending_donations = sqlx::query!(
+    /// Mark Stellar donations that never received a matching payment as
+    /// failed, returning how many were
+    async fn expire_pending_donations(&self) -> Result<usize> {
+        let stale = sqlx::query!(
             r#"
-            SELECT id, project_id, amount, memo, payment_method, created_at
-            FROM donations 
+            SELECT id, created_at
+            FROM donations
             WHERE status = 'pending'
             AND payment_method = 'stellar'
-            AND created_at > NOW() - INTERVAL '24 hours'
+            AND created_at <= NOW() - INTERVAL '24 hours'
             LIMIT 50
             "#
         )
         .fetch_all(&self.pool)
         .await?;
 
-        for donation in pending_donations {
-            let amount_xlm = donation.amount.to_f64().unwrap_or(0.0);
-            let memo = donation.memo.unwrap_or_default();
-            
-            // Get project wallet address or use platform address
-            let project = sqlx::query!(
+        let mut expired = 0;
+        for donation in stale {
+            if self.dry_run {
+                info!("[dry-run] Would mark donation {} as failed (created {:?})", donation.id, donation.created_at);
+                continue;
+            }
+            sqlx::query!(
                 r#"
-                SELECT p.student_id, w.public_key 
-                FROM projects p
-                LEFT JOIN wallets w ON w.student_id = p.student_id
-                WHERE p.id = $1
+                UPDATE donations 
+                SET status = 'failed'
+                WHERE id = $1 AND status = 'pending'
                 "#,
-                donation.project_id
+                donation.id
             )
-            .fetch_optional(&self.pool)
+            .execute(&self.pool)
             .await?;
-
-            if let Some(proj) = project {
-                let destination = if !proj.public_key.is_empty() {
-                    proj.public_key
-                } else {
-                    std::env::var("PLATFORM_WALLET_PUBLIC_KEY").unwrap_or_default()
-                };
-
-                // Search for transactions to this destination with matching memo
-                if let Ok(txs) = self.stellar.fetch_wallet_transactions(&destination).await {
-                    // Look for matching transaction in recent history
-                    for tx in txs {
-                        // In a real implementation, we'd parse memo from transaction
-                        // For now, we'll check if the amount matches
-                        if (tx.amount - amount_xlm).abs() < 0.0001 {
-                            info!("Verified donation {} with tx {}", donation.id, tx.hash);
-                            sqlx::query!(
-                                r#"
-                                UPDATE donations 
-                                SET status = 'confirmed',
-                                    tx_hash = $1,
-                                    confirmed_at = NOW()
-                                WHERE id = $2
-                                "#,
-                                tx.hash,
-                                donation.id
-                            )
-                            .execute(&self.pool)
-                            .await?;
-                            break;
-                        }
-                    }
-                }
-            }
-
-            // Mark as failed if older than 24 hours and still pending
-            if let Some(created_at) = donation.created_at {
-                let age_hours = (chrono::Utc::now() - created_at).num_hours();
-                if age_hours > 24 {
-                    sqlx::query!(
-                        r#"
-                        UPDATE donations 
-                        SET status = 'failed'
-                        WHERE id = $1 AND status = 'pending'
-                        "#,
-                        donation.id
-                    )
-                    .execute(&self.pool)
-                    .await?;
-                }
-            }
+            expired += 1;
         }
 
-        Ok(())
+        Ok(expired)
     }
 }
 
-async fn sync_wallets(pool: &PgPool, stellar: &StellarService) -> Result<()> {
+async fn sync_wallets(pool: &PgPool, stellar: &StellarService) -> Result<usize> {
     let wallets = sqlx::query!("SELECT id, public_key FROM wallets WHERE status = 'connected'")
         .fetch_all(pool)
         .await?;
+    let mut synced = 0;
     for w in wallets {
+        // Stop early rather than fail every remaining wallet against a down Horizon
+        if stellar.horizon_unavailable() {
+            return Err(anyhow::anyhow!("Horizon unavailable; wallet sync deferred"));
+        }
         if let Ok(bal) = stellar.fetch_wallet_balance(&w.public_key).await {
             let _ = sqlx::query!(
                 r#"UPDATE wallets SET balance = $1, last_synced_at = NOW() WHERE id = $2"#,
-                BigDecimal::from_f64(bal.xlm).unwrap_or(BigDecimal::from(0)),
+                bal.xlm.to_decimal(),
                 w.id
             ).execute(pool).await;
+            synced += 1;
         }
     }
-    Ok(())
+    Ok(synced)
 }
 
-async fn collect_analytics(pool: &PgPool) -> Result<()> {
+/// Cache donation totals per project and student, returning how many were
+async fn collect_analytics(pool: &PgPool) -> Result<usize> {
     // Example: total donations per project cached into analytics_summary
     let rows = sqlx::query!(
         r#"SELECT project_id, SUM(amount) as total FROM donations WHERE status = 'confirmed' GROUP BY project_id"#
     ).fetch_all(pool).await?;
+    let summarised = rows.len();
     for r in rows {
         let _ = sqlx::query!(
             r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
@@ -192,6 +250,7 @@ async fn collect_analytics(pool: &PgPool) -> Result<()> {
             WHERE d.status = 'confirmed'
             GROUP BY p.student_id"#
     ).fetch_all(pool).await?;
+    let summarised = summarised + rows2.len();
     for r in rows2 {
         let _ = sqlx::query!(
             r#"INSERT INTO analytics_summary (entity_type, entity_id, metric, value, updated_at)
@@ -202,53 +261,169 @@ async fn collect_analytics(pool: &PgPool) -> Result<()> {
             r.total.unwrap_or_default().to_f64().unwrap_or(0.0)
         ).execute(pool).await;
     }
-    Ok(())
+    Ok(summarised)
 }
 
-pub async fn distribute_campaign_funds(pool: &PgPool, stellar: &StellarService) -> Result<()> {
-    info!("Starting campaign fund distribution...");
+/// Match escrow deposits made since each active pool opened to projects of
+/// the students its campaign's criteria select, until the pool is
+/// exhausted, returning how many were matched
+pub async fn match_campaign_deposits(pool: &PgPool, network: StellarNetwork, dry_run: bool) -> Result<usize> {
+    let pools = sqlx::query!(
+        r#"
+        SELECT m.campaign_id, m.created_at, c.name, c.criteria
+        FROM campaign_matching_pools m
+        JOIN campaigns c ON c.id = m.campaign_id
+        WHERE m.status = 'active' AND m.matched_stroops < LEAST(m.cap_stroops, m.locked_stroops)
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if pools.is_empty() {
+        return Ok(0);
+    }
+
+    let mut contract_client = ContractClient::new(pool.clone(), network);
+    contract_client.load_contracts().await?;
+
+    let mut matched_deposits = 0;
+    for matching_pool in pools {
+        let criteria = match campaign_rules::Criteria::parse(&matching_pool.criteria) {
+            Ok(criteria) => criteria,
+            Err(e) => {
+                error!("Skipping matching pool for campaign {}: {}", matching_pool.name, e);
+                continue;
+            }
+        };
+        let students: Vec<uuid::Uuid> = campaign_rules::eligible(pool, &criteria, chrono::Utc::now())
+            .await?
+            .into_iter()
+            .map(|c| c.student_id)
+            .collect();
+
+        let deposits = sqlx::query!(
+            r#"
+            SELECT d.project_id, d.tx_hash, d.amount_stroops
+            FROM contract_deposits d
+            JOIN projects p ON p.id = d.project_id
+            WHERE d.created_at >= $1
+            AND p.student_id = ANY($2)
+            AND NOT EXISTS (SELECT 1 FROM contract_matches m WHERE m.deposit_tx_hash = d.tx_hash)
+            ORDER BY d.created_at
+            LIMIT 100
+            "#,
+            matching_pool.created_at,
+            &students
+        )
+        .fetch_all(pool)
+        .await?;
+
+        for deposit in deposits {
+            if dry_run {
+                info!(
+                    "[dry-run] Would match deposit {} ({} stroops) from campaign {}",
+                    deposit.tx_hash, deposit.amount_stroops, matching_pool.campaign_id
+                );
+                continue;
+            }
+
+            match contract_client
+                .record_match(matching_pool.campaign_id, deposit.project_id, &deposit.tx_hash, deposit.amount_stroops)
+                .await
+            {
+                Ok(0) => {
+                    info!("Matching pool for campaign {} exhausted", matching_pool.campaign_id);
+                    break;
+                }
+                Ok(matched) => {
+                    info!(
+                        "Matched deposit {} with {} XLM from campaign {}",
+                        deposit.tx_hash, Stroops::from_stroops(matched), matching_pool.campaign_id
+                    );
+                    matched_deposits += 1;
+                }
+                Err(e) => error!("Failed to match deposit {}: {}", deposit.tx_hash, e),
+            }
+        }
+    }
+
+    Ok(matched_deposits)
+}
+
+pub async fn distribute_campaign_funds(
+    pool: &PgPool,
+    payments: Option<&TxSubmitter>,
+    campaign_id: Option<uuid::Uuid>,
+    dry_run: bool,
+) -> Result<()> {
+    info!("Starting campaign fund distribution{}...", if dry_run { " (dry-run)" } else { "" });
     
-    // Get active campaigns
+    // An admin's run pays out active and ended campaigns; an end date's run
+    // pays out only the campaign it moved to distributing
     let active_campaigns = sqlx::query!(
-        "SELECT id, name, criteria, reward_pool_xlm FROM campaigns WHERE status = 'active'"
+        r#"SELECT id, name, criteria, reward_pool_xlm as "reward_pool_xlm: Stroops", status, distribution_strategy FROM campaigns
+           WHERE CASE WHEN $1::uuid IS NULL THEN status IN ('active', 'ended')
+                      ELSE id = $1 AND status = 'distributing' END"#,
+        campaign_id
     ).fetch_all(pool).await?;
 
     for campaign in active_campaigns {
         info!("Processing campaign: {} (ID: {})", campaign.name, campaign.id);
         
-        // Parse criteria and find eligible recipients
-        let recipients = find_eligible_recipients(pool, &campaign.criteria).await?;
+        // Find the students the campaign's criteria select
+        let criteria = match campaign_rules::Criteria::parse(&campaign.criteria) {
+            Ok(criteria) => criteria,
+            Err(e) => {
+                error!("Skipping campaign {}: {}", campaign.name, e);
+                continue;
+            }
+        };
+        let strategy: campaign_rules::Strategy = match serde_json::from_value(campaign.distribution_strategy) {
+            Ok(strategy) => strategy,
+            Err(e) => {
+                error!("Skipping campaign {}: invalid distribution strategy: {}", campaign.name, e);
+                continue;
+            }
+        };
+        let candidates = campaign_rules::eligible(pool, &criteria, chrono::Utc::now()).await?;
         
-        if recipients.is_empty() {
+        // Split the pool to the stroop under the campaign's strategy
+        let shares = campaign_rules::allocate(campaign.reward_pool_xlm, &strategy, &candidates);
+        if shares.is_empty() {
             warn!("No eligible recipients found for campaign: {}", campaign.name);
             continue;
         }
 
-        // Calculate distribution amounts
-        let total_recipients = recipients.len() as f64;
-        let amount_per_recipient = campaign.reward_pool_xlm / total_recipients;
-        
-        info!("Distributing {} XLM to {} recipients ({} XLM each)", 
-              campaign.reward_pool_xlm, total_recipients, amount_per_recipient);
+        info!("Distributing {} XLM to {} recipients ({:?})", 
+              campaign.reward_pool_xlm, shares.len(), strategy);
 
         // Distribute funds to each recipient
-        for recipient in recipients {
+        for (student_id, amount) in shares {
             if let Err(e) = distribute_to_recipient(
                 pool, 
-                stellar, 
+                payments, 
                 &campaign.id, 
-                &recipient.student_id, 
-                amount_per_recipient
+                &student_id, 
+                amount,
+                dry_run,
             ).await {
-                error!("Failed to distribute to recipient {}: {}", recipient.student_id, e);
+                error!("Failed to distribute to recipient {}: {}", student_id, e);
             }
         }
 
+        if dry_run {
+            info!("[dry-run] Would mark campaign {} as completed", campaign.name);
+            continue;
+        }
+
         // Mark campaign as completed
-        let _ = sqlx::query!(
+        let mut tx = pool.begin().await?;
+        sqlx::query!(
             "UPDATE campaigns SET status = 'completed', updated_at = NOW() WHERE id = $1",
             campaign.id
-        ).execute(pool).await;
+        ).execute(&mut *tx).await?;
+        campaign_schedule::record_transition(&mut *tx, campaign.id, &campaign.status, "completed", "distributed").await?;
+        tx.commit().await?;
         
         info!("Campaign {} completed", campaign.name);
     }
@@ -256,73 +431,32 @@ pub async fn distribute_campaign_funds(pool: &PgPool, stellar: &StellarService)
     Ok(())
 }
 
-async fn find_eligible_recipients(pool: &PgPool, criteria: &str) -> Result<Vec<RecipientInfo>> {
-    // Simple criteria parsing - in a real implementation, this would be more sophisticated
-    let recipients = if criteria.contains("verified_students") {
-        sqlx::query_as!(
-            RecipientInfo,
-            r#"
-            SELECT s.id as student_id, u.username, w.public_key
-            FROM students s
-            JOIN users u ON s.user_id = u.id
-            LEFT JOIN wallets w ON s.id = w.student_id AND w.status = 'connected'
-            WHERE s.verification_status = 'verified'
-            "#
-        ).fetch_all(pool).await?
-    } else if criteria.contains("active_projects") {
-        sqlx::query_as!(
-            RecipientInfo,
-            r#"
-            SELECT DISTINCT s.id as student_id, u.username, w.public_key
-            FROM students s
-            JOIN users u ON s.user_id = u.id
-            JOIN projects p ON s.id = p.student_id
-            LEFT JOIN wallets w ON s.id = w.student_id AND w.status = 'connected'
-            WHERE s.verification_status = 'verified'
-                AND p.created_at >= NOW() - INTERVAL '30 days'
-            "#
-        ).fetch_all(pool).await?
-    } else {
-        // Default: all verified students
-        sqlx::query_as!(
-            RecipientInfo,
-            r#"
-            SELECT s.id as student_id, u.username, w.public_key
-            FROM students s
-            JOIN users u ON s.user_id = u.id
-            LEFT JOIN wallets w ON s.id = w.student_id AND w.status = 'connected'
-            WHERE s.verification_status = 'verified'
-            "#
-        ).fetch_all(pool).await?
-    };
-
-    Ok(recipients)
-}
-
 async fn distribute_to_recipient(
     pool: &PgPool,
-    _stellar: &StellarService,
+    payments: Option<&TxSubmitter>,
     campaign_id: &uuid::Uuid,
     student_id: &uuid::Uuid,
-    amount: f64,
+    amount: Stroops,
+    dry_run: bool,
 ) -> Result<()> {
-    // Get recipient's wallet
-    let wallet = sqlx::query!(
-        "SELECT public_key FROM wallets WHERE student_id = $1 AND status = 'connected'",
-        student_id
-    ).fetch_optional(pool).await?;
-
-    let public_key = match wallet {
-        Some(w) => w.public_key,
-        None => {
-            warn!("No connected wallet found for student: {}", student_id);
-            return Ok(());
+    if dry_run {
+        match payouts::student_wallet(pool, *student_id).await? {
+            Some((public_key, _)) => {
+                info!("[dry-run] Would distribute {} XLM to student {} (wallet: {})", amount, student_id, public_key)
+            }
+            None => warn!("[dry-run] Student {} has no wallet key; would skip", student_id),
         }
-    };
+        return Ok(());
+    }
 
-    // In a real implementation, this would create an actual Stellar transaction
-    // For now, we'll just record the distribution
-    let tx_hash = format!("campaign_{}_{}", campaign_id, student_id);
+    let payments = payments.ok_or_else(|| anyhow::anyhow!("Platform payments are not configured"))?;
+    let memo = format!("campaign:{}", &campaign_id.simple().to_string()[..16]);
+    let payout = payouts::pay_student(pool, payments, *student_id, amount, Some(&memo), "campaign", Some(*campaign_id)).await?;
+
+    let Some(payout) = payout else {
+        warn!("Student {} has no wallet key; skipping distribution", student_id);
+        return Ok(());
+    };
     
     // Record the distribution
     let _ = sqlx::query!(
@@ -333,17 +467,18 @@ async fn distribute_to_recipient(
         uuid::Uuid::new_v4(),
         campaign_id,
         student_id,
-        amount,
-        tx_hash
+        amount.to_decimal(),
+        payout.tx_hash()
     ).execute(pool).await?;
 
-    info!("Distributed {} XLM to student {} (wallet: {})", amount, student_id, public_key);
+    match payout {
+        payouts::Payout::Paid { destination, .. } => {
+            info!("Distributed {} XLM to student {} (wallet: {})", amount, student_id, destination)
+        }
+        payouts::Payout::Claimable { claimant, balance_id, .. } => info!(
+            "Distributed {} XLM to student {} as claimable balance {} (claimant: {})",
+            amount, student_id, balance_id, claimant
+        ),
+    }
     Ok(())
 }
-
-#[derive(sqlx::FromRow)]
-struct RecipientInfo {
-    student_id: uuid::Uuid,
-    username: String,
-    public_key: Option<String>,
-}
\ No newline at end of file
@@ -0,0 +1,302 @@
+use anyhow::Result;
+use futures::{pin_mut, StreamExt};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use super::control::WorkerControl;
+use crate::config::EscrowMode;
+use crate::services::{donation_memo, email, fees, follows, ledger, outgoing_webhooks, worker_heartbeats};
+use crate::services::stellar::{self, PaymentRecord, StellarService};
+use crate::utils::money::Stroops;
+
+/// How often the set of watched wallets is refreshed
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(60);
+/// Wait before reopening a stream that closed or failed
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Payments replayed from history when a wallet has no saved cursor yet
+const BACKFILL_LIMIT: u32 = 200;
+
+/// Follows Horizon's payment stream for every wallet that has pending
+/// donations and confirms a donation when a successful native payment to its
+/// destination carries its memo and exactly its amount. Each wallet's cursor
+/// is persisted so restarts resume where they left off.
+#[derive(Clone)]
+pub struct PaymentStreamer {
+    pool: PgPool,
+    stellar: StellarService,
+    dry_run: bool,
+    escrow_mode: EscrowMode,
+    control: WorkerControl,
+}
+
+fn cursor_name(account: &str) -> String {
+    format!("horizon_payments:{}", account)
+}
+
+impl PaymentStreamer {
+    pub fn new(
+        pool: PgPool,
+        stellar: StellarService,
+        dry_run: bool,
+        escrow_mode: EscrowMode,
+        control: WorkerControl,
+    ) -> Self {
+        Self { pool, stellar, dry_run, escrow_mode, control }
+    }
+
+    /// Keep one stream open per watched wallet, closing streams for wallets
+    /// that no longer have pending donations
+    pub async fn start(self) -> Result<()> {
+        let mut streams: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+        loop {
+            match self.watched_accounts().await {
+                Ok(accounts) => {
+                    streams.retain(|account, handle| {
+                        let keep = accounts.contains(account) && !handle.is_finished();
+                        if !keep {
+                            handle.abort();
+                        }
+                        keep
+                    });
+
+                    for account in accounts {
+                        if streams.contains_key(&account) {
+                            continue;
+                        }
+                        info!("Opening payment stream for {}", account);
+                        let streamer = self.clone();
+                        let follow_account = account.clone();
+                        streams.insert(account, tokio::spawn(async move { streamer.follow(follow_account).await }));
+                    }
+                }
+                Err(e) => error!("Failed to load watched wallets: {}", e),
+            }
+
+            if !self.control.idle(SUPERVISE_INTERVAL).await {
+                break;
+            }
+        }
+
+        // Each stream stops between payments, with its cursor saved
+        for (_, handle) in streams {
+            let _ = handle.await;
+        }
+        Ok(())
+    }
+
+    /// Accounts behind the destinations of recent pending Stellar donations, plus the platform wallet
+    async fn watched_accounts(&self) -> Result<HashSet<String>> {
+        let platform = std::env::var("PLATFORM_WALLET_PUBLIC_KEY").unwrap_or_default();
+
+        let rows = sqlx::query_scalar!(
+            r#"
+            SELECT DISTINCT
+                CASE WHEN $1 THEN COALESCE(p.contract_address, NULLIF(w.public_key, ''))
+                     ELSE COALESCE(NULLIF(w.public_key, ''), p.contract_address)
+                END as "account?"
+            FROM donations d
+            JOIN projects p ON p.id = d.project_id
+            LEFT JOIN wallets w ON w.student_id = p.student_id
+            WHERE d.status = 'pending'
+            AND d.payment_method = 'stellar'
+            AND d.created_at > NOW() - INTERVAL '24 hours'
+            "#,
+            self.escrow_mode == EscrowMode::PerProject
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|account| account.unwrap_or_else(|| platform.clone()))
+            .chain(std::iter::once(platform.clone()))
+            // Payments to muxed addresses show up in the underlying account's stream
+            .map(|account| stellar::base_account(&account))
+            .filter(|account| !account.is_empty())
+            .collect())
+    }
+
+    /// Reconnect until shutdown; the supervisor aborts the task when the
+    /// wallet is dropped
+    async fn follow(&self, account: String) {
+        loop {
+            // Only the leading instance streams; the others stand by to take over
+            if self.control.is_paused("donation_verification") || !self.control.leads("donation_verification").await {
+                if !self.control.idle(SUPERVISE_INTERVAL).await {
+                    return;
+                }
+                continue;
+            }
+            // Wait out an open Horizon circuit quietly instead of logging every retry
+            if self.stellar.horizon_unavailable() {
+                if !self.control.idle(RECONNECT_DELAY).await {
+                    return;
+                }
+                continue;
+            }
+
+            match self.consume(&account).await {
+                Ok(()) if self.control.is_shutting_down() => return,
+                Ok(()) => info!("Payment stream for {} closed, reconnecting", account),
+                Err(e) => {
+                    warn!("Payment stream for {} failed: {}", account, e);
+                    worker_heartbeats::record(&self.pool, "donation_verification", &Err(e)).await;
+                }
+            }
+            if !self.control.idle(RECONNECT_DELAY).await {
+                return;
+            }
+        }
+    }
+
+    async fn consume(&self, account: &str) -> Result<()> {
+        let saved = sqlx::query_scalar!(
+            "SELECT cursor FROM indexer_cursors WHERE name = $1",
+            cursor_name(account)
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let cursor = match saved {
+            Some(cursor) => cursor,
+            None => self.backfill(account).await?,
+        };
+
+        let stream = self.stellar.stream_payments(account, &cursor).await?;
+        pin_mut!(stream);
+
+        loop {
+            let payment = tokio::select! {
+                _ = self.control.stopping() => return Ok(()),
+                next = stream.next() => match next {
+                    Some(payment) => payment?,
+                    None => break,
+                },
+            };
+            // Drop the connection; the saved cursor resumes it once unpaused
+            // or wherever leadership moves
+            if self.control.is_paused("donation_verification") || !self.control.leads("donation_verification").await {
+                return Ok(());
+            }
+            self.apply_payment(&payment).await?;
+            self.save_cursor(account, &payment.paging_token).await?;
+            worker_heartbeats::record(&self.pool, "donation_verification", &Ok(1)).await;
+        }
+
+        Ok(())
+    }
+
+    /// Replay recent history for a wallet seen for the first time so payments
+    /// made before its stream opened are not missed; returns the cursor to
+    /// stream from
+    async fn backfill(&self, account: &str) -> Result<String> {
+        let recent = self.stellar.fetch_recent_payments(account, BACKFILL_LIMIT).await?;
+
+        for payment in recent.iter().rev() {
+            self.apply_payment(payment).await?;
+        }
+
+        let cursor = recent
+            .first()
+            .map(|p| p.paging_token.clone())
+            .unwrap_or_else(|| "now".to_string());
+        self.save_cursor(account, &cursor).await?;
+        Ok(cursor)
+    }
+
+    async fn apply_payment(&self, payment: &PaymentRecord) -> Result<()> {
+        let Some(memo) = payment.memo.as_deref() else {
+            return Ok(());
+        };
+
+        let donation = sqlx::query!(
+            r#"
+            SELECT id, project_id, amount as "amount: Stroops"
+            FROM donations
+            WHERE memo = $1 AND status = 'pending' AND payment_method = 'stellar'
+            "#,
+            memo
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(donation) = donation else {
+            return Ok(());
+        };
+
+        let destination = donation_memo::expected_destination(&self.pool, donation.project_id, self.escrow_mode).await?;
+        if !donation_memo::payment_matches(payment, &destination, memo, donation.amount) {
+            warn!(
+                "Payment {} carries the memo of donation {} but does not settle it (expected {} XLM, got {:?})",
+                payment.tx_hash, donation.id, donation.amount, payment.amount
+            );
+            return Ok(());
+        }
+
+        if self.dry_run {
+            info!("[dry-run] Would confirm donation {} with tx {}", donation.id, payment.tx_hash);
+            return Ok(());
+        }
+
+        let confirmed = sqlx::query!(
+            r#"
+            UPDATE donations
+            SET status = 'confirmed',
+                tx_hash = $1,
+                confirmed_at = NOW()
+            WHERE id = $2 AND status = 'pending'
+            "#,
+            payment.tx_hash,
+            donation.id
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        if confirmed > 0 {
+            let entry = ledger::donation_entry(donation.id, donation.project_id, &destination, donation.amount);
+            if let Err(e) = ledger::post(&self.pool, &entry).await {
+                error!("Failed to post donation {} to the ledger: {}", donation.id, e);
+            }
+            if let Err(e) = fees::apply(&self.pool, donation.id, donation.project_id, "stellar", donation.amount).await {
+                error!("Failed to take the platform fee on donation {}: {}", donation.id, e);
+            }
+            if let Err(e) = email::queue_donation_receipt(&self.pool, self.stellar.network(), donation.id).await {
+                error!("Failed to queue the receipt email for donation {}: {}", donation.id, e);
+            }
+            if let Err(e) = outgoing_webhooks::queue_donation_confirmed(&self.pool, donation.id).await {
+                error!("Failed to queue webhooks for donation {}: {}", donation.id, e);
+            }
+            if let Err(e) = follows::check_funding_thresholds(&self.pool, donation.id).await {
+                error!("Failed to check funding thresholds after donation {}: {}", donation.id, e);
+            }
+        }
+
+        info!("Verified donation {} with tx {}", donation.id, payment.tx_hash);
+        Ok(())
+    }
+
+    async fn save_cursor(&self, account: &str, cursor: &str) -> Result<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO indexer_cursors (name, cursor, updated_at)
+            VALUES ($1, $2, NOW())
+            ON CONFLICT (name) DO UPDATE SET cursor = EXCLUDED.cursor, updated_at = NOW()
+            "#,
+            cursor_name(account),
+            cursor
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
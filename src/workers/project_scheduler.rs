@@ -0,0 +1,110 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::control::WorkerControl;
+use crate::config::StellarNetwork;
+use crate::services::contract_client::ContractClient;
+use crate::services::escrow::EscrowService;
+use crate::services::project_schedule;
+use crate::services::worker_heartbeats;
+
+/// Projects published or closed per run, of each kind
+const SCHEDULE_BATCH: i64 = 50;
+
+/// Publishes scheduled projects when their time comes and closes projects'
+/// funding at their deadline, cancelling and refunding those that missed
+/// their goal
+pub struct ProjectScheduler {
+    pool: PgPool,
+    network: StellarNetwork,
+    /// Set when projects hold their own escrow accounts
+    escrow: Option<EscrowService>,
+    dry_run: bool,
+    interval: Duration,
+    control: WorkerControl,
+}
+
+impl ProjectScheduler {
+    pub fn new(
+        pool: PgPool,
+        network: StellarNetwork,
+        escrow: Option<EscrowService>,
+        dry_run: bool,
+        control: WorkerControl,
+    ) -> Self {
+        let interval_secs = std::env::var("PROJECT_SCHEDULER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60);
+        Self {
+            pool,
+            network,
+            escrow,
+            dry_run,
+            interval: Duration::from_secs(interval_secs),
+            control,
+        }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        loop {
+            if self.control.is_paused("project_scheduler") {
+                tracing::info!("Project scheduler paused, skipping run");
+            } else if !self.control.leads("project_scheduler").await {
+                tracing::debug!("Another instance leads project_scheduler, skipping run");
+            } else {
+                let outcome = self.run_once().await;
+                if let Err(e) = &outcome {
+                    eprintln!("Project scheduler error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "project_scheduler", &outcome).await;
+            }
+
+            if !self.control.idle(self.interval).await {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn run_once(&self) -> Result<usize> {
+        let publications = project_schedule::due_publications(&self.pool, SCHEDULE_BATCH).await?;
+        let deadlines = project_schedule::due_deadlines(&self.pool, SCHEDULE_BATCH).await?;
+        if publications.is_empty() && deadlines.is_empty() {
+            return Ok(0);
+        }
+        if self.dry_run {
+            tracing::info!(
+                "[dry-run] Would publish {} projects and close funding on {}",
+                publications.len(),
+                deadlines.len()
+            );
+            return Ok(0);
+        }
+
+        let mut contracts = ContractClient::new(self.pool.clone(), self.network);
+        contracts.load_contracts().await?;
+
+        let mut handled = 0;
+        for project_id in publications {
+            match project_schedule::activate(&self.pool, &contracts, self.escrow.as_ref(), project_id).await {
+                Ok(Some(_)) => {
+                    tracing::info!("Published scheduled project {}", project_id);
+                    handled += 1;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to publish scheduled project {}: {}", project_id, e),
+            }
+        }
+        for project_id in deadlines {
+            match project_schedule::close_funding(&self.pool, &contracts, project_id).await {
+                Ok(outcome) => {
+                    tracing::info!("Closed funding on project {}: {}", project_id, outcome.as_str());
+                    handled += 1;
+                }
+                Err(e) => tracing::error!("Failed to close funding on project {}: {}", project_id, e),
+            }
+        }
+        Ok(handled)
+    }
+}
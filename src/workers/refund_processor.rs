@@ -0,0 +1,86 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::control::WorkerControl;
+use crate::config::StellarNetwork;
+use crate::services::payment_service::ProviderRegistry;
+use crate::services::project_refunds;
+use crate::services::worker_heartbeats;
+
+/// Refunds attempted per run, longest due first
+const REFUND_BATCH: i64 = 50;
+
+/// Returns cancelled projects' donations: escrow deposits on-chain and fiat
+/// payments through their provider, retrying failures with backoff
+pub struct RefundProcessor {
+    pool: PgPool,
+    providers: ProviderRegistry,
+    network: StellarNetwork,
+    dry_run: bool,
+    interval: Duration,
+    control: WorkerControl,
+}
+
+impl RefundProcessor {
+    pub fn new(
+        pool: PgPool,
+        providers: ProviderRegistry,
+        network: StellarNetwork,
+        dry_run: bool,
+        control: WorkerControl,
+    ) -> Self {
+        let interval_secs = std::env::var("REFUND_PROCESSOR_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60);
+        Self {
+            pool,
+            providers,
+            network,
+            dry_run,
+            interval: Duration::from_secs(interval_secs),
+            control,
+        }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        loop {
+            if self.control.is_paused("refund_processor") {
+                tracing::info!("Refund processor paused, skipping run");
+            } else if !self.control.leads("refund_processor").await {
+                tracing::debug!("Another instance leads refund_processor, skipping run");
+            } else {
+                let outcome = self.run_once().await;
+                if let Err(e) = &outcome {
+                    eprintln!("Refund processor error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "refund_processor", &outcome).await;
+            }
+
+            if !self.control.idle(self.interval).await {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn run_once(&self) -> Result<usize> {
+        if self.dry_run {
+            let due = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) as "count!" FROM project_refunds WHERE status = 'pending' AND next_attempt_at <= NOW()"#
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            if due > 0 {
+                tracing::info!("[dry-run] Would attempt {} project refunds", due);
+            }
+            return Ok(0);
+        }
+
+        let attempted = project_refunds::run_due(&self.pool, self.network, &self.providers.service(), None, REFUND_BATCH).await?;
+        if attempted > 0 {
+            tracing::info!("Attempted {} project refunds", attempted);
+        }
+        Ok(attempted)
+    }
+}
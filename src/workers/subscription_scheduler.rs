@@ -0,0 +1,139 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::control::WorkerControl;
+use crate::config::{EscrowMode, StellarNetwork};
+use crate::services::notifications::{Channel, NotificationEvent};
+use crate::services::payment_service::ProviderRegistry;
+use crate::services::subscriptions::{self, DueSubscription};
+use crate::services::worker_heartbeats;
+use crate::state::Notifier;
+
+/// Subscriptions handled per run, longest overdue first
+const DUE_BATCH: i64 = 50;
+
+/// Takes monthly gifts from recurring donors: charges saved cards through
+/// Stripe and sends Stellar subscribers their payment link
+pub struct SubscriptionScheduler {
+    pool: PgPool,
+    providers: ProviderRegistry,
+    escrow_mode: EscrowMode,
+    network: StellarNetwork,
+    notifier: Notifier,
+    dry_run: bool,
+    interval: Duration,
+    control: WorkerControl,
+}
+
+impl SubscriptionScheduler {
+    pub fn new(
+        pool: PgPool,
+        providers: ProviderRegistry,
+        escrow_mode: EscrowMode,
+        network: StellarNetwork,
+        notifier: Notifier,
+        dry_run: bool,
+        control: WorkerControl,
+    ) -> Self {
+        let interval_secs = std::env::var("SUBSCRIPTION_SCHEDULER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(900);
+        Self {
+            pool,
+            providers,
+            escrow_mode,
+            network,
+            notifier,
+            dry_run,
+            interval: Duration::from_secs(interval_secs),
+            control,
+        }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        loop {
+            if self.control.is_paused("subscription_scheduler") {
+                tracing::info!("Subscription scheduler paused, skipping run");
+            } else if !self.control.leads("subscription_scheduler").await {
+                tracing::debug!("Another instance leads subscription_scheduler, skipping run");
+            } else {
+                let outcome = self.run_once().await;
+                if let Err(e) = &outcome {
+                    eprintln!("Subscription scheduler error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "subscription_scheduler", &outcome).await;
+            }
+
+            if !self.control.idle(self.interval).await {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn run_once(&self) -> Result<usize> {
+        if self.dry_run {
+            for subscription in subscriptions::due_subscriptions(&self.pool, DUE_BATCH).await? {
+                tracing::info!(
+                    "[dry-run] Would take {} {} for subscription {} ({})",
+                    subscription.amount, subscription.currency, subscription.id, subscription.payment_method
+                );
+            }
+            return Ok(0);
+        }
+
+        let activated = subscriptions::activate_pending_setups(&self.pool, &self.providers).await?;
+        if activated > 0 {
+            tracing::info!("Activated {} card subscriptions", activated);
+        }
+
+        let mut taken = 0;
+        for subscription in subscriptions::due_subscriptions(&self.pool, DUE_BATCH).await? {
+            match self.take_gift(&subscription).await {
+                Ok(()) => taken += 1,
+                Err(e) => eprintln!("Failed to process subscription {}: {}", subscription.id, e),
+            }
+        }
+
+        Ok(activated + taken)
+    }
+
+    async fn take_gift(&self, subscription: &DueSubscription) -> Result<()> {
+        let Some((charge_id, attempt)) = subscriptions::claim_charge(&self.pool, subscription).await? else {
+            return Ok(());
+        };
+
+        let outcome = match subscription.payment_method.as_str() {
+            "stripe" => subscriptions::charge_card(&self.pool, &self.providers, subscription, charge_id, attempt)
+                .await
+                .map(|payment_id| format!("charged {}", payment_id)),
+            _ => subscriptions::send_stellar_reminder(&self.pool, subscription, charge_id, self.escrow_mode, self.network)
+                .await
+                .map(|donation_id| format!("reminded for donation {}", donation_id))
+                .map_err(|e| e.to_string()),
+        };
+
+        match outcome {
+            Ok(done) => {
+                tracing::info!("Subscription {} {} for {}", subscription.id, done, subscription.period);
+                let event = NotificationEvent::SubscriptionCharged {
+                    subscription_id: subscription.id,
+                    period: subscription.period,
+                };
+                self.notifier.send(Channel::User(subscription.donor_id), event);
+            }
+            Err(e) => {
+                tracing::warn!("Subscription {} attempt {} failed: {}", subscription.id, attempt, e);
+                if subscriptions::record_failure(&self.pool, subscription, charge_id, attempt, &e).await? {
+                    self.notifier.send(
+                        Channel::User(subscription.donor_id),
+                        NotificationEvent::SubscriptionPastDue { subscription_id: subscription.id },
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::control::WorkerControl;
+use crate::services::outgoing_webhooks::{self, QueuedEvent, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER};
+use crate::services::webhook_deliveries::{self, NewDelivery};
+use crate::services::worker_heartbeats;
+
+/// Events delivered per run, oldest due first
+const DELIVERY_BATCH: i64 = 50;
+/// Response bodies kept in the delivery log
+const MAX_LOGGED_RESPONSE_BYTES: usize = 2048;
+
+/// Delivers queued webhook events to their endpoints, signed with each
+/// endpoint's secret, retrying failures with backoff until they're dead-lettered
+pub struct WebhookDispatcher {
+    pool: PgPool,
+    client: reqwest::Client,
+    dry_run: bool,
+    interval: Duration,
+    control: WorkerControl,
+}
+
+impl WebhookDispatcher {
+    pub fn new(pool: PgPool, dry_run: bool, control: WorkerControl) -> Self {
+        let interval_secs = std::env::var("WEBHOOK_DISPATCHER_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(15);
+        let timeout_secs = std::env::var("WEBHOOK_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(timeout_secs))
+            // A redirect could take the signed payload somewhere the owner didn't register
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+        Self {
+            pool,
+            client,
+            dry_run,
+            interval: Duration::from_secs(interval_secs),
+            control,
+        }
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        loop {
+            if self.control.is_paused("webhook_dispatcher") {
+                tracing::info!("Webhook dispatcher paused, skipping run");
+            } else if !self.control.leads("webhook_dispatcher").await {
+                tracing::debug!("Another instance leads webhook_dispatcher, skipping run");
+            } else {
+                let outcome = self.run_once().await;
+                if let Err(e) = &outcome {
+                    eprintln!("Webhook dispatcher error: {}", e);
+                }
+                worker_heartbeats::record(&self.pool, "webhook_dispatcher", &outcome).await;
+            }
+
+            if !self.control.idle(self.interval).await {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn run_once(&self) -> Result<usize> {
+        if self.dry_run {
+            let due = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) as "count!" FROM webhook_outbox WHERE status = 'pending' AND next_attempt_at <= NOW()"#
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            if due > 0 {
+                tracing::info!("[dry-run] Would deliver {} queued webhook events", due);
+            }
+            return Ok(0);
+        }
+
+        let mut delivered = 0;
+        for event in outgoing_webhooks::claim_due(&self.pool, DELIVERY_BATCH).await? {
+            let (status, error) = self.deliver(&event).await;
+            match error {
+                None => {
+                    outgoing_webhooks::mark_delivered(&self.pool, event.id, status.unwrap_or_default()).await?;
+                    delivered += 1;
+                }
+                Some(e) => {
+                    tracing::warn!(
+                        "Webhook {} to {} failed (attempt {}): {}",
+                        event.id, event.endpoint_name, event.attempts, e
+                    );
+                    if outgoing_webhooks::mark_failed(&self.pool, &event, status, &e).await? {
+                        tracing::error!("Dead-lettered webhook {} after {} attempts", event.id, event.attempts);
+                    }
+                }
+            }
+        }
+
+        Ok(delivered)
+    }
+
+    /// Send one event and log the attempt; returns the response status and
+    /// the error, if it failed
+    async fn deliver(&self, event: &QueuedEvent) -> (Option<i32>, Option<String>) {
+        let signature = outgoing_webhooks::sign(&event.secret, chrono::Utc::now().timestamp(), &event.body);
+        let response = self
+            .client
+            .post(&event.url)
+            .header("content-type", "application/json")
+            .header(EVENT_HEADER, &event.event_type)
+            .header(DELIVERY_HEADER, event.id.to_string())
+            .header(SIGNATURE_HEADER, &signature)
+            .body(event.body.clone())
+            .send()
+            .await;
+
+        let (status, body, error) = match response {
+            Ok(resp) => {
+                let status = resp.status();
+                let mut body = resp.text().await.unwrap_or_default();
+                if body.len() > MAX_LOGGED_RESPONSE_BYTES {
+                    let mut end = MAX_LOGGED_RESPONSE_BYTES;
+                    while !body.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    body.truncate(end);
+                }
+                let error = (!status.is_success()).then(|| format!("Endpoint returned {}", status));
+                (Some(status.as_u16() as i32), Some(body), error)
+            }
+            Err(e) => (None, None, Some(e.to_string())),
+        };
+
+        webhook_deliveries::record(&self.pool, NewDelivery {
+            direction: "outbound",
+            provider: &event.endpoint_name,
+            event_type: Some(&event.event_type),
+            target_url: Some(&event.url),
+            request_headers: Some(serde_json::json!({
+                EVENT_HEADER: event.event_type,
+                DELIVERY_HEADER: event.id,
+                SIGNATURE_HEADER: signature,
+                "attempt": event.attempts,
+            })),
+            request_body: &event.body,
+            response_status: status,
+            response_body: body,
+            error: error.clone(),
+            replay_of: None,
+        })
+        .await;
+
+        (status, error)
+    }
+}
@@ -6,14 +6,21 @@ fn test_jwt_creation_and_verification() {
     std::env::set_var("JWT_SECRET", "test-secret-key");
     
     let user_id = Uuid::new_v4();
+    let roles = jwt::TokenRoles {
+        role: "admin".to_string(),
+        base_role: "admin".to_string(),
+        verification_status: None,
+        permissions: Vec::new(),
+    };
     
     // Create token
-    let token = jwt::create_token(&user_id).expect("Failed to create token");
+    let token = jwt::create_token(&user_id, &roles, None).expect("Failed to create token");
     assert!(!token.is_empty());
     
     // Verify token
     let claims = jwt::verify_token(&token).expect("Failed to verify token");
     assert_eq!(claims.sub, user_id);
+    assert!(claims.is_admin());
 }
 
 #[test]